@@ -6,6 +6,7 @@ Subcommands:
     mesh_check <code_file>
     orient <code_file>
     unfold <code_file> <output_dxf> [--thickness <t>]
+    decimate <input_stl> <output_stl> <max_triangles>
 
 Exit codes:
     0 = success
@@ -20,6 +21,7 @@ import sys
 import os
 import json
 import math
+import struct
 import traceback
 import subprocess
 
@@ -52,8 +54,8 @@ def ensure_ezdxf():
         return ezdxf
 
 
-def exec_cad_code(code_file):
-    """Execute a Build123d code file and return the namespace."""
+def exec_cad_namespace(code_file):
+    """Execute a Build123d code file and return its namespace."""
     if not os.path.exists(code_file):
         print(f"Input file not found: {code_file}", file=sys.stderr)
         sys.exit(1)
@@ -68,7 +70,12 @@ def exec_cad_code(code_file):
         traceback.print_exc()
         sys.exit(2)
 
-    result = namespace.get("result")
+    return namespace
+
+
+def exec_cad_code(code_file):
+    """Execute a Build123d code file and return its `result`."""
+    result = exec_cad_namespace(code_file).get("result")
     if result is None:
         print("Error: Code must assign final geometry to 'result' variable.", file=sys.stderr)
         sys.exit(3)
@@ -132,6 +139,35 @@ def shape_from_result(result):
     raise TypeError(f"Unsupported Build123d result type for tessellation: {type(result).__name__}")
 
 
+def shape_rgba(shape):
+    """RGBA floats of a shape's build123d ``color``, or None when unset."""
+    color = getattr(shape, "color", None)
+    if color is None:
+        return None
+    try:
+        rgba = tuple(float(c) for c in color.to_tuple())
+    except Exception:
+        return None
+    return rgba if len(rgba) == 4 else None
+
+
+def labeled_leaves(shape, path=""):
+    """Flatten a labeled Compound tree into (path, leaf) pairs.
+
+    Nested assembly compounds become slash-separated paths such as
+    ``lid_group/lid``; the root label itself is not part of the path.
+    Unlabeled children are named ``part_<n>``.
+    """
+    children = list(getattr(shape, "children", None) or ())
+    if not children:
+        return [(path, shape)]
+    leaves = []
+    for index, child in enumerate(children):
+        name = getattr(child, "label", "") or f"part_{index + 1}"
+        leaves.extend(labeled_leaves(child, f"{path}/{name}" if path else name))
+    return leaves
+
+
 def _count_face_selector(values):
     """Count entries for index lists or boolean masks."""
     try:
@@ -219,29 +255,70 @@ def count_connected_components(mesh):
 def cmd_export_3mf(args):
     """Export model as 3MF with optional per-object colors."""
     if len(args) < 2:
-        print("Usage: manufacturing.py export_3mf <code_file> <output_3mf> [--colors <json>]", file=sys.stderr)
+        print(
+            "Usage: manufacturing.py export_3mf <code_file> <output_3mf> [--colors <json>] [--objects a,b]",
+            file=sys.stderr,
+        )
         sys.exit(1)
 
     code_file = args[0]
     output_path = args[1]
     colors_file = None
+    object_names = None
 
     i = 2
     while i < len(args):
         if args[i] == '--colors' and i + 1 < len(args):
             colors_file = args[i + 1]
             i += 2
+        elif args[i] == '--objects' and i + 1 < len(args):
+            object_names = [n.strip() for n in args[i + 1].split(",") if n.strip()]
+            i += 2
         else:
             i += 1
 
     trimesh = ensure_trimesh()
     import numpy as np
 
-    result = exec_cad_code(code_file)
-    verts, tris = tessellate_result(result)
+    part_colored = set()
+    if object_names:
+        # Named top-level variables of a multi-object script, one 3MF object each.
+        namespace = exec_cad_namespace(code_file)
+        meshes = []
+        for name in object_names:
+            if namespace.get(name) is None:
+                print(f"Error: '{name}' is not a geometry variable in the script.", file=sys.stderr)
+                sys.exit(6)
+            verts, tris = tessellate_result(namespace[name])
+            meshes.append((name, trimesh.Trimesh(vertices=verts, faces=tris)))
+    else:
+        result = exec_cad_code(code_file)
 
-    mesh = trimesh.Trimesh(vertices=verts, faces=tris)
-    mesh.fix_normals()
+        # Assemblies with labeled sub-assemblies (e.g. lid_group/lid) export one
+        # named object per part so downstream tools keep the grouping.
+        try:
+            leaves = labeled_leaves(shape_from_result(result))
+        except Exception:
+            leaves = []
+        if len(leaves) > 1:
+            meshes = []
+            for path, leaf in leaves:
+                verts, tris = tessellate_result(leaf)
+                mesh = trimesh.Trimesh(vertices=verts, faces=tris)
+                # Part colors set by the assembled script (`part_x.color = Color(...)`)
+                rgba = shape_rgba(leaf)
+                if rgba is not None:
+                    mesh.visual.face_colors = np.full(
+                        (len(mesh.faces), 4), [int(c * 255) for c in rgba], dtype=np.uint8
+                    )
+                    part_colored.add(path)
+                meshes.append((path, mesh))
+        else:
+            verts, tris = tessellate_result(result)
+            meshes = [("", trimesh.Trimesh(vertices=verts, faces=tris))]
+
+    for _path, mesh in meshes:
+        mesh.fix_normals()
 
     # Apply colors if provided
     if colors_file and os.path.exists(colors_file):
@@ -249,28 +326,39 @@ def cmd_export_3mf(args):
             with open(colors_file, 'r') as f:
                 colors = json.load(f)
             if colors and len(colors) > 0:
-                # Use the first color for all faces (single-object export)
+                # Use the first color for all faces not tinted by the script
                 c = colors[0]
                 r = int(c.get('r', 0.5) * 255)
                 g = int(c.get('g', 0.5) * 255)
                 b = int(c.get('b', 0.5) * 255)
                 a = int(c.get('a', 1.0) * 255)
-                face_colors = np.full((len(mesh.faces), 4), [r, g, b, a], dtype=np.uint8)
-                mesh.visual.face_colors = face_colors
+                for path, mesh in meshes:
+                    if path in part_colored:
+                        continue
+                    face_colors = np.full((len(mesh.faces), 4), [r, g, b, a], dtype=np.uint8)
+                    mesh.visual.face_colors = face_colors
         except Exception as e:
             print(f"Warning: Could not apply colors: {e}", file=sys.stderr)
 
     try:
-        mesh.export(output_path, file_type='3mf')
+        if len(meshes) > 1 or object_names:
+            scene = trimesh.Scene()
+            for path, mesh in meshes:
+                scene.add_geometry(mesh, node_name=path, geom_name=path)
+            scene.export(output_path, file_type='3mf')
+        else:
+            meshes[0][1].export(output_path, file_type='3mf')
     except Exception:
         traceback.print_exc()
         sys.exit(4)
 
     result_json = {
         "success": True,
-        "triangles": int(len(mesh.faces)),
+        "triangles": int(sum(len(mesh.faces) for _path, mesh in meshes)),
         "path": output_path,
     }
+    if len(meshes) > 1 or object_names:
+        result_json["objects"] = [path for path, _mesh in meshes]
     print(json.dumps(result_json))
 
 
@@ -320,6 +408,17 @@ def cmd_mesh_check(args):
     tri_count = int(len(mesh.faces))
     bounds = mesh.bounds.tolist() if hasattr(mesh, "bounds") else [[0, 0, 0], [0, 0, 0]]
 
+    # Mass properties at unit density; the app applies per-part material density.
+    center_mass = None
+    inertia = None
+    if watertight and volume > 0:
+        try:
+            center_mass = [round(float(v), 4) for v in mesh.center_mass]
+            inertia = [[round(float(v), 4) for v in row] for row in mesh.moment_inertia]
+        except Exception:
+            center_mass = None
+            inertia = None
+
     result_json = {
         "watertight": watertight,
         "winding_consistent": winding,
@@ -328,8 +427,11 @@ def cmd_mesh_check(args):
         "component_count": component_count,
         "expected_euler": expected_euler,
         "volume": round(volume, 4),
+        "surface_area": round(float(mesh.area), 4),
         "triangle_count": tri_count,
         "bounds": bounds,
+        "center_mass": center_mass,
+        "inertia": inertia,
         "issues": issues,
     }
     print(json.dumps(result_json))
@@ -552,10 +654,206 @@ def cmd_unfold(args):
         sys.exit(4)
 
 
+def read_stl_triangles(path):
+    """Read a binary or ASCII STL as a list of (v0, v1, v2) coordinate tuples."""
+    with open(path, "rb") as f:
+        data = f.read()
+
+    if len(data) >= 84:
+        (count,) = struct.unpack_from("<I", data, 80)
+        if len(data) == 84 + 50 * count:
+            triangles = []
+            for i in range(count):
+                vals = struct.unpack_from("<12f", data, 84 + 50 * i)
+                triangles.append((vals[3:6], vals[6:9], vals[9:12]))
+            return triangles
+
+    vertices = []
+    for line in data.decode("utf-8", errors="replace").splitlines():
+        parts = line.split()
+        if len(parts) == 4 and parts[0] == "vertex":
+            vertices.append(tuple(float(v) for v in parts[1:]))
+    return [tuple(vertices[i:i + 3]) for i in range(0, len(vertices) - 2, 3)]
+
+
+def write_binary_stl(path, triangles):
+    """Write triangles as a binary STL with computed facet normals."""
+    with open(path, "wb") as f:
+        f.write(b"CAD AI Studio preview".ljust(80, b" "))
+        f.write(struct.pack("<I", len(triangles)))
+        for a, b, c in triangles:
+            u = (b[0] - a[0], b[1] - a[1], b[2] - a[2])
+            v = (c[0] - a[0], c[1] - a[1], c[2] - a[2])
+            n = (u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0])
+            length = math.sqrt(n[0] ** 2 + n[1] ** 2 + n[2] ** 2) or 1.0
+            f.write(struct.pack("<3f", n[0] / length, n[1] / length, n[2] / length))
+            f.write(struct.pack("<9f", *a, *b, *c))
+            f.write(b"\x00\x00")
+
+
+def triangle_bounds(triangles):
+    """Axis-aligned [min, max] corners of a triangle list."""
+    points = [p for tri in triangles for p in tri]
+    lo = [min(p[i] for p in points) for i in range(3)]
+    hi = [max(p[i] for p in points) for i in range(3)]
+    return lo, hi
+
+
+def decimate_triangles(triangles, max_triangles):
+    """Reduce `triangles` to at most `max_triangles` by vertex clustering.
+
+    Corners are snapped to the mean of their grid cell; collapsed and
+    duplicate triangles are dropped and the grid coarsens until the budget
+    is met. The result is stretched back onto the input bounding box so the
+    preview keeps the part's exact extents.
+    """
+    triangles = list(triangles)
+    if max_triangles <= 0 or len(triangles) <= max_triangles:
+        return triangles
+
+    lo, hi = triangle_bounds(triangles)
+    longest = max(hi[i] - lo[i] for i in range(3)) or 1.0
+    cells = max(2, int(math.sqrt(max_triangles / 2.0)))
+
+    while True:
+        size = longest / cells
+        clusters = {}
+        faces = []
+        seen = set()
+        for tri in triangles:
+            ids = []
+            for p in tri:
+                key = (int((p[0] - lo[0]) / size), int((p[1] - lo[1]) / size), int((p[2] - lo[2]) / size))
+                cluster = clusters.get(key)
+                if cluster is None:
+                    cluster = [0.0, 0.0, 0.0, 0, len(clusters)]
+                    clusters[key] = cluster
+                cluster[0] += p[0]
+                cluster[1] += p[1]
+                cluster[2] += p[2]
+                cluster[3] += 1
+                ids.append(cluster[4])
+            if ids[0] == ids[1] or ids[1] == ids[2] or ids[0] == ids[2]:
+                continue
+            canonical = tuple(sorted(ids))
+            if canonical in seen:
+                continue
+            seen.add(canonical)
+            faces.append(ids)
+        if len(faces) <= max_triangles or cells <= 2:
+            break
+        cells = max(2, int(cells * 0.75))
+
+    if not faces:
+        return triangles
+
+    centers = [None] * len(clusters)
+    for sx, sy, sz, n, index in clusters.values():
+        centers[index] = (sx / n, sy / n, sz / n)
+
+    used = [centers[i] for i in sorted({i for face in faces for i in face})]
+    new_lo = [min(p[i] for p in used) for i in range(3)]
+    new_hi = [max(p[i] for p in used) for i in range(3)]
+
+    def restore(p):
+        out = []
+        for i in range(3):
+            span = new_hi[i] - new_lo[i]
+            if span > 1e-12:
+                out.append(lo[i] + (p[i] - new_lo[i]) * (hi[i] - lo[i]) / span)
+            else:
+                out.append(p[i])
+        return tuple(out)
+
+    restored = {}
+    result = []
+    for face in faces:
+        tri = []
+        for i in face:
+            if i not in restored:
+                restored[i] = restore(centers[i])
+            tri.append(restored[i])
+        result.append(tuple(tri))
+    return result
+
+
+def triangle_stats(triangles):
+    """Enclosed volume (divergence theorem) and surface area of a triangle list."""
+    volume = 0.0
+    area = 0.0
+    for a, b, c in triangles:
+        volume += (
+            a[0] * (b[1] * c[2] - b[2] * c[1])
+            - a[1] * (b[0] * c[2] - b[2] * c[0])
+            + a[2] * (b[0] * c[1] - b[1] * c[0])
+        ) / 6.0
+        u = (b[0] - a[0], b[1] - a[1], b[2] - a[2])
+        v = (c[0] - a[0], c[1] - a[1], c[2] - a[2])
+        n = (u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0])
+        area += math.sqrt(n[0] ** 2 + n[1] ** 2 + n[2] ** 2) / 2.0
+    return abs(volume), area
+
+
+def cmd_stl_stats(args):
+    """Volume, surface area and bounds of an STL, for print estimates."""
+    if len(args) < 1:
+        print("Usage: manufacturing.py stl_stats <stl_file>", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        triangles = read_stl_triangles(args[0])
+        if not triangles:
+            raise ValueError("STL contains no triangles")
+        volume, area = triangle_stats(triangles)
+        lo, hi = triangle_bounds(triangles)
+    except Exception:
+        traceback.print_exc()
+        sys.exit(4)
+
+    print(json.dumps({
+        "volume": round(volume, 4),
+        "surface_area": round(area, 4),
+        "triangle_count": len(triangles),
+        "bounds": [lo, hi],
+    }))
+
+
+def cmd_decimate(args):
+    """Decimate a preview STL to a triangle budget, keeping its bounding box."""
+    if len(args) < 3:
+        print("Usage: manufacturing.py decimate <input_stl> <output_stl> <max_triangles>", file=sys.stderr)
+        sys.exit(1)
+
+    input_path, output_path = args[0], args[1]
+    try:
+        max_triangles = int(args[2])
+    except ValueError:
+        print(f"Invalid triangle budget: {args[2]}", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        triangles = read_stl_triangles(input_path)
+        if not triangles:
+            raise ValueError("STL contains no triangles")
+        decimated = decimate_triangles(triangles, max_triangles)
+        write_binary_stl(output_path, decimated)
+        lo, hi = triangle_bounds(decimated)
+    except Exception:
+        traceback.print_exc()
+        sys.exit(4)
+
+    print(json.dumps({
+        "success": True,
+        "input_triangles": len(triangles),
+        "triangles": len(decimated),
+        "bounds": [lo, hi],
+    }))
+
+
 def main():
     if len(sys.argv) < 2:
         print("Usage: manufacturing.py <subcommand> [args...]", file=sys.stderr)
-        print("Subcommands: export_3mf, mesh_check, orient, unfold", file=sys.stderr)
+        print("Subcommands: export_3mf, mesh_check, orient, unfold, decimate, stl_stats", file=sys.stderr)
         sys.exit(1)
 
     subcommand = sys.argv[1]
@@ -569,9 +867,13 @@ def main():
         cmd_orient(sub_args)
     elif subcommand == 'unfold':
         cmd_unfold(sub_args)
+    elif subcommand == 'decimate':
+        cmd_decimate(sub_args)
+    elif subcommand == 'stl_stats':
+        cmd_stl_stats(sub_args)
     else:
         print(f"Unknown subcommand: {subcommand}", file=sys.stderr)
-        print("Available: export_3mf, mesh_check, orient, unfold", file=sys.stderr)
+        print("Available: export_3mf, mesh_check, orient, unfold, decimate, stl_stats", file=sys.stderr)
         sys.exit(1)
 
 
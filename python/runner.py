@@ -6,10 +6,25 @@ It executes Build123d code and exports the result as STL.
 
 Usage:
     python runner.py <input_file> <output_file>
+    python runner.py <input_file> <output_dir> --all-solids [--include a,b] [--format stl|step]
+    python runner.py <input_file> <output_json> --query <query_json>
+    python runner.py <step_file> <output_json> --measure-step
 
 The input file should contain valid Build123d Python code.
 The code MUST assign the final result to a variable named 'result'.
 The output file will be written as binary STL.
+
+With --all-solids, every top-level geometry variable (or only the --include
+names) is exported to <output_dir>/<name>.<format>, and <output_dir>/objects.json
+lists each object with its geometry report. `result` is not required.
+
+With --query, the BREP of `result` is measured instead of exported: the query
+JSON (point_distance, thickness or face_radius) is answered into <output_json>
+together with the shape's bounding box.
+
+With --measure-step, the input is a STEP file written by an earlier export: it
+is re-imported and its bounding box, volume, solid and face counts are written
+to <output_json> so the export can be checked against the original geometry.
 """
 
 import sys
@@ -209,6 +224,282 @@ def _normalize_result_for_export(result):
     return Compound(children=exportables)
 
 
+def _top_level_object_names(code):
+    """Names assigned exactly once at module level, in assignment order.
+
+    Intermediates that are rebuilt step by step (``body = body.cut(...)``) or
+    private (``_tmp``) are skipped, so only finished objects are exported.
+    """
+    try:
+        tree = ast.parse(code)
+    except SyntaxError:
+        return []
+
+    counts = {}
+    order = []
+
+    def count(target, times=1):
+        if isinstance(target, ast.Name):
+            if target.id not in counts:
+                order.append(target.id)
+            counts[target.id] = counts.get(target.id, 0) + times
+        elif isinstance(target, (ast.Tuple, ast.List)):
+            for elt in target.elts:
+                count(elt, times)
+
+    for node in tree.body:
+        if isinstance(node, ast.Assign):
+            for target in node.targets:
+                count(target)
+        elif isinstance(node, (ast.AnnAssign, ast.AugAssign)):
+            count(node.target)
+        elif isinstance(node, ast.For):
+            # Loop variables are rebound on every iteration.
+            count(node.target, times=2)
+        elif isinstance(node, ast.With):
+            for item in node.items:
+                if item.optional_vars is not None:
+                    # `with BuildPart() as housing:` binds a builder; its `.part` is exported.
+                    count(item.optional_vars)
+
+    return [name for name in order if counts[name] == 1 and not name.startswith("_")]
+
+
+def _object_report(shape):
+    """Bounding box, volume and solid count of one exported object."""
+    report = {"bounds_min": [0.0, 0.0, 0.0], "bounds_max": [0.0, 0.0, 0.0], "volume": 0.0, "solid_count": 0}
+    try:
+        bbox = shape.bounding_box()
+        report["bounds_min"] = [bbox.min.X, bbox.min.Y, bbox.min.Z]
+        report["bounds_max"] = [bbox.max.X, bbox.max.Y, bbox.max.Z]
+    except Exception as e:
+        print(f"Warning: bounding box failed: {e}", file=sys.stderr)
+    try:
+        report["volume"] = float(shape.volume)
+    except Exception as e:
+        print(f"Warning: volume failed: {e}", file=sys.stderr)
+    try:
+        report["solid_count"] = _count_solids(shape)
+    except Exception as e:
+        print(f"Warning: solid count failed: {e}", file=sys.stderr)
+    return report
+
+
+def measure_step_file(step_file):
+    """Re-import an exported STEP file and report what came back."""
+    from build123d import import_step
+
+    shape = import_step(step_file)
+    report = _object_report(shape)
+    report["face_count"] = len(shape.faces())
+    return report
+
+
+def export_named_objects(code, namespace, output_dir, include=None, fmt="stl"):
+    """
+    Export each geometry variable as its own file and write objects.json.
+
+    Without `include`, candidates are the top-level names assigned once;
+    non-geometry values among them are skipped, and `result` is skipped when
+    other objects exist (it usually combines them). Names in `include` must
+    all resolve to geometry. Returns the manifest entries.
+    """
+    from build123d import Compound, export_stl, export_step
+
+    if include:
+        names = list(include)
+    else:
+        names = _top_level_object_names(code)
+
+    objects = []
+    for name in names:
+        value = namespace.get(name)
+        exportables, invalid = _extract_exportables(value)
+        if not exportables or invalid:
+            if include:
+                print(f"Error: '{name}' is not a geometry variable in the script.", file=sys.stderr)
+                sys.exit(6)
+            continue
+        shape = exportables[0] if len(exportables) == 1 else Compound(children=exportables)
+        objects.append((name, shape))
+
+    if not include and len(objects) > 1:
+        objects = [(name, shape) for name, shape in objects if name != "result"]
+    if not objects:
+        print("Error: the script defines no top-level geometry variables.", file=sys.stderr)
+        sys.exit(3)
+
+    os.makedirs(output_dir, exist_ok=True)
+    manifest = []
+    for name, shape in objects:
+        path = os.path.join(output_dir, f"{name}.{fmt}")
+        if fmt == "step":
+            export_step(shape, path)
+        else:
+            export_stl(shape, path)
+        manifest.append({"name": name, "file": path, "report": _object_report(shape)})
+
+    with open(os.path.join(output_dir, "objects.json"), "w", encoding="utf-8") as mf:
+        json.dump(manifest, mf)
+    return manifest
+
+
+class QueryError(Exception):
+    """A geometry query that cannot be answered for this shape."""
+
+
+_SURFACE_TYPES = {
+    "GeomAbs_Plane": "plane",
+    "GeomAbs_Cylinder": "cylinder",
+    "GeomAbs_Cone": "cone",
+    "GeomAbs_Sphere": "sphere",
+    "GeomAbs_Torus": "torus",
+    "GeomAbs_BezierSurface": "bezier",
+    "GeomAbs_BSplineSurface": "bspline",
+    "GeomAbs_SurfaceOfRevolution": "revolution",
+    "GeomAbs_SurfaceOfExtrusion": "extrusion",
+    "GeomAbs_OffsetSurface": "offset",
+}
+
+
+def _faces(wrapped):
+    from OCP.TopAbs import TopAbs_FACE
+    from OCP.TopExp import TopExp_Explorer
+    from OCP.TopoDS import TopoDS
+
+    faces = []
+    explorer = TopExp_Explorer(wrapped, TopAbs_FACE)
+    while explorer.More():
+        faces.append(TopoDS.Face_s(explorer.Current()))
+        explorer.Next()
+    return faces
+
+
+def _surface_type(face):
+    from OCP.BRepAdaptor import BRepAdaptor_Surface
+
+    kind = str(BRepAdaptor_Surface(face).GetType()).split(".")[-1]
+    return _SURFACE_TYPES.get(kind, "other")
+
+
+def _face_entity(faces, face):
+    """Entity record for `face`, indexed like the topology sidecar."""
+    for index, candidate in enumerate(faces):
+        if candidate.IsSame(face):
+            return {"kind": "face", "index": index, "surface_type": _surface_type(candidate)}
+    return {"kind": "face", "index": -1, "surface_type": _surface_type(face)}
+
+
+def _closest_face(faces, point):
+    """(distance, face, point on face) of the face nearest to `point`."""
+    from OCP.BRepBuilderAPI import BRepBuilderAPI_MakeVertex
+    from OCP.BRepExtrema import BRepExtrema_DistShapeShape
+    from OCP.gp import gp_Pnt
+
+    vertex = BRepBuilderAPI_MakeVertex(gp_Pnt(*point)).Vertex()
+    best = None
+    for face in faces:
+        dist = BRepExtrema_DistShapeShape(vertex, face)
+        if not dist.IsDone() or dist.NbSolution() == 0:
+            continue
+        if best is None or dist.Value() < best[0]:
+            p = dist.PointOnShape2(1)
+            best = (dist.Value(), face, [p.X(), p.Y(), p.Z()])
+    if best is None:
+        raise QueryError("No face found near the queried point.")
+    return best
+
+
+def _query_point_distance(faces, query):
+    _, face_a, a = _closest_face(faces, query["a"])
+    _, face_b, b = _closest_face(faces, query["b"])
+    distance = sum((a[i] - b[i]) ** 2 for i in range(3)) ** 0.5
+    return {
+        "kind": "point_distance",
+        "distance": distance,
+        "a": a,
+        "b": b,
+        "entities": [_face_entity(faces, face_a), _face_entity(faces, face_b)],
+    }
+
+
+def _query_thickness(wrapped, faces, query):
+    """Material crossed by a ray from `point` along `direction`."""
+    from OCP.BRepIntCurveSurface import BRepIntCurveSurface_Inter
+    from OCP.gp import gp_Dir, gp_Lin, gp_Pnt
+
+    point = query["point"]
+    line = gp_Lin(gp_Pnt(*point), gp_Dir(*query["direction"]))
+    inter = BRepIntCurveSurface_Inter()
+    inter.Init(wrapped, line, 1e-6)
+    hits = []
+    while inter.More():
+        if inter.W() >= -1e-6:
+            p = inter.Pnt()
+            hits.append((inter.W(), [p.X(), p.Y(), p.Z()], inter.Face()))
+        inter.Next()
+    hits.sort(key=lambda hit: hit[0])
+    # The picked point usually lies on the entry face; skip duplicate hits
+    # where the ray grazes a shared edge.
+    distinct = []
+    for hit in hits:
+        if not distinct or hit[0] - distinct[-1][0] > 1e-6:
+            distinct.append(hit)
+    if len(distinct) < 2:
+        raise QueryError("The ray does not pass through material from this point.")
+    (w_in, entry, face_in), (w_out, exit_, face_out) = distinct[0], distinct[1]
+    return {
+        "kind": "thickness",
+        "thickness": w_out - w_in,
+        "entry": entry,
+        "exit": exit_,
+        "entities": [_face_entity(faces, face_in), _face_entity(faces, face_out)],
+    }
+
+
+def _query_face_radius(faces, query):
+    from OCP.BRepAdaptor import BRepAdaptor_Surface
+    from OCP.gp import gp_Lin, gp_Pnt
+
+    _, face, point = _closest_face(faces, query["point"])
+    entity = _face_entity(faces, face)
+    surf = BRepAdaptor_Surface(face)
+    kind = entity["surface_type"]
+    if kind == "cylinder":
+        radius = surf.Cylinder().Radius()
+    elif kind == "sphere":
+        radius = surf.Sphere().Radius()
+    elif kind == "torus":
+        radius = surf.Torus().MinorRadius()
+    elif kind == "cone":
+        # Radius of the cone where the point sits.
+        radius = gp_Lin(surf.Cone().Axis()).Distance(gp_Pnt(*point))
+    else:
+        raise QueryError(f"The closest face is a {kind} surface and has no radius.")
+    return {"kind": "face_radius", "radius": radius, "point": point, "entities": [entity]}
+
+
+def run_geometry_query(shape, query):
+    """Answer one query against `shape`'s BREP, with its bounding box."""
+    wrapped = shape.wrapped if hasattr(shape, "wrapped") else shape
+    faces = _faces(wrapped)
+    kind = query.get("kind")
+    if kind == "point_distance":
+        response = _query_point_distance(faces, query)
+    elif kind == "thickness":
+        response = _query_thickness(wrapped, faces, query)
+    elif kind == "face_radius":
+        response = _query_face_radius(faces, query)
+    else:
+        raise QueryError(f"Unknown geometry query '{kind}'.")
+    report = _object_report(shape)
+    return {
+        "bounds_min": report["bounds_min"],
+        "bounds_max": report["bounds_max"],
+        "response": response,
+    }
+
+
 def _extract_topology(shape):
     """Extract face/edge topology from a Build123d shape."""
     try:
@@ -466,19 +757,54 @@ def guard_fillet_chamfer(code):
 
 
 def main():
-    if len(sys.argv) != 3:
-        print("Usage: runner.py <input_file> <output_stl_file>", file=sys.stderr)
+    if len(sys.argv) < 3:
+        print("Usage: runner.py <input_file> <output_stl_file> [--all-solids ...]", file=sys.stderr)
         sys.exit(1)
 
     input_file = sys.argv[1]
     output_file = sys.argv[2]
+    all_solids = False
+    include = None
+    fmt = "stl"
+    query_file = None
+    measure_step = False
+    i = 3
+    while i < len(sys.argv):
+        if sys.argv[i] == "--all-solids":
+            all_solids = True
+            i += 1
+        elif sys.argv[i] == "--include" and i + 1 < len(sys.argv):
+            include = [n.strip() for n in sys.argv[i + 1].split(",") if n.strip()]
+            i += 2
+        elif sys.argv[i] == "--format" and i + 1 < len(sys.argv):
+            fmt = sys.argv[i + 1].lower()
+            i += 2
+        elif sys.argv[i] == "--query" and i + 1 < len(sys.argv):
+            query_file = sys.argv[i + 1]
+            i += 2
+        elif sys.argv[i] == "--measure-step":
+            measure_step = True
+            i += 1
+        else:
+            i += 1
 
     if not os.path.exists(input_file):
         print(f"Input file not found: {input_file}", file=sys.stderr)
         sys.exit(1)
 
+    if measure_step:
+        try:
+            report = measure_step_file(input_file)
+        except Exception as e:
+            print(f"STEP re-import failed: {e}", file=sys.stderr)
+            sys.exit(8)
+        with open(output_file, "w", encoding="utf-8") as of:
+            json.dump(report, of)
+        return
+
     with open(input_file, "r", encoding="utf-8") as f:
         code = f.read()
+    original_code = code
 
     code, _stripped = strip_unknown_calls(code)
     code = guard_fillet_chamfer(code)
@@ -500,12 +826,38 @@ def main():
         traceback.print_exc()
         sys.exit(2)
 
+    if all_solids:
+        try:
+            manifest = export_named_objects(original_code, namespace, output_file, include, fmt)
+        except SystemExit:
+            raise
+        except Exception:
+            traceback.print_exc()
+            sys.exit(4)
+        print(f"Exported {len(manifest)} objects to {output_file}")
+        return
+
     # Get the result variable
     result = namespace.get("result")
     if result is None:
         print("Error: Code must assign final geometry to 'result' variable.", file=sys.stderr)
         sys.exit(3)
 
+    if query_file:
+        try:
+            with open(query_file, "r", encoding="utf-8") as qf:
+                query = json.load(qf)
+            answer = run_geometry_query(_normalize_result_for_export(result), query)
+        except QueryError as e:
+            print(f"Error: {e}", file=sys.stderr)
+            sys.exit(7)
+        except Exception:
+            traceback.print_exc()
+            sys.exit(7)
+        with open(output_file, "w", encoding="utf-8") as of:
+            json.dump(answer, of)
+        return
+
     # Export based on file extension
     try:
         normalized = _normalize_result_for_export(result)
@@ -0,0 +1,118 @@
+import importlib.util
+import json
+import math
+import os
+import subprocess
+import sys
+import tempfile
+import unittest
+
+from python.manufacturing import (
+    decimate_triangles,
+    read_stl_triangles,
+    triangle_bounds,
+    triangle_stats,
+    write_binary_stl,
+)
+
+MANUFACTURING_PY = os.path.join(os.path.dirname(os.path.dirname(__file__)), "manufacturing.py")
+
+
+def _uv_sphere(radius, rings, segments):
+    def point(ring, seg):
+        theta = math.pi * ring / rings
+        phi = 2 * math.pi * seg / segments
+        return (
+            radius * math.sin(theta) * math.cos(phi),
+            radius * math.sin(theta) * math.sin(phi),
+            radius * math.cos(theta),
+        )
+
+    triangles = []
+    for ring in range(rings):
+        for seg in range(segments):
+            a, b = point(ring, seg), point(ring, seg + 1)
+            c, d = point(ring + 1, seg), point(ring + 1, seg + 1)
+            if ring > 0:
+                triangles.append((a, c, b))
+            if ring < rings - 1:
+                triangles.append((b, c, d))
+    return triangles
+
+
+class ManufacturingDecimateTests(unittest.TestCase):
+    def assertBoundsEqual(self, actual, expected):
+        for a_corner, e_corner in zip(actual, expected):
+            for a, e in zip(a_corner, e_corner):
+                self.assertAlmostEqual(a, e, places=4)
+
+    def test_decimation_meets_budget_and_keeps_bounds(self):
+        sphere = _uv_sphere(10.0, 60, 120)
+        self.assertGreater(len(sphere), 10000)
+        decimated = decimate_triangles(sphere, 2000)
+        self.assertLessEqual(len(decimated), 2000)
+        self.assertGreater(len(decimated), 100)
+        self.assertBoundsEqual(triangle_bounds(decimated), triangle_bounds(sphere))
+
+    def test_mesh_under_budget_is_unchanged(self):
+        sphere = _uv_sphere(5.0, 8, 16)
+        self.assertEqual(decimate_triangles(sphere, 50000), sphere)
+        self.assertEqual(decimate_triangles(sphere, 0), sphere)
+
+    def test_binary_stl_roundtrip(self):
+        sphere = _uv_sphere(5.0, 8, 16)
+        with tempfile.TemporaryDirectory() as tmp:
+            path = os.path.join(tmp, "sphere.stl")
+            write_binary_stl(path, sphere)
+            self.assertEqual(os.path.getsize(path), 84 + 50 * len(sphere))
+            loaded = read_stl_triangles(path)
+        self.assertEqual(len(loaded), len(sphere))
+        self.assertBoundsEqual(triangle_bounds(loaded), triangle_bounds(sphere))
+
+    def test_stl_stats_subcommand_measures_volume_and_area(self):
+        sphere = _uv_sphere(10.0, 60, 120)
+        volume, area = triangle_stats(sphere)
+        self.assertAlmostEqual(volume, 4 / 3 * math.pi * 1000, delta=4 / 3 * math.pi * 1000 * 0.01)
+        self.assertAlmostEqual(area, 4 * math.pi * 100, delta=4 * math.pi * 100 * 0.01)
+
+        with tempfile.TemporaryDirectory() as tmp:
+            path = os.path.join(tmp, "sphere.stl")
+            write_binary_stl(path, sphere)
+            out = subprocess.run(
+                [sys.executable, MANUFACTURING_PY, "stl_stats", path],
+                capture_output=True,
+                text=True,
+                check=True,
+            )
+        report = json.loads(out.stdout)
+        self.assertAlmostEqual(report["volume"], volume, delta=volume * 1e-4)
+        self.assertAlmostEqual(report["surface_area"], area, delta=area * 1e-4)
+        self.assertEqual(report["triangle_count"], len(sphere))
+
+    @unittest.skipUnless(importlib.util.find_spec("build123d"), "requires the CAD venv")
+    def test_decimate_subcommand_on_build123d_preview(self):
+        from build123d import Sphere, export_stl
+
+        with tempfile.TemporaryDirectory() as tmp:
+            full = os.path.join(tmp, "full.stl")
+            preview = os.path.join(tmp, "preview.stl")
+            export_stl(Sphere(20), full, tolerance=0.005, angular_tolerance=0.05)
+            original = read_stl_triangles(full)
+            budget = max(200, len(original) // 4)
+            self.assertGreater(len(original), budget)
+
+            out = subprocess.run(
+                [sys.executable, MANUFACTURING_PY, "decimate", full, preview, str(budget)],
+                capture_output=True,
+                text=True,
+                check=True,
+            )
+            report = json.loads(out.stdout)
+            self.assertEqual(report["input_triangles"], len(original))
+            self.assertLessEqual(report["triangles"], budget)
+            self.assertLessEqual(len(read_stl_triangles(preview)), budget)
+            self.assertBoundsEqual(report["bounds"], triangle_bounds(original))
+
+
+if __name__ == "__main__":
+    unittest.main()
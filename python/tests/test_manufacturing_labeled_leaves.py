@@ -0,0 +1,42 @@
+import unittest
+
+from python.manufacturing import labeled_leaves, shape_rgba
+
+
+class _Shape:
+    def __init__(self, label="", children=()):
+        self.label = label
+        self.children = list(children)
+
+
+class ManufacturingLabeledLeavesTests(unittest.TestCase):
+    def test_nested_groups_become_paths(self):
+        base = _Shape("base")
+        lid = _Shape("lid")
+        latch = _Shape("latch")
+        assy = _Shape("assembly", [base, _Shape("lid_group", [lid, latch])])
+        paths = [path for path, _leaf in labeled_leaves(assy)]
+        self.assertEqual(paths, ["base", "lid_group/lid", "lid_group/latch"])
+
+    def test_unlabeled_children_get_index_names(self):
+        assy = _Shape("assembly", [_Shape(), _Shape("cover")])
+        paths = [path for path, _leaf in labeled_leaves(assy)]
+        self.assertEqual(paths, ["part_1", "cover"])
+
+    def test_single_shape_is_one_leaf(self):
+        shape = _Shape("bracket")
+        self.assertEqual(labeled_leaves(shape), [("", shape)])
+
+    def test_shape_rgba_reads_assigned_color(self):
+        class _Color:
+            def to_tuple(self):
+                return (1.0, 0.5, 0.0, 1.0)
+
+        colored = _Shape("lid")
+        colored.color = _Color()
+        self.assertEqual(shape_rgba(colored), (1.0, 0.5, 0.0, 1.0))
+        self.assertIsNone(shape_rgba(_Shape("base")))
+
+
+if __name__ == "__main__":
+    unittest.main()
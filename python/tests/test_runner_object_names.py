@@ -0,0 +1,33 @@
+import unittest
+
+from python.runner import _top_level_object_names
+
+
+class RunnerObjectNamesTests(unittest.TestCase):
+    def test_single_assignments_in_order(self):
+        code = "housing = Box(10, 10, 10)\nlid = Box(10, 10, 2)\ngasket = Box(9, 9, 1)\n"
+        self.assertEqual(_top_level_object_names(code), ["housing", "lid", "gasket"])
+
+    def test_reassigned_and_private_names_are_skipped(self):
+        code = (
+            "body = Box(10, 10, 10)\n"
+            "body = body - Cylinder(2, 10)\n"
+            "_tool = Cylinder(1, 5)\n"
+            "count = 0\n"
+            "count += 1\n"
+            "for i in range(3):\n"
+            "    pin = Cylinder(1, 4)\n"
+            "lid = Box(10, 10, 2)\n"
+        )
+        self.assertEqual(_top_level_object_names(code), ["lid"])
+
+    def test_loop_targets_and_context_builders(self):
+        code = "for part in parts:\n    pass\nwith BuildPart() as bracket:\n    Box(5, 5, 5)\n"
+        self.assertEqual(_top_level_object_names(code), ["bracket"])
+
+    def test_syntax_error_yields_nothing(self):
+        self.assertEqual(_top_level_object_names("housing = ("), [])
+
+
+if __name__ == "__main__":
+    unittest.main()
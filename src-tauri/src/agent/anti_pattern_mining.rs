@@ -0,0 +1,402 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::rules::AntiPatternEntry;
+use crate::agent::telemetry;
+use crate::agent::validate::{self, StructuredError};
+use crate::error::AppError;
+
+/// Minimum token overlap (Jaccard) for two error phrases to share a cluster.
+const PHRASE_SIMILARITY_THRESHOLD: f32 = 0.6;
+
+/// Default number of matching failures before a cluster becomes a draft.
+pub const DEFAULT_MIN_OCCURRENCES: usize = 3;
+
+const MAX_EXAMPLES: usize = 3;
+const MAX_PHRASE_CHARS: usize = 160;
+
+/// An anti-pattern mined from recurring failures, pending review or approved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LearnedAntiPattern {
+    pub id: String,
+    pub title: String,
+    /// `ErrorCategory` in its debug form, e.g. `GeometryKernel`.
+    pub error_category: String,
+    pub failing_operation: Option<String>,
+    /// Normalized error phrase shared by the cluster.
+    pub trigger_phrase: String,
+    pub occurrences: u32,
+    pub example_errors: Vec<String>,
+    pub suggested_fix: String,
+    pub created_at_ms: u64,
+}
+
+impl LearnedAntiPattern {
+    /// Shape the pattern like a preset entry so repair prompts can include it.
+    pub fn to_entry(&self) -> AntiPatternEntry {
+        AntiPatternEntry {
+            title: self.title.clone(),
+            wrong_code: String::new(),
+            error_message: self.trigger_phrase.clone(),
+            explanation: self.suggested_fix.clone(),
+            correct_code: format!("# {}", self.suggested_fix),
+        }
+    }
+}
+
+struct Cluster {
+    error_category: String,
+    failing_operation: Option<String>,
+    phrase: String,
+    tokens: HashSet<String>,
+    representative: StructuredError,
+    examples: Vec<String>,
+    count: u32,
+}
+
+/// Lowercase an error message and strip the parts that vary between runs
+/// (quoted names, numbers, hex addresses) so repeats compare equal.
+pub fn normalize_error_phrase(message: &str) -> String {
+    let quoted_re = Regex::new(r#"'[^']*'|"[^"]*""#).unwrap();
+    let hex_re = Regex::new(r"0x[0-9a-fA-F]+").unwrap();
+    let number_re = Regex::new(r"-?\d+(?:\.\d+)?").unwrap();
+    let space_re = Regex::new(r"\s+").unwrap();
+
+    let lower = message.to_lowercase();
+    let stripped = quoted_re.replace_all(&lower, "<name>");
+    let stripped = hex_re.replace_all(&stripped, "<addr>");
+    let stripped = number_re.replace_all(&stripped, "#");
+    let collapsed = space_re.replace_all(stripped.trim(), " ");
+    collapsed.chars().take(MAX_PHRASE_CHARS).collect()
+}
+
+fn phrase_tokens(phrase: &str) -> HashSet<String> {
+    phrase
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| t.len() >= 2)
+        .map(|t| t.to_string())
+        .collect()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let intersection = a.intersection(b).count() as f32;
+    let union = a.union(b).count() as f32;
+    intersection / union
+}
+
+fn error_phrase(error: &StructuredError) -> String {
+    normalize_error_phrase(&format!("{}: {}", error.error_type, error.message))
+}
+
+/// Final errors of failed runs in a `generation_traces_v1.jsonl` dump.
+pub fn failures_from_traces(jsonl: &str) -> Vec<String> {
+    jsonl
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|trace| !trace["execution_success"].as_bool().unwrap_or(true))
+        .filter_map(|trace| trace["final_error"].as_str().map(|s| s.to_string()))
+        .filter(|error| !error.trim().is_empty())
+        .collect()
+}
+
+/// Group failures by (error category, failing operation, similar error phrase)
+/// and turn clusters seen at least `min_occurrences` times into draft patterns.
+pub fn cluster_failures(errors: &[String], min_occurrences: usize) -> Vec<LearnedAntiPattern> {
+    let mut clusters: Vec<Cluster> = Vec::new();
+
+    for raw in errors {
+        let error = validate::parse_traceback(raw);
+        let error_category = format!("{:?}", error.category);
+        let phrase = error_phrase(&error);
+        let tokens = phrase_tokens(&phrase);
+
+        let existing = clusters.iter_mut().find(|c| {
+            c.error_category == error_category
+                && c.failing_operation == error.failing_operation
+                && jaccard(&c.tokens, &tokens) >= PHRASE_SIMILARITY_THRESHOLD
+        });
+        match existing {
+            Some(cluster) => {
+                cluster.count += 1;
+                if cluster.examples.len() < MAX_EXAMPLES && !cluster.examples.contains(raw) {
+                    cluster.examples.push(raw.clone());
+                }
+            }
+            None => clusters.push(Cluster {
+                error_category,
+                failing_operation: error.failing_operation.clone(),
+                phrase,
+                tokens,
+                representative: error,
+                examples: vec![raw.clone()],
+                count: 1,
+            }),
+        }
+    }
+
+    let mut drafts: Vec<LearnedAntiPattern> = clusters
+        .into_iter()
+        .filter(|c| c.count as usize >= min_occurrences.max(1))
+        .map(draft_from_cluster)
+        .collect();
+    drafts.sort_by(|a, b| b.occurrences.cmp(&a.occurrences).then(a.id.cmp(&b.id)));
+    drafts
+}
+
+fn draft_from_cluster(cluster: Cluster) -> LearnedAntiPattern {
+    let id = telemetry::hash_request(&format!(
+        "{}|{}|{}",
+        cluster.error_category,
+        cluster.failing_operation.as_deref().unwrap_or(""),
+        cluster.phrase
+    ));
+    let short_phrase: String = cluster.phrase.chars().take(60).collect();
+    let title = match &cluster.failing_operation {
+        Some(op) => format!("Recurring {} failure in {}(): {}", cluster.error_category, op, short_phrase),
+        None => format!("Recurring {} failure: {}", cluster.error_category, short_phrase),
+    };
+
+    let base_fix = validate::get_retry_strategy(&cluster.representative, 1, None).fix_instruction;
+    let suggested_fix = match &cluster.failing_operation {
+        Some(op) => format!(
+            "{} This exact failure has been seen {} times; restructure the {}() call rather than retrying it with small tweaks.",
+            base_fix, cluster.count, op
+        ),
+        None => format!(
+            "{} This exact failure has been seen {} times; avoid the construction that triggers it.",
+            base_fix, cluster.count
+        ),
+    };
+
+    LearnedAntiPattern {
+        id,
+        title,
+        error_category: cluster.error_category,
+        failing_operation: cluster.failing_operation,
+        trigger_phrase: cluster.phrase,
+        occurrences: cluster.count,
+        example_errors: cluster.examples,
+        suggested_fix,
+        created_at_ms: telemetry::now_ms(),
+    }
+}
+
+/// Find an approved pattern matching a classified error.
+pub fn match_learned<'a>(
+    error: &StructuredError,
+    learned: &'a [LearnedAntiPattern],
+) -> Option<&'a LearnedAntiPattern> {
+    let category = format!("{:?}", error.category);
+    let tokens = phrase_tokens(&error_phrase(error));
+    learned.iter().find(|p| {
+        p.error_category == category
+            && (p.failing_operation.is_none() || p.failing_operation == error.failing_operation)
+            && jaccard(&phrase_tokens(&p.trigger_phrase), &tokens) >= PHRASE_SIMILARITY_THRESHOLD
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Active set and persistence
+// ---------------------------------------------------------------------------
+
+static ACTIVE: OnceLock<Mutex<Vec<LearnedAntiPattern>>> = OnceLock::new();
+
+fn active() -> &'static Mutex<Vec<LearnedAntiPattern>> {
+    ACTIVE.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Approved patterns currently used for retry strategies and repair prompts.
+pub fn active_patterns() -> Vec<LearnedAntiPattern> {
+    active().lock().map(|a| a.clone()).unwrap_or_default()
+}
+
+/// Title of the approved pattern matching `error`, if any.
+pub fn match_active(error: &StructuredError) -> Option<String> {
+    let patterns = active().lock().ok()?;
+    match_learned(error, &patterns).map(|p| p.title.clone())
+}
+
+/// Load approved patterns from disk into the active set. Called at startup.
+pub fn init_active() {
+    let learned = read_list(learned_path());
+    if let Ok(mut guard) = active().lock() {
+        *guard = learned;
+    }
+}
+
+fn storage_dir() -> Result<PathBuf, AppError> {
+    let base = dirs::config_dir()
+        .ok_or_else(|| AppError::ConfigError("Cannot resolve config directory".to_string()))?;
+    Ok(base.join("cadai-studio"))
+}
+
+fn pending_path() -> Result<PathBuf, AppError> {
+    Ok(storage_dir()?.join("anti_patterns_pending.json"))
+}
+
+fn learned_path() -> Result<PathBuf, AppError> {
+    Ok(storage_dir()?.join("anti_patterns_learned.json"))
+}
+
+fn read_list(path: Result<PathBuf, AppError>) -> Vec<LearnedAntiPattern> {
+    path.ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn write_list(path: PathBuf, list: &[LearnedAntiPattern]) -> Result<(), AppError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(list)?)?;
+    Ok(())
+}
+
+/// Cluster failed runs from the telemetry traces and add new drafts to the
+/// pending-review file. Returns the drafts that were added.
+pub fn mine(min_occurrences: usize) -> Result<Vec<LearnedAntiPattern>, AppError> {
+    let traces = match std::fs::read_to_string(telemetry::traces_path()?) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let drafts = cluster_failures(&failures_from_traces(&traces), min_occurrences);
+
+    let mut pending = read_list(pending_path());
+    let learned = read_list(learned_path());
+    let known: HashSet<String> = pending
+        .iter()
+        .chain(learned.iter())
+        .map(|p| p.id.clone())
+        .collect();
+    let added: Vec<LearnedAntiPattern> = drafts
+        .into_iter()
+        .filter(|d| !known.contains(&d.id))
+        .collect();
+
+    if !added.is_empty() {
+        pending.extend(added.iter().cloned());
+        write_list(pending_path()?, &pending)?;
+    }
+    Ok(added)
+}
+
+/// Drafts waiting for review.
+pub fn pending() -> Vec<LearnedAntiPattern> {
+    read_list(pending_path())
+}
+
+/// Move a pending draft into the approved set and activate it.
+pub fn approve(id: &str) -> Result<LearnedAntiPattern, AppError> {
+    let mut pending = read_list(pending_path());
+    let pos = pending
+        .iter()
+        .position(|p| p.id == id)
+        .ok_or_else(|| AppError::ConfigError(format!("No pending anti-pattern '{}'", id)))?;
+    let approved = pending.remove(pos);
+
+    let mut learned = read_list(learned_path());
+    learned.retain(|p| p.id != approved.id);
+    learned.push(approved.clone());
+
+    write_list(learned_path()?, &learned)?;
+    write_list(pending_path()?, &pending)?;
+    if let Ok(mut guard) = active().lock() {
+        *guard = learned;
+    }
+    Ok(approved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OCP_FAILURE: &str = "Traceback (most recent call last):\n  File \"input.py\", line 12, in <module>\n    result = part.fillet(2.5)\nOCP.StdFail_NotDone: BRep_API: command not done at 0x7f3a2c";
+
+    fn trace(success: bool, error: Option<&str>) -> String {
+        serde_json::json!({
+            "version": 1,
+            "execution_success": success,
+            "final_error": error,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_normalize_strips_run_specific_details() {
+        let a = normalize_error_phrase("ValueError: radius 2.5 too large for edge 'e12' at 0x7f00");
+        let b = normalize_error_phrase("ValueError:  radius 4 too large for edge 'e3' at 0x1a2b");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_failures_from_traces_keeps_failed_runs_only() {
+        let jsonl = [
+            trace(true, None),
+            trace(false, Some("NameError: name 'Bx' is not defined")),
+            trace(false, None),
+            "not json".to_string(),
+        ]
+        .join("\n");
+        let failures = failures_from_traces(&jsonl);
+        assert_eq!(failures, vec!["NameError: name 'Bx' is not defined".to_string()]);
+    }
+
+    #[test]
+    fn test_cluster_groups_recurring_errors_above_threshold() {
+        let mut errors: Vec<String> = (0..4)
+            .map(|i| OCP_FAILURE.replace("2.5", &format!("{}.0", i + 1)))
+            .collect();
+        errors.push("NameError: name 'Cylindr' is not defined".to_string());
+        errors.push("NameError: name 'Boxx' is not defined".to_string());
+
+        let drafts = cluster_failures(&errors, 3);
+        assert_eq!(drafts.len(), 1);
+        let draft = &drafts[0];
+        assert_eq!(draft.occurrences, 4);
+        assert_eq!(draft.failing_operation.as_deref(), Some("fillet"));
+        assert!(draft.trigger_phrase.contains("command not done"));
+        assert!(draft.example_errors.len() <= MAX_EXAMPLES);
+        assert!(draft.suggested_fix.contains("seen 4 times"));
+
+        let with_low_threshold = cluster_failures(&errors, 2);
+        assert_eq!(with_low_threshold.len(), 2);
+        assert_eq!(with_low_threshold[1].occurrences, 2);
+    }
+
+    #[test]
+    fn test_cluster_separates_different_operations() {
+        let fillet: Vec<String> = (0..3).map(|_| OCP_FAILURE.to_string()).collect();
+        let shell: Vec<String> = (0..3)
+            .map(|_| OCP_FAILURE.replace("part.fillet(2.5)", "part.shell(-1)"))
+            .collect();
+        let errors: Vec<String> = fillet.into_iter().chain(shell).collect();
+        let drafts = cluster_failures(&errors, 3);
+        assert_eq!(drafts.len(), 2);
+        assert_ne!(drafts[0].id, drafts[1].id);
+    }
+
+    #[test]
+    fn test_match_learned_uses_category_operation_and_phrase() {
+        let errors: Vec<String> = (0..3).map(|_| OCP_FAILURE.to_string()).collect();
+        let learned = cluster_failures(&errors, 3);
+
+        let same = validate::parse_traceback(&OCP_FAILURE.replace("2.5", "9"));
+        assert!(match_learned(&same, &learned).is_some());
+
+        let other = validate::parse_traceback("NameError: name 'Bx' is not defined");
+        assert!(match_learned(&other, &learned).is_none());
+
+        let entry = learned[0].to_entry();
+        assert_eq!(entry.title, learned[0].title);
+        assert_eq!(entry.explanation, learned[0].suggested_fix);
+    }
+}
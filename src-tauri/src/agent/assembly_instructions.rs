@@ -0,0 +1,391 @@
+use regex::Regex;
+
+use crate::agent::clearance::{self, FitKind};
+use crate::agent::layout;
+use crate::ai::message::ChatMessage;
+use crate::ai::provider::AiProvider;
+
+/// Mating features worth naming in an instruction ("the housing's 40mm recess").
+const MATING_FEATURES: &[&str] = &[
+    "recess", "pocket", "groove", "slot", "opening", "bore", "hole", "socket", "channel", "rail",
+    "rim", "cavity", "cutout", "boss", "post", "peg",
+];
+
+const POLISH_SYSTEM: &str = "You edit assembly instructions for a printed multi-part \
+model. Rewrite each numbered step as one clear imperative sentence for someone who has \
+never seen the model. Keep every part name, dimension and step order exactly as given, \
+add nothing new, and reply with the numbered steps only.";
+
+/// One part of a finished run, as described in the instructions.
+#[derive(Debug, Clone)]
+pub struct InstructionPart {
+    pub name: String,
+    pub description: String,
+    pub constraints: Vec<String>,
+    pub material: String,
+    /// Measured bounding-box size (x, y, z) in mm.
+    pub size_mm: Option<[f64; 3]>,
+    /// Image path relative to the instructions file.
+    pub thumbnail: Option<String>,
+}
+
+/// `part` is fitted onto or into `mate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mating {
+    pub part: String,
+    pub mate: String,
+    pub fit: Option<FitKind>,
+    /// The plan constraint that names the mate, when there is one.
+    pub constraint: Option<String>,
+    pub instruction: String,
+}
+
+/// "the housing's 40mm recess" when the constraint sizes a mating feature,
+/// else "the housing".
+fn mate_target(mate: &str, constraint: Option<&str>) -> String {
+    let feature_re = Regex::new(&format!(
+        r"(?i)\b(\d+(?:\.\d+)?\s*mm\s+(?:{}))\b",
+        MATING_FEATURES.join("|")
+    ))
+    .unwrap();
+    match constraint.and_then(|c| feature_re.captures(c)) {
+        Some(cap) => format!("the {}'s {}", mate, cap[1].to_lowercase()),
+        None => format!("the {}", mate),
+    }
+}
+
+fn instruction_for(part: &InstructionPart, mate: &str, constraint: Option<&str>) -> String {
+    let text = constraint.unwrap_or(&part.description).to_lowercase();
+    let target = mate_target(mate, constraint);
+    let into_or_onto = if target.contains("'s ") {
+        "into"
+    } else {
+        "onto"
+    };
+    let mentions = |words: &[&str]| words.iter().any(|w| text.contains(w));
+    if mentions(&["screw", "bolt"]) {
+        let fastener = if text.contains("bolt") {
+            "bolts"
+        } else {
+            "screws"
+        };
+        return format!("Fasten the {} to {} with {}.", part.name, target, fastener);
+    }
+    match clearance::fit_kind(&text) {
+        Some(FitKind::Snap) if mentions(&["snap", "clip", "latch"]) => format!(
+            "Press the {} {} {} until the snap lips engage.",
+            part.name, into_or_onto, target
+        ),
+        Some(FitKind::Snap) => format!("Fit the {} {} {}.", part.name, into_or_onto, target),
+        Some(FitKind::Sliding) => {
+            format!("Slide the {} into {} until it seats.", part.name, target)
+        }
+        None if mentions(&["press fit", "press-fit", "interference"]) => {
+            format!("Press the {} {} {}.", part.name, into_or_onto, target)
+        }
+        None if layout::is_stacked_part(&part.name) => {
+            format!("Place the {} on top of {}.", part.name, target)
+        }
+        None => format!("Attach the {} to {}.", part.name, target),
+    }
+}
+
+/// Mating relationships named by the plan: a constraint that mentions another
+/// part, or a lid-like part resting on its body (see `layout::mating_index`).
+pub fn mating_relationships(parts: &[InstructionPart]) -> Vec<Mating> {
+    let layout_parts: Vec<layout::LayoutPart> = parts
+        .iter()
+        .map(|p| layout::LayoutPart {
+            name: p.name.clone(),
+            position: [0.0; 3],
+            constraints: p.constraints.clone(),
+            bounds: None,
+        })
+        .collect();
+    parts
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, part)| {
+            let mate = &parts[layout::mating_index(&layout_parts, idx)?].name;
+            let constraint = part
+                .constraints
+                .iter()
+                .find(|c| layout::mentions_part(c, mate))
+                .cloned();
+            Some(Mating {
+                part: part.name.clone(),
+                mate: mate.clone(),
+                fit: clearance::fit_kind(constraint.as_deref().unwrap_or(&part.description)),
+                instruction: instruction_for(part, mate, constraint.as_deref()),
+                constraint,
+            })
+        })
+        .collect()
+}
+
+/// Ordered assembly steps. Parts nothing mounts onto are set out first; then
+/// each part is fitted once every part that mounts onto it is already in
+/// place, so sub-assemblies are finished before they go into the main body.
+/// Without any mating relationship the steps follow plan order.
+pub fn assembly_sequence(parts: &[InstructionPart], matings: &[Mating]) -> Vec<String> {
+    if matings.is_empty() {
+        return parts
+            .iter()
+            .map(|p| format!("Place the {} at its assembly position.", p.name))
+            .collect();
+    }
+    let mating_of = |name: &str| matings.iter().find(|m| m.part == name);
+    let mut pending_dependents: Vec<usize> = parts
+        .iter()
+        .map(|p| matings.iter().filter(|m| m.mate == p.name).count())
+        .collect();
+
+    let mut steps: Vec<String> = parts
+        .iter()
+        .zip(&pending_dependents)
+        .filter(|(p, _)| mating_of(&p.name).is_none())
+        .map(|(p, &dependents)| {
+            if dependents > 0 {
+                format!("Start with the {}.", p.name)
+            } else {
+                format!(
+                    "Set the {} in place; the plan names no connection for it.",
+                    p.name
+                )
+            }
+        })
+        .collect();
+
+    let mut fitted = vec![false; parts.len()];
+    loop {
+        let next = (0..parts.len()).find(|&i| {
+            !fitted[i] && pending_dependents[i] == 0 && mating_of(&parts[i].name).is_some()
+        });
+        // A cycle of mutual constraints: fit the rest in plan order.
+        let Some(idx) = next.or_else(|| {
+            (0..parts.len()).find(|&i| !fitted[i] && mating_of(&parts[i].name).is_some())
+        }) else {
+            break;
+        };
+        fitted[idx] = true;
+        let mating = mating_of(&parts[idx].name).expect("only mated parts are fitted");
+        steps.push(mating.instruction.clone());
+        if let Some(mate_idx) = parts.iter().position(|p| p.name == mating.mate) {
+            pending_dependents[mate_idx] = pending_dependents[mate_idx].saturating_sub(1);
+        }
+    }
+    steps
+}
+
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Markdown instructions: bill of materials, part thumbnails (when any were
+/// rendered), mating relationships and the numbered assembly sequence.
+pub fn format_instructions(
+    user_request: &str,
+    parts: &[InstructionPart],
+    matings: &[Mating],
+    steps: &[String],
+) -> String {
+    let mut out = String::from("# Assembly Instructions\n\n");
+    if !user_request.trim().is_empty() {
+        out.push_str(&format!("> {}\n\n", user_request.trim()));
+    }
+
+    out.push_str("## Bill of Materials\n\n");
+    out.push_str("| # | Part | Material | Size (mm) | Description |\n");
+    out.push_str("|---|------|----------|-----------|-------------|\n");
+    for (i, part) in parts.iter().enumerate() {
+        let size = part
+            .size_mm
+            .map(|s| format!("{:.1} × {:.1} × {:.1}", s[0], s[1], s[2]))
+            .unwrap_or_else(|| "—".to_string());
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            i + 1,
+            table_cell(&part.name),
+            table_cell(&part.material),
+            size,
+            table_cell(&part.description)
+        ));
+    }
+    out.push('\n');
+
+    if parts.iter().any(|p| p.thumbnail.is_some()) {
+        out.push_str("## Parts\n\n");
+        for part in parts {
+            if let Some(thumbnail) = &part.thumbnail {
+                out.push_str(&format!(
+                    "### {}\n\n![{}]({})\n\n",
+                    part.name, part.name, thumbnail
+                ));
+            }
+        }
+    }
+
+    out.push_str("## Mating Relationships\n\n");
+    if matings.is_empty() {
+        out.push_str("The plan describes no connections between parts.\n\n");
+    }
+    for mating in matings {
+        let fit = mating
+            .fit
+            .map(|f| format!(" ({})", f.label()))
+            .unwrap_or_default();
+        out.push_str(&format!("- **{} → {}**{}", mating.part, mating.mate, fit));
+        match &mating.constraint {
+            Some(constraint) => out.push_str(&format!(": {}\n", constraint)),
+            None => out.push('\n'),
+        }
+    }
+    if !matings.is_empty() {
+        out.push('\n');
+    }
+
+    out.push_str("## Assembly Sequence\n\n");
+    for (i, step) in steps.iter().enumerate() {
+        out.push_str(&format!("{}. {}\n", i + 1, step));
+    }
+    out
+}
+
+/// Numbered lines of a reply ("1. ...", "2) ..."), without their numbers.
+fn numbered_lines(response: &str) -> Vec<String> {
+    let re = Regex::new(r"^\s*\d+[.)]\s+(.+)$").unwrap();
+    response
+        .lines()
+        .filter_map(|line| re.captures(line).map(|cap| cap[1].trim().to_string()))
+        .collect()
+}
+
+/// Reword `steps` with one AI call. The template steps are kept when the call
+/// fails or the reply does not have one numbered line per step.
+pub async fn polish_steps(provider: Box<dyn AiProvider>, steps: Vec<String>) -> Vec<String> {
+    let numbered: Vec<String> = steps
+        .iter()
+        .enumerate()
+        .map(|(i, s)| format!("{}. {}", i + 1, s))
+        .collect();
+    let messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: POLISH_SYSTEM.to_string(),
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: numbered.join("\n"),
+        },
+    ];
+    match provider.complete(&messages, Some(800)).await {
+        Ok((response, _usage)) => {
+            let polished = numbered_lines(&response);
+            if polished.len() == steps.len() {
+                polished
+            } else {
+                steps
+            }
+        }
+        Err(_) => steps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(name: &str, description: &str, constraints: &[&str]) -> InstructionPart {
+        InstructionPart {
+            name: name.to_string(),
+            description: description.to_string(),
+            constraints: constraints.iter().map(|c| c.to_string()).collect(),
+            material: "pla".to_string(),
+            size_mm: None,
+            thumbnail: None,
+        }
+    }
+
+    fn snap_fit_fixture() -> Vec<InstructionPart> {
+        let mut housing = part("housing", "Open box with a 40mm recess in the back", &[]);
+        housing.size_mm = Some([60.0, 44.0, 25.0]);
+        housing.thumbnail = Some("instructions_thumbnails/housing.svg".to_string());
+        let back_plate = part(
+            "back_plate",
+            "Flat plate with two snap lips",
+            &["Snap fit into the housing's 40mm recess"],
+        );
+        vec![housing, back_plate]
+    }
+
+    #[test]
+    fn test_snap_fit_fixture_instructions() {
+        let parts = snap_fit_fixture();
+        let matings = mating_relationships(&parts);
+        assert_eq!(matings.len(), 1);
+        assert_eq!(matings[0].mate, "housing");
+        assert_eq!(matings[0].fit, Some(FitKind::Snap));
+
+        let steps = assembly_sequence(&parts, &matings);
+        let doc = format_instructions("Snap-fit electronics box", &parts, &matings, &steps);
+        let expected = "# Assembly Instructions\n\n\
+> Snap-fit electronics box\n\n\
+## Bill of Materials\n\n\
+| # | Part | Material | Size (mm) | Description |\n\
+|---|------|----------|-----------|-------------|\n\
+| 1 | housing | pla | 60.0 × 44.0 × 25.0 | Open box with a 40mm recess in the back |\n\
+| 2 | back_plate | pla | — | Flat plate with two snap lips |\n\n\
+## Parts\n\n\
+### housing\n\n\
+![housing](instructions_thumbnails/housing.svg)\n\n\
+## Mating Relationships\n\n\
+- **back_plate → housing** (snap fit): Snap fit into the housing's 40mm recess\n\n\
+## Assembly Sequence\n\n\
+1. Start with the housing.\n\
+2. Press the back_plate into the housing's 40mm recess until the snap lips engage.\n";
+        assert_eq!(doc, expected);
+    }
+
+    #[test]
+    fn test_sequence_finishes_sub_assemblies_first() {
+        let parts = vec![
+            part("housing", "Gearbox housing", &[]),
+            part(
+                "shaft",
+                "Drive shaft",
+                &["Slides into the housing's 8mm bore"],
+            ),
+            part("gear", "Spur gear", &["Press fit onto the shaft"]),
+        ];
+        let matings = mating_relationships(&parts);
+        let steps = assembly_sequence(&parts, &matings);
+        assert_eq!(
+            steps,
+            vec![
+                "Start with the housing.",
+                "Press the gear onto the shaft.",
+                "Slide the shaft into the housing's 8mm bore until it seats.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_connections_degrade_to_plan_order() {
+        let parts = vec![part("left_leg", "Leg", &[]), part("right_leg", "Leg", &[])];
+        let matings = mating_relationships(&parts);
+        assert!(matings.is_empty());
+        let steps = assembly_sequence(&parts, &matings);
+        assert_eq!(steps[0], "Place the left_leg at its assembly position.");
+        let doc = format_instructions("", &parts, &matings, &steps);
+        assert!(doc.contains("The plan describes no connections between parts."));
+        assert!(!doc.contains("## Parts"));
+    }
+
+    #[test]
+    fn test_numbered_lines_strips_numbers() {
+        assert_eq!(
+            numbered_lines("Here you go:\n1. Press it in.\n2) Screw it down.\n"),
+            vec!["Press it in.", "Screw it down."]
+        );
+    }
+}
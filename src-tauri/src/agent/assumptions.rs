@@ -0,0 +1,108 @@
+use regex::Regex;
+use serde::Serialize;
+
+/// A review-worthy comment left in generated code, e.g. `# TODO: verify
+/// clearance` or `# assuming 2mm wall`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CodeAssumption {
+    /// 1-based line number of the comment.
+    pub line: usize,
+    /// Normalized marker: `TODO`, `FIXME`, `ASSUME` or `NOTE`.
+    pub marker: String,
+    /// Comment text without the leading `#`.
+    pub text: String,
+}
+
+/// Start of the `#` comment on `line`, skipping `#` inside string literals.
+fn comment_start(line: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '#' => return Some(i),
+                '\'' | '"' => quote = Some(c),
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+/// Comments starting with a TODO/FIXME/ASSUME/NOTE marker (any case, and
+/// `assuming`/`assumption`/`notes` forms), whole-line or trailing code.
+pub fn extract_assumptions(code: &str) -> Vec<CodeAssumption> {
+    let marker_re = Regex::new(r"(?i)^(todo|fixme|assum\w*|notes?)\b").unwrap();
+    code.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let text = line[comment_start(line)?..].trim_start_matches('#').trim();
+            let word = marker_re.captures(text)?.get(1)?.as_str().to_lowercase();
+            let marker = if word.starts_with("assum") {
+                "ASSUME"
+            } else if word.starts_with("note") {
+                "NOTE"
+            } else {
+                word.as_str()
+            };
+            Some(CodeAssumption {
+                line: i + 1,
+                marker: marker.to_uppercase(),
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_marker_variants() {
+        let code = "from build123d import *\n\
+            # TODO: verify clearance against the lid\n\
+            wall = 2  # assuming 2mm wall\n\
+            #FIXME hole pattern is off by one\n\
+            # Assumption - M3 screws throughout\n\
+            \x20   # note: fillet kept small for printability\n\
+            # todo check overhangs\n\
+            result = Box(10, 10, wall)\n";
+        let found = extract_assumptions(code);
+        let summary: Vec<(usize, &str)> =
+            found.iter().map(|a| (a.line, a.marker.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, "TODO"),
+                (3, "ASSUME"),
+                (4, "FIXME"),
+                (5, "ASSUME"),
+                (6, "NOTE"),
+                (7, "TODO"),
+            ]
+        );
+        assert_eq!(found[0].text, "TODO: verify clearance against the lid");
+        assert_eq!(found[1].text, "assuming 2mm wall");
+    }
+
+    #[test]
+    fn test_ignores_normal_comments_and_strings() {
+        let code = "from build123d import *\n\
+            # Create the base plate\n\
+            # Nothing to do here, the notebook layout is fixed\n\
+            label = \"# TODO not a comment\"\n\
+            tag = 'note # inside'  # mounting holes\n\
+            result = Box(10, 10, 2)  # final body\n";
+        assert!(extract_assumptions(code).is_empty());
+    }
+}
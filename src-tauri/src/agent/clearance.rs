@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::agent::code_import;
+use crate::agent::rules::AgentRules;
+use crate::config::AppConfig;
+
+/// Heading of the prompt section carrying the clearance target.
+pub const CLEARANCE_TARGET_HEADING: &str = "## Clearance Target";
+/// Measured gaps within this many mm of a range bound count as inside it.
+const GAP_TOLERANCE: f64 = 1e-6;
+/// Gaps above this are design dimensions, not fit clearances.
+const MAX_FIT_GAP_MM: f64 = 5.0;
+
+/// Words that mean a part mates with another through a clearance fit.
+const CONNECTION_KEYWORDS: &[&str] = &[
+    "snap",
+    "clip",
+    "latch",
+    "fits into",
+    "fit into",
+    "mates with",
+    "mating",
+    "slot into",
+    "clearance",
+    "tolerance",
+    "gap",
+];
+/// Words that mean the fit moves in use and needs a running clearance.
+const SLIDING_KEYWORDS: &[&str] = &[
+    "slide", "sliding", "slider", "hinge", "pivot", "rotate", "rotating", "shaft", "bearing",
+];
+/// Interference fits have no gap to check.
+const INTERFERENCE_KEYWORDS: &[&str] = &["press fit", "press-fit", "interference"];
+/// Variable name fragments that hold a fit clearance.
+const GAP_NAME_FRAGMENTS: &[&str] = &["clearance", "tolerance", "gap", "play"];
+
+/// Allowed gap between mating surfaces; `max_mm` of None means "at least".
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ClearanceRange {
+    pub min_mm: f64,
+    #[serde(default)]
+    pub max_mm: Option<f64>,
+}
+
+impl ClearanceRange {
+    pub fn contains(&self, gap_mm: f64) -> bool {
+        gap_mm >= self.min_mm - GAP_TOLERANCE
+            && self.max_mm.is_none_or(|max| gap_mm <= max + GAP_TOLERANCE)
+    }
+
+    pub fn describe(&self) -> String {
+        match self.max_mm {
+            Some(max) => format!("{}–{}mm", self.min_mm, max),
+            None => format!("{}mm or more", self.min_mm),
+        }
+    }
+}
+
+/// Process id (`fdm`, `sla`, `cnc_it7`, ...) → fit (`snap`, `sliding`) → range,
+/// from `manufacturing.clearance_rules` in the agent rules.
+pub type ClearanceRules = BTreeMap<String, BTreeMap<String, ClearanceRange>>;
+
+/// Clearance rules of `rules`, empty when the preset defines none.
+pub fn rules_from(rules: &AgentRules) -> ClearanceRules {
+    rules
+        .manufacturing
+        .as_ref()
+        .and_then(|m| m.get("clearance_rules"))
+        .and_then(|v| serde_yaml::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Clearance rules of the configured agent rules preset.
+pub fn load_rules(config: &AppConfig) -> ClearanceRules {
+    AgentRules::from_preset(config.agent_rules_preset.as_deref())
+        .map(|rules| rules_from(&rules))
+        .unwrap_or_default()
+}
+
+/// Process whose clearances apply: `manufacturing_process` when set, else
+/// general-tolerance CNC for the CNC preset and FDM printing otherwise.
+pub fn active_process(config: &AppConfig) -> String {
+    if let Some(process) = config
+        .manufacturing_process
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        return process.to_ascii_lowercase();
+    }
+    match config.agent_rules_preset.as_deref() {
+        Some("cnc") => "cnc_it9".to_string(),
+        _ => "fdm".to_string(),
+    }
+}
+
+/// Display name of a process id, e.g. `cnc_it7` → `CNC IT7`.
+pub fn process_label(process: &str) -> String {
+    process.replace('_', " ").to_uppercase()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FitKind {
+    Snap,
+    Sliding,
+}
+
+impl FitKind {
+    fn key(self) -> &'static str {
+        match self {
+            FitKind::Snap => "snap",
+            FitKind::Sliding => "sliding",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FitKind::Snap => "snap fit",
+            FitKind::Sliding => "sliding fit",
+        }
+    }
+}
+
+/// The clearance fit `text` asks for, or `None` when it names no connection
+/// or clearance (or only an interference fit).
+pub fn fit_kind(text: &str) -> Option<FitKind> {
+    let lower = text.to_lowercase();
+    let mentions = |words: &[&str]| words.iter().any(|w| lower.contains(w));
+    if mentions(INTERFERENCE_KEYWORDS) && !mentions(&["snap", "clip", "latch"]) {
+        None
+    } else if mentions(SLIDING_KEYWORDS) {
+        Some(FitKind::Sliding)
+    } else if mentions(CONNECTION_KEYWORDS) {
+        Some(FitKind::Snap)
+    } else {
+        None
+    }
+}
+
+/// Prompt section with the numeric clearance targets of `process`, or `None`
+/// when `part_text` names no fit or the process has no rules.
+pub fn prompt_section(rules: &ClearanceRules, process: &str, part_text: &str) -> Option<String> {
+    let fit = fit_kind(part_text)?;
+    let fits = rules.get(process).filter(|fits| !fits.is_empty())?;
+    let mut out = format!(
+        "{} ({})\n\
+         This part mates with another. Put the gap between mating surfaces in a top-level \
+         `clearance` variable and use these values for {}:\n",
+        CLEARANCE_TARGET_HEADING,
+        process_label(process),
+        process_label(process)
+    );
+    for (name, range) in fits {
+        let marker = if name == fit.key() {
+            " ← this part"
+        } else {
+            ""
+        };
+        out.push_str(&format!("- {} fit: {}{}\n", name, range.describe(), marker));
+    }
+    Some(out)
+}
+
+/// A part checked for clearances: its plan text and accepted code.
+pub struct ClearancePart<'a> {
+    pub name: &'a str,
+    /// Description and constraints from the plan.
+    pub text: String,
+    pub code: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClearanceViolation {
+    pub part: String,
+    pub mating_part: String,
+    /// Code variable holding the gap.
+    pub parameter: String,
+    pub gap_mm: f64,
+    pub fit: FitKind,
+    pub process: String,
+    pub allowed: ClearanceRange,
+}
+
+impl ClearanceViolation {
+    pub fn message(&self) -> String {
+        format!(
+            "Clearance between '{}' and '{}' is {}mm (`{}`), outside the {} {} range of {}",
+            self.part,
+            self.mating_part,
+            self.gap_mm,
+            self.parameter,
+            process_label(&self.process),
+            self.fit.label(),
+            self.allowed.describe()
+        )
+    }
+}
+
+/// Top-level clearance-like assignments in `code` as (name, gap in mm).
+fn code_gaps(code: &str) -> Vec<(String, f64)> {
+    code_import::extract_parameters(code)
+        .into_iter()
+        .filter(|p| {
+            let name = p.name.to_lowercase();
+            GAP_NAME_FRAGMENTS.iter().any(|f| name.contains(f))
+                && !name.contains("interference")
+                && p.value > 0.0
+                && p.value <= MAX_FIT_GAP_MM
+        })
+        .map(|p| (p.name, p.value))
+        .collect()
+}
+
+/// The part `part` mates with: the first other part its text names, else
+/// the first other part.
+fn mating_part<'a>(part: &ClearancePart, parts: &'a [ClearancePart]) -> Option<&'a str> {
+    let lower = part.text.to_lowercase();
+    let others = || parts.iter().filter(|other| other.name != part.name);
+    others()
+        .find(|other| {
+            let name = other.name.to_lowercase();
+            lower.contains(&name) || lower.contains(&name.replace('_', " "))
+        })
+        .or_else(|| others().next())
+        .map(|other| other.name)
+}
+
+/// Compare every clearance variable of parts that name a fit against the
+/// range `rules` allow for `process`.
+pub fn check_clearances(
+    parts: &[ClearancePart],
+    rules: &ClearanceRules,
+    process: &str,
+) -> Vec<ClearanceViolation> {
+    let Some(fits) = rules.get(process) else {
+        return Vec::new();
+    };
+    let mut violations = Vec::new();
+    for part in parts {
+        let Some(fit) = fit_kind(&part.text) else {
+            continue;
+        };
+        let (Some(allowed), Some(mate)) = (fits.get(fit.key()), mating_part(part, parts)) else {
+            continue;
+        };
+        for (parameter, gap_mm) in code_gaps(part.code) {
+            if !allowed.contains(gap_mm) {
+                violations.push(ClearanceViolation {
+                    part: part.name.to_string(),
+                    mating_part: mate.to_string(),
+                    parameter,
+                    gap_mm,
+                    fit,
+                    process: process.to_string(),
+                    allowed: *allowed,
+                });
+            }
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset_rules(name: Option<&str>) -> ClearanceRules {
+        let (_, yaml) = AgentRules::preset_yaml(name);
+        rules_from(&serde_yaml::from_str(yaml).unwrap())
+    }
+
+    fn part<'a>(name: &'a str, text: &str, code: &'a str) -> ClearancePart<'a> {
+        ClearancePart {
+            name,
+            text: text.to_string(),
+            code,
+        }
+    }
+
+    #[test]
+    fn test_presets_define_process_ranges() {
+        for preset in [None, Some("3d-printing"), Some("cnc")] {
+            let rules = preset_rules(preset);
+            let fdm = &rules["fdm"];
+            assert_eq!(
+                fdm["snap"],
+                ClearanceRange {
+                    min_mm: 0.2,
+                    max_mm: Some(0.3)
+                }
+            );
+            assert_eq!(fdm["sliding"].max_mm, None);
+            assert!(rules["sla"]["snap"].max_mm.unwrap() < 0.3);
+            assert!(rules.contains_key("cnc_it7") && rules.contains_key("cnc_it9"));
+        }
+    }
+
+    #[test]
+    fn test_active_process_follows_override_then_preset() {
+        let mut config = AppConfig::default();
+        assert_eq!(active_process(&config), "fdm");
+        config.agent_rules_preset = Some("cnc".to_string());
+        assert_eq!(active_process(&config), "cnc_it9");
+        config.manufacturing_process = Some("SLA".to_string());
+        assert_eq!(active_process(&config), "sla");
+    }
+
+    #[test]
+    fn test_fit_kind_detection() {
+        assert_eq!(fit_kind("Lid snaps onto the base"), Some(FitKind::Snap));
+        assert_eq!(
+            fit_kind("Drawer slides into the frame"),
+            Some(FitKind::Sliding)
+        );
+        assert_eq!(fit_kind("Bearing press-fit into the housing"), None);
+        assert_eq!(fit_kind("Solid 40mm cube"), None);
+    }
+
+    #[test]
+    fn test_fdm_snap_gap_out_of_range_names_both_parts() {
+        let rules = preset_rules(None);
+        let parts = [
+            part(
+                "lid",
+                "Snap-fit lid for the base",
+                "clearance = 0.1\nwall = 2\nresult = None\n",
+            ),
+            part("base", "Box body", "wall = 2\n"),
+        ];
+        let violations = check_clearances(&parts, &rules, "fdm");
+        assert_eq!(violations.len(), 1);
+        let message = violations[0].message();
+        assert!(message.contains("'lid' and 'base'"), "{}", message);
+        assert!(
+            message.contains("0.1mm") && message.contains("0.2–0.3mm"),
+            "{}",
+            message
+        );
+
+        let fixed = [
+            part("lid", "Snap-fit lid for the base", "clearance = 0.25\n"),
+            part("base", "Box body", ""),
+        ];
+        assert!(check_clearances(&fixed, &rules, "fdm").is_empty());
+    }
+
+    #[test]
+    fn test_same_gap_checked_against_process_range() {
+        let rules = preset_rules(None);
+        let parts = [
+            part("slider", "Slides in the rail", "slide_gap = 0.25\n"),
+            part("rail", "Guide rail", ""),
+        ];
+        // 0.25mm is too tight for a sliding FDM fit but fine on SLA.
+        assert_eq!(check_clearances(&parts, &rules, "fdm").len(), 1);
+        assert!(check_clearances(&parts, &rules, "sla").is_empty());
+    }
+
+    #[test]
+    fn test_mating_part_prefers_named_part() {
+        let rules = preset_rules(None);
+        let parts = [
+            part("base", "Box body", ""),
+            part("pcb_tray", "Tray", ""),
+            part(
+                "clip",
+                "Clip latches onto the pcb tray",
+                "tolerance = 0.6\n",
+            ),
+        ];
+        let violations = check_clearances(&parts, &rules, "fdm");
+        assert_eq!(violations[0].mating_part, "pcb_tray");
+    }
+
+    #[test]
+    fn test_prompt_section_states_numeric_target() {
+        let rules = preset_rules(None);
+        let section = prompt_section(&rules, "fdm", "Lid snaps onto the base").unwrap();
+        assert!(section.starts_with(CLEARANCE_TARGET_HEADING));
+        assert!(
+            section.contains("snap fit: 0.2–0.3mm ← this part"),
+            "{}",
+            section
+        );
+        assert!(prompt_section(&rules, "fdm", "Solid cube").is_none());
+        assert!(prompt_section(&rules, "unknown", "Lid snaps on").is_none());
+    }
+}
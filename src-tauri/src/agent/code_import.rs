@@ -0,0 +1,538 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Constructors and operations whose return value is solid geometry.
+const GEOMETRY_MARKERS: &[&str] = &[
+    "Workplane(",
+    "BuildPart(",
+    "Box(",
+    "Cylinder(",
+    "Sphere(",
+    "Cone(",
+    "Torus(",
+    "Wedge(",
+    "extrude(",
+    "revolve(",
+    "loft(",
+    "sweep(",
+    "Compound(",
+    "Part(",
+    "Solid.",
+];
+
+/// A top-level `name = number` assignment in an imported script.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CodeParameter {
+    pub name: String,
+    pub value: f64,
+    /// 1-based line number of the assignment.
+    pub line: usize,
+}
+
+/// A top-level assignment computed from parameters, e.g. `inner = outer - 2*wall`.
+/// Changing a parameter means recomputing it rather than editing it directly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DerivedParameter {
+    pub name: String,
+    pub expression: String,
+    /// Parameters and earlier derived parameters the expression references.
+    pub depends_on: Vec<String>,
+    /// 1-based line number of the assignment.
+    pub line: usize,
+}
+
+/// Imported code after the `result` adapter has run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptedCode {
+    pub code: String,
+    /// Variable that was bound to `result`, when the adapter had to add it.
+    pub result_from: Option<String>,
+    pub warning: Option<String>,
+}
+
+/// Extract simple top-level numeric assignments (e.g. `wall = 2.5`).
+///
+/// Only unindented lines count, and a repeated name keeps its first value.
+pub fn extract_parameters(code: &str) -> Vec<CodeParameter> {
+    let assign_re =
+        Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)\s*=\s*(-?\d+(?:\.\d+)?)\s*(?:#.*)?$").unwrap();
+    let mut params: Vec<CodeParameter> = Vec::new();
+    for (idx, line) in code.lines().enumerate() {
+        let Some(cap) = assign_re.captures(line.trim_end()) else {
+            continue;
+        };
+        let name = cap[1].to_string();
+        if name == "result" || params.iter().any(|p| p.name == name) {
+            continue;
+        }
+        if let Ok(value) = cap[2].parse::<f64>() {
+            params.push(CodeParameter {
+                name,
+                value,
+                line: idx + 1,
+            });
+        }
+    }
+    params
+}
+
+/// Extract top-level assignments whose right-hand side is plain arithmetic
+/// (`+ - * /`, parentheses, numbers) over `params` or earlier derived values.
+///
+/// Anything else on the right (calls, attributes, unknown names) is skipped.
+pub fn extract_derived_parameters(code: &str, params: &[CodeParameter]) -> Vec<DerivedParameter> {
+    let assign_re = Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)\s*=\s*([^#=]+?)\s*(?:#.*)?$").unwrap();
+    let mut values: HashMap<String, f64> =
+        params.iter().map(|p| (p.name.clone(), p.value)).collect();
+    let mut derived: Vec<DerivedParameter> = Vec::new();
+    for (idx, line) in code.lines().enumerate() {
+        let Some(cap) = assign_re.captures(line.trim_end()) else {
+            continue;
+        };
+        let name = cap[1].to_string();
+        if name == "result" || values.contains_key(&name) {
+            continue;
+        }
+        let expression = cap[2].to_string();
+        let Ok(tokens) = tokenize(&expression) else {
+            continue;
+        };
+        let mut depends_on: Vec<String> = Vec::new();
+        for token in tokens {
+            if let Token::Ident(ident) = token {
+                if !depends_on.contains(&ident) {
+                    depends_on.push(ident);
+                }
+            }
+        }
+        if depends_on.is_empty() {
+            continue;
+        }
+        // Unknown names and syntax the evaluator cannot handle fail here.
+        let Ok(value) = evaluate_expression(&expression, &values) else {
+            continue;
+        };
+        values.insert(name.clone(), value);
+        derived.push(DerivedParameter {
+            name,
+            expression,
+            depends_on,
+            line: idx + 1,
+        });
+    }
+    derived
+}
+
+/// Evaluate an arithmetic expression (`+ - * /`, parentheses, numbers) with
+/// `values` bound to its identifiers.
+pub fn evaluate_expression(expression: &str, values: &HashMap<String, f64>) -> Result<f64, String> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser::new(&tokens, values);
+    let value = parser.expr()?;
+    if parser.pos != tokens.len() {
+        return Err(format!("Unexpected input in '{}'", expression));
+    }
+    Ok(value)
+}
+
+/// Recompute every derived parameter, in order, from the given parameter values.
+/// Returns each derived name with its new value.
+pub fn evaluate_derived_parameters(
+    derived: &[DerivedParameter],
+    params: &HashMap<String, f64>,
+) -> Result<Vec<(String, f64)>, String> {
+    let mut values = params.clone();
+    let mut out = Vec::with_capacity(derived.len());
+    for d in derived {
+        let value = evaluate_expression(&d.expression, &values)
+            .map_err(|e| format!("{}: {}", d.name, e))?;
+        values.insert(d.name.clone(), value);
+        out.push((d.name.clone(), value));
+    }
+    Ok(out)
+}
+
+/// Primitives whose numeric arguments are reported by `extract_dimensions`.
+const DIMENSION_PRIMITIVES: &str = "Box|Cylinder|Sphere|Cone|Torus|Wedge";
+
+/// Most entries `extract_dimensions` returns, so a long script stays a short hint.
+const MAX_DIMENSIONS: usize = 12;
+
+/// Dimensions a script actually builds with, for prompting sibling parts:
+/// top-level parameters (derived ones evaluated), then primitive calls whose
+/// arguments resolve to numbers, e.g. `wall = 2.5` and `Box(40, 20, 4)`.
+///
+/// Keyword arguments that are not plain arithmetic (`align=(...)`) are left
+/// out; a call with no numeric argument is skipped.
+pub fn extract_dimensions(code: &str) -> Vec<String> {
+    let round = |v: f64| (v * 1000.0).round() / 1000.0;
+    let params = extract_parameters(code);
+    let mut values: HashMap<String, f64> =
+        params.iter().map(|p| (p.name.clone(), p.value)).collect();
+    let mut dims: Vec<String> = params
+        .iter()
+        .map(|p| format!("{} = {}", p.name, round(p.value)))
+        .collect();
+    let derived = extract_derived_parameters(code, &params);
+    if let Ok(evaluated) = evaluate_derived_parameters(&derived, &values) {
+        for (name, value) in evaluated {
+            dims.push(format!("{} = {}", name, round(value)));
+            values.insert(name, value);
+        }
+    }
+
+    // Arguments up to the first nested parenthesis; a keyword whose value
+    // starts one (e.g. `align=(...)`) is left empty and dropped below.
+    let call_re = Regex::new(&format!(r"\b({})\(([^()]*)", DIMENSION_PRIMITIVES)).unwrap();
+    for cap in call_re.captures_iter(code) {
+        let mut resolved = 0;
+        let mut args: Vec<String> = Vec::new();
+        for arg in cap[2].split(',') {
+            let (keyword, expression) = match arg.split_once('=') {
+                Some((k, v)) => (Some(k.trim()), v.trim()),
+                None => (None, arg.trim()),
+            };
+            match (keyword, evaluate_expression(expression, &values)) {
+                (Some(k), Ok(value)) => args.push(format!("{}={}", k, round(value))),
+                (None, Ok(value)) => args.push(round(value).to_string()),
+                // Unresolved positional arguments stay as written, so the
+                // others keep their position.
+                (None, Err(_)) if !expression.is_empty() => {
+                    args.push(expression.to_string());
+                    continue;
+                }
+                _ => continue,
+            }
+            resolved += 1;
+        }
+        if resolved == 0 {
+            continue;
+        }
+        let call = format!("{}({})", &cap[1], args.join(", "));
+        if !dims.contains(&call) {
+            dims.push(call);
+        }
+    }
+    dims.truncate(MAX_DIMENSIONS);
+    dims
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid number '{}'", text))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Op(c));
+            i += 1;
+        } else {
+            return Err(format!("Unsupported character '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive-descent evaluator over `tokenize` output.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    values: &'a HashMap<String, f64>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(tokens: &'a [Token], values: &'a HashMap<String, f64>) -> Self {
+        Self {
+            tokens,
+            values,
+            pos: 0,
+        }
+    }
+
+    fn peek_op(&self) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek_op() {
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.factor()?;
+        while let Some(op @ ('*' | '/')) = self.peek_op() {
+            self.pos += 1;
+            let rhs = self.factor()?;
+            value = if op == '*' {
+                value * rhs
+            } else if rhs == 0.0 {
+                return Err("Division by zero".to_string());
+            } else {
+                value / rhs
+            };
+        }
+        Ok(value)
+    }
+
+    fn factor(&mut self) -> Result<f64, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| "Unexpected end of expression".to_string())?;
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(value),
+            Token::Ident(name) => self
+                .values
+                .get(&name)
+                .copied()
+                .ok_or_else(|| format!("Unknown name '{}'", name)),
+            Token::Op('-') => Ok(-self.factor()?),
+            Token::Op('+') => self.factor(),
+            Token::Op('(') => {
+                let value = self.expr()?;
+                if self.peek_op() != Some(')') {
+                    return Err("Missing ')'".to_string());
+                }
+                self.pos += 1;
+                Ok(value)
+            }
+            Token::Op(c) => Err(format!("Unexpected '{}'", c)),
+        }
+    }
+}
+
+fn has_result_assignment(code: &str) -> bool {
+    let result_re = Regex::new(r"(?m)^result\s*=").unwrap();
+    result_re.is_match(code)
+}
+
+/// Find the last top-level variable that holds geometry.
+///
+/// Builder contexts (`with BuildPart() as bp:`) resolve to `bp.part`.
+fn last_geometry_variable(code: &str) -> Option<String> {
+    let assign_re = Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)\s*=\s*(.+)$").unwrap();
+    let builder_re = Regex::new(r"^with\s+BuildPart\(.*\)\s+as\s+([A-Za-z_][A-Za-z0-9_]*)\s*:").unwrap();
+
+    let mut geometry_vars: Vec<String> = Vec::new();
+    let mut last: Option<String> = None;
+    for line in code.lines() {
+        let line = line.trim_end();
+        if let Some(cap) = builder_re.captures(line) {
+            last = Some(format!("{}.part", &cap[1]));
+            continue;
+        }
+        let Some(cap) = assign_re.captures(line) else {
+            continue;
+        };
+        let name = cap[1].to_string();
+        let rhs = &cap[2];
+        let is_geometry = GEOMETRY_MARKERS.iter().any(|m| rhs.contains(m))
+            || geometry_vars.iter().any(|v| {
+                rhs.starts_with(&format!("{}.", v))
+                    || rhs.starts_with(&format!("{} ", v))
+                    || rhs.contains(&format!("{}.part", v))
+            });
+        if is_geometry {
+            if !geometry_vars.contains(&name) {
+                geometry_vars.push(name.clone());
+            }
+            last = Some(name);
+        }
+    }
+    last
+}
+
+/// Make sure imported code assigns its final geometry to `result`.
+///
+/// When the script never assigns `result`, bind the last geometry-valued
+/// top-level variable to it and report a warning instead of failing.
+pub fn adapt_result_assignment(code: &str) -> AdaptedCode {
+    if has_result_assignment(code) {
+        return AdaptedCode {
+            code: code.to_string(),
+            result_from: None,
+            warning: None,
+        };
+    }
+
+    match last_geometry_variable(code) {
+        Some(var) => {
+            let mut adapted = code.trim_end().to_string();
+            adapted.push_str(&format!("\n\nresult = {}\n", var));
+            AdaptedCode {
+                code: adapted,
+                warning: Some(format!(
+                    "Script does not assign `result`; appended `result = {}`.",
+                    var
+                )),
+                result_from: Some(var),
+            }
+        }
+        None => AdaptedCode {
+            code: code.to_string(),
+            result_from: None,
+            warning: Some(
+                "Script does not assign `result` and no geometry variable was found; add `result = <shape>` to preview it."
+                    .to_string(),
+            ),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_parameters_top_level_numbers_only() {
+        let code = "import cadquery as cq\n\
+                    width = 40\n\
+                    height = 12.5  # mm\n\
+                    offset = -3\n\
+                    label = \"box\"\n\
+                    def f():\n    inner = 5\n\
+                    width = 99\n\
+                    result = cq.Workplane().box(width, width, height)\n";
+        let params = extract_parameters(code);
+        let names: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["width", "height", "offset"]);
+        assert_eq!(params[0].value, 40.0);
+        assert_eq!(params[1].line, 3);
+        assert_eq!(params[2].value, -3.0);
+    }
+
+    #[test]
+    fn test_adapter_keeps_existing_result() {
+        let code = "from build123d import *\nresult = Box(1, 2, 3)\n";
+        let adapted = adapt_result_assignment(code);
+        assert_eq!(adapted.code, code);
+        assert!(adapted.warning.is_none());
+    }
+
+    #[test]
+    fn test_adapter_binds_last_workplane_variable() {
+        let code = "import cadquery as cq\n\
+                    base = cq.Workplane(\"XY\").box(10, 10, 2)\n\
+                    count = 4\n\
+                    shell = base.faces(\">Z\").shell(-1)\n\
+                    show_object(shell)\n";
+        let adapted = adapt_result_assignment(code);
+        assert_eq!(adapted.result_from.as_deref(), Some("shell"));
+        assert!(adapted.code.ends_with("result = shell\n"));
+        assert!(adapted.warning.unwrap().contains("result = shell"));
+    }
+
+    #[test]
+    fn test_adapter_uses_builder_part() {
+        let code = "from build123d import *\nwith BuildPart() as bracket:\n    Box(10, 5, 2)\n";
+        let adapted = adapt_result_assignment(code);
+        assert_eq!(adapted.result_from.as_deref(), Some("bracket.part"));
+    }
+
+    #[test]
+    fn test_adapter_without_geometry_warns_but_keeps_code() {
+        let code = "width = 5\nprint(width)\n";
+        let adapted = adapt_result_assignment(code);
+        assert_eq!(adapted.code, code);
+        assert!(adapted.result_from.is_none());
+        assert!(adapted.warning.is_some());
+    }
+
+    #[test]
+    fn test_derived_parameter_dependencies_and_evaluation() {
+        let code = "outer = 40\n\
+                    wall = 2\n\
+                    inner = outer - 2*wall  # cavity\n\
+                    half = inner / 2\n\
+                    shape = Box(outer, outer, wall)\n\
+                    other = missing + 1\n";
+        let params = extract_parameters(code);
+        let derived = extract_derived_parameters(code, &params);
+        let names: Vec<&str> = derived.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["inner", "half"]);
+        assert_eq!(derived[0].expression, "outer - 2*wall");
+        assert_eq!(derived[0].depends_on, vec!["outer", "wall"]);
+        assert_eq!(derived[0].line, 3);
+        assert_eq!(derived[1].depends_on, vec!["inner"]);
+
+        let inputs: HashMap<String, f64> =
+            [("outer".to_string(), 50.0), ("wall".to_string(), 3.0)].into();
+        assert_eq!(evaluate_expression("outer - 2*wall", &inputs), Ok(44.0));
+        let values = evaluate_derived_parameters(&derived, &inputs).unwrap();
+        assert_eq!(
+            values,
+            vec![("inner".to_string(), 44.0), ("half".to_string(), 22.0)]
+        );
+    }
+
+    #[test]
+    fn test_extract_dimensions_resolves_parameters_and_primitives() {
+        let code = "from build123d import *\n\
+                    width = 40\n\
+                    wall = 2.5\n\
+                    inner = width - 2 * wall\n\
+                    shell = Box(width, 20, 4, align=(Align.CENTER, Align.CENTER, Align.MIN))\n\
+                    bore = Cylinder(radius=inner / 10, height=4)\n\
+                    pin = Cylinder(pin_radius, 8)\n\
+                    result = shell - bore\n";
+        assert_eq!(
+            extract_dimensions(code),
+            vec![
+                "width = 40",
+                "wall = 2.5",
+                "inner = 35",
+                "Box(40, 20, 4)",
+                "Cylinder(radius=3.5, height=4)",
+                "Cylinder(pin_radius, 8)",
+            ]
+        );
+        assert!(extract_dimensions("result = Sphere(r)").is_empty());
+    }
+
+    #[test]
+    fn test_evaluator_precedence_and_errors() {
+        let values: HashMap<String, f64> = [("a".to_string(), 4.0)].into();
+        assert_eq!(evaluate_expression("-(a + 2) * 3 / 2", &values), Ok(-9.0));
+        assert!(evaluate_expression("a / 0", &values).is_err());
+        assert!(evaluate_expression("a ** 2", &values).is_err());
+        assert!(evaluate_expression("b + 1", &values).is_err());
+        assert!(evaluate_expression("max(a, 1)", &values).is_err());
+    }
+}
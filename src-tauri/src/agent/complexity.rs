@@ -0,0 +1,226 @@
+use regex::Regex;
+
+use crate::agent::features;
+
+/// Largest repeat count taken from a single plan step.
+const MAX_STEP_MULTIPLIER: u32 = 1000;
+/// A step expanded as a pattern loop: one instance and one looped boolean.
+const PATTERN_STEP_OPERATIONS: u32 = 2;
+
+/// Build123d constructors and operations that each add one geometric operation.
+const CODE_OPERATIONS: &[&str] = &[
+    "Box",
+    "Cylinder",
+    "Sphere",
+    "Cone",
+    "Torus",
+    "Wedge",
+    "Circle",
+    "Ellipse",
+    "Rectangle",
+    "RectangleRounded",
+    "Polygon",
+    "RegularPolygon",
+    "Polyline",
+    "SlotOverall",
+    "SlotCenterToCenter",
+    "Text",
+    "Hole",
+    "CounterBoreHole",
+    "CounterSinkHole",
+    "extrude",
+    "revolve",
+    "loft",
+    "sweep",
+    "fillet",
+    "chamfer",
+    "offset",
+    "shell",
+    "mirror",
+    "split",
+    "make_face",
+    "cut",
+    "fuse",
+    "union",
+    "intersect",
+];
+
+/// Where a count stands against the soft and hard operation budgets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetVerdict {
+    Within,
+    OverSoft,
+    OverHard,
+}
+
+/// Compare `count` against the budgets; a budget of 0 is disabled.
+pub fn assess_budget(count: u32, soft_budget: u32, hard_budget: u32) -> BudgetVerdict {
+    if hard_budget > 0 && count > hard_budget {
+        BudgetVerdict::OverHard
+    } else if soft_budget > 0 && count > soft_budget {
+        BudgetVerdict::OverSoft
+    } else {
+        BudgetVerdict::Within
+    }
+}
+
+/// Warning for a count above the soft budget. `what` names the counted thing
+/// ("Plan" or "Script").
+pub fn soft_budget_warning(what: &str, count: u32, soft_budget: u32) -> String {
+    format!(
+        "{} needs ~{} geometric operations, above the soft budget of {}; expect slower \
+         and less reliable generation.",
+        what, count, soft_budget
+    )
+}
+
+/// Re-plan instruction for a count above the hard budget.
+pub fn simplification_instruction(what: &str, count: u32, hard_budget: u32) -> String {
+    format!(
+        "{} needs ~{} geometric operations, over the hard budget of {}. Simplify: build \
+         repeated features with a for-loop over one helper function, model a few \
+         representative instances instead of every copy, and drop cosmetic detail.",
+        what, count, hard_budget
+    )
+}
+
+/// Repeat count named in one plan step ("×40", "array of 12", "24 holes"),
+/// or 1 when the step builds a single feature.
+fn step_multiplier(step: &str) -> u32 {
+    let lower = step.to_lowercase();
+    let mut best = 1u32;
+    let mut take = |n: u32| best = best.max(n.min(MAX_STEP_MULTIPLIER));
+
+    // "×40" / "x 40", but not a dimension product like "40 x 40 mm".
+    let times_re = Regex::new(
+        r"(\d+(?:\.\d+)?\s*(?:mm|cm|in)?\s*)?(?:×|\bx)\s*(\d+)\b(\s*(?:mm|cm|in\b|×|x\b))?",
+    )
+    .unwrap();
+    for cap in times_re.captures_iter(&lower) {
+        if cap.get(1).is_none() && cap.get(3).is_none() {
+            if let Ok(n) = cap[2].parse() {
+                take(n);
+            }
+        }
+    }
+
+    let pattern_re =
+        Regex::new(r"\b(?:array|pattern|ring|row|set|series|grid) of (\d+)\b").unwrap();
+    for cap in pattern_re.captures_iter(&lower) {
+        if let Ok(n) = cap[1].parse() {
+            take(n);
+        }
+    }
+
+    let copies_re = Regex::new(
+        r"\b(\d+)\s+(?:(?:evenly|equally)[- ]spaced\s+|identical\s+|radial\s+|parallel\s+)?(?:copies|instances|times|holes|slots|ribs|fins|pins|bosses|spokes|teeth|vents|studs|posts|legs|bolts|screws|cutouts|pockets|grooves|bars|rods|tiles|keys|buttons|louvers|perforations)\b",
+    )
+    .unwrap();
+    for cap in copies_re.captures_iter(&lower) {
+        if let Ok(n) = cap[1].parse() {
+            take(n);
+        }
+    }
+
+    best
+}
+
+/// Estimated geometric operations for a plan's build steps: one per step,
+/// multiplied by any repeat count the step names. A step naming a grid,
+/// circular or linear pattern is built with a pattern loop, so it costs
+/// `PATTERN_STEP_OPERATIONS` whatever its count.
+pub fn estimate_plan_operations(steps: &[String]) -> u32 {
+    steps
+        .iter()
+        .map(|step| {
+            if features::detect_patterns(step).is_empty() {
+                step_multiplier(step)
+            } else {
+                PATTERN_STEP_OPERATIONS
+            }
+        })
+        .fold(0u32, |acc, n| acc.saturating_add(n))
+}
+
+/// Geometric operations written in a script: constructor, operation, and
+/// boolean calls, including each link of a method chain, plus in-place
+/// booleans (`part -= hole`). Comments are ignored; a call inside a loop
+/// counts once, which is what keeps looped helpers cheap.
+pub fn count_code_operations(code: &str) -> u32 {
+    let names = CODE_OPERATIONS.join("|");
+    let call_re = Regex::new(&format!(r"(?:^|[^\w])(?:{})\s*\(", names)).unwrap();
+    let in_place_re = Regex::new(r"[\w\)\]]\s*(?:\+|-|&)=\s*[A-Za-z_]").unwrap();
+
+    let mut count = 0u32;
+    for line in code.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        // Overlapping boundaries ("Box(...).cut(") need a per-call scan.
+        let mut rest = line;
+        while let Some(m) = call_re.find(rest) {
+            count += 1;
+            rest = &rest[m.end()..];
+        }
+        count += in_place_re.find_iter(line).count() as u32;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_estimate_multiplies_repeated_steps() {
+        let plan = steps(&[
+            "Create a 120 x 80 x 6 mm base plate",
+            "Cut a rectangular array of 12 mounting holes",
+            "Add cooling fins ×40 along the top face",
+            "Fillet the outer edges",
+        ]);
+        assert_eq!(estimate_plan_operations(&plan), 1 + 12 + 40 + 1);
+    }
+
+    #[test]
+    fn test_estimate_handles_patterns_and_copies() {
+        let plan = steps(&["Cut a grid of 4x6 vent slots", "Place 8 evenly spaced pins"]);
+        assert_eq!(estimate_plan_operations(&plan), PATTERN_STEP_OPERATIONS + 8);
+        let plan = steps(&[
+            "Drill 8 holes equally spaced on a 60mm circle",
+            "Cut a row of 10 slots at 6mm pitch",
+        ]);
+        assert_eq!(estimate_plan_operations(&plan), 2 * PATTERN_STEP_OPERATIONS);
+        assert_eq!(
+            estimate_plan_operations(&steps(&["Box 40 x 40 x 10 mm"])),
+            1
+        );
+    }
+
+    #[test]
+    fn test_count_code_operations_counts_long_chains() {
+        let code = r#"from build123d import *
+
+# Box(1, 1, 1) in a comment does not count
+base = Box(100, 60, 8).fillet(2).cut(Cylinder(3, 8)).cut(Cylinder(3, 8)).cut(Cylinder(3, 8))
+lid = extrude(Rectangle(100, 60), amount=2)
+for i in range(40):
+    base -= Pos(i * 2, 0, 0) * Box(1, 60, 2)
+result = base.fuse(lid)
+"#;
+        // Chain: Box, fillet, 3x cut, 3x Cylinder; lid: extrude, Rectangle;
+        // loop body: -=, Box; final fuse.
+        assert_eq!(count_code_operations(code), 8 + 2 + 2 + 1);
+    }
+
+    #[test]
+    fn test_assess_budget_levels() {
+        assert_eq!(assess_budget(10, 22, 60), BudgetVerdict::Within);
+        assert_eq!(assess_budget(30, 22, 60), BudgetVerdict::OverSoft);
+        assert_eq!(assess_budget(61, 22, 60), BudgetVerdict::OverHard);
+        assert_eq!(assess_budget(500, 0, 0), BudgetVerdict::Within);
+        assert!(simplification_instruction("Plan", 61, 60).contains("for-loop"));
+    }
+}
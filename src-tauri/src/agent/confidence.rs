@@ -1,10 +1,11 @@
 use serde::Serialize;
+use schemars::JsonSchema;
 
 use crate::agent::design::{self, PlanValidation};
 use crate::agent::rules::{CookbookEntry, DesignPatternEntry};
 use crate::config::GenerationReliabilityProfile;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ConfidenceLevel {
     High,
@@ -178,6 +179,116 @@ pub fn assess_confidence_with_profile(
     }
 }
 
+/// Whether a plan that passed validation should get one more planning round
+/// because its confidence is still Low.
+pub fn should_replan_for_confidence(
+    validation: &PlanValidation,
+    assessment: &ConfidenceAssessment,
+    enabled: bool,
+) -> bool {
+    enabled && validation.is_valid && assessment.level == ConfidenceLevel::Low
+}
+
+/// Keep a re-planned result only if it is still valid and scores higher.
+pub fn replan_is_better(
+    original: &ConfidenceAssessment,
+    replanned_validation: &PlanValidation,
+    replanned: &ConfidenceAssessment,
+) -> bool {
+    replanned_validation.is_valid && replanned.score > original.score
+}
+
+/// Result of the gates that let a design plan skip manual approval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct AutoApprovalDecision {
+    pub eligible: bool,
+    pub risk_score: u32,
+    pub max_risk_score: u32,
+    pub confidence_level: ConfidenceLevel,
+    /// The first gate that failed, when not eligible.
+    pub blocked_by: Option<String>,
+}
+
+impl AutoApprovalDecision {
+    /// `PlanStatus` text naming the gating values.
+    pub fn describe(&self) -> String {
+        let level = match self.confidence_level {
+            ConfidenceLevel::High => "high",
+            ConfidenceLevel::Medium => "medium",
+            ConfidenceLevel::Low => "low",
+        };
+        match &self.blocked_by {
+            None => format!(
+                "Plan auto-approved (risk {}/10 <= {}, confidence {}); generating code...",
+                self.risk_score, self.max_risk_score, level
+            ),
+            Some(reason) => format!("Plan needs manual approval: {}", reason),
+        }
+    }
+}
+
+/// A plan is auto-approved only when it is valid, its risk score is at most
+/// `max_risk_score`, confidence is High and no clarification was requested.
+pub fn auto_approval_decision(
+    validation_is_valid: bool,
+    risk_score: u32,
+    level: ConfidenceLevel,
+    needs_clarification: bool,
+    max_risk_score: u32,
+) -> AutoApprovalDecision {
+    let blocked_by = if !validation_is_valid {
+        Some("plan failed validation".to_string())
+    } else if risk_score > max_risk_score {
+        Some(format!(
+            "risk score {}/10 is above the auto-approval limit {}",
+            risk_score, max_risk_score
+        ))
+    } else if level != ConfidenceLevel::High {
+        Some("confidence is not high".to_string())
+    } else if needs_clarification {
+        Some("clarification questions were raised".to_string())
+    } else {
+        None
+    };
+    AutoApprovalDecision {
+        eligible: blocked_by.is_none(),
+        risk_score,
+        max_risk_score,
+        confidence_level: level,
+        blocked_by,
+    }
+}
+
+/// Re-plan feedback for a valid plan with Low confidence, formatted like
+/// `design::build_rejection_feedback`.
+pub fn build_low_confidence_feedback(assessment: &ConfidenceAssessment) -> String {
+    let mut feedback = format!(
+        "## Plan Confidence Feedback\nYour previous plan passed validation but scored low confidence ({}/100).\n\n",
+        assessment.score
+    );
+
+    feedback.push_str(&format!("**Primary concern:** {}\n\n", assessment.message));
+
+    if !assessment.warnings.is_empty() {
+        feedback.push_str("**All warnings:**\n");
+        for w in &assessment.warnings {
+            feedback.push_str(&format!("- {}\n", w));
+        }
+        feedback.push('\n');
+    }
+
+    feedback.push_str(
+        "**Instructions for revision:**\n\
+         - Prefer fewer, simpler operations; avoid combining loft and shell\n\
+         - Describe shapes only — do NOT name operations (no 'shell', 'loft', 'revolve', 'sweep')\n\
+         - Keep the same object and dimensions unless they caused the warnings\n\
+         - Output EXACTLY these headings: `### Object Analysis`, `### Geometry Breakdown`, `### Build Plan`, `### Approximation Notes`\n\
+         - Include numbered steps in Build Plan (`1.`, `2.`, `3.`)\n"
+    );
+
+    feedback
+}
+
 /// Match plan operations against cookbook recipes.
 ///
 /// For each cookbook entry, extract operations from title + description,
@@ -611,4 +722,70 @@ mod tests {
             result_no_pattern.score
         );
     }
+
+    #[test]
+    fn test_replan_only_for_valid_low_confidence_when_enabled() {
+        let low_valid = make_validation(7, vec!["loft", "shell", "cut", "union", "fuse"]);
+        let low = assess_confidence(&low_valid, None, None);
+        assert!(low_valid.is_valid);
+        assert_eq!(low.level, ConfidenceLevel::Low);
+        assert!(should_replan_for_confidence(&low_valid, &low, true));
+        assert!(!should_replan_for_confidence(&low_valid, &low, false));
+
+        let invalid = make_validation(9, vec!["loft", "shell"]);
+        let invalid_conf = assess_confidence(&invalid, None, None);
+        assert!(!should_replan_for_confidence(&invalid, &invalid_conf, true));
+
+        let simple = make_validation(0, vec!["extrude"]);
+        let high = assess_confidence(&simple, None, None);
+        assert!(!should_replan_for_confidence(&simple, &high, true));
+
+        let feedback = build_low_confidence_feedback(&low);
+        assert!(feedback.contains(&format!("({}/100)", low.score)));
+        assert!(feedback.contains("Novel operation combination"));
+    }
+
+    #[test]
+    fn test_replan_kept_only_when_valid_and_more_confident() {
+        let original_validation = make_validation(7, vec!["loft", "shell", "cut", "union", "fuse"]);
+        let original = assess_confidence(&original_validation, None, None);
+
+        let better_validation = make_validation(2, vec!["extrude", "fillet"]);
+        let better = assess_confidence(&better_validation, None, None);
+        assert!(replan_is_better(&original, &better_validation, &better));
+
+        let worse_validation =
+            make_validation(7, vec!["loft", "shell", "cut", "union", "fuse", "sweep"]);
+        let worse = assess_confidence(&worse_validation, None, None);
+        assert!(!replan_is_better(&original, &worse_validation, &worse));
+
+        let mut invalid_validation = make_validation(2, vec!["extrude"]);
+        invalid_validation.is_valid = false;
+        let invalid = assess_confidence(&invalid_validation, None, None);
+        assert!(!replan_is_better(&original, &invalid_validation, &invalid));
+    }
+
+    #[test]
+    fn test_auto_approval_gates() {
+        let approved = auto_approval_decision(true, 2, ConfidenceLevel::High, false, 3);
+        assert!(approved.eligible);
+        assert!(approved.describe().contains("risk 2/10 <= 3, confidence high"));
+
+        let high_risk = auto_approval_decision(true, 5, ConfidenceLevel::High, false, 3);
+        assert!(!high_risk.eligible);
+        assert!(high_risk.blocked_by.unwrap().contains("risk score 5/10"));
+
+        let clarification = auto_approval_decision(true, 1, ConfidenceLevel::High, true, 3);
+        assert!(!clarification.eligible);
+        assert!(clarification.blocked_by.unwrap().contains("clarification"));
+
+        for level in [ConfidenceLevel::Medium, ConfidenceLevel::Low] {
+            let low = auto_approval_decision(true, 1, level, false, 3);
+            assert!(!low.eligible);
+            assert_eq!(low.blocked_by.as_deref(), Some("confidence is not high"));
+        }
+
+        let invalid = auto_approval_decision(false, 0, ConfidenceLevel::High, false, 3);
+        assert!(!invalid.eligible);
+    }
 }
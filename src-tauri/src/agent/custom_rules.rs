@@ -0,0 +1,390 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use serde::Serialize;
+use serde_yaml::Value;
+
+use crate::agent::rules::AgentRules;
+use crate::error::AppError;
+
+/// A file that contributed to the effective rules, in merge order.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RuleSource {
+    /// File path, or `preset:<name>` for the bundled preset.
+    pub path: String,
+    /// "preset" or "user".
+    pub kind: String,
+    /// Top-level sections the file sets.
+    pub sections: Vec<String>,
+    /// Why the file was skipped, if it was.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MergedRules {
+    pub rules: AgentRules,
+    pub sources: Vec<RuleSource>,
+}
+
+impl MergedRules {
+    pub fn warnings(&self) -> Vec<String> {
+        self.sources
+            .iter()
+            .filter_map(|s| {
+                s.error
+                    .as_ref()
+                    .map(|e| format!("Ignoring custom rules file {}: {}", s.path, e))
+            })
+            .collect()
+    }
+}
+
+/// Merge `overlay` into `base`. Mappings merge key by key; any other value
+/// (scalars, sequences) from `overlay` replaces the one in `base`.
+fn deep_merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base_map), Value::Mapping(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn top_level_sections(value: &Value) -> Vec<String> {
+    value
+        .as_mapping()
+        .map(|m| {
+            m.keys()
+                .filter_map(|k| k.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Merge user rule files over a preset, in the given order (later files
+/// win). A file that is not valid YAML, or that would make the rules fail to
+/// deserialize, is skipped and reported in its `RuleSource::error`.
+pub fn merge_rule_files(
+    preset_name: &str,
+    preset_yaml: &str,
+    files: &[(PathBuf, String)],
+) -> Result<MergedRules, AppError> {
+    let mut merged: Value = serde_yaml::from_str(preset_yaml)
+        .map_err(|e| AppError::ConfigError(format!("Failed to parse agent rules: {}", e)))?;
+    let mut rules: AgentRules = serde_yaml::from_value(merged.clone())
+        .map_err(|e| AppError::ConfigError(format!("Failed to parse agent rules: {}", e)))?;
+    let mut sources = vec![RuleSource {
+        path: format!("preset:{}", preset_name),
+        kind: "preset".to_string(),
+        sections: top_level_sections(&merged),
+        error: None,
+    }];
+
+    for (path, contents) in files {
+        let mut source = RuleSource {
+            path: path.display().to_string(),
+            kind: "user".to_string(),
+            sections: Vec::new(),
+            error: None,
+        };
+        match serde_yaml::from_str::<Value>(contents) {
+            Err(e) => source.error = Some(e.to_string()),
+            Ok(Value::Null) => {}
+            Ok(value @ Value::Mapping(_)) => {
+                source.sections = top_level_sections(&value);
+                let mut candidate = merged.clone();
+                deep_merge(&mut candidate, value);
+                match serde_yaml::from_value::<AgentRules>(candidate.clone()) {
+                    Ok(candidate_rules) => {
+                        merged = candidate;
+                        rules = candidate_rules;
+                    }
+                    Err(e) => source.error = Some(e.to_string()),
+                }
+            }
+            Ok(_) => source.error = Some("top level must be a mapping of sections".to_string()),
+        }
+        sources.push(source);
+    }
+
+    Ok(MergedRules { rules, sources })
+}
+
+/// YAML files in `dir`, ordered by file name.
+fn rule_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.is_file()
+                        && p.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+                            e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml")
+                        })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    files
+}
+
+type DirStamp = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+fn dir_stamp(files: &[PathBuf]) -> DirStamp {
+    files
+        .iter()
+        .map(|p| {
+            let meta = std::fs::metadata(p).ok();
+            (
+                p.clone(),
+                meta.as_ref().and_then(|m| m.modified().ok()),
+                meta.map(|m| m.len()).unwrap_or(0),
+            )
+        })
+        .collect()
+}
+
+/// Watches `custom_rules_dir` and caches the merged rules per preset. Any
+/// added, removed or modified file (by mtime and size) drops the cache, so
+/// the next generation sees edits without a restart.
+#[derive(Debug, Default)]
+pub struct RuleDirWatch {
+    dir: Option<PathBuf>,
+    stamp: DirStamp,
+    files: Vec<(PathBuf, String)>,
+    merged: HashMap<String, MergedRules>,
+    reported: HashSet<String>,
+}
+
+impl RuleDirWatch {
+    pub fn set_dir(&mut self, dir: Option<PathBuf>) {
+        if self.dir != dir {
+            *self = Self {
+                dir,
+                ..Self::default()
+            };
+        }
+    }
+
+    fn refresh(&mut self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let stamp = dir_stamp(&rule_files(dir));
+        if stamp == self.stamp {
+            return;
+        }
+        self.files = stamp
+            .iter()
+            .filter_map(|(path, _, _)| {
+                std::fs::read_to_string(path)
+                    .ok()
+                    .map(|contents| (path.clone(), contents))
+            })
+            .collect();
+        self.stamp = stamp;
+        self.merged.clear();
+        self.reported.clear();
+    }
+
+    /// Preset merged with the user files, or `None` when there are none.
+    pub fn merged_for_preset(
+        &mut self,
+        preset_name: &str,
+        preset_yaml: &str,
+    ) -> Option<Result<MergedRules, AppError>> {
+        self.refresh();
+        if self.files.is_empty() {
+            return None;
+        }
+        if let Some(merged) = self.merged.get(preset_name) {
+            return Some(Ok(merged.clone()));
+        }
+        let result = merge_rule_files(preset_name, preset_yaml, &self.files);
+        if let Ok(merged) = &result {
+            self.merged.insert(preset_name.to_string(), merged.clone());
+        }
+        Some(result)
+    }
+
+    /// Warnings for skipped files not reported since they last changed.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        let mut warnings: Vec<String> = self
+            .merged
+            .values()
+            .flat_map(|m| m.warnings())
+            .filter(|w| !self.reported.contains(w))
+            .collect();
+        warnings.sort();
+        warnings.dedup();
+        self.reported.extend(warnings.iter().cloned());
+        warnings
+    }
+}
+
+static WATCH: OnceLock<Mutex<RuleDirWatch>> = OnceLock::new();
+
+fn watch() -> &'static Mutex<RuleDirWatch> {
+    WATCH.get_or_init(|| Mutex::new(RuleDirWatch::default()))
+}
+
+/// Point the global watch at `custom_rules_dir` (empty or `None` disables it).
+pub fn set_dir(dir: Option<&str>) {
+    let dir = dir
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from);
+    if let Ok(mut w) = watch().lock() {
+        w.set_dir(dir);
+    }
+}
+
+/// Merged rules for `preset_name` from the global watch, if user files exist
+/// and merging succeeded.
+pub fn merged_for_preset(preset_name: &str, preset_yaml: &str) -> Option<AgentRules> {
+    let mut w = watch().lock().ok()?;
+    match w.merged_for_preset(preset_name, preset_yaml)? {
+        Ok(merged) => Some(merged.rules),
+        Err(_) => None,
+    }
+}
+
+pub fn take_warnings() -> Vec<String> {
+    watch()
+        .lock()
+        .map(|mut w| w.take_warnings())
+        .unwrap_or_default()
+}
+
+/// Files contributing to the rules for `preset_name`, preset first.
+pub fn list_sources(preset_name: &str, preset_yaml: &str) -> Result<Vec<RuleSource>, AppError> {
+    let mut w = watch()
+        .lock()
+        .map_err(|_| AppError::ConfigError("Custom rules lock poisoned".into()))?;
+    match w.merged_for_preset(preset_name, preset_yaml) {
+        Some(result) => result.map(|m| m.sources),
+        None => Ok(merge_rule_files(preset_name, preset_yaml, &[])?.sources),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESET: &str = "version: 1\n\
+failure_prevention:\n  general:\n    - preset rule\n  fillets:\n    - preset fillet rule\n";
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cadai-custom-rules-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn failure_prevention(rules: &AgentRules, key: &str) -> Vec<String> {
+        rules.failure_prevention.as_ref().unwrap()[key].clone()
+    }
+
+    #[test]
+    fn test_user_files_merge_over_preset_in_filename_order() {
+        let dir = temp_dir();
+        // Written out of order; merge order follows file names.
+        std::fs::write(
+            dir.join("20-b.yaml"),
+            "failure_prevention:\n  general:\n    - rule from b\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("10-a.yml"),
+            "failure_prevention:\n  general:\n    - rule from a\n  shop:\n    - min wall 2mm\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "not rules").unwrap();
+
+        let mut watch = RuleDirWatch::default();
+        watch.set_dir(Some(dir.clone()));
+        let merged = watch.merged_for_preset("default", PRESET).unwrap().unwrap();
+
+        assert_eq!(
+            failure_prevention(&merged.rules, "general"),
+            vec!["rule from b"]
+        );
+        assert_eq!(
+            failure_prevention(&merged.rules, "shop"),
+            vec!["min wall 2mm"]
+        );
+        assert_eq!(
+            failure_prevention(&merged.rules, "fillets"),
+            vec!["preset fillet rule"]
+        );
+
+        let paths: Vec<&str> = merged.sources.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(paths[0], "preset:default");
+        assert!(paths[1].ends_with("10-a.yml"));
+        assert!(paths[2].ends_with("20-b.yaml"));
+        assert_eq!(paths.len(), 3);
+        assert_eq!(merged.sources[1].sections, vec!["failure_prevention"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_syntax_error_mid_session_falls_back_and_recovers() {
+        let dir = temp_dir();
+        let file = dir.join("shop.yaml");
+        std::fs::write(&file, "failure_prevention:\n  general:\n    - shop rule\n").unwrap();
+
+        let mut watch = RuleDirWatch::default();
+        watch.set_dir(Some(dir.clone()));
+        let merged = watch.merged_for_preset("default", PRESET).unwrap().unwrap();
+        assert_eq!(
+            failure_prevention(&merged.rules, "general"),
+            vec!["shop rule"]
+        );
+        assert!(watch.take_warnings().is_empty());
+
+        // Broken edit: the preset applies alone and the file is named once.
+        std::fs::write(&file, "failure_prevention:\n  general: [unclosed\n").unwrap();
+        let merged = watch.merged_for_preset("default", PRESET).unwrap().unwrap();
+        assert_eq!(
+            failure_prevention(&merged.rules, "general"),
+            vec!["preset rule"]
+        );
+        let warnings = watch.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("shop.yaml"));
+        assert!(watch.take_warnings().is_empty());
+
+        // Fixed again: picked up on the next call without a restart.
+        std::fs::write(
+            &file,
+            "failure_prevention:\n  general:\n    - fixed shop rule\n",
+        )
+        .unwrap();
+        let merged = watch.merged_for_preset("default", PRESET).unwrap().unwrap();
+        assert_eq!(
+            failure_prevention(&merged.rules, "general"),
+            vec!["fixed shop rule"]
+        );
+        assert!(merged.sources.iter().all(|s| s.error.is_none()));
+
+        // A schema mismatch is also skipped rather than failing the preset.
+        std::fs::write(&file, "failure_prevention: 42\n").unwrap();
+        let merged = watch.merged_for_preset("default", PRESET).unwrap().unwrap();
+        assert_eq!(
+            failure_prevention(&merged.rules, "general"),
+            vec!["preset rule"]
+        );
+        assert_eq!(watch.take_warnings().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
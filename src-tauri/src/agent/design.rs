@@ -1,9 +1,11 @@
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::agent::rules::AgentRules;
+use crate::agent::{complexity, modify, numparse};
 use crate::ai::message::ChatMessage;
 use crate::ai::provider::{AiProvider, TokenUsage};
-use crate::config::GenerationReliabilityProfile;
+use crate::config::{AppConfig, GenerationReliabilityProfile};
 use crate::error::AppError;
 
 /// Result of the fast prompt triage that determines whether a user request
@@ -40,6 +42,8 @@ pub struct PlanRiskSignals {
     pub fatal_combo: bool,
     pub negation_conflict: bool,
     pub repair_sensitive_ops: Vec<String>,
+    /// Geometric operations the Build Plan steps are estimated to need.
+    pub estimated_operations: Option<u32>,
 }
 
 
@@ -97,26 +101,142 @@ What can't Build123d do perfectly? What's the closest buildable shape? Where sho
 // Manufacturing constraints formatting
 // ---------------------------------------------------------------------------
 
+/// Words too common to make a rule relevant to a request.
+const RELEVANCE_STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "from", "are", "use", "into", "not", "all",
+];
+
+/// Character budget and relevance context for one formatted rule section.
+#[derive(Debug, Clone, Default)]
+pub struct RuleBudget {
+    /// Characters of rule text the section may use; 0 is unlimited.
+    pub max_chars: usize,
+    /// Words of the request and its intent tags; rules mentioning more of
+    /// them are kept first when the budget runs out.
+    pub terms: std::collections::HashSet<String>,
+}
+
+impl RuleBudget {
+    pub fn new(max_chars: usize, request: &str, intent_tags: &[String]) -> Self {
+        let mut terms = relevance_words(request);
+        terms.extend(intent_tags.iter().map(|t| t.to_lowercase()));
+        Self { max_chars, terms }
+    }
+}
+
+/// Included and omitted rule counts of one budgeted prompt section.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleSectionStats {
+    pub section: String,
+    pub included: usize,
+    pub omitted: usize,
+}
+
+/// Intent tags implied by a manufacturing process id, e.g. `cnc_it7`.
+pub fn process_intent_tags(process: &str) -> Vec<String> {
+    let process = process.to_lowercase();
+    let tags: &[&str] = if process.starts_with("cnc") {
+        &["cnc", "milling", "machining", "machined", "mill"]
+    } else if process.starts_with("sla") {
+        &["sla", "resin", "printing", "print"]
+    } else if process.starts_with("fdm") {
+        &["fdm", "printing", "print", "printed", "layer"]
+    } else {
+        &[]
+    };
+    tags.iter().map(|t| t.to_string()).collect()
+}
+
+fn relevance_words(text: &str) -> std::collections::HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| w.len() >= 3 && !RELEVANCE_STOPWORDS.contains(w))
+        .map(str::to_string)
+        .collect()
+}
+
+fn relevance_score(text: &str, budget: &RuleBudget) -> usize {
+    relevance_words(text)
+        .iter()
+        .filter(|w| budget.terms.contains(*w))
+        .count()
+}
+
+/// Which entries fit the budget, given each entry's scoring text and cost in
+/// characters. Entries are taken by relevance (file order breaking ties); one
+/// that no longer fits is skipped so smaller, less relevant ones can still
+/// use the rest of the budget.
+fn select_rule_entries(entries: &[(String, usize)], budget: &RuleBudget) -> Vec<bool> {
+    if budget.max_chars == 0 {
+        return vec![true; entries.len()];
+    }
+    let mut order: Vec<(usize, usize)> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, (text, _))| (relevance_score(text, budget), i))
+        .collect();
+    order.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    let mut included = vec![false; entries.len()];
+    let mut used = 0;
+    for (_, i) in order {
+        if used + entries[i].1 > budget.max_chars {
+            continue;
+        }
+        used += entries[i].1;
+        included[i] = true;
+    }
+    included
+}
+
+fn omitted_note(omitted: usize) -> String {
+    format!(
+        "_{} additional rule{} omitted to fit the prompt budget._\n",
+        omitted,
+        if omitted == 1 { "" } else { "s" }
+    )
+}
+
+fn section_stats(section: &str, included: &[bool]) -> RuleSectionStats {
+    let kept = included.iter().filter(|i| **i).count();
+    RuleSectionStats {
+        section: section.to_string(),
+        included: kept,
+        omitted: included.len() - kept,
+    }
+}
+
+/// Format one `key: value` pair of a YAML mapping as a bullet.
+fn format_yaml_entry(
+    out: &mut String,
+    key: &serde_yaml::Value,
+    value: &serde_yaml::Value,
+    indent: usize,
+) {
+    let prefix = "  ".repeat(indent);
+    let key = match key {
+        serde_yaml::Value::String(s) => s.clone(),
+        other => format!("{:?}", other),
+    };
+    match value {
+        serde_yaml::Value::Mapping(_) | serde_yaml::Value::Sequence(_) => {
+            out.push_str(&format!("{}- **{}**:\n", prefix, key));
+            format_yaml_value(out, value, indent + 1);
+        }
+        _ => {
+            let val = format_yaml_scalar(value);
+            out.push_str(&format!("{}- **{}**: {}\n", prefix, key, val));
+        }
+    }
+}
+
 /// Recursively format a YAML value into indented bullet-point markdown.
 fn format_yaml_value(out: &mut String, value: &serde_yaml::Value, indent: usize) {
     let prefix = "  ".repeat(indent);
     match value {
         serde_yaml::Value::Mapping(map) => {
             for (k, v) in map {
-                let key = match k {
-                    serde_yaml::Value::String(s) => s.clone(),
-                    other => format!("{:?}", other),
-                };
-                match v {
-                    serde_yaml::Value::Mapping(_) | serde_yaml::Value::Sequence(_) => {
-                        out.push_str(&format!("{}- **{}**:\n", prefix, key));
-                        format_yaml_value(out, v, indent + 1);
-                    }
-                    _ => {
-                        let val = format_yaml_scalar(v);
-                        out.push_str(&format!("{}- **{}**: {}\n", prefix, key, val));
-                    }
-                }
+                format_yaml_entry(out, k, v, indent);
             }
         }
         serde_yaml::Value::Sequence(seq) => {
@@ -153,71 +273,195 @@ fn format_yaml_scalar(value: &serde_yaml::Value) -> String {
 
 /// Format manufacturing constraints from AgentRules into a prompt-ready string.
 /// Returns a markdown section that can be appended to the geometry advisor prompt.
-pub fn format_manufacturing_constraints(manufacturing: &serde_yaml::Value) -> String {
+/// Each top-level key is one rule for budgeting.
+pub fn format_manufacturing_constraints(
+    manufacturing: &serde_yaml::Value,
+    budget: &RuleBudget,
+) -> (String, RuleSectionStats) {
+    let entries: Vec<String> = match manufacturing {
+        serde_yaml::Value::Mapping(map) => map
+            .iter()
+            .map(|(k, v)| {
+                let mut entry = String::new();
+                format_yaml_entry(&mut entry, k, v, 0);
+                entry
+            })
+            .collect(),
+        other => {
+            let mut entry = String::new();
+            format_yaml_value(&mut entry, other, 0);
+            vec![entry]
+        }
+    };
+    let scored: Vec<(String, usize)> = entries
+        .iter()
+        .map(|e| (e.clone(), e.chars().count()))
+        .collect();
+    let included = select_rule_entries(&scored, budget);
+
     let mut out = String::new();
     out.push_str("## Manufacturing Constraints\n");
     out.push_str("The user's active manufacturing profile imposes these constraints. ");
     out.push_str("Your geometry plan MUST respect them.\n\n");
-    format_yaml_value(&mut out, manufacturing, 0);
-    out
+    for (entry, keep) in entries.iter().zip(&included) {
+        if *keep {
+            out.push_str(entry);
+        }
+    }
+    let stats = section_stats("manufacturing", &included);
+    if stats.omitted > 0 {
+        out.push_str(&omitted_note(stats.omitted));
+    }
+    (out, stats)
 }
 
-/// Format dimension guidance rules into a prompt-ready string for the geometry advisor.
-pub fn format_dimension_guidance(
-    guidance: &std::collections::HashMap<String, Vec<String>>,
-) -> String {
-    let mut out = String::new();
-    out.push_str("## Dimension Estimation Guidance\n");
-    out.push_str("Follow these rules when choosing dimensions for your geometry plan.\n\n");
-    for (category, items) in guidance {
-        let title: String = category
-            .split('_')
-            .map(|w| {
-                let mut c = w.chars();
-                match c.next() {
-                    Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
-                    None => String::new(),
-                }
-            })
-            .collect::<Vec<_>>()
-            .join(" ");
-        out.push_str(&format!("### {}\n", title));
-        for item in items {
-            out.push_str(&format!("- {}\n", item));
+fn category_title(category: &str) -> String {
+    category
+        .split('_')
+        .map(|w| {
+            let mut c = w.chars();
+            match c.next() {
+                Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Format `category → rules` lists under `header`, one `###` heading per
+/// category in name order; each list item is one rule for budgeting.
+fn format_categorized_rules(
+    section: &str,
+    header: &str,
+    rules: &std::collections::HashMap<String, Vec<String>>,
+    budget: &RuleBudget,
+) -> (String, RuleSectionStats) {
+    let mut categories: Vec<&String> = rules.keys().collect();
+    categories.sort();
+    let items: Vec<(&str, &str)> = categories
+        .iter()
+        .flat_map(|c| {
+            rules[*c]
+                .iter()
+                .map(move |item| (c.as_str(), item.as_str()))
+        })
+        .collect();
+    let scored: Vec<(String, usize)> = items
+        .iter()
+        .map(|(category, item)| (format!("{} {}", category, item), item.chars().count() + 3))
+        .collect();
+    let included = select_rule_entries(&scored, budget);
+
+    let mut out = String::from(header);
+    let mut current: Option<&str> = None;
+    for ((category, item), keep) in items.iter().zip(&included) {
+        if !*keep {
+            continue;
+        }
+        if current != Some(*category) {
+            if current.is_some() {
+                out.push('\n');
+            }
+            out.push_str(&format!("### {}\n", category_title(category)));
+            current = Some(*category);
         }
+        out.push_str(&format!("- {}\n", item));
+    }
+    if current.is_some() {
         out.push('\n');
     }
-    out
+    let stats = section_stats(section, &included);
+    if stats.omitted > 0 {
+        out.push_str(&omitted_note(stats.omitted));
+    }
+    (out, stats)
+}
+
+/// Format dimension guidance rules into a prompt-ready string for the geometry advisor.
+pub fn format_dimension_guidance(
+    guidance: &std::collections::HashMap<String, Vec<String>>,
+    budget: &RuleBudget,
+) -> (String, RuleSectionStats) {
+    format_categorized_rules(
+        "dimension_guidance",
+        "## Dimension Estimation Guidance\n\
+         Follow these rules when choosing dimensions for your geometry plan.\n\n",
+        guidance,
+        budget,
+    )
 }
 
 /// Format failure prevention rules into a prompt-ready string for the geometry advisor.
 pub fn format_failure_prevention(
     prevention: &std::collections::HashMap<String, Vec<String>>,
-) -> String {
-    let mut out = String::new();
-    out.push_str("## Failure Prevention Rules\n");
-    out.push_str(
-        "Follow these rules when planning geometry to avoid operations that commonly fail.\n\n",
-    );
-    for (category, items) in prevention {
-        let title: String = category
-            .split('_')
-            .map(|w| {
-                let mut c = w.chars();
-                match c.next() {
-                    Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
-                    None => String::new(),
-                }
+    budget: &RuleBudget,
+) -> (String, RuleSectionStats) {
+    format_categorized_rules(
+        "failure_prevention",
+        "## Failure Prevention Rules\n\
+         Follow these rules when planning geometry to avoid operations that commonly fail.\n\n",
+        prevention,
+        budget,
+    )
+}
+
+/// Rule content handed to the geometry advisor, with per-section counts.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleContext {
+    pub context: String,
+    pub sections: Vec<RuleSectionStats>,
+}
+
+/// Manufacturing, dimension guidance and failure prevention sections for
+/// `request`, each cut to its configured character budget. Rules are ranked
+/// by overlap with the request and the active process's intent tags.
+pub fn format_rule_context(rules: &AgentRules, request: &str, config: &AppConfig) -> RuleContext {
+    let tags = process_intent_tags(&crate::agent::clearance::active_process(config));
+    let budget = |max_chars: usize| RuleBudget::new(max_chars, request, &tags);
+    let mut parts = Vec::new();
+    let mut sections = Vec::new();
+    if let Some(ref m) = rules.manufacturing {
+        let (text, stats) =
+            format_manufacturing_constraints(m, &budget(config.manufacturing_rules_budget_chars));
+        parts.push(text);
+        sections.push(stats);
+    }
+    if let Some(ref d) = rules.dimension_guidance {
+        let (text, stats) =
+            format_dimension_guidance(d, &budget(config.dimension_guidance_budget_chars));
+        parts.push(text);
+        sections.push(stats);
+    }
+    if let Some(ref fp) = rules.failure_prevention {
+        let (text, stats) =
+            format_failure_prevention(fp, &budget(config.failure_prevention_budget_chars));
+        parts.push(text);
+        sections.push(stats);
+    }
+    RuleContext {
+        context: parts.join("\n\n"),
+        sections,
+    }
+}
+
+impl RuleContext {
+    /// One-line included/omitted summary for the run log.
+    pub fn summary(&self) -> String {
+        let counts: Vec<String> = self
+            .sections
+            .iter()
+            .map(|s| {
+                format!(
+                    "{} {}/{}",
+                    s.section.replace('_', " "),
+                    s.included,
+                    s.included + s.omitted
+                )
             })
-            .collect::<Vec<_>>()
-            .join(" ");
-        out.push_str(&format!("### {}\n", title));
-        for item in items {
-            out.push_str(&format!("- {}\n", item));
-        }
-        out.push('\n');
+            .collect();
+        format!("Rule context: {} rules included", counts.join(", "))
     }
-    out
 }
 
 // ---------------------------------------------------------------------------
@@ -254,6 +498,32 @@ const NEGATION_PREFIXES: &[&str] = &[
     "no ",
 ];
 const REPAIR_SENSITIVE_OPS: &[&str] = &["shell", "loft", "sweep", "fillet", "chamfer"];
+/// Nouns whose size a Build Plan step should state as a number.
+const DIMENSION_NOUNS: &[&str] = &[
+    "hole",
+    "wall",
+    "thickness",
+    "diameter",
+    "radius",
+    "width",
+    "height",
+    "depth",
+    "length",
+    "size",
+    "fillet",
+    "chamfer",
+    "gap",
+    "clearance",
+    "slot",
+    "boss",
+    "lip",
+    "rim",
+    "offset",
+    "spacing",
+    "pocket",
+    "groove",
+    "margin",
+];
 
 /// Check if "shell" appears as a CAD operation (verb) rather than an English noun.
 /// Matches: "shell(", "shell it", "shell the body", "apply shell", "use shell", "then shell"
@@ -374,43 +644,62 @@ fn extract_negated_operations(plan_text: &str) -> Vec<String> {
 }
 
 /// Extract numeric dimensions (in mm) from the plan text.
+///
+/// Accepts `.` and `,` decimal separators and exponents like "1e2 mm" (see
+/// [`numparse`]), and ranges such as "1,5–2,0 mm", where both ends count as
+/// dimensions.
 fn extract_dimensions(plan_text: &str) -> Vec<f64> {
     let mut dims = Vec::new();
     let mut multi_dim_values = std::collections::HashSet::new();
 
     // Pattern 1: multi-dim like "50x30x20mm" or "50 x 30 x 20 mm"
-    let multi_re =
-        Regex::new(r"(-?\d+\.?\d*)\s*[x×]\s*(-?\d+\.?\d*)(?:\s*[x×]\s*(-?\d+\.?\d*))?\s*(?:mm)?\b")
-            .unwrap();
+    let multi_re = Regex::new(&format!(
+        r"({n})\s*[x×]\s*({n})(?:\s*[x×]\s*({n}))?\s*(?:mm)?\b",
+        n = numparse::SIGNED_NUMBER
+    ))
+    .unwrap();
     for cap in multi_re.captures_iter(plan_text) {
-        if let Ok(v) = cap[1].parse::<f64>() {
-            dims.push(v);
-            multi_dim_values.insert(cap[1].to_string());
-        }
-        if let Ok(v) = cap[2].parse::<f64>() {
-            dims.push(v);
-            multi_dim_values.insert(cap[2].to_string());
-        }
-        if let Some(m) = cap.get(3) {
-            if let Ok(v) = m.as_str().parse::<f64>() {
+        for m in [cap.get(1), cap.get(2), cap.get(3)].into_iter().flatten() {
+            if let Some(v) = numparse::parse_number(m.as_str()) {
                 dims.push(v);
                 multi_dim_values.insert(m.as_str().to_string());
             }
         }
     }
 
-    // Pattern 2: single like "5mm", "100 mm", "-2mm"
-    let single_re = Regex::new(r"(-?\d+\.?\d*)\s*mm\b").unwrap();
+    // Pattern 2: range like "1,5–2,0 mm" or "1.5-2mm"
+    let range_re = Regex::new(&format!(
+        r"({n}){sep}({n})\s*mm\b",
+        n = numparse::NUMBER,
+        sep = numparse::RANGE_SEPARATOR
+    ))
+    .unwrap();
+    let mut range_spans = Vec::new();
+    for cap in range_re.captures_iter(plan_text) {
+        let whole = cap.get(0).unwrap();
+        range_spans.push(whole.start()..whole.end());
+        for m in [cap.get(1), cap.get(2)].into_iter().flatten() {
+            if let Some(v) = numparse::parse_number(m.as_str()) {
+                dims.push(v);
+            }
+        }
+    }
+
+    // Pattern 3: single like "5mm", "100 mm", "-2mm", "1,8 mm"
+    let single_re = Regex::new(&format!(r"({})\s*mm\b", numparse::SIGNED_NUMBER)).unwrap();
     for cap in single_re.captures_iter(plan_text) {
-        let val_str = &cap[1];
-        // Skip if already captured by multi-dim pattern
-        if multi_dim_values.contains(val_str) {
+        let val_match = cap.get(1).unwrap();
+        let val_str = val_match.as_str();
+        // Skip if already captured by the multi-dim or range patterns
+        if multi_dim_values.contains(val_str)
+            || range_spans.iter().any(|span| span.contains(&val_match.start()))
+        {
             continue;
         }
-        if let Ok(v) = val_str.parse::<f64>() {
+        if let Some(v) = numparse::parse_number(val_str) {
             // Skip negative values in offset/position context
             if v < 0.0 {
-                let match_start = cap.get(1).unwrap().start();
+                let match_start = val_match.start();
                 if is_offset_context(plan_text, match_start) {
                     continue;
                 }
@@ -424,10 +713,14 @@ fn extract_dimensions(plan_text: &str) -> Vec<f64> {
 
 /// Extract fillet radii mentioned near "fillet" keywords.
 fn extract_fillet_radii(plan_text: &str) -> Vec<f64> {
-    let re = Regex::new(r"(?i)fillet\w*[\s\(\-\x{2014}:]+(\d+\.?\d*)\s*(?:mm)?").unwrap();
+    let re = Regex::new(&format!(
+        r"(?i)fillet\w*[\s\(\-\x{{2014}}:]+({})\s*(?:mm)?",
+        numparse::NUMBER
+    ))
+    .unwrap();
     let mut radii = Vec::new();
     for cap in re.captures_iter(plan_text) {
-        if let Ok(v) = cap[1].parse::<f64>() {
+        if let Some(v) = numparse::parse_number(&cap[1]) {
             radii.push(v);
         }
     }
@@ -436,10 +729,14 @@ fn extract_fillet_radii(plan_text: &str) -> Vec<f64> {
 
 /// Extract chamfer sizes mentioned near "chamfer" keywords.
 fn extract_chamfer_sizes(plan_text: &str) -> Vec<f64> {
-    let re = Regex::new(r"(?i)chamfer\w*[\s\(\-\x{2014}:]+(\d+\.?\d*)\s*(?:mm)?").unwrap();
+    let re = Regex::new(&format!(
+        r"(?i)chamfer\w*[\s\(\-\x{{2014}}:]+({})\s*(?:mm)?",
+        numparse::NUMBER
+    ))
+    .unwrap();
     let mut sizes = Vec::new();
     for cap in re.captures_iter(plan_text) {
-        if let Ok(v) = cap[1].parse::<f64>() {
+        if let Some(v) = numparse::parse_number(&cap[1]) {
             sizes.push(v);
         }
     }
@@ -698,6 +995,183 @@ fn canonicalize_plan_sections(plan_text: &str) -> String {
     }
 }
 
+/// Plan sections as structured data, for programmatic consumers of a plan.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct StructuredPlan {
+    pub object_analysis: Option<String>,
+    /// CAD Approach section, or one of its aliases (e.g. Geometry Breakdown).
+    pub cad_approach: Option<String>,
+    /// Numbered Build Plan steps without their numbers.
+    pub build_steps: Vec<String>,
+    pub approximation_notes: Option<String>,
+}
+
+/// Split a markdown plan into its sections and numbered build steps.
+///
+/// Section aliases and loose labels are normalized first, but section text
+/// is kept as written (unlike the canonicalized plan the validator sees).
+pub fn parse_plan_to_struct(plan_text: &str) -> StructuredPlan {
+    let plan_text = extract_plan_block(plan_text).unwrap_or(plan_text);
+    let normalized = normalize_section_labels(plan_text).unwrap_or_else(|| plan_text.to_string());
+    let plan_text = normalized.as_str();
+    StructuredPlan {
+        object_analysis: extract_section(plan_text, "Object Analysis"),
+        cad_approach: extract_section(plan_text, "CAD Approach"),
+        build_steps: extract_section(plan_text, "Build Plan")
+            .map(|section| extract_numbered_steps(&section))
+            .unwrap_or_default(),
+        approximation_notes: extract_section(plan_text, "Approximation Notes"),
+    }
+}
+
+/// A dimension that changed between two plans; `None` on one side means it
+/// was added or removed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DimensionDelta {
+    pub old: Option<f64>,
+    pub new: Option<f64>,
+}
+
+/// Operations and dimensions that changed within one plan section.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SectionDiff {
+    pub section: String,
+    pub added_operations: Vec<String>,
+    pub removed_operations: Vec<String>,
+    pub dimension_changes: Vec<DimensionDelta>,
+}
+
+/// A build step rewritten in place, paired with the step it replaced.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BuildStepChange {
+    pub old: String,
+    pub new: String,
+    pub added_operations: Vec<String>,
+    pub removed_operations: Vec<String>,
+    pub dimension_changes: Vec<DimensionDelta>,
+}
+
+/// Semantic differences between two design plans. Only sections whose text
+/// changed are listed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlanDiff {
+    pub sections: Vec<SectionDiff>,
+    pub added_steps: Vec<String>,
+    pub removed_steps: Vec<String>,
+    pub changed_steps: Vec<BuildStepChange>,
+}
+
+/// Dimensions of `old` and `new` that are not in the other, paired in order.
+fn dimension_changes(old: &str, new: &str) -> Vec<DimensionDelta> {
+    let old_dims = extract_dimensions(old);
+    let mut added = extract_dimensions(new);
+    let mut removed = Vec::new();
+    for dim in old_dims {
+        match added.iter().position(|d| (d - dim).abs() < 1e-9) {
+            Some(i) => {
+                added.remove(i);
+            }
+            None => removed.push(dim),
+        }
+    }
+    let len = removed.len().max(added.len());
+    (0..len)
+        .map(|i| DimensionDelta {
+            old: removed.get(i).copied(),
+            new: added.get(i).copied(),
+        })
+        .collect()
+}
+
+/// Operations of `new` missing from `old`, and of `old` missing from `new`.
+fn operation_changes(old: &str, new: &str) -> (Vec<String>, Vec<String>) {
+    let old_ops = extract_operations_from_text(old);
+    let new_ops = extract_operations_from_text(new);
+    let added = new_ops
+        .iter()
+        .filter(|op| !old_ops.contains(op))
+        .cloned()
+        .collect();
+    let removed = old_ops
+        .iter()
+        .filter(|op| !new_ops.contains(op))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+/// Compare two design plans section by section. Build steps are aligned
+/// with a line diff: a run of removed steps followed by added ones is
+/// reported as changed steps, pairwise, and the remainder as added/removed.
+pub fn diff_plans(old_plan: &str, new_plan: &str) -> PlanDiff {
+    let old = parse_plan_to_struct(old_plan);
+    let new = parse_plan_to_struct(new_plan);
+    // One step per line, each newline-terminated so appending a step does
+    // not read as changing the last one.
+    let old_steps: String = old.build_steps.iter().map(|s| format!("{}\n", s)).collect();
+    let new_steps: String = new.build_steps.iter().map(|s| format!("{}\n", s)).collect();
+    let sections = [
+        ("Object Analysis", old.object_analysis, new.object_analysis),
+        ("CAD Approach", old.cad_approach, new.cad_approach),
+        (
+            "Build Plan",
+            Some(old_steps.clone()),
+            Some(new_steps.clone()),
+        ),
+        (
+            "Approximation Notes",
+            old.approximation_notes,
+            new.approximation_notes,
+        ),
+    ];
+
+    let mut diff = PlanDiff::default();
+    for (section, old_text, new_text) in sections {
+        let old_text = old_text.unwrap_or_default();
+        let new_text = new_text.unwrap_or_default();
+        if old_text.trim() == new_text.trim() {
+            continue;
+        }
+        let (added_operations, removed_operations) = operation_changes(&old_text, &new_text);
+        diff.sections.push(SectionDiff {
+            section: section.to_string(),
+            added_operations,
+            removed_operations,
+            dimension_changes: dimension_changes(&old_text, &new_text),
+        });
+    }
+
+    let mut removed: Vec<String> = Vec::new();
+    let mut added: Vec<String> = Vec::new();
+    let lines = modify::compute_diff(&old_steps, &new_steps);
+    for (i, line) in lines.iter().enumerate() {
+        match line.tag.as_str() {
+            "delete" => removed.push(line.text.clone()),
+            "insert" => added.push(line.text.clone()),
+            _ => {}
+        }
+        let run_ends = lines.get(i + 1).is_none_or(|next| next.tag == "equal");
+        if !run_ends {
+            continue;
+        }
+        let paired = removed.len().min(added.len());
+        for (old_step, new_step) in removed.drain(..paired).zip(added.drain(..paired)) {
+            let (added_operations, removed_operations) = operation_changes(&old_step, &new_step);
+            let dimension_changes = dimension_changes(&old_step, &new_step);
+            diff.changed_steps.push(BuildStepChange {
+                old: old_step,
+                new: new_step,
+                added_operations,
+                removed_operations,
+                dimension_changes,
+            });
+        }
+        diff.removed_steps.append(&mut removed);
+        diff.added_steps.append(&mut added);
+    }
+    diff
+}
+
 /// Normalize loose section label styles into canonical markdown headings.
 ///
 /// Accepts variants like:
@@ -813,6 +1287,35 @@ fn count_boolean_mentions(plan_text: &str) -> usize {
     count
 }
 
+/// Vague-sizing phrases next to a dimension noun ("appropriate-sized hole",
+/// "reasonable wall thickness", "fillet radius as needed").
+fn vague_sizing_phrases(steps_text: &str) -> Vec<String> {
+    let lower = steps_text.to_lowercase();
+    let nouns = DIMENSION_NOUNS.join("|");
+    let before_re = Regex::new(&format!(
+        r"\b(?:appropriate|reasonable|suitable)(?:ly)?(?:[- ]sized?)?\s+(?:[a-z]+\s+)?(?:{})s?\b",
+        nouns
+    ))
+    .unwrap();
+    let after_re = Regex::new(&format!(
+        r"\b(?:{})s?(?:\s+[a-z]+)?\s+(?:as needed|as appropriate|as suitable)\b",
+        nouns
+    ))
+    .unwrap();
+
+    let mut phrases: Vec<String> = Vec::new();
+    for m in before_re
+        .find_iter(&lower)
+        .chain(after_re.find_iter(&lower))
+    {
+        let phrase = m.as_str().to_string();
+        if !phrases.contains(&phrase) {
+            phrases.push(phrase);
+        }
+    }
+    phrases
+}
+
 /// Count boolean mentions scoped to the Build Plan section only.
 /// Falls back to the full text if no Build Plan section is found.
 #[cfg(test)]
@@ -1084,6 +1587,24 @@ pub fn validate_plan_with_profile(
         warnings.push("no concrete dimensions found in plan".to_string());
     }
 
+    // Rule 11b: vague sizing ("appropriate", "reasonable") where a step
+    // should give a number, even when other steps are dimensioned.
+    let vague_sizing = build_plan_steps_text
+        .as_deref()
+        .map(vague_sizing_phrases)
+        .unwrap_or_default();
+    if !vague_sizing.is_empty() {
+        risk += 2;
+        warnings.push(format!(
+            "vague sizing in Build Plan steps ({}); give concrete dimensions in mm",
+            vague_sizing
+                .iter()
+                .map(|p| format!("'{}'", p))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
     // Rule 12: no operations
     if operations_for_presence.is_empty() {
         risk += 1;
@@ -1144,11 +1665,7 @@ pub fn validate_plan_with_profile(
         && has_section(plan_text, "CAD Approach")
         && has_section(plan_text, "Build Plan")
         && build_plan_steps_text.is_some();
-    let risk_threshold = match profile {
-        GenerationReliabilityProfile::ReliabilityFirst => 5,
-        GenerationReliabilityProfile::Balanced => 7,
-        GenerationReliabilityProfile::FidelityFirst => 8,
-    };
+    let risk_threshold = risk_threshold(profile);
     let has_fatal_reliability_combo =
         matches!(profile, GenerationReliabilityProfile::ReliabilityFirst)
             && (fatal_loft_shell || fatal_shell_internal_fillet);
@@ -1166,6 +1683,7 @@ pub fn validate_plan_with_profile(
         fatal_combo: has_fatal_reliability_combo,
         negation_conflict,
         repair_sensitive_ops,
+        estimated_operations: None,
     };
 
     let is_valid = risk <= risk_threshold && has_required_structure && !has_fatal_reliability_combo;
@@ -1192,10 +1710,465 @@ pub fn validate_plan_with_profile(
     }
 }
 
+/// Highest risk score a plan may have and still be accepted.
+fn risk_threshold(profile: &GenerationReliabilityProfile) -> u32 {
+    match profile {
+        GenerationReliabilityProfile::ReliabilityFirst => 5,
+        GenerationReliabilityProfile::Balanced => 7,
+        GenerationReliabilityProfile::FidelityFirst => 8,
+    }
+}
+
+/// Words that mark a request or plan as organic / free-form geometry.
+const ORGANIC_CUES: &[&str] = &[
+    "curved",
+    "ergonomic",
+    "organic",
+    "smooth blend",
+    "free-form",
+    "freeform",
+    "sculpted",
+];
+
+/// Organic cues found in the request or plan text.
+pub fn organic_cues(request: &str, plan_text: &str) -> Vec<&'static str> {
+    let text = format!("{}\n{}", request, plan_text).to_lowercase();
+    ORGANIC_CUES
+        .iter()
+        .copied()
+        .filter(|cue| text.contains(cue))
+        .collect()
+}
+
+/// Organic requests whose plan has no `Approximation Notes` section tend to
+/// get naive plans that fail; add `extra_risk` and a targeted warning, and
+/// re-check acceptance. Inert for prismatic requests or when `extra_risk` is 0.
+pub fn apply_organic_approximation_check(
+    validation: &mut PlanValidation,
+    request: &str,
+    profile: &GenerationReliabilityProfile,
+    extra_risk: u32,
+) {
+    if extra_risk == 0 || has_section(&validation.plan_text, "Approximation Notes") {
+        return;
+    }
+    let cues = organic_cues(request, &validation.plan_text);
+    if cues.is_empty() {
+        return;
+    }
+
+    let warning = format!(
+        "organic request ({}) but the plan has no 'Approximation Notes' section; explain how \
+         curved surfaces are approximated with robust operations",
+        cues.join(", ")
+    );
+    validation.risk_score = (validation.risk_score + extra_risk).min(10);
+    validation.warnings.push(warning.clone());
+    if validation.is_valid && validation.risk_score > risk_threshold(profile) {
+        validation.is_valid = false;
+        validation.rejected_reason = Some(warning);
+    }
+}
+
+/// Estimate the plan's geometric operation count and hold it against the
+/// budgets: above `soft_budget` adds a warning, above `hard_budget` rejects
+/// the plan with a simplification instruction for the re-plan.
+pub fn apply_operation_budget(validation: &mut PlanValidation, soft_budget: u32, hard_budget: u32) {
+    let Some(steps_text) = extract_build_plan_steps_text(&validation.plan_text) else {
+        return;
+    };
+    let estimated = complexity::estimate_plan_operations(&extract_numbered_steps(&steps_text));
+    validation.risk_signals.estimated_operations = Some(estimated);
+
+    match complexity::assess_budget(estimated, soft_budget, hard_budget) {
+        complexity::BudgetVerdict::Within => {}
+        complexity::BudgetVerdict::OverSoft => {
+            validation.warnings.push(complexity::soft_budget_warning(
+                "Plan",
+                estimated,
+                soft_budget,
+            ));
+        }
+        complexity::BudgetVerdict::OverHard => {
+            let instruction =
+                complexity::simplification_instruction("Plan", estimated, hard_budget);
+            validation.warnings.push(instruction.clone());
+            if validation.is_valid {
+                validation.is_valid = false;
+                validation.rejected_reason = Some(instruction);
+            }
+        }
+    }
+}
+
+/// Words that mark a request as simple prismatic geometry.
+const PRISMATIC_CUES: &[&str] = &[
+    "box",
+    "cube",
+    "block",
+    "plate",
+    "bracket",
+    "spacer",
+    "washer",
+    "standoff",
+    "panel",
+    "rectangular",
+];
+
+/// Operations in a request that most often need the robust path.
+const RISKY_REQUEST_OPS: &[&str] = &["shell", "loft", "sweep"];
+
+/// Reliability profile recommended for a request, with a one-line reason.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileSuggestion {
+    pub profile: GenerationReliabilityProfile,
+    pub rationale: String,
+    /// The suggestion is already the configured profile.
+    pub already_selected: bool,
+}
+
+/// Suggest a reliability profile from the request text alone (no AI calls).
+///
+/// Organic cues or risky operations favour reliability; plainly prismatic
+/// requests can afford fidelity; anything else is balanced.
+pub fn suggest_profile(request: &str) -> ProfileSuggestion {
+    let organic = organic_cues(request, "");
+    let risky: Vec<String> = extract_positive_operations(request)
+        .into_iter()
+        .filter(|op| RISKY_REQUEST_OPS.contains(&op.as_str()))
+        .collect();
+    let lower = request.to_lowercase();
+    let prismatic: Vec<&str> = PRISMATIC_CUES
+        .iter()
+        .copied()
+        .filter(|cue| {
+            Regex::new(&format!(r"\b{}(?:e?s)?\b", cue))
+                .unwrap()
+                .is_match(&lower)
+        })
+        .collect();
+
+    let (profile, rationale) = if !organic.is_empty() {
+        (
+            GenerationReliabilityProfile::ReliabilityFirst,
+            format!(
+                "Organic geometry ({}) fails often; reliability-first approximates it with robust operations.",
+                organic.join(", ")
+            ),
+        )
+    } else if !risky.is_empty() {
+        (
+            GenerationReliabilityProfile::ReliabilityFirst,
+            format!(
+                "Requested {} is repair-prone; reliability-first rejects fragile plans early.",
+                risky.join(", ")
+            ),
+        )
+    } else if !prismatic.is_empty() {
+        (
+            GenerationReliabilityProfile::FidelityFirst,
+            format!(
+                "Simple prismatic geometry ({}) rarely fails; fidelity-first keeps every detail.",
+                prismatic.join(", ")
+            ),
+        )
+    } else {
+        (
+            GenerationReliabilityProfile::Balanced,
+            "No strong organic or prismatic cues; balanced trades detail against robustness."
+                .to_string(),
+        )
+    };
+    ProfileSuggestion {
+        profile,
+        rationale,
+        already_selected: false,
+    }
+}
+
 pub fn validate_plan(plan_text: &str) -> PlanValidation {
     validate_plan_with_profile(plan_text, &GenerationReliabilityProfile::Balanced)
 }
 
+// ---------------------------------------------------------------------------
+// Proportional dimensions
+// ---------------------------------------------------------------------------
+
+/// Relative sizes ("1/3 of the width") resolved against absolute dimensions
+/// found in the request or plan.
+#[derive(Debug, Clone, Default)]
+pub struct ProportionalResolution {
+    /// Plan text with resolved phrases in the Build Plan replaced by mm values.
+    pub plan_text: String,
+    /// One informational note per substitution.
+    pub notes: Vec<String>,
+    /// Clarification questions for relatives with no base dimension anywhere.
+    pub questions: Vec<String>,
+}
+
+const PROPORTION_FACTOR: &str = r"(\d+\s*/\s*\d+|\d+(?:[.,]\d+)?\s*(?:%|percent)|(?:two|three)\s+(?:thirds|quarters)|(?:a|one)\s+(?:half|third|quarter|fourth|fifth)|half|twice|double|triple|(?:two|three|four|\d+(?:[.,]\d+)?)\s*(?:times|x))";
+const AXIS_NOUN: &str = r"(width|length|height|depth|thickness|diameter)";
+const AXIS_ADJECTIVE: &str = r"(wide|long|tall|high|deep|thick)";
+
+fn axis_from_word(word: &str) -> Option<&'static str> {
+    match word.to_lowercase().as_str() {
+        "width" | "wide" => Some("width"),
+        "length" | "long" => Some("length"),
+        "height" | "tall" | "high" => Some("height"),
+        "depth" | "deep" => Some("depth"),
+        "thickness" | "thick" => Some("thickness"),
+        "diameter" => Some("diameter"),
+        _ => None,
+    }
+}
+
+fn axis_adjective(axis: &str) -> &'static str {
+    match axis {
+        "width" => "wide",
+        "length" => "long",
+        "height" => "tall",
+        "depth" => "deep",
+        "thickness" => "thick",
+        _ => "in diameter",
+    }
+}
+
+fn parse_proportion_factor(text: &str) -> Option<f64> {
+    let t = text.trim().to_lowercase();
+    let words = t.split_whitespace().collect::<Vec<_>>().join(" ");
+    let named = match words.as_str() {
+        "half" | "a half" | "one half" => Some(0.5),
+        "a third" | "one third" => Some(1.0 / 3.0),
+        "two thirds" => Some(2.0 / 3.0),
+        "a quarter" | "one quarter" | "a fourth" | "one fourth" => Some(0.25),
+        "three quarters" => Some(0.75),
+        "a fifth" | "one fifth" => Some(0.2),
+        "twice" | "double" | "two times" | "two x" => Some(2.0),
+        "triple" | "three times" | "three x" => Some(3.0),
+        "four times" | "four x" => Some(4.0),
+        _ => None,
+    };
+    if named.is_some() {
+        return named;
+    }
+    if let Some((num, den)) = words.split_once('/') {
+        let den = numparse::parse_number(den)?;
+        if den == 0.0 {
+            return None;
+        }
+        return numparse::parse_number(num).map(|n| n / den);
+    }
+    if let Some(pct) = words
+        .strip_suffix("percent")
+        .or_else(|| words.strip_suffix('%'))
+    {
+        return numparse::parse_number(pct).map(|p| p / 100.0);
+    }
+    let multiple = words
+        .strip_suffix("times")
+        .or_else(|| words.strip_suffix('x'))?;
+    numparse::parse_number(multiple)
+}
+
+/// First absolute value per axis, from "width 80 mm", "80 mm wide" or an
+/// "LxWxH mm" triple. Earlier texts take precedence.
+fn base_dimensions(texts: &[&str]) -> std::collections::HashMap<&'static str, f64> {
+    let noun_re = Regex::new(&format!(
+        r"(?i)\b{}\b[^\n\d/%]{{0,20}}?({})\s*mm\b",
+        AXIS_NOUN,
+        numparse::NUMBER
+    ))
+    .unwrap();
+    let adjective_re = Regex::new(&format!(
+        r"(?i)({})\s*mm\s+(?:{}|in\s+(diameter))\b",
+        numparse::NUMBER,
+        AXIS_ADJECTIVE
+    ))
+    .unwrap();
+    let triple_re = Regex::new(&format!(
+        r"({n})\s*[x×]\s*({n})\s*[x×]\s*({n})\s*mm\b",
+        n = numparse::NUMBER
+    ))
+    .unwrap();
+
+    let mut bases = std::collections::HashMap::new();
+    for text in texts {
+        // "1/3 of the width, 1 mm deep" does not give the width.
+        let relatives = find_relative_phrases(text);
+        for cap in noun_re.captures_iter(text) {
+            let noun_start = cap.get(1).unwrap().start();
+            if relatives.iter().any(|r| r.range.contains(&noun_start)) {
+                continue;
+            }
+            if let (Some(axis), Some(v)) =
+                (axis_from_word(&cap[1]), numparse::parse_number(&cap[2]))
+            {
+                bases.entry(axis).or_insert(v);
+            }
+        }
+        for cap in adjective_re.captures_iter(text) {
+            let word = cap.get(2).or(cap.get(3)).map_or("", |m| m.as_str());
+            if let (Some(axis), Some(v)) = (axis_from_word(word), numparse::parse_number(&cap[1])) {
+                bases.entry(axis).or_insert(v);
+            }
+        }
+        if let Some(cap) = triple_re.captures(text) {
+            for (axis, idx) in [("length", 1), ("width", 2), ("height", 3)] {
+                if let Some(v) = numparse::parse_number(&cap[idx]) {
+                    bases.entry(axis).or_insert(v);
+                }
+            }
+        }
+    }
+    bases
+}
+
+fn format_mm(value: f64) -> String {
+    let rounded = (value * 10.0).round() / 10.0;
+    if rounded.fract().abs() < 1e-9 {
+        format!("{:.0} mm", rounded)
+    } else {
+        format!("{:.1} mm", rounded)
+    }
+}
+
+struct RelativeMatch {
+    range: std::ops::Range<usize>,
+    phrase: String,
+    factor: f64,
+    /// Axis the factor applies to.
+    base_axis: &'static str,
+    /// Adjective to append for "twice as long as it is wide" phrasings.
+    target_adjective: Option<&'static str>,
+}
+
+fn find_relative_phrases(text: &str) -> Vec<RelativeMatch> {
+    let comparative_re = Regex::new(&format!(
+        r"(?i)\b{f}\s+as\s+{a}\s+as\s+(?:it\s+is\s+|the\s+\w+\s+is\s+)?{a}\b",
+        f = PROPORTION_FACTOR,
+        a = AXIS_ADJECTIVE
+    ))
+    .unwrap();
+    let of_re = Regex::new(&format!(
+        r"(?i)\b{}\s+(?:of\s+)?(?:(?:the|its|overall|total)\s+)*{}\b",
+        PROPORTION_FACTOR, AXIS_NOUN
+    ))
+    .unwrap();
+
+    let mut matches: Vec<RelativeMatch> = Vec::new();
+    for cap in comparative_re.captures_iter(text) {
+        let whole = cap.get(0).unwrap();
+        let (Some(factor), Some(target), Some(base)) = (
+            parse_proportion_factor(&cap[1]),
+            axis_from_word(&cap[2]),
+            axis_from_word(&cap[3]),
+        ) else {
+            continue;
+        };
+        matches.push(RelativeMatch {
+            range: whole.range(),
+            phrase: whole.as_str().to_string(),
+            factor,
+            base_axis: base,
+            target_adjective: Some(axis_adjective(target)),
+        });
+    }
+    for cap in of_re.captures_iter(text) {
+        let whole = cap.get(0).unwrap();
+        if matches.iter().any(|m| m.range.contains(&whole.start())) {
+            continue;
+        }
+        let (Some(factor), Some(base)) =
+            (parse_proportion_factor(&cap[1]), axis_from_word(&cap[2]))
+        else {
+            continue;
+        };
+        matches.push(RelativeMatch {
+            range: whole.range(),
+            phrase: whole.as_str().to_string(),
+            factor,
+            base_axis: base,
+            target_adjective: None,
+        });
+    }
+    matches.sort_by_key(|m| m.range.start);
+    matches
+}
+
+/// Byte range of the Build Plan section body, or the whole text without one.
+fn build_plan_range(plan_text: &str) -> std::ops::Range<usize> {
+    let heading_re = Regex::new(r"(?im)^#{2,3}\s+build plan\s*$").unwrap();
+    let Some(heading) = heading_re.find(plan_text) else {
+        return 0..plan_text.len();
+    };
+    let next_re = Regex::new(r"(?m)^#{2,3}\s").unwrap();
+    let end = next_re
+        .find(&plan_text[heading.end()..])
+        .map_or(plan_text.len(), |m| heading.end() + m.start());
+    heading.end()..end
+}
+
+/// Resolve proportional phrases in the plan's Build Plan steps against
+/// absolute dimensions from the request and plan. Relatives whose base axis
+/// has no absolute value anywhere become clarification questions.
+pub fn resolve_proportional_dimensions(
+    plan_text: &str,
+    user_request: &str,
+) -> ProportionalResolution {
+    let bases = base_dimensions(&[user_request, plan_text]);
+    let mut resolution = ProportionalResolution {
+        plan_text: plan_text.to_string(),
+        ..Default::default()
+    };
+
+    let mut unresolved_axes: Vec<(&'static str, String)> = Vec::new();
+    for text in [user_request, plan_text] {
+        for m in find_relative_phrases(text) {
+            if !bases.contains_key(m.base_axis)
+                && !unresolved_axes.iter().any(|(axis, _)| *axis == m.base_axis)
+            {
+                unresolved_axes.push((m.base_axis, m.phrase));
+            }
+        }
+    }
+    for (axis, phrase) in unresolved_axes {
+        resolution.questions.push(format!(
+            "What is the overall {} in mm? \"{}\" needs it to size the feature.",
+            axis,
+            phrase.trim()
+        ));
+    }
+
+    let section = build_plan_range(plan_text);
+    let mut rewritten = String::with_capacity(plan_text.len());
+    let mut last = section.start;
+    rewritten.push_str(&plan_text[..section.start]);
+    for m in find_relative_phrases(&plan_text[section.clone()]) {
+        let Some(base) = bases.get(m.base_axis) else {
+            continue;
+        };
+        let value = format_mm(m.factor * base);
+        let replacement = match m.target_adjective {
+            Some(adjective) => format!("{} {}", value, adjective),
+            None => value.clone(),
+        };
+        let start = section.start + m.range.start;
+        rewritten.push_str(&plan_text[last..start]);
+        rewritten.push_str(&replacement);
+        last = section.start + m.range.end;
+        resolution.notes.push(format!(
+            "Note: resolved \"{}\" to {} ({} {})",
+            m.phrase.trim(),
+            value,
+            m.base_axis,
+            format_mm(*base)
+        ));
+    }
+    rewritten.push_str(&plan_text[last..]);
+    resolution.plan_text = rewritten;
+    resolution
+}
+
 // ---------------------------------------------------------------------------
 // Feedback and re-prompt
 // ---------------------------------------------------------------------------
@@ -1380,6 +2353,80 @@ fn parse_triage_response(response: &str) -> PromptAnalysis {
 // Geometry advisor
 // ---------------------------------------------------------------------------
 
+/// Whether the plan has a Build Plan section but no numbered steps in it,
+/// which `request_numbered_build_steps` can fix without a full re-plan.
+pub fn build_plan_lacks_numbered_steps(plan_text: &str) -> bool {
+    has_section(plan_text, "Build Plan") && extract_build_plan_steps_text(plan_text).is_none()
+}
+
+/// `plan_text` with the body of its Build Plan section replaced by `steps`,
+/// numbered from 1. Other sections are kept as they are.
+fn replace_build_plan_steps(plan_text: &str, steps: &[String]) -> String {
+    let numbered: String = steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| format!("{}. {}\n", i + 1, step))
+        .collect();
+    let heading_re = Regex::new(r"(?im)^#{2,3}\s+build plan\s*$").unwrap();
+    let Some(heading) = heading_re.find(plan_text) else {
+        return format!("{}\n\n### Build Plan\n{}", plan_text.trim_end(), numbered);
+    };
+    let body_start = plan_text[heading.end()..]
+        .find('\n')
+        .map(|p| heading.end() + p + 1)
+        .unwrap_or(plan_text.len());
+    let next_heading_re = Regex::new(r"(?m)^#{2,3}\s+\S").unwrap();
+    let body_end = next_heading_re
+        .find(&plan_text[body_start..])
+        .map(|m| body_start + m.start());
+    let mut out = plan_text[..heading.end()].to_string();
+    out.push('\n');
+    out.push_str(&numbered);
+    if let Some(end) = body_end {
+        out.push('\n');
+        out.push_str(&plan_text[end..]);
+    }
+    out.trim_end().to_string()
+}
+
+/// Ask the advisor to restate only the Build Plan as numbered steps and
+/// splice them into `plan_text`. Cheaper than a full re-plan when the rest of
+/// the plan is usable. `None` when the reply still has no numbered steps.
+pub async fn request_numbered_build_steps(
+    provider: Box<dyn AiProvider>,
+    user_request: &str,
+    plan_text: &str,
+) -> Result<(Option<String>, Option<TokenUsage>), AppError> {
+    let messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: GEOMETRY_ADVISOR_PROMPT.to_string(),
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: user_request.to_string(),
+        },
+        ChatMessage {
+            role: "assistant".to_string(),
+            content: plan_text.to_string(),
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: "Your Build Plan section has no numbered steps. Rewrite ONLY the Build Plan \
+                      as numbered steps (`1.`, `2.`, `3.`, ...), one operation per step, keeping \
+                      its shapes and dimensions. Output only the numbered steps."
+                .to_string(),
+        },
+    ];
+
+    let (reply, usage) = provider.complete(&messages, Some(1024)).await?;
+    let steps = extract_numbered_steps(&reply);
+    if steps.is_empty() {
+        return Ok((None, usage));
+    }
+    Ok((Some(replace_build_plan_steps(plan_text, &steps)), usage))
+}
+
 /// Call the AI to produce a geometry design plan for the user's request.
 ///
 /// This is the "design-first" phase that runs before code generation,
@@ -1512,6 +2559,89 @@ mod tests {
         assert!(!has_section(text, "Build Plan"));
     }
 
+    #[test]
+    fn test_parse_plan_to_struct_canonical_plan() {
+        let text = "### Object Analysis\nA 60x40x25mm enclosure with a lid.\n\n\
+            ### CadQuery Approach\nExtruded box with a boolean cavity.\n\n\
+            ### Build Plan\n1. Extrude a 60x40x25mm box.\n2. Cut a 56x36x23mm cavity from the top.\n\
+            3. Fillet vertical outer edges 3mm.\n\n\
+            ### Approximation Notes\nCorner radii approximate the molded look.";
+        let plan = parse_plan_to_struct(text);
+        assert_eq!(
+            plan.object_analysis.as_deref(),
+            Some("A 60x40x25mm enclosure with a lid.")
+        );
+        assert_eq!(
+            plan.cad_approach.as_deref(),
+            Some("Extruded box with a boolean cavity.")
+        );
+        assert_eq!(plan.build_steps.len(), 3);
+        assert_eq!(plan.build_steps[1], "Cut a 56x36x23mm cavity from the top.");
+        assert!(plan.approximation_notes.unwrap().contains("Corner radii"));
+    }
+
+    #[test]
+    fn test_parse_plan_to_struct_missing_sections_are_empty() {
+        let plan = parse_plan_to_struct("### Object Analysis\nA plain 20mm cube.");
+        assert_eq!(plan.object_analysis.as_deref(), Some("A plain 20mm cube."));
+        assert_eq!(plan.cad_approach, None);
+        assert!(plan.build_steps.is_empty());
+        assert_eq!(plan.approximation_notes, None);
+    }
+
+    const DIFF_BASE_PLAN: &str = "### Object Analysis\nA 60x40x25mm enclosure.\n\n\
+        ### CAD Approach\nExtruded box with a boolean cavity.\n\n\
+        ### Build Plan\n1. Extrude a 60x40x25mm box.\n2. Cut a 56x36x23mm cavity from the top.\n\n\
+        ### Approximation Notes\nNone.";
+
+    #[test]
+    fn test_diff_plans_detects_added_build_step() {
+        let new_plan = DIFF_BASE_PLAN.replace(
+            "from the top.\n",
+            "from the top.\n3. Chamfer the top edges 1mm.\n",
+        );
+        let diff = diff_plans(DIFF_BASE_PLAN, &new_plan);
+        assert_eq!(diff.added_steps, vec!["Chamfer the top edges 1mm."]);
+        assert!(diff.removed_steps.is_empty());
+        assert!(diff.changed_steps.is_empty());
+        assert_eq!(diff.sections.len(), 1);
+        let build_plan = &diff.sections[0];
+        assert_eq!(build_plan.section, "Build Plan");
+        assert_eq!(build_plan.added_operations, vec!["chamfer"]);
+        assert_eq!(
+            build_plan.dimension_changes,
+            vec![DimensionDelta {
+                old: None,
+                new: Some(1.0)
+            }]
+        );
+
+        let unchanged = diff_plans(DIFF_BASE_PLAN, DIFF_BASE_PLAN);
+        assert_eq!(unchanged, PlanDiff::default());
+    }
+
+    #[test]
+    fn test_diff_plans_detects_changed_dimension() {
+        let new_plan = DIFF_BASE_PLAN.replace("56x36x23mm", "56x36x21mm");
+        let diff = diff_plans(DIFF_BASE_PLAN, &new_plan);
+        assert!(diff.added_steps.is_empty());
+        assert!(diff.removed_steps.is_empty());
+        assert_eq!(diff.changed_steps.len(), 1);
+        let step = &diff.changed_steps[0];
+        assert_eq!(step.old, "Cut a 56x36x23mm cavity from the top.");
+        assert_eq!(step.new, "Cut a 56x36x21mm cavity from the top.");
+        assert!(step.added_operations.is_empty() && step.removed_operations.is_empty());
+        assert_eq!(
+            step.dimension_changes,
+            vec![DimensionDelta {
+                old: Some(23.0),
+                new: Some(21.0)
+            }]
+        );
+        assert_eq!(diff.sections.len(), 1);
+        assert_eq!(diff.sections[0].dimension_changes, step.dimension_changes);
+    }
+
     // -----------------------------------------------------------------------
     // Risk score tests
     // -----------------------------------------------------------------------
@@ -1655,6 +2785,29 @@ mod tests {
             .any(|w| w.contains("no concrete dimensions")));
     }
 
+    #[test]
+    fn test_validate_vague_sizing_in_steps() {
+        let text = "### Build Plan\n1. Extrude a 60x40x10mm plate.\n\
+            2. Cut an appropriate-sized hole in the center.";
+        let v = validate_plan(text);
+        assert!(!v
+            .warnings
+            .iter()
+            .any(|w| w.contains("no concrete dimensions")));
+        assert!(v
+            .warnings
+            .iter()
+            .any(|w| w.contains("vague sizing") && w.contains("appropriate-sized hole")));
+
+        assert_eq!(
+            vague_sizing_phrases(
+                "1. Add a reasonable wall thickness.\n2. Fillet radius as needed."
+            ),
+            vec!["reasonable wall thickness", "fillet radius as needed"]
+        );
+        assert!(vague_sizing_phrases("1. Cut a 5mm hole at a suitable location.").is_empty());
+    }
+
     #[test]
     fn test_validate_fillet_on_small_feature() {
         let text = "### Build Plan\n1. Create a 15x15x10mm bracket.\n2. Apply fillet 8mm on edges.";
@@ -1763,7 +2916,7 @@ max_overhang: 45
 "#,
         )
         .unwrap();
-        let result = format_manufacturing_constraints(&yaml);
+        let (result, _) = format_manufacturing_constraints(&yaml, &RuleBudget::default());
         assert!(result.contains("## Manufacturing Constraints"));
         assert!(result.contains("MUST respect"));
         assert!(result.contains("process"));
@@ -1791,7 +2944,7 @@ overhangs:
 "#,
         )
         .unwrap();
-        let result = format_manufacturing_constraints(&yaml);
+        let (result, _) = format_manufacturing_constraints(&yaml, &RuleBudget::default());
         assert!(result.contains("## Manufacturing Constraints"));
         assert!(result.contains("wall_thickness"));
         assert!(result.contains("minimum"));
@@ -1811,7 +2964,7 @@ overhangs:
     #[test]
     fn test_format_manufacturing_constraints_empty_mapping() {
         let yaml: serde_yaml::Value = serde_yaml::from_str("{}").unwrap();
-        let result = format_manufacturing_constraints(&yaml);
+        let (result, _) = format_manufacturing_constraints(&yaml, &RuleBudget::default());
         assert!(result.contains("## Manufacturing Constraints"));
         assert!(result.contains("MUST respect"));
         // Header present but no bullet items beyond it
@@ -1837,7 +2990,7 @@ overhangs:
             "size_classes".to_string(),
             vec!["Tiny: < 20mm".to_string(), "Small: 20-60mm".to_string()],
         );
-        let result = format_dimension_guidance(&guidance);
+        let (result, _) = format_dimension_guidance(&guidance, &RuleBudget::default());
         assert!(result.contains("## Dimension Estimation Guidance"));
         assert!(result.contains("Follow these rules when choosing dimensions"));
         assert!(result.contains("real-world objects"));
@@ -1862,7 +3015,7 @@ overhangs:
             "preemptive_warnings".to_string(),
             vec!["If you are about to use shell() after booleans, STOP".to_string()],
         );
-        let result = format_failure_prevention(&prevention);
+        let (result, _) = format_failure_prevention(&prevention, &RuleBudget::default());
         assert!(result.contains("## Failure Prevention Rules"));
         assert!(result.contains("Follow these rules when planning geometry"));
         assert!(result.contains("Self Diagnosis"));
@@ -1871,6 +3024,100 @@ overhangs:
         assert!(result.contains("shell() after booleans"));
     }
 
+    #[test]
+    fn test_rule_budget_keeps_rules_matching_the_process() {
+        let yaml: serde_yaml::Value = serde_yaml::from_str(
+            r#"
+fdm_overhangs: "FDM printing: keep overhangs under 45 degrees or add supports"
+fdm_layers: "FDM printing: walls are multiples of the 0.4mm layer line width"
+cnc_tool_access: "CNC milling: features must be reachable by the end mill from Z+"
+cnc_internal_radii: "CNC machining: internal corners need a radius >= 1.5mm"
+"#,
+        )
+        .unwrap();
+        let tags = process_intent_tags("cnc_it7");
+        let budget = RuleBudget::new(200, "mounting bracket with two holes", &tags);
+        let (result, stats) = format_manufacturing_constraints(&yaml, &budget);
+        assert!(result.contains("cnc_tool_access"));
+        assert!(result.contains("cnc_internal_radii"));
+        assert!(!result.contains("fdm_overhangs"));
+        assert!(!result.contains("fdm_layers"));
+        assert!(result.contains("_2 additional rules omitted to fit the prompt budget._"));
+        assert_eq!((stats.included, stats.omitted), (2, 2));
+
+        // The same budget for an FDM request keeps the FDM rules instead.
+        let budget = RuleBudget::new(200, "mounting bracket", &process_intent_tags("fdm"));
+        let (result, _) = format_manufacturing_constraints(&yaml, &budget);
+        assert!(result.contains("fdm_overhangs"));
+        assert!(!result.contains("cnc_tool_access"));
+    }
+
+    #[test]
+    fn test_rule_budget_truncates_deterministically() {
+        let mut prevention = std::collections::HashMap::new();
+        prevention.insert(
+            "self_diagnosis".to_string(),
+            vec!["a".repeat(40), "b".repeat(40), "c".repeat(40)],
+        );
+        prevention.insert("alternatives".to_string(), vec!["d".repeat(40)]);
+        // Each rule costs 43 characters: two fit in 100.
+        let budget = RuleBudget {
+            max_chars: 100,
+            ..Default::default()
+        };
+        let (first, stats) = format_failure_prevention(&prevention, &budget);
+        for _ in 0..5 {
+            assert_eq!(format_failure_prevention(&prevention, &budget).0, first);
+        }
+        assert_eq!((stats.included, stats.omitted), (2, 2));
+        // Categories are taken in name order, rules in file order.
+        assert!(first.contains("### Alternatives\n- dddd"));
+        assert!(first.contains("### Self Diagnosis\n- aaaa"));
+        assert!(!first.contains("bbbb"));
+        assert!(first.ends_with("_2 additional rules omitted to fit the prompt budget._\n"));
+
+        // A zero budget is unlimited.
+        let (all, stats) = format_failure_prevention(&prevention, &RuleBudget::default());
+        assert_eq!(stats.omitted, 0);
+        assert!(all.contains("cccc") && !all.contains("omitted"));
+    }
+
+    #[test]
+    fn test_rule_budget_skips_rules_that_do_not_fit() {
+        let mut prevention = std::collections::HashMap::new();
+        prevention.insert(
+            "self_diagnosis".to_string(),
+            vec!["a".repeat(120), "é".repeat(40), "b".repeat(40)],
+        );
+        let budget = RuleBudget {
+            max_chars: 100,
+            ..Default::default()
+        };
+        let (result, stats) = format_failure_prevention(&prevention, &budget);
+        // The oversized first rule is skipped, not the end of the section,
+        // and the budget counts characters, so both 43-character rules fit.
+        assert_eq!((stats.included, stats.omitted), (2, 1));
+        assert!(!result.contains("aaaa"));
+        assert!(result.contains("éééé"));
+        assert!(result.contains("bbbb"));
+    }
+
+    #[test]
+    fn test_builtin_presets_fit_default_rule_budgets() {
+        let config = AppConfig::default();
+        for preset in [None, Some("3d-printing"), Some("cnc")] {
+            let rules = crate::agent::rules::AgentRules::from_preset(preset).unwrap();
+            let context = format_rule_context(&rules, "a phone stand", &config);
+            assert_eq!(context.sections.len(), 3);
+            assert!(
+                context.sections.iter().all(|s| s.omitted == 0),
+                "preset {:?} exceeds the default budgets: {}",
+                preset,
+                context.summary()
+            );
+        }
+    }
+
     // -----------------------------------------------------------------------
     // New tests for validator improvements
     // -----------------------------------------------------------------------
@@ -1884,6 +3131,45 @@ overhangs:
         assert!(!dims.contains(&-3.0), "move -3mm should be skipped");
     }
 
+    #[test]
+    fn test_extract_dimensions_comma_decimals() {
+        let text = "Gehäuse 42,5x28x7,5mm mit 1,8 mm Wandstärke und Boden 2,25 mm.";
+        let dims = extract_dimensions(text);
+        for expected in [42.5, 28.0, 7.5, 1.8, 2.25] {
+            assert!(dims.contains(&expected), "missing {} in {:?}", expected, dims);
+        }
+        assert!(!dims.contains(&8.0), "1,8 must not be read as 8");
+    }
+
+    #[test]
+    fn test_extract_dimensions_ranges_and_thousands() {
+        let dims = extract_dimensions("Wall 1,5–2,0 mm, rail 1.234,5 mm long, rib 0.8-1.2mm.");
+        for expected in [1.5, 2.0, 1234.5, 0.8, 1.2] {
+            assert!(dims.contains(&expected), "missing {} in {:?}", expected, dims);
+        }
+        assert!(!dims.contains(&-1.2), "range end must not be read as negative");
+    }
+
+    #[test]
+    fn test_extract_dimensions_scientific_and_thousands() {
+        let dims = extract_dimensions("Wall 1,8mm, rib 1.8mm, span 1e2 mm, rail 1,000mm long.");
+        assert_eq!(dims.iter().filter(|d| **d == 1.8).count(), 2, "{:?}", dims);
+        assert!(dims.contains(&100.0), "missing 1e2 in {:?}", dims);
+        assert!(dims.contains(&1000.0), "missing 1,000 in {:?}", dims);
+        for wrong in [1.0, 2.0, 8.0] {
+            assert!(!dims.contains(&wrong), "misread {} in {:?}", wrong, dims);
+        }
+    }
+
+    #[test]
+    fn test_comma_decimal_plan_is_not_dimensionless() {
+        let plan = "### Object Analysis\nKleine Dose.\n\n### Build Plan\n1. Grundkörper 42,5 x 28,0 mm, Höhe 7,5 mm\n2. Wandstärke 1,8 mm\n";
+        let dims = extract_dimensions(plan);
+        assert!(dims.contains(&42.5) && dims.contains(&28.0) && dims.contains(&1.8));
+        assert!(extract_fillet_radii("fillet 0,5 mm on top").contains(&0.5));
+        assert!(extract_chamfer_sizes("chamfer: 1,25mm").contains(&1.25));
+    }
+
     #[test]
     fn test_extract_chamfer_sizes() {
         let text = "Apply chamfer 1.5mm on bottom edges and chamfer(0.5) on top.";
@@ -1931,6 +3217,36 @@ overhangs:
         assert!(!steps.contains("Notes."));
     }
 
+    #[test]
+    fn test_stepless_build_plan_gets_numbered_steps_spliced_in() {
+        let plan = "### Object Analysis\n- A flat bracket\n\n\
+                    ### Build Plan\n- Make a 40x20x5mm plate\n- Drill two holes\n\n\
+                    ### Approximation Notes\n- Holes are simple cylinders";
+        assert!(build_plan_lacks_numbered_steps(plan));
+        assert!(!build_plan_lacks_numbered_steps("### Object Analysis\n- A box"));
+
+        let steps = vec![
+            "Create a 40x20x5mm box".to_string(),
+            "Cut two 4mm holes".to_string(),
+        ];
+        let repaired = replace_build_plan_steps(plan, &steps);
+        assert!(!build_plan_lacks_numbered_steps(&repaired));
+        assert_eq!(
+            extract_build_plan_steps_text(&repaired).unwrap(),
+            "1. Create a 40x20x5mm box\n2. Cut two 4mm holes"
+        );
+        assert!(repaired.starts_with("### Object Analysis\n- A flat bracket\n\n### Build Plan\n"));
+        assert!(repaired.ends_with("### Approximation Notes\n- Holes are simple cylinders"));
+        assert!(!repaired.contains("Drill two holes"));
+
+        // A trailing Build Plan keeps no stray text after the steps.
+        let trailing = replace_build_plan_steps("### Build Plan\n- plate", &steps);
+        assert_eq!(
+            trailing,
+            "### Build Plan\n1. Create a 40x20x5mm box\n2. Cut two 4mm holes"
+        );
+    }
+
     #[test]
     fn test_validate_ignores_reasoning_outside_numbered_steps() {
         let text = "### Object Analysis\nbooleans booleans booleans cut union fuse intersect.\n\n\
@@ -2197,4 +3513,237 @@ overhangs:
         let v_balanced = validate_plan_with_profile(text, &GenerationReliabilityProfile::Balanced);
         assert!(v_balanced.is_valid);
     }
+
+    // -----------------------------------------------------------------------
+    // Proportional dimensions
+    // -----------------------------------------------------------------------
+
+    const PEBBLE_PLAN: &str = "### Object Analysis\nA pebble-shaped paperweight.\n\n\
+        ### CAD Approach\nExtrude and fillet.\n\n\
+        ### Build Plan\n1. Create a 90x60x20mm rounded body.\n\
+        2. Cut a recessed oval roughly 1/3 of the width, 1 mm deep.\n\
+        3. Add a groove at half the height.";
+
+    #[test]
+    fn test_proportional_fraction_resolves_against_plan_dimensions() {
+        let r = resolve_proportional_dimensions(PEBBLE_PLAN, "pebble paperweight with a logo");
+        assert!(r
+            .plan_text
+            .contains("recessed oval roughly 20 mm, 1 mm deep"));
+        assert!(r.plan_text.contains("groove at 10 mm."));
+        assert!(r.questions.is_empty());
+        assert_eq!(r.notes.len(), 2);
+        assert!(
+            r.notes[0].starts_with("Note: resolved \"1/3 of the width\" to 20 mm (width 60 mm)")
+        );
+    }
+
+    #[test]
+    fn test_proportional_multiple_and_comparative() {
+        let plan = "### Build Plan\n1. Base plate twice as long as it is wide.\n\
+            2. Boss with three times the thickness.";
+        let r = resolve_proportional_dimensions(plan, "plate 40 mm wide, thickness 3 mm");
+        assert!(r.plan_text.contains("Base plate 80 mm long."));
+        assert!(r.plan_text.contains("Boss with 9 mm."));
+        assert!(r.questions.is_empty());
+    }
+
+    #[test]
+    fn test_proportional_percentage_and_words() {
+        let plan = "### Build Plan\n1. Slot 25% of the length.\n2. Rib a third of the height.";
+        let r = resolve_proportional_dimensions(plan, "box length 120 mm, 45 mm tall");
+        assert!(r.plan_text.contains("Slot 30 mm."));
+        assert!(r.plan_text.contains("Rib 15 mm."));
+    }
+
+    #[test]
+    fn test_proportional_rewrites_only_build_plan() {
+        let plan = "### Object Analysis\nLogo is 1/3 of the width.\n\n\
+            ### Build Plan\n1. Make a 30x30x5mm block.\n2. Recess 1/3 of the width.\n\n\
+            ### Approximation Notes\nAbout 1/3 of the width.";
+        let r = resolve_proportional_dimensions(plan, "");
+        assert!(r.plan_text.contains("Logo is 1/3 of the width."));
+        assert!(r.plan_text.contains("2. Recess 10 mm."));
+        assert!(r.plan_text.contains("About 1/3 of the width."));
+    }
+
+    #[test]
+    fn test_unresolvable_proportion_asks_for_base_dimension() {
+        let plan = "### Build Plan\n1. Make a rounded body.\n2. Recess 1/3 of the width.";
+        let r = resolve_proportional_dimensions(
+            plan,
+            "the logo area is a recessed oval roughly 1/3 of the width",
+        );
+        assert_eq!(r.plan_text, plan);
+        assert!(r.notes.is_empty());
+        assert_eq!(r.questions.len(), 1);
+        assert!(r.questions[0].contains("overall width"));
+    }
+
+    #[test]
+    fn test_parse_proportion_factor_phrasings() {
+        for (text, expected) in [
+            ("1/3", 1.0 / 3.0),
+            ("half", 0.5),
+            ("two thirds", 2.0 / 3.0),
+            ("25%", 0.25),
+            ("12,5 percent", 0.125),
+            ("twice", 2.0),
+            ("1.5 times", 1.5),
+            ("3x", 3.0),
+        ] {
+            let v = parse_proportion_factor(text).unwrap();
+            assert!((v - expected).abs() < 1e-9, "{}", text);
+        }
+        assert!(parse_proportion_factor("1/0").is_none());
+    }
+
+    const ORGANIC_PLAN_BASE: &str = "### Object Analysis\nA mouse shell 110x60x35mm.\n\n\
+        ### CAD Approach\nBox base with fillets.\n\n\
+        ### Build Plan\n1. Extrude a 110x60x35mm box.\n2. Fillet top edges 8mm.";
+
+    #[test]
+    fn test_organic_request_without_notes_gets_extra_risk() {
+        let request = "an ergonomic computer mouse with curved sides";
+        assert_eq!(organic_cues(request, ""), vec!["curved", "ergonomic"]);
+
+        let mut validation =
+            validate_plan_with_profile(ORGANIC_PLAN_BASE, &GenerationReliabilityProfile::Balanced);
+        let base_risk = validation.risk_score;
+        apply_organic_approximation_check(
+            &mut validation,
+            request,
+            &GenerationReliabilityProfile::Balanced,
+            2,
+        );
+        assert_eq!(validation.risk_score, base_risk + 2);
+        assert!(validation
+            .warnings
+            .iter()
+            .any(|w| w.contains("no 'Approximation Notes' section")));
+
+        // Same request, but the plan explains its approximation.
+        let with_notes = format!(
+            "{}\n\n### Approximation Notes\nCurved sides approximated by large fillets.",
+            ORGANIC_PLAN_BASE
+        );
+        let mut validation =
+            validate_plan_with_profile(&with_notes, &GenerationReliabilityProfile::Balanced);
+        let base_risk = validation.risk_score;
+        apply_organic_approximation_check(
+            &mut validation,
+            request,
+            &GenerationReliabilityProfile::Balanced,
+            2,
+        );
+        assert_eq!(validation.risk_score, base_risk);
+    }
+
+    #[test]
+    fn test_prismatic_request_or_zero_risk_is_inert() {
+        let plan = ORGANIC_PLAN_BASE.replace("A mouse shell", "A mounting block");
+        let mut validation =
+            validate_plan_with_profile(&plan, &GenerationReliabilityProfile::Balanced);
+        let before = (validation.risk_score, validation.warnings.len());
+        apply_organic_approximation_check(
+            &mut validation,
+            "a rectangular mounting block with two M4 holes",
+            &GenerationReliabilityProfile::Balanced,
+            2,
+        );
+        assert_eq!((validation.risk_score, validation.warnings.len()), before);
+
+        let mut validation =
+            validate_plan_with_profile(ORGANIC_PLAN_BASE, &GenerationReliabilityProfile::Balanced);
+        let before = validation.risk_score;
+        apply_organic_approximation_check(
+            &mut validation,
+            "an organic vase",
+            &GenerationReliabilityProfile::Balanced,
+            0,
+        );
+        assert_eq!(validation.risk_score, before);
+    }
+
+    #[test]
+    fn test_organic_extra_risk_can_reject_borderline_plan() {
+        let mut validation = validate_plan_with_profile(
+            ORGANIC_PLAN_BASE,
+            &GenerationReliabilityProfile::ReliabilityFirst,
+        );
+        // Borderline plan: valid, just under the reliability-first threshold.
+        validation.is_valid = true;
+        validation.rejected_reason = None;
+        validation.risk_score = 4;
+        apply_organic_approximation_check(
+            &mut validation,
+            "smooth blend between handle and body",
+            &GenerationReliabilityProfile::ReliabilityFirst,
+            2,
+        );
+        assert_eq!(validation.risk_score, 6);
+        assert!(!validation.is_valid);
+        assert!(validation
+            .rejected_reason
+            .as_deref()
+            .unwrap()
+            .contains("smooth blend"));
+    }
+
+    #[test]
+    fn test_operation_budget_warns_then_rejects_repeated_features() {
+        let plan = "### Object Analysis\nA heat sink.\n\n\
+            ### CAD Approach\nExtruded base with fins.\n\n\
+            ### Build Plan\n1. Extrude a 100x80x5mm base plate.\n\
+            2. Add cooling fins ×40 along the top face.\n\
+            3. Cut an array of 4 mounting holes.";
+        let mut validation =
+            validate_plan_with_profile(plan, &GenerationReliabilityProfile::Balanced);
+        assert!(validation.is_valid);
+        apply_operation_budget(&mut validation, 22, 60);
+        assert_eq!(validation.risk_signals.estimated_operations, Some(45));
+        assert!(validation.is_valid);
+        assert!(validation
+            .warnings
+            .iter()
+            .any(|w| w.contains("soft budget of 22")));
+
+        let mut validation =
+            validate_plan_with_profile(plan, &GenerationReliabilityProfile::Balanced);
+        apply_operation_budget(&mut validation, 22, 40);
+        assert!(!validation.is_valid);
+        assert!(validation
+            .rejected_reason
+            .as_deref()
+            .unwrap()
+            .contains("representative instances"));
+    }
+
+    #[test]
+    fn test_suggest_profile_from_request_cues() {
+        let organic = suggest_profile("an organic ergonomic handle for a kitchen drawer");
+        assert_eq!(
+            organic.profile,
+            GenerationReliabilityProfile::ReliabilityFirst
+        );
+        assert!(organic.rationale.contains("ergonomic"));
+
+        let simple = suggest_profile("a simple box 40x30x20mm");
+        assert_eq!(simple.profile, GenerationReliabilityProfile::FidelityFirst);
+        assert!(simple.rationale.contains("box"));
+
+        let lofted = suggest_profile("loft a square base into a round top");
+        assert_eq!(
+            lofted.profile,
+            GenerationReliabilityProfile::ReliabilityFirst
+        );
+
+        let vague = suggest_profile("a desk lamp arm");
+        assert_eq!(vague.profile, GenerationReliabilityProfile::Balanced);
+        // "gearbox" is not a box.
+        assert_eq!(
+            suggest_profile("a gearbox").profile,
+            GenerationReliabilityProfile::Balanced
+        );
+    }
 }
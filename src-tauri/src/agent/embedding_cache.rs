@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::telemetry::hash_request;
+use crate::error::AppError;
+
+/// Bumped when the on-disk layout changes; files with another version are discarded.
+const CACHE_FORMAT_VERSION: u32 = 1;
+/// Upper bound on stored vectors. Least recently used entries are evicted first.
+pub const MAX_CACHE_ENTRIES: usize = 2048;
+/// Minimum time between writes of the global cache. Every retrieval embeds
+/// its query, so saving after each one would rewrite the whole file per run;
+/// whatever is left unsaved is flushed on exit.
+const PERSIST_INTERVAL: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedVector {
+    vector: Vec<f32>,
+    last_used: u64,
+}
+
+/// Content hash → embedding vector, for a single embedding model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingCache {
+    version: u32,
+    model: String,
+    #[serde(default)]
+    clock: u64,
+    #[serde(default)]
+    entries: HashMap<String, CachedVector>,
+    #[serde(skip, default = "default_max_entries")]
+    max_entries: usize,
+    #[serde(skip)]
+    dirty: bool,
+    #[serde(skip)]
+    last_saved: Option<Instant>,
+}
+
+fn default_max_entries() -> usize {
+    MAX_CACHE_ENTRIES
+}
+
+fn content_key(text: &str) -> String {
+    format!("{}:{}", text.len(), hash_request(text))
+}
+
+impl EmbeddingCache {
+    pub fn new(model: &str) -> Self {
+        Self::with_capacity(model, MAX_CACHE_ENTRIES)
+    }
+
+    pub fn with_capacity(model: &str, max_entries: usize) -> Self {
+        Self {
+            version: CACHE_FORMAT_VERSION,
+            model: model.to_string(),
+            clock: 0,
+            entries: HashMap::new(),
+            max_entries: max_entries.max(1),
+            dirty: false,
+            last_saved: None,
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Whether entries changed since the last load or save.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Whether a debounced save should write now: there are unsaved entries
+    /// and the last save is at least `PERSIST_INTERVAL` old.
+    fn save_due(&self, now: Instant) -> bool {
+        self.dirty
+            && !matches!(self.last_saved, Some(saved) if now.duration_since(saved) < PERSIST_INTERVAL)
+    }
+
+    pub fn contains(&self, text: &str) -> bool {
+        self.entries.contains_key(&content_key(text))
+    }
+
+    /// Cached vector for `text`, marking it as recently used.
+    pub fn get(&mut self, text: &str) -> Option<Vec<f32>> {
+        self.clock += 1;
+        let clock = self.clock;
+        let entry = self.entries.get_mut(&content_key(text))?;
+        entry.last_used = clock;
+        Some(entry.vector.clone())
+    }
+
+    pub fn insert(&mut self, text: &str, vector: Vec<f32>) {
+        self.clock += 1;
+        self.entries.insert(
+            content_key(text),
+            CachedVector {
+                vector,
+                last_used: self.clock,
+            },
+        );
+        self.dirty = true;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        let excess = self.entries.len().saturating_sub(self.max_entries);
+        if excess == 0 {
+            return;
+        }
+        let mut by_age: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        by_age.sort();
+        for (_, key) in by_age.into_iter().take(excess) {
+            self.entries.remove(&key);
+        }
+    }
+
+    /// Load the cache at `path`, starting empty when the file is missing,
+    /// unreadable, from another format version or for another model.
+    pub fn load(path: &Path, model: &str) -> Self {
+        let loaded = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<EmbeddingCache>(&text).ok());
+        match loaded {
+            Some(cache) if cache.version == CACHE_FORMAT_VERSION && cache.model == model => {
+                let mut cache = cache;
+                cache.evict();
+                cache
+            }
+            _ => Self::new(model),
+        }
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<(), AppError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        self.dirty = false;
+        self.last_saved = Some(Instant::now());
+        Ok(())
+    }
+}
+
+pub fn cache_path() -> Result<PathBuf, AppError> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| AppError::ConfigError("Cannot find app data directory".into()))?;
+    Ok(data_dir.join("cadai-studio").join("embedding-cache.json"))
+}
+
+static EMBEDDING_CACHE: OnceLock<Mutex<EmbeddingCache>> = OnceLock::new();
+
+/// Process-wide cache, loaded from disk on first use.
+pub fn global_cache(model: &str) -> &'static Mutex<EmbeddingCache> {
+    let cache = EMBEDDING_CACHE.get_or_init(|| {
+        Mutex::new(match cache_path() {
+            Ok(path) => EmbeddingCache::load(&path, model),
+            Err(_) => EmbeddingCache::new(model),
+        })
+    });
+    if let Ok(mut guard) = cache.lock() {
+        if guard.model() != model {
+            *guard = EmbeddingCache::new(model);
+        }
+    }
+    cache
+}
+
+/// Write the global cache to disk if it has unsaved entries and was not
+/// saved within `PERSIST_INTERVAL`.
+pub fn persist(cache: &Mutex<EmbeddingCache>) -> Result<(), AppError> {
+    let mut guard = cache
+        .lock()
+        .map_err(|_| AppError::ConfigError("Embedding cache lock poisoned".into()))?;
+    if !guard.save_due(Instant::now()) {
+        return Ok(());
+    }
+    guard.save(&cache_path()?)
+}
+
+/// Write any unsaved entries of the global cache, e.g. on app exit.
+/// Does nothing when the cache was never used this session.
+pub fn flush() -> Result<(), AppError> {
+    let Some(cache) = EMBEDDING_CACHE.get() else {
+        return Ok(());
+    };
+    let mut guard = cache
+        .lock()
+        .map_err(|_| AppError::ConfigError("Embedding cache lock poisoned".into()))?;
+    if !guard.is_dirty() {
+        return Ok(());
+    }
+    guard.save(&cache_path()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut cache = EmbeddingCache::with_capacity("m", 2);
+        cache.insert("a", vec![1.0]);
+        cache.insert("b", vec![2.0]);
+        assert_eq!(cache.get("a"), Some(vec![1.0]));
+        cache.insert("c", vec![3.0]);
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("b"));
+        assert!(cache.contains("c"));
+    }
+
+    #[test]
+    fn test_save_is_debounced_until_interval_passes() {
+        let path = std::env::temp_dir().join(format!(
+            "cadai-embedding-cache-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let mut cache = EmbeddingCache::new("m");
+        let start = Instant::now();
+        assert!(!cache.save_due(start));
+
+        cache.insert("a", vec![1.0]);
+        assert!(cache.save_due(start));
+        cache.save(&path).unwrap();
+
+        cache.insert("b", vec![2.0]);
+        let saved = cache.last_saved.unwrap();
+        assert!(!cache.save_due(saved + Duration::from_secs(1)));
+        assert!(cache.save_due(saved + PERSIST_INTERVAL));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_cache_roundtrip_is_versioned_by_model() {
+        let path = std::env::temp_dir().join(format!(
+            "cadai-embedding-cache-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let mut cache = EmbeddingCache::new("text-embedding-3-small");
+        cache.insert("shell enclosure", vec![0.5, 0.25]);
+        assert!(cache.is_dirty());
+        cache.save(&path).unwrap();
+        assert!(!cache.is_dirty());
+
+        let mut same = EmbeddingCache::load(&path, "text-embedding-3-small");
+        assert_eq!(same.get("shell enclosure"), Some(vec![0.5, 0.25]));
+
+        let other = EmbeddingCache::load(&path, "text-embedding-3-large");
+        assert!(!other.contains("shell enclosure"));
+        assert_eq!(other.model(), "text-embedding-3-large");
+
+        std::fs::write(
+            &path,
+            "{\"version\":0,\"model\":\"text-embedding-3-small\"}",
+        )
+        .unwrap();
+        assert!(!EmbeddingCache::load(&path, "text-embedding-3-small").contains("shell enclosure"));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,18 +1,27 @@
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::time::Duration;
 
 use base64::Engine;
+use futures_util::future::{BoxFuture, FutureExt};
 use regex::Regex;
 use serde::Serialize;
+use schemars::JsonSchema;
 use tokio::time::timeout;
-use uuid::Uuid;
 
+use crate::agent::complexity;
+use crate::agent::repair_examples::{self, RepairExampleUse};
+use crate::agent::revalidation::{self, RevalidationCache, RevalidationScope};
 use crate::agent::rules::AgentRules;
 use crate::agent::static_validate;
+use crate::agent::telemetry;
+use crate::agent::threads;
 use crate::agent::validate;
+use crate::agent::validation_history::{AttemptHistory, AttemptRecord};
+use crate::agent::views;
 use crate::ai::message::ChatMessage;
 use crate::ai::provider::TokenUsage;
-use crate::commands::chat::{build_retry_prompt, create_provider};
+use crate::commands::chat::{build_retry_prompt, create_code_provider, escalation_config};
 use crate::config::AppConfig;
 use crate::error::AppError;
 use crate::python::runner;
@@ -20,14 +29,28 @@ use crate::python::runner;
 const EXECUTION_TIMEOUT_SECS: u64 = 30;
 
 /// Everything the executor needs to run and validate code.
+#[derive(Clone)]
 pub struct ExecutionContext {
     pub venv_dir: PathBuf,
     pub runner_script: PathBuf,
     pub config: AppConfig,
 }
 
+type ExecuteFn<'a> =
+    dyn Fn(&str) -> BoxFuture<'static, Result<runner::ExecutionResult, String>> + Send + Sync + 'a;
+type PostCheckFn<'a> =
+    dyn Fn(&str) -> Result<PostGeometryValidationReport, String> + Send + Sync + 'a;
+
+/// The Python-backed steps of a validation attempt and the cache that lets
+/// them be skipped, passed in so tests can count real executions.
+struct ValidationSteps<'a> {
+    cache: &'a Mutex<RevalidationCache>,
+    execute: &'a ExecuteFn<'a>,
+    post_check: &'a PostCheckFn<'a>,
+}
+
 /// Geometry quality report emitted after successful execution.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct PostGeometryValidationReport {
     pub watertight: bool,
     pub manifold: bool,
@@ -38,6 +61,12 @@ pub struct PostGeometryValidationReport {
     pub bounds_min: [f64; 3],
     pub bounds_max: [f64; 3],
     pub volume: f64,
+    /// Total mesh surface area (mm^2).
+    pub surface_area: f64,
+    /// Center of mass in part coordinates, when the mesh is closed.
+    pub center_of_mass: Option<[f64; 3]>,
+    /// Inertia tensor about the center of mass at unit density (mm^5).
+    pub unit_inertia: Option<[[f64; 3]; 3]>,
     pub bbox_ok: bool,
     pub warnings: Vec<String>,
 }
@@ -55,6 +84,12 @@ pub struct ValidationResult {
     pub post_geometry_report: Option<PostGeometryValidationReport>,
     pub post_check_warning: Option<String>,
     pub retry_ladder_stage_reached: Option<u32>,
+    /// Model used for the last-chance repair, when escalation kicked in.
+    pub escalated_model: Option<String>,
+    /// Stored repair examples injected into repair prompts, with outcomes.
+    pub repair_example_uses: Vec<RepairExampleUse>,
+    /// Code, error and repair of every attempt, with diffs between them.
+    pub attempt_history: Vec<AttemptRecord>,
 }
 
 /// Progress events emitted during the validation loop.
@@ -64,10 +99,13 @@ pub enum ValidationEvent {
         attempt: u32,
         max_attempts: u32,
         message: String,
+        revalidation_scope: RevalidationScope,
     },
     StaticValidation {
         passed: bool,
         findings: Vec<String>,
+        /// Geometric operations counted in the validated script.
+        operation_count: Option<u32>,
     },
     Success {
         attempt: u32,
@@ -85,12 +123,39 @@ pub enum ValidationEvent {
     PostGeometryWarning {
         message: String,
     },
+    ModelEscalation {
+        from_model: String,
+        to_model: String,
+        message: String,
+    },
+    /// A repair changed the code between `attempt` and the next attempt.
+    AttemptDiff {
+        attempt: u32,
+        additions: usize,
+        deletions: usize,
+    },
+    /// Why `attempt` failed and what the retry changes, when
+    /// `explain_retries` is set.
+    FixExplanation {
+        attempt: u32,
+        explanation: String,
+    },
 }
 
 fn configured_max_attempts(config: &AppConfig) -> u32 {
     config.max_validation_attempts.clamp(1, 8)
 }
 
+/// Config for the AI fix that feeds the final validation attempt.
+///
+/// Returns the escalation-model config only when the next attempt is the last one.
+fn last_chance_config(config: &AppConfig, attempt: u32, max_attempts: u32) -> Option<AppConfig> {
+    if attempt + 1 != max_attempts {
+        return None;
+    }
+    escalation_config(config)
+}
+
 /// Run CAD code through `runner.py` with a timeout, using an isolated temp directory.
 ///
 /// Safe for concurrent execution — each call gets its own temp subdirectory.
@@ -718,6 +783,23 @@ fn maybe_apply_ladder_auto_repair(
     None
 }
 
+/// Strategy for the retry after a failed `attempt`. When the previous attempt
+/// failed with the same kernel error in the same operation, switch to
+/// replacing that operation; the flag reports whether that happened.
+fn select_retry_strategy(
+    previous_failure: Option<&validate::StructuredError>,
+    error: &validate::StructuredError,
+    attempt: u32,
+    code: &str,
+) -> (validate::RetryStrategy, bool) {
+    match previous_failure {
+        Some(previous) if validate::is_repeated_kernel_failure(previous, error) => {
+            (validate::simplify_operation_strategy(error), true)
+        }
+        _ => (validate::get_retry_strategy(error, attempt, Some(code)), false),
+    }
+}
+
 fn build_retry_prompt_with_findings(
     code: &str,
     runtime_error: &str,
@@ -752,7 +834,172 @@ fn build_retry_prompt_with_findings(
     prompt
 }
 
-fn run_post_geometry_checks(
+/// Triangle count of an STL: the header count for binary files, facets for ASCII.
+pub(crate) fn stl_triangle_count(stl: &[u8]) -> u64 {
+    if stl.len() >= 84 {
+        let count = u32::from_le_bytes([stl[80], stl[81], stl[82], stl[83]]) as u64;
+        if stl.len() as u64 == 84 + 50 * count {
+            return count;
+        }
+    }
+    String::from_utf8_lossy(stl).matches("facet normal").count() as u64
+}
+
+/// Vertex coordinates closer than this (in mm) hash the same.
+const GEOMETRY_HASH_TOLERANCE: f64 = 1e-3;
+
+/// Triangles of a binary or ASCII STL as nine coordinates each.
+fn stl_triangles(stl: &[u8]) -> Vec<[f64; 9]> {
+    if stl.len() >= 84 {
+        let count = u32::from_le_bytes([stl[80], stl[81], stl[82], stl[83]]) as usize;
+        if stl.len() == 84 + 50 * count {
+            return stl[84..]
+                .chunks_exact(50)
+                .map(|facet| {
+                    let mut tri = [0.0; 9];
+                    for (i, value) in tri.iter_mut().enumerate() {
+                        let at = 12 + i * 4;
+                        *value = f32::from_le_bytes([
+                            facet[at],
+                            facet[at + 1],
+                            facet[at + 2],
+                            facet[at + 3],
+                        ]) as f64;
+                    }
+                    tri
+                })
+                .collect();
+        }
+    }
+    let vertices: Vec<f64> = String::from_utf8_lossy(stl)
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("vertex"))
+        .flat_map(|coords| {
+            coords
+                .split_whitespace()
+                .map(|v| v.parse::<f64>().unwrap_or(0.0))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    vertices
+        .chunks_exact(9)
+        .map(|c| {
+            let mut tri = [0.0; 9];
+            tri.copy_from_slice(c);
+            tri
+        })
+        .collect()
+}
+
+/// Stable fingerprint of a mesh: vertices rounded to `GEOMETRY_HASH_TOLERANCE`,
+/// each triangle rotated to start at its smallest vertex (keeping winding),
+/// and triangles sorted, so facet order and float noise don't change it.
+/// `None` for an empty or unreadable mesh.
+pub(crate) fn geometry_hash(stl: &[u8]) -> Option<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut triangles: Vec<[[i64; 3]; 3]> = stl_triangles(stl)
+        .iter()
+        .map(|tri| {
+            let vertex = |i: usize| {
+                [0, 1, 2].map(|axis| (tri[i * 3 + axis] / GEOMETRY_HASH_TOLERANCE).round() as i64)
+            };
+            let vertices = [vertex(0), vertex(1), vertex(2)];
+            let first = (0..3).min_by_key(|&i| vertices[i]).unwrap_or(0);
+            [0, 1, 2].map(|i| vertices[(first + i) % 3])
+        })
+        .collect();
+    if triangles.is_empty() {
+        return None;
+    }
+    triangles.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    for value in triangles.iter().flatten().flatten() {
+        hasher.update(value.to_le_bytes());
+    }
+    let digest = hasher.finalize();
+    Some(digest[..8].iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// `geometry_hash` of a base64-encoded STL, as carried by `stl_base64` fields.
+pub(crate) fn geometry_hash_base64(stl_base64: Option<&str>) -> Option<String> {
+    let stl = base64::engine::general_purpose::STANDARD
+        .decode(stl_base64?)
+        .ok()?;
+    geometry_hash(&stl)
+}
+
+/// Axis-aligned bounding box of a base64-encoded STL; `None` when empty.
+pub(crate) fn stl_bounds_base64(stl_base64: &str) -> Option<views::Bounds> {
+    let stl = base64::engine::general_purpose::STANDARD
+        .decode(stl_base64)
+        .ok()?;
+    let triangles = stl_triangles(&stl);
+    let mut vertices = triangles.iter().flat_map(|tri| tri.chunks_exact(3));
+    let first = vertices.next()?;
+    let mut bounds = views::Bounds {
+        min: [first[0], first[1], first[2]],
+        max: [first[0], first[1], first[2]],
+    };
+    for v in vertices {
+        for (axis, &value) in v.iter().enumerate() {
+            bounds.min[axis] = bounds.min[axis].min(value);
+            bounds.max[axis] = bounds.max[axis].max(value);
+        }
+    }
+    Some(bounds)
+}
+
+/// Preview copy of `stl` within `max_triangles` (0 disables), decimated by
+/// `manufacturing.py decimate` with the bounding box preserved. Returns the
+/// input unchanged when it is already within budget. Exports re-run the
+/// code and never see the decimated mesh.
+pub(crate) fn decimate_preview_stl(
+    stl: &[u8],
+    max_triangles: u32,
+    ctx: &ExecutionContext,
+) -> Result<Vec<u8>, String> {
+    if max_triangles == 0 || stl_triangle_count(stl) <= max_triangles as u64 {
+        return Ok(stl.to_vec());
+    }
+    let script = crate::commands::find_python_script("manufacturing.py")
+        .map_err(|e| format!("cannot find manufacturing.py: {}", e))?;
+
+    let temp_dir = crate::artifacts::scratch_dir("decimate")
+        .map_err(|e| format!("failed to create decimation temp dir: {}", e))?;
+    let input = temp_dir.join("full.stl");
+    let output = temp_dir.join("preview.stl");
+
+    let result = (|| {
+        std::fs::write(&input, stl).map_err(|e| format!("failed to write preview STL: {}", e))?;
+        let input_s = input.to_string_lossy().to_string();
+        let output_s = output.to_string_lossy().to_string();
+        let budget = max_triangles.to_string();
+        let args: Vec<&str> = vec!["decimate", &input_s, &output_s, &budget];
+
+        const DECIMATE_TIMEOUT_MS: u64 = 30_000;
+        let script_result = runner::execute_python_script_with_timeout(
+            &ctx.venv_dir,
+            &script,
+            &args,
+            DECIMATE_TIMEOUT_MS,
+        )
+        .map_err(|e| format!("decimation failed: {}", e))?;
+        if script_result.exit_code != 0 {
+            return Err(format!(
+                "decimation returned exit code {}: {}",
+                script_result.exit_code, script_result.stderr
+            ));
+        }
+        std::fs::read(&output).map_err(|e| format!("failed to read decimated STL: {}", e))
+    })();
+
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    result
+}
+
+pub(crate) fn run_post_geometry_checks(
     code: &str,
     ctx: &ExecutionContext,
     user_request: Option<&str>,
@@ -760,10 +1007,7 @@ fn run_post_geometry_checks(
     let script = crate::commands::find_python_script("manufacturing.py")
         .map_err(|e| format!("cannot find manufacturing.py: {}", e))?;
 
-    let temp_dir = std::env::temp_dir()
-        .join("cadai-studio")
-        .join(format!("post-check-{}", Uuid::new_v4()));
-    std::fs::create_dir_all(&temp_dir)
+    let temp_dir = crate::artifacts::scratch_dir("post-check")
         .map_err(|e| format!("failed to create post-check temp dir: {}", e))?;
 
     let code_file = temp_dir.join("post_check_code.py");
@@ -799,6 +1043,7 @@ fn run_post_geometry_checks(
     let component_count = parsed["component_count"].as_u64().unwrap_or(1).max(1);
     let triangle_count = parsed["triangle_count"].as_u64().unwrap_or(0);
     let volume = parsed["volume"].as_f64().unwrap_or(0.0);
+    let surface_area = parsed["surface_area"].as_f64().unwrap_or(0.0);
 
     let mut warnings: Vec<String> = parsed["issues"]
         .as_array()
@@ -854,6 +1099,18 @@ fn run_post_geometry_checks(
         }
     }
 
+    let center_of_mass = parse_vec3(&parsed["center_mass"]);
+    let unit_inertia = parsed["inertia"].as_array().and_then(|rows| {
+        if rows.len() != 3 {
+            return None;
+        }
+        Some([
+            parse_vec3(&rows[0])?,
+            parse_vec3(&rows[1])?,
+            parse_vec3(&rows[2])?,
+        ])
+    });
+
     let expected_euler = (2_u64.saturating_mul(component_count)) as i64;
     let manifold = compute_manifold_status(
         watertight,
@@ -881,11 +1138,22 @@ fn run_post_geometry_checks(
         bounds_min,
         bounds_max,
         volume,
+        surface_area,
+        center_of_mass,
+        unit_inertia,
         bbox_ok,
         warnings,
     })
 }
 
+fn parse_vec3(value: &serde_json::Value) -> Option<[f64; 3]> {
+    let arr = value.as_array()?;
+    if arr.len() != 3 {
+        return None;
+    }
+    Some([arr[0].as_f64()?, arr[1].as_f64()?, arr[2].as_f64()?])
+}
+
 // ---------------------------------------------------------------------------
 // Post-processing: fix common AI mistakes before execution
 // ---------------------------------------------------------------------------
@@ -905,6 +1173,11 @@ fn postprocess_generated_code(code: &str) -> String {
     result
 }
 
+/// `postprocess_generated_code` plus the helpers `config` provides to scripts.
+fn prepare_generated_code(code: &str, config: &AppConfig) -> String {
+    threads::prepend_helper(&postprocess_generated_code(code), config)
+}
+
 /// Add `from math import *` if trig functions or math.xxx are used without import.
 fn ensure_math_import(code: &str) -> String {
     if code.contains("from math import") || code.contains("import math") {
@@ -1076,6 +1349,140 @@ def chamfer(*_a, **_kw):\n\
     out.join("\n")
 }
 
+/// Repair prompt for code that never assigns the top-level `result`.
+fn missing_result_prompt(code: &str) -> String {
+    format!(
+        "Your Build123d code never assigns the final geometry to a top-level `result` \
+         variable, so the runner has nothing to export.\n\n\
+         Code:\n```python\n{}\n```\n\n\
+         Assign final geometry to `result` at the top level of the script (not inside a \
+         function or block) and return the complete code wrapped in <CODE>...</CODE> tags.",
+        code
+    )
+}
+
+/// Catch a missing top-level `result` before execution: report it as a static
+/// finding and ask the model once to add the assignment. Returns the new code,
+/// or `None` when the code is fine or the regeneration did not fix it.
+async fn regenerate_missing_result(
+    code: &str,
+    ctx: &ExecutionContext,
+    system_prompt: &str,
+    on_event: &(dyn Fn(ValidationEvent) + Send + Sync),
+    retry_usage: &mut TokenUsage,
+    static_findings_accum: &mut Vec<String>,
+) -> Option<String> {
+    if static_validate::has_top_level_result_assignment(code) {
+        return None;
+    }
+    let finding = format!(
+        "{:?}: {}",
+        static_validate::FindingLevel::Error,
+        static_validate::MISSING_RESULT_MESSAGE
+    );
+    on_event(ValidationEvent::StaticValidation {
+        passed: false,
+        findings: vec![finding.clone()],
+        operation_count: None,
+    });
+    static_findings_accum.push(finding);
+
+    let provider = match create_code_provider(&ctx.config) {
+        Ok(provider) => provider,
+        Err(e) => {
+            eprintln!("[validate] Cannot request `result` assignment: {}", e);
+            return None;
+        }
+    };
+    let messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: system_prompt.to_string(),
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: missing_result_prompt(code),
+        },
+    ];
+    let (response, usage) = match provider.complete(&messages, None).await {
+        Ok(reply) => reply,
+        Err(e) => {
+            eprintln!("[validate] `result` assignment request failed: {}", e);
+            return None;
+        }
+    };
+    if let Some(ref u) = usage {
+        retry_usage.add(u);
+    }
+    crate::agent::extract::extract_code(&response)
+        .filter(|new_code| static_validate::has_top_level_result_assignment(new_code))
+}
+
+/// An AI repair of an execution error, awaiting the next attempt's verdict.
+struct PendingRepair {
+    error_category: String,
+    error_message: String,
+    before: String,
+    example_id: Option<String>,
+}
+
+/// Resolve the previous AI repair once the next attempt has run. A repair
+/// that made the code pass (`repaired_code`) is stored as an example; the
+/// outcome is counted for the example that assisted it, if any.
+fn settle_pending_repair(
+    pending: &mut Option<PendingRepair>,
+    repaired_code: Option<&str>,
+    config: &AppConfig,
+    uses: &mut Vec<RepairExampleUse>,
+) {
+    let Some(repair) = pending.take() else {
+        return;
+    };
+    let succeeded = repaired_code.is_some();
+    if let Some(id) = repair.example_id.clone() {
+        uses.push(RepairExampleUse {
+            example_id: id,
+            succeeded,
+        });
+    }
+    let Ok(mut store) = repair_examples::global_store().lock() else {
+        return;
+    };
+    if let Some(id) = repair.example_id.as_deref() {
+        store.record_outcome(id, succeeded);
+    }
+    if let Some(after) = repaired_code {
+        store.record(
+            &repair.error_category,
+            &repair.error_message,
+            &repair.before,
+            after,
+            crate::python::version_watch::detected_version(),
+            telemetry::now_ms(),
+        );
+    }
+    if config.allows_app_data_writes() {
+        if let Err(e) = store.save() {
+            eprintln!("[validate] Failed to save repair examples: {}", e);
+        }
+    }
+}
+
+/// Keep the diff from `attempt`'s code to its repair and report its size.
+fn record_attempt_diff(
+    history: &mut AttemptHistory,
+    attempt: u32,
+    next_code: &str,
+    on_event: &(dyn Fn(ValidationEvent) + Send + Sync),
+) {
+    let (additions, deletions) = history.advance(next_code);
+    on_event(ValidationEvent::AttemptDiff {
+        attempt,
+        additions,
+        deletions,
+    });
+}
+
 /// Execute code and retry with AI fixes if execution fails.
 ///
 /// The loop runs up to `config.max_validation_attempts` times:
@@ -1091,34 +1498,191 @@ pub async fn validate_and_retry(
     user_request: Option<&str>,
     on_event: &(dyn Fn(ValidationEvent) + Send + Sync),
 ) -> Result<ValidationResult, AppError> {
-    let mut current_code = postprocess_generated_code(&code);
+    validate_and_retry_after(code, None, ctx, system_prompt, user_request, on_event).await
+}
+
+/// `validate_and_retry` for code whose execution already failed with
+/// `known_error`: the first attempt repairs from that error instead of
+/// running the same script again, and reports no events of its own since
+/// the caller already did.
+pub async fn validate_and_retry_after(
+    code: String,
+    known_error: Option<String>,
+    ctx: &ExecutionContext,
+    system_prompt: &str,
+    user_request: Option<&str>,
+    on_event: &(dyn Fn(ValidationEvent) + Send + Sync),
+) -> Result<ValidationResult, AppError> {
+    let execute = |code: &str| {
+        let code = code.to_string();
+        let venv_dir = ctx.venv_dir.clone();
+        let runner_script = ctx.runner_script.clone();
+        async move { execute_with_timeout(&code, &venv_dir, &runner_script).await }.boxed()
+    };
+    let post_check = |code: &str| run_post_geometry_checks(code, ctx, user_request);
+    let steps = ValidationSteps {
+        cache: revalidation::global_cache(),
+        execute: &execute,
+        post_check: &post_check,
+    };
+    validate_and_retry_with(
+        code,
+        known_error,
+        ctx,
+        system_prompt,
+        user_request,
+        on_event,
+        &steps,
+    )
+    .await
+}
+
+/// `validate_and_retry_after` with the Python steps and cache supplied by the caller.
+async fn validate_and_retry_with(
+    code: String,
+    known_error: Option<String>,
+    ctx: &ExecutionContext,
+    system_prompt: &str,
+    user_request: Option<&str>,
+    on_event: &(dyn Fn(ValidationEvent) + Send + Sync),
+    steps: &ValidationSteps<'_>,
+) -> Result<ValidationResult, AppError> {
+    let mut known_error = known_error;
+    let mut current_code = prepare_generated_code(&code, &ctx.config);
     let mut retry_usage = TokenUsage::default();
     let max_attempts = configured_max_attempts(&ctx.config);
     let mut static_findings_accum: Vec<String> = Vec::new();
     let mut retry_ladder_stage_reached: Option<u32> = None;
+    let mut escalated_model: Option<String> = None;
+    let mut repair_example_uses: Vec<RepairExampleUse> = Vec::new();
+    let mut pending_repair: Option<PendingRepair> = None;
+    let mut previous_failure: Option<validate::StructuredError> = None;
+    let mut history = AttemptHistory::default();
+    let revalidation_key = revalidation::validation_key(&ctx.config, user_request);
+
+    if let Some(regenerated) = regenerate_missing_result(
+        &current_code,
+        ctx,
+        system_prompt,
+        on_event,
+        &mut retry_usage,
+        &mut static_findings_accum,
+    )
+    .await
+    {
+        current_code = prepare_generated_code(&regenerated, &ctx.config);
+    }
 
     for attempt in 1..=max_attempts {
-        let message = if attempt == 1 {
+        let replayed_error = known_error.take();
+        let replaying = replayed_error.is_some();
+        let plan = steps
+            .cache
+            .lock()
+            .map(|cache| cache.plan(&current_code, &revalidation_key))
+            .unwrap_or(revalidation::RevalidationPlan {
+                scope: RevalidationScope::Full,
+                cached: None,
+            });
+        let mut message = if attempt == 1 {
             "Validating generated code...".to_string()
         } else {
             format!("Retrying... (attempt {}/{})", attempt, max_attempts)
         };
-        on_event(ValidationEvent::Attempt {
-            attempt,
-            max_attempts,
-            message,
-        });
+        if let Some(note) = plan.scope.describe() {
+            message = format!("{} ({})", message, note);
+        }
+        if !replaying {
+            on_event(ValidationEvent::Attempt {
+                attempt,
+                max_attempts,
+                message,
+                revalidation_scope: plan.scope,
+            });
+        }
+        history.begin(attempt, &current_code);
 
-        let static_result = static_validate::validate_code_with_profile(
-            &current_code,
-            &ctx.config.generation_reliability_profile,
-            attempt == 1,
-        );
-        let static_findings: Vec<String> = static_result
-            .findings
-            .iter()
-            .map(|f| format!("{:?}: {}", f.level, f.message))
-            .collect();
+        if let Some(cached) = plan
+            .cached
+            .as_ref()
+            .filter(|_| !plan.scope.executes() && !replaying)
+        {
+            on_event(ValidationEvent::StaticValidation {
+                passed: cached.static_passed,
+                findings: cached.static_findings.clone(),
+                operation_count: Some(complexity::count_code_operations(&current_code)),
+            });
+            if let Some(report) = &cached.result.post_geometry_report {
+                on_event(ValidationEvent::PostGeometryValidation {
+                    report: report.clone(),
+                });
+            }
+            on_event(ValidationEvent::Success {
+                attempt,
+                message: format!(
+                    "Reused the previous validation on attempt {}; only comments or whitespace changed.",
+                    attempt
+                ),
+            });
+            for finding in &cached.static_findings {
+                if !static_findings_accum.contains(finding) {
+                    static_findings_accum.push(finding.clone());
+                }
+            }
+            settle_pending_repair(
+                &mut pending_repair,
+                Some(&current_code),
+                &ctx.config,
+                &mut repair_example_uses,
+            );
+            return Ok(ValidationResult {
+                code: current_code,
+                attempts: attempt,
+                retry_usage,
+                static_findings: static_findings_accum,
+                retry_ladder_stage_reached,
+                escalated_model,
+                repair_example_uses,
+                attempt_history: history.records(),
+                ..cached.result.clone()
+            });
+        }
+
+        let (static_passed, static_findings) = match (plan.scope, &plan.cached) {
+            (RevalidationScope::ReuseStatic, Some(cached)) => {
+                (cached.static_passed, cached.static_findings.clone())
+            }
+            _ => {
+                let mut static_result = static_validate::validate_code_with_profile(
+                    &current_code,
+                    &ctx.config.generation_reliability_profile,
+                    attempt == 1,
+                );
+                if let Some(request) = user_request {
+                    static_result
+                        .findings
+                        .extend(static_validate::fastener_findings(&current_code, request));
+                    static_result
+                        .findings
+                        .extend(static_validate::pattern_findings(&current_code, request));
+                }
+                let budget_findings = static_validate::operation_budget_findings(
+                    &current_code,
+                    ctx.config.operation_soft_budget,
+                    ctx.config.operation_hard_budget,
+                );
+                static_result.passed &= budget_findings
+                    .iter()
+                    .all(|f| !matches!(f.level, static_validate::FindingLevel::Error));
+                static_result.findings.extend(budget_findings);
+                let findings: Vec<String> = static_result
+                    .findings
+                    .iter()
+                    .map(|f| format!("{:?}: {}", f.level, f.message))
+                    .collect();
+                (static_result.passed, findings)
+            }
+        };
 
         for finding in &static_findings {
             if !static_findings_accum.contains(finding) {
@@ -1126,13 +1690,18 @@ pub async fn validate_and_retry(
             }
         }
 
-        on_event(ValidationEvent::StaticValidation {
-            passed: static_result.passed,
-            findings: static_findings.clone(),
-        });
+        if !replaying {
+            on_event(ValidationEvent::StaticValidation {
+                passed: static_passed,
+                findings: static_findings.clone(),
+                operation_count: Some(complexity::count_code_operations(&current_code)),
+            });
+        }
 
-        let execution_result = if static_result.passed {
-            execute_with_timeout(&current_code, &ctx.venv_dir, &ctx.runner_script).await
+        let execution_result = if let Some(error) = replayed_error {
+            Err(error)
+        } else if static_passed {
+            (steps.execute)(&current_code).await
         } else {
             Err(format!(
                 "Static validation failed:\n{}",
@@ -1142,13 +1711,19 @@ pub async fn validate_and_retry(
 
         match execution_result {
             Ok(exec_result) => {
-                match run_post_geometry_checks(&current_code, ctx, user_request) {
+                match (steps.post_check)(&current_code) {
                     Ok(post_report) => {
                         on_event(ValidationEvent::PostGeometryValidation {
                             report: post_report.clone(),
                         });
 
                         if should_retry_from_post_geometry(&post_report) {
+                            settle_pending_repair(
+                                &mut pending_repair,
+                                None,
+                                &ctx.config,
+                                &mut repair_example_uses,
+                            );
                             let mut feedback_parts: Vec<String> = Vec::new();
                             if post_report.component_count > 1 {
                                 feedback_parts.push(format!(
@@ -1191,6 +1766,7 @@ pub async fn validate_and_retry(
                                 error_message: err.clone(),
                                 will_retry,
                             });
+                            history.fail("PostGeometry", &err, None);
 
                             if !will_retry {
                                 return Ok(ValidationResult {
@@ -1204,6 +1780,9 @@ pub async fn validate_and_retry(
                                     post_geometry_report: Some(post_report),
                                     post_check_warning: None,
                                     retry_ladder_stage_reached,
+                                    escalated_model,
+                                    repair_example_uses,
+                                    attempt_history: history.records(),
                                 });
                             }
 
@@ -1218,8 +1797,21 @@ pub async fn validate_and_retry(
                                 feedback_parts.join("\n"),
                                 current_code
                             );
-
-                            let provider = create_provider(&ctx.config)?;
+                            history.repair(&retry_prompt);
+
+                            let escalated = last_chance_config(&ctx.config, attempt, max_attempts);
+                            if let Some(ref esc) = escalated {
+                                on_event(ValidationEvent::ModelEscalation {
+                                    from_model: ctx.config.model.clone(),
+                                    to_model: esc.model.clone(),
+                                    message: format!(
+                                        "Escalating to {} for final repair attempt",
+                                        esc.model
+                                    ),
+                                });
+                                escalated_model = Some(esc.model.clone());
+                            }
+                            let provider = create_code_provider(escalated.as_ref().unwrap_or(&ctx.config))?;
                             let messages = vec![
                                 ChatMessage {
                                     role: "system".to_string(),
@@ -1238,7 +1830,13 @@ pub async fn validate_and_retry(
 
                             match crate::agent::extract::extract_code(&ai_response) {
                                 Some(new_code) => {
-                                    current_code = postprocess_generated_code(&new_code);
+                                    current_code = prepare_generated_code(&new_code, &ctx.config);
+                                    record_attempt_diff(
+                                        &mut history,
+                                        attempt,
+                                        &current_code,
+                                        on_event,
+                                    );
                                 }
                                 None => {
                                     return Ok(ValidationResult {
@@ -1252,10 +1850,19 @@ pub async fn validate_and_retry(
                                         post_geometry_report: Some(post_report),
                                         post_check_warning: None,
                                         retry_ladder_stage_reached,
+                                        escalated_model,
+                                        repair_example_uses,
+                                        attempt_history: history.records(),
                                     });
                                 }
                             }
                         } else {
+                            settle_pending_repair(
+                                &mut pending_repair,
+                                Some(&current_code),
+                                &ctx.config,
+                                &mut repair_example_uses,
+                            );
                             let stl_base64 = base64::engine::general_purpose::STANDARD
                                 .encode(&exec_result.stl_data);
                             on_event(ValidationEvent::Success {
@@ -1265,7 +1872,7 @@ pub async fn validate_and_retry(
                                     attempt
                                 ),
                             });
-                            return Ok(ValidationResult {
+                            let result = ValidationResult {
                                 code: current_code,
                                 stl_base64: Some(stl_base64),
                                 success: true,
@@ -1276,11 +1883,32 @@ pub async fn validate_and_retry(
                                 post_geometry_report: Some(post_report),
                                 post_check_warning: None,
                                 retry_ladder_stage_reached,
-                            });
+                                escalated_model,
+                                repair_example_uses,
+                                attempt_history: history.records(),
+                            };
+                            if let Ok(mut cache) = steps.cache.lock() {
+                                cache.record(
+                                    &result.code,
+                                    &revalidation_key,
+                                    revalidation::CachedValidation {
+                                        static_passed,
+                                        static_findings,
+                                        result: result.clone(),
+                                    },
+                                );
+                            }
+                            return Ok(result);
                         }
                     }
                     Err(reason) => {
                         // Soft-fail policy for post-check infrastructure errors.
+                        settle_pending_repair(
+                            &mut pending_repair,
+                            Some(&current_code),
+                            &ctx.config,
+                            &mut repair_example_uses,
+                        );
                         let warning = format_post_check_warning(&reason);
                         on_event(ValidationEvent::PostGeometryWarning {
                             message: warning.clone(),
@@ -1307,24 +1935,41 @@ pub async fn validate_and_retry(
                             post_geometry_report: None,
                             post_check_warning: Some(warning),
                             retry_ladder_stage_reached,
+                            escalated_model,
+                            repair_example_uses,
+                            attempt_history: history.records(),
                         });
                     }
                 }
             }
             Err(error_msg) => {
+                settle_pending_repair(
+                    &mut pending_repair,
+                    None,
+                    &ctx.config,
+                    &mut repair_example_uses,
+                );
                 let structured_error = validate::parse_traceback(&error_msg);
-                let strategy =
-                    validate::get_retry_strategy(&structured_error, attempt, Some(&current_code));
+                let (strategy, simplify_operation) = select_retry_strategy(
+                    previous_failure.as_ref(),
+                    &structured_error,
+                    attempt,
+                    &current_code,
+                );
+                previous_failure = Some(structured_error.clone());
 
                 let category_str = format!("{:?}", structured_error.category);
                 let will_retry = attempt < max_attempts;
 
-                on_event(ValidationEvent::Failed {
-                    attempt,
-                    error_category: category_str,
-                    error_message: error_msg.clone(),
-                    will_retry,
-                });
+                if !replaying {
+                    on_event(ValidationEvent::Failed {
+                        attempt,
+                        error_category: category_str.clone(),
+                        error_message: error_msg.clone(),
+                        will_retry,
+                    });
+                }
+                history.fail(&category_str, &error_msg, structured_error.line_number);
 
                 if !will_retry {
                     return Ok(ValidationResult {
@@ -1338,6 +1983,9 @@ pub async fn validate_and_retry(
                         post_geometry_report: None,
                         post_check_warning: None,
                         retry_ladder_stage_reached,
+                        escalated_model,
+                        repair_example_uses,
+                        attempt_history: history.records(),
                     });
                 }
 
@@ -1352,10 +2000,39 @@ pub async fn validate_and_retry(
                             .map(|s| s.max(stage))
                             .unwrap_or(stage),
                     );
+                    if ctx.config.explain_retries {
+                        on_event(ValidationEvent::FixExplanation {
+                            attempt,
+                            explanation: format!(
+                                "Attempt {} hit {}. Fix: an automatic code repair (retry \
+                                 ladder stage {}), without asking the model.",
+                                attempt,
+                                validate::category_label(&structured_error.category),
+                                stage
+                            ),
+                        });
+                    }
+                    history.repair(&format!("Automatic repair (retry ladder stage {})", stage));
                     current_code = auto_fixed_code;
+                    record_attempt_diff(&mut history, attempt, &current_code, on_event);
                     continue;
                 }
 
+                if ctx.config.explain_retries {
+                    on_event(ValidationEvent::FixExplanation {
+                        attempt,
+                        explanation: validate::explain_retry(&structured_error, &strategy, attempt),
+                    });
+                }
+
+                if simplify_operation {
+                    retry_ladder_stage_reached = Some(
+                        retry_ladder_stage_reached
+                            .map(|s| s.max(validate::SIMPLIFY_OPERATION_STAGE))
+                            .unwrap_or(validate::SIMPLIFY_OPERATION_STAGE),
+                    );
+                }
+
                 let rules = AgentRules::from_preset(ctx.config.agent_rules_preset.as_deref()).ok();
                 let anti_pattern = rules.as_ref().and_then(|r| {
                     r.anti_patterns.as_ref().and_then(|patterns| {
@@ -1366,7 +2043,7 @@ pub async fn validate_and_retry(
                     })
                 });
 
-                let retry_prompt = build_retry_prompt_with_findings(
+                let mut retry_prompt = build_retry_prompt_with_findings(
                     &current_code,
                     &error_msg,
                     &static_findings,
@@ -1375,8 +2052,41 @@ pub async fn validate_and_retry(
                     &strategy,
                     anti_pattern,
                 );
-
-                let provider = create_provider(&ctx.config)?;
+                if let (Some(api), Some(version)) = (
+                    validate::missing_api_name(&structured_error),
+                    crate::python::version_watch::detected_version(),
+                ) {
+                    retry_prompt.push_str("\n\n");
+                    retry_prompt.push_str(&validate::version_api_hint(&api, &version));
+                }
+                let example = repair_examples::global_store()
+                    .lock()
+                    .ok()
+                    .and_then(|mut store| {
+                        store.best_match(&category_str, &error_msg, telemetry::now_ms())
+                    });
+                if let Some(ref example) = example {
+                    retry_prompt.push_str("\n\n");
+                    retry_prompt.push_str(&repair_examples::few_shot_section(example));
+                }
+                pending_repair = Some(PendingRepair {
+                    error_category: category_str,
+                    error_message: error_msg.clone(),
+                    before: current_code.clone(),
+                    example_id: example.map(|e| e.id),
+                });
+                history.repair(&retry_prompt);
+
+                let escalated = last_chance_config(&ctx.config, attempt, max_attempts);
+                if let Some(ref esc) = escalated {
+                    on_event(ValidationEvent::ModelEscalation {
+                        from_model: ctx.config.model.clone(),
+                        to_model: esc.model.clone(),
+                        message: format!("Escalating to {} for final repair attempt", esc.model),
+                    });
+                    escalated_model = Some(esc.model.clone());
+                }
+                let provider = create_code_provider(escalated.as_ref().unwrap_or(&ctx.config))?;
                 let messages = vec![
                     ChatMessage {
                         role: "system".to_string(),
@@ -1395,7 +2105,8 @@ pub async fn validate_and_retry(
 
                 match crate::agent::extract::extract_code(&ai_response) {
                     Some(new_code) => {
-                        current_code = postprocess_generated_code(&new_code);
+                        current_code = prepare_generated_code(&new_code, &ctx.config);
+                        record_attempt_diff(&mut history, attempt, &current_code, on_event);
                     }
                     None => {
                         return Ok(ValidationResult {
@@ -1409,6 +2120,9 @@ pub async fn validate_and_retry(
                             post_geometry_report: None,
                             post_check_warning: None,
                             retry_ladder_stage_reached,
+                            escalated_model,
+                            repair_example_uses,
+                            attempt_history: history.records(),
                         });
                     }
                 }
@@ -1427,12 +2141,256 @@ pub async fn validate_and_retry(
         post_geometry_report: None,
         post_check_warning: None,
         retry_ladder_stage_reached,
+        escalated_model,
+        repair_example_uses,
+        attempt_history: history.records(),
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const PLATE: &str =
+        "from build123d import *\n\n# Base plate\nplate = Box(80, 60, 5)\nresult = plate\n";
+
+    fn clean_report() -> PostGeometryValidationReport {
+        PostGeometryValidationReport {
+            watertight: true,
+            manifold: true,
+            degenerate_faces: 0,
+            euler_number: 2,
+            triangle_count: 12,
+            component_count: 1,
+            bounds_min: [0.0, 0.0, 0.0],
+            bounds_max: [80.0, 60.0, 5.0],
+            volume: 24000.0,
+            surface_area: 11200.0,
+            center_of_mass: None,
+            unit_inertia: None,
+            bbox_ok: true,
+            warnings: vec![],
+        }
+    }
+
+    /// Run the validation loop against `cache`, with a runner that always
+    /// succeeds, and report how many times the runner was invoked.
+    async fn validate_counting(
+        cache: &Mutex<RevalidationCache>,
+        code: &str,
+        known_error: Option<&str>,
+        config: AppConfig,
+    ) -> (ValidationResult, usize) {
+        validate_counting_events(cache, code, known_error, config, &|_| {}).await
+    }
+
+    async fn validate_counting_events(
+        cache: &Mutex<RevalidationCache>,
+        code: &str,
+        known_error: Option<&str>,
+        config: AppConfig,
+        on_event: &(dyn Fn(ValidationEvent) + Send + Sync),
+    ) -> (ValidationResult, usize) {
+        let ctx = ExecutionContext {
+            venv_dir: PathBuf::from("/nonexistent/venv"),
+            runner_script: PathBuf::from("/nonexistent/runner.py"),
+            config,
+        };
+        let executions = AtomicUsize::new(0);
+        let execute = |_code: &str| {
+            executions.fetch_add(1, Ordering::SeqCst);
+            async {
+                Ok(runner::ExecutionResult {
+                    stl_data: b"stl".to_vec(),
+                    stdout: String::new(),
+                    stderr: String::new(),
+                })
+            }
+            .boxed()
+        };
+        let post_check =
+            |_code: &str| -> Result<PostGeometryValidationReport, String> { Ok(clean_report()) };
+        let steps = ValidationSteps {
+            cache,
+            execute: &execute,
+            post_check: &post_check,
+        };
+        let result = validate_and_retry_with(
+            code.to_string(),
+            known_error.map(str::to_string),
+            &ctx,
+            "sys",
+            None,
+            on_event,
+            &steps,
+        )
+        .await
+        .unwrap();
+        (result, executions.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_comment_only_edit_runs_no_execution() {
+        let cache = Mutex::new(RevalidationCache::default());
+        let (first, executions) =
+            validate_counting(&cache, PLATE, None, AppConfig::default()).await;
+        assert!(first.success);
+        assert_eq!(executions, 1);
+
+        let commented = PLATE
+            .replace("# Base plate", "# Base plate, 5 mm thick")
+            .replace("Box(80, 60, 5)", "Box(80,60,5)   # outer")
+            + "\n\n";
+        let (reused, executions) =
+            validate_counting(&cache, &commented, None, AppConfig::default()).await;
+        assert_eq!(executions, 0);
+        assert!(reused.success);
+        assert_eq!(reused.stl_base64, first.stl_base64);
+    }
+
+    #[tokio::test]
+    async fn test_dimension_edit_runs_exactly_one_execution() {
+        let cache = Mutex::new(RevalidationCache::default());
+        validate_counting(&cache, PLATE, None, AppConfig::default()).await;
+
+        let resized = PLATE.replace("Box(80, 60, 5)", "Box(80, 60, 4)");
+        let (result, executions) =
+            validate_counting(&cache, &resized, None, AppConfig::default()).await;
+        assert!(result.success);
+        assert_eq!(executions, 1);
+        // The resized version is now cached too.
+        let (_, executions) = validate_counting(&cache, &resized, None, AppConfig::default()).await;
+        assert_eq!(executions, 0);
+    }
+
+    #[tokio::test]
+    async fn test_structural_edit_and_threshold_change_execute_again() {
+        let cache = Mutex::new(RevalidationCache::default());
+        validate_counting(&cache, PLATE, None, AppConfig::default()).await;
+
+        let structural = PLATE.replace("result = plate", "result = plate.rotate(Axis.Z, 90)");
+        let (_, executions) =
+            validate_counting(&cache, &structural, None, AppConfig::default()).await;
+        assert_eq!(executions, 1);
+
+        let mut strict = AppConfig::default();
+        strict.quality_gates_strict = !strict.quality_gates_strict;
+        let (_, executions) = validate_counting(&cache, PLATE, None, strict).await;
+        assert_eq!(executions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_result_assignment_checked_before_execution() {
+        let mut config = AppConfig::default();
+        config.ai_provider = "ollama".to_string();
+        // Nothing listens here, so a regeneration request fails fast.
+        config.ollama_base_url = Some("http://127.0.0.1:9".to_string());
+        let ctx = ExecutionContext {
+            venv_dir: PathBuf::from("/nonexistent/venv"),
+            runner_script: PathBuf::from("/nonexistent/runner.py"),
+            config,
+        };
+        let events = std::sync::Mutex::new(Vec::new());
+        let on_event = |evt: ValidationEvent| {
+            if let ValidationEvent::StaticValidation { findings, .. } = evt {
+                events.lock().unwrap().extend(findings);
+            }
+        };
+        let mut usage = TokenUsage::default();
+        let mut accum = Vec::new();
+
+        let with_result = "from build123d import *\nresult = Box(1, 1, 1)\n";
+        let regenerated =
+            regenerate_missing_result(with_result, &ctx, "sys", &on_event, &mut usage, &mut accum)
+                .await;
+        assert!(regenerated.is_none());
+        assert!(events.lock().unwrap().is_empty());
+
+        let without_result = "from build123d import *\npart = Box(1, 1, 1)\n";
+        let regenerated = regenerate_missing_result(
+            without_result,
+            &ctx,
+            "sys",
+            &on_event,
+            &mut usage,
+            &mut accum,
+        )
+        .await;
+        assert!(regenerated.is_none());
+        let findings = events.lock().unwrap();
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].contains("assign final geometry to `result`"));
+        assert_eq!(accum, *findings);
+        assert!(missing_result_prompt(without_result).contains("part = Box(1, 1, 1)"));
+    }
+
+    #[tokio::test]
+    async fn test_known_error_is_not_executed_again() {
+        let config = AppConfig {
+            max_validation_attempts: 1,
+            ..AppConfig::default()
+        };
+        let cache = Mutex::new(RevalidationCache::default());
+        let code = "from build123d import *\nresult = Box(1, 1, 1)\n";
+        let known = "Traceback (most recent call last):\nValueError: fillet too large";
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let on_event = |evt: ValidationEvent| events.lock().unwrap().push(evt);
+
+        let (result, executions) =
+            validate_counting_events(&cache, code, Some(known), config, &on_event).await;
+
+        assert_eq!(executions, 0);
+        assert!(!result.success);
+        assert_eq!(result.attempts, 1);
+        assert_eq!(result.error.as_deref(), Some(known));
+        assert_eq!(result.attempt_history.len(), 1);
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_repeated_shell_failure_simplifies_on_attempt_3() {
+        let shell_failure = r#"Traceback (most recent call last):
+  File "input.py", line 5, in <module>
+    result = shell(Box(20, 20, 20).faces(), thickness=-2.0)
+  File "/venv/lib/python3.10/site-packages/build123d/operations_generic.py", line 987, in shell
+    raise StdFail_NotDone
+OCP.StdFail_NotDone: Shell offset not done"#;
+        let code = "result = shell(Box(20, 20, 20).faces(), thickness=-2.0)";
+
+        let mut previous: Option<validate::StructuredError> = None;
+        let mut simplified_for_attempt = Vec::new();
+        for failed_attempt in 1..=2 {
+            let error = validate::parse_traceback(shell_failure);
+            let (strategy, simplify) =
+                select_retry_strategy(previous.as_ref(), &error, failed_attempt, code);
+            if simplify {
+                simplified_for_attempt.push(failed_attempt + 1);
+                assert!(strategy.fix_instruction.contains("Do NOT call shell()"));
+                assert!(strategy.fix_instruction.contains("outer.cut(inner)"));
+                assert_eq!(strategy.forbidden_operations, vec!["shell".to_string()]);
+            }
+            previous = Some(error);
+        }
+        assert_eq!(simplified_for_attempt, vec![3]);
+    }
+
+    #[test]
+    fn test_different_failures_do_not_simplify() {
+        let shell_failure = validate::parse_traceback(
+            "File \"input.py\", line 5\n    result = shell(b.faces(), thickness=-2)\nOCP.StdFail_NotDone: Shell offset not done",
+        );
+        let fillet_failure = validate::parse_traceback(
+            "File \"input.py\", line 6\n    result = fillet(b.edges(), radius=3)\nOCP.StdFail_NotDone: BRep_API: command not done",
+        );
+        let (_, simplify) = select_retry_strategy(Some(&shell_failure), &fillet_failure, 2, "");
+        assert!(!simplify);
+
+        let name_error = validate::parse_traceback("NameError: name 'Bx' is not defined");
+        let (_, simplify) = select_retry_strategy(Some(&name_error), &name_error, 2, "");
+        assert!(!simplify, "non-kernel errors keep the normal ladder");
+    }
 
     #[test]
     fn test_validation_result_serialization() {
@@ -1454,11 +2412,17 @@ mod tests {
                 bounds_min: [0.0, 0.0, 0.0],
                 bounds_max: [10.0, 10.0, 10.0],
                 volume: 1000.0,
+                surface_area: 600.0,
+                center_of_mass: None,
+                unit_inertia: None,
                 bbox_ok: true,
                 warnings: vec![],
             }),
             post_check_warning: None,
             retry_ladder_stage_reached: None,
+            escalated_model: None,
+            repair_example_uses: vec![],
+            attempt_history: vec![],
         };
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("\"success\":true"));
@@ -1482,6 +2446,9 @@ mod tests {
             post_geometry_report: None,
             post_check_warning: None,
             retry_ladder_stage_reached: None,
+            escalated_model: None,
+            repair_example_uses: vec![],
+            attempt_history: vec![],
         };
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("\"success\":false"));
@@ -1524,6 +2491,9 @@ mod tests {
             bounds_min: [0.0, 0.0, 0.0],
             bounds_max: [10.0, 10.0, 10.0],
             volume: 1000.0,
+            surface_area: 600.0,
+            center_of_mass: None,
+            unit_inertia: None,
             bbox_ok: true,
             warnings: vec![],
         };
@@ -1592,6 +2562,9 @@ mod tests {
             post_geometry_report: None,
             post_check_warning: Some(format_post_check_warning("trimesh API mismatch")),
             retry_ladder_stage_reached: None,
+            escalated_model: None,
+            repair_example_uses: vec![],
+            attempt_history: vec![],
         };
 
         assert!(result.success);
@@ -1600,6 +2573,18 @@ mod tests {
         assert!(result.post_check_warning.is_some());
     }
 
+    #[test]
+    fn test_last_chance_config_only_on_final_retry() {
+        let mut config = AppConfig::default();
+        config.escalation_model = Some("claude-opus-4-1".to_string());
+        assert!(last_chance_config(&config, 1, 4).is_none());
+        let esc = last_chance_config(&config, 3, 4).expect("final retry should escalate");
+        assert_eq!(esc.model, "claude-opus-4-1");
+
+        config.escalation_model = None;
+        assert!(last_chance_config(&config, 3, 4).is_none());
+    }
+
     #[test]
     fn test_maybe_apply_fillet_auto_repair_targets_source_line() {
         let code = r#"from build123d import *
@@ -1824,4 +2809,77 @@ result = body"#;
         let pass2 = postprocess_generated_code(&pass1);
         assert_eq!(pass1, pass2);
     }
+
+    fn binary_stl(triangles: &[[f32; 9]]) -> Vec<u8> {
+        let mut stl = vec![0u8; 80];
+        stl.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+        for tri in triangles {
+            stl.extend_from_slice(&[0u8; 12]);
+            for value in tri {
+                stl.extend_from_slice(&value.to_le_bytes());
+            }
+            stl.extend_from_slice(&[0u8; 2]);
+        }
+        stl
+    }
+
+    const BOX_FACETS: [[f32; 9]; 2] = [
+        [0.0, 0.0, 0.0, 10.0, 0.0, 0.0, 10.0, 10.0, 0.0],
+        [0.0, 0.0, 0.0, 10.0, 10.0, 0.0, 0.0, 10.0, 0.0],
+    ];
+
+    #[test]
+    fn test_geometry_hash_is_stable_across_identical_runs() {
+        let first = geometry_hash(&binary_stl(&BOX_FACETS)).unwrap();
+        assert_eq!(first.len(), 16);
+        assert_eq!(geometry_hash(&binary_stl(&BOX_FACETS)).unwrap(), first);
+
+        // Facet order, vertex rotation and float noise don't matter.
+        let mut reordered = [BOX_FACETS[1], BOX_FACETS[0]];
+        reordered[0].rotate_left(3);
+        reordered[1][3] += 1e-5;
+        assert_eq!(geometry_hash(&binary_stl(&reordered)).unwrap(), first);
+
+        let ascii = "solid part\n\
+            facet normal 0 0 1\n outer loop\n\
+            vertex 0 0 0\n vertex 10 0 0\n vertex 10 10 0\n\
+            endloop\n endfacet\n\
+            facet normal 0 0 1\n outer loop\n\
+            vertex 0 0 0\n vertex 10 10 0\n vertex 0 10 0\n\
+            endloop\n endfacet\nendsolid part\n";
+        assert_eq!(geometry_hash(ascii.as_bytes()).unwrap(), first);
+
+        let encoded = base64::engine::general_purpose::STANDARD.encode(binary_stl(&BOX_FACETS));
+        assert_eq!(geometry_hash_base64(Some(&encoded)).unwrap(), first);
+    }
+
+    #[test]
+    fn test_geometry_hash_changes_for_perturbed_part() {
+        let original = geometry_hash(&binary_stl(&BOX_FACETS)).unwrap();
+        let mut taller = BOX_FACETS;
+        taller[1][7] = 10.5;
+        assert_ne!(geometry_hash(&binary_stl(&taller)).unwrap(), original);
+
+        // Flipped winding is a different surface.
+        let mut flipped = BOX_FACETS;
+        flipped[0].swap(3, 6);
+        flipped[0].swap(4, 7);
+        flipped[0].swap(5, 8);
+        assert_ne!(geometry_hash(&binary_stl(&flipped)).unwrap(), original);
+
+        assert!(geometry_hash(b"").is_none());
+        assert!(geometry_hash_base64(None).is_none());
+        assert!(geometry_hash_base64(Some("not base64!")).is_none());
+    }
+
+    #[test]
+    fn test_stl_bounds_cover_all_vertices() {
+        let mut facets = BOX_FACETS;
+        facets[1][8] = -4.0;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(binary_stl(&facets));
+        let bounds = stl_bounds_base64(&encoded).unwrap();
+        assert_eq!(bounds.min, [0.0, 0.0, -4.0]);
+        assert_eq!(bounds.max, [10.0, 10.0, 0.0]);
+        assert!(stl_bounds_base64("").is_none());
+    }
 }
@@ -0,0 +1,310 @@
+//! Round-trip check for STEP exports: the written file is re-imported by the
+//! runner and measured, then compared against the post-geometry report of the
+//! code that produced it, so a STEP writer that drops faces is caught before
+//! the file is handed over.
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::executor::PostGeometryValidationReport;
+use crate::config::AppConfig;
+use crate::error::AppError;
+
+/// Wall-clock budget for re-importing one STEP file.
+pub const MEASURE_TIMEOUT_MS: u64 = 30_000;
+/// Volume below which the relative volume check compares absolutely (mm³).
+const MIN_REFERENCE_VOLUME: f64 = 1e-6;
+
+/// What the runner's `--measure-step` mode reports for a re-imported file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StepMeasurement {
+    pub bounds_min: [f64; 3],
+    pub bounds_max: [f64; 3],
+    pub volume: f64,
+    pub solid_count: u64,
+    #[serde(default)]
+    pub face_count: u64,
+}
+
+/// Geometry the export is expected to reproduce.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportReference {
+    pub bounds_min: [f64; 3],
+    pub bounds_max: [f64; 3],
+    pub volume: f64,
+    /// Solids in the source shape, compared exactly with the re-imported
+    /// file's. `None` skips the check.
+    pub solid_count: Option<u64>,
+}
+
+impl From<&PostGeometryValidationReport> for ExportReference {
+    /// The report counts connected mesh components, not solids: touching
+    /// solids merge into one component, so its count is not compared.
+    fn from(report: &PostGeometryValidationReport) -> Self {
+        Self {
+            bounds_min: report.bounds_min,
+            bounds_max: report.bounds_max,
+            volume: report.volume,
+            solid_count: None,
+        }
+    }
+}
+
+/// Allowed drift between the reference and the re-imported file. The
+/// reference comes from a tessellated mesh, so exact equality is not expected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportTolerances {
+    /// Per-axis bounding box drift (mm).
+    pub bbox_mm: f64,
+    /// Relative volume drift (percent).
+    pub volume_pct: f64,
+}
+
+impl ExportTolerances {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            bbox_mm: config.export_verify_bbox_tolerance_mm.max(0.0),
+            volume_pct: config.export_verify_volume_tolerance_pct.max(0.0),
+        }
+    }
+}
+
+/// One compared metric, e.g. "bounds_max.z" or "volume".
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricCheck {
+    pub metric: String,
+    pub expected: f64,
+    pub actual: f64,
+    /// `actual - expected`; for volume, in mm³.
+    pub delta: f64,
+    /// Largest accepted `|delta|`, in the metric's unit.
+    pub tolerance: f64,
+    pub passed: bool,
+}
+
+/// Outcome of re-importing one exported file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportVerificationReport {
+    pub path: String,
+    pub passed: bool,
+    pub checks: Vec<MetricCheck>,
+    /// Faces in the re-imported file, for diagnosing dropped faces.
+    pub face_count: u64,
+}
+
+fn check(metric: &str, expected: f64, actual: f64, tolerance: f64) -> MetricCheck {
+    let delta = actual - expected;
+    MetricCheck {
+        metric: metric.to_string(),
+        expected,
+        actual,
+        delta,
+        tolerance,
+        passed: delta.is_finite() && delta.abs() <= tolerance,
+    }
+}
+
+/// Compare a re-imported file against its reference, one check per metric.
+pub fn verify_export(
+    path: &str,
+    reference: &ExportReference,
+    measured: &StepMeasurement,
+    tolerances: ExportTolerances,
+) -> ExportVerificationReport {
+    let mut checks = Vec::new();
+    for (i, axis) in ["x", "y", "z"].iter().enumerate() {
+        checks.push(check(
+            &format!("bounds_min.{}", axis),
+            reference.bounds_min[i],
+            measured.bounds_min[i],
+            tolerances.bbox_mm,
+        ));
+        checks.push(check(
+            &format!("bounds_max.{}", axis),
+            reference.bounds_max[i],
+            measured.bounds_max[i],
+            tolerances.bbox_mm,
+        ));
+    }
+    let volume_tolerance =
+        reference.volume.abs().max(MIN_REFERENCE_VOLUME) * tolerances.volume_pct / 100.0;
+    checks.push(check(
+        "volume",
+        reference.volume,
+        measured.volume,
+        volume_tolerance,
+    ));
+    if let Some(solid_count) = reference.solid_count {
+        checks.push(check(
+            "solid_count",
+            solid_count as f64,
+            measured.solid_count as f64,
+            0.0,
+        ));
+    }
+
+    ExportVerificationReport {
+        path: path.to_string(),
+        passed: checks.iter().all(|c| c.passed),
+        checks,
+        face_count: measured.face_count,
+    }
+}
+
+/// Parse the runner's measurement JSON and compare it with `reference`.
+pub fn verify_measurement_json(
+    path: &str,
+    reference: &ExportReference,
+    json: &str,
+    tolerances: ExportTolerances,
+) -> Result<ExportVerificationReport, AppError> {
+    let measured: StepMeasurement = serde_json::from_str(json)?;
+    Ok(verify_export(path, reference, &measured, tolerances))
+}
+
+impl ExportVerificationReport {
+    /// The error handed back instead of a file that failed verification,
+    /// naming each metric that drifted.
+    pub fn failure(&self) -> Option<AppError> {
+        let drifted: Vec<String> = self
+            .checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| {
+                format!(
+                    "{} drifted by {:+.4} (expected {:.4}, re-imported {:.4}, tolerance {:.4})",
+                    c.metric, c.delta, c.expected, c.actual, c.tolerance
+                )
+            })
+            .collect();
+        if drifted.is_empty() {
+            return None;
+        }
+        Some(AppError::CadError(format!(
+            "STEP export verification failed for {}: {}",
+            self.path,
+            drifted.join("; ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCES: ExportTolerances = ExportTolerances {
+        bbox_mm: 0.1,
+        volume_pct: 1.0,
+    };
+
+    fn reference() -> ExportReference {
+        ExportReference {
+            bounds_min: [0.0, 0.0, 0.0],
+            bounds_max: [40.0, 20.0, 4.0],
+            volume: 3200.0,
+            solid_count: Some(1),
+        }
+    }
+
+    fn measurement() -> StepMeasurement {
+        StepMeasurement {
+            bounds_min: [0.0, 0.0, 0.0],
+            bounds_max: [40.0, 20.0, 4.0],
+            volume: 3200.0,
+            solid_count: 1,
+            face_count: 6,
+        }
+    }
+
+    #[test]
+    fn test_faithful_export_passes() {
+        let mut measured = measurement();
+        // Mesh-vs-BREP noise stays inside the tolerances.
+        measured.bounds_max[2] = 4.04;
+        measured.volume = 3215.0;
+        let report = verify_export("plate.step", &reference(), &measured, TOLERANCES);
+        assert!(report.passed);
+        assert_eq!(report.checks.len(), 8);
+        assert!(report.failure().is_none());
+    }
+
+    #[test]
+    fn test_missing_faces_fail_volume_and_components() {
+        let mut measured = measurement();
+        // A shell with a missing face re-imports without a closed solid.
+        measured.volume = 0.0;
+        measured.solid_count = 0;
+        measured.face_count = 5;
+        let report = verify_export("plate.step", &reference(), &measured, TOLERANCES);
+        assert!(!report.passed);
+        let failed: Vec<&str> = report
+            .checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| c.metric.as_str())
+            .collect();
+        assert_eq!(failed, vec!["volume", "solid_count"]);
+        let volume = report.checks.iter().find(|c| c.metric == "volume").unwrap();
+        assert_eq!(volume.delta, -3200.0);
+        assert!((volume.tolerance - 32.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mesh_reference_skips_solid_count() {
+        let mesh = PostGeometryValidationReport {
+            watertight: true,
+            manifold: true,
+            degenerate_faces: 0,
+            euler_number: 2,
+            triangle_count: 12,
+            // Two touching solids read as one mesh component.
+            component_count: 1,
+            bounds_min: [0.0, 0.0, 0.0],
+            bounds_max: [40.0, 20.0, 4.0],
+            volume: 3200.0,
+            surface_area: 1920.0,
+            center_of_mass: None,
+            unit_inertia: None,
+            bbox_ok: true,
+            warnings: vec![],
+        };
+        let mut measured = measurement();
+        measured.solid_count = 2;
+        let report = verify_export(
+            "plate.step",
+            &ExportReference::from(&mesh),
+            &measured,
+            TOLERANCES,
+        );
+        assert!(report.passed);
+        assert!(report.checks.iter().all(|c| c.metric != "solid_count"));
+    }
+
+    #[test]
+    fn test_failure_names_drifted_metric() {
+        let mut measured = measurement();
+        measured.bounds_max[0] = 38.5;
+        let report = verify_export("plate.step", &reference(), &measured, TOLERANCES);
+        let err = report.failure().unwrap().to_string();
+        assert!(err.contains("plate.step"));
+        assert!(err.contains("bounds_max.x drifted by -1.5000"));
+        assert!(!err.contains("volume"));
+    }
+
+    #[test]
+    fn test_parses_runner_measurement() {
+        let json = r#"{"bounds_min": [0, 0, 0], "bounds_max": [40, 20, 4],
+                       "volume": 3200.0, "solid_count": 1, "face_count": 6}"#;
+        let report = verify_measurement_json("plate.step", &reference(), json, TOLERANCES).unwrap();
+        assert!(report.passed);
+        assert_eq!(report.face_count, 6);
+        assert!(verify_measurement_json("plate.step", &reference(), "{}", TOLERANCES).is_err());
+    }
+
+    #[test]
+    fn test_nan_measurement_never_passes() {
+        let mut measured = measurement();
+        measured.volume = f64::NAN;
+        let report = verify_export("plate.step", &reference(), &measured, TOLERANCES);
+        assert!(!report.passed);
+    }
+}
@@ -53,10 +53,23 @@ pub fn extract_code(response: &str) -> Option<String> {
 }
 
 /// Tier 1: Extract code from `<CODE>...</CODE>` XML-style tags (case-insensitive).
+///
+/// A `</CODE>` stop sequence ends the response before the closing tag, so an
+/// unclosed block running to the end of the response also counts, unless it
+/// holds a markdown fence (left to tier 2).
 fn try_xml_tags(response: &str) -> Option<ExtractionOutcome> {
     let re = Regex::new(r"(?si)<CODE>([\s\S]*?)</CODE>").ok()?;
-    let cap = re.captures(response)?;
-    let code = cap[1].trim().to_string();
+    let code = match re.captures(response) {
+        Some(cap) => cap[1].trim().to_string(),
+        None => {
+            let open = Regex::new(r"(?si)<CODE>([\s\S]*)\z").ok()?;
+            let body = open.captures(response)?[1].to_string();
+            if body.contains("```") {
+                return None;
+            }
+            body.trim().to_string()
+        }
+    };
     if code.is_empty() {
         return None;
     }
@@ -107,6 +120,21 @@ mod tests {
         assert!(outcome.code.contains("from build123d import *"));
     }
 
+    #[test]
+    fn test_extract_xml_tags_cut_by_stop_sequence() {
+        // The `</CODE>` stop sequence is not part of the returned text.
+        let response = "Here is the code:\n<CODE>\nfrom build123d import *\nresult = Box(10, 10, 10)\n";
+        let outcome = extract_python_code(response).unwrap();
+        assert_eq!(outcome.format, ExtractionFormat::XmlTags);
+        assert_eq!(outcome.code, "from build123d import *\nresult = Box(10, 10, 10)");
+
+        // A fenced block inside the unclosed tag is left to the fence tier.
+        let fenced = "<CODE>\n```python\nfrom build123d import *\nresult = Box(1, 1, 1)\n```\n";
+        let outcome = extract_python_code(fenced).unwrap();
+        assert_eq!(outcome.format, ExtractionFormat::MarkdownFence);
+        assert_eq!(outcome.code, "from build123d import *\nresult = Box(1, 1, 1)");
+    }
+
     #[test]
     fn test_extract_xml_tags_case_insensitive() {
         let response =
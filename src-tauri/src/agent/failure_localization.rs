@@ -0,0 +1,191 @@
+//! Map an assembled-script failure back to the part section that raised it.
+//! `assemble_parts` writes a `# --- <name> ---` marker before each part, so
+//! the failing line number gives the owning part exactly, and that part can
+//! be repaired on its own and spliced back instead of sending the whole
+//! assembly to the model.
+
+use regex::Regex;
+
+use crate::agent::validate::StructuredError;
+
+/// Markers `assemble_parts` writes before the assembly-level sections.
+const ASSEMBLY_MARKERS: [&str; 2] = ["Assembly", "Assembly operations"];
+const SHARED_SUFFIX: &str = " (shared structure)";
+
+/// A single part's block in an assembled script, as 0-based line indices.
+/// `end` is exclusive and stops before the blank lines separating it from
+/// the next marker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartSection {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Where in the assembled script a failure was raised.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureLocation {
+    /// Inside one part's block; that part can be repaired in isolation.
+    Part(PartSection),
+    /// In the assembly operations or `assy` section, or in a structure shared
+    /// by several parts: the whole script has to be repaired.
+    Assembly,
+    /// No usable line number, or a line outside any section.
+    Unknown,
+}
+
+/// The script line that raised the error, 1-based. The runner `exec`s the
+/// script, so its frames are `File "<string>"`; the last one is the script
+/// line closest to the error, unlike library frames further down.
+pub fn failing_line(error_message: &str, structured: &StructuredError) -> Option<usize> {
+    let frame_re = Regex::new(r#"File "<string>", line (\d+)"#).unwrap();
+    frame_re
+        .captures_iter(error_message)
+        .filter_map(|c| c[1].parse::<usize>().ok())
+        .last()
+        .or(structured.line_number.map(|n| n as usize))
+}
+
+/// Locate 1-based `line` among the `# --- <name> ---` sections of `code`.
+pub fn locate(code: &str, line: usize) -> FailureLocation {
+    let marker_re = Regex::new(r"^# --- (.+) ---$").unwrap();
+    let lines: Vec<&str> = code.lines().collect();
+    let Some(index) = line.checked_sub(1).filter(|i| *i < lines.len()) else {
+        return FailureLocation::Unknown;
+    };
+    let Some(marker) = (0..=index).rev().find(|&i| marker_re.is_match(lines[i])) else {
+        return FailureLocation::Unknown;
+    };
+    let name = &marker_re.captures(lines[marker]).unwrap()[1];
+    if marker == index || ASSEMBLY_MARKERS.contains(&name) || name.ends_with(SHARED_SUFFIX) {
+        return FailureLocation::Assembly;
+    }
+
+    let mut end = (marker + 1..lines.len())
+        .find(|&i| marker_re.is_match(lines[i]))
+        .unwrap_or(lines.len());
+    while end > marker + 1 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    FailureLocation::Part(PartSection {
+        name: name.to_string(),
+        start: marker + 1,
+        end,
+    })
+}
+
+/// The section as a standalone script, with `part_<name>` renamed back to
+/// `result` so it validates like a freshly generated part.
+pub fn isolate_part(code: &str, section: &PartSection) -> String {
+    let var_re = Regex::new(&format!(r"\bpart_{}\b", regex::escape(&section.name))).unwrap();
+    let body: Vec<&str> = code
+        .lines()
+        .skip(section.start)
+        .take(section.end - section.start)
+        .collect();
+    format!(
+        "from build123d import *\n\n{}\n",
+        var_re.replace_all(&body.join("\n"), "result")
+    )
+}
+
+/// Replace the section's lines with `part_code`, renamed the way
+/// `assemble_parts` renames it. Every line outside the section is kept.
+pub fn splice_part(code: &str, section: &PartSection, part_code: &str) -> String {
+    let result_re = Regex::new(r"\bresult\b").unwrap();
+    let var_name = format!("part_{}", section.name);
+    let body: Vec<&str> = part_code
+        .trim_end()
+        .lines()
+        .filter(|line| {
+            let trimmed = line.trim();
+            !trimmed.starts_with("from build123d") && !trimmed.starts_with("import build123d")
+        })
+        .collect();
+    let renamed = result_re
+        .replace_all(body.join("\n").trim_start_matches('\n'), var_name.as_str())
+        .to_string();
+
+    let lines: Vec<&str> = code.lines().collect();
+    let mut spliced: Vec<&str> = lines[..section.start].to_vec();
+    spliced.extend(renamed.lines());
+    spliced.extend(&lines[section.end..]);
+    let mut out = spliced.join("\n");
+    if code.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::validate::parse_traceback;
+
+    const ASSEMBLED: &str = "from build123d import *
+
+# --- base ---
+part_base = Box(40, 40, 5)
+
+# --- lid ---
+lid_w = 40
+part_lid = Box(lid_w, 40, 2)
+part_lid = part_lid.fillet(99, part_lid.edges())
+
+# --- Assembly ---
+assy = Compound(label=\"assembly\", children=[
+    Pos(0, 0, 0) * part_base,
+    Pos(0, 0, 5) * part_lid,
+])
+result = assy
+";
+
+    #[test]
+    fn test_failure_is_localized_to_the_owning_part() {
+        let stderr = "Traceback (most recent call last):
+  File \"/venv/runner.py\", line 824, in main
+  File \"<string>\", line 9, in <module>
+  File \"/venv/site-packages/build123d/topology.py\", line 1530, in fillet
+ValueError: Failed creating a fillet";
+        let line = failing_line(stderr, &parse_traceback(stderr));
+        assert_eq!(line, Some(9));
+        assert_eq!(
+            locate(ASSEMBLED, 9),
+            FailureLocation::Part(PartSection {
+                name: "lid".to_string(),
+                start: 6,
+                end: 9,
+            })
+        );
+        assert_eq!(locate(ASSEMBLED, 13), FailureLocation::Assembly);
+        assert_eq!(locate(ASSEMBLED, 1), FailureLocation::Unknown);
+        assert_eq!(locate(ASSEMBLED, 400), FailureLocation::Unknown);
+    }
+
+    #[test]
+    fn test_only_the_failing_block_changes_after_repair() {
+        let FailureLocation::Part(section) = locate(ASSEMBLED, 9) else {
+            panic!("expected a part location");
+        };
+        let isolated = isolate_part(ASSEMBLED, &section);
+        assert_eq!(
+            isolated,
+            "from build123d import *\n\nlid_w = 40\nresult = Box(lid_w, 40, 2)\nresult = result.fillet(99, result.edges())\n"
+        );
+
+        let repaired = isolated.replace("fillet(99", "fillet(0.5");
+        let spliced = splice_part(ASSEMBLED, &section, &repaired);
+        let before: Vec<&str> = ASSEMBLED.lines().collect();
+        let after: Vec<&str> = spliced.lines().collect();
+        assert_eq!(before.len(), after.len());
+        let changed: Vec<usize> = (0..before.len())
+            .filter(|&i| before[i] != after[i])
+            .collect();
+        assert_eq!(changed, vec![8]);
+        assert_eq!(
+            after[8],
+            "part_lid = part_lid.fillet(0.5, part_lid.edges())"
+        );
+        assert!(spliced.ends_with("result = assy\n"));
+    }
+}
@@ -0,0 +1,1139 @@
+use regex::Regex;
+use serde::Serialize;
+
+/// Heading of the prompt section carrying the generated fastener helpers.
+pub const FASTENER_FEATURES_HEADING: &str = "## Standard Fastener Features";
+/// Diameters within this many mm of a table value count as standard.
+pub const STANDARD_DIAMETER_TOLERANCE: f64 = 0.05;
+/// Cut tools overshoot the target surface by this much for clean booleans.
+const CUT_OVERSHOOT: f64 = 0.05;
+
+/// Standard metric fastener dimensions in mm. Clearances follow ISO 273
+/// (close/normal fit), countersinks ISO 15065 (90°), counterbores fit ISO
+/// 4762 socket heads, insert holes common heat-set insert datasheets.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FastenerSize {
+    pub name: &'static str,
+    pub nominal: f64,
+    pub clearance_close: f64,
+    pub clearance_normal: f64,
+    pub tap_drill: f64,
+    pub countersink_diameter: f64,
+    pub counterbore_diameter: f64,
+    pub counterbore_depth: f64,
+    pub insert_hole: f64,
+    pub insert_depth: f64,
+    pub boss_diameter: f64,
+}
+
+pub const FASTENER_SIZES: &[FastenerSize] = &[
+    FastenerSize {
+        name: "M2",
+        nominal: 2.0,
+        clearance_close: 2.2,
+        clearance_normal: 2.4,
+        tap_drill: 1.6,
+        countersink_diameter: 4.4,
+        counterbore_diameter: 4.4,
+        counterbore_depth: 2.2,
+        insert_hole: 3.2,
+        insert_depth: 4.0,
+        boss_diameter: 6.0,
+    },
+    FastenerSize {
+        name: "M2.5",
+        nominal: 2.5,
+        clearance_close: 2.7,
+        clearance_normal: 2.9,
+        tap_drill: 2.05,
+        countersink_diameter: 5.5,
+        counterbore_diameter: 5.5,
+        counterbore_depth: 2.7,
+        insert_hole: 3.6,
+        insert_depth: 5.7,
+        boss_diameter: 7.0,
+    },
+    FastenerSize {
+        name: "M3",
+        nominal: 3.0,
+        clearance_close: 3.2,
+        clearance_normal: 3.4,
+        tap_drill: 2.5,
+        countersink_diameter: 6.3,
+        counterbore_diameter: 6.5,
+        counterbore_depth: 3.2,
+        insert_hole: 4.0,
+        insert_depth: 5.7,
+        boss_diameter: 8.0,
+    },
+    FastenerSize {
+        name: "M4",
+        nominal: 4.0,
+        clearance_close: 4.3,
+        clearance_normal: 4.5,
+        tap_drill: 3.3,
+        countersink_diameter: 9.4,
+        counterbore_diameter: 8.0,
+        counterbore_depth: 4.4,
+        insert_hole: 5.6,
+        insert_depth: 8.1,
+        boss_diameter: 10.0,
+    },
+    FastenerSize {
+        name: "M5",
+        nominal: 5.0,
+        clearance_close: 5.3,
+        clearance_normal: 5.5,
+        tap_drill: 4.2,
+        countersink_diameter: 10.4,
+        counterbore_diameter: 10.0,
+        counterbore_depth: 5.4,
+        insert_hole: 6.4,
+        insert_depth: 9.5,
+        boss_diameter: 12.0,
+    },
+    FastenerSize {
+        name: "M6",
+        nominal: 6.0,
+        clearance_close: 6.4,
+        clearance_normal: 6.6,
+        tap_drill: 5.0,
+        countersink_diameter: 12.6,
+        counterbore_diameter: 11.0,
+        counterbore_depth: 6.4,
+        insert_hole: 8.0,
+        insert_depth: 12.7,
+        boss_diameter: 14.0,
+    },
+    FastenerSize {
+        name: "M8",
+        nominal: 8.0,
+        clearance_close: 8.4,
+        clearance_normal: 9.0,
+        tap_drill: 6.8,
+        countersink_diameter: 17.3,
+        counterbore_diameter: 15.0,
+        counterbore_depth: 8.6,
+        insert_hole: 10.0,
+        insert_depth: 12.7,
+        boss_diameter: 18.0,
+    },
+];
+
+pub fn fastener_size(name: &str) -> Option<&'static FastenerSize> {
+    FASTENER_SIZES
+        .iter()
+        .find(|s| s.name.eq_ignore_ascii_case(name))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureKind {
+    ClearanceHole,
+    CountersunkHole,
+    CounterboredHole,
+    TappedHole,
+    InsertBoss,
+}
+
+impl FeatureKind {
+    fn label(self) -> &'static str {
+        match self {
+            FeatureKind::ClearanceHole => "clearance hole",
+            FeatureKind::CountersunkHole => "countersunk hole",
+            FeatureKind::CounterboredHole => "counterbored hole",
+            FeatureKind::TappedHole => "tapped hole",
+            FeatureKind::InsertBoss => "heat-set insert boss",
+        }
+    }
+
+    fn helper_suffix(self) -> &'static str {
+        match self {
+            FeatureKind::ClearanceHole => "clearance_holes",
+            FeatureKind::CountersunkHole => "countersunk_holes",
+            FeatureKind::CounterboredHole => "counterbored_holes",
+            FeatureKind::TappedHole => "tapped_holes",
+            FeatureKind::InsertBoss => "insert_bosses",
+        }
+    }
+
+    /// Hole diameters that are correct for this feature.
+    fn standard_diameters(self, size: &FastenerSize) -> Vec<f64> {
+        match self {
+            FeatureKind::ClearanceHole
+            | FeatureKind::CountersunkHole
+            | FeatureKind::CounterboredHole => vec![
+                size.clearance_close,
+                size.clearance_normal,
+                size.countersink_diameter,
+                size.counterbore_diameter,
+            ],
+            FeatureKind::TappedHole => vec![size.tap_drill, size.nominal],
+            FeatureKind::InsertBoss => vec![size.insert_hole, size.boss_diameter],
+        }
+    }
+}
+
+/// A fastener feature named in a request or plan, e.g. "four M4 countersunk
+/// holes on a 30mm bolt circle".
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeatureSpec {
+    pub kind: FeatureKind,
+    pub size: &'static FastenerSize,
+    pub count: Option<usize>,
+    pub bolt_circle_diameter: Option<f64>,
+}
+
+fn fmt_mm(value: f64) -> String {
+    let s = format!("{:.3}", value);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Evenly spaced points on the bolt circle, the first on +X.
+fn bolt_circle_positions(count: usize, diameter: f64) -> Vec<(f64, f64)> {
+    (0..count)
+        .map(|i| {
+            let angle = std::f64::consts::TAU * i as f64 / count as f64;
+            (diameter / 2.0 * angle.cos(), diameter / 2.0 * angle.sin())
+        })
+        .collect()
+}
+
+/// Name of the helper `generate_hole_pattern` emits for `spec`.
+pub fn helper_name(spec: &FeatureSpec) -> String {
+    format!(
+        "{}_{}",
+        spec.size.name.to_ascii_lowercase().replace('.', "_"),
+        spec.kind.helper_suffix()
+    )
+}
+
+/// Build123d helper function for `spec` with the standard dimensions baked
+/// in. Hole helpers cut from `top_z` down by `depth`; the insert helper adds
+/// bosses from `base_z` up by `height` and drills them for the insert. When
+/// the spec has a bolt circle, `positions` defaults to it.
+pub fn generate_hole_pattern(spec: &FeatureSpec) -> String {
+    let s = spec.size;
+    let name = helper_name(spec);
+    let positions = match (spec.count, spec.bolt_circle_diameter) {
+        (Some(count), Some(diameter)) if count > 0 => {
+            let points: Vec<String> = bolt_circle_positions(count, diameter)
+                .iter()
+                .map(|(x, y)| format!("({}, {})", fmt_mm(*x), fmt_mm(*y)))
+                .collect();
+            Some(format!("[{}]", points.join(", ")))
+        }
+        _ => None,
+    };
+    let (signature_tail, pattern_doc) = match &positions {
+        Some(points) => (
+            format!("positions={}", points),
+            format!(
+                " Defaults to {} positions on a {}mm bolt circle.",
+                spec.count.unwrap_or(0),
+                fmt_mm(spec.bolt_circle_diameter.unwrap_or(0.0))
+            ),
+        ),
+        None => ("positions".to_string(), String::new()),
+    };
+    let align = "align=(Align.CENTER, Align.CENTER, Align.MIN)";
+    let hole_radius = match spec.kind {
+        FeatureKind::TappedHole => s.tap_drill / 2.0,
+        FeatureKind::InsertBoss => s.insert_hole / 2.0,
+        _ => s.clearance_normal / 2.0,
+    };
+
+    let mut out = String::new();
+    match spec.kind {
+        FeatureKind::InsertBoss => {
+            out.push_str(&format!(
+                "def {}(part, base_z, height, {}):\n    \
+                 \"\"\"{} {} ({}mm boss, {}mm x {}mm insert hole).{} \
+                 `positions` are (x, y) pairs.\"\"\"\n    \
+                 for x, y in positions:\n        \
+                 part = part + Pos(x, y, base_z - {}) * Cylinder({}, height + {}, {})\n        \
+                 part = part - Pos(x, y, base_z + height - {}) * Cylinder({}, {}, {})\n    \
+                 return part\n",
+                name,
+                signature_tail,
+                s.name,
+                spec.kind.label(),
+                fmt_mm(s.boss_diameter),
+                fmt_mm(s.insert_hole),
+                fmt_mm(s.insert_depth),
+                pattern_doc,
+                fmt_mm(CUT_OVERSHOOT),
+                fmt_mm(s.boss_diameter / 2.0),
+                fmt_mm(CUT_OVERSHOOT),
+                align,
+                fmt_mm(s.insert_depth),
+                fmt_mm(hole_radius),
+                fmt_mm(s.insert_depth + CUT_OVERSHOOT),
+                align,
+            ));
+        }
+        kind => {
+            let (fit, diameter) = match kind {
+                FeatureKind::TappedHole => ("tap drill", s.tap_drill),
+                _ => ("ISO 273 normal clearance", s.clearance_normal),
+            };
+            out.push_str(&format!(
+                "def {}(part, top_z, depth, {}):\n    \
+                 \"\"\"{} {}: {} {}mm.{} `positions` are (x, y) pairs.\"\"\"\n    \
+                 for x, y in positions:\n        \
+                 part = part - Pos(x, y, top_z - depth - {}) * Cylinder({}, depth + {}, {})\n",
+                name,
+                signature_tail,
+                s.name,
+                kind.label(),
+                fit,
+                fmt_mm(diameter),
+                pattern_doc,
+                fmt_mm(CUT_OVERSHOOT),
+                fmt_mm(hole_radius),
+                fmt_mm(2.0 * CUT_OVERSHOOT),
+                align,
+            ));
+            match kind {
+                FeatureKind::CountersunkHole => {
+                    // 90° countersink: the cone drops by its radial step.
+                    let sink_radius = s.countersink_diameter / 2.0;
+                    let sink_height = sink_radius - hole_radius;
+                    out.push_str(&format!(
+                        "        part = part - Pos(x, y, top_z - {}) * Cone({}, {}, {}, {})\n",
+                        fmt_mm(sink_height),
+                        fmt_mm(hole_radius),
+                        fmt_mm(sink_radius + CUT_OVERSHOOT),
+                        fmt_mm(sink_height + CUT_OVERSHOOT),
+                        align,
+                    ));
+                }
+                FeatureKind::CounterboredHole => {
+                    out.push_str(&format!(
+                        "        part = part - Pos(x, y, top_z - {}) * Cylinder({}, {}, {})\n",
+                        fmt_mm(s.counterbore_depth),
+                        fmt_mm(s.counterbore_diameter / 2.0),
+                        fmt_mm(s.counterbore_depth + CUT_OVERSHOOT),
+                        align,
+                    ));
+                }
+                _ => {}
+            }
+            out.push_str("    return part\n");
+        }
+    }
+    out
+}
+
+const NUMBER_WORDS: &[(&str, usize)] = &[
+    ("one", 1),
+    ("single", 1),
+    ("two", 2),
+    ("pair", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("eight", 8),
+    ("ten", 10),
+    ("twelve", 12),
+];
+
+fn window_kind(window: &str) -> Option<FeatureKind> {
+    let lower = window.to_lowercase();
+    if lower.contains("insert") {
+        Some(FeatureKind::InsertBoss)
+    } else if lower.contains("countersunk") || lower.contains("countersink") {
+        Some(FeatureKind::CountersunkHole)
+    } else if lower.contains("counterbore") || lower.contains("counter-bore") {
+        Some(FeatureKind::CounterboredHole)
+    } else if lower.contains("tapped") || lower.contains("threaded hole") {
+        Some(FeatureKind::TappedHole)
+    } else if ["hole", "clearance", "screw", "bolt"]
+        .iter()
+        .any(|k| lower.contains(k))
+    {
+        Some(FeatureKind::ClearanceHole)
+    } else {
+        None
+    }
+}
+
+/// Last count in `window` ("four", "4x", "6 "), skipping dimensions like
+/// "30 mm".
+fn window_count(window: &str) -> Option<usize> {
+    let count_re = Regex::new(r"(?i)\b(?:(\d+)\s*[x×]?(?:\s+|$)|([a-z]+)\b)").unwrap();
+    count_re
+        .captures_iter(window)
+        .filter_map(|c| {
+            if let Some(n) = c.get(1) {
+                let rest = window[c.get(0)?.end()..].trim_start().to_lowercase();
+                if ["mm", "cm", "deg", "°"].iter().any(|u| rest.starts_with(u)) {
+                    return None;
+                }
+                return n.as_str().parse().ok();
+            }
+            let word = c.get(2)?.as_str().to_lowercase();
+            NUMBER_WORDS
+                .iter()
+                .find(|(w, _)| *w == word)
+                .map(|(_, n)| *n)
+        })
+        .last()
+}
+
+fn window_bolt_circle(window: &str) -> Option<f64> {
+    let before = Regex::new(
+        r"(?i)(\d+(?:\.\d+)?)\s*mm\s*(?:diameter\s+|dia\.?\s+)?(?:bolt[ -]circle|pcd|pitch circle)",
+    )
+    .unwrap();
+    let after = Regex::new(
+        r"(?i)(?:bolt[ -]circle|pcd|pitch circle)(?:\s+diameter)?\s*(?:of|=|:)?\s*(\d+(?:\.\d+)?)\s*mm",
+    )
+    .unwrap();
+    before
+        .captures(window)
+        .or_else(|| after.captures(window))
+        .and_then(|c| c[1].parse().ok())
+}
+
+/// Fastener features named in `text`, one per (size, kind), in order of
+/// appearance. Each size mention is read together with the words up to the
+/// neighbouring mentions in its sentence: the kind and bolt circle from the
+/// words after it (falling back to those before), the count from before.
+pub fn detect_fastener_features(text: &str) -> Vec<FeatureSpec> {
+    let size_re = Regex::new(r"\b[Mm](2\.5|2|3|4|5|6|8)\b").unwrap();
+    let mut found: Vec<FeatureSpec> = Vec::new();
+    for clause in text.split(['\n', ';']).flat_map(|l| l.split(". ")) {
+        let mentions: Vec<regex::Captures> = size_re.captures_iter(clause).collect();
+        for (i, m) in mentions.iter().enumerate() {
+            let whole = m.get(0).unwrap();
+            let before_start = if i == 0 {
+                0
+            } else {
+                mentions[i - 1].get(0).unwrap().end()
+            };
+            let after_end = mentions
+                .get(i + 1)
+                .map(|next| next.get(0).unwrap().start())
+                .unwrap_or(clause.len());
+            let before = &clause[before_start..whole.start()];
+            let after = &clause[whole.end()..after_end];
+
+            let Some(kind) = window_kind(after).or_else(|| window_kind(before)) else {
+                continue;
+            };
+            let Some(size) = fastener_size(&format!("M{}", &m[1])) else {
+                continue;
+            };
+            if found
+                .iter()
+                .any(|f| f.kind == kind && f.size.name == size.name)
+            {
+                continue;
+            }
+            found.push(FeatureSpec {
+                kind,
+                size,
+                count: window_count(before),
+                bolt_circle_diameter: window_bolt_circle(after)
+                    .or_else(|| window_bolt_circle(before)),
+            });
+        }
+    }
+    found
+}
+
+/// Prompt section with a ready-made helper per detected feature, or `None`
+/// when `text` names no standard fastener.
+pub fn fastener_features_section(text: &str) -> Option<String> {
+    let specs = detect_fastener_features(text);
+    if specs.is_empty() {
+        return None;
+    }
+    let mut out = format!(
+        "{}\n\
+         The request names standard fasteners. Copy these helpers verbatim into your code and \
+         call them for those features; do NOT re-derive hole, countersink, counterbore or \
+         insert dimensions.\n",
+        FASTENER_FEATURES_HEADING
+    );
+    for spec in &specs {
+        let usage = match spec.kind {
+            FeatureKind::InsertBoss => "part = {}(part, base_z, height)",
+            _ => "part = {}(part, top_z, depth)",
+        }
+        .replace("{}", &helper_name(spec));
+        let usage = if spec.bolt_circle_diameter.is_some() && spec.count.is_some() {
+            usage
+        } else {
+            usage.replace(')', ", positions=[(x, y), ...])")
+        };
+        out.push_str(&format!(
+            "\n### {} {}\nCall: `{}`\n```python\n{}```\n",
+            spec.size.name,
+            spec.kind.label(),
+            usage,
+            generate_hole_pattern(spec)
+        ));
+    }
+    Some(out)
+}
+
+/// Heading of the prompt section carrying the pattern loops.
+pub const PATTERN_FEATURES_HEADING: &str = "## Patterned Features";
+/// More identical cut lines than this, when a pattern was requested, read as
+/// a pattern written out by hand.
+pub const LITERAL_REPEAT_THRESHOLD: usize = 4;
+
+/// Plural feature nouns and the variable stem used for one instance.
+const PATTERN_NOUNS: &[(&str, &str)] = &[
+    ("holes", "hole"),
+    ("slots", "slot"),
+    ("vents", "vent"),
+    ("pockets", "pocket"),
+    ("cutouts", "cutout"),
+    ("grooves", "groove"),
+    ("louvers", "louver"),
+    ("perforations", "perforation"),
+    ("ribs", "rib"),
+    ("fins", "fin"),
+    ("pins", "pin"),
+    ("bosses", "boss"),
+    ("posts", "post"),
+    ("studs", "stud"),
+    ("standoffs", "standoff"),
+    ("spokes", "spoke"),
+];
+/// Stems that are added to the body rather than cut from it.
+const ADDITIVE_STEMS: &[&str] = &[
+    "rib", "fin", "pin", "boss", "post", "stud", "standoff", "spoke",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternKind {
+    Grid,
+    Circular,
+    Linear,
+}
+
+/// A repeated feature named in a request or plan, e.g. "a grid of 6x4
+/// ventilation slots" or "8 holes equally spaced on a 60mm circle".
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PatternSpec {
+    pub kind: PatternKind,
+    /// Variable stem for one instance ("slot", "hole").
+    pub feature: String,
+    /// Instances along X for grids and rows, around the circle for circular
+    /// patterns.
+    pub count: usize,
+    /// Rows along Y; 1 unless `kind` is `Grid`.
+    pub rows: usize,
+    /// Centre-to-centre spacing in mm, when given.
+    pub spacing: Option<f64>,
+    /// Diameter in mm of the circle a circular pattern sits on.
+    pub diameter: Option<f64>,
+}
+
+impl PatternSpec {
+    pub fn instances(&self) -> usize {
+        self.count * self.rows
+    }
+
+    fn label(&self) -> String {
+        let layout = match self.kind {
+            PatternKind::Grid => format!("{} x {} grid", self.count, self.rows),
+            PatternKind::Circular => format!(
+                "{} on a {}mm circle",
+                self.count,
+                fmt_mm(self.diameter.unwrap_or(0.0))
+            ),
+            PatternKind::Linear => format!("Row of {}", self.count),
+        };
+        format!("{} (`{}`)", layout, self.feature)
+    }
+}
+
+/// Regex alternation for a count: digits or a number word.
+fn count_pattern() -> String {
+    let words: Vec<&str> = NUMBER_WORDS.iter().map(|(w, _)| *w).collect();
+    format!(r"(\d+|{})", words.join("|"))
+}
+
+fn parse_count(token: &str) -> Option<usize> {
+    let lower = token.to_lowercase();
+    lower.parse().ok().or_else(|| {
+        NUMBER_WORDS
+            .iter()
+            .find(|(w, _)| *w == lower)
+            .map(|(_, n)| *n)
+    })
+}
+
+/// Centre-to-centre spacing in `clause`: "spaced 10mm", "at 12mm pitch",
+/// "spacing of 8mm". "spaced 10mm from the edge" is an edge offset, not a
+/// pitch, and is skipped.
+fn clause_spacing(clause: &str) -> Option<f64> {
+    let spacing_re = Regex::new(
+        r"(?i)\bspaced\s+(?:at\s+)?(\d+(?:\.\d+)?)\s*mm|\b(?:at|with)\s+(?:an?\s+)?(\d+(?:\.\d+)?)\s*mm\s+(?:pitch|spacing|centers|centres|intervals)\b|\b(?:pitch|spacing)\s+(?:of\s+)?(\d+(?:\.\d+)?)\s*mm",
+    )
+    .unwrap();
+    let offset_re = Regex::new(r"(?i)^\s*(?:in\s+|away\s+)?(?:from|to)\b").unwrap();
+    for caps in spacing_re.captures_iter(clause) {
+        let whole = caps.get(0)?;
+        if offset_re.is_match(&clause[whole.end()..]) {
+            continue;
+        }
+        return (1..=3)
+            .find_map(|i| caps.get(i))
+            .and_then(|m| m.as_str().parse().ok());
+    }
+    None
+}
+
+fn clause_feature(clause: &str) -> String {
+    let lower = clause.to_lowercase();
+    PATTERN_NOUNS
+        .iter()
+        .filter_map(|(plural, stem)| {
+            Regex::new(&format!(r"\b{}\b", plural))
+                .unwrap()
+                .find(&lower)
+                .map(|m| (m.start(), *stem))
+        })
+        .min_by_key(|(start, _)| *start)
+        .map(|(_, stem)| stem.to_string())
+        .unwrap_or_else(|| "feature".to_string())
+}
+
+/// Grid, circular and linear patterns named in `text`, at most one per
+/// clause. A grid wins over a row in the same clause, and a circular
+/// pattern needs the circle's diameter.
+pub fn detect_patterns(text: &str) -> Vec<PatternSpec> {
+    let count = count_pattern();
+    let grid_re = Regex::new(
+        r"(?i)\b(?:grid|array|matrix|pattern) of (\d+)\s*(?:x|×|by)\s*(\d+)\b|\b(\d+)\s*(?:x|×|by)\s*(\d+)\s+(?:grid|array|matrix)\b",
+    )
+    .unwrap();
+    let circular_re = Regex::new(&format!(
+        r"(?i)\b{}\s+(?:[a-z-]+\s+){{0,4}}?(?:on|around)\s+(?:an?|the)\s+(\d+(?:\.\d+)?)\s*mm(?:\s+diameter)?\s+(?:(?:bolt[ -]|pitch\s+)?circle|pcd)\b",
+        count
+    ))
+    .unwrap();
+    let row_re = Regex::new(&format!(
+        r"(?i)\b(?:row|line|linear (?:array|pattern)) of {}\b",
+        count
+    ))
+    .unwrap();
+    let spaced_re = Regex::new(&format!(
+        r"(?i)\b{}\s+(?:[a-z-]+\s+){{0,3}}?(?:spaced|at|with)\s+(?:at\s+|an?\s+)?\d+(?:\.\d+)?\s*mm",
+        count
+    ))
+    .unwrap();
+
+    let mut found: Vec<PatternSpec> = Vec::new();
+    for clause in text.split(['\n', ';']).flat_map(|l| l.split(". ")) {
+        let spacing = clause_spacing(clause);
+        let spec = if let Some(c) = grid_re.captures(clause) {
+            let columns = c.get(1).or(c.get(3)).and_then(|m| m.as_str().parse().ok());
+            let rows = c.get(2).or(c.get(4)).and_then(|m| m.as_str().parse().ok());
+            columns.zip(rows).map(|(count, rows)| PatternSpec {
+                kind: PatternKind::Grid,
+                feature: clause_feature(clause),
+                count,
+                rows,
+                spacing,
+                diameter: None,
+            })
+        } else if let Some(c) = circular_re.captures(clause) {
+            parse_count(&c[1]).map(|count| PatternSpec {
+                kind: PatternKind::Circular,
+                feature: clause_feature(clause),
+                count,
+                rows: 1,
+                spacing: None,
+                diameter: c[2].parse().ok(),
+            })
+        } else {
+            let row_count = row_re.captures(clause).and_then(|c| parse_count(&c[1]));
+            let spaced_count = spacing
+                .and(spaced_re.captures(clause))
+                .and_then(|c| parse_count(&c[1]));
+            row_count.or(spaced_count).map(|count| PatternSpec {
+                kind: PatternKind::Linear,
+                feature: clause_feature(clause),
+                count,
+                rows: 1,
+                spacing,
+                diameter: None,
+            })
+        };
+        if let Some(spec) = spec.filter(|s| s.instances() > 1) {
+            if !found.contains(&spec) {
+                found.push(spec);
+            }
+        }
+    }
+    found
+}
+
+/// Build123d loop placing one instance of `spec.feature` at every pattern
+/// position, with the count and spacing as named variables. Cut features
+/// are subtracted from `part`, additive ones (ribs, pins, ...) unioned.
+/// A spacing the text didn't give is left as `...` for the model to fill.
+pub fn generate_pattern_loop(spec: &PatternSpec) -> String {
+    let f = &spec.feature;
+    let spacing = spec
+        .spacing
+        .map(fmt_mm)
+        .unwrap_or_else(|| "...  # not given: set from the design".to_string());
+    let op = if ADDITIVE_STEMS.contains(&f.as_str()) {
+        "+"
+    } else {
+        "-"
+    };
+    let (variables, locations) = match spec.kind {
+        PatternKind::Grid => (
+            format!(
+                "{f}_columns = {}\n{f}_rows = {}\n{f}_x_spacing = {spacing}\n{f}_y_spacing = {f}_x_spacing\n",
+                spec.count, spec.rows
+            ),
+            format!("GridLocations({f}_x_spacing, {f}_y_spacing, {f}_columns, {f}_rows)"),
+        ),
+        PatternKind::Circular => (
+            format!(
+                "{f}_count = {}\n{f}_circle_diameter = {}\n",
+                spec.count,
+                fmt_mm(spec.diameter.unwrap_or(0.0))
+            ),
+            format!("PolarLocations({f}_circle_diameter / 2, {f}_count)"),
+        ),
+        PatternKind::Linear => (
+            format!("{f}_count = {}\n{f}_spacing = {spacing}\n", spec.count),
+            format!("GridLocations({f}_spacing, 0, {f}_count, 1)"),
+        ),
+    };
+    format!(
+        "# {}: {} instances from one loop, centred on the origin.\n\
+         {variables}\
+         for loc in {locations}.locations:\n    \
+         part = part {op} loc * {f}\n",
+        spec.label(),
+        spec.instances(),
+    )
+}
+
+/// Prompt section with a loop per detected pattern, or `None` when `text`
+/// names none. Bolt circles already covered by a fastener helper are left
+/// to that helper.
+pub fn pattern_features_section(text: &str) -> Option<String> {
+    let fasteners = detect_fastener_features(text);
+    let mut specs: Vec<PatternSpec> = detect_patterns(text)
+        .into_iter()
+        .filter(|p| {
+            p.kind != PatternKind::Circular
+                || !fasteners
+                    .iter()
+                    .any(|f| f.count == Some(p.count) && f.bolt_circle_diameter == p.diameter)
+        })
+        .collect();
+    if specs.is_empty() {
+        return None;
+    }
+    // Two patterns of the same feature need distinct variable names.
+    for i in 1..specs.len() {
+        let stem = specs[i].feature.clone();
+        let taken = specs[..i]
+            .iter()
+            .filter(|p| p.feature == stem || p.feature.starts_with(&format!("{}_", stem)))
+            .count();
+        if taken > 0 {
+            specs[i].feature = format!("{}_{}", specs[i].feature, taken + 1);
+        }
+    }
+
+    let mut out = format!(
+        "{}\n\
+         The request repeats features in a pattern. Implement each pattern with its loop below \
+         as the required construction: build ONE instance under the loop's feature name, \
+         centred on the origin at the right height, and let the loop place every copy. Do NOT \
+         write the copies out one operation at a time, and keep the count and spacing \
+         variables.\n",
+        PATTERN_FEATURES_HEADING
+    );
+    for spec in &specs {
+        out.push_str(&format!(
+            "\n### {}\n```python\n{}```\n",
+            spec.label(),
+            generate_pattern_loop(spec)
+        ));
+    }
+    Some(out)
+}
+
+/// Largest number of identical cut lines in `code` once numbers are masked,
+/// e.g. 24 `part -= Pos(x, y, 0) * slot` lines with different literals.
+pub fn literal_repeated_cuts(code: &str) -> usize {
+    let cut_re = Regex::new(
+        r"-=|\.cut\(|Mode\.SUBTRACT|\b(?:CounterBore|CounterSink)?Hole\(|-\s*(?:Pos|Location|Rot)\(",
+    )
+    .unwrap();
+    let number_re = Regex::new(r"-?\d+(?:\.\d+)?").unwrap();
+    let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for line in code.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if cut_re.is_match(line) {
+            *counts
+                .entry(number_re.replace_all(line, "#").to_string())
+                .or_default() += 1;
+        }
+    }
+    counts.into_values().max().unwrap_or(0)
+}
+
+/// Hole diameters drawn in `code`: `Hole`/`CounterSinkHole`/`CounterBoreHole`
+/// and `Cylinder` radii, `.hole(d)` calls, and `*hole*`/`*clearance*`
+/// variables named as a diameter or radius.
+fn drawn_hole_diameters(code: &str) -> Vec<f64> {
+    let radius_call =
+        Regex::new(r"\b(?:Hole|CounterSinkHole|CounterBoreHole|Cylinder)\(\s*(?:radius\s*=\s*)?(\d+(?:\.\d+)?)\s*[,)]")
+            .unwrap();
+    let diameter_call =
+        Regex::new(r"\.hole\(\s*(?:diameter\s*=\s*)?(\d+(?:\.\d+)?)\s*[,)]").unwrap();
+    let variable = Regex::new(
+        r"(?im)^\s*\w*(?:hole|clearance|screw|bolt)\w*?_?(d|dia|diameter|r|rad|radius)\s*=\s*(\d+(?:\.\d+)?)\s*(?:#.*)?$",
+    )
+    .unwrap();
+    let mut diameters: Vec<f64> = Vec::new();
+    for c in radius_call.captures_iter(code) {
+        if let Ok(r) = c[1].parse::<f64>() {
+            diameters.push(r * 2.0);
+        }
+    }
+    for c in diameter_call.captures_iter(code) {
+        if let Ok(d) = c[1].parse::<f64>() {
+            diameters.push(d);
+        }
+    }
+    for c in variable.captures_iter(code) {
+        if let Ok(v) = c[2].parse::<f64>() {
+            let is_radius = c[1].to_lowercase().starts_with('r');
+            diameters.push(if is_radius { v * 2.0 } else { v });
+        }
+    }
+    diameters
+}
+
+/// A drawn hole that looks meant for a requested fastener but misses every
+/// standard diameter for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonstandardHole {
+    pub diameter: f64,
+    pub size: &'static str,
+    pub kind: FeatureKind,
+    pub expected: Vec<f64>,
+}
+
+/// Holes in `code` within reach of a fastener `requested` (tap drill - 0.3mm
+/// up to normal clearance + 0.6mm) that match none of the standard diameters
+/// of the requested features at that size.
+pub fn nonstandard_holes(code: &str, requested: &[FeatureSpec]) -> Vec<NonstandardHole> {
+    let mut out: Vec<NonstandardHole> = Vec::new();
+    for diameter in drawn_hole_diameters(code) {
+        let near: Vec<&FeatureSpec> = requested
+            .iter()
+            .filter(|f| {
+                diameter >= f.size.tap_drill - 0.3 && diameter <= f.size.clearance_normal + 0.6
+            })
+            .collect();
+        let Some(first) = near.first() else {
+            continue;
+        };
+        let standard = near.iter().any(|f| {
+            f.kind
+                .standard_diameters(f.size)
+                .iter()
+                .any(|d| (d - diameter).abs() <= STANDARD_DIAMETER_TOLERANCE)
+        });
+        if standard || out.iter().any(|h| (h.diameter - diameter).abs() < 1e-9) {
+            continue;
+        }
+        let mut expected = first.kind.standard_diameters(first.size);
+        expected.truncate(2);
+        out.push(NonstandardHole {
+            diameter,
+            size: first.size.name,
+            kind: first.kind,
+            expected,
+        });
+    }
+    out
+}
+
+impl NonstandardHole {
+    pub fn message(&self) -> String {
+        let expected: Vec<String> = self.expected.iter().map(|d| fmt_mm(*d)).collect();
+        format!(
+            "Hole drawn at {}mm for a requested {} {}; the standard diameter is {}mm. \
+             Use the standard fastener helper instead of hand-rolled dimensions.",
+            fmt_mm(self.diameter),
+            self.size,
+            self.kind.label(),
+            expected.join(" or ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(kind: FeatureKind, size: &str) -> FeatureSpec {
+        FeatureSpec {
+            kind,
+            size: fastener_size(size).unwrap(),
+            count: None,
+            bolt_circle_diameter: None,
+        }
+    }
+
+    #[test]
+    fn test_table_is_ordered_and_consistent() {
+        assert_eq!(FASTENER_SIZES.len(), 7);
+        for pair in FASTENER_SIZES.windows(2) {
+            assert!(pair[0].nominal < pair[1].nominal);
+        }
+        for s in FASTENER_SIZES {
+            assert!(s.tap_drill < s.nominal, "{}", s.name);
+            assert!(s.nominal < s.clearance_close && s.clearance_close < s.clearance_normal);
+            assert!(s.countersink_diameter > s.clearance_normal, "{}", s.name);
+            assert!(s.counterbore_diameter > s.clearance_normal, "{}", s.name);
+            assert!(s.boss_diameter > s.insert_hole, "{}", s.name);
+        }
+        assert_eq!(fastener_size("m4").unwrap().clearance_normal, 4.5);
+        assert!(fastener_size("M7").is_none());
+    }
+
+    #[test]
+    fn test_countersunk_bolt_circle_snippet() {
+        let spec = FeatureSpec {
+            count: Some(4),
+            bolt_circle_diameter: Some(30.0),
+            ..spec(FeatureKind::CountersunkHole, "M4")
+        };
+        let code = generate_hole_pattern(&spec);
+        assert!(code.starts_with(
+            "def m4_countersunk_holes(part, top_z, depth, \
+             positions=[(15, 0), (0, 15), (-15, 0), (0, -15)]):\n"
+        ));
+        // 4.5mm normal clearance, 9.4mm countersink dropping 2.45mm at 90°.
+        assert!(code.contains("* Cylinder(2.25, depth + 0.1, "));
+        assert!(code.contains("part = part - Pos(x, y, top_z - 2.45) * Cone(2.25, 4.75, 2.5, "));
+        assert!(code.ends_with("    return part\n"));
+        // Same spec, same snippet.
+        assert_eq!(generate_hole_pattern(&spec), code);
+    }
+
+    #[test]
+    fn test_snippets_for_other_kinds() {
+        let cbore = generate_hole_pattern(&spec(FeatureKind::CounterboredHole, "M3"));
+        assert!(cbore.starts_with("def m3_counterbored_holes(part, top_z, depth, positions):"));
+        assert!(cbore.contains("Pos(x, y, top_z - 3.2) * Cylinder(3.25, 3.25, "));
+
+        let tapped = generate_hole_pattern(&spec(FeatureKind::TappedHole, "M2.5"));
+        assert!(tapped.starts_with("def m2_5_tapped_holes("));
+        assert!(tapped.contains("tap drill 2.05mm"));
+        assert!(tapped.contains("Cylinder(1.025, depth + 0.1, "));
+
+        let boss = generate_hole_pattern(&spec(FeatureKind::InsertBoss, "M3"));
+        assert!(boss.starts_with("def m3_insert_bosses(part, base_z, height, positions):"));
+        assert!(
+            boss.contains("part = part + Pos(x, y, base_z - 0.05) * Cylinder(4, height + 0.05, ")
+        );
+        assert!(
+            boss.contains("part = part - Pos(x, y, base_z + height - 5.7) * Cylinder(2, 5.75, ")
+        );
+    }
+
+    #[test]
+    fn test_detects_common_phrasings() {
+        let found = detect_fastener_features(
+            "A round flange with four M4 countersunk holes on a 30mm bolt circle.",
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, FeatureKind::CountersunkHole);
+        assert_eq!(found[0].size.name, "M4");
+        assert_eq!(found[0].count, Some(4));
+        assert_eq!(found[0].bolt_circle_diameter, Some(30.0));
+
+        let found = detect_fastener_features("Add heat-set insert bosses for M3 in each corner");
+        assert_eq!(found[0].kind, FeatureKind::InsertBoss);
+        assert_eq!(found[0].size.name, "M3");
+        assert_eq!(found[0].count, None);
+
+        let found = detect_fastener_features(
+            "Base plate 80x60x5 with 4x M5 counterbored holes. Lid uses two M2.5 clearance holes; \
+             motor mount has 6 M3 tapped holes, PCD 40mm",
+        );
+        let summary: Vec<(FeatureKind, &str, Option<usize>, Option<f64>)> = found
+            .iter()
+            .map(|f| (f.kind, f.size.name, f.count, f.bolt_circle_diameter))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (FeatureKind::CounterboredHole, "M5", Some(4), None),
+                (FeatureKind::ClearanceHole, "M2.5", Some(2), None),
+                (FeatureKind::TappedHole, "M3", Some(6), Some(40.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detection_ignores_non_fastener_mentions() {
+        assert!(detect_fastener_features("A 40mm cube with 3mm fillets").is_empty());
+        assert!(detect_fastener_features("Label the lid M4 in raised text").is_empty());
+        assert!(detect_fastener_features("Hole for a 5mm LED, 30mm bolt circle").is_empty());
+        assert!(fastener_features_section("a plain box").is_none());
+    }
+
+    #[test]
+    fn test_features_section_lists_helpers() {
+        let section = fastener_features_section(
+            "Bracket with M4 clearance holes and four M3 heat-set inserts on a 20mm bolt circle",
+        )
+        .unwrap();
+        assert!(section.starts_with(FASTENER_FEATURES_HEADING));
+        assert!(section.contains("### M4 clearance hole"));
+        assert!(section.contains(
+            "Call: `part = m4_clearance_holes(part, top_z, depth, positions=[(x, y), ...])`"
+        ));
+        assert!(section.contains("Call: `part = m3_insert_bosses(part, base_z, height)`"));
+        assert!(section.contains("def m3_insert_bosses(part, base_z, height, positions=[(10, 0)"));
+    }
+
+    #[test]
+    fn test_detects_pattern_phrasings() {
+        // kind, feature, count x rows, spacing, diameter
+        let summary = |text: &str| -> Vec<String> {
+            detect_patterns(text)
+                .iter()
+                .map(|p| {
+                    format!(
+                        "{:?} {} {}x{} {:?} {:?}",
+                        p.kind, p.feature, p.count, p.rows, p.spacing, p.diameter
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            summary("A vented lid with a grid of 6x4 ventilation slots spaced 12mm"),
+            vec!["Grid slot 6x4 Some(12.0) None"]
+        );
+        assert_eq!(
+            summary("Tray with a 3 by 2 array of pockets"),
+            vec!["Grid pocket 3x2 None None"]
+        );
+        assert_eq!(
+            summary("Flange with 8 holes equally spaced on a 60mm circle"),
+            vec!["Circular hole 8x1 None Some(60.0)"]
+        );
+        assert_eq!(
+            summary("Hub with six spokes around a 40 mm pitch circle"),
+            vec!["Circular spoke 6x1 None Some(40.0)"]
+        );
+        assert_eq!(
+            summary("A row of 5 slots at 10mm pitch. Then 4 ribs spaced 8.5mm apart"),
+            vec![
+                "Linear slot 5x1 Some(10.0) None",
+                "Linear rib 4x1 Some(8.5) None",
+            ]
+        );
+        assert!(detect_patterns("Box 80x60x5 mm with 4 holes").is_empty());
+        assert!(detect_patterns("Plate 100 x 60 mm, two holes at 10mm from the edge").is_empty());
+        assert!(detect_patterns("Plate with 4 holes spaced 10mm from the edge").is_empty());
+        assert!(pattern_features_section("a plain box").is_none());
+    }
+
+    #[test]
+    fn test_pattern_loop_snippets() {
+        let grid = &detect_patterns("a grid of 6x4 ventilation slots spaced 12mm")[0];
+        let code = generate_pattern_loop(grid);
+        assert!(code.starts_with("# 6 x 4 grid (`slot`): 24 instances from one loop"));
+        assert!(code.contains(
+            "slot_columns = 6\nslot_rows = 4\nslot_x_spacing = 12\nslot_y_spacing = slot_x_spacing\n"
+        ));
+        assert!(code.ends_with(
+            "for loc in GridLocations(slot_x_spacing, slot_y_spacing, slot_columns, slot_rows).locations:\n    \
+             part = part - loc * slot\n"
+        ));
+
+        let circle = &detect_patterns("8 holes equally spaced on a 60mm circle")[0];
+        let code = generate_pattern_loop(circle);
+        assert!(code.contains("hole_count = 8\nhole_circle_diameter = 60\n"));
+        assert!(code.contains(
+            "for loc in PolarLocations(hole_circle_diameter / 2, hole_count).locations:\n    \
+             part = part - loc * hole\n"
+        ));
+
+        // Additive features are unioned; a missing spacing is left for the model.
+        let ribs = &detect_patterns("a row of 4 ribs")[0];
+        let code = generate_pattern_loop(ribs);
+        assert!(code.contains("rib_count = 4\nrib_spacing = ...  # not given"));
+        assert!(code.contains("GridLocations(rib_spacing, 0, rib_count, 1)"));
+        assert!(code.ends_with("part = part + loc * rib\n"));
+    }
+
+    #[test]
+    fn test_pattern_section_names_and_skips_fastener_circles() {
+        let section = pattern_features_section(
+            "Panel with a grid of 3x3 holes spaced 10mm; a row of 4 holes at 6mm pitch; \
+             four M4 clearance holes on a 30mm bolt circle",
+        )
+        .unwrap();
+        assert!(section.starts_with(PATTERN_FEATURES_HEADING));
+        assert!(section.contains("### 3 x 3 grid (`hole`)"));
+        assert!(section.contains("### Row of 4 (`hole_2`)"));
+        assert!(section.contains("part = part - loc * hole_2\n"));
+        // The bolt circle is the fastener helper's job.
+        assert!(!section.contains("circle"));
+    }
+
+    #[test]
+    fn test_literal_repeated_cuts() {
+        let unrolled: String = (0..6)
+            .map(|i| format!("part -= Pos({}, 0, 0) * Box(2, 10, 5)\n", i * 4))
+            .collect();
+        assert_eq!(literal_repeated_cuts(&unrolled), 6);
+        let looped =
+            "for loc in GridLocations(4, 0, 6, 1).locations:\n    part -= loc * Box(2, 10, 5)\n";
+        assert_eq!(literal_repeated_cuts(looped), 1);
+        assert_eq!(literal_repeated_cuts("result = Box(10, 10, 10)\n"), 0);
+    }
+
+    #[test]
+    fn test_flags_nonstandard_hole_for_named_size() {
+        let requested = detect_fastener_features("plate with M4 clearance holes");
+        let bad = "hole_d = 3.8\nresult = Box(40, 40, 5) - Cylinder(hole_d / 2, 5)\n";
+        let holes = nonstandard_holes(bad, &requested);
+        assert_eq!(holes.len(), 1);
+        assert_eq!(holes[0].diameter, 3.8);
+        assert!(holes[0]
+            .message()
+            .contains("standard diameter is 4.3 or 4.5mm"));
+
+        let good = "hole_r = 2.25\nresult = Box(40, 40, 5) - Cylinder(hole_r, 5)\n";
+        assert!(nonstandard_holes(good, &requested).is_empty());
+        // Unrelated round features far from the fastener size are left alone.
+        let boss = "result = Box(40, 40, 5) + Cylinder(10, 8)\n";
+        assert!(nonstandard_holes(boss, &requested).is_empty());
+        // Nothing requested, nothing to compare against.
+        assert!(nonstandard_holes(bad, &[]).is_empty());
+    }
+}
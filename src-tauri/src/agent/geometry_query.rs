@@ -0,0 +1,290 @@
+//! Ad-hoc measurements answered from a model's BREP by the runner's
+//! `--query` mode: distance between picked points, wall thickness along a
+//! ray, and the radius of the face under a point.
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::views::Bounds;
+use crate::error::AppError;
+
+/// Wall-clock budget for one query, including re-running the script.
+pub const QUERY_TIMEOUT_MS: u64 = 10_000;
+/// Smallest slack around the bounding box for query points (mm).
+const MIN_BOUNDS_MARGIN: f64 = 1.0;
+/// Slack around the bounding box as a fraction of its diagonal.
+const BOUNDS_MARGIN_FRACTION: f64 = 0.05;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GeometryQuery {
+    /// Distance between two picked points, each snapped onto its nearest face.
+    PointDistance { a: [f64; 3], b: [f64; 3] },
+    /// Material crossed by a ray from `point` along `direction`, usually the
+    /// inward normal of the picked face.
+    Thickness {
+        point: [f64; 3],
+        direction: [f64; 3],
+    },
+    /// Radius of the face closest to `point`: cylinder, cone, sphere, or the
+    /// minor radius of a torus (fillets).
+    FaceRadius { point: [f64; 3] },
+}
+
+/// A face used to answer a query, indexed like the runner's topology sidecar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryEntity {
+    pub kind: String,
+    /// -1 when the face could not be matched to the shape's face list.
+    pub index: i64,
+    /// "plane", "cylinder", "cone", "sphere", "torus", "bspline", ...
+    pub surface_type: String,
+}
+
+/// Measured answer to a `GeometryQuery`, in mm.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GeometryQueryResponse {
+    PointDistance {
+        distance: f64,
+        /// The picked points after snapping onto the surface.
+        a: [f64; 3],
+        b: [f64; 3],
+        entities: Vec<QueryEntity>,
+    },
+    Thickness {
+        thickness: f64,
+        entry: [f64; 3],
+        exit: [f64; 3],
+        entities: Vec<QueryEntity>,
+    },
+    FaceRadius {
+        radius: f64,
+        /// Closest point on the measured face.
+        point: [f64; 3],
+        entities: Vec<QueryEntity>,
+    },
+}
+
+impl GeometryQueryResponse {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::PointDistance { .. } => "point_distance",
+            Self::Thickness { .. } => "thickness",
+            Self::FaceRadius { .. } => "face_radius",
+        }
+    }
+}
+
+impl GeometryQuery {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::PointDistance { .. } => "point_distance",
+            Self::Thickness { .. } => "thickness",
+            Self::FaceRadius { .. } => "face_radius",
+        }
+    }
+
+    fn points(&self) -> Vec<[f64; 3]> {
+        match self {
+            Self::PointDistance { a, b } => vec![*a, *b],
+            Self::Thickness { point, .. } | Self::FaceRadius { point } => vec![*point],
+        }
+    }
+
+    /// Reject non-finite coordinates and a zero-length ray direction.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let finite = |v: &[f64; 3]| v.iter().all(|c| c.is_finite());
+        if !self.points().iter().all(finite) {
+            return Err(AppError::ConfigError(
+                "Query points must have finite coordinates".into(),
+            ));
+        }
+        if let Self::Thickness { direction, .. } = self {
+            let length = direction.iter().map(|c| c * c).sum::<f64>().sqrt();
+            if !finite(direction) || length < 1e-9 {
+                return Err(AppError::ConfigError(
+                    "Thickness query needs a non-zero ray direction".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject points clearly outside `bounds`. Picked points lie on the
+    /// surface, so one beyond a small margin is a stale or mistaken pick.
+    pub fn check_bounds(&self, bounds: &Bounds) -> Result<(), AppError> {
+        let diagonal = (0..3)
+            .map(|i| (bounds.max[i] - bounds.min[i]).powi(2))
+            .sum::<f64>()
+            .sqrt();
+        let margin = (diagonal * BOUNDS_MARGIN_FRACTION).max(MIN_BOUNDS_MARGIN);
+        for point in self.points() {
+            let outside = (0..3)
+                .any(|i| point[i] < bounds.min[i] - margin || point[i] > bounds.max[i] + margin);
+            if outside {
+                return Err(AppError::ConfigError(format!(
+                    "Query point ({:.2}, {:.2}, {:.2}) is outside the model's bounding box",
+                    point[0], point[1], point[2]
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What the runner writes for a query: the shape's bounding box and the answer.
+#[derive(Deserialize)]
+struct RunnerAnswer {
+    bounds_min: [f64; 3],
+    bounds_max: [f64; 3],
+    response: GeometryQueryResponse,
+}
+
+/// Parse the runner's answer to `query`, checking the query points against
+/// the bounding box the runner measured on the BREP.
+pub fn parse_answer(query: &GeometryQuery, json: &str) -> Result<GeometryQueryResponse, AppError> {
+    let answer: RunnerAnswer = serde_json::from_str(json)?;
+    query.check_bounds(&Bounds {
+        min: answer.bounds_min,
+        max: answer.bounds_max,
+    })?;
+    if answer.response.kind() != query.kind() {
+        return Err(AppError::CadError(format!(
+            "Runner answered a {} query with a {} result",
+            query.kind(),
+            answer.response.kind()
+        )));
+    }
+    Ok(answer.response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLATE_BOUNDS: &str = r#""bounds_min": [0, 0, 0], "bounds_max": [40, 20, 4]"#;
+
+    #[test]
+    fn test_query_spec_round_trips_tagged() {
+        let query: GeometryQuery = serde_json::from_str(
+            r#"{"kind": "thickness", "point": [10, 10, 4], "direction": [0, 0, -1]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            GeometryQuery::Thickness {
+                point: [10.0, 10.0, 4.0],
+                direction: [0.0, 0.0, -1.0]
+            }
+        );
+        let json = serde_json::to_value(&query).unwrap();
+        assert_eq!(json["kind"], "thickness");
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_input() {
+        let zero_ray = GeometryQuery::Thickness {
+            point: [0.0; 3],
+            direction: [0.0; 3],
+        };
+        assert!(zero_ray.validate().is_err());
+        let nan = GeometryQuery::FaceRadius {
+            point: [f64::NAN, 0.0, 0.0],
+        };
+        assert!(nan.validate().is_err());
+        let ok = GeometryQuery::PointDistance {
+            a: [0.0; 3],
+            b: [1.0, 2.0, 3.0],
+        };
+        assert!(ok.validate().is_ok());
+    }
+
+    #[test]
+    fn test_points_outside_bounds_are_rejected() {
+        let bounds = Bounds {
+            min: [0.0; 3],
+            max: [40.0, 20.0, 4.0],
+        };
+        // Within the 1 mm / 5% margin of a 44.9 mm diagonal.
+        let near = GeometryQuery::FaceRadius {
+            point: [42.0, 10.0, 2.0],
+        };
+        assert!(near.check_bounds(&bounds).is_ok());
+        let far = GeometryQuery::PointDistance {
+            a: [10.0, 10.0, 4.0],
+            b: [10.0, 10.0, 30.0],
+        };
+        let err = far.check_bounds(&bounds).unwrap_err();
+        assert!(err.to_string().contains("outside the model's bounding box"));
+    }
+
+    #[test]
+    fn test_parses_canned_thickness_answer() {
+        let query = GeometryQuery::Thickness {
+            point: [10.0, 10.0, 4.0],
+            direction: [0.0, 0.0, -1.0],
+        };
+        let json = format!(
+            r#"{{{}, "response": {{"kind": "thickness", "thickness": 4.0,
+                "entry": [10, 10, 4], "exit": [10, 10, 0],
+                "entities": [{{"kind": "face", "index": 5, "surface_type": "plane"}},
+                             {{"kind": "face", "index": 4, "surface_type": "plane"}}]}}}}"#,
+            PLATE_BOUNDS
+        );
+        match parse_answer(&query, &json).unwrap() {
+            GeometryQueryResponse::Thickness {
+                thickness,
+                exit,
+                entities,
+                ..
+            } => {
+                assert_eq!(thickness, 4.0);
+                assert_eq!(exit, [10.0, 10.0, 0.0]);
+                assert_eq!(entities[0].index, 5);
+            }
+            other => panic!("unexpected answer {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parses_canned_radius_and_distance_answers() {
+        let query = GeometryQuery::FaceRadius {
+            point: [20.0, 10.0, 4.0],
+        };
+        let json = format!(
+            r#"{{{}, "response": {{"kind": "face_radius", "radius": 3.0, "point": [23, 10, 4],
+                "entities": [{{"kind": "face", "index": 9, "surface_type": "cylinder"}}]}}}}"#,
+            PLATE_BOUNDS
+        );
+        let answer = parse_answer(&query, &json).unwrap();
+        assert!(matches!(
+            answer,
+            GeometryQueryResponse::FaceRadius { radius, ref entities, .. }
+                if radius == 3.0 && entities[0].surface_type == "cylinder"
+        ));
+
+        // An answer of the wrong kind is refused.
+        let distance = GeometryQuery::PointDistance {
+            a: [0.0; 3],
+            b: [40.0, 0.0, 0.0],
+        };
+        let err = parse_answer(&distance, &json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("point_distance query with a face_radius"));
+    }
+
+    #[test]
+    fn test_answer_bounds_recheck_points() {
+        // No cached geometry was available; the runner's bounds catch the pick.
+        let query = GeometryQuery::FaceRadius {
+            point: [0.0, 0.0, 50.0],
+        };
+        let json = format!(
+            r#"{{{}, "response": {{"kind": "face_radius", "radius": 3.0, "point": [0, 0, 4],
+                "entities": []}}}}"#,
+            PLATE_BOUNDS
+        );
+        assert!(parse_answer(&query, &json).is_err());
+    }
+}
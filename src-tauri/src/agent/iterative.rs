@@ -1,6 +1,7 @@
 use base64::Engine;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
 use crate::agent::design;
 use crate::agent::executor::{self, ExecutionContext};
@@ -9,7 +10,7 @@ use crate::agent::rules::AgentRules;
 use crate::agent::validate;
 use crate::ai::message::ChatMessage;
 use crate::ai::provider::TokenUsage;
-use crate::commands::chat::{build_retry_prompt, create_provider};
+use crate::commands::chat::{build_retry_prompt, create_code_provider};
 use crate::config::AppConfig;
 use crate::error::AppError;
 
@@ -21,7 +22,7 @@ const RISKY_OPS: &[&str] = &["shell", "loft", "sweep", "revolve"];
 // ---------------------------------------------------------------------------
 
 /// A single step parsed from the Build Plan.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct BuildStep {
     pub index: usize,
     pub name: String,
@@ -30,7 +31,7 @@ pub struct BuildStep {
 }
 
 /// Info about a step that was skipped (for retry).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SkippedStep {
     pub step_index: usize,
     pub name: String,
@@ -317,7 +318,7 @@ pub async fn run_iterative_build_from(
 
         // Generate code for this step
         let step_prompt = build_step_prompt(&current_code, step, design_plan, user_request);
-        let provider = create_provider(config)?;
+        let provider = create_code_provider(config)?;
         let messages = vec![
             ChatMessage {
                 role: "system".to_string(),
@@ -386,7 +387,7 @@ pub async fn run_iterative_build_from(
                         // Ask AI for a fix
                         let retry_prompt =
                             build_step_retry_prompt(&extracted, &error_msg, step, design_plan);
-                        let retry_provider = create_provider(config)?;
+                        let retry_provider = create_code_provider(config)?;
                         let retry_messages = vec![
                             ChatMessage {
                                 role: "system".to_string(),
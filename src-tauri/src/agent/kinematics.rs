@@ -0,0 +1,363 @@
+use std::collections::VecDeque;
+
+use regex::Regex;
+use serde::Serialize;
+use schemars::JsonSchema;
+
+use crate::commands::parallel::PartSpec;
+
+/// Name tokens that mark a part as a fastener.
+const FASTENER_TOKENS: &[&str] = &[
+    "screw",
+    "screws",
+    "bolt",
+    "bolts",
+    "nut",
+    "nuts",
+    "washer",
+    "washers",
+    "rivet",
+    "rivets",
+    "dowel",
+    "pin",
+    "pins",
+    "fastener",
+    "fasteners",
+    "standoff",
+    "insert",
+];
+
+/// Words that describe intended motion between two parts.
+const MOTION_PATTERN: &str =
+    r"(?i)\b(hinge[sd]?|pivot\w*|rotat\w*|swivel\w*|slid\w*|slider\w*|snap\w*)\b";
+
+/// How a part behaves in an assembly with intended motion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionRole {
+    Static,
+    Moving,
+    Fastener,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct PartKinematics {
+    pub name: String,
+    pub role: MotionRole,
+    /// Sub-assembly of a moving part and everything rigidly attached to it.
+    pub group: Option<String>,
+    pub note: Option<String>,
+}
+
+impl PartKinematics {
+    /// Assembly path of the part, e.g. `lid_group/lid`.
+    pub fn hierarchical_name(&self) -> String {
+        match &self.group {
+            Some(group) => format!("{}/{}", group, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Connection {
+    declared_by: usize,
+    other: usize,
+    motion: bool,
+}
+
+fn is_fastener(name: &str) -> bool {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|token| FASTENER_TOKENS.contains(&token))
+}
+
+fn mentions(text: &str, name: &str) -> bool {
+    let spaced = regex::escape(&name.replace('_', " "));
+    let raw = regex::escape(name);
+    Regex::new(&format!(r"(?i)\b(?:{}|{})\b", raw, spaced))
+        .map(|re| re.is_match(text))
+        .unwrap_or(false)
+}
+
+/// Statements a part makes about itself: description sentences and constraints.
+fn statements(part: &PartSpec) -> Vec<&str> {
+    part.description
+        .split(['.', ';'])
+        .chain(part.constraints.iter().map(|c| c.as_str()))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn connections(parts: &[PartSpec], motion_re: &Regex) -> Vec<Connection> {
+    let mut found: Vec<Connection> = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        for statement in statements(part) {
+            let motion = motion_re.is_match(statement);
+            for (j, other) in parts.iter().enumerate() {
+                if i == j || !mentions(statement, &other.name) {
+                    continue;
+                }
+                match found
+                    .iter_mut()
+                    .find(|c| c.declared_by == i && c.other == j)
+                {
+                    Some(existing) => existing.motion |= motion,
+                    None => found.push(Connection {
+                        declared_by: i,
+                        other: j,
+                        motion,
+                    }),
+                }
+            }
+        }
+    }
+    found
+}
+
+fn distances_from(root: usize, count: usize, links: &[Connection]) -> Vec<Option<usize>> {
+    let mut distance = vec![None; count];
+    distance[root] = Some(0);
+    let mut queue = VecDeque::from([root]);
+    while let Some(current) = queue.pop_front() {
+        let next = distance[current].map(|d| d + 1);
+        for link in links {
+            let neighbour = if link.declared_by == current {
+                link.other
+            } else if link.other == current {
+                link.declared_by
+            } else {
+                continue;
+            };
+            if distance[neighbour].is_none() {
+                distance[neighbour] = next;
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    distance
+}
+
+/// Tag each part as static, moving or fastener from its name, motion
+/// keywords (hinge, slide, rotate, snap, ...) and the parts its description
+/// and constraints refer to. The part others move against (then the
+/// best-connected one) is the static base; in a motion connection the part
+/// away from the base moves, and parts rigidly attached to a mover join its
+/// `{mover}_group`. Parts that mention motion without a resolvable partner
+/// stay static with a note.
+pub fn classify_parts(parts: &[PartSpec]) -> Vec<PartKinematics> {
+    let motion_re = Regex::new(MOTION_PATTERN).unwrap();
+    let fastener: Vec<bool> = parts.iter().map(|p| is_fastener(&p.name)).collect();
+    let links: Vec<Connection> = connections(parts, &motion_re)
+        .into_iter()
+        .filter(|c| !fastener[c.declared_by] && !fastener[c.other])
+        .collect();
+
+    let mut result: Vec<PartKinematics> = parts
+        .iter()
+        .zip(&fastener)
+        .map(|(p, &is_fastener)| PartKinematics {
+            name: p.name.clone(),
+            role: if is_fastener {
+                MotionRole::Fastener
+            } else {
+                MotionRole::Static
+            },
+            group: None,
+            note: None,
+        })
+        .collect();
+
+    let root = (0..parts.len()).filter(|&i| !fastener[i]).max_by_key(|&i| {
+        let degree = links
+            .iter()
+            .filter(|c| c.declared_by == i || c.other == i)
+            .count();
+        let motion_target = links.iter().filter(|c| c.motion && c.other == i).count();
+        // Earlier parts win ties (`max_by_key` keeps the last maximum).
+        (motion_target, degree, usize::MAX - i)
+    });
+
+    if let Some(root) = root {
+        let distance = distances_from(root, parts.len(), &links);
+        let mut movers: Vec<usize> = Vec::new();
+        for link in links.iter().filter(|c| c.motion) {
+            let (a, b) = (link.declared_by, link.other);
+            let mover = if a == root {
+                b
+            } else if b == root {
+                a
+            } else if distance[b] > distance[a] {
+                b
+            } else {
+                a
+            };
+            if !movers.contains(&mover) {
+                movers.push(mover);
+            }
+        }
+
+        for &mover in &movers {
+            let group = format!("{}_group", parts[mover].name);
+            let mut queue = VecDeque::from([mover]);
+            while let Some(current) = queue.pop_front() {
+                if result[current].group.is_some() {
+                    continue;
+                }
+                result[current].role = MotionRole::Moving;
+                result[current].group = Some(group.clone());
+                for link in links.iter().filter(|c| !c.motion) {
+                    let neighbour = if link.declared_by == current {
+                        link.other
+                    } else if link.other == current {
+                        link.declared_by
+                    } else {
+                        continue;
+                    };
+                    if neighbour != root && !movers.contains(&neighbour) {
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+        }
+    }
+
+    for (i, part) in parts.iter().enumerate() {
+        if result[i].role != MotionRole::Static || Some(i) == root {
+            continue;
+        }
+        let has_motion_link = links
+            .iter()
+            .any(|c| c.motion && (c.declared_by == i || c.other == i));
+        if let Some(word) = statements(part)
+            .iter()
+            .find_map(|s| motion_re.find(s).map(|m| m.as_str().to_lowercase()))
+        {
+            if !has_motion_link {
+                result[i].note = Some(format!(
+                    "mentions motion ('{}') but no connected part could be identified; treated as static",
+                    word
+                ));
+            }
+        }
+    }
+
+    result
+}
+
+/// Roles with at least one moving sub-assembly, i.e. worth grouping.
+pub fn has_moving_groups(kinematics: &[PartKinematics]) -> bool {
+    kinematics.iter().any(|k| k.group.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(name: &str, description: &str, constraints: &[&str]) -> PartSpec {
+        PartSpec {
+            name: name.to_string(),
+            description: description.to_string(),
+            position: [0.0, 0.0, 0.0],
+            constraints: constraints.iter().map(|c| c.to_string()).collect(),
+            reliability_profile: None,
+        }
+    }
+
+    fn role_of<'a>(tags: &'a [PartKinematics], name: &str) -> &'a PartKinematics {
+        tags.iter().find(|k| k.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_hinged_box_groups_lid_and_latch() {
+        let parts = vec![
+            part(
+                "base",
+                "Open-top box 80x60x40mm with hinge knuckles on the back edge",
+                &[],
+            ),
+            part(
+                "lid",
+                "Flat lid 80x60x4mm",
+                &["hinged to the base along the back edge"],
+            ),
+            part(
+                "hinge_pin",
+                "3mm pin through the base and lid knuckles",
+                &[],
+            ),
+            part(
+                "latch",
+                "Small latch",
+                &["attached to the front of the lid"],
+            ),
+        ];
+        let tags = classify_parts(&parts);
+
+        assert_eq!(role_of(&tags, "base").role, MotionRole::Static);
+        assert_eq!(role_of(&tags, "base").group, None);
+        assert_eq!(role_of(&tags, "hinge_pin").role, MotionRole::Fastener);
+
+        let lid = role_of(&tags, "lid");
+        assert_eq!(lid.role, MotionRole::Moving);
+        assert_eq!(lid.hierarchical_name(), "lid_group/lid");
+        let latch = role_of(&tags, "latch");
+        assert_eq!(latch.role, MotionRole::Moving);
+        assert_eq!(latch.hierarchical_name(), "lid_group/latch");
+        assert!(has_moving_groups(&tags));
+        assert!(tags.iter().all(|k| k.note.is_none()));
+    }
+
+    #[test]
+    fn test_plan_without_motion_keywords_is_all_static() {
+        let parts = vec![
+            part("housing", "Rectangular housing 50x30x20mm", &[]),
+            part(
+                "cover",
+                "Cover plate 50x30x2mm",
+                &["sits on top of the housing"],
+            ),
+            part(
+                "m3_screw",
+                "M3x8 screw",
+                &["through the cover into the housing"],
+            ),
+        ];
+        let tags = classify_parts(&parts);
+
+        assert_eq!(role_of(&tags, "housing").role, MotionRole::Static);
+        assert_eq!(role_of(&tags, "cover").role, MotionRole::Static);
+        assert_eq!(role_of(&tags, "m3_screw").role, MotionRole::Fastener);
+        assert!(!has_moving_groups(&tags));
+        assert!(tags.iter().all(|k| k.note.is_none()));
+    }
+
+    #[test]
+    fn test_motion_without_partner_defaults_to_static_with_note() {
+        let parts = vec![
+            part("frame", "Cabinet frame 300x200x150mm", &[]),
+            part("drawer", "Sliding drawer 280x190x60mm", &[]),
+        ];
+        let tags = classify_parts(&parts);
+        let drawer = role_of(&tags, "drawer");
+        assert_eq!(drawer.role, MotionRole::Static);
+        assert!(drawer.note.as_deref().unwrap().contains("sliding"));
+
+        let parts = vec![
+            part("frame", "Cabinet frame 300x200x150mm", &[]),
+            part("drawer", "Drawer 280x190x60mm", &["slides into the frame"]),
+            part("handle", "Pull handle", &["screwed to the drawer front"]),
+        ];
+        let tags = classify_parts(&parts);
+        assert_eq!(
+            role_of(&tags, "drawer").hierarchical_name(),
+            "drawer_group/drawer"
+        );
+        assert_eq!(
+            role_of(&tags, "handle").hierarchical_name(),
+            "drawer_group/handle"
+        );
+        assert_eq!(role_of(&tags, "frame").role, MotionRole::Static);
+    }
+}
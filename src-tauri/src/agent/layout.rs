@@ -0,0 +1,490 @@
+/// Name tokens that mark a part as sitting on top of its mating part.
+const STACKED_PART_TOKENS: &[&str] = &["lid", "cap", "cover"];
+
+/// Constraint keywords that request XY alignment with the mating part.
+const CONCENTRIC_KEYWORDS: &[&str] = &["concentric", "coaxial"];
+
+/// Changes smaller than this are treated as "already in place".
+const ADJUSTMENT_EPSILON_MM: f64 = 0.01;
+
+/// Measured bounding box of a part in its own (unpositioned) coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PartBounds {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl PartBounds {
+    fn center_xy(&self) -> [f64; 2] {
+        [
+            (self.min[0] + self.max[0]) / 2.0,
+            (self.min[1] + self.max[1]) / 2.0,
+        ]
+    }
+}
+
+/// One accepted part as seen by the layout step.
+#[derive(Debug, Clone)]
+pub struct LayoutPart {
+    pub name: String,
+    pub position: [f64; 3],
+    pub constraints: Vec<String>,
+    pub bounds: Option<PartBounds>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionAdjustment {
+    pub part_name: String,
+    pub mating_part: String,
+    pub from: [f64; 3],
+    pub to: [f64; 3],
+    pub reasons: Vec<String>,
+}
+
+impl PositionAdjustment {
+    pub fn message(&self) -> String {
+        format!(
+            "Moved '{}' from ({:.1}, {:.1}, {:.1}) to ({:.1}, {:.1}, {:.1}): {}",
+            self.part_name,
+            self.from[0],
+            self.from[1],
+            self.from[2],
+            self.to[0],
+            self.to[1],
+            self.to[2],
+            self.reasons.join(", ")
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LayoutRefinement {
+    /// Refined position for every input part, in input order.
+    pub positions: Vec<[f64; 3]>,
+    pub adjustments: Vec<PositionAdjustment>,
+}
+
+fn name_tokens(name: &str) -> Vec<String> {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+pub(crate) fn is_stacked_part(name: &str) -> bool {
+    name_tokens(name)
+        .iter()
+        .any(|t| STACKED_PART_TOKENS.contains(&t.as_str()))
+}
+
+pub(crate) fn mentions_part(constraint: &str, name: &str) -> bool {
+    let lower = constraint.to_lowercase();
+    let name = name.to_lowercase();
+    lower.contains(&name) || lower.contains(&name.replace('_', " "))
+}
+
+/// Find the part this one mates with: the first other part named in its
+/// constraints, or for lid-like parts the first non-lid part in the plan.
+pub(crate) fn mating_index(parts: &[LayoutPart], idx: usize) -> Option<usize> {
+    let part = &parts[idx];
+    let referenced = parts.iter().enumerate().find(|(other_idx, other)| {
+        *other_idx != idx
+            && part
+                .constraints
+                .iter()
+                .any(|c| mentions_part(c, &other.name))
+    });
+    if let Some((other_idx, _)) = referenced {
+        return Some(other_idx);
+    }
+    if is_stacked_part(&part.name) {
+        return parts
+            .iter()
+            .enumerate()
+            .find(|(other_idx, other)| *other_idx != idx && !is_stacked_part(&other.name))
+            .map(|(other_idx, _)| other_idx);
+    }
+    None
+}
+
+fn wants_concentric(part: &LayoutPart) -> bool {
+    part.constraints.iter().any(|c| {
+        let lower = c.to_lowercase();
+        CONCENTRIC_KEYWORDS.iter().any(|kw| lower.contains(kw))
+    })
+}
+
+/// Snap obvious mating relationships using measured part bounds.
+///
+/// Lid/cap/cover parts are placed so their bottom face rests on the mating
+/// part's top face, and parts with a concentric constraint are centered in XY
+/// on their mating part. Parts without measured bounds, or without a
+/// recognisable relationship, keep their plan positions.
+pub fn refine_positions(parts: &[LayoutPart]) -> LayoutRefinement {
+    let mut positions: Vec<[f64; 3]> = parts.iter().map(|p| p.position).collect();
+    let mut adjustments = Vec::new();
+
+    for (idx, part) in parts.iter().enumerate() {
+        let Some(bounds) = part.bounds else {
+            continue;
+        };
+        let stacked = is_stacked_part(&part.name);
+        let concentric = wants_concentric(part);
+        if !stacked && !concentric {
+            continue;
+        }
+        let Some(mate_idx) = mating_index(parts, idx) else {
+            continue;
+        };
+        let Some(mate_bounds) = parts[mate_idx].bounds else {
+            continue;
+        };
+        let mate_pos = positions[mate_idx];
+
+        let from = positions[idx];
+        let mut to = from;
+        let mut reasons = Vec::new();
+
+        if stacked {
+            let mate_top = mate_pos[2] + mate_bounds.max[2];
+            to[2] = mate_top - bounds.min[2];
+            if (to[2] - from[2]).abs() > ADJUSTMENT_EPSILON_MM {
+                reasons.push(format!("seated on top of '{}'", parts[mate_idx].name));
+            }
+        }
+        if concentric {
+            let mate_center = mate_bounds.center_xy();
+            let center = bounds.center_xy();
+            to[0] = mate_pos[0] + mate_center[0] - center[0];
+            to[1] = mate_pos[1] + mate_center[1] - center[1];
+            if (to[0] - from[0]).abs() > ADJUSTMENT_EPSILON_MM
+                || (to[1] - from[1]).abs() > ADJUSTMENT_EPSILON_MM
+            {
+                reasons.push(format!("centered on '{}'", parts[mate_idx].name));
+            }
+        }
+
+        if reasons.is_empty() {
+            continue;
+        }
+        positions[idx] = to;
+        adjustments.push(PositionAdjustment {
+            part_name: part.name.clone(),
+            mating_part: parts[mate_idx].name.clone(),
+            from,
+            to,
+            reasons,
+        });
+    }
+
+    LayoutRefinement {
+        positions,
+        adjustments,
+    }
+}
+
+/// Push parts apart so every pair of measured bounding boxes keeps at least
+/// `gap_mm` of clearance. Each part is moved along the axis needing the
+/// smallest displacement, away from the earlier part it conflicts with.
+/// A gap of zero (or less) leaves positions untouched.
+pub fn apply_part_gap(
+    parts: &[LayoutPart],
+    positions: &mut [[f64; 3]],
+    gap_mm: f64,
+) -> Vec<PositionAdjustment> {
+    let mut adjustments = Vec::new();
+    if gap_mm <= 0.0 {
+        return adjustments;
+    }
+
+    for idx in 0..parts.len() {
+        let Some(bounds) = parts[idx].bounds else {
+            continue;
+        };
+        let from = positions[idx];
+        let mut last_neighbor: Option<usize> = None;
+
+        // Moving away from one neighbor can bump into another; a pass per
+        // earlier part is enough for every conflict to be resolved once.
+        for _ in 0..idx.max(1) {
+            let mut moved = false;
+            for other in 0..idx {
+                let Some(other_bounds) = parts[other].bounds else {
+                    continue;
+                };
+                if let Some((axis, delta)) = gap_push(
+                    &bounds,
+                    positions[idx],
+                    &other_bounds,
+                    positions[other],
+                    gap_mm,
+                ) {
+                    positions[idx][axis] += delta;
+                    last_neighbor = Some(other);
+                    moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+
+        if let Some(other) = last_neighbor {
+            adjustments.push(PositionAdjustment {
+                part_name: parts[idx].name.clone(),
+                mating_part: parts[other].name.clone(),
+                from,
+                to: positions[idx],
+                reasons: vec![format!("kept {:.1}mm gap from '{}'", gap_mm, parts[other].name)],
+            });
+        }
+    }
+
+    adjustments
+}
+
+/// Lowest Z of a placed part: its measured bottom, or its origin when it has
+/// no bounds (parts are built with `centered=(True, True, False)`, so their
+/// base sits at local Z=0).
+fn part_bottom(part: &LayoutPart, position: [f64; 3]) -> f64 {
+    position[2] + part.bounds.map_or(0.0, |b| b.min[2])
+}
+
+/// Parts whose geometry reaches below the build plate (Z=0).
+pub fn parts_below_plate(parts: &[LayoutPart], positions: &[[f64; 3]]) -> Vec<String> {
+    parts
+        .iter()
+        .zip(positions)
+        .filter(|(part, pos)| part_bottom(part, **pos) < -ADJUSTMENT_EPSILON_MM)
+        .map(|(part, _)| part.name.clone())
+        .collect()
+}
+
+/// Z offset that brings the assembly's lowest point to the build plate, or
+/// `None` when it already rests there (or there are no parts).
+pub fn plate_shift(parts: &[LayoutPart], positions: &[[f64; 3]]) -> Option<f64> {
+    let lowest = parts
+        .iter()
+        .zip(positions)
+        .map(|(part, pos)| part_bottom(part, *pos))
+        .reduce(f64::min)?;
+    (lowest.abs() > ADJUSTMENT_EPSILON_MM).then_some(-lowest)
+}
+
+/// Smallest single-axis move of part A that restores `gap_mm` clearance
+/// from part B, or `None` when the boxes are already far enough apart.
+fn gap_push(
+    a: &PartBounds,
+    a_pos: [f64; 3],
+    b: &PartBounds,
+    b_pos: [f64; 3],
+    gap_mm: f64,
+) -> Option<(usize, f64)> {
+    let mut best: Option<(usize, f64)> = None;
+    // Z first so stacked parts separate vertically when displacements tie.
+    for axis in [2, 0, 1] {
+        let a_min = a_pos[axis] + a.min[axis];
+        let a_max = a_pos[axis] + a.max[axis];
+        let b_min = b_pos[axis] + b.min[axis];
+        let b_max = b_pos[axis] + b.max[axis];
+
+        let forward = b_max + gap_mm - a_min;
+        let backward = a_max + gap_mm - b_min;
+        if forward <= ADJUSTMENT_EPSILON_MM || backward <= ADJUSTMENT_EPSILON_MM {
+            // Separated by at least the gap on this axis.
+            return None;
+        }
+        let (delta, magnitude) = if forward <= backward {
+            (forward, forward)
+        } else {
+            (-backward, backward)
+        };
+        if best.is_none_or(|(_, d)| magnitude < d.abs()) {
+            best = Some((axis, delta));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(
+        name: &str,
+        position: [f64; 3],
+        constraints: &[&str],
+        bounds: Option<([f64; 3], [f64; 3])>,
+    ) -> LayoutPart {
+        LayoutPart {
+            name: name.to_string(),
+            position,
+            constraints: constraints.iter().map(|c| c.to_string()).collect(),
+            bounds: bounds.map(|(min, max)| PartBounds { min, max }),
+        }
+    }
+
+    #[test]
+    fn test_lid_sits_on_measured_box_top() {
+        // Plan assumed a 20mm tall box, generated box is 32mm tall.
+        let parts = vec![
+            part(
+                "box",
+                [0.0, 0.0, 0.0],
+                &[],
+                Some(([-30.0, -20.0, 0.0], [30.0, 20.0, 32.0])),
+            ),
+            part(
+                "lid",
+                [0.0, 0.0, 20.0],
+                &[],
+                Some(([-30.0, -20.0, 0.0], [30.0, 20.0, 3.0])),
+            ),
+        ];
+        let refined = refine_positions(&parts);
+        assert_eq!(refined.positions[0], [0.0, 0.0, 0.0]);
+        assert_eq!(refined.positions[1], [0.0, 0.0, 32.0]);
+        assert_eq!(refined.adjustments.len(), 1);
+        assert_eq!(refined.adjustments[0].mating_part, "box");
+        assert!(refined.adjustments[0].message().contains("seated on top of 'box'"));
+    }
+
+    #[test]
+    fn test_cap_centered_and_seated_on_bottle() {
+        // Bottle is centered on its origin; the cap was modelled off-center
+        // and the planner guessed both the wrong height and XY offset.
+        let parts = vec![
+            part(
+                "bottle_body",
+                [10.0, 5.0, 0.0],
+                &[],
+                Some(([-15.0, -15.0, 0.0], [15.0, 15.0, 120.0])),
+            ),
+            part(
+                "screw_cap",
+                [0.0, 0.0, 100.0],
+                &["Concentric with bottle body neck"],
+                Some(([0.0, 0.0, -2.0], [20.0, 20.0, 14.0])),
+            ),
+        ];
+        let refined = refine_positions(&parts);
+        assert_eq!(refined.positions[1], [0.0, -5.0, 122.0]);
+        let adj = &refined.adjustments[0];
+        assert_eq!(adj.mating_part, "bottle_body");
+        assert_eq!(adj.reasons.len(), 2);
+    }
+
+    #[test]
+    fn test_parts_without_relationships_keep_plan_positions() {
+        let parts = vec![
+            part(
+                "base",
+                [0.0, 0.0, 0.0],
+                &[],
+                Some(([0.0, 0.0, 0.0], [10.0, 10.0, 10.0])),
+            ),
+            part(
+                "bracket",
+                [50.0, 0.0, 0.0],
+                &["bolt holes match base"],
+                Some(([0.0, 0.0, 0.0], [5.0, 5.0, 5.0])),
+            ),
+            part("cover", [0.0, 0.0, 99.0], &[], None),
+        ];
+        let refined = refine_positions(&parts);
+        assert_eq!(
+            refined.positions,
+            vec![[0.0, 0.0, 0.0], [50.0, 0.0, 0.0], [0.0, 0.0, 99.0]]
+        );
+        assert!(refined.adjustments.is_empty());
+    }
+
+    #[test]
+    fn test_capacitor_is_not_treated_as_cap() {
+        assert!(!is_stacked_part("capacitor"));
+        assert!(is_stacked_part("top_cover"));
+    }
+
+    fn cube(name: &str, position: [f64; 3]) -> LayoutPart {
+        part(
+            name,
+            position,
+            &[],
+            Some(([0.0, 0.0, 0.0], [10.0, 10.0, 10.0])),
+        )
+    }
+
+    #[test]
+    fn test_gap_pushes_overlapping_part_along_smallest_axis() {
+        let parts = vec![cube("left", [0.0, 0.0, 0.0]), cube("right", [8.0, 0.0, 0.0])];
+        let mut positions: Vec<[f64; 3]> = parts.iter().map(|p| p.position).collect();
+        let adjustments = apply_part_gap(&parts, &mut positions, 1.0);
+        assert_eq!(positions[0], [0.0, 0.0, 0.0]);
+        assert_eq!(positions[1], [11.0, 0.0, 0.0]);
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].mating_part, "left");
+    }
+
+    #[test]
+    fn test_gap_lifts_touching_lid() {
+        let parts = vec![
+            cube("box", [0.0, 0.0, 0.0]),
+            part(
+                "lid",
+                [0.0, 0.0, 10.0],
+                &[],
+                Some(([0.0, 0.0, 0.0], [10.0, 10.0, 2.0])),
+            ),
+        ];
+        let mut positions: Vec<[f64; 3]> = parts.iter().map(|p| p.position).collect();
+        apply_part_gap(&parts, &mut positions, 0.5);
+        assert_eq!(positions[1], [0.0, 0.0, 10.5]);
+    }
+
+    #[test]
+    fn test_zero_gap_and_separated_parts_are_untouched() {
+        let overlapping = vec![cube("a", [0.0, 0.0, 0.0]), cube("b", [5.0, 0.0, 0.0])];
+        let mut positions: Vec<[f64; 3]> = overlapping.iter().map(|p| p.position).collect();
+        assert!(apply_part_gap(&overlapping, &mut positions, 0.0).is_empty());
+        assert_eq!(positions[1], [5.0, 0.0, 0.0]);
+
+        let apart = vec![cube("a", [0.0, 0.0, 0.0]), cube("b", [13.0, 0.0, 0.0])];
+        let mut positions: Vec<[f64; 3]> = apart.iter().map(|p| p.position).collect();
+        assert!(apply_part_gap(&apart, &mut positions, 2.0).is_empty());
+        assert_eq!(positions[1], [13.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_plate_shift_lifts_parts_below_zero() {
+        let parts = vec![
+            cube("base", [0.0, 0.0, -12.0]),
+            part(
+                "knob",
+                [0.0, 0.0, 0.0],
+                &[],
+                Some(([-3.0, -3.0, -20.0], [3.0, 3.0, 5.0])),
+            ),
+            part("label", [0.0, 0.0, -5.0], &[], None),
+        ];
+        let positions: Vec<[f64; 3]> = parts.iter().map(|p| p.position).collect();
+        assert_eq!(
+            parts_below_plate(&parts, &positions),
+            vec!["base", "knob", "label"]
+        );
+        // The knob's measured bottom (-20) is the lowest point.
+        assert_eq!(plate_shift(&parts, &positions), Some(20.0));
+    }
+
+    #[test]
+    fn test_plate_shift_for_resting_and_floating_assemblies() {
+        let resting = vec![cube("a", [0.0, 0.0, 0.0]), cube("b", [0.0, 0.0, 10.0])];
+        let positions: Vec<[f64; 3]> = resting.iter().map(|p| p.position).collect();
+        assert!(parts_below_plate(&resting, &positions).is_empty());
+        assert_eq!(plate_shift(&resting, &positions), None);
+
+        let floating = vec![cube("shade", [0.0, 0.0, 40.0])];
+        assert_eq!(plate_shift(&floating, &[[0.0, 0.0, 40.0]]), Some(-40.0));
+        assert_eq!(plate_shift(&[], &[]), None);
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use schemars::JsonSchema;
+
+use crate::config::AppConfig;
+
+/// Request words that mark an object as standing on its own base.
+const FREE_STANDING_TAGS: &[&str] = &["stand", "holder", "organizer", "organiser"];
+
+/// Parts whose bottom is within this distance of the lowest part form the footprint.
+const FOOTPRINT_Z_TOLERANCE_MM: f64 = 0.5;
+
+/// Material resolved for one part.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartMaterial {
+    pub name: String,
+    pub density_g_cm3: f64,
+    /// True when no known material applied and the default density was used.
+    pub assumed: bool,
+}
+
+/// Measured geometry for one placed part, taken from its post-geometry report.
+#[derive(Debug, Clone)]
+pub struct PartMassInput {
+    pub part_name: String,
+    pub material: PartMaterial,
+    pub volume_mm3: f64,
+    /// Center of mass in the part's own coordinates.
+    pub center_of_mass: [f64; 3],
+    /// Inertia tensor about the part's center of mass at unit density (mm^5).
+    pub unit_inertia: [[f64; 3]; 3],
+    pub bounds_min: [f64; 3],
+    pub bounds_max: [f64; 3],
+    /// Assembly placement applied with `Pos(...)`.
+    pub position: [f64; 3],
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PartMassProperties {
+    pub part_name: String,
+    pub material: String,
+    pub density_g_cm3: f64,
+    pub density_assumed: bool,
+    pub volume_mm3: f64,
+    pub mass_g: f64,
+    /// Center of gravity in assembly coordinates.
+    pub center_of_gravity_mm: [f64; 3],
+    /// Inertia tensor about the part's own center of gravity.
+    pub inertia_g_mm2: [[f64; 3]; 3],
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct MassPropertiesReport {
+    pub parts: Vec<PartMassProperties>,
+    pub total_mass_g: f64,
+    pub center_of_gravity_mm: [f64; 3],
+    /// Inertia tensor of the whole assembly about its center of gravity.
+    pub inertia_g_mm2: [[f64; 3]; 3],
+    pub assumptions: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+fn word_tokens(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+fn contains_phrase(tokens: &[String], phrase: &str) -> bool {
+    let wanted = word_tokens(phrase);
+    !wanted.is_empty() && tokens.windows(wanted.len()).any(|w| w == wanted.as_slice())
+}
+
+/// Find the configured material named in a part description, preferring the
+/// longest match so "stainless steel" wins over "steel".
+pub fn material_from_description(
+    description: &str,
+    materials: &BTreeMap<String, f64>,
+) -> Option<String> {
+    let tokens = word_tokens(description);
+    materials
+        .keys()
+        .filter(|name| contains_phrase(&tokens, name))
+        .max_by_key(|name| name.len())
+        .cloned()
+}
+
+/// Resolve a part's material: explicit assignment first, then the plan
+/// description, then the configured default density.
+pub fn resolve_material(
+    part_name: &str,
+    description: &str,
+    assignments: &HashMap<String, String>,
+    config: &AppConfig,
+) -> PartMaterial {
+    let named = assignments
+        .get(part_name)
+        .map(|m| m.to_lowercase())
+        .or_else(|| material_from_description(description, &config.materials));
+    match named.and_then(|name| config.materials.get(&name).map(|d| (name, *d))) {
+        Some((name, density)) => PartMaterial {
+            name,
+            density_g_cm3: density,
+            assumed: false,
+        },
+        None => PartMaterial {
+            name: "unspecified".to_string(),
+            density_g_cm3: config.default_material_density_g_cm3,
+            assumed: true,
+        },
+    }
+}
+
+/// Whether the request describes something that must stand on its own base.
+pub fn is_free_standing(request: &str) -> bool {
+    let tokens = word_tokens(request);
+    FREE_STANDING_TAGS
+        .iter()
+        .any(|tag| tokens.iter().any(|t| t == tag || *t == format!("{}s", tag)))
+}
+
+fn add_tensor(a: &mut [[f64; 3]; 3], b: &[[f64; 3]; 3]) {
+    for (row_a, row_b) in a.iter_mut().zip(b.iter()) {
+        for (x, y) in row_a.iter_mut().zip(row_b.iter()) {
+            *x += y;
+        }
+    }
+}
+
+/// Parallel-axis term for a point mass `mass` offset by `d` from the reference point.
+fn parallel_axis(mass: f64, d: [f64; 3]) -> [[f64; 3]; 3] {
+    let d2 = d[0] * d[0] + d[1] * d[1] + d[2] * d[2];
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            let identity = if i == j { d2 } else { 0.0 };
+            *cell = mass * (identity - d[i] * d[j]);
+        }
+    }
+    out
+}
+
+/// XY rectangle covered by the parts resting on the lowest Z level.
+fn base_footprint(inputs: &[PartMassInput]) -> Option<([f64; 2], [f64; 2])> {
+    let floor = inputs
+        .iter()
+        .map(|p| p.position[2] + p.bounds_min[2])
+        .fold(f64::INFINITY, f64::min);
+    if !floor.is_finite() {
+        return None;
+    }
+    let mut min = [f64::INFINITY; 2];
+    let mut max = [f64::NEG_INFINITY; 2];
+    for part in inputs {
+        if part.position[2] + part.bounds_min[2] > floor + FOOTPRINT_Z_TOLERANCE_MM {
+            continue;
+        }
+        for axis in 0..2 {
+            min[axis] = min[axis].min(part.position[axis] + part.bounds_min[axis]);
+            max[axis] = max[axis].max(part.position[axis] + part.bounds_max[axis]);
+        }
+    }
+    Some((min, max))
+}
+
+/// Whether the assembly center of gravity projects outside the base footprint.
+pub fn cog_outside_footprint(inputs: &[PartMassInput], cog: [f64; 3]) -> bool {
+    match base_footprint(inputs) {
+        Some((min, max)) => {
+            cog[0] < min[0] || cog[0] > max[0] || cog[1] < min[1] || cog[1] > max[1]
+        }
+        None => false,
+    }
+}
+
+/// Combine per-part measurements into part and assembly mass properties.
+///
+/// Densities are in g/cm³ and geometry in mm, so masses come out in grams.
+pub fn compute_mass_properties(
+    inputs: &[PartMassInput],
+    free_standing: bool,
+) -> MassPropertiesReport {
+    let mut parts = Vec::with_capacity(inputs.len());
+    let mut assumptions = Vec::new();
+    let mut total_mass = 0.0;
+    let mut weighted = [0.0_f64; 3];
+
+    for input in inputs {
+        let density_g_mm3 = input.material.density_g_cm3 / 1000.0;
+        let mass_g = input.volume_mm3 * density_g_mm3;
+        let cog = [
+            input.position[0] + input.center_of_mass[0],
+            input.position[1] + input.center_of_mass[1],
+            input.position[2] + input.center_of_mass[2],
+        ];
+        let mut inertia = input.unit_inertia;
+        for row in inertia.iter_mut() {
+            for cell in row.iter_mut() {
+                *cell *= density_g_mm3;
+            }
+        }
+        if input.material.assumed {
+            assumptions.push(format!(
+                "'{}' has no known material; assumed {:.2} g/cm³",
+                input.part_name, input.material.density_g_cm3
+            ));
+        }
+        total_mass += mass_g;
+        for axis in 0..3 {
+            weighted[axis] += cog[axis] * mass_g;
+        }
+        parts.push(PartMassProperties {
+            part_name: input.part_name.clone(),
+            material: input.material.name.clone(),
+            density_g_cm3: input.material.density_g_cm3,
+            density_assumed: input.material.assumed,
+            volume_mm3: input.volume_mm3,
+            mass_g,
+            center_of_gravity_mm: cog,
+            inertia_g_mm2: inertia,
+        });
+    }
+
+    let center_of_gravity_mm = if total_mass > 0.0 {
+        [
+            weighted[0] / total_mass,
+            weighted[1] / total_mass,
+            weighted[2] / total_mass,
+        ]
+    } else {
+        [0.0; 3]
+    };
+
+    let mut inertia_g_mm2 = [[0.0; 3]; 3];
+    for part in &parts {
+        add_tensor(&mut inertia_g_mm2, &part.inertia_g_mm2);
+        let offset = [
+            part.center_of_gravity_mm[0] - center_of_gravity_mm[0],
+            part.center_of_gravity_mm[1] - center_of_gravity_mm[1],
+            part.center_of_gravity_mm[2] - center_of_gravity_mm[2],
+        ];
+        add_tensor(&mut inertia_g_mm2, &parallel_axis(part.mass_g, offset));
+    }
+
+    let mut warnings = Vec::new();
+    if free_standing && total_mass > 0.0 && cog_outside_footprint(inputs, center_of_gravity_mm) {
+        warnings.push(format!(
+            "Center of gravity ({:.1}, {:.1}) lies outside the base footprint; the object may tip over.",
+            center_of_gravity_mm[0], center_of_gravity_mm[1]
+        ));
+    }
+
+    MassPropertiesReport {
+        parts,
+        total_mass_g: total_mass,
+        center_of_gravity_mm,
+        inertia_g_mm2,
+        assumptions,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material(name: &str, density: f64) -> PartMaterial {
+        PartMaterial {
+            name: name.to_string(),
+            density_g_cm3: density,
+            assumed: false,
+        }
+    }
+
+    /// Canned post-geometry output for an axis-aligned box with its min corner at the origin.
+    fn box_input(name: &str, size: [f64; 3], position: [f64; 3], mat: PartMaterial) -> PartMassInput {
+        let volume = size[0] * size[1] * size[2];
+        let unit_inertia = [
+            [volume * (size[1].powi(2) + size[2].powi(2)) / 12.0, 0.0, 0.0],
+            [0.0, volume * (size[0].powi(2) + size[2].powi(2)) / 12.0, 0.0],
+            [0.0, 0.0, volume * (size[0].powi(2) + size[1].powi(2)) / 12.0],
+        ];
+        PartMassInput {
+            part_name: name.to_string(),
+            material: mat,
+            volume_mm3: volume,
+            center_of_mass: [size[0] / 2.0, size[1] / 2.0, size[2] / 2.0],
+            unit_inertia,
+            bounds_min: [0.0; 3],
+            bounds_max: size,
+            position,
+        }
+    }
+
+    #[test]
+    fn test_material_from_description_prefers_longest_match() {
+        let cfg = AppConfig::default();
+        assert_eq!(
+            material_from_description("Aluminum bracket, 3mm plate", &cfg.materials).as_deref(),
+            Some("aluminum")
+        );
+        assert_eq!(
+            material_from_description("stainless steel hinge pin", &cfg.materials).as_deref(),
+            Some("stainless steel")
+        );
+        assert!(material_from_description("printed widget", &cfg.materials).is_none());
+    }
+
+    #[test]
+    fn test_resolve_material_assignment_overrides_description() {
+        let cfg = AppConfig::default();
+        let mut assignments = HashMap::new();
+        assignments.insert("bracket".to_string(), "Steel".to_string());
+        let mat = resolve_material("bracket", "aluminum bracket", &assignments, &cfg);
+        assert_eq!(mat.name, "steel");
+        assert!(!mat.assumed);
+
+        let unknown = resolve_material("knob", "a knob", &HashMap::new(), &cfg);
+        assert!(unknown.assumed);
+        assert_eq!(unknown.density_g_cm3, cfg.default_material_density_g_cm3);
+    }
+
+    #[test]
+    fn test_mass_and_cog_for_two_boxes() {
+        // 10x10x10 aluminum cube (2.7 g) with a 10x10x10 PLA cube on top.
+        let inputs = vec![
+            box_input("base", [10.0, 10.0, 10.0], [0.0, 0.0, 0.0], material("aluminum", 2.7)),
+            box_input("top", [10.0, 10.0, 10.0], [0.0, 0.0, 10.0], material("pla", 1.24)),
+        ];
+        let report = compute_mass_properties(&inputs, false);
+        assert!((report.parts[0].mass_g - 2.7).abs() < 1e-9);
+        assert!((report.total_mass_g - 3.94).abs() < 1e-9);
+        let expected_z = (2.7 * 5.0 + 1.24 * 15.0) / 3.94;
+        assert!((report.center_of_gravity_mm[2] - expected_z).abs() < 1e-9);
+        assert!((report.center_of_gravity_mm[0] - 5.0).abs() < 1e-9);
+        // Stacking along Z raises Ixx above the sum of the per-part terms.
+        let own = report.parts[0].inertia_g_mm2[0][0] + report.parts[1].inertia_g_mm2[0][0];
+        assert!(report.inertia_g_mm2[0][0] > own);
+        assert!((report.inertia_g_mm2[2][2] - own).abs() < 1e-9);
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_cog_outside_footprint_warns_for_free_standing() {
+        // Narrow 10mm post with a heavy steel arm cantilevered 60mm out.
+        let inputs = vec![
+            box_input("post", [10.0, 10.0, 80.0], [0.0, 0.0, 0.0], material("pla", 1.24)),
+            box_input("arm", [60.0, 10.0, 10.0], [10.0, 0.0, 70.0], material("steel", 7.85)),
+        ];
+        let report = compute_mass_properties(&inputs, true);
+        assert!(report.center_of_gravity_mm[0] > 10.0);
+        assert_eq!(report.warnings.len(), 1);
+
+        let not_standing = compute_mass_properties(&inputs, false);
+        assert!(not_standing.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_assumed_density_is_reported() {
+        let inputs = vec![box_input(
+            "body",
+            [10.0, 10.0, 10.0],
+            [0.0; 3],
+            PartMaterial {
+                name: "unspecified".to_string(),
+                density_g_cm3: 1.24,
+                assumed: true,
+            },
+        )];
+        let report = compute_mass_properties(&inputs, false);
+        assert_eq!(report.assumptions.len(), 1);
+        assert!(report.parts[0].density_assumed);
+    }
+
+    #[test]
+    fn test_free_standing_tags() {
+        assert!(is_free_standing("a phone stand with cable slot"));
+        assert!(is_free_standing("desk organizer"));
+        assert!(!is_free_standing("wall-mounted bracket"));
+    }
+}
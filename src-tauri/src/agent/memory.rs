@@ -15,15 +15,24 @@ pub struct GenerationAttempt {
 /// In-memory session memory — tracks generation outcomes within a conversation.
 pub struct SessionMemory {
     attempts: Vec<GenerationAttempt>,
+    /// Description of a project loaded from disk, so follow-ups continue it.
+    loaded_project: Option<String>,
 }
 
 impl SessionMemory {
     pub fn new() -> Self {
         Self {
             attempts: Vec::new(),
+            loaded_project: None,
         }
     }
 
+    /// Replace the session with the context of a loaded project file.
+    pub fn seed_loaded_project(&mut self, section: Option<String>) {
+        self.attempts.clear();
+        self.loaded_project = section;
+    }
+
     /// Record a generation attempt. Caps at 20 entries (drops oldest).
     pub fn record_attempt(&mut self, attempt: GenerationAttempt) {
         self.attempts.push(attempt);
@@ -33,13 +42,17 @@ impl SessionMemory {
     }
 
     /// Build a context section for injection into the system prompt.
-    /// Returns `None` if no attempts have been recorded.
+    /// Returns `None` if no attempts have been recorded and no project was loaded.
     pub fn build_context_section(&self) -> Option<String> {
         if self.attempts.is_empty() {
-            return None;
+            return self.loaded_project.clone();
         }
 
         let mut out = String::new();
+        if let Some(ref project) = self.loaded_project {
+            out.push_str(project);
+            out.push_str("\n\n");
+        }
         out.push_str("## Session Context\nPrevious generation attempts in this conversation:\n");
 
         for (i, attempt) in self.attempts.iter().enumerate() {
@@ -96,9 +109,10 @@ impl SessionMemory {
         ops
     }
 
-    /// Clear all recorded attempts.
+    /// Clear all recorded attempts and any loaded project context.
     pub fn reset(&mut self) {
         self.attempts.clear();
+        self.loaded_project = None;
     }
 
     /// Build learning bullet points from attempts (capped at 5).
@@ -1,17 +1,50 @@
+pub mod assumptions;
+pub mod anti_pattern_mining;
+pub mod assembly_instructions;
+pub mod clearance;
+pub mod code_import;
+pub mod complexity;
 pub mod confidence;
 pub mod consensus;
 pub mod context;
+pub mod custom_rules;
 pub mod design;
+pub mod embedding_cache;
 pub mod executor;
+pub mod export_verify;
 pub mod extract;
+pub mod failure_localization;
+pub mod features;
+pub mod geometry_query;
 pub mod iterative;
+pub mod kinematics;
+pub mod layout;
+pub mod mass;
+pub mod mechanism_reference;
 pub mod memory;
 pub mod modify;
+pub mod next_actions;
+pub mod numparse;
+pub mod parametrize;
+pub mod part_colors;
+pub mod part_constraints;
+pub mod part_dedup;
+pub mod print_estimate;
+pub mod prompt_guard;
 pub mod prompts;
+pub mod queue;
+pub mod repair_examples;
 pub mod retrieval;
+pub mod revalidation;
 pub mod review;
 pub mod rules;
+pub mod run_state;
 pub mod semantic_validate;
+pub mod slash_commands;
 pub mod static_validate;
 pub mod telemetry;
+pub mod threads;
+pub mod transcript;
 pub mod validate;
+pub mod validation_history;
+pub mod views;
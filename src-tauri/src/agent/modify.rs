@@ -1,5 +1,6 @@
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use similar::{ChangeTag, TextDiff};
 
 // ---------------------------------------------------------------------------
@@ -12,16 +13,41 @@ pub struct ModificationIntent {
     pub intent_summary: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// One part's code cut out of an assembled multi-part script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartScope {
+    pub name: String,
+    /// Position of the part among the script's part sections.
+    pub index: usize,
+    /// The part's code with its `part_<name>` variable renamed back to `result`.
+    pub code: String,
+    /// Other part names and the assembly section, shown to the model read-only.
+    pub context: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DiffLine {
     pub tag: String, // "equal", "insert", "delete"
     pub text: String,
 }
 
+/// A run of changed lines with surrounding context, like a unified-diff hunk.
+/// `start_line`/`end_line` are inclusive 0-based indices into the diff lines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DiffHunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub added: usize,
+    pub removed: usize,
+}
+
 // ---------------------------------------------------------------------------
 // Constants
 // ---------------------------------------------------------------------------
 
+/// Unchanged lines kept around each hunk, as in `diff -u`.
+const HUNK_CONTEXT_LINES: usize = 3;
+
 /// Default/template code markers — if the editor only has one of these,
 /// there is no meaningful code to modify.
 const DEFAULT_CODE_MARKERS: &[&str] = &[
@@ -62,6 +88,26 @@ Critical rules:
 6. Wrap in <CODE>...</CODE> tags
 "#;
 
+/// System prompt addendum for modifying a single part of an assembly.
+pub const SCOPED_MODIFICATION_INSTRUCTIONS: &str = r#"
+## PART MODIFICATION MODE
+You are modifying ONE part of a multi-part Build123d assembly.
+
+Critical rules:
+1. Return the COMPLETE updated code for this part only
+2. Do not define or change any other part; they are shown for reference only
+3. Preserve existing variable names, structure and comments
+4. Only change what the user asked for
+5. The part must still be assigned to `result`
+6. Wrap in <CODE>...</CODE> tags
+"#;
+
+/// Section headers written by `assemble_parts`: `# --- <name> ---`.
+const SECTION_PREFIX: &str = "# --- ";
+const SECTION_SUFFIX: &str = " ---";
+const ASSEMBLY_SECTION: &str = "Assembly";
+const SHARED_SECTION_SUFFIX: &str = " (shared structure)";
+
 // ---------------------------------------------------------------------------
 // Detection
 // ---------------------------------------------------------------------------
@@ -129,6 +175,185 @@ pub fn detect_modification_intent(
     }
 }
 
+// ---------------------------------------------------------------------------
+// Part scoping
+// ---------------------------------------------------------------------------
+
+/// Section title, header start and body start for every `# --- ... ---`
+/// header. A body runs until the next header or the end of the script.
+fn sections(code: &str) -> Vec<(&str, usize, usize)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    for line in code.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if let Some(title) = trimmed
+            .strip_prefix(SECTION_PREFIX)
+            .and_then(|t| t.strip_suffix(SECTION_SUFFIX))
+        {
+            found.push((title, offset, offset + line.len()));
+        }
+        offset += line.len();
+    }
+    found
+}
+
+/// Byte range of the standalone section body for `name`. Parts generated
+/// from a shared `make_*` function have no body of their own.
+fn part_block_range(code: &str, name: &str) -> Option<std::ops::Range<usize>> {
+    let sections = sections(code);
+    let idx = sections.iter().position(|(title, _, _)| *title == name)?;
+    let end = sections
+        .get(idx + 1)
+        .map(|(_, start, _)| *start)
+        .unwrap_or(code.len());
+    Some(sections[idx].2..end)
+}
+
+/// Part names of an assembled script, in section order.
+pub fn assembled_part_names(code: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for (title, _, _) in sections(code) {
+        if title == ASSEMBLY_SECTION {
+            continue;
+        }
+        match title.strip_suffix(SHARED_SECTION_SUFFIX) {
+            Some(shared) => names.extend(shared.split(", ").map(str::to_string)),
+            None => names.push(title.to_string()),
+        }
+    }
+    names
+}
+
+/// The single part the message refers to, e.g. "the lid" or "back plate"
+/// for `back_plate`. `None` when no part or more than one part is named.
+pub fn detect_target_part(user_message: &str, part_names: &[String]) -> Option<String> {
+    let mentioned: Vec<&String> = part_names
+        .iter()
+        .filter(|name| {
+            let words = regex::escape(name).replace('_', r"[\s_-]?");
+            Regex::new(&format!(r"(?i)\b{}(?:s|es)?\b", words))
+                .map(|re| re.is_match(user_message))
+                .unwrap_or(false)
+        })
+        .collect();
+    match mentioned.as_slice() {
+        [only] => Some((*only).clone()),
+        _ => None,
+    }
+}
+
+fn part_var_regex(name: &str) -> Regex {
+    Regex::new(&format!(r"\bpart_{}\b", regex::escape(name))).unwrap()
+}
+
+/// Cut the part the request targets out of an assembled script.
+///
+/// `explicit_part` wins over detection from `user_message`. Returns `Ok(None)`
+/// when the script is not a multi-part assembly or no single part is named;
+/// an explicit part that cannot be isolated is an error.
+pub fn resolve_part_scope(
+    existing_code: &str,
+    user_message: &str,
+    explicit_part: Option<&str>,
+) -> Result<Option<PartScope>, String> {
+    let names = assembled_part_names(existing_code);
+    let explicit_part = explicit_part.map(str::trim).filter(|p| !p.is_empty());
+    if names.len() < 2 {
+        return match explicit_part {
+            Some(part) => Err(format!(
+                "Cannot modify part '{}': the editor code is not an assembled multi-part script",
+                part
+            )),
+            None => Ok(None),
+        };
+    }
+
+    let target = match explicit_part {
+        Some(part) => match names.iter().find(|n| n.eq_ignore_ascii_case(part)) {
+            Some(name) => name.clone(),
+            None => {
+                return Err(format!(
+                    "Part '{}' is not in the assembly (parts: {})",
+                    part,
+                    names.join(", ")
+                ))
+            }
+        },
+        None => match detect_target_part(user_message, &names) {
+            Some(name) => name,
+            None => return Ok(None),
+        },
+    };
+
+    let Some(range) = part_block_range(existing_code, &target) else {
+        return match explicit_part {
+            Some(_) => Err(format!(
+                "Part '{}' shares generated code with other parts and cannot be modified on its own",
+                target
+            )),
+            None => Ok(None),
+        };
+    };
+
+    let block = existing_code[range].trim_end();
+    let code = part_var_regex(&target)
+        .replace_all(block, "result")
+        .to_string();
+    let assembly = part_block_range(existing_code, ASSEMBLY_SECTION)
+        .map(|r| existing_code[r].trim_end().to_string())
+        .unwrap_or_default();
+    let others: Vec<&str> = names
+        .iter()
+        .filter(|n| **n != target)
+        .map(String::as_str)
+        .collect();
+    let context = format!(
+        "Other parts (read-only, unchanged): {}\n\nAssembly (read-only):\n```python\n{}\n```",
+        others.join(", "),
+        assembly
+    );
+
+    Ok(Some(PartScope {
+        index: names.iter().position(|n| *n == target).unwrap_or(0),
+        name: target,
+        code,
+        context,
+    }))
+}
+
+/// Replace `name`'s section body with `part_code` (which assigns `result`),
+/// renaming and stripping imports the same way `assemble_parts` does.
+pub fn splice_part_block(existing_code: &str, name: &str, part_code: &str) -> Option<String> {
+    let range = part_block_range(existing_code, name)?;
+    let result_re = Regex::new(r"\bresult\b").unwrap();
+    let body = part_code
+        .lines()
+        .filter(|line| {
+            let trimmed = line.trim();
+            !trimmed.starts_with("from build123d") && !trimmed.starts_with("import build123d")
+        })
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let renamed = result_re.replace_all(body.trim(), format!("part_{}", name).as_str());
+    Some(format!(
+        "{}{}\n\n{}",
+        &existing_code[..range.start],
+        renamed,
+        &existing_code[range.end..]
+    ))
+}
+
+/// True when everything outside `name`'s section is byte-identical.
+pub fn only_part_changed(old_code: &str, new_code: &str, name: &str) -> bool {
+    let outside = |code: &str| {
+        part_block_range(code, name).map(|r| format!("{}{}", &code[..r.start], &code[r.end..]))
+    };
+    match (outside(old_code), outside(new_code)) {
+        (Some(old), Some(new)) => old == new,
+        _ => false,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Prompt building
 // ---------------------------------------------------------------------------
@@ -142,6 +367,14 @@ pub fn build_modification_message(existing_code: &str, user_request: &str) -> St
     )
 }
 
+/// Build the user message for modifying one part of an assembly.
+pub fn build_scoped_modification_message(scope: &PartScope, user_request: &str) -> String {
+    format!(
+        "## Part '{}'\n```python\n{}\n```\n\n## Context\n{}\n\n## Modification Request\n{}",
+        scope.name, scope.code, scope.context, user_request
+    )
+}
+
 // ---------------------------------------------------------------------------
 // Diff computation
 // ---------------------------------------------------------------------------
@@ -164,6 +397,38 @@ pub fn compute_diff(old_code: &str, new_code: &str) -> Vec<DiffLine> {
         .collect()
 }
 
+/// Group changed lines into hunks with `HUNK_CONTEXT_LINES` of context.
+/// Changes whose context would overlap or touch share one hunk.
+pub fn compute_hunks(diff: &[DiffLine]) -> Vec<DiffHunk> {
+    let mut hunks: Vec<DiffHunk> = Vec::new();
+    for (i, line) in diff.iter().enumerate() {
+        if line.tag == "equal" {
+            continue;
+        }
+        let start = i.saturating_sub(HUNK_CONTEXT_LINES);
+        let end = (i + HUNK_CONTEXT_LINES).min(diff.len() - 1);
+        let hunk = match hunks.last_mut() {
+            Some(last) if start <= last.end_line + 1 => last,
+            _ => {
+                hunks.push(DiffHunk {
+                    start_line: start,
+                    end_line: end,
+                    added: 0,
+                    removed: 0,
+                });
+                hunks.last_mut().unwrap()
+            }
+        };
+        hunk.end_line = end;
+        if line.tag == "insert" {
+            hunk.added += 1;
+        } else {
+            hunk.removed += 1;
+        }
+    }
+    hunks
+}
+
 /// Returns true if the diff contains any insert or delete lines.
 pub fn diff_has_changes(diff: &[DiffLine]) -> bool {
     diff.iter().any(|line| line.tag != "equal")
@@ -268,6 +533,59 @@ result = Box(10, 10, 10)
         assert!(!diff_has_changes(&diff));
     }
 
+    /// Diff lines from tags: `=` equal, `+` insert, `-` delete.
+    fn diff_of(tags: &str) -> Vec<DiffLine> {
+        tags.chars()
+            .enumerate()
+            .map(|(i, c)| DiffLine {
+                tag: match c {
+                    '+' => "insert",
+                    '-' => "delete",
+                    _ => "equal",
+                }
+                .to_string(),
+                text: format!("line {}", i),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compute_hunks_splits_distant_changes() {
+        let hunks = compute_hunks(&diff_of("====+-========-+="));
+        assert_eq!(
+            hunks,
+            vec![
+                DiffHunk {
+                    start_line: 1,
+                    end_line: 8,
+                    added: 1,
+                    removed: 1,
+                },
+                DiffHunk {
+                    start_line: 11,
+                    end_line: 16,
+                    added: 1,
+                    removed: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compute_hunks_merges_when_context_touches() {
+        let hunks = compute_hunks(&diff_of("==+======-+-=="));
+        assert_eq!(
+            hunks,
+            vec![DiffHunk {
+                start_line: 0,
+                end_line: 13,
+                added: 2,
+                removed: 2,
+            }]
+        );
+        assert!(compute_hunks(&diff_of("=====")).is_empty());
+    }
+
     #[test]
     fn test_build_modification_message_format() {
         let msg = build_modification_message("code here", "make it bigger");
@@ -278,6 +596,108 @@ result = Box(10, 10, 10)
         assert!(msg.contains("make it bigger"));
     }
 
+    const ASSEMBLED: &str = r#"from build123d import *
+
+# --- base ---
+part_base = Box(60, 40, 20)
+
+# --- lid ---
+part_lid = Box(60, 40, 3)
+part_lid = fillet(part_lid.edges().filter_by(Axis.Z), radius=2)
+
+# --- Assembly ---
+assy = Compound(label="assembly", children=[
+    Pos(0, 0, 0) * part_base,
+    Pos(0, 0, 21.5) * part_lid,
+])
+result = assy
+"#;
+
+    #[test]
+    fn test_keyword_detection_scopes_to_lid() {
+        let scope = resolve_part_scope(ASSEMBLED, "make the lid 2mm taller", None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(scope.name, "lid");
+        assert_eq!(scope.index, 1);
+        assert!(scope.code.starts_with("result = Box(60, 40, 3)"));
+        assert!(scope.code.contains("fillet(result.edges()"));
+        assert!(!scope.code.contains("part_base"));
+        assert!(scope.context.contains("base"));
+        assert!(scope.context.contains("Pos(0, 0, 21.5) * part_lid"));
+
+        // Naming both parts, or none, falls back to whole-script modification.
+        assert!(
+            resolve_part_scope(ASSEMBLED, "make the lid and base wider", None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(resolve_part_scope(ASSEMBLED, "make it wider", None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_explicit_part_wins_over_detection() {
+        let scope = resolve_part_scope(ASSEMBLED, "make the lid thicker", Some("Base"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(scope.name, "base");
+        assert_eq!(scope.index, 0);
+        assert_eq!(scope.code, "result = Box(60, 40, 20)");
+
+        let missing = resolve_part_scope(ASSEMBLED, "make it thicker", Some("hinge")).unwrap_err();
+        assert!(missing.contains("base, lid"), "{}", missing);
+        assert!(resolve_part_scope(REAL_CODE, "make it thicker", Some("lid")).is_err());
+    }
+
+    #[test]
+    fn test_detect_target_part_spaced_and_plural_names() {
+        let names = vec!["back_plate".to_string(), "leg".to_string()];
+        assert_eq!(
+            detect_target_part("thicken the back plate", &names).as_deref(),
+            Some("back_plate")
+        );
+        assert_eq!(
+            detect_target_part("make the legs longer", &names).as_deref(),
+            Some("leg")
+        );
+        assert!(detect_target_part("make the legendary box", &names).is_none());
+    }
+
+    #[test]
+    fn test_splice_keeps_other_parts_identical() {
+        let new_lid = "from build123d import *\nresult = Box(60, 40, 5)\n";
+        let spliced = splice_part_block(ASSEMBLED, "lid", new_lid).unwrap();
+        assert!(spliced.contains("# --- lid ---\npart_lid = Box(60, 40, 5)\n\n# --- Assembly ---"));
+        assert_eq!(spliced.matches("from build123d").count(), 1);
+        assert!(only_part_changed(ASSEMBLED, &spliced, "lid"));
+
+        let perturbed = spliced.replace("Box(60, 40, 20)", "Box(61, 40, 20)");
+        assert!(!only_part_changed(ASSEMBLED, &perturbed, "lid"));
+        assert!(!only_part_changed(ASSEMBLED, REAL_CODE, "lid"));
+    }
+
+    #[test]
+    fn test_shared_structure_parts_are_not_scoped() {
+        let shared = "from build123d import *\n\n# --- leg_a, leg_b (shared structure) ---\n\
+                      def make_leg(h):\n    return Cylinder(5, h)\n\n\
+                      part_leg_a = make_leg(40)\npart_leg_b = make_leg(40)\n\n\
+                      # --- seat ---\npart_seat = Box(50, 50, 5)\n\n# --- Assembly ---\nresult = None\n";
+        assert_eq!(assembled_part_names(shared), vec!["leg_a", "leg_b", "seat"]);
+        assert!(resolve_part_scope(shared, "make leg_a longer", None)
+            .unwrap()
+            .is_none());
+        assert!(resolve_part_scope(shared, "make it longer", Some("leg_a")).is_err());
+        assert_eq!(
+            resolve_part_scope(shared, "make the seat thicker", None)
+                .unwrap()
+                .unwrap()
+                .index,
+            2
+        );
+    }
+
     #[test]
     fn test_modification_message_preserves_code() {
         let code = "result = fillet(Box(10, 10, 10).edges(), radius=2)";
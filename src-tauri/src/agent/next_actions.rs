@@ -0,0 +1,266 @@
+//! Deterministic "what to try next" suggestions for a failed generation,
+//! derived from the pipeline outcome's failure signatures, retry ladder stage
+//! and part acceptance rate.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::config::{AppConfig, GenerationReliabilityProfile};
+
+/// Acceptance rate below which most parts failed and the request is likely
+/// too ambitious for one run.
+const LOW_ACCEPTANCE_RATE: f32 = 0.5;
+
+/// What the user can do next; the frontend may map a kind to a button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestedActionKind {
+    IncreaseTimeout,
+    SetUpPython,
+    CheckProviderSettings,
+    UseReliabilityFirst,
+    SimplifyRequest,
+    ReduceParts,
+    RetryGeneration,
+}
+
+/// One suggestion shown after a failed generation.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SuggestedAction {
+    pub kind: SuggestedActionKind,
+    pub label: String,
+    /// The part of the outcome that triggered the suggestion.
+    pub reason: String,
+}
+
+/// The parts of a pipeline outcome the suggestions are derived from.
+#[derive(Debug, Clone, Default)]
+pub struct FailureSummary<'a> {
+    pub error: Option<&'a str>,
+    pub failure_signatures: &'a [String],
+    pub retry_ladder_stage_reached: Option<u32>,
+    pub part_acceptance_rate: Option<f32>,
+    /// An execution context existed, so code could be validated.
+    pub python_available: bool,
+}
+
+fn action(kind: SuggestedActionKind, label: &str, reason: String) -> SuggestedAction {
+    SuggestedAction {
+        kind,
+        label: label.to_string(),
+        reason,
+    }
+}
+
+fn mentions_any(text: &str, needles: &[&str]) -> bool {
+    let lower = text.to_lowercase();
+    needles.iter().any(|n| lower.contains(n))
+}
+
+/// Map a failed run to next steps, most specific first. The mapping is a pure
+/// function of the outcome and settings, so the same failure always yields
+/// the same suggestions.
+pub fn suggest_actions(summary: &FailureSummary, config: &AppConfig) -> Vec<SuggestedAction> {
+    let mut actions: Vec<SuggestedAction> = Vec::new();
+    let texts: Vec<&str> = summary
+        .error
+        .into_iter()
+        .chain(summary.failure_signatures.iter().map(String::as_str))
+        .collect();
+    let first_match = |needles: &[&str]| texts.iter().find(|t| mentions_any(t, needles)).copied();
+
+    if let Some(text) = first_match(&["timed out", "timeout", "runtime exceeded"]) {
+        actions.push(action(
+            SuggestedActionKind::IncreaseTimeout,
+            "Increase the generation timeout in Settings",
+            format!("The run hit a time limit: {}", text),
+        ));
+    }
+    if !summary.python_available {
+        actions.push(action(
+            SuggestedActionKind::SetUpPython,
+            "Set up Python so generated code is executed and repaired",
+            "Code was not validated because no Python environment is set up".to_string(),
+        ));
+    }
+    if let Some(text) = first_match(&[
+        "api key",
+        "rate limit",
+        "429",
+        "unauthorized",
+        "ai provider error",
+    ]) {
+        actions.push(action(
+            SuggestedActionKind::CheckProviderSettings,
+            "Check the AI provider, API key and rate limit",
+            format!("The provider rejected a request: {}", text),
+        ));
+    }
+
+    let ladder_exhausted = summary.retry_ladder_stage_reached.is_some_and(|s| s >= 2);
+    let kernel_failure = first_match(&["geometrykernel", "topology", "fillet", "boolean"]);
+    if config.generation_reliability_profile != GenerationReliabilityProfile::ReliabilityFirst
+        && (ladder_exhausted || kernel_failure.is_some())
+    {
+        let reason = match kernel_failure {
+            Some(text) => format!("Geometry operations kept failing: {}", text),
+            None => "Repairs escalated through the retry ladder without success".to_string(),
+        };
+        actions.push(action(
+            SuggestedActionKind::UseReliabilityFirst,
+            "Switch the reliability profile to reliability-first",
+            reason,
+        ));
+    }
+
+    let low_acceptance = summary
+        .part_acceptance_rate
+        .filter(|rate| *rate < LOW_ACCEPTANCE_RATE);
+    if let Some(rate) = low_acceptance {
+        actions.push(action(
+            SuggestedActionKind::ReduceParts,
+            "Ask for fewer parts, or generate the failing parts separately",
+            format!(
+                "Only {:.0}% of the planned parts were accepted",
+                rate * 100.0
+            ),
+        ));
+    }
+    if let Some(text) = first_match(&[
+        "semantic",
+        "multipart_contract",
+        "component count",
+        "split_part",
+        "clearance_out_of_range",
+    ]) {
+        actions.push(action(
+            SuggestedActionKind::SimplifyRequest,
+            "Simplify the request or state key dimensions explicitly",
+            format!("The result did not match the request: {}", text),
+        ));
+    } else if ladder_exhausted || kernel_failure.is_some() {
+        actions.push(action(
+            SuggestedActionKind::SimplifyRequest,
+            "Simplify the request, e.g. drop fillets and decorative features",
+            "Validation could not repair the generated geometry".to_string(),
+        ));
+    }
+
+    if actions.is_empty() {
+        actions.push(action(
+            SuggestedActionKind::RetryGeneration,
+            "Retry the generation",
+            summary
+                .error
+                .map(|e| format!("The run failed: {}", e))
+                .unwrap_or_else(|| "The run failed without a specific cause".to_string()),
+        ));
+    }
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(actions: &[SuggestedAction]) -> Vec<SuggestedActionKind> {
+        actions.iter().map(|a| a.kind).collect()
+    }
+
+    fn balanced() -> AppConfig {
+        AppConfig {
+            generation_reliability_profile: GenerationReliabilityProfile::Balanced,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_timeout_suggests_increasing_it() {
+        let summary = FailureSummary {
+            error: Some("Generation runtime exceeded 600 seconds (effective timeout)"),
+            python_available: true,
+            ..Default::default()
+        };
+        let actions = suggest_actions(&summary, &AppConfig::default());
+        assert_eq!(kinds(&actions), vec![SuggestedActionKind::IncreaseTimeout]);
+        assert!(actions[0].reason.contains("600 seconds"));
+    }
+
+    #[test]
+    fn test_kernel_failures_suggest_reliability_first_and_simplifying() {
+        let signatures = vec!["lid:GeometryKernel".to_string()];
+        let summary = FailureSummary {
+            error: Some("Validation failed after 4 attempts"),
+            failure_signatures: &signatures,
+            retry_ladder_stage_reached: Some(3),
+            python_available: true,
+            ..Default::default()
+        };
+        let actions = suggest_actions(&summary, &balanced());
+        assert_eq!(
+            kinds(&actions),
+            vec![
+                SuggestedActionKind::UseReliabilityFirst,
+                SuggestedActionKind::SimplifyRequest
+            ]
+        );
+        assert!(actions[0].reason.contains("lid:GeometryKernel"));
+
+        // Already on reliability-first: only the simplification remains.
+        let actions = suggest_actions(&summary, &AppConfig::default());
+        assert_eq!(kinds(&actions), vec![SuggestedActionKind::SimplifyRequest]);
+    }
+
+    #[test]
+    fn test_rejected_parts_suggest_fewer_parts() {
+        let signatures = vec![
+            "semantic_acceptance_all_rejected".to_string(),
+            "multipart_contract_missing_parts".to_string(),
+        ];
+        let summary = FailureSummary {
+            failure_signatures: &signatures,
+            part_acceptance_rate: Some(0.25),
+            python_available: true,
+            ..Default::default()
+        };
+        let actions = suggest_actions(&summary, &balanced());
+        assert_eq!(
+            kinds(&actions),
+            vec![
+                SuggestedActionKind::ReduceParts,
+                SuggestedActionKind::SimplifyRequest
+            ]
+        );
+        assert!(actions[0].reason.contains("25%"));
+    }
+
+    #[test]
+    fn test_missing_python_and_provider_errors() {
+        let summary = FailureSummary {
+            error: Some("AI provider error: 429 Too Many Requests"),
+            python_available: false,
+            ..Default::default()
+        };
+        let actions = suggest_actions(&summary, &AppConfig::default());
+        assert_eq!(
+            kinds(&actions),
+            vec![
+                SuggestedActionKind::SetUpPython,
+                SuggestedActionKind::CheckProviderSettings
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unexplained_failure_falls_back_to_retry() {
+        let summary = FailureSummary {
+            error: Some("Model returned no code"),
+            python_available: true,
+            ..Default::default()
+        };
+        let actions = suggest_actions(&summary, &AppConfig::default());
+        assert_eq!(kinds(&actions), vec![SuggestedActionKind::RetryGeneration]);
+        // Deterministic: the same outcome yields the same suggestions.
+        assert_eq!(actions, suggest_actions(&summary, &AppConfig::default()));
+    }
+}
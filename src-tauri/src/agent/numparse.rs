@@ -0,0 +1,187 @@
+//! Numeric tokens in user- and planner-written text, accepting both `.` and
+//! `,` as decimal separators ("1,8 mm" as well as "1.8 mm").
+//!
+//! Regex sites embed [`NUMBER`] in a capture group and convert the capture
+//! with [`parse_number`]. Disambiguation rules:
+//! - one separator occurring once is the decimal point (`1,8`, `1.250`),
+//!   except a comma followed by exactly `000`, which groups thousands
+//!   (`1,000`); nobody writes a decimal comma with three trailing zeros.
+//!   Any other `,NNN` stays ambiguous (`2,500` is 2.5 in German but 2500 in
+//!   English) and is read as a decimal, so "2,500mm" parses as 2.5 mm;
+//! - with both separators, the last one is the decimal point and the other
+//!   groups thousands (`1.234,5`, `1,234.5`);
+//! - one separator repeated is a thousands separator and every group after
+//!   the first must have three digits (`1.234.567`), otherwise the token is
+//!   rejected;
+//! - a simple exponent scales the value (`1e2`, `1,5e-3`).
+
+/// Regex fragment for an unsigned numeric token with optional separators.
+pub const NUMBER: &str = r"\d+(?:[.,]\d+)*(?:[eE][-+]?\d+)?";
+
+/// Regex fragment for a signed numeric token.
+pub const SIGNED_NUMBER: &str = r"-?\d+(?:[.,]\d+)*(?:[eE][-+]?\d+)?";
+
+/// Regex fragment for the separator between the two ends of a range
+/// ("1,5–2,0 mm", "1.5 - 2 mm", "1,5 bis 2 mm", "1.5 to 2 mm").
+pub const RANGE_SEPARATOR: &str = r"(?:\s*[-–—]\s*|\s+(?:to|bis|à)\s+)";
+
+/// Parse a numeric token matched by [`NUMBER`] or [`SIGNED_NUMBER`].
+pub fn parse_number(token: &str) -> Option<f64> {
+    let token = token.trim();
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+    let (digits, exponent) = match digits.find(['e', 'E']) {
+        Some(i) => (&digits[..i], Some(digits[i + 1..].parse::<i32>().ok()?)),
+        None => (digits, None),
+    };
+    if digits.is_empty() || !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let last_dot = digits.rfind('.');
+    let last_comma = digits.rfind(',');
+    let normalized = match (last_dot, last_comma) {
+        (None, None) => digits.to_string(),
+        (Some(d), Some(c)) => {
+            let (decimal_at, thousands) = if d > c { (d, ',') } else { (c, '.') };
+            let (int_part, frac_part) = (&digits[..decimal_at], &digits[decimal_at + 1..]);
+            if int_part.contains(if thousands == ',' { '.' } else { ',' })
+                || !valid_grouping(int_part, thousands)
+            {
+                return None;
+            }
+            format!("{}.{}", int_part.replace(thousands, ""), frac_part)
+        }
+        (Some(_), None) | (None, Some(_)) => {
+            let sep = if last_dot.is_some() { '.' } else { ',' };
+            // Only `,000` is unambiguous; `2,500` stays a decimal comma.
+            let thousand_comma = sep == ',' && digits.ends_with(",000");
+            if digits.matches(sep).count() == 1 && !thousand_comma {
+                digits.replace(sep, ".")
+            } else if valid_grouping(digits, sep) {
+                digits.replace(sep, "")
+            } else {
+                return None;
+            }
+        }
+    };
+
+    let normalized = match exponent {
+        Some(exp) => format!("{}e{}", normalized, exp),
+        None => normalized,
+    };
+    let value: f64 = normalized.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// `1.234.567`-style grouping: first group 1–3 digits, the rest exactly 3.
+fn valid_grouping(int_part: &str, sep: char) -> bool {
+    let mut groups = int_part.split(sep);
+    let first_ok = groups
+        .next()
+        .is_some_and(|g| (1..=3).contains(&g.len()) || !int_part.contains(sep));
+    first_ok && groups.all(|g| g.len() == 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    #[test]
+    fn test_parse_number_matrix() {
+        let cases: &[(&str, Option<f64>)] = &[
+            ("42", Some(42.0)),
+            ("7.5", Some(7.5)),
+            ("7,5", Some(7.5)),
+            ("1,8", Some(1.8)),
+            ("0,25", Some(0.25)),
+            ("-2,5", Some(-2.5)),
+            ("1.250", Some(1.25)),
+            ("1,250", Some(1.25)),
+            ("1.234,5", Some(1234.5)),
+            ("1,234.5", Some(1234.5)),
+            ("12.345.678", Some(12_345_678.0)),
+            ("12,345,678", Some(12_345_678.0)),
+            ("1.234.567,89", Some(1_234_567.89)),
+            ("1,234,567.89", Some(1_234_567.89)),
+            ("1,000", Some(1000.0)),
+            ("12,000", Some(12_000.0)),
+            ("1.000", Some(1.0)),
+            ("1,0005", Some(1.0005)),
+            ("1e2", Some(100.0)),
+            ("1.5e-3", Some(0.0015)),
+            ("2,5E3", Some(2500.0)),
+            ("-4e+1", Some(-40.0)),
+            ("1e", None),
+            ("10,20,30", None),
+            ("1.23.4", None),
+            ("1,2.3,4", None),
+            ("", None),
+            ("-", None),
+        ];
+        for (token, expected) in cases {
+            assert_eq!(parse_number(token), *expected, "token {:?}", token);
+        }
+    }
+
+    #[test]
+    fn test_number_fragment_in_mm_regex() {
+        let re = Regex::new(&format!(r"({})\s*mm", NUMBER)).unwrap();
+        let values: Vec<f64> = re
+            .captures_iter("1,8 mm Wandstärke, Boden 2.5mm, Länge 1.234,5 mm")
+            .filter_map(|c| parse_number(&c[1]))
+            .collect();
+        assert_eq!(values, vec![1.8, 2.5, 1234.5]);
+    }
+
+    #[test]
+    fn test_ambiguous_comma_group_reads_as_decimal() {
+        let re = Regex::new(&format!(r"({})\s*mm", NUMBER)).unwrap();
+        let cap = re.captures("Plattenbreite 2,500mm").unwrap();
+        assert_eq!(parse_number(&cap[1]), Some(2.5));
+        assert_eq!(parse_number("2,000"), Some(2000.0));
+    }
+
+    #[test]
+    fn test_number_fragment_takes_exponents() {
+        let re = Regex::new(&format!(r"({})\s*mm", NUMBER)).unwrap();
+        let values: Vec<f64> = re
+            .captures_iter("span 1e2 mm, gap 5E-1mm, plate 1,000mm")
+            .filter_map(|c| parse_number(&c[1]))
+            .collect();
+        assert_eq!(values, vec![100.0, 0.5, 1000.0]);
+    }
+
+    #[test]
+    fn test_number_fragment_does_not_swallow_list_commas() {
+        let re = Regex::new(&format!(r"({})\s*[x×]\s*({})\s*mm", NUMBER, NUMBER)).unwrap();
+        let cap = re.captures("Abmessungen: 42,5x28mm, Höhe 7,5 mm").unwrap();
+        assert_eq!(parse_number(&cap[1]), Some(42.5));
+        assert_eq!(parse_number(&cap[2]), Some(28.0));
+    }
+
+    #[test]
+    fn test_range_separator_variants() {
+        let re = Regex::new(&format!(
+            r"({}){}({})\s*mm",
+            NUMBER, RANGE_SEPARATOR, NUMBER
+        ))
+        .unwrap();
+        for (text, lo, hi) in [
+            ("1,5–2,0 mm", 1.5, 2.0),
+            ("1,5 - 2 mm", 1.5, 2.0),
+            ("1.5-2.0mm", 1.5, 2.0),
+            ("1,5 bis 2,5 mm", 1.5, 2.5),
+            ("3 to 4,5 mm", 3.0, 4.5),
+        ] {
+            let cap = re
+                .captures(text)
+                .unwrap_or_else(|| panic!("no range in {:?}", text));
+            assert_eq!(parse_number(&cap[1]), Some(lo), "{}", text);
+            assert_eq!(parse_number(&cap[2]), Some(hi), "{}", text);
+        }
+    }
+}
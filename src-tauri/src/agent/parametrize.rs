@@ -0,0 +1,835 @@
+//! Named parameters for magic numbers the model repeated across its code.
+//! A literal used at least `min_repeats` times is named from the request and
+//! plan text ("1.8 mm walls" gives `wall`) or from the calls it appears in,
+//! declared once after the imports, and substituted back. A value whose uses
+//! play different roles (a radius here, a height there) is never merged: it
+//! is listed as uncertain for the user to decide.
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::agent::executor::PostGeometryValidationReport;
+use crate::agent::numparse::{parse_number, NUMBER};
+
+/// Uses of one literal before it is worth a name.
+pub const DEFAULT_MIN_REPEATS: usize = 3;
+/// Too generic to name: origins, unit scales and halving.
+const TRIVIAL_VALUES: &[f64] = &[0.0, 1.0, 2.0];
+/// Per-axis bounding box drift accepted after substitution (mm).
+const BBOX_EPSILON_MM: f64 = 1e-3;
+/// Relative volume drift accepted after substitution.
+const VOLUME_EPSILON_RATIO: f64 = 1e-6;
+const PARAMETERS_COMMENT: &str = "# Parameters";
+
+/// What a literal measures, from the call or keyword it is passed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiteralRole {
+    Thickness,
+    Radius,
+    Diameter,
+    Chamfer,
+    Length,
+    Width,
+    Height,
+    Depth,
+    Spacing,
+    Count,
+    Angle,
+}
+
+impl LiteralRole {
+    fn noun(self) -> &'static str {
+        match self {
+            LiteralRole::Thickness => "thickness",
+            LiteralRole::Radius => "radius",
+            LiteralRole::Diameter => "diameter",
+            LiteralRole::Chamfer => "chamfer",
+            LiteralRole::Length => "length",
+            LiteralRole::Width => "width",
+            LiteralRole::Height => "height",
+            LiteralRole::Depth => "depth",
+            LiteralRole::Spacing => "spacing",
+            LiteralRole::Count => "count",
+            LiteralRole::Angle => "angle",
+        }
+    }
+
+    /// Counts, angles and lengths never share a variable.
+    fn kind(self) -> u8 {
+        match self {
+            LiteralRole::Count => 1,
+            LiteralRole::Angle => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Role of each positional argument of common build123d calls. A single
+/// entry applies to every numeric argument. Positions (`Pos`, `Location`)
+/// are usually derived from other dimensions and carry no role.
+const CALL_ROLES: &[(&str, &[LiteralRole])] = {
+    use LiteralRole::*;
+    &[
+        ("Box", &[Length, Width, Height]),
+        ("Cylinder", &[Radius, Height]),
+        ("Cone", &[Radius, Radius, Height]),
+        ("Sphere", &[Radius]),
+        ("Torus", &[Radius]),
+        ("Circle", &[Radius]),
+        ("Rectangle", &[Length, Width]),
+        ("RectangleRounded", &[Length, Width, Radius]),
+        ("SlotOverall", &[Length, Height]),
+        ("Hole", &[Radius, Depth]),
+        ("CounterBoreHole", &[Radius, Radius, Depth, Depth]),
+        ("fillet", &[Radius]),
+        ("chamfer", &[Chamfer]),
+        ("offset", &[Thickness]),
+        ("extrude", &[Depth]),
+        ("Rot", &[Angle]),
+        ("rotate", &[Angle]),
+        ("range", &[Count]),
+        ("PolarLocations", &[Radius, Count]),
+        ("GridLocations", &[Spacing, Spacing, Count, Count]),
+    ]
+};
+
+/// Part names that lead a parameter name, as in `wall` or `hole_diameter`.
+const SUBJECT_NOUNS: &[&str] = &[
+    "wall", "hole", "slot", "boss", "lip", "rim", "rib", "base", "lid", "plate", "post", "pin",
+    "tab", "flange", "groove", "pocket", "fillet", "chamfer", "shaft", "bore", "standoff",
+];
+
+/// Canonical measure noun for a word, e.g. "thick" → "thickness".
+fn measure_noun(word: &str) -> Option<&'static str> {
+    Some(match word {
+        "thickness" | "thick" => "thickness",
+        "diameter" | "dia" => "diameter",
+        "radius" | "radii" => "radius",
+        "width" | "wide" => "width",
+        "height" | "tall" | "high" => "height",
+        "depth" | "deep" => "depth",
+        "length" | "long" => "length",
+        "spacing" | "pitch" => "spacing",
+        "gap" => "gap",
+        "clearance" => "clearance",
+        "angle" => "angle",
+        "offset" => "offset",
+        _ => return None,
+    })
+}
+
+fn role_of_word(word: &str) -> Option<LiteralRole> {
+    use LiteralRole::*;
+    Some(match word {
+        "wall" | "walls" | "thickness" | "thick" | "shell" | "offset" => Thickness,
+        "radius" | "radii" | "fillet" | "round" | "r" => Radius,
+        "diameter" | "dia" | "bore" | "hole" | "holes" => Diameter,
+        "chamfer" => Chamfer,
+        "length" | "long" => Length,
+        "width" | "wide" => Width,
+        "height" | "tall" | "high" => Height,
+        "depth" | "deep" | "amount" => Depth,
+        "spacing" | "pitch" | "gap" | "clearance" | "distance" => Spacing,
+        "count" | "rows" | "columns" | "cols" | "copies" | "num" | "n" => Count,
+        "angle" | "angles" | "degrees" | "deg" | "rotation" => Angle,
+        _ => return None,
+    })
+}
+
+/// Role of an identifier such as `wall_thickness` or `hole_r`: names end in
+/// their measure, so the last word with a role wins.
+fn role_of_name(name: &str) -> Option<LiteralRole> {
+    name.to_ascii_lowercase()
+        .split('_')
+        .rev()
+        .find_map(role_of_word)
+}
+
+/// One use of a literal in the code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteralSpan {
+    /// 0-based line index.
+    pub line: usize,
+    /// Byte range within the line.
+    pub start: usize,
+    pub end: usize,
+    pub role: Option<LiteralRole>,
+}
+
+/// A repeated literal that can safely become one variable.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParameterSuggestion {
+    pub name: String,
+    pub value: f64,
+    /// The literal as first written, used for the declaration.
+    pub literal: String,
+    /// 1-based lines where it is substituted.
+    pub lines: Vec<usize>,
+    /// The name came from the request or plan text rather than the code.
+    pub named_from_request: bool,
+    #[serde(skip)]
+    pub spans: Vec<LiteralSpan>,
+}
+
+/// A repeated literal left alone because its uses may mean different things.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UncertainLiteral {
+    pub value: f64,
+    pub literal: String,
+    pub lines: Vec<usize>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ParametrizationPlan {
+    pub suggestions: Vec<ParameterSuggestion>,
+    pub uncertain: Vec<UncertainLiteral>,
+}
+
+/// Lines of `code` with comments and string literals blanked out, byte for
+/// byte, so offsets still index the original lines.
+fn masked_lines(code: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut triple: Option<&str> = None;
+    for line in code.lines() {
+        let mut out = String::with_capacity(line.len());
+        let mut quote: Option<char> = None;
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+            let c = rest.chars().next().unwrap();
+            let blank = |out: &mut String, n: usize| out.push_str(&" ".repeat(n));
+            if let Some(delim) = triple {
+                if rest.starts_with(delim) {
+                    triple = None;
+                    blank(&mut out, 3);
+                    i += 3;
+                } else {
+                    blank(&mut out, c.len_utf8());
+                    i += c.len_utf8();
+                }
+                continue;
+            }
+            if let Some(q) = quote {
+                if c == '\\' && rest.len() > 1 {
+                    let escaped = rest[1..].chars().next().unwrap().len_utf8();
+                    blank(&mut out, 1 + escaped);
+                    i += 1 + escaped;
+                    continue;
+                }
+                if c == q {
+                    quote = None;
+                }
+                blank(&mut out, c.len_utf8());
+                i += c.len_utf8();
+                continue;
+            }
+            if rest.starts_with("\"\"\"") || rest.starts_with("'''") {
+                triple = Some(&rest[..3]);
+                blank(&mut out, 3);
+                i += 3;
+            } else if c == '"' || c == '\'' {
+                quote = Some(c);
+                blank(&mut out, 1);
+                i += 1;
+            } else if c == '#' {
+                blank(&mut out, rest.len());
+                break;
+            } else {
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+        lines.push(out);
+    }
+    lines
+}
+
+/// Innermost call enclosing byte `pos` of `line`, with the argument index.
+fn enclosing_call(line: &str, pos: usize) -> Option<(&str, usize)> {
+    let bytes = line.as_bytes();
+    let mut depth = 0usize;
+    let mut commas = 0usize;
+    for i in (0..pos).rev() {
+        match bytes[i] {
+            b')' | b']' => depth += 1,
+            b'(' | b'[' if depth > 0 => depth -= 1,
+            b'[' => return None,
+            b'(' => {
+                let head = line[..i].trim_end();
+                let start = head
+                    .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .map_or(0, |j| j + 1);
+                let name = &head[start..];
+                return (!name.is_empty()).then_some((name, commas));
+            }
+            b',' if depth == 0 => commas += 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Role of the literal at `start..end` from the keyword or assignment it
+/// follows, else from the call it is an argument of. A literal inside a
+/// larger expression (`40 - 2 * 1.8`) has no role of its own.
+fn literal_role(line: &str, start: usize, end: usize) -> Option<LiteralRole> {
+    let after = line[end..].trim_start();
+    if !(after.is_empty() || after.starts_with([',', ')'])) {
+        return None;
+    }
+    let before = line[..start].trim_end().trim_end_matches('-').trim_end();
+    if let Some(head) = before.strip_suffix('=') {
+        if !head.ends_with(['=', '<', '>', '!']) {
+            let head = head.trim_end();
+            let name_start = head
+                .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .map_or(0, |j| j + 1);
+            if let Some(role) = role_of_name(&head[name_start..]) {
+                return Some(role);
+            }
+        }
+    }
+    if !before.ends_with(['(', ',']) {
+        return None;
+    }
+    let (call, index) = enclosing_call(line, start)?;
+    let roles = CALL_ROLES.iter().find(|(name, _)| *name == call)?.1;
+    match roles {
+        [single] => Some(*single),
+        _ => roles.get(index).copied(),
+    }
+}
+
+/// Top-level `name = number` lines, which already are parameters.
+fn is_parameter_declaration(line: &str) -> bool {
+    let re = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*\s*=\s*-?\d+(?:\.\d+)?\s*$").unwrap();
+    re.is_match(line.trim_end())
+}
+
+fn is_import(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("import ") || trimmed.starts_with("from ")
+}
+
+/// Non-trivial numeric literals in `code`, grouped by value in order of
+/// first use, with the literal text of that first use.
+fn literal_groups(code: &str) -> Vec<(f64, String, Vec<LiteralSpan>)> {
+    let literal_re = Regex::new(r"\d+(?:\.\d+)?").unwrap();
+    let mut groups: Vec<(f64, String, Vec<LiteralSpan>)> = Vec::new();
+    for (index, line) in masked_lines(code).iter().enumerate() {
+        if is_import(line) || is_parameter_declaration(line) {
+            continue;
+        }
+        let bytes = line.as_bytes();
+        for m in literal_re.find_iter(line) {
+            let prev = m.start().checked_sub(1).map(|i| bytes[i]);
+            let next = bytes.get(m.end()).copied();
+            let glued = |b: Option<u8>| {
+                b.is_some_and(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'.')
+            };
+            // Identifiers, attribute access, exponents and list indices.
+            if glued(prev) || glued(next) || (prev == Some(b'[') && next == Some(b']')) {
+                continue;
+            }
+            let Ok(value) = m.as_str().parse::<f64>() else {
+                continue;
+            };
+            if TRIVIAL_VALUES.contains(&value) {
+                continue;
+            }
+            let span = LiteralSpan {
+                line: index,
+                start: m.start(),
+                end: m.end(),
+                role: literal_role(line, m.start(), m.end()),
+            };
+            match groups.iter_mut().find(|(v, _, _)| *v == value) {
+                Some((_, _, spans)) => spans.push(span),
+                None => groups.push((value, m.as_str().to_string(), vec![span])),
+            }
+        }
+    }
+    groups
+}
+
+fn normalize_word(word: &str) -> String {
+    let word = word.to_ascii_lowercase();
+    match word.strip_suffix('s') {
+        Some(stem) if stem.len() > 2 && !word.ends_with("ss") && word != "radius" => {
+            stem.to_string()
+        }
+        _ => word,
+    }
+}
+
+fn is_name_word(word: &str) -> bool {
+    SUBJECT_NOUNS.contains(&word) || measure_noun(word).is_some()
+}
+
+/// Parameter name from the words around a number: part nouns first, then
+/// the measure; a wall's thickness is just `wall`.
+fn name_from_words(words: &[String]) -> Option<String> {
+    let subject = words.iter().find(|w| SUBJECT_NOUNS.contains(&w.as_str()));
+    let measure = words
+        .iter()
+        .filter_map(|w| measure_noun(w))
+        .find(|m| Some(*m) != subject.map(|s| s.as_str()));
+    match (subject, measure) {
+        (Some(s), Some("thickness")) if s == "wall" => Some(s.clone()),
+        (Some(s), Some(m)) => Some(format!("{}_{}", s, m)),
+        (Some(s), None) => Some(s.clone()),
+        (None, Some(m)) => Some(m.to_string()),
+        (None, None) => None,
+    }
+}
+
+/// Names the request and plan give to millimetre values: "1.8 mm walls",
+/// "wall thickness of 1.8 mm", "hole_diameter: 4".
+fn text_names(texts: &[&str]) -> Vec<(f64, String)> {
+    let number_re = Regex::new(&format!(
+        r"({})\s*(mm\b|millimet(?:er|re)s?\b|cm\b|m\b|in\b|inch(?:es)?\b|\x22)?",
+        NUMBER
+    ))
+    .unwrap();
+    let word_re = Regex::new(r"^[\s-]*([A-Za-z]+)").unwrap();
+    let filler_re = Regex::new(r"(?i)(?:[\s:=]|\bof\b|\bis\b|\bat\b)*$").unwrap();
+    let mut names = Vec::new();
+    for text in texts {
+        for cap in number_re.captures_iter(text) {
+            let unit = cap.get(2).map(|u| u.as_str().to_ascii_lowercase());
+            if unit
+                .as_deref()
+                .is_some_and(|u| !u.starts_with("mm") && !u.starts_with("milli"))
+            {
+                continue;
+            }
+            let Some(value) = parse_number(&cap[1]) else {
+                continue;
+            };
+            let mut words: Vec<String> = Vec::new();
+            if unit.is_some() {
+                let mut rest = &text[cap.get(0).unwrap().end()..];
+                while let Some(w) = word_re.captures(rest) {
+                    let word = normalize_word(&w[1]);
+                    if !is_name_word(&word) {
+                        break;
+                    }
+                    words.push(word);
+                    rest = &rest[w.get(0).unwrap().end()..];
+                }
+            }
+            if words.is_empty() {
+                let head = &text[..cap.get(1).unwrap().start()];
+                let head = &head[..filler_re.find(head).map_or(head.len(), |f| f.start())];
+                for word in head.split(|c: char| !c.is_ascii_alphabetic()).rev() {
+                    let word = normalize_word(word);
+                    if word.is_empty() {
+                        continue;
+                    }
+                    if !is_name_word(&word) {
+                        break;
+                    }
+                    words.insert(0, word);
+                }
+            }
+            if let Some(name) = name_from_words(&words) {
+                names.push((value, name));
+            }
+        }
+    }
+    names
+}
+
+/// Every number the request and plan mention, named or not.
+fn text_values(texts: &[&str]) -> Vec<f64> {
+    let number_re = Regex::new(NUMBER).unwrap();
+    texts
+        .iter()
+        .flat_map(|text| number_re.find_iter(text))
+        .filter_map(|m| parse_number(m.as_str()))
+        .collect()
+}
+
+/// `name`, or `name_2`, `name_3`... if the code or an earlier suggestion
+/// already uses it.
+fn unique_name(name: &str, code: &str, taken: &[String]) -> String {
+    let used = |candidate: &str| {
+        taken.iter().any(|t| t == candidate)
+            || Regex::new(&format!(r"\b{}\b", regex::escape(candidate)))
+                .unwrap()
+                .is_match(code)
+    };
+    if !used(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}_{}", name, n))
+        .find(|candidate| !used(candidate))
+        .unwrap()
+}
+
+/// Repeated literals of `code` to turn into variables, named from `texts`
+/// (request and plan) where possible. Literals whose uses disagree on what
+/// they measure, that mix role-bearing and role-less uses the text doesn't
+/// name, or that the text names twice, go to `uncertain`.
+pub fn suggest_parameters(code: &str, texts: &[&str], min_repeats: usize) -> ParametrizationPlan {
+    let named = text_names(texts);
+    let mentioned = text_values(texts);
+    let mut plan = ParametrizationPlan::default();
+    for (value, literal, spans) in literal_groups(code) {
+        if spans.len() < min_repeats.max(2) {
+            continue;
+        }
+        let lines: Vec<usize> = {
+            let mut lines: Vec<usize> = spans.iter().map(|s| s.line + 1).collect();
+            lines.dedup();
+            lines
+        };
+        let mut roles: Vec<LiteralRole> = Vec::new();
+        for role in spans.iter().filter_map(|s| s.role) {
+            if !roles.contains(&role) {
+                roles.push(role);
+            }
+        }
+        let mut text_matches: Vec<&String> = Vec::new();
+        for (_, name) in named.iter().filter(|(v, _)| (v - value).abs() < 1e-9) {
+            if !text_matches.contains(&name) {
+                text_matches.push(name);
+            }
+        }
+        let uncertain = |reason: String| UncertainLiteral {
+            value,
+            literal: literal.clone(),
+            lines: lines.clone(),
+            reason,
+        };
+
+        if roles.len() > 1 {
+            let listed: Vec<&str> = roles.iter().map(|r| r.noun()).collect();
+            plan.uncertain.push(uncertain(format!(
+                "used as {} in different places",
+                listed.join(" and ")
+            )));
+            continue;
+        }
+        if text_matches.len() > 1 {
+            let listed: Vec<String> = text_matches.iter().map(|n| format!("`{}`", n)).collect();
+            plan.uncertain.push(uncertain(format!(
+                "the request gives this value to {}",
+                listed.join(" and ")
+            )));
+            continue;
+        }
+        let text_name = text_matches.first().map(|n| n.to_string());
+        if let (None, Some(role)) = (&text_name, roles.first()) {
+            if spans.iter().any(|s| s.role.is_none()) && !mentioned.contains(&value) {
+                plan.uncertain.push(uncertain(format!(
+                    "used as a {} in some places and with no clear role in others",
+                    role.noun()
+                )));
+                continue;
+            }
+        }
+        if let (Some(name), Some(role)) = (&text_name, roles.first()) {
+            if role_of_name(name).is_some_and(|r| r.kind() != role.kind()) {
+                plan.uncertain.push(uncertain(format!(
+                    "the request calls it `{}` but the code uses it as a {}",
+                    name,
+                    role.noun()
+                )));
+                continue;
+            }
+        }
+
+        let base = text_name.clone().unwrap_or_else(|| match roles.first() {
+            Some(role) => role.noun().to_string(),
+            None => format!("dim_{}", literal.replace('.', "_")),
+        });
+        let taken: Vec<String> = plan.suggestions.iter().map(|s| s.name.clone()).collect();
+        plan.suggestions.push(ParameterSuggestion {
+            name: unique_name(&base, code, &taken),
+            value,
+            literal,
+            lines,
+            named_from_request: text_name.is_some(),
+            spans,
+        });
+    }
+    plan
+}
+
+/// `code` with each suggestion declared after the imports and its literals
+/// replaced by the variable. Only the recorded spans change.
+pub fn apply_parameters(code: &str, suggestions: &[ParameterSuggestion]) -> String {
+    if suggestions.is_empty() {
+        return code.to_string();
+    }
+    let mut lines: Vec<String> = code.lines().map(str::to_string).collect();
+    let mut edits: Vec<(&LiteralSpan, &str)> = suggestions
+        .iter()
+        .flat_map(|s| s.spans.iter().map(move |span| (span, s.name.as_str())))
+        .collect();
+    // Right to left, so earlier offsets on the same line stay valid.
+    edits.sort_by_key(|(span, _)| std::cmp::Reverse((span.line, span.start)));
+    for (span, name) in &edits {
+        lines[span.line].replace_range(span.start..span.end, name);
+    }
+
+    let first_use = edits.iter().map(|(span, _)| span.line).min().unwrap_or(0);
+    let mut insert_at = lines[..first_use]
+        .iter()
+        .rposition(|line| is_import(line) && !line.starts_with([' ', '\t']))
+        .map_or(0, |i| i + 1);
+    // Past the closing parenthesis of a wrapped `from x import (...)`.
+    if insert_at > 0 && lines[insert_at - 1].contains('(') && !lines[insert_at - 1].contains(')') {
+        while insert_at < first_use && !lines[insert_at - 1].contains(')') {
+            insert_at += 1;
+        }
+    }
+    let mut block = vec![String::new(), PARAMETERS_COMMENT.to_string()];
+    if insert_at == 0 {
+        block.remove(0);
+    }
+    block.extend(
+        suggestions
+            .iter()
+            .map(|s| format!("{} = {}", s.name, s.literal)),
+    );
+    if lines.get(insert_at).is_some_and(|l| !l.trim().is_empty()) {
+        block.push(String::new());
+    }
+    lines.splice(insert_at..insert_at, block);
+
+    let mut out = lines.join("\n");
+    if code.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Ways the rewritten code's geometry differs from the original's; empty
+/// when bounding box and volume agree within epsilon.
+pub fn equivalence_mismatches(
+    before: &PostGeometryValidationReport,
+    after: &PostGeometryValidationReport,
+) -> Vec<String> {
+    let close = |a: f64, b: f64, epsilon: f64| (a - b).is_finite() && (a - b).abs() <= epsilon;
+    let mut mismatches = Vec::new();
+    for (i, axis) in ["x", "y", "z"].iter().enumerate() {
+        for (label, a, b) in [
+            ("bounds_min", before.bounds_min[i], after.bounds_min[i]),
+            ("bounds_max", before.bounds_max[i], after.bounds_max[i]),
+        ] {
+            if !close(a, b, BBOX_EPSILON_MM) {
+                mismatches.push(format!("{}.{} changed from {} to {}", label, axis, a, b));
+            }
+        }
+    }
+    let volume_tolerance = before.volume.abs().max(1.0) * VOLUME_EPSILON_RATIO;
+    if !close(before.volume, after.volume, volume_tolerance) {
+        mismatches.push(format!(
+            "volume changed from {} to {}",
+            before.volume, after.volume
+        ));
+    }
+    if before.component_count != after.component_count {
+        mismatches.push(format!(
+            "component count changed from {} to {}",
+            before.component_count, after.component_count
+        ));
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCLOSURE: &str = "from build123d import *
+
+# 1.8 mm walls all round
+outer = Box(40, 30, 20)
+inner = Pos(0, 0, 1.8) * Box(40 - 2 * 1.8, 30 - 2 * 1.8, 20)
+lid = Pos(0, 0, 25) * Box(40, 30, 1.8)
+label = \"1.8 mm\"
+result = outer - inner + lid
+";
+
+    fn report(bounds_max: [f64; 3], volume: f64) -> PostGeometryValidationReport {
+        PostGeometryValidationReport {
+            watertight: true,
+            manifold: true,
+            degenerate_faces: 0,
+            euler_number: 2,
+            triangle_count: 100,
+            component_count: 1,
+            bounds_min: [0.0, 0.0, 0.0],
+            bounds_max,
+            volume,
+            surface_area: 600.0,
+            center_of_mass: None,
+            unit_inertia: None,
+            bbox_ok: true,
+            warnings: vec![],
+        }
+    }
+
+    #[test]
+    fn test_repeated_literals_are_named_from_request_and_context() {
+        let request = "A 40 x 30 mm enclosure with 1.8 mm walls";
+        let plan = suggest_parameters(ENCLOSURE, &[request], DEFAULT_MIN_REPEATS);
+        let summary: Vec<String> = plan
+            .suggestions
+            .iter()
+            .map(|s| {
+                format!(
+                    "{}={} {:?} {}",
+                    s.name, s.literal, s.lines, s.named_from_request
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "length=40 [4, 5, 6] false",
+                "width=30 [4, 5, 6] false",
+                "wall=1.8 [5, 6] true",
+            ]
+        );
+        // The comment and the string never count as uses.
+        assert_eq!(plan.suggestions[2].spans.len(), 4);
+        assert!(plan.uncertain.is_empty());
+
+        let names = text_names(&[
+            "wall thickness of 2,5 mm; hole_diameter: 4",
+            "3 mm thick walls",
+        ]);
+        assert_eq!(
+            names,
+            vec![
+                (2.5, "wall".to_string()),
+                (4.0, "hole_diameter".to_string()),
+                (3.0, "wall".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literals_with_different_roles_are_not_merged() {
+        let pegs = "from build123d import *
+base = Box(12, 12, 4)
+pegs = [Pos(i * 5, 0, 4) * Cylinder(4, 8) for i in range(4)]
+result = base + pegs
+";
+        let plan = suggest_parameters(pegs, &[], 2);
+        assert!(plan.suggestions.is_empty());
+        let reasons: Vec<String> = plan
+            .uncertain
+            .iter()
+            .map(|u| format!("{}: {}", u.literal, u.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "12: used as length and width in different places",
+                "4: used as height and radius and count in different places",
+            ]
+        );
+        assert_eq!(apply_parameters(pegs, &plan.suggestions), pegs);
+
+        let offsets = "from build123d import *
+a = Pos(3.5, 0, 0) * Box(10, 10, 10)
+b = Pos(-3.5, 0, 0) * Box(10, 10, 10)
+c = Pos(0, 3.5, 0) * Box(10, 10, 10)
+result = a + b + c
+";
+        let plan = suggest_parameters(offsets, &["3.5 mm walls and 3.5 mm slots"], 3);
+        assert_eq!(plan.uncertain.len(), 2);
+        assert_eq!(
+            plan.uncertain[0].reason,
+            "the request gives this value to `wall` and `slot`"
+        );
+        assert_eq!(plan.uncertain[1].literal, "10");
+
+        let counted = "from build123d import *
+ring = PolarLocations(20, 6)
+spokes = [Rot(0, 0, i * 60) * Box(40, 3, 3) for i in range(6)]
+holes = [Cylinder(1.5, 3) for _ in range(6)]
+";
+        let plan = suggest_parameters(counted, &["six 6 mm holes"], 3);
+        assert_eq!(
+            plan.uncertain[0].reason,
+            "the request calls it `hole` but the code uses it as a count"
+        );
+        assert!(plan.suggestions.iter().all(|s| s.value != 6.0));
+
+        let shifted = "from build123d import *
+base = Box(25, 10, 4)
+peg = Pos(25 / 2, 0, 4) * Cylinder(2, 6)
+result = base + peg
+";
+        let plan = suggest_parameters(shifted, &["a base with a peg"], 2);
+        assert!(plan.suggestions.iter().all(|s| s.value != 25.0));
+        assert_eq!(plan.uncertain[0].literal, "25");
+        assert_eq!(
+            plan.uncertain[0].reason,
+            "used as a length in some places and with no clear role in others"
+        );
+        let plan = suggest_parameters(shifted, &["a 25 mm base with a peg"], 2);
+        assert!(plan.suggestions.iter().any(|s| s.value == 25.0));
+    }
+
+    #[test]
+    fn test_rewrite_declares_parameters_after_imports() {
+        let plan = suggest_parameters(
+            ENCLOSURE,
+            &["A 40 x 30 mm enclosure with 1.8 mm walls"],
+            DEFAULT_MIN_REPEATS,
+        );
+        let rewritten = apply_parameters(ENCLOSURE, &plan.suggestions);
+        assert_eq!(
+            rewritten,
+            "from build123d import *
+
+# Parameters
+length = 40
+width = 30
+wall = 1.8
+
+# 1.8 mm walls all round
+outer = Box(length, width, 20)
+inner = Pos(0, 0, wall) * Box(length - 2 * wall, width - 2 * wall, 20)
+lid = Pos(0, 0, 25) * Box(length, width, wall)
+label = \"1.8 mm\"
+result = outer - inner + lid
+"
+        );
+        // Applying again finds nothing left to name.
+        assert!(suggest_parameters(&rewritten, &[], DEFAULT_MIN_REPEATS)
+            .suggestions
+            .is_empty());
+    }
+
+    #[test]
+    fn test_equivalence_gate_compares_bbox_and_volume() {
+        let before = report([40.0, 30.0, 26.8], 9876.5);
+        assert!(equivalence_mismatches(&before, &report([40.0, 30.0, 26.8], 9876.5)).is_empty());
+        assert!(
+            equivalence_mismatches(&before, &report([40.0, 30.0, 26.8000001], 9876.5000001))
+                .is_empty()
+        );
+
+        let drifted = equivalence_mismatches(&before, &report([40.0, 30.0, 27.0], 9900.0));
+        assert_eq!(
+            drifted,
+            vec![
+                "bounds_max.z changed from 26.8 to 27",
+                "volume changed from 9876.5 to 9900",
+            ]
+        );
+        let failed = equivalence_mismatches(&before, &report([40.0, 30.0, 26.8], f64::NAN));
+        assert_eq!(failed.len(), 1);
+    }
+}
@@ -0,0 +1,102 @@
+//! Per-part display colors for multi-part assemblies. A part keeps the color
+//! the user assigned with `set_part_color`; any other part gets a palette
+//! color picked by a hash of its name, so the same part is tinted the same
+//! way across runs, retries and modifications.
+
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+
+/// Linear RGB, each channel in 0..=1 as build123d's `Color` expects.
+pub type PartColor = [f64; 3];
+
+/// Qualitative palette, distinguishable on the viewer's dark background.
+/// Gray is left out so colored parts never look like untinted ones.
+const PALETTE: [PartColor; 9] = [
+    [0.306, 0.475, 0.655],
+    [0.949, 0.557, 0.169],
+    [0.882, 0.341, 0.349],
+    [0.463, 0.718, 0.698],
+    [0.349, 0.631, 0.310],
+    [0.929, 0.788, 0.282],
+    [0.690, 0.478, 0.631],
+    [1.000, 0.616, 0.655],
+    [0.612, 0.459, 0.373],
+];
+
+/// Palette color for a part name. Uses SHA-256 rather than `DefaultHasher`,
+/// whose output may change between Rust releases.
+pub fn palette_color(part_name: &str) -> PartColor {
+    let digest = Sha256::digest(part_name.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    PALETTE[(u64::from_le_bytes(bytes) % PALETTE.len() as u64) as usize]
+}
+
+/// The user's color for `part_name` if one was assigned, else its palette color.
+pub fn resolve(part_name: &str, assigned: &HashMap<String, PartColor>) -> PartColor {
+    assigned
+        .get(part_name)
+        .copied()
+        .unwrap_or_else(|| palette_color(part_name))
+}
+
+/// Check a user-supplied color before it is stored.
+pub fn validate(color: PartColor) -> Result<PartColor, String> {
+    if color
+        .iter()
+        .all(|c| c.is_finite() && (0.0..=1.0).contains(c))
+    {
+        Ok(color)
+    } else {
+        Err(format!(
+            "Color channels must be between 0 and 1, got {:?}",
+            color
+        ))
+    }
+}
+
+/// `part_<name>.color = Color(r, g, b)` line for the assembly section.
+pub fn color_statement(part_name: &str, color: PartColor) -> String {
+    format!(
+        "part_{}.color = Color({:.3}, {:.3}, {:.3})\n",
+        part_name, color[0], color[1], color[2]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_is_deterministic() {
+        assert_eq!(palette_color("lid"), palette_color("lid"));
+        assert!(PALETTE.contains(&palette_color("lid")));
+        // Different names spread over the palette rather than sharing one color.
+        let names = ["base", "lid", "hinge_pin", "latch", "gasket", "foot"];
+        let distinct: std::collections::HashSet<String> = names
+            .iter()
+            .map(|n| format!("{:?}", palette_color(n)))
+            .collect();
+        assert!(distinct.len() > 1);
+    }
+
+    #[test]
+    fn test_user_color_takes_precedence() {
+        let mut assigned = HashMap::new();
+        assigned.insert("lid".to_string(), [1.0, 0.0, 0.0]);
+        assert_eq!(resolve("lid", &assigned), [1.0, 0.0, 0.0]);
+        assert_eq!(resolve("base", &assigned), palette_color("base"));
+    }
+
+    #[test]
+    fn test_color_statement_and_validation() {
+        assert_eq!(
+            color_statement("lid", [1.0, 0.5, 0.25]),
+            "part_lid.color = Color(1.000, 0.500, 0.250)\n"
+        );
+        assert!(validate([0.0, 0.5, 1.0]).is_ok());
+        assert!(validate([0.0, 1.5, 1.0]).is_err());
+        assert!(validate([f64::NAN, 0.0, 0.0]).is_err());
+    }
+}
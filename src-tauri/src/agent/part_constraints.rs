@@ -0,0 +1,281 @@
+use regex::Regex;
+use serde::Serialize;
+
+use crate::agent::numparse;
+use crate::commands::parallel::PartSpec;
+
+/// Values closer than this (absolute mm or relative) count as the same.
+const ABS_TOLERANCE_MM: f64 = 0.01;
+const REL_TOLERANCE: f64 = 0.005;
+
+/// Words that make a dimension describe a sub-feature ("hole diameter 5mm"),
+/// not the part itself.
+const SUB_FEATURE_QUALIFIERS: &[&str] = &[
+    "hole",
+    "holes",
+    "bore",
+    "pin",
+    "screw",
+    "bolt",
+    "boss",
+    "slot",
+    "recess",
+    "pocket",
+    "groove",
+    "rib",
+    "tab",
+    "fillet",
+    "chamfer",
+    "mounting",
+    "post",
+    "magnet",
+    "thread",
+    "counterbore",
+    "cutout",
+    "window",
+    "lip",
+];
+
+/// Two requirements for one part that cannot both hold.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ConstraintConflict {
+    /// Canonical feature name, e.g. "outer diameter".
+    pub feature: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+struct StatedDimension {
+    feature: &'static str,
+    value: f64,
+    text: String,
+}
+
+fn canonical_feature(word: &str) -> Option<&'static str> {
+    let lower = word.to_lowercase();
+    let words = lower.split_whitespace().collect::<Vec<_>>().join(" ");
+    match words.as_str() {
+        "outer diameter" | "outside diameter" | "od" | "diameter" => Some("outer diameter"),
+        "inner diameter" | "inside diameter" | "id" => Some("inner diameter"),
+        "width" | "wide" => Some("width"),
+        "length" | "long" => Some("length"),
+        "height" | "tall" | "high" => Some("height"),
+        "thickness" | "thick" => Some("thickness"),
+        "depth" | "deep" => Some("depth"),
+        _ => None,
+    }
+}
+
+fn same_value(a: f64, b: f64) -> bool {
+    (a - b).abs() <= ABS_TOLERANCE_MM.max(REL_TOLERANCE * a.abs().max(b.abs()))
+}
+
+fn format_mm(value: f64) -> String {
+    format!("{}mm", (value * 1000.0).round() / 1000.0)
+}
+
+fn is_sub_feature(text: &str, feature_start: usize) -> bool {
+    text[..feature_start]
+        .split_whitespace()
+        .last()
+        .map(|w| {
+            w.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .is_some_and(|w| SUB_FEATURE_QUALIFIERS.contains(&w.as_str()))
+}
+
+/// Dimensions of the part itself, from "diameter 40mm", "OD: 35 mm" or
+/// "35mm OD" / "20 mm tall" phrasings.
+fn stated_dimensions(text: &str) -> Vec<StatedDimension> {
+    let features = r"outer\s+diameter|outside\s+diameter|inner\s+diameter|inside\s+diameter|od|id|diameter|width|length|height|thickness|depth";
+    let leading_re = Regex::new(&format!(
+        r"(?i)\b({})\b\s*(?:[:=]|of|is)?\s*({})\s*mm\b",
+        features,
+        numparse::NUMBER
+    ))
+    .unwrap();
+    let trailing_re = Regex::new(&format!(
+        r"(?i)\b({})\s*mm\s+({}|wide|long|tall|high|thick|deep)\b",
+        numparse::NUMBER,
+        features
+    ))
+    .unwrap();
+
+    let mut dims = Vec::new();
+    for cap in leading_re.captures_iter(text) {
+        let feature_match = cap.get(1).unwrap();
+        if is_sub_feature(text, feature_match.start()) {
+            continue;
+        }
+        if let (Some(feature), Some(value)) = (
+            canonical_feature(feature_match.as_str()),
+            numparse::parse_number(&cap[2]),
+        ) {
+            dims.push(StatedDimension {
+                feature,
+                value,
+                text: cap[0].trim().to_string(),
+            });
+        }
+    }
+    for cap in trailing_re.captures_iter(text) {
+        let whole = cap.get(0).unwrap();
+        if is_sub_feature(text, whole.start()) {
+            continue;
+        }
+        if let (Some(feature), Some(value)) =
+            (canonical_feature(&cap[2]), numparse::parse_number(&cap[1]))
+        {
+            dims.push(StatedDimension {
+                feature,
+                value,
+                text: whole.as_str().trim().to_string(),
+            });
+        }
+    }
+    dims
+}
+
+/// Clearance limits from "fits inside a 30mm bore" (outer size must be
+/// smaller) and "slides over a 10mm shaft" (inner size must be larger).
+fn fit_limits(text: &str) -> Vec<(&'static str, f64, String)> {
+    let inside_re = Regex::new(&format!(
+        r"(?i)\b(?:fits?|slides?|goes?|sits?|inserts?)\s+(?:inside|into|within|in)\s+(?:an?\s+|the\s+)?({})\s*mm\s+(?:diameter\s+)?(bore|hole|opening|tube|pipe|socket|recess)\b",
+        numparse::NUMBER
+    ))
+    .unwrap();
+    let over_re = Regex::new(&format!(
+        r"(?i)\b(?:fits?|slides?|goes?|sits?)\s+(?:over|onto|around|on)\s+(?:an?\s+|the\s+)?({})\s*mm\s+(?:diameter\s+)?(shaft|pin|rod|post|axle|tube|pipe|dowel)\b",
+        numparse::NUMBER
+    ))
+    .unwrap();
+
+    let mut limits = Vec::new();
+    for cap in inside_re.captures_iter(text) {
+        if let Some(v) = numparse::parse_number(&cap[1]) {
+            limits.push(("outer diameter", v, cap[0].trim().to_string()));
+        }
+    }
+    for cap in over_re.captures_iter(text) {
+        if let Some(v) = numparse::parse_number(&cap[1]) {
+            limits.push(("inner diameter", v, cap[0].trim().to_string()));
+        }
+    }
+    limits
+}
+
+/// Flag requirements in a part's description and constraints that contradict
+/// each other: the same feature stated with different values, or a stated
+/// size that cannot fit the bore/shaft it is supposed to fit.
+pub fn validate_part_constraints(part: &PartSpec) -> Vec<ConstraintConflict> {
+    let sources: Vec<&str> = std::iter::once(part.description.as_str())
+        .chain(part.constraints.iter().map(|c| c.as_str()))
+        .collect();
+    let dims: Vec<StatedDimension> = sources.iter().flat_map(|s| stated_dimensions(s)).collect();
+    let limits: Vec<(&'static str, f64, String)> =
+        sources.iter().flat_map(|s| fit_limits(s)).collect();
+
+    let mut conflicts = Vec::new();
+    let mut reported: Vec<&'static str> = Vec::new();
+    for (i, first) in dims.iter().enumerate() {
+        if reported.contains(&first.feature) {
+            continue;
+        }
+        if let Some(other) = dims[i + 1..]
+            .iter()
+            .find(|d| d.feature == first.feature && !same_value(d.value, first.value))
+        {
+            reported.push(first.feature);
+            conflicts.push(ConstraintConflict {
+                feature: first.feature.to_string(),
+                message: format!(
+                    "{} is given as both {} (\"{}\") and {} (\"{}\")",
+                    first.feature,
+                    format_mm(first.value),
+                    first.text,
+                    format_mm(other.value),
+                    other.text
+                ),
+            });
+        }
+    }
+
+    for (feature, limit, fit_text) in &limits {
+        for dim in dims.iter().filter(|d| d.feature == *feature) {
+            let impossible = match *feature {
+                "outer diameter" => dim.value > *limit && !same_value(dim.value, *limit),
+                _ => dim.value < *limit && !same_value(dim.value, *limit),
+            };
+            if impossible {
+                conflicts.push(ConstraintConflict {
+                    feature: feature.to_string(),
+                    message: format!(
+                        "{} {} (\"{}\") cannot satisfy \"{}\"",
+                        feature,
+                        format_mm(dim.value),
+                        dim.text,
+                        fit_text
+                    ),
+                });
+                break;
+            }
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(description: &str, constraints: &[&str]) -> PartSpec {
+        PartSpec {
+            name: "sleeve".to_string(),
+            description: description.to_string(),
+            position: [0.0, 0.0, 0.0],
+            constraints: constraints.iter().map(|c| c.to_string()).collect(),
+            reliability_profile: None,
+        }
+    }
+
+    #[test]
+    fn test_diameter_contradiction_is_flagged() {
+        let conflicts = validate_part_constraints(&part(
+            "Cylindrical knob, diameter 40mm, height 15mm",
+            &["diameter 50mm to match the dial"],
+        ));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].feature, "outer diameter");
+        assert!(conflicts[0].message.contains("40mm"));
+        assert!(conflicts[0].message.contains("50mm"));
+    }
+
+    #[test]
+    fn test_impossible_fit_is_flagged() {
+        let conflicts = validate_part_constraints(&part(
+            "Sleeve with 35mm OD and 2mm wall",
+            &["fits inside 30mm bore of the housing"],
+        ));
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].message.contains("cannot satisfy"));
+        assert!(conflicts[0].message.contains("30mm bore"));
+
+        let conflicts = validate_part_constraints(&part(
+            "Collar, ID 8mm, OD 20mm",
+            &["slides over a 10mm shaft"],
+        ));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].feature, "inner diameter");
+    }
+
+    #[test]
+    fn test_consistent_constraints_pass() {
+        assert!(validate_part_constraints(&part(
+            "Knob, diameter 40 mm, 15 mm tall, hole diameter 6mm",
+            &["diameter 40,0 mm", "fits inside 42mm bore", "height 15mm"],
+        ))
+        .is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+use regex::Regex;
+
+/// Groups whose members differ in more literals than this stay as copies.
+pub const MAX_SHARED_PARAMS: usize = 6;
+
+/// Parts whose code only differs in a few numeric literals, emitted as one
+/// `def make_<base>(...)` plus one call per part.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedPartGroup {
+    /// Indices into the parts slice, in input order.
+    pub members: Vec<usize>,
+    pub function_name: String,
+    pub param_names: Vec<String>,
+    /// Function definition (without trailing blank line).
+    pub definition: String,
+    /// Argument values per member, aligned with `param_names`.
+    pub args: Vec<Vec<String>>,
+}
+
+struct Normalized {
+    template: String,
+    literals: Vec<String>,
+    /// Name a literal is directly assigned to (`height = 700`), per literal.
+    literal_names: Vec<Option<String>>,
+}
+
+const PLACEHOLDER: char = '\u{0}';
+
+fn number_re() -> Regex {
+    Regex::new(r"\b\d+(?:\.\d+)?\b").unwrap()
+}
+
+/// Replace numeric literals with a placeholder so structurally identical
+/// code compares equal.
+fn normalize(code: &str) -> Normalized {
+    let re = number_re();
+    let assign_re = Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)\s*=\s*$").unwrap();
+    let mut template = String::with_capacity(code.len());
+    let mut literals = Vec::new();
+    let mut literal_names = Vec::new();
+    let mut last = 0;
+    for m in re.find_iter(code) {
+        // Skip digits that belong to an identifier-like token (e.g. `1e3`, `.5`).
+        let prev = code[..m.start()].chars().next_back();
+        if matches!(prev, Some(c) if c == '.' || c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        // Digits inside a string literal are text, not dimensions.
+        let line_prefix = &code[code[..m.start()].rfind('\n').map_or(0, |i| i + 1)..m.start()];
+        if line_prefix.matches('"').count() % 2 == 1 || line_prefix.matches('\'').count() % 2 == 1 {
+            continue;
+        }
+        template.push_str(&code[last..m.start()]);
+        template.push(PLACEHOLDER);
+        let next = code[m.end()..].chars().next();
+        let standalone = matches!(next, None | Some('\n') | Some(' ') | Some('#'));
+        literal_names.push(
+            assign_re
+                .captures(line_prefix)
+                .filter(|_| standalone)
+                .map(|c| c[1].to_string()),
+        );
+        literals.push(m.as_str().to_string());
+        last = m.end();
+    }
+    template.push_str(&code[last..]);
+    Normalized {
+        template,
+        literals,
+        literal_names,
+    }
+}
+
+fn assignment_count(code: &str, name: &str) -> usize {
+    Regex::new(&format!(r"(?m)^\s*{}\s*=[^=]", regex::escape(name)))
+        .map(|re| re.find_iter(code).count())
+        .unwrap_or(0)
+}
+
+/// False for code that cannot be safely indented into a function body.
+fn can_wrap(code: &str) -> bool {
+    !code.contains("\"\"\"")
+        && !code.contains("'''")
+        && !code.contains("global ")
+        && !code.contains("nonlocal ")
+        && Regex::new(r"(?m)^result\s*=").unwrap().is_match(code)
+}
+
+fn function_base_name(names: &[&str]) -> String {
+    let first = names[0];
+    let mut prefix_len = first.len();
+    for name in &names[1..] {
+        prefix_len = first
+            .bytes()
+            .zip(name.bytes())
+            .take(prefix_len)
+            .take_while(|(a, b)| a == b)
+            .count();
+    }
+    while !first.is_char_boundary(prefix_len) {
+        prefix_len -= 1;
+    }
+    let base = first[..prefix_len].trim_end_matches(|c: char| c == '_' || c.is_ascii_digit());
+    if base.is_empty() {
+        first.to_string()
+    } else {
+        base.to_string()
+    }
+}
+
+/// Find groups of parts (by cleaned code) that can share one function.
+pub fn find_shared_groups(parts: &[(&str, &str)]) -> Vec<SharedPartGroup> {
+    let normalized: Vec<Normalized> = parts.iter().map(|(_, code)| normalize(code)).collect();
+    let mut grouped = vec![false; parts.len()];
+    let mut groups = Vec::new();
+    let mut used_function_names: Vec<String> = Vec::new();
+
+    for i in 0..parts.len() {
+        if grouped[i] || !can_wrap(parts[i].1) {
+            continue;
+        }
+        let members: Vec<usize> = (i..parts.len())
+            .filter(|&j| !grouped[j] && normalized[j].template == normalized[i].template)
+            .collect();
+        if members.len() < 2 {
+            continue;
+        }
+
+        let literal_count = normalized[i].literals.len();
+        let varying: Vec<usize> = (0..literal_count)
+            .filter(|&k| {
+                members
+                    .iter()
+                    .any(|&m| normalized[m].literals[k] != normalized[i].literals[k])
+            })
+            .collect();
+        if varying.len() > MAX_SHARED_PARAMS {
+            continue;
+        }
+
+        let names: Vec<&str> = members.iter().map(|&m| parts[m].0).collect();
+        let mut function_name = format!("make_{}", function_base_name(&names));
+        let mut suffix = 2;
+        while used_function_names.contains(&function_name) {
+            function_name = format!("make_{}_{}", function_base_name(&names), suffix);
+            suffix += 1;
+        }
+        used_function_names.push(function_name.clone());
+
+        // Reuse the variable name of `name = <literal>` lines when that is the
+        // only assignment to the name; otherwise fall back to `dim_<n>`.
+        let mut param_names: Vec<String> = Vec::new();
+        for (n, &k) in varying.iter().enumerate() {
+            let candidate = normalized[i].literal_names[k]
+                .clone()
+                .filter(|name| {
+                    name != "result"
+                        && !param_names.contains(name)
+                        && assignment_count(&normalized[i].template, name) == 1
+                })
+                .unwrap_or_else(|| format!("dim_{}", n + 1));
+            param_names.push(candidate);
+        }
+
+        // Rebuild the body: varying literals become parameters. A literal
+        // assigned to a same-named variable (`height = 700`) becomes
+        // `height = height`, which is harmless.
+        let mut body = String::new();
+        let mut literal_idx = 0;
+        for ch in normalized[i].template.chars() {
+            if ch == PLACEHOLDER {
+                match varying.iter().position(|&k| k == literal_idx) {
+                    Some(p) => body.push_str(&param_names[p]),
+                    None => body.push_str(&normalized[i].literals[literal_idx]),
+                }
+                literal_idx += 1;
+            } else {
+                body.push(ch);
+            }
+        }
+
+        let mut definition = format!("def {}({}):\n", function_name, param_names.join(", "));
+        for line in body.trim_end().lines() {
+            if line.trim().is_empty() {
+                definition.push('\n');
+            } else {
+                definition.push_str(&format!("    {}\n", line));
+            }
+        }
+        definition.push_str("    return result");
+
+        let args = members
+            .iter()
+            .map(|&m| {
+                varying
+                    .iter()
+                    .map(|&k| normalized[m].literals[k].clone())
+                    .collect()
+            })
+            .collect();
+
+        for &m in &members {
+            grouped[m] = true;
+        }
+        groups.push(SharedPartGroup {
+            members,
+            function_name,
+            param_names,
+            definition,
+            args,
+        });
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEG: &str = "height = 700\nwith BuildPart() as leg:\n    Box(40, 40, height)\n    fillet(leg.edges().filter_by(Axis.Z), radius=3)\nresult = leg.part";
+
+    #[test]
+    fn test_identical_parts_share_parameterless_function() {
+        let parts = [
+            ("leg_1", LEG),
+            ("leg_2", LEG),
+            ("leg_3", LEG),
+            ("leg_4", LEG),
+        ];
+        let groups = find_shared_groups(&parts);
+        assert_eq!(groups.len(), 1);
+        let group = &groups[0];
+        assert_eq!(group.members, vec![0, 1, 2, 3]);
+        assert_eq!(group.function_name, "make_leg");
+        assert!(group.param_names.is_empty());
+        assert!(group
+            .definition
+            .starts_with("def make_leg():\n    height = 700\n"));
+        assert!(group.definition.ends_with("    return result"));
+    }
+
+    #[test]
+    fn test_literal_differences_become_named_parameters() {
+        let short = LEG.replace("700", "450");
+        let parts = [("front_leg", LEG), ("rear_leg", short.as_str())];
+        let groups = find_shared_groups(&parts);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].function_name, "make_front_leg");
+        assert_eq!(groups[0].param_names, vec!["height".to_string()]);
+        assert_eq!(
+            groups[0].args,
+            vec![vec!["700".to_string()], vec!["450".to_string()]]
+        );
+        assert!(groups[0].definition.contains("    height = height\n"));
+    }
+
+    #[test]
+    fn test_structural_or_wide_differences_are_not_grouped() {
+        let different = "result = Cylinder(10, 20)";
+        let parts = [("leg", LEG), ("cap", different)];
+        assert!(find_shared_groups(&parts).is_empty());
+
+        let many_a = "result = Box(1, 2, 3) + Box(4, 5, 6) + Box(7, 8, 9)";
+        let many_b = "result = Box(11, 12, 13) + Box(14, 15, 16) + Box(17, 18, 19)";
+        assert!(find_shared_groups(&[("a", many_a), ("b", many_b)]).is_empty());
+    }
+
+    #[test]
+    fn test_base_name_cuts_on_char_boundary() {
+        assert_eq!(function_base_name(&["füße_1", "füße_2"]), "füße");
+        assert_eq!(function_base_name(&["bügel", "büro"]), "bü");
+        assert_eq!(function_base_name(&["ä_leg", "ö_leg"]), "ä_leg");
+    }
+
+    #[test]
+    fn test_docstrings_are_not_wrapped() {
+        let code = "\"\"\"Leg\n\"\"\"\nresult = Box(1, 1, 1)";
+        assert!(find_shared_groups(&[("a", code), ("b", code)]).is_empty());
+    }
+}
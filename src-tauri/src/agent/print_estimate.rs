@@ -0,0 +1,375 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::executor::PostGeometryValidationReport;
+use crate::config::AppConfig;
+use crate::error::AppError;
+
+/// Relative error band quoted with every estimate.
+pub const ESTIMATE_UNCERTAINTY_PCT: u32 = 30;
+/// Extra time for travel moves, retractions and layer changes, as a fraction
+/// of pure extrusion time.
+const TRAVEL_OVERHEAD_FACTOR: f64 = 0.25;
+
+pub const DEFAULT_PRINTER_PROFILE_ID: &str = "generic_fdm";
+
+/// Slicer settings the estimate is based on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PrinterProfile {
+    pub id: String,
+    pub name: String,
+    pub nozzle_width_mm: f64,
+    pub layer_height_mm: f64,
+    /// Average extrusion speed.
+    pub print_speed_mm_s: f64,
+    pub infill_pct: f64,
+    #[serde(default = "default_perimeters")]
+    pub perimeters: u32,
+    #[serde(default = "default_filament_diameter_mm")]
+    pub filament_diameter_mm: f64,
+    /// Key into `AppConfig.materials`, used for the mass estimate.
+    #[serde(default = "default_material")]
+    pub material: String,
+}
+
+fn default_perimeters() -> u32 {
+    2
+}
+
+fn default_filament_diameter_mm() -> f64 {
+    1.75
+}
+
+fn default_material() -> String {
+    "pla".to_string()
+}
+
+pub fn default_printer_profiles() -> Vec<PrinterProfile> {
+    vec![PrinterProfile {
+        id: DEFAULT_PRINTER_PROFILE_ID.to_string(),
+        name: "Generic FDM (0.4 mm nozzle)".to_string(),
+        nozzle_width_mm: 0.4,
+        layer_height_mm: 0.2,
+        print_speed_mm_s: 60.0,
+        infill_pct: 20.0,
+        perimeters: default_perimeters(),
+        filament_diameter_mm: default_filament_diameter_mm(),
+        material: default_material(),
+    }]
+}
+
+/// Raw numbers from the mesh analysis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshStats {
+    pub volume_mm3: f64,
+    pub surface_area_mm2: f64,
+    /// Height in the print orientation.
+    pub height_mm: f64,
+}
+
+impl MeshStats {
+    /// Combined stats for parts printed side by side, or `None` when no part
+    /// has a closed mesh to measure.
+    pub fn from_reports<'a>(
+        reports: impl IntoIterator<Item = &'a PostGeometryValidationReport>,
+    ) -> Option<Self> {
+        let mut stats = MeshStats {
+            volume_mm3: 0.0,
+            surface_area_mm2: 0.0,
+            height_mm: 0.0,
+        };
+        for report in reports {
+            if report.volume <= 0.0 {
+                return None;
+            }
+            stats.volume_mm3 += report.volume;
+            stats.surface_area_mm2 += report.surface_area;
+            stats.height_mm = stats
+                .height_mm
+                .max(report.bounds_max[2] - report.bounds_min[2]);
+        }
+        (stats.volume_mm3 > 0.0).then_some(stats)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, JsonSchema)]
+pub struct PrintEstimate {
+    pub profile_id: String,
+    pub profile_name: String,
+    pub material: String,
+    /// Extruded plastic: perimeter shell plus sparse infill.
+    pub material_volume_mm3: f64,
+    pub filament_length_m: f64,
+    /// `None` when the profile's material has no configured density.
+    pub material_mass_g: Option<f64>,
+    pub print_time_s: f64,
+    pub print_time_min_s: f64,
+    pub print_time_max_s: f64,
+    pub layer_count: u32,
+    pub uncertainty_pct: u32,
+    pub summary: String,
+}
+
+fn format_duration(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    if minutes < 60 {
+        format!("{}m", minutes.max(1))
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+/// Rough slicer-free estimate of material and time for one print job.
+pub fn estimate_print_job(
+    stats: &MeshStats,
+    profile: &PrinterProfile,
+    config: &AppConfig,
+) -> PrintEstimate {
+    let solid = stats.volume_mm3.max(0.0);
+    let shell =
+        (stats.surface_area_mm2 * profile.perimeters as f64 * profile.nozzle_width_mm).min(solid);
+    let infill = (solid - shell) * (profile.infill_pct / 100.0).clamp(0.0, 1.0);
+    let material_volume_mm3 = shell + infill;
+
+    let volumetric_speed =
+        profile.nozzle_width_mm * profile.layer_height_mm * profile.print_speed_mm_s;
+    let print_time_s = material_volume_mm3 / volumetric_speed * (1.0 + TRAVEL_OVERHEAD_FACTOR);
+    let band = ESTIMATE_UNCERTAINTY_PCT as f64 / 100.0;
+
+    let filament_area = std::f64::consts::PI * (profile.filament_diameter_mm / 2.0).powi(2);
+    let filament_length_m = material_volume_mm3 / filament_area / 1000.0;
+    let material = profile.material.trim().to_lowercase();
+    let material_mass_g = config
+        .materials
+        .get(&material)
+        .map(|density| material_volume_mm3 / 1000.0 * density);
+
+    let usage = match material_mass_g {
+        Some(grams) => format!("{:.0} g {}", grams, material.to_uppercase()),
+        None => format!("{:.1} cm³ {}", material_volume_mm3 / 1000.0, material),
+    };
+    let summary = format!(
+        "Print estimate (±{}%, not a slicer result): ~{}, ~{} ({:.1} m filament) on {}.",
+        ESTIMATE_UNCERTAINTY_PCT,
+        format_duration(print_time_s),
+        usage,
+        filament_length_m,
+        profile.name
+    );
+
+    PrintEstimate {
+        profile_id: profile.id.clone(),
+        profile_name: profile.name.clone(),
+        material,
+        material_volume_mm3,
+        filament_length_m,
+        material_mass_g,
+        print_time_s,
+        print_time_min_s: print_time_s * (1.0 - band),
+        print_time_max_s: print_time_s * (1.0 + band),
+        layer_count: (stats.height_mm / profile.layer_height_mm).ceil().max(0.0) as u32,
+        uncertainty_pct: ESTIMATE_UNCERTAINTY_PCT,
+        summary,
+    }
+}
+
+/// Profile to estimate with: `id` if given, then the active profile, then
+/// the first configured one, then the built-in default.
+pub fn resolve_profile(config: &AppConfig, id: Option<&str>) -> Result<PrinterProfile, AppError> {
+    if let Some(id) = id.map(str::trim).filter(|id| !id.is_empty()) {
+        return config
+            .printer_profiles
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+            .ok_or_else(|| AppError::ConfigError(format!("Printer profile '{}' not found", id)));
+    }
+    let active = config
+        .active_printer_profile
+        .as_deref()
+        .and_then(|id| config.printer_profiles.iter().find(|p| p.id == id));
+    Ok(active
+        .or_else(|| config.printer_profiles.first())
+        .cloned()
+        .unwrap_or_else(|| default_printer_profiles().remove(0)))
+}
+
+fn validate(profile: &PrinterProfile) -> Result<(), AppError> {
+    let positive = [
+        ("nozzle width", profile.nozzle_width_mm),
+        ("layer height", profile.layer_height_mm),
+        ("print speed", profile.print_speed_mm_s),
+        ("filament diameter", profile.filament_diameter_mm),
+    ];
+    for (label, value) in positive {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(AppError::ConfigError(format!(
+                "Printer profile {} must be positive",
+                label
+            )));
+        }
+    }
+    if !(0.0..=100.0).contains(&profile.infill_pct) {
+        return Err(AppError::ConfigError(
+            "Printer profile infill must be between 0 and 100%".into(),
+        ));
+    }
+    if profile.perimeters == 0 {
+        return Err(AppError::ConfigError(
+            "Printer profile needs at least one perimeter".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Validate and insert or replace a printer profile.
+pub fn save(config: &mut AppConfig, mut profile: PrinterProfile) -> Result<(), AppError> {
+    profile.id = profile.id.trim().to_string();
+    if profile.id.is_empty() {
+        return Err(AppError::ConfigError(
+            "Printer profile id cannot be empty".into(),
+        ));
+    }
+    validate(&profile)?;
+    match config
+        .printer_profiles
+        .iter_mut()
+        .find(|p| p.id == profile.id)
+    {
+        Some(existing) => *existing = profile,
+        None => config.printer_profiles.push(profile),
+    }
+    Ok(())
+}
+
+pub fn delete(config: &mut AppConfig, id: &str) -> Result<(), AppError> {
+    let before = config.printer_profiles.len();
+    config.printer_profiles.retain(|p| p.id != id);
+    if config.printer_profiles.len() == before {
+        return Err(AppError::ConfigError(format!(
+            "Printer profile '{}' not found",
+            id
+        )));
+    }
+    if config.active_printer_profile.as_deref() == Some(id) {
+        config.active_printer_profile = None;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> PrinterProfile {
+        default_printer_profiles().remove(0)
+    }
+
+    fn cube(side: f64) -> MeshStats {
+        MeshStats {
+            volume_mm3: side.powi(3),
+            surface_area_mm2: 6.0 * side * side,
+            height_mm: side,
+        }
+    }
+
+    #[test]
+    fn test_estimate_for_20mm_cube() {
+        let config = AppConfig::default();
+        let estimate = estimate_print_job(&cube(20.0), &profile(), &config);
+
+        // Shell 2400 × 2 × 0.4 = 1920 mm³; infill (8000 − 1920) × 20% = 1216 mm³.
+        assert!((estimate.material_volume_mm3 - 3136.0).abs() < 1e-6);
+        // 0.4 × 0.2 × 60 = 4.8 mm³/s, plus 25% travel.
+        assert!((estimate.print_time_s - 3136.0 / 4.8 * 1.25).abs() < 1e-6);
+        assert!((estimate.print_time_min_s - estimate.print_time_s * 0.7).abs() < 1e-6);
+        assert!((estimate.print_time_max_s - estimate.print_time_s * 1.3).abs() < 1e-6);
+        assert_eq!(estimate.layer_count, 100);
+        let grams = estimate.material_mass_g.unwrap();
+        assert!((grams - 3.136 * 1.24).abs() < 1e-6);
+        assert!((estimate.filament_length_m - 1.304).abs() < 0.01);
+        assert!(estimate.summary.contains("±30%"));
+    }
+
+    #[test]
+    fn test_thin_part_is_all_shell() {
+        let config = AppConfig::default();
+        let plate = MeshStats {
+            volume_mm3: 100.0 * 100.0 * 0.8,
+            surface_area_mm2: 2.0 * 100.0 * 100.0 + 4.0 * 100.0 * 0.8,
+            height_mm: 0.8,
+        };
+        let estimate = estimate_print_job(&plate, &profile(), &config);
+        assert!((estimate.material_volume_mm3 - plate.volume_mm3).abs() < 1e-6);
+
+        let mut unknown = profile();
+        unknown.material = "unobtainium".to_string();
+        assert!(estimate_print_job(&plate, &unknown, &config)
+            .material_mass_g
+            .is_none());
+    }
+
+    #[test]
+    fn test_mesh_stats_need_closed_meshes() {
+        let report = |volume: f64, height: f64| PostGeometryValidationReport {
+            watertight: volume > 0.0,
+            manifold: true,
+            degenerate_faces: 0,
+            euler_number: 2,
+            triangle_count: 12,
+            component_count: 1,
+            bounds_min: [0.0, 0.0, 0.0],
+            bounds_max: [10.0, 10.0, height],
+            volume,
+            surface_area: 600.0,
+            center_of_mass: None,
+            unit_inertia: None,
+            bbox_ok: true,
+            warnings: vec![],
+        };
+        let stats = MeshStats::from_reports(&[report(1000.0, 10.0), report(500.0, 30.0)]).unwrap();
+        assert_eq!(stats.volume_mm3, 1500.0);
+        assert_eq!(stats.surface_area_mm2, 1200.0);
+        assert_eq!(stats.height_mm, 30.0);
+        assert!(MeshStats::from_reports(&[report(1000.0, 10.0), report(0.0, 5.0)]).is_none());
+    }
+
+    #[test]
+    fn test_profile_crud_and_resolution() {
+        let mut config = AppConfig::default();
+        assert_eq!(
+            resolve_profile(&config, None).unwrap().id,
+            DEFAULT_PRINTER_PROFILE_ID
+        );
+
+        let mut fine = profile();
+        fine.id = " fine ".to_string();
+        fine.layer_height_mm = 0.12;
+        save(&mut config, fine.clone()).unwrap();
+        config.active_printer_profile = Some("fine".to_string());
+        assert_eq!(
+            resolve_profile(&config, None).unwrap().layer_height_mm,
+            0.12
+        );
+        assert_eq!(
+            resolve_profile(&config, Some(DEFAULT_PRINTER_PROFILE_ID))
+                .unwrap()
+                .id,
+            DEFAULT_PRINTER_PROFILE_ID
+        );
+        assert!(resolve_profile(&config, Some("missing")).is_err());
+
+        fine.id = "fine".to_string();
+        fine.infill_pct = 150.0;
+        assert!(save(&mut config, fine).is_err());
+
+        delete(&mut config, "fine").unwrap();
+        assert!(config.active_printer_profile.is_none());
+        assert!(delete(&mut config, "fine").is_err());
+        delete(&mut config, DEFAULT_PRINTER_PROFILE_ID).unwrap();
+        assert_eq!(
+            resolve_profile(&config, None).unwrap().id,
+            DEFAULT_PRINTER_PROFILE_ID
+        );
+    }
+}
@@ -73,6 +73,16 @@ fn render_yaml_value(prompt: &mut String, value: &serde_yaml::Value, depth: usiz
     }
 }
 
+/// Name the installed Build123d so the model avoids APIs it does not have.
+fn push_installed_version(prompt: &mut String, cq_version: Option<&str>) {
+    if let Some(version) = cq_version {
+        prompt.push_str(&format!(
+            "- The installed library is Build123d {}; only use APIs available in that version\n",
+            version
+        ));
+    }
+}
+
 /// Build a system prompt for the CAD AI agent from the loaded agent rules.
 pub fn build_system_prompt(rules: &AgentRules, cq_version: Option<&str>) -> String {
     let mut prompt = String::new();
@@ -87,7 +97,9 @@ pub fn build_system_prompt(rules: &AgentRules, cq_version: Option<&str>) -> Stri
     prompt.push_str("- All dimensions are in millimeters\n");
     prompt.push_str("- Use Build123d's builder-mode API with context managers\n");
     prompt.push_str("- Do NOT use show_object(), display(), or any GUI calls\n");
-    prompt.push_str("- Do NOT read/write files or use any external resources\n\n");
+    prompt.push_str("- Do NOT read/write files or use any external resources\n");
+    push_installed_version(&mut prompt, cq_version);
+    prompt.push('\n');
 
     if let Some(ref reqs) = rules.code_requirements {
         if let Some(ref mandatory) = reqs.mandatory {
@@ -502,7 +514,7 @@ pub fn build_system_prompt_for_preset(
 /// Single-part generation keeps the full prompt via `build_system_prompt`.
 pub fn build_compact_system_prompt_for_preset(
     preset_name: Option<&str>,
-    _cq_version: Option<&str>,
+    cq_version: Option<&str>,
 ) -> String {
     let rules = AgentRules::from_preset(preset_name).unwrap_or_else(|_| {
         AgentRules::from_preset(None).unwrap_or_else(|_| AgentRules::default_empty())
@@ -521,7 +533,9 @@ pub fn build_compact_system_prompt_for_preset(
     prompt.push_str("- All dimensions are in millimeters\n");
     prompt.push_str("- Use Build123d's builder-mode API with context managers\n");
     prompt.push_str("- Do NOT use show_object(), display(), or any GUI calls\n");
-    prompt.push_str("- Do NOT read/write files or use any external resources\n\n");
+    prompt.push_str("- Do NOT read/write files or use any external resources\n");
+    push_installed_version(&mut prompt, cq_version);
+    prompt.push('\n');
 
     // -- YAML mandatory/forbidden rules --
     if let Some(ref reqs) = rules.code_requirements {
@@ -743,6 +757,45 @@ pub fn build_default_system_prompt() -> String {
     build_system_prompt_for_preset(None, None)
 }
 
+// ---------------------------------------------------------------------------
+// House style
+// ---------------------------------------------------------------------------
+
+/// Character budget for `custom_system_prompt_suffix`.
+pub const MAX_HOUSE_STYLE_CHARS: usize = 2000;
+pub const HOUSE_STYLE_HEADING: &str = "## House Style (standing instructions)";
+const HOUSE_STYLE_END: &str = "## End of House Style";
+
+/// Delimited section for the configured house-style instructions, or `None`
+/// when unset. The flag reports whether the text was cut at
+/// `MAX_HOUSE_STYLE_CHARS`.
+pub fn house_style_section(suffix: Option<&str>) -> Option<(String, bool)> {
+    let text = suffix.map(str::trim).filter(|s| !s.is_empty())?;
+    let (body, truncated) = match text.char_indices().nth(MAX_HOUSE_STYLE_CHARS) {
+        Some((idx, _)) => (format!("{}\n...", &text[..idx]), true),
+        None => (text.to_string(), false),
+    };
+    Some((
+        format!(
+            "{}\nThe user's team applies these to every design. They are not retrieved reference material.\n{}\n{}",
+            HOUSE_STYLE_HEADING, body, HOUSE_STYLE_END
+        ),
+        truncated,
+    ))
+}
+
+/// Append the house-style section to `prompt`; returns whether it was truncated.
+pub fn append_house_style(prompt: &mut String, suffix: Option<&str>) -> bool {
+    match house_style_section(suffix) {
+        Some((section, truncated)) => {
+            prompt.push_str("\n\n");
+            prompt.push_str(&section);
+            truncated
+        }
+        None => false,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Fine-tuned provider helpers
 // ---------------------------------------------------------------------------
@@ -810,6 +863,29 @@ pub fn build_finetuned_system_prompt() -> String {
 mod tests {
     use super::*;
 
+    // ── house_style_section ────────────────────────────────────────────
+
+    #[test]
+    fn test_house_style_section_is_delimited() {
+        assert!(house_style_section(None).is_none());
+        assert!(house_style_section(Some("   ")).is_none());
+        let (section, truncated) =
+            house_style_section(Some("Use metric coarse threads.")).unwrap();
+        assert!(!truncated);
+        assert!(section.starts_with(HOUSE_STYLE_HEADING));
+        assert!(section.contains("Use metric coarse threads."));
+        assert!(section.ends_with(HOUSE_STYLE_END));
+    }
+
+    #[test]
+    fn test_house_style_section_truncates_past_cap() {
+        let long = "a".repeat(MAX_HOUSE_STYLE_CHARS + 500);
+        let (section, truncated) = house_style_section(Some(&long)).unwrap();
+        assert!(truncated);
+        assert!(section.contains(&"a".repeat(MAX_HOUSE_STYLE_CHARS)));
+        assert!(!section.contains(&"a".repeat(MAX_HOUSE_STYLE_CHARS + 1)));
+    }
+
     // ── format_category_name ───────────────────────────────────────────
 
     #[test]
@@ -0,0 +1,316 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::agent::telemetry;
+use crate::ai::message::ChatMessage;
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueEntryStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Extra inputs forwarded to the generation pipeline for a queued entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueEntryOptions {
+    #[serde(default)]
+    pub history: Vec<ChatMessage>,
+    #[serde(default)]
+    pub existing_code: Option<String>,
+    /// Pipeline preset applied to this entry's run.
+    #[serde(default)]
+    pub preset: Option<String>,
+}
+
+/// Result of a finished queue entry, kept for the session's results list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEntryOutcome {
+    pub success: bool,
+    pub error: Option<String>,
+    pub final_code: Option<String>,
+    pub finished_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub id: String,
+    pub message: String,
+    #[serde(default)]
+    pub plan_text: Option<String>,
+    #[serde(default)]
+    pub options: QueueEntryOptions,
+    pub status: QueueEntryStatus,
+    pub enqueued_at_ms: u64,
+    #[serde(default)]
+    pub outcome: Option<QueueEntryOutcome>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStatus {
+    pub running: bool,
+    pub entries: Vec<QueueEntry>,
+}
+
+/// Sequential generation queue. Only pending entries are persisted, so work
+/// that was mid-run when the app closed is never resumed automatically.
+#[derive(Debug, Default)]
+pub struct GenerationQueue {
+    entries: Vec<QueueEntry>,
+    running: bool,
+}
+
+fn queue_path() -> Result<PathBuf, AppError> {
+    let base = dirs::config_dir()
+        .ok_or_else(|| AppError::ConfigError("Cannot resolve config directory".to_string()))?;
+    Ok(base.join("cadai-studio").join("generation_queue.json"))
+}
+
+impl GenerationQueue {
+    /// Load pending entries persisted by a previous session.
+    pub fn load() -> Self {
+        let entries = queue_path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|raw| serde_json::from_str::<Vec<QueueEntry>>(&raw).ok())
+            .unwrap_or_default();
+        Self::from_persisted(entries)
+    }
+
+    fn from_persisted(entries: Vec<QueueEntry>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .filter(|e| e.status == QueueEntryStatus::Pending)
+                .collect(),
+            running: false,
+        }
+    }
+
+    /// Persist pending entries to disk.
+    pub fn save(&self) -> Result<(), AppError> {
+        let path = queue_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let bytes = serde_json::to_vec_pretty(&self.pending_entries())?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    fn pending_entries(&self) -> Vec<&QueueEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.status == QueueEntryStatus::Pending)
+            .collect()
+    }
+
+    pub fn enqueue(
+        &mut self,
+        message: String,
+        plan_text: Option<String>,
+        options: QueueEntryOptions,
+    ) -> String {
+        let id = Uuid::new_v4().to_string();
+        self.entries.push(QueueEntry {
+            id: id.clone(),
+            message,
+            plan_text: plan_text.filter(|p| !p.trim().is_empty()),
+            options,
+            status: QueueEntryStatus::Pending,
+            enqueued_at_ms: telemetry::now_ms(),
+            outcome: None,
+        });
+        id
+    }
+
+    /// Cancel a pending or running entry. Returns `false` if the entry is
+    /// unknown or already finished.
+    pub fn cancel(&mut self, entry_id: &str) -> bool {
+        match self.entries.iter_mut().find(|e| e.id == entry_id) {
+            Some(entry)
+                if matches!(
+                    entry.status,
+                    QueueEntryStatus::Pending | QueueEntryStatus::Running
+                ) =>
+            {
+                entry.status = QueueEntryStatus::Cancelled;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_cancelled(&self, entry_id: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.id == entry_id && e.status == QueueEntryStatus::Cancelled)
+    }
+
+    /// Reorder pending entries. Listed ids move to the front of the pending
+    /// run order in the given sequence; unlisted pending entries keep their
+    /// relative order after them.
+    pub fn reorder(&mut self, entry_ids: &[String]) -> Result<(), String> {
+        for id in entry_ids {
+            if !self
+                .entries
+                .iter()
+                .any(|e| &e.id == id && e.status == QueueEntryStatus::Pending)
+            {
+                return Err(format!("Queue entry '{}' is not pending", id));
+            }
+        }
+
+        let (mut pending, others): (Vec<QueueEntry>, Vec<QueueEntry>) = self
+            .entries
+            .drain(..)
+            .partition(|e| e.status == QueueEntryStatus::Pending);
+
+        let mut reordered = Vec::with_capacity(pending.len());
+        for id in entry_ids {
+            if let Some(pos) = pending.iter().position(|e| &e.id == id) {
+                reordered.push(pending.remove(pos));
+            }
+        }
+        reordered.extend(pending);
+
+        self.entries = others;
+        self.entries.extend(reordered);
+        Ok(())
+    }
+
+    /// Claim the runner slot. Returns `false` if another runner is active.
+    pub fn try_start(&mut self) -> bool {
+        if self.running {
+            return false;
+        }
+        self.running = true;
+        true
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    /// Mark the next pending entry as running and return a copy of it.
+    pub fn next_pending(&mut self) -> Option<QueueEntry> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.status == QueueEntryStatus::Pending)?;
+        entry.status = QueueEntryStatus::Running;
+        Some(entry.clone())
+    }
+
+    /// Record the outcome of a running entry. Cancelled entries keep their
+    /// status but still receive the outcome.
+    pub fn finish(&mut self, entry_id: &str, outcome: QueueEntryOutcome) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == entry_id) {
+            if entry.status == QueueEntryStatus::Running {
+                entry.status = if outcome.success {
+                    QueueEntryStatus::Completed
+                } else {
+                    QueueEntryStatus::Failed
+                };
+            }
+            entry.outcome = Some(outcome);
+        }
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        QueueStatus {
+            running: self.running,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(success: bool) -> QueueEntryOutcome {
+        QueueEntryOutcome {
+            success,
+            error: None,
+            final_code: None,
+            finished_at_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_entries_run_in_fifo_order() {
+        let mut queue = GenerationQueue::default();
+        let a = queue.enqueue("a box".into(), None, QueueEntryOptions::default());
+        let b = queue.enqueue("a cylinder".into(), None, QueueEntryOptions::default());
+
+        let first = queue.next_pending().unwrap();
+        assert_eq!(first.id, a);
+        queue.finish(&a, outcome(true));
+        let second = queue.next_pending().unwrap();
+        assert_eq!(second.id, b);
+        queue.finish(&b, outcome(false));
+        assert!(queue.next_pending().is_none());
+
+        let status = queue.status();
+        assert_eq!(status.entries[0].status, QueueEntryStatus::Completed);
+        assert_eq!(status.entries[1].status, QueueEntryStatus::Failed);
+    }
+
+    #[test]
+    fn test_reorder_moves_listed_entries_first() {
+        let mut queue = GenerationQueue::default();
+        let a = queue.enqueue("a".into(), None, QueueEntryOptions::default());
+        let b = queue.enqueue("b".into(), None, QueueEntryOptions::default());
+        let c = queue.enqueue("c".into(), None, QueueEntryOptions::default());
+
+        queue.reorder(&[c.clone()]).unwrap();
+        let order: Vec<String> = queue.status().entries.into_iter().map(|e| e.id).collect();
+        assert_eq!(order, vec![c, a, b]);
+
+        assert!(queue.reorder(&["missing".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_cancel_skips_pending_entry() {
+        let mut queue = GenerationQueue::default();
+        let a = queue.enqueue("a".into(), None, QueueEntryOptions::default());
+        let b = queue.enqueue("b".into(), None, QueueEntryOptions::default());
+        assert!(queue.cancel(&a));
+        assert!(!queue.cancel(&a));
+        assert_eq!(queue.next_pending().unwrap().id, b);
+    }
+
+    #[test]
+    fn test_cancelled_running_entry_keeps_status() {
+        let mut queue = GenerationQueue::default();
+        let a = queue.enqueue("a".into(), None, QueueEntryOptions::default());
+        queue.next_pending();
+        assert!(queue.cancel(&a));
+        assert!(queue.is_cancelled(&a));
+        queue.finish(&a, outcome(true));
+        assert_eq!(queue.status().entries[0].status, QueueEntryStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_persisted_queue_keeps_only_pending() {
+        let mut queue = GenerationQueue::default();
+        let a = queue.enqueue("a".into(), None, QueueEntryOptions::default());
+        let b = queue.enqueue("b".into(), Some("plan".into()), QueueEntryOptions::default());
+        queue.next_pending();
+
+        let json = serde_json::to_string(&queue.pending_entries()).unwrap();
+        let restored =
+            GenerationQueue::from_persisted(serde_json::from_str::<Vec<QueueEntry>>(&json).unwrap());
+        let ids: Vec<String> = restored.status().entries.into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![b]);
+        assert!(!ids.contains(&a));
+        assert!(!restored.status().running);
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::agent::telemetry;
+use crate::error::AppError;
+
+/// Stored examples beyond this are evicted least-recently-used first.
+pub const MAX_REPAIR_EXAMPLES: usize = 200;
+/// Budget for the few-shot section injected into a repair prompt.
+pub const MAX_EXAMPLE_PROMPT_CHARS: usize = 800;
+const MAX_PHRASE_CHARS: usize = 160;
+const MAX_DIFF_LINES: usize = 24;
+/// Word overlap needed to reuse an example whose phrase is not identical.
+const MIN_PHRASE_SIMILARITY: f32 = 0.5;
+
+/// A validation repair that turned a failing script into a passing one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RepairExample {
+    pub id: String,
+    /// `ErrorCategory` of the failure, as formatted by the executor.
+    pub error_category: String,
+    /// Normalized exception line, e.g. `ocp.stdfail_notdone: no pending wires`.
+    pub error_phrase: String,
+    /// Minimal unified-style hunks from the failing to the fixed code.
+    pub diff: String,
+    pub cq_version: Option<String>,
+    pub created_at_ms: u64,
+    pub last_used_ms: u64,
+    /// Repairs where this example was in the prompt, and how many passed.
+    #[serde(default)]
+    pub assisted_attempts: u32,
+    #[serde(default)]
+    pub assisted_successes: u32,
+}
+
+/// Whether a repair prompt carried a stored example, and how it went.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RepairExampleUse {
+    pub example_id: String,
+    pub succeeded: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RepairExampleStore {
+    examples: Vec<RepairExample>,
+}
+
+fn store_path() -> Result<PathBuf, AppError> {
+    let base = dirs::config_dir()
+        .ok_or_else(|| AppError::ConfigError("Cannot resolve config directory".to_string()))?;
+    Ok(base.join("cadai-studio").join("repair_examples.json"))
+}
+
+static REPAIR_EXAMPLES: OnceLock<Mutex<RepairExampleStore>> = OnceLock::new();
+
+/// Process-wide store, loaded from disk on first use.
+pub fn global_store() -> &'static Mutex<RepairExampleStore> {
+    REPAIR_EXAMPLES.get_or_init(|| Mutex::new(RepairExampleStore::load()))
+}
+
+impl RepairExampleStore {
+    pub fn load() -> Self {
+        store_path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|raw| serde_json::from_str::<RepairExampleStore>(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        let path = store_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Most recently used first.
+    pub fn list(&self) -> Vec<RepairExample> {
+        let mut examples = self.examples.clone();
+        examples.sort_by_key(|e| std::cmp::Reverse(e.last_used_ms));
+        examples
+    }
+
+    pub fn delete(&mut self, id: &str) -> bool {
+        let before = self.examples.len();
+        self.examples.retain(|e| e.id != id);
+        self.examples.len() != before
+    }
+
+    /// Store the fix from `before` to `after` for this failure. Returns the
+    /// example id, or `None` when the code did not change. Re-recording the
+    /// same fix only refreshes it.
+    pub fn record(
+        &mut self,
+        error_category: &str,
+        error_message: &str,
+        before: &str,
+        after: &str,
+        cq_version: Option<String>,
+        now_ms: u64,
+    ) -> Option<String> {
+        let diff = minimal_diff(before, after);
+        if diff.is_empty() {
+            return None;
+        }
+        let error_phrase = salient_phrase(error_message);
+        if let Some(existing) = self.examples.iter_mut().find(|e| {
+            e.error_category == error_category && e.error_phrase == error_phrase && e.diff == diff
+        }) {
+            existing.last_used_ms = now_ms;
+            return Some(existing.id.clone());
+        }
+        let id = Uuid::new_v4().to_string();
+        self.examples.push(RepairExample {
+            id: id.clone(),
+            error_category: error_category.to_string(),
+            error_phrase,
+            diff,
+            cq_version,
+            created_at_ms: now_ms,
+            last_used_ms: now_ms,
+            assisted_attempts: 0,
+            assisted_successes: 0,
+        });
+        self.evict(MAX_REPAIR_EXAMPLES);
+        Some(id)
+    }
+
+    /// The stored example closest to this failure: same category, and an
+    /// identical or mostly overlapping error phrase. Marks it as used.
+    pub fn best_match(
+        &mut self,
+        error_category: &str,
+        error_message: &str,
+        now_ms: u64,
+    ) -> Option<RepairExample> {
+        let phrase = salient_phrase(error_message);
+        let best = self
+            .examples
+            .iter_mut()
+            .filter(|e| e.error_category == error_category)
+            .map(|e| {
+                let score = phrase_similarity(&e.error_phrase, &phrase);
+                (score, e)
+            })
+            .filter(|(score, _)| *score >= MIN_PHRASE_SIMILARITY)
+            .max_by(|(a, ea), (b, eb)| {
+                a.total_cmp(b)
+                    .then(success_rate(ea).total_cmp(&success_rate(eb)))
+                    .then(ea.last_used_ms.cmp(&eb.last_used_ms))
+            })
+            .map(|(_, e)| e)?;
+        best.last_used_ms = now_ms;
+        Some(best.clone())
+    }
+
+    /// Count a repair attempt that had `id` in its prompt.
+    pub fn record_outcome(&mut self, id: &str, succeeded: bool) {
+        if let Some(example) = self.examples.iter_mut().find(|e| e.id == id) {
+            example.assisted_attempts = example.assisted_attempts.saturating_add(1);
+            if succeeded {
+                example.assisted_successes = example.assisted_successes.saturating_add(1);
+            }
+        }
+    }
+
+    fn evict(&mut self, max_examples: usize) {
+        while self.examples.len() > max_examples {
+            let Some(oldest) = self
+                .examples
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, e)| e.last_used_ms)
+                .map(|(i, _)| i)
+            else {
+                break;
+            };
+            self.examples.remove(oldest);
+        }
+    }
+}
+
+/// Untried examples rank as neutral rather than as failures.
+fn success_rate(example: &RepairExample) -> f32 {
+    if example.assisted_attempts == 0 {
+        0.5
+    } else {
+        example.assisted_successes as f32 / example.assisted_attempts as f32
+    }
+}
+
+/// The exception line of a traceback (its last line that is not a frame or
+/// source echo), normalized so temp paths and line numbers do not matter.
+pub fn salient_phrase(error_message: &str) -> String {
+    let line = error_message
+        .lines()
+        .map(str::trim)
+        .rev()
+        .find(|l| {
+            !l.is_empty()
+                && !l.starts_with("File ")
+                && !l.starts_with("Traceback")
+                && !l.starts_with('^')
+        })
+        .unwrap_or("");
+    telemetry::normalize_failure_signature(line)
+        .to_lowercase()
+        .chars()
+        .take(MAX_PHRASE_CHARS)
+        .collect()
+}
+
+fn phrase_words(phrase: &str) -> HashSet<&str> {
+    phrase
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| w.len() >= 3)
+        .collect()
+}
+
+/// 1.0 for identical phrases, otherwise word-level Jaccard overlap.
+fn phrase_similarity(a: &str, b: &str) -> f32 {
+    if a == b {
+        return 1.0;
+    }
+    let (wa, wb) = (phrase_words(a), phrase_words(b));
+    let union = wa.union(&wb).count();
+    if union == 0 {
+        return 0.0;
+    }
+    wa.intersection(&wb).count() as f32 / union as f32
+}
+
+/// Changed lines between two scripts as `-`/`+` hunks with one line of
+/// context, capped at [`MAX_DIFF_LINES`]. Empty when nothing changed.
+pub fn minimal_diff(before: &str, after: &str) -> String {
+    let a: Vec<&str> = before.lines().collect();
+    let b: Vec<&str> = after.lines().collect();
+
+    // LCS table over the lines; scripts are short enough for O(n*m).
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // (tag, line) where tag is ' ', '-' or '+'.
+    let mut ops: Vec<(char, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((' ', a[i]));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            ops.push(('+', b[j]));
+            j += 1;
+        } else {
+            ops.push(('-', a[i]));
+            i += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+    let keep: HashSet<usize> = changed
+        .iter()
+        .flat_map(|&k| k.saturating_sub(1)..=(k + 1).min(ops.len() - 1))
+        .collect();
+
+    let mut lines = Vec::new();
+    let mut previous: Option<usize> = None;
+    for (k, (tag, line)) in ops.iter().enumerate() {
+        if !keep.contains(&k) {
+            continue;
+        }
+        if previous.is_some_and(|p| k > p + 1) {
+            lines.push("...".to_string());
+        }
+        lines.push(format!("{} {}", tag, line));
+        previous = Some(k);
+    }
+    if lines.len() > MAX_DIFF_LINES {
+        lines.truncate(MAX_DIFF_LINES);
+        lines.push("...".to_string());
+    }
+    lines.join("\n")
+}
+
+/// Few-shot block for a repair prompt, within [`MAX_EXAMPLE_PROMPT_CHARS`].
+/// Diff lines are dropped from the end until it fits.
+pub fn few_shot_section(example: &RepairExample) -> String {
+    let header = format!(
+        "## A Similar Repair That Worked\n\
+         A previous `{}` failure (\"{}\") was fixed with this change:\n",
+        example.error_category, example.error_phrase
+    );
+    let mut diff_lines: Vec<&str> = example.diff.lines().collect();
+    loop {
+        let section = format!("{}```diff\n{}\n```\n", header, diff_lines.join("\n"));
+        if section.chars().count() <= MAX_EXAMPLE_PROMPT_CHARS || diff_lines.is_empty() {
+            return section.chars().take(MAX_EXAMPLE_PROMPT_CHARS).collect();
+        }
+        diff_lines.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_WIRES: &str = "Traceback (most recent call last):\n  File \"/tmp/cadai-studio/run_1/code.py\", line 9, in <module>\n    path = sweep(profile, path=rail)\nOCP.StdFail_NotDone: no pending wires";
+
+    const BEFORE: &str = "from build123d import *\nrail = Line((0, 0), (0, 20))\nprofile = Circle(2)\nresult = sweep(profile, path=rail)\n";
+    const AFTER: &str = "from build123d import *\nrail = Line((0, 0), (0, 20))\nprofile = Plane.XZ * Circle(2)\nresult = sweep(profile.face(), path=rail)\n";
+
+    #[test]
+    fn test_salient_phrase_ignores_frames_and_paths() {
+        assert_eq!(
+            salient_phrase(NO_WIRES),
+            "ocp.stdfail_notdone: no pending wires"
+        );
+        let other_run = NO_WIRES.replace("run_1/code.py\", line 9", "run_7/code.py\", line 30");
+        assert_eq!(salient_phrase(&other_run), salient_phrase(NO_WIRES));
+    }
+
+    #[test]
+    fn test_minimal_diff_keeps_only_changed_hunks() {
+        let diff = minimal_diff(BEFORE, AFTER);
+        assert!(diff.contains("- profile = Circle(2)"), "{}", diff);
+        assert!(
+            diff.contains("+ profile = Plane.XZ * Circle(2)"),
+            "{}",
+            diff
+        );
+        assert!(
+            diff.contains("+ result = sweep(profile.face(), path=rail)"),
+            "{}",
+            diff
+        );
+        assert!(!diff.contains("from build123d"), "{}", diff);
+        assert!(minimal_diff(BEFORE, BEFORE).is_empty());
+    }
+
+    #[test]
+    fn test_matching_by_category_and_phrase() {
+        let mut store = RepairExampleStore::default();
+        let id = store
+            .record("Kernel", NO_WIRES, BEFORE, AFTER, Some("0.8.0".into()), 1)
+            .unwrap();
+        assert_eq!(
+            store.record("Kernel", NO_WIRES, BEFORE, AFTER, None, 2),
+            Some(id.clone())
+        );
+        assert_eq!(store.list().len(), 1);
+
+        let hit = store.best_match("Kernel", NO_WIRES, 5).unwrap();
+        assert_eq!(hit.id, id);
+        assert_eq!(hit.last_used_ms, 5);
+        let similar = "OCP.StdFail_NotDone: no pending wires on sweep";
+        assert!(store.best_match("Kernel", similar, 6).is_some());
+        assert!(store.best_match("Syntax", NO_WIRES, 7).is_none());
+        assert!(store
+            .best_match("Kernel", "ValueError: radius too large", 8)
+            .is_none());
+
+        store.record_outcome(&id, true);
+        store.record_outcome(&id, false);
+        let stored = &store.list()[0];
+        assert_eq!(
+            (stored.assisted_attempts, stored.assisted_successes),
+            (2, 1)
+        );
+        assert!(store.delete(&id));
+        assert!(!store.delete(&id));
+    }
+
+    #[test]
+    fn test_store_is_bounded_with_lru_eviction() {
+        let mut store = RepairExampleStore::default();
+        let first = store
+            .record("Kernel", NO_WIRES, BEFORE, AFTER, None, 0)
+            .unwrap();
+        for n in 1..=MAX_REPAIR_EXAMPLES as u64 {
+            let fixed = format!("{}# fix {}\n", AFTER, n);
+            // Keep the first example warm so an older one is evicted instead.
+            if n == 100 {
+                store.best_match("Kernel", NO_WIRES, n);
+            }
+            store.record("Kernel", &format!("Error {}", n), BEFORE, &fixed, None, n);
+        }
+        let listed = store.list();
+        assert_eq!(listed.len(), MAX_REPAIR_EXAMPLES);
+        assert!(listed.iter().any(|e| e.id == first));
+        assert!(!listed.iter().any(|e| e.error_phrase == "error 1"));
+    }
+
+    #[test]
+    fn test_few_shot_section_is_bounded() {
+        let mut store = RepairExampleStore::default();
+        let long_after: String = (0..200)
+            .map(|i| format!("x_{} = Box({}, 1, 1)\n", i, i))
+            .collect();
+        store.record("Kernel", NO_WIRES, BEFORE, &long_after, None, 0);
+        let example = store.best_match("Kernel", NO_WIRES, 1).unwrap();
+        let section = few_shot_section(&example);
+        assert!(section.chars().count() <= MAX_EXAMPLE_PROMPT_CHARS);
+        assert!(section.contains("no pending wires"));
+        assert!(section.trim_end().ends_with("```"));
+    }
+}
@@ -0,0 +1,318 @@
+use std::sync::{Mutex, OnceLock};
+
+use regex::Regex;
+use serde::Serialize;
+use schemars::JsonSchema;
+
+use crate::agent::executor::ValidationResult;
+use crate::agent::telemetry;
+use crate::config::AppConfig;
+
+/// Validated versions kept for differential re-validation.
+const MAX_CACHED_VALIDATIONS: usize = 16;
+
+/// How much of the validation stack a code change needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevalidationScope {
+    /// No previously validated version is related: run everything.
+    Full,
+    /// Only numeric literals changed: re-execute, reuse static analysis.
+    ReuseStatic,
+    /// Only comments or whitespace changed: reuse the previous result.
+    SkipAll,
+}
+
+impl RevalidationScope {
+    pub fn executes(&self) -> bool {
+        !matches!(self, Self::SkipAll)
+    }
+
+    pub fn skipped_stages(&self) -> &'static [&'static str] {
+        match self {
+            Self::Full => &[],
+            Self::ReuseStatic => &["static checks"],
+            Self::SkipAll => &["static checks", "execution", "post-geometry checks"],
+        }
+    }
+
+    /// Suffix for `ValidationAttempt` messages naming what was skipped.
+    pub fn describe(&self) -> Option<String> {
+        let reason = match self {
+            Self::Full => return None,
+            Self::ReuseStatic => "only dimensions changed",
+            Self::SkipAll => "only comments or whitespace changed",
+        };
+        Some(format!(
+            "{}; skipped {}",
+            reason,
+            self.skipped_stages().join(", ")
+        ))
+    }
+}
+
+/// A successful validation and the static analysis it was based on.
+#[derive(Debug, Clone)]
+pub struct CachedValidation {
+    pub static_passed: bool,
+    pub static_findings: Vec<String>,
+    pub result: ValidationResult,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    validation_key: String,
+    comment_free_hash: String,
+    structure_hash: String,
+    cached: CachedValidation,
+}
+
+#[derive(Debug, Clone)]
+pub struct RevalidationPlan {
+    pub scope: RevalidationScope,
+    pub cached: Option<CachedValidation>,
+}
+
+#[derive(Debug, Default)]
+pub struct RevalidationCache {
+    entries: Vec<CacheEntry>,
+}
+
+static REVALIDATION_CACHE: OnceLock<Mutex<RevalidationCache>> = OnceLock::new();
+
+pub fn global_cache() -> &'static Mutex<RevalidationCache> {
+    REVALIDATION_CACHE.get_or_init(|| Mutex::new(RevalidationCache::default()))
+}
+
+/// Hash of the settings (and request) that decide whether a validation
+/// passes. Entries recorded under a different key are never reused.
+pub fn validation_key(config: &AppConfig, user_request: Option<&str>) -> String {
+    let thresholds = serde_json::json!({
+        "profile": config.generation_reliability_profile,
+        "quality_gates_strict": config.quality_gates_strict,
+        "semantic_contract_strict": config.semantic_contract_strict,
+        "semantic_bbox_mode": config.semantic_bbox_mode,
+        "allow_euler_override": config.allow_euler_override,
+        "user_request": user_request.unwrap_or(""),
+    });
+    telemetry::hash_request(&thresholds.to_string())
+}
+
+/// Code with comments, blank lines and insignificant whitespace removed.
+/// Indentation and string contents are kept.
+fn strip_comments_and_whitespace(code: &str) -> String {
+    let mut out = String::with_capacity(code.len());
+    let mut quote: Option<char> = None;
+    for line in code.lines() {
+        let indent_len = line.len() - line.trim_start().len();
+        let mut stripped = String::new();
+        let mut pending_space = false;
+        let mut escaped = false;
+        for ch in line[indent_len..].chars() {
+            if let Some(q) = quote {
+                stripped.push(ch);
+                if escaped {
+                    escaped = false;
+                } else if ch == '\\' {
+                    escaped = true;
+                } else if ch == q {
+                    quote = None;
+                }
+                continue;
+            }
+            match ch {
+                '#' => break,
+                c if c.is_whitespace() => pending_space = true,
+                c => {
+                    let prev = stripped.chars().next_back();
+                    let joins_words = prev.is_some_and(|p| p.is_alphanumeric() || p == '_')
+                        && (c.is_alphanumeric() || c == '_');
+                    if pending_space && joins_words {
+                        stripped.push(' ');
+                    }
+                    pending_space = false;
+                    if c == '"' || c == '\'' {
+                        quote = Some(c);
+                    }
+                    stripped.push(c);
+                }
+            }
+        }
+        // Unterminated quotes do not carry over lines (no triple-quote tracking).
+        quote = None;
+        if stripped.is_empty() {
+            continue;
+        }
+        out.push_str(&line[..indent_len]);
+        out.push_str(&stripped);
+        out.push('\n');
+    }
+    out
+}
+
+/// Comment-free code with numeric literals replaced by a placeholder.
+fn structure_of(comment_free: &str) -> String {
+    let re = Regex::new(r"\d+(?:\.\d*)?(?:[eE][-+]?\d+)?|\.\d+(?:[eE][-+]?\d+)?").unwrap();
+    let mut out = String::with_capacity(comment_free.len());
+    let mut last = 0;
+    for m in re.find_iter(comment_free) {
+        let prev = comment_free[..m.start()].chars().next_back();
+        if prev.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        out.push_str(&comment_free[last..m.start()]);
+        out.push('#');
+        last = m.end();
+    }
+    out.push_str(&comment_free[last..]);
+    out
+}
+
+fn hashes(code: &str) -> (String, String) {
+    let comment_free = strip_comments_and_whitespace(code);
+    (
+        telemetry::hash_request(&comment_free),
+        telemetry::hash_request(&structure_of(&comment_free)),
+    )
+}
+
+impl RevalidationCache {
+    /// Classify `code` against previously validated versions.
+    pub fn plan(&self, code: &str, validation_key: &str) -> RevalidationPlan {
+        let (comment_free_hash, structure_hash) = hashes(code);
+        let candidates = || {
+            self.entries
+                .iter()
+                .rev()
+                .filter(|e| e.validation_key == validation_key)
+        };
+        if let Some(entry) = candidates().find(|e| e.comment_free_hash == comment_free_hash) {
+            return RevalidationPlan {
+                scope: RevalidationScope::SkipAll,
+                cached: Some(entry.cached.clone()),
+            };
+        }
+        if let Some(entry) = candidates().find(|e| e.structure_hash == structure_hash) {
+            return RevalidationPlan {
+                scope: RevalidationScope::ReuseStatic,
+                cached: Some(entry.cached.clone()),
+            };
+        }
+        RevalidationPlan {
+            scope: RevalidationScope::Full,
+            cached: None,
+        }
+    }
+
+    /// Forget every cached validation, e.g. after the Build123d install changed.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Remember a successful validation of `code`.
+    pub fn record(&mut self, code: &str, validation_key: &str, cached: CachedValidation) {
+        let (comment_free_hash, structure_hash) = hashes(code);
+        self.entries.retain(|e| {
+            !(e.validation_key == validation_key && e.comment_free_hash == comment_free_hash)
+        });
+        self.entries.push(CacheEntry {
+            validation_key: validation_key.to_string(),
+            comment_free_hash,
+            structure_hash,
+            cached,
+        });
+        if self.entries.len() > MAX_CACHED_VALIDATIONS {
+            let excess = self.entries.len() - MAX_CACHED_VALIDATIONS;
+            self.entries.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::provider::TokenUsage;
+
+    const BASE: &str = "from build123d import *\n\n# Base plate\nplate = Box(80, 60, 5)\nresult = fillet(plate.edges(), radius=2)\n";
+
+    fn validated(code: &str) -> CachedValidation {
+        CachedValidation {
+            static_passed: true,
+            static_findings: vec![],
+            result: ValidationResult {
+                code: code.to_string(),
+                stl_base64: Some("c3Rs".to_string()),
+                success: true,
+                attempts: 1,
+                error: None,
+                retry_usage: TokenUsage::default(),
+                static_findings: vec![],
+                post_geometry_report: None,
+                post_check_warning: None,
+                retry_ladder_stage_reached: None,
+                escalated_model: None,
+                repair_example_uses: vec![],
+                attempt_history: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn test_comment_only_edit_reuses_previous_validation() {
+        let key = validation_key(&AppConfig::default(), Some("plate"));
+        let mut cache = RevalidationCache::default();
+        assert_eq!(cache.plan(BASE, &key).scope, RevalidationScope::Full);
+        cache.record(BASE, &key, validated(BASE));
+
+        let commented = BASE
+            .replace("# Base plate", "# Base plate, 5 mm thick")
+            .replace("Box(80, 60, 5)", "Box(80,60,5)   # outer")
+            + "\n\n";
+        let plan = cache.plan(&commented, &key);
+        assert_eq!(plan.scope, RevalidationScope::SkipAll);
+        assert!(!plan.scope.executes());
+        assert!(plan.cached.unwrap().result.success);
+    }
+
+    #[test]
+    fn test_dimension_edit_reuses_static_analysis() {
+        let key = validation_key(&AppConfig::default(), Some("plate"));
+        let mut cache = RevalidationCache::default();
+        cache.record(BASE, &key, validated(BASE));
+
+        let resized = BASE.replace("radius=2", "radius=1.5");
+        let plan = cache.plan(&resized, &key);
+        assert_eq!(plan.scope, RevalidationScope::ReuseStatic);
+        assert!(plan.scope.executes());
+        cache.record(&resized, &key, validated(&resized));
+        assert_eq!(cache.plan(&resized, &key).scope, RevalidationScope::SkipAll);
+    }
+
+    #[test]
+    fn test_structural_edit_and_threshold_change_run_full_stack() {
+        let key = validation_key(&AppConfig::default(), Some("plate"));
+        let mut cache = RevalidationCache::default();
+        cache.record(BASE, &key, validated(BASE));
+
+        let structural = BASE.replace("fillet(plate.edges(), radius=2)", "plate");
+        assert_eq!(cache.plan(&structural, &key).scope, RevalidationScope::Full);
+
+        let renamed = BASE.replace("plate = ", "plate2 = ");
+        assert_eq!(cache.plan(&renamed, &key).scope, RevalidationScope::Full);
+
+        let mut strict = AppConfig::default();
+        strict.quality_gates_strict = !strict.quality_gates_strict;
+        let strict_key = validation_key(&strict, Some("plate"));
+        assert_ne!(strict_key, key);
+        assert_eq!(cache.plan(BASE, &strict_key).scope, RevalidationScope::Full);
+    }
+
+    #[test]
+    fn test_hash_inside_string_is_not_a_comment() {
+        let a = strip_comments_and_whitespace("label = \"#1\"  # note\n");
+        let b = strip_comments_and_whitespace("label = \"#2\"\n");
+        assert_eq!(a, "label=\"#1\"\n");
+        assert_ne!(a, b);
+        assert_eq!(strip_comments_and_whitespace("return  x\n"), "return x\n");
+    }
+}
@@ -1,6 +1,12 @@
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::numparse::{parse_number, NUMBER};
+use crate::agent::static_validate::{self, StaticValidationFinding};
 use crate::ai::message::ChatMessage;
 use crate::ai::provider::{AiProvider, TokenUsage};
-use crate::config::ReviewerMode;
+use crate::config::{AppConfig, ReviewFocus, ReviewerMode};
 use crate::error::AppError;
 
 const REVIEW_SYSTEM_PROMPT: &str = r#"You are a Build123d code reviewer. Your job is to verify that generated Build123d code correctly implements what the user requested.
@@ -62,11 +68,198 @@ IMPORTANT:
   - Do not replace a robust operation path with a riskier one unless required to fix a real defect
 - When in doubt, APPROVE the code"#;
 
+const DIMENSIONAL_ACCURACY_FOCUS: &str = r#"### Focus: dimensional_accuracy
+- Cross-check every numeric literal in the code against the user's request and the design plan.
+- Flag each value that contradicts a stated dimension, count, spacing or position.
+- Never change a value listed under "Protected Dimensions"; if the code disagrees with one, fix the code to use the protected value, never the other way around."#;
+
+const MANUFACTURABILITY_FOCUS: &str = r#"### Focus: manufacturability
+- Apply the manufacturing rules below to the code.
+- Check wall thickness variables and shell thicknesses against the minimum wall.
+- Flag geometry that creates unsupported overhangs, unreachable internal corners or features below the minimum size."#;
+
+const CODE_QUALITY_FOCUS: &str = r#"### Focus: code_quality
+- Check naming (descriptive variable names, dimensions as named variables), structure (one logical step per block) and dead code (unused variables, unreachable branches).
+- Code quality issues alone never justify changing geometry."#;
+
+const FINDINGS_FORMAT: &str = r#"Before APPROVED or ISSUES, list what you checked under each focus:
+FINDINGS:
+- [focus_name] one-line finding (fixed)
+- [focus_name] one-line finding (not fixed)
+Use the focus names exactly as written above. Write "(fixed)" only when FIXED CODE addresses the finding."#;
+
+/// Character budget for the manufacturing rules quoted in a manufacturability review.
+const MAX_MANUFACTURING_RULES_CHARS: usize = 1500;
+/// At most this many user-specified values are listed as protected.
+const MAX_PROTECTED_DIMENSIONS: usize = 24;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ReviewFinding {
+    pub focus: ReviewFocus,
+    pub finding: String,
+    pub was_fixed: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct ReviewResult {
     pub was_modified: bool,
     pub code: String,
     pub explanation: String,
+    /// Per-focus findings; empty for a generic review.
+    pub findings: Vec<ReviewFinding>,
+    /// The AI reviewer was called; false when the static pre-screen passed
+    /// the code without one.
+    pub ai_reviewed: bool,
+}
+
+/// What a review should look at and whether it may rewrite the code.
+#[derive(Debug, Clone, Default)]
+pub struct ReviewOptions {
+    pub mode: ReviewerMode,
+    pub focus: Vec<ReviewFocus>,
+    /// Formatted manufacturing rules, used by the manufacturability focus.
+    pub manufacturing_rules: Option<String>,
+    /// Call the reviewer even when the static pre-screen finds nothing.
+    pub always_review: bool,
+}
+
+impl ReviewOptions {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let manufacturing_rules = if config
+            .reviewer_focus
+            .contains(&ReviewFocus::Manufacturability)
+        {
+            crate::agent::rules::AgentRules::from_preset(config.agent_rules_preset.as_deref())
+                .ok()
+                .and_then(|rules| rules.manufacturing)
+                .map(|m| {
+                    let budget = crate::agent::design::RuleBudget {
+                        max_chars: config.manufacturing_rules_budget_chars,
+                        ..Default::default()
+                    };
+                    crate::agent::design::format_manufacturing_constraints(&m, &budget).0
+                })
+        } else {
+            None
+        };
+        Self {
+            mode: config.reviewer_mode.clone(),
+            focus: config.reviewer_focus.clone(),
+            manufacturing_rules,
+            always_review: config.always_review,
+        }
+    }
+}
+
+fn parse_focus(name: &str) -> Option<ReviewFocus> {
+    match name
+        .trim()
+        .to_ascii_lowercase()
+        .replace([' ', '-'], "_")
+        .as_str()
+    {
+        "dimensional_accuracy" => Some(ReviewFocus::DimensionalAccuracy),
+        "manufacturability" => Some(ReviewFocus::Manufacturability),
+        "code_quality" => Some(ReviewFocus::CodeQuality),
+        _ => None,
+    }
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}\n...", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+/// Generic review prompt followed by one instruction block per selected focus.
+fn build_review_system_prompt(focus: &[ReviewFocus], manufacturing_rules: Option<&str>) -> String {
+    let mut prompt = REVIEW_SYSTEM_PROMPT.to_string();
+    let mut selected: Vec<ReviewFocus> = Vec::new();
+    for f in focus {
+        if !selected.contains(f) {
+            selected.push(*f);
+        }
+    }
+    if selected.is_empty() {
+        return prompt;
+    }
+
+    prompt.push_str("\n\n## Review Focus\nIn addition to the checklist, review the code for:\n\n");
+    for f in &selected {
+        match f {
+            ReviewFocus::DimensionalAccuracy => prompt.push_str(DIMENSIONAL_ACCURACY_FOCUS),
+            ReviewFocus::Manufacturability => {
+                prompt.push_str(MANUFACTURABILITY_FOCUS);
+                match manufacturing_rules {
+                    Some(rules) if !rules.trim().is_empty() => {
+                        prompt.push_str("\n\n");
+                        prompt.push_str(&truncate_chars(rules.trim(), MAX_MANUFACTURING_RULES_CHARS));
+                    }
+                    _ => prompt.push_str(
+                        "\n- No manufacturing profile is active; assume FDM printing (1.2mm minimum wall, 45° maximum overhang).",
+                    ),
+                }
+            }
+            ReviewFocus::CodeQuality => prompt.push_str(CODE_QUALITY_FOCUS),
+        }
+        prompt.push_str("\n\n");
+    }
+    prompt.push_str(FINDINGS_FORMAT);
+    prompt
+}
+
+/// Dimensions the user stated explicitly, converted to millimetres. A review
+/// may never change these.
+fn protected_dimensions(user_request: &str) -> Vec<f64> {
+    let re = Regex::new(&format!(
+        r"(?i)({})\s*(mm|cm|m|inch|inches|\x22)(?:\b|\s|$)",
+        NUMBER
+    ))
+    .unwrap();
+    let mut values: Vec<f64> = Vec::new();
+    for cap in re.captures_iter(user_request) {
+        let Some(value) = parse_number(&cap[1]) else {
+            continue;
+        };
+        let mm = match cap[2].to_ascii_lowercase().as_str() {
+            "cm" => value * 10.0,
+            "m" => value * 1000.0,
+            "inch" | "inches" | "\"" => value * 25.4,
+            _ => value,
+        };
+        if mm > 0.0 && !values.iter().any(|v| (v - mm).abs() < 1e-9) {
+            values.push(mm);
+        }
+        if values.len() >= MAX_PROTECTED_DIMENSIONS {
+            break;
+        }
+    }
+    values
+}
+
+fn format_dimension(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn numeric_literals(code: &str) -> Vec<f64> {
+    let re = Regex::new(r"\b\d+(?:\.\d+)?\b").unwrap();
+    re.find_iter(code)
+        .filter_map(|m| m.as_str().parse::<f64>().ok())
+        .collect()
+}
+
+/// Protected values present in the original code that the reviewed code dropped.
+fn changed_protected_dimensions(protected: &[f64], original: &str, reviewed: &str) -> Vec<f64> {
+    let before = numeric_literals(original);
+    let after = numeric_literals(reviewed);
+    let present = |literals: &[f64], value: f64| literals.iter().any(|l| (l - value).abs() < 1e-6);
+    protected
+        .iter()
+        .copied()
+        .filter(|v| present(&before, *v) && !present(&after, *v))
+        .collect()
 }
 
 /// Build the user message for the review prompt, optionally including the design plan.
@@ -86,47 +279,159 @@ fn build_review_user_message(
     content
 }
 
+/// Static lint findings that warrant an AI review: the general checks plus
+/// the fastener and pattern checks against the request.
+pub fn prescreen_findings(code: &str, user_request: &str) -> Vec<StaticValidationFinding> {
+    let mut findings = static_validate::validate_code(code).findings;
+    findings.extend(static_validate::fastener_findings(code, user_request));
+    findings.extend(static_validate::pattern_findings(code, user_request));
+    findings
+}
+
 /// Review generated Build123d code against the user's original request.
-/// Returns the original or corrected code with an explanation.
+/// Returns the original or corrected code with an explanation. All selected
+/// focuses share a single provider call, which is skipped for code the
+/// static pre-screen passes unless `always_review` is set.
 pub async fn review_code(
     provider: Box<dyn AiProvider>,
     user_request: &str,
     generated_code: &str,
     design_plan: Option<&str>,
-    reviewer_mode: &ReviewerMode,
+    options: &ReviewOptions,
 ) -> Result<(ReviewResult, Option<TokenUsage>), AppError> {
+    if !options.always_review && prescreen_findings(generated_code, user_request).is_empty() {
+        let skipped = ReviewResult {
+            was_modified: false,
+            code: generated_code.to_string(),
+            explanation: "Static checks found no issues; AI review skipped.".to_string(),
+            findings: Vec::new(),
+            ai_reviewed: false,
+        };
+        return Ok((skipped, None));
+    }
+
+    let protected = protected_dimensions(user_request);
+    let mut user_message = build_review_user_message(user_request, generated_code, design_plan);
+    if !protected.is_empty() {
+        let listed: Vec<String> = protected
+            .iter()
+            .map(|v| format!("{}mm", format_dimension(*v)))
+            .collect();
+        user_message.push_str(&format!(
+            "\n\n## Protected Dimensions\nUser-specified values; never change them: {}",
+            listed.join(", ")
+        ));
+    }
     let messages = vec![
         ChatMessage {
             role: "system".to_string(),
-            content: REVIEW_SYSTEM_PROMPT.to_string(),
+            content: build_review_system_prompt(
+                &options.focus,
+                options.manufacturing_rules.as_deref(),
+            ),
         },
         ChatMessage {
             role: "user".to_string(),
-            content: build_review_user_message(user_request, generated_code, design_plan),
+            content: user_message,
         },
     ];
 
     let (response, usage) = provider.complete(&messages, Some(2048)).await?;
 
-    let mut parsed = parse_review_response(&response, generated_code);
-    if matches!(reviewer_mode, ReviewerMode::AdvisoryOnly) && parsed.was_modified {
-        parsed = ReviewResult {
+    let parsed = parse_review_response(&response, generated_code);
+    Ok((
+        finalize_review(parsed, generated_code, &protected, &options.mode),
+        usage,
+    ))
+}
+
+/// Enforce protected dimensions and the reviewer mode on a parsed review.
+fn finalize_review(
+    parsed: ReviewResult,
+    original_code: &str,
+    protected: &[f64],
+    reviewer_mode: &ReviewerMode,
+) -> ReviewResult {
+    if !parsed.was_modified {
+        return parsed;
+    }
+    let changed = changed_protected_dimensions(protected, original_code, &parsed.code);
+    let rejection = if !changed.is_empty() {
+        let listed: Vec<String> = changed
+            .iter()
+            .map(|v| format!("{}mm", format_dimension(*v)))
+            .collect();
+        Some(format!(
+            "Reviewer fix rejected because it changed user-specified dimension(s) {}; code unchanged. Findings: {}",
+            listed.join(", "),
+            parsed.explanation
+        ))
+    } else if matches!(reviewer_mode, ReviewerMode::AdvisoryOnly) {
+        Some(format!(
+            "Reviewer findings (advisory only; code unchanged): {}",
+            parsed.explanation
+        ))
+    } else {
+        None
+    };
+    match rejection {
+        Some(explanation) => ReviewResult {
             was_modified: false,
-            code: generated_code.to_string(),
-            explanation: format!(
-                "Reviewer findings (advisory only; code unchanged): {}",
-                parsed.explanation
+            code: original_code.to_string(),
+            explanation,
+            findings: unfixed(parsed.findings),
+            ai_reviewed: parsed.ai_reviewed,
+        },
+        None => parsed,
+    }
+}
+
+/// Split a `FINDINGS:` block off the response. Returns the findings and the
+/// rest of the response; findings with an unknown focus are dropped.
+fn extract_findings(response: &str) -> (Vec<ReviewFinding>, String) {
+    let Some(start) = response.find("FINDINGS:") else {
+        return (Vec::new(), response.to_string());
+    };
+    let line_re = Regex::new(r"^[-*]\s*\[([^\]]+)\]\s*(.+?)\s*$").unwrap();
+    let status_re = Regex::new(r"(?i)\s*\((not fixed|unfixed|fixed)\)\s*$").unwrap();
+    let after = &response[start + "FINDINGS:".len()..];
+    let mut findings = Vec::new();
+    let mut consumed = 0;
+    for line in after.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with('-') && !trimmed.starts_with('*') {
+            break;
+        }
+        consumed += line.len();
+        let Some(cap) = line_re.captures(trimmed) else {
+            continue;
+        };
+        let Some(focus) = parse_focus(&cap[1]) else {
+            continue;
+        };
+        let text = cap[2].to_string();
+        let (finding, was_fixed) = match status_re.captures(&text) {
+            Some(status) => (
+                text[..status.get(0).unwrap().start()].trim().to_string(),
+                status[1].eq_ignore_ascii_case("fixed"),
             ),
+            None => (text.trim().to_string(), false),
         };
+        findings.push(ReviewFinding {
+            focus,
+            finding,
+            was_fixed,
+        });
     }
-
-    Ok((parsed, usage))
+    let rest = format!("{}{}", &response[..start], &after[consumed..]);
+    (findings, rest)
 }
 
 /// Parse the reviewer's response into a ReviewResult.
 /// Falls back to keeping the original code if parsing fails.
 fn parse_review_response(response: &str, original_code: &str) -> ReviewResult {
-    let trimmed = response.trim();
+    let (findings, rest) = extract_findings(response);
+    let trimmed = rest.trim();
 
     // Check for APPROVED
     if trimmed.starts_with("APPROVED") {
@@ -134,6 +439,8 @@ fn parse_review_response(response: &str, original_code: &str) -> ReviewResult {
             was_modified: false,
             code: original_code.to_string(),
             explanation: "Code approved by reviewer.".to_string(),
+            findings: unfixed(findings),
+            ai_reviewed: true,
         };
     }
 
@@ -158,6 +465,8 @@ fn parse_review_response(response: &str, original_code: &str) -> ReviewResult {
                     was_modified: true,
                     code: fixed_code,
                     explanation,
+                    findings,
+                    ai_reviewed: true,
                 };
             }
         }
@@ -168,12 +477,29 @@ fn parse_review_response(response: &str, original_code: &str) -> ReviewResult {
         was_modified: false,
         code: original_code.to_string(),
         explanation: "Review completed (no changes).".to_string(),
+        findings: unfixed(findings),
+        ai_reviewed: true,
     }
 }
 
+fn unfixed(findings: Vec<ReviewFinding>) -> Vec<ReviewFinding> {
+    findings
+        .into_iter()
+        .map(|f| ReviewFinding {
+            was_fixed: false,
+            ..f
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ai::provider::StreamSignal;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
 
     // ── Review prompt content ──────────────────────────────────────────
 
@@ -360,4 +686,207 @@ result = Box(10, 10, 10)
         let code_pos = msg.find("## Generated Code").unwrap();
         assert!(plan_pos < code_pos, "plan should appear before code");
     }
+
+    // ── Review focus ──────────────────────────────────────────────────
+
+    #[test]
+    fn test_system_prompt_without_focus_is_generic() {
+        assert_eq!(build_review_system_prompt(&[], None), REVIEW_SYSTEM_PROMPT);
+    }
+
+    #[test]
+    fn test_system_prompt_composes_selected_focus_blocks() {
+        let dims = build_review_system_prompt(&[ReviewFocus::DimensionalAccuracy], None);
+        assert!(dims.starts_with(REVIEW_SYSTEM_PROMPT));
+        assert!(dims.contains("### Focus: dimensional_accuracy"));
+        assert!(dims.contains("Protected Dimensions"));
+        assert!(!dims.contains("### Focus: manufacturability"));
+        assert!(!dims.contains("### Focus: code_quality"));
+        assert!(dims.contains("FINDINGS:"));
+
+        let rules = format!(
+            "## Manufacturing Constraints\nmin_wall: 0.8\n{}",
+            "x".repeat(5000)
+        );
+        let all = build_review_system_prompt(
+            &[
+                ReviewFocus::CodeQuality,
+                ReviewFocus::Manufacturability,
+                ReviewFocus::DimensionalAccuracy,
+                ReviewFocus::CodeQuality,
+            ],
+            Some(&rules),
+        );
+        assert_eq!(all.matches("### Focus: code_quality").count(), 1);
+        assert!(all.contains("### Focus: manufacturability"));
+        assert!(all.contains("min_wall: 0.8"));
+        assert!(
+            all.find("### Focus: code_quality").unwrap()
+                < all.find("### Focus: manufacturability").unwrap()
+        );
+        let bound = REVIEW_SYSTEM_PROMPT.len() + MAX_MANUFACTURING_RULES_CHARS + 3000;
+        assert!(all.len() < bound, "prompt grew to {} chars", all.len());
+
+        let no_profile = build_review_system_prompt(&[ReviewFocus::Manufacturability], None);
+        assert!(no_profile.contains("No manufacturing profile is active"));
+    }
+
+    #[test]
+    fn test_parse_structured_findings_fixture() {
+        let response = r#"FINDINGS:
+- [dimensional_accuracy] Hole spacing is 30 but the request says 40mm (fixed)
+- [manufacturability] Wall thickness variable is 0.6, below the 0.8mm minimum (not fixed)
+- [code quality] Unused variable `tmp` (fixed)
+- [aesthetics] Looks fine
+
+ISSUES:
+- Hole spacing mismatch
+- Dead variable
+
+FIXED CODE:
+```python
+from build123d import *
+spacing = 40
+result = Box(80, 40, 0.6)
+```"#;
+        let result = parse_review_response(response, "old code");
+        assert!(result.was_modified);
+        assert!(result.code.contains("spacing = 40"));
+        assert!(result.explanation.contains("Hole spacing mismatch"));
+        assert!(!result.explanation.contains("FINDINGS"));
+        assert_eq!(
+            result.findings,
+            vec![
+                ReviewFinding {
+                    focus: ReviewFocus::DimensionalAccuracy,
+                    finding: "Hole spacing is 30 but the request says 40mm".to_string(),
+                    was_fixed: true,
+                },
+                ReviewFinding {
+                    focus: ReviewFocus::Manufacturability,
+                    finding: "Wall thickness variable is 0.6, below the 0.8mm minimum".to_string(),
+                    was_fixed: false,
+                },
+                ReviewFinding {
+                    focus: ReviewFocus::CodeQuality,
+                    finding: "Unused variable `tmp`".to_string(),
+                    was_fixed: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_findings_then_approved() {
+        let response =
+            "FINDINGS:\n- [code_quality] Dimensions are named variables (fixed)\n\nAPPROVED";
+        let result = parse_review_response(response, "original code");
+        assert!(!result.was_modified);
+        assert_eq!(result.findings.len(), 1);
+        assert!(!result.findings[0].was_fixed);
+    }
+
+    #[test]
+    fn test_protected_dimensions_from_request() {
+        let protected =
+            protected_dimensions("A 50mm cube with a 1,5 cm hole and a 2\" boss, 4 bolts");
+        assert_eq!(protected, vec![50.0, 15.0, 50.8]);
+        assert!(protected_dimensions("two slots, 3 in a row").is_empty());
+    }
+
+    #[test]
+    fn test_fix_changing_protected_dimension_is_rejected() {
+        let original = "from build123d import *\nwidth = 50\nresult = Box(width, 30, 10)";
+        let reviewed = ReviewResult {
+            was_modified: true,
+            code: "from build123d import *\nwidth = 48\nresult = Box(width, 30, 10)".to_string(),
+            explanation: "- width should be 48".to_string(),
+            findings: vec![ReviewFinding {
+                focus: ReviewFocus::DimensionalAccuracy,
+                finding: "width 50 looks wrong".to_string(),
+                was_fixed: true,
+            }],
+            ai_reviewed: true,
+        };
+        let protected = protected_dimensions("a 50mm wide plate");
+        let result = finalize_review(
+            reviewed.clone(),
+            original,
+            &protected,
+            &ReviewerMode::RewriteAllowed,
+        );
+        assert!(!result.was_modified);
+        assert_eq!(result.code, original);
+        assert!(result
+            .explanation
+            .contains("user-specified dimension(s) 50mm"));
+        assert!(!result.findings[0].was_fixed);
+
+        let allowed = finalize_review(reviewed, original, &[], &ReviewerMode::RewriteAllowed);
+        assert!(allowed.was_modified);
+        assert!(allowed.findings[0].was_fixed);
+    }
+
+    // ── Static pre-screen ──────────────────────────────────────────────
+
+    struct CountingReviewer {
+        calls: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl AiProvider for CountingReviewer {
+        async fn complete(
+            &self,
+            _messages: &[ChatMessage],
+            _max_tokens: Option<u32>,
+        ) -> Result<(String, Option<TokenUsage>), AppError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(("APPROVED".to_string(), Some(TokenUsage::default())))
+        }
+
+        async fn stream(
+            &self,
+            _messages: &[ChatMessage],
+            _tx: mpsc::Sender<StreamSignal>,
+        ) -> Result<Option<TokenUsage>, AppError> {
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prescreen_skips_review_of_clean_code() {
+        let clean = "from build123d import *\nWIDTH = 40\nresult = Box(WIDTH, 30, 10)\n";
+        let flagged = "from build123d import *\nbody = Box(40, 30, 10)\nbody = body - Cylinder(3, 10)\nresult = body\n";
+        assert!(prescreen_findings(clean, "a 40mm block").is_empty());
+        assert!(!prescreen_findings(flagged, "a 40mm block with a hole").is_empty());
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let options = ReviewOptions {
+            always_review: false,
+            ..Default::default()
+        };
+        let review = |code: &'static str, options: ReviewOptions| {
+            let provider = Box::new(CountingReviewer {
+                calls: calls.clone(),
+            });
+            async move { review_code(provider, "a 40mm block", code, None, &options).await }
+        };
+
+        let (result, usage) = review(clean, options.clone()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert!(!result.ai_reviewed && !result.was_modified && usage.is_none());
+        assert_eq!(result.code, clean);
+
+        let (result, _) = review(flagged, options).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(result.ai_reviewed);
+
+        let always = ReviewOptions {
+            always_review: true,
+            ..Default::default()
+        };
+        let (result, _) = review(clean, always).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(result.ai_reviewed);
+    }
 }
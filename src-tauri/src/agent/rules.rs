@@ -134,16 +134,37 @@ impl AgentRules {
         Ok(rules)
     }
 
-    /// Load agent rules from an embedded preset by name.
-    /// Valid names: "3d-printing", "cnc". Anything else (including None) loads the default preset.
+    /// Embedded preset name and YAML for `name`.
+    /// Valid names: "3d-printing", "cnc". Anything else (including None) is the default preset.
+    pub fn preset_yaml(name: Option<&str>) -> (&'static str, &'static str) {
+        match name {
+            Some("3d-printing") => ("3d-printing", PRINTING_YAML),
+            Some("cnc") => ("cnc", CNC_YAML),
+            _ => ("default", DEFAULT_YAML),
+        }
+    }
+
+    /// Load agent rules from an embedded preset by name, with any files in
+    /// `custom_rules_dir` merged over it.
     pub fn from_preset(name: Option<&str>) -> Result<Self, AppError> {
-        let yaml_str = match name {
-            Some("3d-printing") => PRINTING_YAML,
-            Some("cnc") => CNC_YAML,
-            _ => DEFAULT_YAML,
-        };
-        serde_yaml::from_str(yaml_str)
-            .map_err(|e| AppError::ConfigError(format!("Failed to parse agent rules: {}", e)))
+        let (preset_name, yaml_str) = Self::preset_yaml(name);
+        let mut rules: AgentRules =
+            match crate::agent::custom_rules::merged_for_preset(preset_name, yaml_str) {
+                Some(rules) => rules,
+                None => serde_yaml::from_str(yaml_str).map_err(|e| {
+                    AppError::ConfigError(format!("Failed to parse agent rules: {}", e))
+                })?,
+            };
+
+        // Approved anti-patterns mined from recurring failures join every preset.
+        let learned = crate::agent::anti_pattern_mining::active_patterns();
+        if !learned.is_empty() {
+            rules
+                .anti_patterns
+                .get_or_insert_with(Vec::new)
+                .extend(learned.iter().map(|p| p.to_entry()));
+        }
+        Ok(rules)
     }
 
     /// Create a default (empty) set of rules.
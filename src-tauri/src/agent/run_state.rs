@@ -0,0 +1,666 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::executor::PostGeometryValidationReport;
+use crate::agent::telemetry;
+use crate::agent::validation_history::{ValidationChain, ValidationHistoryStore};
+use crate::commands::parallel::GenerationPlan;
+use crate::error::AppError;
+
+/// How long a finished multi-part run stays available for cherry-picking.
+pub const RUN_STATE_TTL_MS: u64 = 2 * 60 * 60 * 1000;
+
+/// How long accepted parts of an unfinished run stay resumable on disk.
+pub const RESUMABLE_TTL_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+/// One attempt at a part, kept whether or not per-part acceptance passed.
+#[derive(Debug, Clone, Serialize)]
+pub struct PartCandidate {
+    pub code: String,
+    pub stl_base64: Option<String>,
+    pub accepted: bool,
+    /// Semantic findings, or the execution error for candidates that never ran.
+    pub findings: Vec<String>,
+    pub created_at_ms: u64,
+    /// Measured geometry, reused for layout and mass when the candidate is picked.
+    #[serde(skip)]
+    pub post_geometry_report: Option<PostGeometryValidationReport>,
+}
+
+impl PartCandidate {
+    fn size_bytes(&self) -> usize {
+        self.code.len() + self.stl_base64.as_ref().map_or(0, |s| s.len())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunPart {
+    pub candidates: Vec<PartCandidate>,
+    /// Candidate currently used for this part in the assembly.
+    pub selected: Option<usize>,
+}
+
+/// Everything needed to re-assemble a multi-part run from stored candidates.
+#[derive(Debug, Clone)]
+pub struct RunRecord {
+    pub run_id: String,
+    pub created_at_ms: u64,
+    pub user_request: String,
+    pub plan_text: String,
+    pub plan: GenerationPlan,
+    pub parts: Vec<RunPart>,
+    /// Set while the run waits for `approve_assembly`: the generation runtime
+    /// spent before parking, so the timeout clock stops while parked.
+    pub parked_elapsed_ms: Option<u64>,
+}
+
+impl RunRecord {
+    fn size_bytes(&self) -> usize {
+        self.parts
+            .iter()
+            .flat_map(|p| p.candidates.iter())
+            .map(PartCandidate::size_bytes)
+            .sum()
+    }
+
+    fn part(&self, part_index: usize) -> Result<&RunPart, AppError> {
+        self.parts.get(part_index).ok_or_else(|| {
+            AppError::ConfigError(format!(
+                "Run '{}' has no part at index {}",
+                self.run_id, part_index
+            ))
+        })
+    }
+
+    /// Drop the selection of each part in `part_indices`, leaving it out of
+    /// assembly. Fails on an unknown index or when no selected part remains.
+    pub fn exclude_parts(&mut self, part_indices: &[usize]) -> Result<(), AppError> {
+        for &index in part_indices {
+            self.part(index)?;
+        }
+        for &index in part_indices {
+            self.parts[index].selected = None;
+        }
+        if self.parts.iter().all(|p| p.selected.is_none()) {
+            return Err(AppError::ConfigError(
+                "Every part is excluded; nothing left to assemble".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// (name, code, position) of every part with a selected candidate, in plan order.
+    pub fn selected_parts(&self) -> Vec<(String, String, [f64; 3])> {
+        self.plan
+            .parts
+            .iter()
+            .zip(self.parts.iter())
+            .filter_map(|(spec, part)| {
+                let candidate = part.candidates.get(part.selected?)?;
+                Some((spec.name.clone(), candidate.code.clone(), spec.position))
+            })
+            .collect()
+    }
+
+    /// Geometry reports of the selected candidates, keyed by part name.
+    pub fn selected_reports(
+        &self,
+    ) -> std::collections::HashMap<String, PostGeometryValidationReport> {
+        self.plan
+            .parts
+            .iter()
+            .zip(self.parts.iter())
+            .filter_map(|(spec, part)| {
+                let report = part
+                    .candidates
+                    .get(part.selected?)?
+                    .post_geometry_report
+                    .clone()?;
+                Some((spec.name.clone(), report))
+            })
+            .collect()
+    }
+}
+
+/// A part that passed acceptance, as persisted for `resume_generation`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcceptedPart {
+    pub name: String,
+    pub code: String,
+    pub position: [f64; 3],
+}
+
+/// Accepted parts of a multi-part run, saved to disk so assembly can be
+/// re-run after a failure or restart without regenerating any part.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumableGeneration {
+    pub generation_id: String,
+    pub created_at_ms: u64,
+    pub user_request: String,
+    pub plan_text: String,
+    pub plan: GenerationPlan,
+    pub parts: Vec<AcceptedPart>,
+}
+
+impl ResumableGeneration {
+    /// Snapshot the selected candidates of a run. `None` when no part was accepted.
+    pub fn from_run(run: &RunRecord) -> Option<Self> {
+        let parts: Vec<AcceptedPart> = run
+            .selected_parts()
+            .into_iter()
+            .map(|(name, code, position)| AcceptedPart {
+                name,
+                code,
+                position,
+            })
+            .collect();
+        if parts.is_empty() {
+            return None;
+        }
+        Some(Self {
+            generation_id: run.run_id.clone(),
+            created_at_ms: run.created_at_ms,
+            user_request: run.user_request.clone(),
+            plan_text: run.plan_text.clone(),
+            plan: run.plan.clone(),
+            parts,
+        })
+    }
+
+    /// Rebuild a run whose only candidates are the accepted parts, each selected.
+    pub fn into_run_record(self) -> RunRecord {
+        let mut plan = self.plan;
+        let parts = plan
+            .parts
+            .iter_mut()
+            .map(
+                |spec| match self.parts.iter().find(|p| p.name == spec.name) {
+                    Some(accepted) => {
+                        spec.position = accepted.position;
+                        RunPart {
+                            candidates: vec![PartCandidate {
+                                code: accepted.code.clone(),
+                                stl_base64: None,
+                                accepted: true,
+                                findings: vec![],
+                                created_at_ms: self.created_at_ms,
+                                post_geometry_report: None,
+                            }],
+                            selected: Some(0),
+                        }
+                    }
+                    None => RunPart::default(),
+                },
+            )
+            .collect();
+        RunRecord {
+            run_id: self.generation_id,
+            created_at_ms: self.created_at_ms,
+            user_request: self.user_request,
+            plan_text: self.plan_text,
+            plan,
+            parts,
+            parked_elapsed_ms: None,
+        }
+    }
+}
+
+fn resumable_dir() -> Result<PathBuf, AppError> {
+    let base = dirs::config_dir()
+        .ok_or_else(|| AppError::ConfigError("Cannot resolve config directory".to_string()))?;
+    Ok(base.join("cadai-studio").join("resumable"))
+}
+
+/// Generation ids are run UUIDs; anything else could escape the directory.
+fn resumable_path(dir: &Path, generation_id: &str) -> Result<PathBuf, AppError> {
+    if generation_id.is_empty()
+        || !generation_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(AppError::ConfigError(format!(
+            "Invalid generation id '{}'",
+            generation_id
+        )));
+    }
+    Ok(dir.join(format!("{}.json", generation_id)))
+}
+
+pub fn save_resumable(generation: &ResumableGeneration) -> Result<(), AppError> {
+    save_resumable_in(&resumable_dir()?, generation)
+}
+
+pub fn load_resumable(generation_id: &str) -> Result<ResumableGeneration, AppError> {
+    load_resumable_in(&resumable_dir()?, generation_id, telemetry::now_ms())
+}
+
+pub fn remove_resumable(generation_id: &str) -> Result<(), AppError> {
+    remove_resumable_in(&resumable_dir()?, generation_id)
+}
+
+fn save_resumable_in(dir: &Path, generation: &ResumableGeneration) -> Result<(), AppError> {
+    let path = resumable_path(dir, &generation.generation_id)?;
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(path, serde_json::to_vec_pretty(generation)?)?;
+    Ok(())
+}
+
+/// Load saved accepted parts; expired entries are deleted and reported missing.
+fn load_resumable_in(
+    dir: &Path,
+    generation_id: &str,
+    now_ms: u64,
+) -> Result<ResumableGeneration, AppError> {
+    let path = resumable_path(dir, generation_id)?;
+    let not_found = || {
+        AppError::ConfigError(format!(
+            "No resumable state for generation '{}'",
+            generation_id
+        ))
+    };
+    let raw = std::fs::read_to_string(&path).map_err(|_| not_found())?;
+    let generation: ResumableGeneration = serde_json::from_str(&raw)?;
+    if now_ms.saturating_sub(generation.created_at_ms) >= RESUMABLE_TTL_MS {
+        let _ = std::fs::remove_file(&path);
+        return Err(not_found());
+    }
+    Ok(generation)
+}
+
+fn remove_resumable_in(dir: &Path, generation_id: &str) -> Result<(), AppError> {
+    let path = resumable_path(dir, generation_id)?;
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// In-memory part candidates for recent multi-part runs.
+#[derive(Debug, Default)]
+pub struct RunStore {
+    runs: Vec<RunRecord>,
+    /// Parked runs dropped by the TTL before anyone approved their assembly.
+    expired_parked: Vec<String>,
+    /// Validation attempt chains of recent runs, single-part runs included.
+    validation_chains: ValidationHistoryStore,
+}
+
+impl RunStore {
+    /// Register a new run, dropping runs older than [`RUN_STATE_TTL_MS`].
+    pub fn start_run(
+        &mut self,
+        run_id: &str,
+        user_request: &str,
+        plan_text: &str,
+        plan: &GenerationPlan,
+    ) {
+        let now = telemetry::now_ms();
+        self.prune_expired(now);
+        self.runs.retain(|r| r.run_id != run_id);
+        self.runs.push(RunRecord {
+            run_id: run_id.to_string(),
+            created_at_ms: now,
+            user_request: user_request.to_string(),
+            plan_text: plan_text.to_string(),
+            plan: plan.clone(),
+            parts: vec![RunPart::default(); plan.parts.len()],
+            parked_elapsed_ms: None,
+        });
+    }
+
+    /// Drop a run and its candidates.
+    pub fn remove_run(&mut self, run_id: &str) {
+        self.runs.retain(|r| r.run_id != run_id);
+    }
+
+    pub fn prune_expired(&mut self, now_ms: u64) {
+        let (kept, expired): (Vec<RunRecord>, Vec<RunRecord>) = std::mem::take(&mut self.runs)
+            .into_iter()
+            .partition(|r| now_ms.saturating_sub(r.created_at_ms) < RUN_STATE_TTL_MS);
+        self.runs = kept;
+        self.expired_parked.extend(
+            expired
+                .into_iter()
+                .filter(|r| r.parked_elapsed_ms.is_some())
+                .map(|r| r.run_id),
+        );
+    }
+
+    /// Ids of parked runs that expired since the last call.
+    pub fn take_expired_parked(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.expired_parked)
+    }
+
+    /// Hold a run before assembly until `unpark` is called.
+    pub fn park(&mut self, run_id: &str, elapsed_ms: u64) -> Result<(), AppError> {
+        let run = self.run_mut(run_id)?;
+        run.parked_elapsed_ms = Some(elapsed_ms);
+        Ok(())
+    }
+
+    /// Release a parked run, returning it and the runtime spent before parking.
+    pub fn unpark(&mut self, run_id: &str) -> Result<(RunRecord, u64), AppError> {
+        let run = self.run_mut(run_id)?;
+        let elapsed_ms = run.parked_elapsed_ms.take().ok_or_else(|| {
+            AppError::ConfigError(format!(
+                "Run '{}' is not awaiting assembly approval",
+                run_id
+            ))
+        })?;
+        Ok((run.clone(), elapsed_ms))
+    }
+
+    fn run_mut(&mut self, run_id: &str) -> Result<&mut RunRecord, AppError> {
+        self.runs
+            .iter_mut()
+            .find(|r| r.run_id == run_id)
+            .ok_or_else(|| AppError::ConfigError(format!("Run '{}' not found or expired", run_id)))
+    }
+
+    /// Store a candidate for a part. An accepted candidate becomes the
+    /// selection. Beyond `max_per_part` the oldest unselected candidate is
+    /// dropped; beyond `max_total_bytes` whole runs are evicted oldest-first.
+    pub fn record_candidate(
+        &mut self,
+        run_id: &str,
+        part_index: usize,
+        candidate: PartCandidate,
+        max_per_part: usize,
+        max_total_bytes: usize,
+    ) {
+        let Some(run) = self.runs.iter_mut().find(|r| r.run_id == run_id) else {
+            return;
+        };
+        let Some(part) = run.parts.get_mut(part_index) else {
+            return;
+        };
+
+        let accepted = candidate.accepted;
+        part.candidates.push(candidate);
+        if accepted {
+            part.selected = Some(part.candidates.len() - 1);
+        }
+
+        while part.candidates.len() > max_per_part.max(1) {
+            let Some(drop_idx) = (0..part.candidates.len()).find(|&i| Some(i) != part.selected)
+            else {
+                break;
+            };
+            part.candidates.remove(drop_idx);
+            if let Some(selected) = part.selected.as_mut() {
+                if *selected > drop_idx {
+                    *selected -= 1;
+                }
+            }
+        }
+
+        while self.runs.len() > 1
+            && self.runs.iter().map(RunRecord::size_bytes).sum::<usize>() > max_total_bytes
+        {
+            let oldest = self
+                .runs
+                .iter()
+                .enumerate()
+                .filter(|(_, r)| r.run_id != run_id)
+                .min_by_key(|(_, r)| r.created_at_ms)
+                .map(|(i, _)| i);
+            match oldest {
+                Some(i) => {
+                    self.runs.remove(i);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Keep a finished validation's attempt chain with its run.
+    pub fn record_validation_chain(&mut self, chain: ValidationChain) {
+        self.validation_chains.insert(chain);
+    }
+
+    /// Attempt chains of `run_id`, oldest first; only `part_name`'s when given.
+    pub fn validation_chains(&self, run_id: &str, part_name: Option<&str>) -> Vec<ValidationChain> {
+        self.validation_chains.chains(run_id, part_name)
+    }
+
+    pub fn run(&self, run_id: &str) -> Result<&RunRecord, AppError> {
+        self.runs
+            .iter()
+            .find(|r| r.run_id == run_id)
+            .ok_or_else(|| AppError::ConfigError(format!("Run '{}' not found or expired", run_id)))
+    }
+
+    pub fn candidates(
+        &self,
+        run_id: &str,
+        part_index: usize,
+    ) -> Result<Vec<PartCandidate>, AppError> {
+        Ok(self.run(run_id)?.part(part_index)?.candidates.clone())
+    }
+
+    /// Make `candidate_idx` the selection for a part and return the updated run.
+    pub fn select_candidate(
+        &mut self,
+        run_id: &str,
+        part_index: usize,
+        candidate_idx: usize,
+    ) -> Result<RunRecord, AppError> {
+        let run = self.run_mut(run_id)?;
+        let count = run.part(part_index)?.candidates.len();
+        if candidate_idx >= count {
+            return Err(AppError::ConfigError(format!(
+                "Part {} has {} candidate(s); index {} is out of range",
+                part_index, count, candidate_idx
+            )));
+        }
+        run.parts[part_index].selected = Some(candidate_idx);
+        Ok(run.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parallel::PartSpec;
+
+    fn plan(names: &[&str]) -> GenerationPlan {
+        GenerationPlan {
+            mode: "multi".to_string(),
+            description: None,
+            parts: names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| PartSpec {
+                    name: name.to_string(),
+                    description: String::new(),
+                    position: [0.0, 0.0, i as f64 * 10.0],
+                    constraints: vec![],
+                    reliability_profile: None,
+                })
+                .collect(),
+            assembly_ops: vec![],
+        }
+    }
+
+    fn candidate(code: &str, accepted: bool) -> PartCandidate {
+        PartCandidate {
+            code: code.to_string(),
+            stl_base64: None,
+            accepted,
+            findings: vec![],
+            created_at_ms: 0,
+            post_geometry_report: None,
+        }
+    }
+
+    #[test]
+    fn test_rejected_and_accepted_candidates_are_kept() {
+        let mut store = RunStore::default();
+        store.start_run("r1", "box with lid", "", &plan(&["base", "lid"]));
+        store.record_candidate("r1", 1, candidate("first", false), 3, usize::MAX);
+        store.record_candidate("r1", 1, candidate("retry", true), 3, usize::MAX);
+
+        let candidates = store.candidates("r1", 1).unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert!(!candidates[0].accepted);
+        assert!(candidates[1].accepted);
+        assert_eq!(store.run("r1").unwrap().parts[1].selected, Some(1));
+        assert!(matches!(
+            store.candidates("r1", 5),
+            Err(AppError::ConfigError(_))
+        ));
+        assert!(matches!(
+            store.candidates("missing", 0),
+            Err(AppError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_cap_keeps_selected_candidate() {
+        let mut store = RunStore::default();
+        store.start_run("r1", "", "", &plan(&["base"]));
+        store.record_candidate("r1", 0, candidate("accepted", true), 2, usize::MAX);
+        store.record_candidate("r1", 0, candidate("a", false), 2, usize::MAX);
+        store.record_candidate("r1", 0, candidate("b", false), 2, usize::MAX);
+
+        let run = store.run("r1").unwrap();
+        let codes: Vec<&str> = run.parts[0]
+            .candidates
+            .iter()
+            .map(|c| c.code.as_str())
+            .collect();
+        assert_eq!(codes, vec!["accepted", "b"]);
+        assert_eq!(run.parts[0].selected, Some(0));
+    }
+
+    #[test]
+    fn test_select_candidate_swaps_part_code() {
+        let mut store = RunStore::default();
+        store.start_run("r1", "", "", &plan(&["base", "lid"]));
+        store.record_candidate("r1", 0, candidate("base_ok", true), 3, usize::MAX);
+        store.record_candidate("r1", 1, candidate("lid_nice", false), 3, usize::MAX);
+        store.record_candidate("r1", 1, candidate("lid_ugly", true), 3, usize::MAX);
+
+        let run = store.select_candidate("r1", 1, 0).unwrap();
+        let parts = run.selected_parts();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].0, "lid");
+        assert_eq!(parts[1].1, "lid_nice");
+        assert_eq!(parts[1].2, [0.0, 0.0, 10.0]);
+        assert!(matches!(
+            store.select_candidate("r1", 1, 7),
+            Err(AppError::ConfigError(_))
+        ));
+        assert!(matches!(
+            store.select_candidate("missing", 0, 0),
+            Err(AppError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_size_cap_and_ttl_evict_old_runs() {
+        let mut store = RunStore::default();
+        store.start_run("old", "", "", &plan(&["a"]));
+        store.record_candidate("old", 0, candidate(&"x".repeat(100), true), 3, 150);
+        store.start_run("new", "", "", &plan(&["a"]));
+        store.record_candidate("new", 0, candidate(&"y".repeat(100), true), 3, 150);
+        assert!(store.run("old").is_err());
+        assert!(store.run("new").is_ok());
+
+        let created = store.run("new").unwrap().created_at_ms;
+        store.prune_expired(created + RUN_STATE_TTL_MS);
+        assert!(store.run("new").is_err());
+    }
+
+    #[test]
+    fn test_excluded_parts_are_left_out_of_assembly() {
+        let mut store = RunStore::default();
+        store.start_run("r1", "", "", &plan(&["base", "lid", "hinge"]));
+        for (i, code) in ["b", "l", "h"].iter().enumerate() {
+            store.record_candidate("r1", i, candidate(code, true), 3, usize::MAX);
+        }
+        let mut run = store.run("r1").unwrap().clone();
+
+        assert!(run.exclude_parts(&[1, 7]).is_err());
+        assert_eq!(run.selected_parts().len(), 3);
+
+        run.exclude_parts(&[1]).unwrap();
+        let names: Vec<String> = run.selected_parts().into_iter().map(|p| p.0).collect();
+        assert_eq!(names, vec!["base", "hinge"]);
+        assert!(run.exclude_parts(&[0, 2]).is_err());
+    }
+
+    #[test]
+    fn test_parked_run_unparks_once_and_reports_expiry() {
+        let mut store = RunStore::default();
+        store.start_run("r1", "", "", &plan(&["base"]));
+        assert!(store.unpark("r1").is_err());
+        store.park("r1", 4_000).unwrap();
+        let (run, elapsed_ms) = store.unpark("r1").unwrap();
+        assert_eq!(run.run_id, "r1");
+        assert_eq!(elapsed_ms, 4_000);
+        assert!(store.unpark("r1").is_err());
+
+        store.start_run("r2", "", "", &plan(&["base"]));
+        store.park("r2", 1_000).unwrap();
+        let created = store.run("r2").unwrap().created_at_ms;
+        store.prune_expired(created + RUN_STATE_TTL_MS);
+        assert_eq!(store.take_expired_parked(), vec!["r2"]);
+        assert!(store.take_expired_parked().is_empty());
+    }
+
+    #[test]
+    fn test_resumable_roundtrip_keeps_accepted_parts() {
+        let dir = std::env::temp_dir().join(format!("cadai-resumable-{}", uuid::Uuid::new_v4()));
+        let mut store = RunStore::default();
+        store.start_run("gen-1", "box with lid", "plan", &plan(&["base", "lid"]));
+        store.record_candidate("gen-1", 0, candidate("base_ok", true), 3, usize::MAX);
+        store.record_candidate("gen-1", 1, candidate("lid_bad", false), 3, usize::MAX);
+
+        let saved = ResumableGeneration::from_run(store.run("gen-1").unwrap()).unwrap();
+        assert_eq!(
+            saved.parts,
+            vec![AcceptedPart {
+                name: "base".to_string(),
+                code: "base_ok".to_string(),
+                position: [0.0, 0.0, 0.0],
+            }]
+        );
+        save_resumable_in(&dir, &saved).unwrap();
+
+        let loaded = load_resumable_in(&dir, "gen-1", saved.created_at_ms).unwrap();
+        assert_eq!(loaded.user_request, "box with lid");
+        let run = loaded.into_run_record();
+        assert_eq!(run.run_id, "gen-1");
+        assert_eq!(run.parts.len(), 2);
+        assert_eq!(run.parts[1].selected, None);
+        let parts = run.selected_parts();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].1, "base_ok");
+
+        remove_resumable_in(&dir, "gen-1").unwrap();
+        assert!(load_resumable_in(&dir, "gen-1", saved.created_at_ms).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resumable_rejects_bad_ids_and_expires() {
+        let dir = std::env::temp_dir().join(format!("cadai-resumable-{}", uuid::Uuid::new_v4()));
+        assert!(resumable_path(&dir, "../config").is_err());
+        assert!(resumable_path(&dir, "").is_err());
+
+        let mut store = RunStore::default();
+        store.start_run("gen-2", "", "", &plan(&["a"]));
+        assert!(ResumableGeneration::from_run(store.run("gen-2").unwrap()).is_none());
+        store.record_candidate("gen-2", 0, candidate("a_ok", true), 3, usize::MAX);
+        let saved = ResumableGeneration::from_run(store.run("gen-2").unwrap()).unwrap();
+        save_resumable_in(&dir, &saved).unwrap();
+
+        let later = saved.created_at_ms + RESUMABLE_TTL_MS;
+        assert!(load_resumable_in(&dir, "gen-2", later).is_err());
+        assert!(!dir.join("gen-2.json").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
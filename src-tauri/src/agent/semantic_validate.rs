@@ -2,6 +2,7 @@ use regex::Regex;
 use serde::Serialize;
 
 use crate::agent::executor::PostGeometryValidationReport;
+use crate::agent::numparse;
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -89,7 +90,7 @@ fn parse_dims_line(description: &str) -> Option<[f64; 3]> {
     let dims_text = &description[dims_start..];
     let dims_line = dims_text.lines().next().unwrap_or(dims_text);
 
-    let kv_re = Regex::new(r"(?i)(\w+)\s*=\s*(\d+(?:\.\d+)?)\s*mm").ok()?;
+    let kv_re = Regex::new(&format!(r"(?i)(\w+)\s*=\s*({})\s*mm", numparse::NUMBER)).ok()?;
     let mut length = None;
     let mut width = None;
     let mut height = None;
@@ -104,7 +105,7 @@ fn parse_dims_line(description: &str) -> Option<[f64; 3]> {
 
     for cap in kv_re.captures_iter(dims_line) {
         let key = cap[1].to_lowercase();
-        let val: f64 = cap[2].parse().ok()?;
+        let val: f64 = numparse::parse_number(&cap[2])?;
         if val <= 0.0 {
             continue;
         }
@@ -144,13 +145,14 @@ fn parse_dims_line(description: &str) -> Option<[f64; 3]> {
 }
 
 fn parse_footprint_plus_height(description: &str) -> Option<[f64; 3]> {
-    let fp_re = Regex::new(
-        r"(?i)footprint\s+(\d+(?:\.\d+)?)\s*[x×]\s*(\d+(?:\.\d+)?)\s*(?:mm)?",
-    )
+    let fp_re = Regex::new(&format!(
+        r"(?i)footprint\s+({n})\s*[x×]\s*({n})\s*(?:mm)?",
+        n = numparse::NUMBER
+    ))
     .ok()?;
     let cap = fp_re.captures(description)?;
-    let a: f64 = cap.get(1)?.as_str().parse().ok().filter(|v: &f64| *v > 0.0)?;
-    let b: f64 = cap.get(2)?.as_str().parse().ok().filter(|v: &f64| *v > 0.0)?;
+    let a: f64 = numparse::parse_number(cap.get(1)?.as_str()).filter(|v| *v > 0.0)?;
+    let b: f64 = numparse::parse_number(cap.get(2)?.as_str()).filter(|v| *v > 0.0)?;
     let height = parse_named_dimension(
         description,
         &["overall height", "outer height", "envelope height", "height"],
@@ -161,13 +163,14 @@ fn parse_footprint_plus_height(description: &str) -> Option<[f64; 3]> {
 fn parse_named_dimension(description: &str, labels: &[&str]) -> Option<f64> {
     for label in labels {
         let pat = format!(
-            r"(?i)\b{}\b[^0-9-]*(-?\d+(?:\.\d+)?)\s*mm",
-            regex::escape(label)
+            r"(?i)\b{}\b[^0-9-]*({})\s*mm",
+            regex::escape(label),
+            numparse::SIGNED_NUMBER
         );
         let re = Regex::new(&pat).ok()?;
 
         for cap in re.captures_iter(description) {
-            let val: f64 = match cap.get(1).and_then(|m| m.as_str().parse().ok()) {
+            let val: f64 = match cap.get(1).and_then(|m| numparse::parse_number(m.as_str())) {
                 Some(v) if v > 0.0 => v,
                 _ => continue,
             };
@@ -197,16 +200,17 @@ pub fn infer_envelope_dimensions_mm(description: &str) -> Option<[f64; 3]> {
     }
 
     // 2. Compact NxNxN format (e.g. "42x28x7.5mm")
-    let compact = Regex::new(
-        r"(?i)(\d+(?:\.\d+)?)\s*[x×]\s*(\d+(?:\.\d+)?)\s*[x×]\s*(\d+(?:\.\d+)?)\s*(?:mm)?",
-    )
+    let compact = Regex::new(&format!(
+        r"(?i)({n})\s*[x×]\s*({n})\s*[x×]\s*({n})\s*(?:mm)?",
+        n = numparse::NUMBER
+    ))
     .unwrap();
     if let Some(c) = compact.captures(description) {
         let mut vals = [0.0_f64; 3];
         for (i, slot) in vals.iter_mut().enumerate() {
             *slot = c
                 .get(i + 1)
-                .and_then(|m| m.as_str().parse::<f64>().ok())
+                .and_then(|m| numparse::parse_number(m.as_str()))
                 .unwrap_or(0.0);
         }
         if vals.iter().all(|v| *v > 0.0) {
@@ -362,6 +366,42 @@ pub fn validate_part_semantics(
     }
 }
 
+/// Compare one part's bbox with the whole-assembly envelope (sorted extents,
+/// largest first). A single part larger than the assembly it belongs to is
+/// almost always a unit or scale mistake, so the first extent beyond
+/// `margin_pct` is reported.
+pub fn check_assembly_envelope(
+    report: &PostGeometryValidationReport,
+    envelope_mm: [f64; 3],
+    margin_pct: f64,
+) -> Option<String> {
+    let mut actual = [
+        (report.bounds_max[0] - report.bounds_min[0]).abs(),
+        (report.bounds_max[1] - report.bounds_min[1]).abs(),
+        (report.bounds_max[2] - report.bounds_min[2]).abs(),
+    ];
+    actual.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    let mut envelope = envelope_mm;
+    envelope.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+    let margin = margin_pct.max(0.0);
+    for (idx, (got, limit)) in actual.iter().zip(envelope.iter()).enumerate() {
+        if *limit <= 0.0 {
+            continue;
+        }
+        if *got > limit * (1.0 + margin / 100.0) {
+            return Some(format!(
+                "bbox extent {} exceeds assembly envelope: got {:.2}mm, assembly {:.2}mm +{}%",
+                idx + 1,
+                got,
+                limit,
+                margin.round()
+            ));
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -377,6 +417,9 @@ mod tests {
             bounds_min: [0.0, 0.0, 0.0],
             bounds_max: [40.0, 20.0, 10.0],
             volume: 1000.0,
+            surface_area: 600.0,
+            center_of_mass: None,
+            unit_inertia: None,
             bbox_ok: true,
             warnings: vec![],
         }
@@ -592,6 +635,22 @@ mod tests {
         assert!(dims.is_none(), "Dims line present but incomplete should return None");
     }
 
+    #[test]
+    fn test_envelope_accepts_comma_decimals() {
+        let cases: &[(&str, [f64; 3])] = &[
+            ("Dims: length=42,5mm, width=28mm, height=7,5mm, wall=1,8mm", [42.5, 28.0, 7.5]),
+            ("Gehäuse 42,5x28x7,5mm, Wandstärke 1,8 mm", [42.5, 28.0, 7.5]),
+            ("Footprint 42,5x28 mm, height 7,5 mm", [42.5, 28.0, 7.5]),
+            ("Overall length 1.234,5 mm, width 80,0 mm, height 12,25 mm", [1234.5, 80.0, 12.25]),
+            ("Overall length 1,234.5 mm, width 80.0 mm, height 12.25 mm", [1234.5, 80.0, 12.25]),
+        ];
+        for (desc, expected) in cases {
+            let dims = infer_envelope_dimensions_mm(desc)
+                .unwrap_or_else(|| panic!("no envelope parsed from {:?}", desc));
+            assert_eq!(dims, *expected, "{}", desc);
+        }
+    }
+
     #[test]
     fn test_parse_dims_line_ignores_wall() {
         let desc = "Dims: length=42mm, width=28mm, height=7.5mm, wall=1.8mm";
@@ -661,4 +720,27 @@ mod tests {
         let dims = infer_envelope_dimensions_mm(desc).expect("should use 2-of-3 fallback for unknown key");
         assert_eq!(dims, [42.0, 28.0, 5.0]);
     }
+
+    #[test]
+    fn assembly_envelope_rejects_part_larger_than_assembly() {
+        let mut report = base_report();
+        report.bounds_max = [40.0, 20.0, 95.0];
+        // Assembly is 60x40x30: a 95mm part cannot fit inside it.
+        let finding = check_assembly_envelope(&report, [30.0, 60.0, 40.0], 25.0)
+            .expect("oversized part should be flagged");
+        assert!(finding.contains("bbox extent 1 exceeds assembly envelope"));
+        assert!(finding.contains("got 95.00mm"));
+        assert!(finding.contains("assembly 60.00mm +25%"));
+    }
+
+    #[test]
+    fn assembly_envelope_allows_parts_within_margin() {
+        let report = base_report();
+        // 40x20x10 against 35x30x20: 40mm is within 25% of 35mm.
+        assert!(check_assembly_envelope(&report, [35.0, 30.0, 20.0], 25.0).is_none());
+        // Without a margin the same part is flagged.
+        assert!(check_assembly_envelope(&report, [35.0, 30.0, 20.0], 0.0).is_some());
+        // Unknown (zero) envelope extents are skipped.
+        assert!(check_assembly_envelope(&report, [0.0, 0.0, 0.0], 0.0).is_none());
+    }
 }
@@ -0,0 +1,251 @@
+//! Leading `/directives` in a chat message that override settings for one run,
+//! e.g. `/profile balanced /noreview make the bracket stiffer`.
+
+use serde_json::{json, Map, Value};
+
+use crate::config::AppConfig;
+use crate::pipeline_presets;
+
+/// Usage lines listed whenever a directive is rejected.
+const SUPPORTED: &[&str] = &[
+    "/profile <reliability_first|balanced|fidelity_first>",
+    "/review",
+    "/noreview",
+    "/model <name>",
+    "/parts <n>",
+    "/draft",
+];
+
+/// A message with its leading directives stripped and mapped to a config overlay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlashCommands {
+    /// The message with leading directives removed.
+    pub message: String,
+    /// `AppConfig` fields to override, keyed by their serialized name.
+    pub overlay: Map<String, Value>,
+    /// Human-readable form of each directive, in order.
+    pub applied: Vec<String>,
+}
+
+impl SlashCommands {
+    /// `PlanStatus` line naming the overrides, or `None` when there are none.
+    pub fn summary(&self) -> Option<String> {
+        if self.applied.is_empty() {
+            None
+        } else {
+            Some(format!("Run overrides: {}", self.applied.join(", ")))
+        }
+    }
+}
+
+fn rejected(problem: String) -> String {
+    format!("{}. Supported commands: {}", problem, SUPPORTED.join(", "))
+}
+
+/// A directive is `/` plus a lowercase word, followed by whitespace or the end.
+/// Paths such as `/home/me/part.step` are not directives.
+fn leading_directive(input: &str) -> Option<(&str, &str)> {
+    let body = input.strip_prefix('/')?;
+    let end = body
+        .find(|c: char| !(c.is_ascii_lowercase() || c == '_'))
+        .unwrap_or(body.len());
+    let rest = &body[end..];
+    if end == 0 || !(rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        return None;
+    }
+    Some((&body[..end], rest))
+}
+
+/// Next whitespace-separated value, honoring `"..."` and `'...'` quoting.
+fn take_value<'a>(name: &str, input: &'a str) -> Result<(String, &'a str), String> {
+    let input = input.trim_start();
+    if let Some(quote) = input.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let body = &input[1..];
+        let close = body
+            .find(quote)
+            .ok_or_else(|| rejected(format!("Unterminated quote in /{} value", name)))?;
+        return Ok((body[..close].to_string(), &body[close + 1..]));
+    }
+    let end = input.find(char::is_whitespace).unwrap_or(input.len());
+    if end == 0 {
+        return Err(rejected(format!("/{} needs a value", name)));
+    }
+    Ok((input[..end].to_string(), &input[end..]))
+}
+
+/// Strip and interpret the directives at the start of `message`. Directives
+/// after the first ordinary word are left in the text untouched.
+pub fn parse(message: &str) -> Result<SlashCommands, String> {
+    let mut overlay = Map::new();
+    let mut applied = Vec::new();
+    let mut rest = message.trim_start();
+
+    while let Some((name, after)) = leading_directive(rest) {
+        let after = match name {
+            "profile" => {
+                let (value, after) = take_value(name, after)?;
+                overlay.insert("generation_reliability_profile".to_string(), json!(value));
+                applied.push(format!("profile={}", value));
+                after
+            }
+            "review" | "noreview" => {
+                overlay.insert("enable_code_review".to_string(), json!(name == "review"));
+                applied.push(format!(
+                    "review={}",
+                    if name == "review" { "on" } else { "off" }
+                ));
+                after
+            }
+            "model" => {
+                let (value, after) = take_value(name, after)?;
+                let value = value.trim().to_string();
+                if value.is_empty() {
+                    return Err(rejected("/model needs a model name".to_string()));
+                }
+                overlay.insert("model".to_string(), json!(value));
+                applied.push(format!("model={}", value));
+                after
+            }
+            "parts" => {
+                let (value, after) = take_value(name, after)?;
+                let count = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| {
+                        rejected(format!(
+                            "/parts needs a positive whole number, got '{}'",
+                            value
+                        ))
+                    })?;
+                overlay.insert("max_plan_parts".to_string(), json!(count));
+                applied.push(format!("max parts={}", count));
+                after
+            }
+            "draft" => {
+                let draft = pipeline_presets::builtin_presets()
+                    .into_iter()
+                    .find(|p| p.id == "fast_draft")
+                    .map(|p| p.overlay)
+                    .unwrap_or_default();
+                overlay.extend(draft);
+                applied.push("draft".to_string());
+                after
+            }
+            other => return Err(rejected(format!("Unknown command '/{}'", other))),
+        };
+        rest = after.trim_start();
+    }
+
+    Ok(SlashCommands {
+        message: rest.to_string(),
+        overlay,
+        applied,
+    })
+}
+
+/// Parse `message` and overlay its directives on `config`. Values go through
+/// the same deserialization as saved settings, so e.g. an unknown profile
+/// name is rejected here rather than reaching the model.
+pub fn apply(config: &AppConfig, message: &str) -> Result<(AppConfig, SlashCommands), String> {
+    let commands = parse(message)?;
+    if commands.overlay.is_empty() {
+        return Ok((config.clone(), commands));
+    }
+    if commands.message.trim().is_empty() {
+        return Err(rejected("Commands need a request after them".to_string()));
+    }
+    let run_config = pipeline_presets::apply_overlay(config, &commands.overlay)
+        .map_err(|e| rejected(format!("Invalid command value ({})", e)))?;
+    Ok((run_config, commands))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GenerationReliabilityProfile;
+
+    #[test]
+    fn test_multiple_leading_directives() {
+        let parsed =
+            parse("/profile balanced /noreview  /parts 3 make the bracket stiffer").unwrap();
+        assert_eq!(parsed.message, "make the bracket stiffer");
+        assert_eq!(parsed.overlay["generation_reliability_profile"], "balanced");
+        assert_eq!(parsed.overlay["enable_code_review"], false);
+        assert_eq!(parsed.overlay["max_plan_parts"], 3);
+        assert_eq!(
+            parsed.summary().unwrap(),
+            "Run overrides: profile=balanced, review=off, max parts=3"
+        );
+
+        let (config, _) = apply(
+            &AppConfig::default(),
+            "/profile balanced /noreview /parts 3 bracket",
+        )
+        .unwrap();
+        assert_eq!(
+            config.generation_reliability_profile,
+            GenerationReliabilityProfile::Balanced
+        );
+        assert!(!config.enable_code_review);
+        assert_eq!(config.max_plan_parts, Some(3));
+    }
+
+    #[test]
+    fn test_quoted_values() {
+        let parsed = parse("/model \"llama3.1:8b instruct\" /review a hinge").unwrap();
+        assert_eq!(parsed.overlay["model"], "llama3.1:8b instruct");
+        assert_eq!(parsed.overlay["enable_code_review"], true);
+        assert_eq!(parsed.message, "a hinge");
+
+        let parsed = parse("/model 'gpt-4o-mini' gear").unwrap();
+        assert_eq!(parsed.overlay["model"], "gpt-4o-mini");
+        assert!(parse("/model \"unterminated gear").is_err());
+    }
+
+    #[test]
+    fn test_only_leading_directives_are_honored() {
+        let parsed = parse("make a box /noreview with a lid").unwrap();
+        assert_eq!(parsed.message, "make a box /noreview with a lid");
+        assert!(parsed.overlay.is_empty());
+        assert!(parsed.summary().is_none());
+
+        let parsed = parse("/noreview make a box /parts 9").unwrap();
+        assert_eq!(parsed.message, "make a box /parts 9");
+        assert!(!parsed.overlay.contains_key("max_plan_parts"));
+
+        let parsed = parse("/home/me/part.step import this").unwrap();
+        assert_eq!(parsed.message, "/home/me/part.step import this");
+    }
+
+    #[test]
+    fn test_bad_directives_list_supported_commands() {
+        let unknown = parse("/turbo make a box").unwrap_err();
+        assert!(unknown.contains("Unknown command '/turbo'"), "{}", unknown);
+        assert!(unknown.contains("/profile <"), "{}", unknown);
+
+        assert!(parse("/parts 0 box").is_err());
+        assert!(parse("/parts many box").is_err());
+        assert!(parse("/model").is_err());
+
+        let bad_profile = apply(&AppConfig::default(), "/profile fastest box").unwrap_err();
+        assert!(
+            bad_profile.contains("Supported commands"),
+            "{}",
+            bad_profile
+        );
+        assert!(apply(&AppConfig::default(), "/noreview").is_err());
+    }
+
+    #[test]
+    fn test_draft_applies_fast_draft_overlay() {
+        let (config, commands) = apply(&AppConfig::default(), "/draft quick clip").unwrap();
+        assert!(!config.enable_code_review);
+        assert_eq!(config.max_validation_attempts, 2);
+        assert_eq!(commands.applied, vec!["draft"]);
+        assert_ne!(
+            pipeline_presets::config_fingerprint(&config),
+            pipeline_presets::config_fingerprint(&AppConfig::default())
+        );
+    }
+}
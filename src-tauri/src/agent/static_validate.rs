@@ -1,6 +1,7 @@
 use regex::Regex;
 use serde::Serialize;
 
+use crate::agent::{complexity, features};
 use crate::config::GenerationReliabilityProfile;
 
 #[derive(Debug, Clone, Serialize)]
@@ -53,6 +54,16 @@ fn push_profile_finding(
     }
 }
 
+pub const MISSING_RESULT_MESSAGE: &str = "Code must assign final geometry to `result`.";
+
+/// Whether the script binds `result` at module level (the runner exports that
+/// name). Indented assignments inside functions or blocks do not count, and
+/// `result == ...` comparisons are not assignments.
+pub fn has_top_level_result_assignment(code: &str) -> bool {
+    let re = Regex::new(r"(?m)^result\s*(?::[^=\n]*)?=(?:[^=]|$)").unwrap();
+    re.is_match(code)
+}
+
 pub fn validate_code_with_profile(
     code: &str,
     profile: &GenerationReliabilityProfile,
@@ -69,13 +80,8 @@ pub fn validate_code_with_profile(
         );
     }
 
-    let result_re = Regex::new(r"(?m)^\s*result\s*=").unwrap();
-    if !result_re.is_match(code) {
-        push_error(
-            &mut findings,
-            "missing_result",
-            "Code must assign final geometry to `result`.",
-        );
+    if !has_top_level_result_assignment(code) {
+        push_error(&mut findings, "missing_result", MISSING_RESULT_MESSAGE);
     }
 
     let banned_patterns = [
@@ -202,6 +208,65 @@ pub fn validate_code(code: &str) -> StaticValidationResult {
     validate_code_with_profile(code, &GenerationReliabilityProfile::Balanced, true)
 }
 
+/// Warnings for hand-rolled holes that miss the standard diameter of a
+/// fastener size named in `request` (e.g. an M4 clearance hole at 3.8mm).
+pub fn fastener_findings(code: &str, request: &str) -> Vec<StaticValidationFinding> {
+    let requested = features::detect_fastener_features(request);
+    let mut findings = Vec::new();
+    for hole in features::nonstandard_holes(code, &requested) {
+        push_warning(&mut findings, "nonstandard_fastener_hole", &hole.message());
+    }
+    findings
+}
+
+/// Warning for a requested pattern written out as near-identical cuts
+/// instead of a loop.
+pub fn pattern_findings(code: &str, request: &str) -> Vec<StaticValidationFinding> {
+    let mut findings = Vec::new();
+    let repeats = features::literal_repeated_cuts(code);
+    if repeats > features::LITERAL_REPEAT_THRESHOLD
+        && !features::detect_patterns(request).is_empty()
+    {
+        push_warning(
+            &mut findings,
+            "literal_pattern_repeats",
+            &format!(
+                "The request asks for a pattern but the script writes {} near-identical cuts \
+                 one by one. Build one instance and place it with a GridLocations/PolarLocations \
+                 loop.",
+                repeats
+            ),
+        );
+    }
+    findings
+}
+
+/// Findings for a script whose geometric operation count is over budget: a
+/// warning above `soft_budget`, an error carrying the simplification
+/// instruction above `hard_budget`.
+pub fn operation_budget_findings(
+    code: &str,
+    soft_budget: u32,
+    hard_budget: u32,
+) -> Vec<StaticValidationFinding> {
+    let count = complexity::count_code_operations(code);
+    let mut findings = Vec::new();
+    match complexity::assess_budget(count, soft_budget, hard_budget) {
+        complexity::BudgetVerdict::Within => {}
+        complexity::BudgetVerdict::OverSoft => push_warning(
+            &mut findings,
+            "operation_budget_exceeded",
+            &complexity::soft_budget_warning("Script", count, soft_budget),
+        ),
+        complexity::BudgetVerdict::OverHard => push_error(
+            &mut findings,
+            "operation_budget_hard_limit",
+            &complexity::simplification_instruction("Script", count, hard_budget),
+        ),
+    }
+    findings
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,6 +290,71 @@ result = Box(10, 10, 10)
         assert!(result.findings.iter().any(|f| f.code == "missing_result"));
     }
 
+    #[test]
+    fn test_fastener_findings_flag_nonstandard_named_size() {
+        let code = "from build123d import *\nhole_d = 3.8\nresult = Box(20, 20, 4) - Cylinder(hole_d / 2, 4)\n";
+        let findings = fastener_findings(code, "plate with two M4 clearance holes");
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].code, "nonstandard_fastener_hole");
+        assert!(matches!(findings[0].level, FindingLevel::Warning));
+        assert!(fastener_findings(code, "plate with two 3.8mm holes").is_empty());
+    }
+
+    #[test]
+    fn test_pattern_findings_flag_unrolled_pattern() {
+        let cuts: String = (0..6)
+            .map(|i| format!("part -= Pos({}, 0, 0) * Box(2, 10, 5)\n", i * 12 - 30))
+            .collect();
+        let code = format!(
+            "from build123d import *\npart = Box(80, 20, 5)\n{}result = part\n",
+            cuts
+        );
+        let findings = pattern_findings(&code, "a row of 6 slots at 12mm pitch");
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].code, "literal_pattern_repeats");
+        assert!(findings[0].message.contains("6 near-identical cuts"));
+        assert!(pattern_findings(&code, "a plate with some slots").is_empty());
+    }
+
+    #[test]
+    fn test_operation_budget_findings_on_long_chains() {
+        let chain = ".cut(Cylinder(2, 5))".repeat(15);
+        let code = format!(
+            "from build123d import *\nresult = Box(80, 80, 5){}\n",
+            chain
+        );
+        // Box plus 15 cuts and 15 cylinders.
+        assert!(operation_budget_findings(&code, 40, 60).is_empty());
+
+        let findings = operation_budget_findings(&code, 22, 60);
+        assert_eq!(findings[0].code, "operation_budget_exceeded");
+        assert!(matches!(findings[0].level, FindingLevel::Warning));
+
+        let findings = operation_budget_findings(&code, 10, 30);
+        assert_eq!(findings[0].code, "operation_budget_hard_limit");
+        assert!(matches!(findings[0].level, FindingLevel::Error));
+        assert!(findings[0].message.contains("~31"));
+    }
+
+    #[test]
+    fn test_result_assignment_must_be_top_level() {
+        assert!(has_top_level_result_assignment(
+            "from build123d import *\nresult = Box(1, 1, 1)\n"
+        ));
+        assert!(has_top_level_result_assignment(
+            "from build123d import *\nresult: Part = Box(1, 1, 1)"
+        ));
+        assert!(!has_top_level_result_assignment(
+            "from build123d import *\ndef build():\n    result = Box(1, 1, 1)\n    return result\n"
+        ));
+        assert!(!has_top_level_result_assignment(
+            "from build123d import *\npart = Box(1, 1, 1)\nresult == part\n"
+        ));
+        assert!(!has_top_level_result_assignment(
+            "final_result = Box(1, 1, 1)"
+        ));
+    }
+
     #[test]
     fn test_static_validation_detects_file_io() {
         let code = r#"
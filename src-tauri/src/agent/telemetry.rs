@@ -3,14 +3,18 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use regex::Regex;
 use serde::Serialize;
 
+use crate::config::AppConfig;
 use crate::error::AppError;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct GenerationTraceV1 {
     pub version: u32,
     pub timestamp_ms: u64,
+    /// Id of the run's event stream, for `compare_runs`.
+    pub run_id: Option<String>,
     pub request_hash: String,
     pub intent_tags: Vec<String>,
     pub provider: String,
@@ -40,6 +44,64 @@ pub struct GenerationTraceV1 {
     pub failure_signatures: Vec<String>,
     pub mechanism_candidates: Vec<String>,
     pub mechanism_selected_ids: Vec<String>,
+    /// Mechanisms whose reference code was injected into a part prompt, and
+    /// whether the final code reused it.
+    pub mechanism_code_uptake: Vec<MechanismCodeUptake>,
+    pub model_escalations: Vec<ModelEscalation>,
+    /// Hash of the effective run config, after any pipeline preset overlay.
+    pub config_fingerprint: String,
+    /// The design plan skipped manual approval via the auto-approval gates.
+    pub auto_approved: bool,
+    /// Repairs whose prompt carried a stored repair example, and how many of
+    /// them made the code pass.
+    pub repair_examples_used: u32,
+    pub repair_examples_succeeded: u32,
+    /// Where the first failure of the assembled script was routed; `None`
+    /// when the assembly passed without repair.
+    pub failure_localization: Option<FailureLocalization>,
+    /// Geometric operations estimated from the plan, and counted in the
+    /// final script.
+    pub estimated_operations: Option<u32>,
+    pub actual_operations: Option<u32>,
+}
+
+/// One retry that switched from the default model to `escalation_model`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelEscalation {
+    pub part: String,
+    pub from: String,
+    pub to: String,
+    pub succeeded: bool,
+}
+
+/// An assembled-script failure and where its repair was routed. `part` is
+/// set when the failing line fell inside one part's block (a localization
+/// hit); otherwise the whole script was repaired.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureLocalization {
+    pub part: Option<String>,
+    /// The part was repaired on its own and the spliced assembly then passed
+    /// without a whole-script repair.
+    pub repaired: bool,
+}
+
+/// How much of a mechanism's reference code reappears in the final code.
+#[derive(Debug, Clone, Serialize)]
+pub struct MechanismCodeUptake {
+    pub mechanism_id: String,
+    /// Share of the reference's distinctive identifiers found, 0.0 to 1.0.
+    pub overlap: f32,
+    pub reused: bool,
+}
+
+/// A run-level event that has no generation trace of its own.
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryNoteV1 {
+    pub version: u32,
+    pub timestamp_ms: u64,
+    pub run_id: String,
+    pub kind: String,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -95,10 +157,25 @@ fn telemetry_dir() -> Result<PathBuf, AppError> {
     Ok(base.join("cadai-studio").join("telemetry"))
 }
 
+/// Location of the append-only generation trace log.
+pub fn traces_path() -> Result<PathBuf, AppError> {
+    Ok(telemetry_dir()?.join("generation_traces_v1.jsonl"))
+}
+
+/// Location of the append-only telemetry note log.
+pub fn notes_path() -> Result<PathBuf, AppError> {
+    Ok(telemetry_dir()?.join("notes_v1.jsonl"))
+}
+
+/// Traces are written only with telemetry on and safe mode off.
+pub fn trace_writes_allowed(config: &AppConfig) -> bool {
+    config.telemetry_enabled && config.allows_app_data_writes()
+}
+
 pub fn write_trace(trace: &GenerationTraceV1) -> Result<(), AppError> {
     let dir = telemetry_dir()?;
     fs::create_dir_all(&dir)?;
-    let path = dir.join("generation_traces_v1.jsonl");
+    let path = traces_path()?;
 
     let mut file = OpenOptions::new().create(true).append(true).open(path)?;
 
@@ -107,6 +184,62 @@ pub fn write_trace(trace: &GenerationTraceV1) -> Result<(), AppError> {
     Ok(())
 }
 
+pub fn write_note(note: &TelemetryNoteV1) -> Result<(), AppError> {
+    fs::create_dir_all(telemetry_dir()?)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(notes_path()?)?;
+    writeln!(file, "{}", serde_json::to_string(note)?)?;
+    Ok(())
+}
+
+/// Strip volatile parts of a failure signature (temp paths, line numbers,
+/// ids) so the same root cause produces the same string.
+pub fn normalize_failure_signature(signature: &str) -> String {
+    let temp_path = Regex::new(
+        r#"(?i)(?:[a-z]:)?(?:[\\/][^\s'"\\/]+)*?[\\/](?:tmp|temp|var[\\/]folders)[\\/][^\s'",)]*"#,
+    )
+    .unwrap();
+    let uuid = Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b")
+        .unwrap();
+    let line_word = Regex::new(r"(?i)\bline\s+\d+").unwrap();
+    let line_suffix = Regex::new(r"\.py:\d+(?::\d+)?").unwrap();
+    let spaces = Regex::new(r"\s+").unwrap();
+
+    let text = temp_path.replace_all(signature, "<tmp>");
+    let text = uuid.replace_all(&text, "<id>");
+    let text = line_word.replace_all(&text, "line <n>");
+    let text = line_suffix.replace_all(&text, ".py:<n>");
+    spaces.replace_all(text.trim(), " ").into_owned()
+}
+
+/// Normalize signatures and collapse repeats into one entry with an
+/// occurrence count (`"...(x3)"`), keeping first-seen order.
+pub fn dedup_failure_signatures(signatures: &[String]) -> Vec<String> {
+    let mut unique: Vec<(String, usize)> = Vec::new();
+    for signature in signatures {
+        let normalized = normalize_failure_signature(signature);
+        if normalized.is_empty() {
+            continue;
+        }
+        match unique.iter_mut().find(|(s, _)| *s == normalized) {
+            Some((_, count)) => *count += 1,
+            None => unique.push((normalized, 1)),
+        }
+    }
+    unique
+        .into_iter()
+        .map(|(s, count)| {
+            if count > 1 {
+                format!("{} (x{})", s, count)
+            } else {
+                s
+            }
+        })
+        .collect()
+}
+
 pub fn now_ms() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -125,6 +258,17 @@ mod tests {
         assert_eq!(a, b);
     }
 
+    #[test]
+    fn test_safe_mode_disables_trace_writes() {
+        let mut config = AppConfig::default();
+        assert!(trace_writes_allowed(&config));
+        config.safe_mode = true;
+        assert!(!trace_writes_allowed(&config));
+        config.safe_mode = false;
+        config.telemetry_enabled = false;
+        assert!(!trace_writes_allowed(&config));
+    }
+
     #[test]
     fn test_intent_tags_detected() {
         let tags = infer_intent_tags("Create a wrist tracker enclosure with snap fit");
@@ -132,4 +276,49 @@ mod tests {
         assert!(tags.contains(&"enclosure".to_string()));
         assert!(tags.contains(&"assembly".to_string()));
     }
+
+    #[test]
+    fn test_failure_signatures_normalized_and_deduplicated() {
+        let noisy: Vec<String> = [
+            "semantic: part 'lid' bbox mismatch",
+            "semantic: part 'lid' bbox mismatch",
+            "semantic:  part 'lid' bbox mismatch ",
+            "execution: Traceback File \"/tmp/cadai-studio/run_3f2a/code.py\", line 12, in <module>",
+            "execution: Traceback File \"/tmp/cadai-studio/run_9bc1/code.py\", line 40, in <module>",
+            "execution: error at C:\\Users\\me\\AppData\\Local\\Temp\\cad_1.py:7:3",
+            "fallback_activated:lid",
+            "fallback_activated:base",
+            "run 123e4567-e89b-12d3-a456-426614174000 timed out",
+            "run 00000000-0000-0000-0000-000000000001 timed out",
+            "   ",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let deduped = dedup_failure_signatures(&noisy);
+        assert_eq!(
+            deduped,
+            vec![
+                "semantic: part 'lid' bbox mismatch (x3)",
+                "execution: Traceback File \"<tmp>\", line <n>, in <module> (x2)",
+                "execution: error at <tmp>",
+                "fallback_activated:lid",
+                "fallback_activated:base",
+                "run <id> timed out (x2)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_keeps_stable_signatures() {
+        assert_eq!(
+            normalize_failure_signature("multipart contract: missing part_lid"),
+            "multipart contract: missing part_lid"
+        );
+        assert_eq!(
+            normalize_failure_signature("SyntaxError at code.py:14"),
+            "SyntaxError at code.py:<n>"
+        );
+    }
 }
@@ -0,0 +1,240 @@
+//! Real helical threads for bolts, studs and threaded rods. Models rarely get
+//! a helix sweep right from scratch, so the prompt offers a predefined
+//! `metric_thread` helper and the executor prepends its definition to any
+//! script that calls it.
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::agent::features::STANDARD_DIAMETER_TOLERANCE;
+use crate::config::AppConfig;
+
+/// Heading of the prompt section announcing the thread helper.
+pub const THREAD_HELPER_HEADING: &str = "## Metric Thread Helper";
+pub const THREAD_HELPER_NAME: &str = "metric_thread";
+/// First line of the prepended helper, so it is never added twice.
+const HELPER_MARKER: &str = "# auto-postprocess: metric thread helper";
+
+/// ISO metric coarse thread in mm: pitch from ISO 261, external root
+/// (minor) diameter d3 = d - 1.226869 P from ISO 724.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThreadSize {
+    pub name: &'static str,
+    pub major: f64,
+    pub pitch: f64,
+    pub minor: f64,
+}
+
+// M4's root diameter happens to read like pi.
+#[allow(clippy::approx_constant)]
+pub const METRIC_THREADS: &[ThreadSize] = &[
+    ThreadSize {
+        name: "M3",
+        major: 3.0,
+        pitch: 0.5,
+        minor: 2.387,
+    },
+    ThreadSize {
+        name: "M4",
+        major: 4.0,
+        pitch: 0.7,
+        minor: 3.141,
+    },
+    ThreadSize {
+        name: "M5",
+        major: 5.0,
+        pitch: 0.8,
+        minor: 4.019,
+    },
+    ThreadSize {
+        name: "M6",
+        major: 6.0,
+        pitch: 1.0,
+        minor: 4.773,
+    },
+    ThreadSize {
+        name: "M8",
+        major: 8.0,
+        pitch: 1.25,
+        minor: 6.466,
+    },
+    ThreadSize {
+        name: "M10",
+        major: 10.0,
+        pitch: 1.5,
+        minor: 8.160,
+    },
+    ThreadSize {
+        name: "M12",
+        major: 12.0,
+        pitch: 1.75,
+        minor: 9.853,
+    },
+];
+
+/// Coarse thread for a nominal diameter in mm, if it is a standard size.
+pub fn metric_thread_size(diameter: f64) -> Option<&'static ThreadSize> {
+    METRIC_THREADS
+        .iter()
+        .find(|t| (t.major - diameter).abs() <= STANDARD_DIAMETER_TOLERANCE)
+}
+
+/// The request asks for something with an external thread.
+pub fn requests_threads(text: &str) -> bool {
+    let thread_re =
+        Regex::new(r"(?i)\b(threads?|threaded|bolts?|screws?|studs?|rods?|M(?:3|4|5|6|8|10|12))\b")
+            .unwrap();
+    thread_re.is_match(text)
+}
+
+/// Prompt directive offering the helper, when enabled and `text` asks for
+/// threaded parts.
+pub fn thread_helper_section(text: &str, config: &AppConfig) -> Option<String> {
+    if !config.enable_thread_helper || !requests_threads(text) {
+        return None;
+    }
+    let sizes: Vec<String> = METRIC_THREADS
+        .iter()
+        .map(|t| format!("{} x {}", t.name, t.pitch))
+        .collect();
+    Some(format!(
+        "{}\n\
+         A `{}(diameter, pitch=None, length=10.0)` helper is predefined when your code runs. \
+         Call it for every external thread (bolts, studs, threaded rods) instead of modeling \
+         threads yourself, and do NOT define it. It returns one solid: a real helical ISO \
+         thread along +Z from z=0 to z=length, with its core. `pitch` defaults to the coarse \
+         pitch ({}). Position it like any other solid and union it with the shank or head, \
+         e.g. `result = head + Pos(0, 0, -30) * {}(8, length=30)`. For tapped holes keep \
+         using tap-drill holes.\n",
+        THREAD_HELPER_HEADING,
+        THREAD_HELPER_NAME,
+        sizes.join(", "),
+        THREAD_HELPER_NAME,
+    ))
+}
+
+/// Build123d source of `metric_thread`, with the coarse-pitch table baked in.
+pub fn helper_source() -> String {
+    let table: Vec<String> = METRIC_THREADS
+        .iter()
+        .map(|t| format!("{:?}: ({:?}, {:?})", t.major, t.pitch, t.minor))
+        .collect();
+    format!(
+        "{marker}\n\
+         from build123d import *\n\
+         \n\
+         _METRIC_COARSE = {{{table}}}\n\
+         \n\
+         def {name}(diameter, pitch=None, length=10.0):\n    \
+             \"\"\"ISO metric external thread along +Z from z=0 to z=length.\"\"\"\n    \
+             standard = _METRIC_COARSE.get(round(float(diameter), 2))\n    \
+             if pitch is None:\n        \
+                 if standard is None:\n            \
+                     raise ValueError(f\"{name}: no coarse pitch for M{{diameter:g}}, pass pitch=\")\n        \
+                 pitch = standard[0]\n    \
+             if standard is not None and abs(standard[0] - pitch) < 1e-9:\n        \
+                 root = standard[1] / 2.0\n    \
+             else:\n        \
+                 root = (diameter - 1.226869 * pitch) / 2.0\n    \
+             crest = diameter / 2.0\n    \
+             # 60 degree tooth, sunk slightly into the core so the union fuses.\n    \
+             inner = root - 0.05 * pitch\n    \
+             half_crest = pitch / 16.0\n    \
+             half_inner = half_crest + (crest - inner) * 0.57735\n    \
+             tooth = Face(Wire.make_polygon([\n        \
+                 Vector(inner, 0, -pitch - half_inner),\n        \
+                 Vector(crest, 0, -pitch - half_crest),\n        \
+                 Vector(crest, 0, -pitch + half_crest),\n        \
+                 Vector(inner, 0, -pitch + half_inner),\n    \
+             ], close=True))\n    \
+             path = Helix(pitch, length + 2 * pitch, inner, center=(0, 0, -pitch))\n    \
+             thread = sweep(tooth, path=path, is_frenet=True)\n    \
+             align = (Align.CENTER, Align.CENTER, Align.MIN)\n    \
+             core = Cylinder(root, length, align=align)\n    \
+             return (core + thread) & Cylinder(crest, length, align=align)\n",
+        marker = HELPER_MARKER,
+        table = table.join(", "),
+        name = THREAD_HELPER_NAME,
+    )
+}
+
+/// Prepend the helper to `code` when it is enabled, called, and not
+/// already defined.
+pub fn prepend_helper(code: &str, config: &AppConfig) -> String {
+    let call_re = Regex::new(&format!(r"\b{}\s*\(", THREAD_HELPER_NAME)).unwrap();
+    let defines =
+        code.contains(HELPER_MARKER) || code.contains(&format!("def {}(", THREAD_HELPER_NAME));
+    if !config.enable_thread_helper || defines || !call_re.is_match(code) {
+        return code.to_string();
+    }
+    format!("{}\n{}", helper_source(), code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metric_thread_table_m3_to_m12() {
+        let expected = [
+            (3.0, 0.5),
+            (4.0, 0.7),
+            (5.0, 0.8),
+            (6.0, 1.0),
+            (8.0, 1.25),
+            (10.0, 1.5),
+            (12.0, 1.75),
+        ];
+        for (diameter, pitch) in expected {
+            let size = metric_thread_size(diameter).unwrap();
+            assert_eq!(size.pitch, pitch, "M{}", diameter);
+            let d3 = diameter - 1.226869 * pitch;
+            assert!((size.minor - d3).abs() < 0.001, "M{}", diameter);
+            assert!(size.minor < size.major);
+        }
+        assert_eq!(metric_thread_size(8.02).unwrap().name, "M8");
+        assert!(metric_thread_size(7.0).is_none());
+        assert!(metric_thread_size(16.0).is_none());
+        assert!(helper_source().contains("8.0: (1.25, 6.466)"));
+    }
+
+    #[test]
+    fn test_helper_prepended_only_when_enabled_and_called() {
+        let code = "from build123d import *\nresult = metric_thread(8, length=30)\n";
+        let config = AppConfig {
+            enable_thread_helper: true,
+            ..Default::default()
+        };
+        let prepared = prepend_helper(code, &config);
+        assert!(prepared.starts_with(HELPER_MARKER));
+        assert!(prepared.ends_with(code));
+        assert!(prepared.contains("def metric_thread(diameter, pitch=None, length=10.0):"));
+        // Idempotent: repairs and re-validation see the helper only once.
+        assert_eq!(prepend_helper(&prepared, &config), prepared);
+
+        let plain = "from build123d import *\nresult = Cylinder(4, 30)\n";
+        assert_eq!(prepend_helper(plain, &config), plain);
+        let disabled = AppConfig {
+            enable_thread_helper: false,
+            ..Default::default()
+        };
+        assert_eq!(prepend_helper(code, &disabled), code);
+    }
+
+    #[test]
+    fn test_prompt_section_follows_request_and_setting() {
+        let config = AppConfig {
+            enable_thread_helper: true,
+            ..Default::default()
+        };
+        let section = thread_helper_section("An M8 bolt, 30mm long", &config).unwrap();
+        assert!(section.starts_with(THREAD_HELPER_HEADING));
+        assert!(section.contains("M8 x 1.25"));
+        assert!(thread_helper_section("A plain box with a lid", &config).is_none());
+        let disabled = AppConfig {
+            enable_thread_helper: false,
+            ..Default::default()
+        };
+        assert!(thread_helper_section("An M8 bolt", &disabled).is_none());
+    }
+}
@@ -0,0 +1,316 @@
+use serde_json::Value;
+
+/// Streaming and binary payload events that add nothing to a readable record.
+const SKIPPED_KINDS: &[&str] = &[
+    "SingleDelta",
+    "SingleDone",
+    "PartDelta",
+    "PartStlReady",
+    "PartCodeExtracted",
+    "FinalCode",
+    "IterativeStepComplete",
+    "CodeDiff",
+    "RetrievalStatus",
+    "DesignPlan",
+    "RunStarted",
+];
+
+fn str_field<'a>(event: &'a Value, key: &str) -> &'a str {
+    event[key].as_str().unwrap_or("")
+}
+
+fn string_list(event: &Value, key: &str) -> Vec<String> {
+    event[key]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn part_label(event: &Value) -> String {
+    match event["part_name"].as_str() {
+        Some(name) if !name.is_empty() => format!("**{}**: ", name),
+        _ => String::new(),
+    }
+}
+
+fn with_findings(line: String, findings: Vec<String>) -> String {
+    let mut out = line;
+    for finding in findings {
+        out.push_str(&format!("\n  - {}", finding));
+    }
+    out
+}
+
+/// One Markdown bullet for a `MultiPartEvent`, or `None` when the event is
+/// noise in a shared transcript (streaming deltas, STL payloads).
+fn format_event(event: &Value) -> Option<String> {
+    let kind = event["kind"].as_str().unwrap_or("Unknown");
+    if SKIPPED_KINDS.contains(&kind) {
+        return None;
+    }
+    let line = match kind {
+        "PlanValidation" => with_findings(
+            format!(
+                "Plan validation: {} (risk score {})",
+                if event["is_valid"].as_bool().unwrap_or(false) {
+                    "valid"
+                } else {
+                    "rejected"
+                },
+                event["risk_score"].as_u64().unwrap_or(0)
+            ),
+            string_list(event, "warnings"),
+        ),
+        "ConfidenceAssessment" => format!(
+            "Confidence: {} ({}) — {}",
+            str_field(event, "level"),
+            event["score"].as_u64().unwrap_or(0),
+            str_field(event, "message")
+        ),
+        "PlanResult" => {
+            let plan = &event["plan"];
+            let parts: Vec<&str> = plan["parts"]
+                .as_array()
+                .map(|p| p.iter().filter_map(|part| part["name"].as_str()).collect())
+                .unwrap_or_default();
+            if parts.is_empty() {
+                format!("Plan: {} mode", plan["mode"].as_str().unwrap_or("single"))
+            } else {
+                format!(
+                    "Plan: {} mode with {} parts ({})",
+                    plan["mode"].as_str().unwrap_or("multi"),
+                    parts.len(),
+                    parts.join(", ")
+                )
+            }
+        }
+        "PartComplete" => match event["error"].as_str() {
+            Some(error) if !event["success"].as_bool().unwrap_or(false) => {
+                format!("{}generation failed: {}", part_label(event), error)
+            }
+            _ => format!("{}generated", part_label(event)),
+        },
+        "PartStlFailed" => format!(
+            "{}STL failed: {}",
+            part_label(event),
+            str_field(event, "error")
+        ),
+        "PlanStatus" | "AssemblyStatus" | "ReviewStatus" | "PostGeometryValidationWarning" => {
+            str_field(event, "message").to_string()
+        }
+        "Warning" => format!(
+            "Warning (`{}`): {}",
+            str_field(event, "code"),
+            str_field(event, "message")
+        ),
+        "ReviewComplete" => format!(
+            "Review {}: {}",
+            if event["was_modified"].as_bool().unwrap_or(false) {
+                "modified the code"
+            } else if event["ai_reviewed"].as_bool() == Some(false) {
+                "skipped"
+            } else {
+                "passed"
+            },
+            str_field(event, "explanation")
+        ),
+        "ValidationAttempt" => format!(
+            "Validation attempt {}/{}: {}",
+            event["attempt"].as_u64().unwrap_or(0),
+            event["max_attempts"].as_u64().unwrap_or(0),
+            str_field(event, "message")
+        ),
+        "ValidationSuccess" => format!(
+            "Validation succeeded on attempt {}",
+            event["attempt"].as_u64().unwrap_or(0)
+        ),
+        "ValidationFailed" => format!(
+            "Validation failed on attempt {} ({}){}{}: {}",
+            event["attempt"].as_u64().unwrap_or(0),
+            str_field(event, "error_category"),
+            event["localized_part"]
+                .as_str()
+                .map(|part| format!(" in part '{}'", part))
+                .unwrap_or_default(),
+            if event["will_retry"].as_bool().unwrap_or(false) {
+                ", retrying"
+            } else {
+                ""
+            },
+            str_field(event, "error_message")
+        ),
+        "ValidationAttemptDiff" => format!(
+            "Repair after attempt {}: +{}/-{} lines",
+            event["attempt"].as_u64().unwrap_or(0),
+            event["additions"].as_u64().unwrap_or(0),
+            event["deletions"].as_u64().unwrap_or(0)
+        ),
+        "ValidationFixExplanation" => str_field(event, "explanation").to_string(),
+        "StaticValidationReport" | "SemanticValidationReport" => with_findings(
+            format!(
+                "{}{} validation {}",
+                part_label(event),
+                if kind == "StaticValidationReport" {
+                    "Static"
+                } else {
+                    "Semantic"
+                },
+                if event["passed"].as_bool().unwrap_or(false) {
+                    "passed"
+                } else {
+                    "found issues"
+                }
+            ),
+            string_list(event, "findings"),
+        ),
+        "ModelEscalation" => format!("{}{}", part_label(event), str_field(event, "message")),
+        "ModificationDetected" => format!("Modification: {}", str_field(event, "intent_summary")),
+        "ConsensusWinner" => format!(
+            "Consensus winner: {} (score {}) — {}",
+            str_field(event, "label"),
+            event["score"].as_u64().unwrap_or(0),
+            str_field(event, "reason")
+        ),
+        "ClarificationNeeded" => with_findings(
+            "Clarification needed:".to_string(),
+            string_list(event, "questions"),
+        ),
+        "IterativeStepStarted" => format!(
+            "Step {}: {}",
+            event["step_index"].as_u64().unwrap_or(0) + 1,
+            str_field(event, "step_name")
+        ),
+        "IterativeStepSkipped" => format!(
+            "Step {} skipped ({}): {}",
+            event["step_index"].as_u64().unwrap_or(0) + 1,
+            str_field(event, "name"),
+            str_field(event, "error")
+        ),
+        "TokenUsage" => format!(
+            "Tokens ({}): {} in / {} out",
+            str_field(event, "phase"),
+            event["input_tokens"].as_u64().unwrap_or(0),
+            event["output_tokens"].as_u64().unwrap_or(0)
+        ),
+        "Done" => match event["error"].as_str() {
+            Some(error) => format!("Finished with error: {}", error),
+            None => format!(
+                "Finished ({})",
+                if event["validated"].as_bool().unwrap_or(false) {
+                    "validated"
+                } else {
+                    "not validated"
+                }
+            ),
+        },
+        other => other.to_string(),
+    };
+    if line.trim().is_empty() {
+        return None;
+    }
+    Some(format!("- {}", line))
+}
+
+/// Render a generation session as a shareable Markdown document: request,
+/// design plan, event timeline and final code.
+pub fn format_transcript(
+    user_request: Option<&str>,
+    plan_text: &str,
+    events: &[Value],
+    final_code: &str,
+) -> String {
+    let mut out = String::from("# CAD AI Studio transcript\n");
+
+    if let Some(request) = user_request.map(str::trim).filter(|r| !r.is_empty()) {
+        out.push_str("\n## Request\n\n");
+        for line in request.lines() {
+            out.push_str(&format!("> {}\n", line));
+        }
+    }
+
+    if !plan_text.trim().is_empty() {
+        out.push_str("\n## Design plan\n\n");
+        out.push_str(plan_text.trim());
+        out.push('\n');
+    }
+
+    let timeline: Vec<String> = events.iter().filter_map(format_event).collect();
+    if !timeline.is_empty() {
+        out.push_str("\n## Generation events\n\n");
+        out.push_str(&timeline.join("\n"));
+        out.push('\n');
+    }
+
+    out.push_str("\n## Final code\n\n```python\n");
+    out.push_str(final_code.trim_end());
+    out.push_str("\n```\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_events() -> Vec<Value> {
+        vec![
+            json!({"kind": "PlanStatus", "message": "Planning parts..."}),
+            json!({"kind": "PlanResult", "plan": {"mode": "multi", "parts": [
+                {"name": "base", "description": "", "position": [0, 0, 0]},
+                {"name": "lid", "description": "", "position": [0, 0, 20]}
+            ]}}),
+            json!({"kind": "PartDelta", "part_index": 0, "part_name": "base", "delta": "from build"}),
+            json!({"kind": "PartComplete", "part_index": 1, "part_name": "lid", "success": false, "error": "timeout"}),
+            json!({"kind": "ValidationFailed", "attempt": 1, "error_category": "Geometry", "error_message": "fillet failed", "will_retry": true}),
+            json!({"kind": "Warning", "code": "embeddings_unavailable", "message": "Using lexical retrieval."}),
+            json!({"kind": "Done", "success": true, "error": null, "validated": true}),
+        ]
+    }
+
+    #[test]
+    fn test_transcript_sections_in_order() {
+        let md = format_transcript(
+            Some("A box with a lid"),
+            "Base 80x60x20 with lid.",
+            &sample_events(),
+            "result = Box(1, 1, 1)\n",
+        );
+        let request = md.find("## Request").unwrap();
+        let plan = md.find("## Design plan").unwrap();
+        let events = md.find("## Generation events").unwrap();
+        let code = md.find("## Final code").unwrap();
+        assert!(request < plan && plan < events && events < code);
+        assert!(md.contains("> A box with a lid"));
+        assert!(md.ends_with("```python\nresult = Box(1, 1, 1)\n```\n"));
+    }
+
+    #[test]
+    fn test_transcript_formats_key_events_and_skips_deltas() {
+        let md = format_transcript(None, "", &sample_events(), "");
+        assert!(md.contains("- Planning parts..."));
+        assert!(md.contains("- Plan: multi mode with 2 parts (base, lid)"));
+        assert!(md.contains("- **lid**: generation failed: timeout"));
+        assert!(md.contains("- Validation failed on attempt 1 (Geometry), retrying: fillet failed"));
+        assert!(md.contains("- Warning (`embeddings_unavailable`): Using lexical retrieval."));
+        assert!(md.contains("- Finished (validated)"));
+        assert!(!md.contains("from build"));
+        assert!(!md.contains("## Request"));
+        assert!(!md.contains("## Design plan"));
+    }
+
+    #[test]
+    fn test_transcript_lists_findings_under_event() {
+        let events = vec![json!({
+            "kind": "StaticValidationReport",
+            "passed": false,
+            "findings": ["missing import", "no result"]
+        })];
+        let md = format_transcript(None, "", &events, "");
+        assert!(md.contains("- Static validation found issues\n  - missing import\n  - no result"));
+    }
+}
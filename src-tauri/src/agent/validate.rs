@@ -553,6 +553,90 @@ fn match_anti_pattern(error: &StructuredError, code: Option<&str>) -> Option<Str
     }
 }
 
+/// Retry-ladder stage recorded when a repeated kernel failure switches the
+/// retry to replacing the failing operation outright. Stages 1-4 are the
+/// automatic repairs (4 being the sweep guard), so this one comes after them.
+pub const SIMPLIFY_OPERATION_STAGE: u32 = 5;
+
+/// Name of a Build123d API the code used that the installed version does not
+/// provide (removed or renamed between releases), if the error looks like one:
+/// a missing attribute/import, an unknown keyword argument, or an undefined
+/// capitalized name pulled in by `from build123d import *`.
+pub fn missing_api_name(error: &StructuredError) -> Option<String> {
+    let pattern = match error.error_type.as_str() {
+        "AttributeError" => r"has no attribute '(\w+)'",
+        "ImportError" => r"cannot import name '(\w+)'",
+        "TypeError" => r"unexpected keyword argument '(\w+)'",
+        "NameError" => r"name '([A-Z]\w*)' is not defined",
+        _ => return None,
+    };
+    Regex::new(pattern)
+        .ok()?
+        .captures(&error.message)
+        .map(|cap| cap[1].to_string())
+}
+
+/// Repair hint for [`missing_api_name`] errors naming the detected version.
+pub fn version_api_hint(api_name: &str, installed_version: &str) -> String {
+    format!(
+        "`{}` is not available in the installed Build123d {}; it was likely removed or \
+         renamed in this version. Rewrite that step with the Build123d {} API instead of \
+         retrying the same call.",
+        api_name, installed_version, installed_version
+    )
+}
+
+/// True when two consecutive attempts hit the same geometry-kernel/topology
+/// error in the same operation, i.e. prompt tweaks are not converging.
+pub fn is_repeated_kernel_failure(previous: &StructuredError, current: &StructuredError) -> bool {
+    let kernel_error = matches!(
+        current.category,
+        ErrorCategory::GeometryKernel | ErrorCategory::Topology(_)
+    );
+    kernel_error
+        && previous.category == current.category
+        && current.failing_operation.is_some()
+        && previous.failing_operation == current.failing_operation
+}
+
+/// Aggressive strategy for a repeated kernel failure: stop repairing the
+/// operation and replace it with its simplest robust primitive equivalent.
+pub fn simplify_operation_strategy(error: &StructuredError) -> RetryStrategy {
+    let op = error.failing_operation.as_deref().unwrap_or("the failing operation");
+    let replacement = match op {
+        "shell" => "Replace shell() with explicit hollowing: build the outer solid, build an \
+             inner solid smaller by the wall thickness, and subtract it (outer.cut(inner) or \
+             `outer - inner`).",
+        "fillet" | "chamfer" => "Remove every fillet() and chamfer() call. Sharp edges are \
+             acceptable; the part must render first.",
+        "loft" => "Replace loft() with stacked extrude() sections or a Cone/Cylinder \
+             primitive that approximates the transition.",
+        "sweep" => "Replace sweep() with straight extrude() segments (or Cylinder primitives) \
+             placed along the path and joined with union.",
+        "revolve" => "Replace revolve() with stacked Cylinder/Cone primitives aligned on the \
+             axis.",
+        "cut" | "fuse" | "union" | "intersect" | "split" => "Rebuild the boolean with simple \
+             tool bodies (Box, Cylinder) that extend at least 1mm past every target face, and \
+             perform one boolean at a time.",
+        "offset" | "thicken" => "Replace the offset with an explicitly dimensioned second \
+             primitive and a boolean cut or union.",
+        _ => "Replace the failing operation with basic primitives (Box, Cylinder, Sphere, \
+             Cone) combined with boolean operations.",
+    };
+
+    RetryStrategy {
+        fix_instruction: format!(
+            "The {op}() operation failed the same way on two consecutive attempts; repairing \
+             it again will not work. Do NOT call {op}() at all. {replacement} Keep every other \
+             feature and dimension unchanged.",
+            op = op,
+            replacement = replacement
+        ),
+        forbidden_operations: error.failing_operation.iter().cloned().collect(),
+        matching_anti_pattern: None,
+    }
+}
+
 /// Get a targeted retry strategy based on the classified error and attempt number.
 ///
 /// - Attempt 1: Category-specific targeted fix
@@ -566,7 +650,8 @@ pub fn get_retry_strategy(
     attempt: u32,
     code: Option<&str>,
 ) -> RetryStrategy {
-    let anti_pattern = match_anti_pattern(error, code);
+    let anti_pattern = match_anti_pattern(error, code)
+        .or_else(|| crate::agent::anti_pattern_mining::match_active(error));
 
     // Attempt 3+: nuclear option — same for all categories.
     if attempt >= 3 {
@@ -767,6 +852,56 @@ pub fn get_retry_strategy(
     }
 }
 
+/// Plain-language name of an error category, for retry explanations.
+pub fn category_label(category: &ErrorCategory) -> &'static str {
+    match category {
+        ErrorCategory::Syntax => "a Python syntax error",
+        ErrorCategory::GeometryKernel => "a geometry kernel failure",
+        ErrorCategory::Topology(TopologySubKind::FilletFailure) => "a fillet/chamfer failure",
+        ErrorCategory::Topology(TopologySubKind::ShellFailure) => "a shell (hollowing) failure",
+        ErrorCategory::Topology(TopologySubKind::BooleanFailure) => "a boolean operation failure",
+        ErrorCategory::Topology(TopologySubKind::LoftFailure) => "a loft failure",
+        ErrorCategory::Topology(TopologySubKind::SweepFailure) => "a sweep failure",
+        ErrorCategory::Topology(TopologySubKind::RevolveFailure) => "a revolve failure",
+        ErrorCategory::Topology(TopologySubKind::MissingSolid) => {
+            "an operation with no solid to work on"
+        }
+        ErrorCategory::Topology(TopologySubKind::DisconnectedSolids) => {
+            "a body split into disconnected solids"
+        }
+        ErrorCategory::Topology(TopologySubKind::General) => "a topology failure",
+        ErrorCategory::ApiMisuse => "a build123d API usage error",
+        ErrorCategory::ImportRuntime => "an import or name error",
+        ErrorCategory::Unknown => "an unclassified error",
+    }
+}
+
+/// Short note on why `attempt` failed and what the retry changes, for users
+/// following along. Built from the strategy's fix instruction: sentences that
+/// only restate the failure are dropped and at most two are kept.
+pub fn explain_retry(error: &StructuredError, strategy: &RetryStrategy, attempt: u32) -> String {
+    let fix: Vec<String> = strategy
+        .fix_instruction
+        .split(". ")
+        .map(|s| s.trim().trim_end_matches('.').to_string())
+        .filter(|s| !s.is_empty() && !s.ends_with(" failed"))
+        .take(2)
+        .collect();
+    let mut explanation = format!(
+        "Attempt {} hit {}. Fix: {}.",
+        attempt,
+        category_label(&error.category),
+        fix.join(". ")
+    );
+    if !strategy.forbidden_operations.is_empty() {
+        explanation.push_str(&format!(
+            " The next attempt avoids {}.",
+            strategy.forbidden_operations.join(", ")
+        ));
+    }
+    explanation
+}
+
 /// Check if code uses a risky blanket fillet pattern on complex geometry.
 ///
 /// Returns a warning message if the code contains `fillet()` with `.edges()`
@@ -1319,6 +1454,28 @@ ValueError: Cannot sweep along path: expected Wire, got Edge"#;
         );
     }
 
+    #[test]
+    fn test_explain_retry_shell_failure() {
+        let err = make_error(
+            ErrorCategory::Topology(TopologySubKind::ShellFailure),
+            "Shell offset not done",
+            Some("shell"),
+        );
+        let strategy = get_retry_strategy(&err, 1, None);
+        assert_eq!(
+            explain_retry(&err, &strategy, 1),
+            "Attempt 1 hit a shell (hollowing) failure. Fix: Replace .shell() with manual \
+             hollowing: create a slightly smaller inner solid and use .cut() to subtract it. \
+             Or apply shell BEFORE boolean operations on the simple base shape. The next \
+             attempt avoids shell."
+        );
+
+        let repeated = simplify_operation_strategy(&err);
+        let explanation = explain_retry(&err, &repeated, 2);
+        assert!(explanation.starts_with("Attempt 2 hit a shell (hollowing) failure. Fix: "));
+        assert!(explanation.contains("Do NOT call shell() at all"));
+    }
+
     #[test]
     fn test_strategy_shell_attempt1() {
         let err = make_error(
@@ -1647,4 +1804,23 @@ result = body
             Some("Through-cut splitting body")
         );
     }
+
+    #[test]
+    fn test_missing_api_name_detects_removed_or_renamed_calls() {
+        let stderr = "Traceback (most recent call last):\n  File \"script.py\", line 3, in <module>\nAttributeError: 'Part' object has no attribute 'fillet_all'";
+        let err = parse_traceback(stderr);
+        assert_eq!(missing_api_name(&err).as_deref(), Some("fillet_all"));
+        let hint = version_api_hint("fillet_all", "0.9.1");
+        assert!(hint.contains("Build123d 0.9.1"));
+
+        let err = parse_traceback("TypeError: Box.__init__() got an unexpected keyword argument 'centered'");
+        assert_eq!(missing_api_name(&err).as_deref(), Some("centered"));
+
+        let err = parse_traceback("NameError: name 'SlotCenterToCenter' is not defined");
+        assert_eq!(missing_api_name(&err).as_deref(), Some("SlotCenterToCenter"));
+
+        // Lowercase undefined names are ordinary typos, not API drift.
+        let err = parse_traceback("NameError: name 'widht' is not defined");
+        assert_eq!(missing_api_name(&err), None);
+    }
 }
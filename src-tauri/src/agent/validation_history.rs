@@ -0,0 +1,402 @@
+//! Per-attempt record of the validation retry chain: the code each attempt
+//! ran, how it failed, what the repair asked for, and the diff to the code
+//! the next attempt ran. Chains are kept per run (and per part for part
+//! acceptance) in the `RunStore`, for `get_validation_history` and exported
+//! transcripts.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::agent::modify::{compute_diff, DiffLine};
+use crate::agent::run_state::RunStore;
+use crate::agent::telemetry;
+use crate::artifacts;
+use crate::config::AppConfig;
+
+/// Attempts kept per chain: the first one and the most recent ones.
+pub const MAX_STORED_ATTEMPTS: usize = 12;
+/// Longer attempt scripts are stored truncated; the full script is kept in
+/// the run's artifact directory under its SHA-256.
+pub const MAX_INLINE_CODE_BYTES: usize = 64 * 1024;
+/// Chains kept in memory; the oldest one is dropped first.
+pub const MAX_STORED_CHAINS: usize = 64;
+
+/// One pass through the validation loop.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttemptRecord {
+    pub attempt: u32,
+    /// The code this attempt ran; only its start when `code_truncated`.
+    pub code: String,
+    pub code_sha256: String,
+    pub code_truncated: bool,
+    pub error_category: Option<String>,
+    pub error_message: Option<String>,
+    pub error_line: Option<u32>,
+    /// Repair prompt sent to the model, or a note for automatic repairs.
+    pub repair_instruction: Option<String>,
+    /// Line diff to the next attempt's code; empty for the last attempt.
+    /// Oversized diffs keep their changed lines only.
+    pub diff_to_next: Vec<DiffLine>,
+}
+
+/// Builds the records of one `validate_and_retry` call.
+#[derive(Debug, Clone, Default)]
+pub struct AttemptHistory {
+    records: Vec<AttemptRecord>,
+}
+
+impl AttemptHistory {
+    /// Start recording `attempt`, which runs `code`.
+    pub fn begin(&mut self, attempt: u32, code: &str) {
+        self.records.push(AttemptRecord {
+            attempt,
+            code: code.to_string(),
+            code_sha256: code_sha256(code),
+            code_truncated: false,
+            error_category: None,
+            error_message: None,
+            error_line: None,
+            repair_instruction: None,
+            diff_to_next: Vec::new(),
+        });
+    }
+
+    pub fn fail(&mut self, category: &str, message: &str, line: Option<u32>) {
+        if let Some(record) = self.records.last_mut() {
+            record.error_category = Some(category.to_string());
+            record.error_message = Some(message.to_string());
+            record.error_line = line;
+        }
+    }
+
+    pub fn repair(&mut self, instruction: &str) {
+        if let Some(record) = self.records.last_mut() {
+            record.repair_instruction = Some(instruction.to_string());
+        }
+    }
+
+    /// The current attempt was repaired into `next_code`. Keeps the diff and
+    /// returns its added and deleted line counts.
+    pub fn advance(&mut self, next_code: &str) -> (usize, usize) {
+        let Some(record) = self.records.last_mut() else {
+            return (0, 0);
+        };
+        record.diff_to_next = compute_diff(&record.code, next_code);
+        let count = |tag: &str| record.diff_to_next.iter().filter(|l| l.tag == tag).count();
+        (count("insert"), count("delete"))
+    }
+
+    pub fn records(&self) -> Vec<AttemptRecord> {
+        self.records.clone()
+    }
+}
+
+fn code_sha256(code: &str) -> String {
+    format!("{:x}", Sha256::digest(code.as_bytes()))
+}
+
+/// Recorded chain of one run, for the whole script (`part_name` unset) or
+/// for one part's acceptance.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationChain {
+    pub run_id: String,
+    pub part_name: Option<String>,
+    pub recorded_at_ms: u64,
+    pub attempts: Vec<AttemptRecord>,
+    /// Attempts dropped from the middle of the chain by [`MAX_STORED_ATTEMPTS`].
+    pub omitted_attempts: usize,
+}
+
+/// Apply the storage bounds. `save_full` receives the SHA-256 and full code
+/// of every truncated attempt.
+fn bounded(
+    attempts: &[AttemptRecord],
+    save_full: impl Fn(&str, &str),
+) -> (Vec<AttemptRecord>, usize) {
+    let omitted = attempts.len().saturating_sub(MAX_STORED_ATTEMPTS);
+    let kept = attempts
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i == 0 || *i > omitted)
+        .map(|(_, record)| {
+            let mut record = record.clone();
+            if record.code.len() > MAX_INLINE_CODE_BYTES {
+                save_full(&record.code_sha256, &record.code);
+                let mut end = MAX_INLINE_CODE_BYTES;
+                while !record.code.is_char_boundary(end) {
+                    end -= 1;
+                }
+                record.code.truncate(end);
+                record.code_truncated = true;
+            }
+            let diff_bytes: usize = record.diff_to_next.iter().map(|l| l.text.len()).sum();
+            if diff_bytes > MAX_INLINE_CODE_BYTES {
+                record.diff_to_next.retain(|l| l.tag != "equal");
+            }
+            record
+        })
+        .collect();
+    (kept, omitted)
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationHistoryStore {
+    chains: VecDeque<ValidationChain>,
+}
+
+impl ValidationHistoryStore {
+    /// Keep `chain`, replacing an earlier chain of the same run and part.
+    pub fn insert(&mut self, chain: ValidationChain) {
+        self.chains
+            .retain(|c| c.run_id != chain.run_id || c.part_name != chain.part_name);
+        self.chains.push_back(chain);
+        while self.chains.len() > MAX_STORED_CHAINS {
+            self.chains.pop_front();
+        }
+    }
+
+    /// Chains of `run_id`, oldest first; only `part_name`'s when given.
+    pub fn chains(&self, run_id: &str, part_name: Option<&str>) -> Vec<ValidationChain> {
+        self.chains
+            .iter()
+            .filter(|c| c.run_id == run_id)
+            .filter(|c| part_name.is_none() || c.part_name.as_deref() == part_name)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Store the attempts of a finished validation as the chain of `run_id` and
+/// `part_name`. Full scripts of truncated attempts go to the run's artifact
+/// directory unless safe mode forbids app data writes.
+pub fn record(
+    run_store: &Mutex<RunStore>,
+    config: &AppConfig,
+    run_id: &str,
+    part_name: Option<&str>,
+    attempts: &[AttemptRecord],
+) {
+    if attempts.is_empty() {
+        return;
+    }
+    let (attempts, omitted_attempts) = bounded(attempts, |sha256, code| {
+        artifacts::save_attempt_code(config, run_id, sha256, code)
+    });
+    if let Ok(mut store) = run_store.lock() {
+        store.record_validation_chain(ValidationChain {
+            run_id: run_id.to_string(),
+            part_name: part_name.map(str::to_string),
+            recorded_at_ms: telemetry::now_ms(),
+            attempts,
+            omitted_attempts,
+        });
+    }
+}
+
+/// Markdown section listing every chain's attempts, errors and diffs.
+pub fn transcript_section(chains: &[ValidationChain]) -> String {
+    let mut out = String::from("\n## Validation history\n");
+    for chain in chains {
+        out.push_str(&format!(
+            "\n### {}\n",
+            chain
+                .part_name
+                .as_deref()
+                .map(|name| format!("Part '{}'", name))
+                .unwrap_or_else(|| "Final script".to_string())
+        ));
+        if chain.omitted_attempts > 0 {
+            out.push_str(&format!(
+                "\n_{} intermediate attempt(s) omitted._\n",
+                chain.omitted_attempts
+            ));
+        }
+        for record in &chain.attempts {
+            out.push_str(&format!(
+                "\n#### Attempt {}{}\n\n",
+                record.attempt,
+                match (&record.error_category, record.error_line) {
+                    (Some(category), Some(line)) => format!(" — {} at line {}", category, line),
+                    (Some(category), None) => format!(" — {}", category),
+                    _ => " — passed".to_string(),
+                }
+            ));
+            if let Some(message) = &record.error_message {
+                out.push_str(&format!("```\n{}\n```\n\n", message.trim_end()));
+            }
+            if let Some(instruction) = &record.repair_instruction {
+                out.push_str(&format!(
+                    "Repair instruction:\n\n```\n{}\n```\n\n",
+                    instruction.trim_end()
+                ));
+            }
+            if record.diff_to_next.is_empty() {
+                out.push_str(&format!("```python\n{}\n```\n", record.code.trim_end()));
+                if record.code_truncated {
+                    out.push_str(&format!(
+                        "\n_Truncated; full script sha256 {}._\n",
+                        record.code_sha256
+                    ));
+                }
+            } else {
+                let diff: Vec<String> = record
+                    .diff_to_next
+                    .iter()
+                    .filter(|l| l.tag != "equal")
+                    .map(|l| format!("{}{}", if l.tag == "insert" { "+" } else { "-" }, l.text))
+                    .collect();
+                out.push_str(&format!(
+                    "Changes for the next attempt:\n\n```diff\n{}\n```\n",
+                    diff.join("\n")
+                ));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replay `attempts`' diffs from the first attempt's code; `None` when a
+    /// diff does not apply to the code it was recorded against.
+    fn replay(attempts: &[AttemptRecord]) -> Option<Vec<String>> {
+        let mut code: Vec<String> = attempts.first()?.code.lines().map(str::to_string).collect();
+        for record in attempts.iter().filter(|r| !r.diff_to_next.is_empty()) {
+            let old: Vec<&str> = record
+                .diff_to_next
+                .iter()
+                .filter(|l| l.tag != "insert")
+                .map(|l| l.text.as_str())
+                .collect();
+            if old != code {
+                return None;
+            }
+            code = record
+                .diff_to_next
+                .iter()
+                .filter(|l| l.tag != "delete")
+                .map(|l| l.text.clone())
+                .collect();
+        }
+        Some(code)
+    }
+
+    #[test]
+    fn test_two_repair_chain_replays_to_final_code() {
+        let first = "from build123d import *\nresult = Box(10, 10, 10).fillet(9, None)\n";
+        let second =
+            "from build123d import *\nbox = Box(10, 10, 10)\nresult = box.fillet(9, box.edges())\n";
+        let last =
+            "from build123d import *\nbox = Box(10, 10, 10)\nresult = box.fillet(1, box.edges())\n";
+
+        let mut history = AttemptHistory::default();
+        history.begin(1, first);
+        history.fail("TypeError", "fillet() edge list is None", Some(2));
+        history.repair("Pass the edges to fillet.");
+        assert_eq!(history.advance(second), (2, 1));
+        history.begin(2, second);
+        history.fail("Geometry", "Failed creating a fillet", Some(3));
+        history.repair("Use a smaller fillet radius.");
+        assert_eq!(history.advance(last), (1, 1));
+        history.begin(3, last);
+
+        let records = history.records();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].error_line, Some(3));
+        assert!(records[2].diff_to_next.is_empty());
+        assert!(records[2].error_category.is_none());
+        let expected: Vec<String> = last.lines().map(str::to_string).collect();
+        assert_eq!(replay(&records), Some(expected));
+        assert_eq!(records[2].code_sha256, code_sha256(last));
+    }
+
+    #[test]
+    fn test_storage_is_bounded() {
+        let mut history = AttemptHistory::default();
+        let big = format!(
+            "result = None\n{}",
+            "# padding\n".repeat(MAX_INLINE_CODE_BYTES / 8)
+        );
+        for attempt in 1..=(MAX_STORED_ATTEMPTS as u32 + 3) {
+            let code = if attempt == 2 {
+                big.clone()
+            } else {
+                format!("result = {}\n", attempt)
+            };
+            if attempt > 1 {
+                history.advance(&code);
+            }
+            history.begin(attempt, &code);
+        }
+
+        let saved = std::cell::RefCell::new(Vec::new());
+        let (kept, omitted) = bounded(&history.records(), |sha, code| {
+            saved.borrow_mut().push((sha.to_string(), code.len()))
+        });
+        assert_eq!(omitted, 3);
+        assert_eq!(kept.len(), MAX_STORED_ATTEMPTS);
+        assert_eq!(kept[0].attempt, 1);
+        assert_eq!(kept[1].attempt, 5);
+        // Attempt 2 was dropped with the middle of the chain, so nothing was saved.
+        assert!(saved.borrow().is_empty());
+
+        let mut history = AttemptHistory::default();
+        history.begin(1, &big);
+        history.advance("result = 1\n");
+        let (kept, _) = bounded(&history.records(), |sha, code| {
+            saved.borrow_mut().push((sha.to_string(), code.len()))
+        });
+        assert!(kept[0].code_truncated);
+        assert_eq!(kept[0].code.len(), MAX_INLINE_CODE_BYTES);
+        assert_eq!(saved.borrow()[0], (code_sha256(&big), big.len()));
+        assert!(kept[0].diff_to_next.iter().all(|l| l.tag != "equal"));
+
+        let mut store = ValidationHistoryStore::default();
+        for i in 0..=MAX_STORED_CHAINS {
+            store.insert(ValidationChain {
+                run_id: format!("run-{}", i),
+                part_name: None,
+                recorded_at_ms: 0,
+                attempts: kept.clone(),
+                omitted_attempts: 0,
+            });
+        }
+        assert!(store.chains("run-0", None).is_empty());
+        assert_eq!(store.chains("run-1", None).len(), 1);
+        assert!(store.chains("run-1", Some("lid")).is_empty());
+    }
+
+    #[test]
+    fn test_chains_are_kept_with_their_run() {
+        let config = AppConfig {
+            safe_mode: true,
+            ..AppConfig::default()
+        };
+        let run_store = Mutex::new(RunStore::default());
+        let mut history = AttemptHistory::default();
+        history.begin(1, "result = Box(1, 1, 1)\n");
+
+        record(
+            &run_store,
+            &config,
+            "run-a",
+            Some("lid"),
+            &history.records(),
+        );
+        record(&run_store, &config, "run-b", None, &history.records());
+
+        let store = run_store.lock().unwrap();
+        assert_eq!(store.validation_chains("run-a", Some("lid")).len(), 1);
+        assert!(store.validation_chains("run-a", Some("base")).is_empty());
+        assert_eq!(store.validation_chains("run-b", None).len(), 1);
+        // Another store shares nothing with this one.
+        assert!(RunStore::default()
+            .validation_chains("run-a", None)
+            .is_empty());
+    }
+}
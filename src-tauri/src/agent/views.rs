@@ -0,0 +1,294 @@
+//! Standard camera views and saved bookmarks framed on a model's bounding
+//! box, in the viewer's Z-up coordinates.
+
+use serde::{Deserialize, Serialize};
+
+/// Vertical field of view of the viewport's perspective camera.
+pub const DEFAULT_FOV_DEG: f64 = 50.0;
+/// Extra room around the projected bounding box.
+pub const FRAMING_MARGIN: f64 = 1.2;
+/// Smallest half-extent used for framing, so flat or empty boxes still work.
+const MIN_HALF_EXTENT: f64 = 0.5;
+
+/// Directions from the target towards the camera, matching the drawing
+/// module's projection vectors.
+const STANDARD_DIRECTIONS: [(&str, [f64; 3]); 7] = [
+    ("front", [0.0, -1.0, 0.0]),
+    ("back", [0.0, 1.0, 0.0]),
+    ("top", [0.0, 0.0, 1.0]),
+    ("bottom", [0.0, 0.0, -1.0]),
+    ("left", [-1.0, 0.0, 0.0]),
+    ("right", [1.0, 0.0, 0.0]),
+    ("isometric", [1.0, -1.0, 1.0]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl Bounds {
+    pub fn center(&self) -> [f64; 3] {
+        [0, 1, 2].map(|i| (self.min[i] + self.max[i]) / 2.0)
+    }
+
+    fn half_extents(&self) -> [f64; 3] {
+        [0, 1, 2].map(|i| ((self.max[i] - self.min[i]) / 2.0).max(MIN_HALF_EXTENT))
+    }
+
+    /// Half the box diagonal; the scale bookmarks are stored relative to.
+    pub fn radius(&self) -> f64 {
+        norm(self.half_extents())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraView {
+    pub name: String,
+    pub position: [f64; 3],
+    pub target: [f64; 3],
+    pub up: [f64; 3],
+}
+
+/// Standard views for one geometry, from `get_standard_views`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StandardViews {
+    pub bounds: Bounds,
+    pub views: Vec<CameraView>,
+}
+
+/// A user-saved camera, kept with the bounds it was saved against so it can
+/// follow the model when the geometry changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViewBookmark {
+    pub camera: CameraView,
+    pub bounds: Bounds,
+}
+
+fn norm(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let len = norm(v);
+    v.map(|c| c / len)
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Half-size of the box measured along `axis` (a unit vector).
+fn projected_half_extent(half: [f64; 3], axis: [f64; 3]) -> f64 {
+    (0..3).map(|i| half[i] * axis[i].abs()).sum()
+}
+
+/// Camera looking along `-direction` at the box center, far enough back that
+/// the box's silhouette fits the field of view with `FRAMING_MARGIN` to spare.
+pub fn frame_view(
+    name: &str,
+    direction: [f64; 3],
+    bounds: &Bounds,
+    fov_deg: f64,
+    aspect: f64,
+) -> CameraView {
+    let direction = normalize(direction);
+    // Looking straight down or up, screen-up is +Y; otherwise it is +Z.
+    let up = if direction[0] == 0.0 && direction[1] == 0.0 {
+        [0.0, 1.0, 0.0]
+    } else {
+        [0.0, 0.0, 1.0]
+    };
+    let right = normalize(cross(direction.map(|c| -c), up));
+    let screen_up = cross(right, direction.map(|c| -c));
+
+    let half = bounds.half_extents();
+    let half_width = projected_half_extent(half, right);
+    let half_height = projected_half_extent(half, screen_up);
+    let half_depth = projected_half_extent(half, direction);
+
+    let tan_half_fov = (fov_deg.to_radians() / 2.0).tan();
+    let fit = half_height.max(half_width / aspect.max(1e-3)) * FRAMING_MARGIN;
+    let distance = half_depth + fit / tan_half_fov;
+
+    let target = bounds.center();
+    CameraView {
+        name: name.to_string(),
+        position: [0, 1, 2].map(|i| target[i] + direction[i] * distance),
+        target,
+        up,
+    }
+}
+
+/// Front/back/top/bottom/left/right/isometric cameras for `bounds`.
+pub fn standard_views(bounds: &Bounds, fov_deg: f64, aspect: f64) -> Vec<CameraView> {
+    STANDARD_DIRECTIONS
+        .iter()
+        .map(|(name, direction)| frame_view(name, *direction, bounds, fov_deg, aspect))
+        .collect()
+}
+
+/// `bookmark` moved onto `bounds`: position and target keep their offset
+/// from the box center, scaled by the change in box size, so the view
+/// direction is preserved.
+pub fn rescale_bookmark(bookmark: &ViewBookmark, bounds: &Bounds) -> ViewBookmark {
+    let old_center = bookmark.bounds.center();
+    let new_center = bounds.center();
+    let scale = bounds.radius() / bookmark.bounds.radius();
+    let moved = |p: [f64; 3]| [0, 1, 2].map(|i| new_center[i] + (p[i] - old_center[i]) * scale);
+    ViewBookmark {
+        camera: CameraView {
+            name: bookmark.camera.name.clone(),
+            position: moved(bookmark.camera.position),
+            target: moved(bookmark.camera.target),
+            up: bookmark.camera.up,
+        },
+        bounds: *bounds,
+    }
+}
+
+/// Replace the bookmark with the same name, or append a new one.
+pub fn upsert_bookmark(bookmarks: &mut Vec<ViewBookmark>, bookmark: ViewBookmark) {
+    match bookmarks
+        .iter_mut()
+        .find(|b| b.camera.name == bookmark.camera.name)
+    {
+        Some(existing) => *existing = bookmark,
+        None => bookmarks.push(bookmark),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view<'a>(views: &'a [CameraView], name: &str) -> &'a CameraView {
+        views.iter().find(|v| v.name == name).unwrap()
+    }
+
+    fn distance(v: &CameraView) -> f64 {
+        norm([0, 1, 2].map(|i| v.position[i] - v.target[i]))
+    }
+
+    /// Half-angle the box's extent along `axis` subtends from the camera.
+    fn half_angle_deg(v: &CameraView, bounds: &Bounds, axis: usize, depth_axis: usize) -> f64 {
+        let half = bounds.half_extents();
+        (half[axis] / (distance(v) - half[depth_axis]))
+            .atan()
+            .to_degrees()
+    }
+
+    #[test]
+    fn test_elongated_rod_frames_its_length() {
+        // 200mm rod along X
+        let bounds = Bounds {
+            min: [-100.0, -5.0, -5.0],
+            max: [100.0, 5.0, 5.0],
+        };
+        let views = standard_views(&bounds, DEFAULT_FOV_DEG, 1.0);
+        assert_eq!(views.len(), 7);
+
+        let front = view(&views, "front");
+        assert_eq!(front.target, [0.0, 0.0, 0.0]);
+        assert_eq!(front.up, [0.0, 0.0, 1.0]);
+        assert!(front.position[1] < 0.0 && front.position[0].abs() < 1e-9);
+        // The length fills the view minus the margin.
+        let angle = half_angle_deg(front, &bounds, 0, 1);
+        assert!((angle - (DEFAULT_FOV_DEG / 2.0)).abs() < 5.0, "{}", angle);
+        assert!(angle < DEFAULT_FOV_DEG / 2.0);
+
+        // Looking down the rod only its 10mm section needs framing.
+        let right = view(&views, "right");
+        assert!(distance(right) < distance(front) / 2.0);
+        assert!(distance(right) > 100.0, "camera must sit outside the rod");
+    }
+
+    #[test]
+    fn test_flat_plate_top_and_side_views() {
+        // 100 x 60 plate, 2mm thick
+        let bounds = Bounds {
+            min: [0.0, 0.0, 0.0],
+            max: [100.0, 60.0, 2.0],
+        };
+        let views = standard_views(&bounds, DEFAULT_FOV_DEG, 1.0);
+
+        let top = view(&views, "top");
+        assert_eq!(top.target, [50.0, 30.0, 1.0]);
+        assert_eq!(top.up, [0.0, 1.0, 0.0]);
+        assert!(top.position[2] > 2.0);
+        assert!(half_angle_deg(top, &bounds, 0, 2) < DEFAULT_FOV_DEG / 2.0);
+
+        let bottom = view(&views, "bottom");
+        assert!(bottom.position[2] < 0.0);
+        assert!((distance(bottom) - distance(top)).abs() < 1e-9);
+
+        // Edge-on, the plate's length still sets the distance; the camera
+        // only backs off by the extra depth (30mm instead of 1mm).
+        let front = view(&views, "front");
+        assert!((distance(front) - distance(top) - 29.0).abs() < 1e-9);
+
+        // A wide viewport only needs to fit the height.
+        let wide = standard_views(&bounds, DEFAULT_FOV_DEG, 2.0);
+        assert!(distance(view(&wide, "top")) < distance(top));
+    }
+
+    #[test]
+    fn test_isometric_sits_on_the_diagonal() {
+        let bounds = Bounds {
+            min: [-10.0, -10.0, -10.0],
+            max: [10.0, 10.0, 10.0],
+        };
+        let iso = frame_view("isometric", [1.0, -1.0, 1.0], &bounds, DEFAULT_FOV_DEG, 1.0);
+        let p = iso.position;
+        assert!(p[0] > 0.0 && p[1] < 0.0 && p[2] > 0.0);
+        assert!((p[0] + p[1]).abs() < 1e-9 && (p[0] - p[2]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_degenerate_bounds_still_frame() {
+        let point = Bounds {
+            min: [5.0, 5.0, 5.0],
+            max: [5.0, 5.0, 5.0],
+        };
+        for v in standard_views(&point, DEFAULT_FOV_DEG, 1.0) {
+            assert!(distance(&v).is_finite() && distance(&v) > 0.0, "{:?}", v);
+        }
+    }
+
+    #[test]
+    fn test_bookmark_rescales_with_geometry() {
+        let small = Bounds {
+            min: [0.0, 0.0, 0.0],
+            max: [10.0, 10.0, 10.0],
+        };
+        let large = Bounds {
+            min: [100.0, 0.0, 0.0],
+            max: [120.0, 20.0, 20.0],
+        };
+        let bookmark = ViewBookmark {
+            camera: CameraView {
+                name: "hinge closeup".to_string(),
+                position: [5.0, -25.0, 15.0],
+                target: [5.0, 5.0, 5.0],
+                up: [0.0, 0.0, 1.0],
+            },
+            bounds: small,
+        };
+        let moved = rescale_bookmark(&bookmark, &large);
+        assert_eq!(moved.bounds, large);
+        assert_eq!(moved.camera.name, "hinge closeup");
+        let close = |a: [f64; 3], b: [f64; 3]| (0..3).all(|i| (a[i] - b[i]).abs() < 1e-9);
+        assert!(close(moved.camera.target, [110.0, 10.0, 10.0]));
+        assert!(close(moved.camera.position, [110.0, -50.0, 30.0]));
+
+        let mut bookmarks = vec![bookmark.clone()];
+        upsert_bookmark(&mut bookmarks, moved);
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].bounds, large);
+    }
+}
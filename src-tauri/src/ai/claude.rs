@@ -5,9 +5,9 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
 use crate::ai::message::ChatMessage;
-use crate::ai::provider::{AiProvider, StreamDelta, TokenUsage};
+use crate::ai::provider::{AiProvider, StreamDelta, StreamSignal, TokenUsage};
 use crate::ai::retry;
-use crate::ai::streaming::parse_sse_events;
+use crate::ai::streaming::{is_sse_keep_alive, parse_sse_events};
 use crate::error::AppError;
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
@@ -19,6 +19,7 @@ pub struct ClaudeProvider {
     api_key: String,
     model: String,
     temperature: Option<f32>,
+    stop: Vec<String>,
 }
 
 impl ClaudeProvider {
@@ -28,6 +29,7 @@ impl ClaudeProvider {
             api_key,
             model,
             temperature: None,
+            stop: Vec::new(),
         }
     }
 
@@ -36,6 +38,12 @@ impl ClaudeProvider {
         self
     }
 
+    /// Stop generating at any of `stop`; the matched sequence is not returned.
+    pub fn with_stop_sequences(mut self, stop: Vec<String>) -> Self {
+        self.stop = stop;
+        self
+    }
+
     /// Separate system messages from the conversation and build the request.
     fn build_request(
         &self,
@@ -69,6 +77,24 @@ impl ClaudeProvider {
         let _ = stream; // used by caller, not here
         (system_text, claude_messages)
     }
+
+    fn request_body(
+        &self,
+        messages: &[ChatMessage],
+        max_tokens: u32,
+        stream: bool,
+    ) -> ClaudeRequest {
+        let (system, claude_messages) = self.build_request(messages, stream);
+        ClaudeRequest {
+            model: self.model.clone(),
+            max_tokens,
+            system,
+            messages: claude_messages,
+            stream,
+            temperature: self.temperature,
+            stop_sequences: self.stop.clone(),
+        }
+    }
 }
 
 // --- Request / Response types for the Anthropic Messages API ---
@@ -83,6 +109,8 @@ struct ClaudeRequest {
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -142,16 +170,7 @@ impl AiProvider for ClaudeProvider {
         messages: &[ChatMessage],
         max_tokens: Option<u32>,
     ) -> Result<(String, Option<TokenUsage>), AppError> {
-        let (system, claude_messages) = self.build_request(messages, false);
-
-        let body = ClaudeRequest {
-            model: self.model.clone(),
-            max_tokens: max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
-            system,
-            messages: claude_messages,
-            stream: false,
-            temperature: self.temperature,
-        };
+        let body = self.request_body(messages, max_tokens.unwrap_or(DEFAULT_MAX_TOKENS), false);
 
         let response = retry::send_with_retry(
             || {
@@ -198,18 +217,9 @@ impl AiProvider for ClaudeProvider {
     async fn stream(
         &self,
         messages: &[ChatMessage],
-        tx: mpsc::Sender<StreamDelta>,
+        tx: mpsc::Sender<StreamSignal>,
     ) -> Result<Option<TokenUsage>, AppError> {
-        let (system, claude_messages) = self.build_request(messages, true);
-
-        let body = ClaudeRequest {
-            model: self.model.clone(),
-            max_tokens: DEFAULT_MAX_TOKENS,
-            system,
-            messages: claude_messages,
-            stream: true,
-            temperature: self.temperature,
-        };
+        let body = self.request_body(messages, DEFAULT_MAX_TOKENS, true);
 
         let response = retry::send_with_retry(
             || {
@@ -242,6 +252,9 @@ impl AiProvider for ClaudeProvider {
                 let event_block = buffer[..pos].to_string();
                 buffer = buffer[pos + 2..].to_string();
 
+                if is_sse_keep_alive(&event_block) {
+                    let _ = tx.send(StreamSignal::KeepAlive).await;
+                }
                 let events = parse_sse_events(&event_block);
                 for event_data in events {
                     if let Ok(sse_event) = serde_json::from_str::<ClaudeSSEEvent>(&event_data) {
@@ -259,10 +272,10 @@ impl AiProvider for ClaudeProvider {
                                 if let Some(delta) = sse_event.delta {
                                     if let Some(text) = delta.text {
                                         let _ = tx
-                                            .send(StreamDelta {
+                                            .send(StreamSignal::Delta(StreamDelta {
                                                 content: text,
                                                 done: false,
-                                            })
+                                            }))
                                             .await;
                                     }
                                 }
@@ -278,10 +291,10 @@ impl AiProvider for ClaudeProvider {
                             }
                             "message_stop" => {
                                 let _ = tx
-                                    .send(StreamDelta {
+                                    .send(StreamSignal::Delta(StreamDelta {
                                         content: String::new(),
                                         done: true,
-                                    })
+                                    }))
                                     .await;
                             }
                             _ => {
@@ -295,12 +308,48 @@ impl AiProvider for ClaudeProvider {
 
         // Ensure a done signal was sent even if the stream ended without message_stop.
         let _ = tx
-            .send(StreamDelta {
+            .send(StreamSignal::Delta(StreamDelta {
                 content: String::new(),
                 done: true,
-            })
+            }))
             .await;
 
         Ok(if has_usage { Some(tracked_usage) } else { None })
     }
+
+    /// Anthropic streams `event: ping` while the model is thinking.
+    fn detects_keep_alives(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "Wrap code in <CODE>...</CODE> tags.".to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: "A 20mm cube".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_stop_sequences_in_request_body() {
+        let provider = ClaudeProvider::new("key".into(), "claude-test".into())
+            .with_stop_sequences(vec!["</CODE>".to_string()]);
+        let body = serde_json::to_value(provider.request_body(&messages(), 1024, true)).unwrap();
+        assert_eq!(body["stop_sequences"], serde_json::json!(["</CODE>"]));
+        assert_eq!(body["system"], "Wrap code in <CODE>...</CODE> tags.");
+
+        let plain = ClaudeProvider::new("key".into(), "claude-test".into());
+        let body = serde_json::to_value(plain.request_body(&messages(), 1024, false)).unwrap();
+        assert!(body.get("stop_sequences").is_none());
+    }
 }
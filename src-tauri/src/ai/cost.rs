@@ -1,31 +1,255 @@
+//! Model pricing per million tokens. The bundled table is merged with the
+//! user's `model_pricing.json` in app data; a model neither lists is priced
+//! at the default rate, and the estimate says so.
+
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
 use super::provider::TokenUsage;
+use crate::error::AppError;
+
+const BUNDLED_PRICING: &str = include_str!("../../../pricing/model_pricing.json");
+
+/// Rates above this per million tokens are taken for typos.
+pub const MAX_RATE_PER_MILLION: f64 = 1000.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Rate {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+/// Rate of the `provider` models whose id matches `model`, where `*`
+/// matches any run of characters.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelRate {
+    pub provider: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub rate: Rate,
+}
+
+/// Effective pricing table. `currency` is only displayed; costs are
+/// computed and reported in it as-is.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelPricing {
+    pub currency: String,
+    pub default_rate: Rate,
+    pub models: Vec<ModelRate>,
+}
+
+/// The user's `model_pricing.json`. Unset fields keep the bundled values.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PricingOverrides {
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub default_rate: Option<Rate>,
+    #[serde(default)]
+    pub models: Vec<ModelRate>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CostEstimate {
+    pub cost: f64,
+    pub currency: String,
+    /// The model is not in the pricing table; `default_rate` was used.
+    pub estimated_with_default_rate: bool,
+}
+
+/// `*` matches any run of characters, everything else itself.
+fn pattern_matches(pattern: &str, model: &str) -> bool {
+    let pieces: Vec<&str> = pattern.split('*').collect();
+    if pieces.len() == 1 {
+        return pattern == model;
+    }
+    let (first, last) = (pieces[0], pieces[pieces.len() - 1]);
+    if model.len() < first.len() + last.len() {
+        return false;
+    }
+    if !model.starts_with(first) || !model.ends_with(last) {
+        return false;
+    }
+    let mut rest = &model[first.len()..model.len() - last.len()];
+    for piece in &pieces[1..pieces.len() - 1] {
+        match rest.find(piece) {
+            Some(i) => rest = &rest[i + piece.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+impl ModelPricing {
+    pub fn bundled() -> Self {
+        serde_json::from_str(BUNDLED_PRICING).expect("bundled model pricing is valid")
+    }
+
+    /// `overrides` applied over this table. An override replaces the entry
+    /// with the same provider and pattern; all overrides are matched before
+    /// the remaining entries.
+    pub fn merged(&self, overrides: &PricingOverrides) -> Self {
+        let mut models = overrides.models.clone();
+        models.extend(
+            self.models
+                .iter()
+                .filter(|entry| {
+                    !overrides
+                        .models
+                        .iter()
+                        .any(|o| o.provider == entry.provider && o.model == entry.model)
+                })
+                .cloned(),
+        );
+        Self {
+            currency: overrides
+                .currency
+                .clone()
+                .unwrap_or_else(|| self.currency.clone()),
+            default_rate: overrides.default_rate.unwrap_or(self.default_rate),
+            models,
+        }
+    }
+
+    /// Rate of the first entry matching the provider and model.
+    pub fn rate(&self, provider: &str, model: &str) -> Option<Rate> {
+        self.models
+            .iter()
+            .find(|entry| entry.provider == provider && pattern_matches(&entry.model, model))
+            .map(|entry| entry.rate)
+    }
+
+    pub fn estimate(&self, provider: &str, model: &str, usage: &TokenUsage) -> CostEstimate {
+        let known = self.rate(provider, model);
+        let rate = known.unwrap_or(self.default_rate);
+        CostEstimate {
+            cost: (usage.input_tokens as f64 * rate.input_per_million
+                + usage.output_tokens as f64 * rate.output_per_million)
+                / 1_000_000.0,
+            currency: self.currency.clone(),
+            estimated_with_default_rate: known.is_none(),
+        }
+    }
+}
+
+fn validate_rate(rate: &Rate, what: &str) -> Result<(), AppError> {
+    for (side, value) in [
+        ("input", rate.input_per_million),
+        ("output", rate.output_per_million),
+    ] {
+        if !value.is_finite() || !(0.0..=MAX_RATE_PER_MILLION).contains(&value) {
+            return Err(AppError::ConfigError(format!(
+                "{} {} rate must be between 0 and {} per million tokens, got {}",
+                what, side, MAX_RATE_PER_MILLION, value
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn overrides_path() -> Result<PathBuf, AppError> {
+    let base = dirs::config_dir()
+        .ok_or_else(|| AppError::ConfigError("Cannot resolve config directory".to_string()))?;
+    Ok(base.join("cadai-studio").join("model_pricing.json"))
+}
+
+impl PricingOverrides {
+    /// Reject empty names, non-ISO currency codes and negative or absurd rates.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(currency) = &self.currency {
+            if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(AppError::ConfigError(format!(
+                    "Currency must be a three-letter ISO code like USD, got '{}'",
+                    currency
+                )));
+            }
+        }
+        if let Some(rate) = &self.default_rate {
+            validate_rate(rate, "Default")?;
+        }
+        for entry in &self.models {
+            if entry.provider.trim().is_empty() || entry.model.trim().is_empty() {
+                return Err(AppError::ConfigError(
+                    "Pricing entries need a provider and a model pattern".to_string(),
+                ));
+            }
+            validate_rate(&entry.rate, &format!("{}/{}", entry.provider, entry.model))?;
+        }
+        Ok(())
+    }
+
+    /// Overrides saved by the user, or none when the file is missing or invalid.
+    pub fn load() -> Self {
+        overrides_path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|raw| serde_json::from_str::<PricingOverrides>(&raw).ok())
+            .filter(|overrides| overrides.validate().is_ok())
+            .unwrap_or_default()
+    }
 
-/// Estimate cost in USD for the given usage. Returns None for unknown provider/model combos.
-pub fn estimate_cost(provider: &str, model: &str, usage: &TokenUsage) -> Option<f64> {
-    let (input_rate, output_rate) = get_rates_per_million(provider, model)?;
-    Some(
-        (usage.input_tokens as f64 * input_rate + usage.output_tokens as f64 * output_rate)
-            / 1_000_000.0,
-    )
-}
-
-/// Returns (input_rate, output_rate) per million tokens.
-fn get_rates_per_million(provider: &str, model: &str) -> Option<(f64, f64)> {
-    match (provider, model) {
-        ("claude", m) if m.contains("sonnet") => Some((3.0, 15.0)),
-        ("claude", m) if m.contains("opus") => Some((15.0, 75.0)),
-        ("claude", m) if m.contains("haiku") => Some((0.25, 1.25)),
-        ("openai", m) if m.starts_with("gpt-5") => Some((2.5, 10.0)),
-        ("openai", "o3-mini") => Some((1.1, 4.4)),
-        ("deepseek", "deepseek-chat") => Some((0.27, 1.10)),
-        ("deepseek", "deepseek-reasoner") => Some((0.55, 2.19)),
-        ("qwen", _) => Some((0.30, 0.60)),
-        ("kimi", _) => Some((0.70, 2.80)),
-        ("gemini", m) if m.contains("pro") => Some((1.25, 10.0)),
-        ("gemini", m) if m.contains("flash") => Some((0.15, 0.60)),
-        ("runpod", _) => Some((0.0, 0.0)),
-        ("ollama", _) => Some((0.0, 0.0)),
-        _ => None,
+    pub fn save(&self) -> Result<(), AppError> {
+        let path = overrides_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Current overrides, and the bundled table merged with them.
+struct PricingState {
+    overrides: PricingOverrides,
+    effective: ModelPricing,
+}
+
+impl PricingState {
+    fn new(overrides: PricingOverrides) -> Self {
+        Self {
+            effective: ModelPricing::bundled().merged(&overrides),
+            overrides,
+        }
+    }
+
+    fn set_overrides(&mut self, overrides: PricingOverrides) -> Result<ModelPricing, AppError> {
+        overrides.validate()?;
+        *self = Self::new(overrides);
+        Ok(self.effective.clone())
+    }
+}
+
+static PRICING: OnceLock<Mutex<PricingState>> = OnceLock::new();
+
+fn pricing_state() -> &'static Mutex<PricingState> {
+    PRICING.get_or_init(|| Mutex::new(PricingState::new(PricingOverrides::load())))
+}
+
+/// The effective pricing table and the user overrides it was built from.
+pub fn current_pricing() -> (ModelPricing, PricingOverrides) {
+    match pricing_state().lock() {
+        Ok(state) => (state.effective.clone(), state.overrides.clone()),
+        Err(_) => (ModelPricing::bundled(), PricingOverrides::default()),
+    }
+}
+
+/// Validate `overrides` and use them for every later estimate. Saving them
+/// to disk is up to the caller.
+pub fn set_overrides(overrides: PricingOverrides) -> Result<ModelPricing, AppError> {
+    pricing_state()
+        .lock()
+        .map_err(|_| AppError::ConfigError("Model pricing lock poisoned".into()))?
+        .set_overrides(overrides)
+}
+
+/// Estimate the cost of `usage` with the current pricing table. Unknown
+/// models get the default rate and `estimated_with_default_rate`.
+pub fn estimate_cost(provider: &str, model: &str, usage: &TokenUsage) -> CostEstimate {
+    match pricing_state().lock() {
+        Ok(state) => state.effective.estimate(provider, model, usage),
+        Err(_) => ModelPricing::bundled().estimate(provider, model, usage),
     }
 }
 
@@ -39,9 +263,12 @@ mod tests {
             input_tokens: 1000,
             output_tokens: 500,
         };
-        let cost = estimate_cost("claude", "claude-3-5-sonnet-20241022", &usage).unwrap();
+        let estimate =
+            ModelPricing::bundled().estimate("claude", "claude-3-5-sonnet-20241022", &usage);
         // 1000 * 3.0 / 1M + 500 * 15.0 / 1M = 0.003 + 0.0075 = 0.0105
-        assert!((cost - 0.0105).abs() < 1e-10);
+        assert!((estimate.cost - 0.0105).abs() < 1e-10);
+        assert!(!estimate.estimated_with_default_rate);
+        assert_eq!(estimate.currency, "USD");
     }
 
     #[test]
@@ -50,17 +277,22 @@ mod tests {
             input_tokens: 5000,
             output_tokens: 2000,
         };
-        let cost = estimate_cost("ollama", "llama3", &usage).unwrap();
+        let cost = ModelPricing::bundled()
+            .estimate("ollama", "llama3", &usage)
+            .cost;
         assert_eq!(cost, 0.0);
     }
 
     #[test]
-    fn test_estimate_cost_unknown_returns_none() {
+    fn test_estimate_cost_unknown_uses_default_rate() {
         let usage = TokenUsage {
-            input_tokens: 100,
-            output_tokens: 50,
+            input_tokens: 1_000_000,
+            output_tokens: 0,
         };
-        assert!(estimate_cost("unknown_provider", "unknown_model", &usage).is_none());
+        let pricing = ModelPricing::bundled();
+        let estimate = pricing.estimate("unknown_provider", "unknown_model", &usage);
+        assert!(estimate.estimated_with_default_rate);
+        assert!((estimate.cost - pricing.default_rate.input_per_million).abs() < 1e-10);
     }
 
     #[test]
@@ -70,7 +302,119 @@ mod tests {
             output_tokens: 1_000_000,
         };
         // Claude Opus: 15.0 + 75.0 = $90.00
-        let cost = estimate_cost("claude", "claude-3-opus-20240229", &usage).unwrap();
+        let cost = ModelPricing::bundled()
+            .estimate("claude", "claude-3-opus-20240229", &usage)
+            .cost;
         assert!((cost - 90.0).abs() < 1e-10);
     }
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(pattern_matches("*sonnet*", "claude-sonnet-4"));
+        assert!(pattern_matches("gpt-5*", "gpt-5-mini"));
+        assert!(!pattern_matches("gpt-5*", "gpt-4o"));
+        assert!(pattern_matches("o3-mini", "o3-mini"));
+        assert!(!pattern_matches("o3-mini", "o3-mini-high"));
+        assert!(pattern_matches("*", "anything"));
+        assert!(pattern_matches("a*b*c", "a-b-c"));
+        assert!(!pattern_matches("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_overrides_take_precedence() {
+        let bundled = ModelPricing::bundled();
+        let overrides = PricingOverrides {
+            currency: Some("EUR".to_string()),
+            default_rate: None,
+            models: vec![
+                ModelRate {
+                    provider: "claude".to_string(),
+                    model: "*opus*".to_string(),
+                    rate: Rate {
+                        input_per_million: 5.0,
+                        output_per_million: 25.0,
+                    },
+                },
+                ModelRate {
+                    provider: "claude".to_string(),
+                    model: "claude-sonnet-9".to_string(),
+                    rate: Rate {
+                        input_per_million: 1.0,
+                        output_per_million: 2.0,
+                    },
+                },
+            ],
+        };
+        let merged = bundled.merged(&overrides);
+        assert_eq!(merged.currency, "EUR");
+        assert_eq!(merged.default_rate, bundled.default_rate);
+        assert_eq!(merged.models.len(), bundled.models.len() + 1);
+        let input_rate = |model: &str| merged.rate("claude", model).unwrap().input_per_million;
+        assert_eq!(input_rate("claude-opus-4"), 5.0);
+        // The exact override is matched before the bundled `*sonnet*` entry.
+        assert_eq!(input_rate("claude-sonnet-9"), 1.0);
+        assert_eq!(input_rate("claude-sonnet-4"), 3.0);
+    }
+
+    #[test]
+    fn test_overrides_are_validated() {
+        let with_rate = |input: f64| PricingOverrides {
+            models: vec![ModelRate {
+                provider: "openai".to_string(),
+                model: "gpt-5*".to_string(),
+                rate: Rate {
+                    input_per_million: input,
+                    output_per_million: 1.0,
+                },
+            }],
+            ..Default::default()
+        };
+        assert!(with_rate(2.0).validate().is_ok());
+        assert!(with_rate(-1.0).validate().is_err());
+        assert!(with_rate(1e6).validate().is_err());
+        assert!(with_rate(f64::NAN).validate().is_err());
+        let currency = PricingOverrides {
+            currency: Some("dollars".to_string()),
+            ..Default::default()
+        };
+        assert!(currency.validate().is_err());
+    }
+
+    #[test]
+    fn test_override_applies_to_next_estimate() {
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 1_000_000,
+        };
+        let mut state = PricingState::new(PricingOverrides::default());
+        let before = state.effective.estimate("acme-test", "widget-1", &usage);
+        assert!(before.estimated_with_default_rate);
+
+        state
+            .set_overrides(PricingOverrides {
+                models: vec![ModelRate {
+                    provider: "acme-test".to_string(),
+                    model: "widget-*".to_string(),
+                    rate: Rate {
+                        input_per_million: 0.5,
+                        output_per_million: 1.5,
+                    },
+                }],
+                ..Default::default()
+            })
+            .unwrap();
+        let after = state.effective.estimate("acme-test", "widget-1", &usage);
+        assert!(!after.estimated_with_default_rate);
+        assert!((after.cost - 2.0).abs() < 1e-10);
+        assert!(state
+            .set_overrides(PricingOverrides {
+                default_rate: Some(Rate {
+                    input_per_million: -1.0,
+                    output_per_million: 0.0,
+                }),
+                ..Default::default()
+            })
+            .is_err());
+        assert_eq!(state.overrides.models.len(), 1);
+    }
 }
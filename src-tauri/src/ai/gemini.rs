@@ -5,18 +5,21 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
 use crate::ai::message::ChatMessage;
-use crate::ai::provider::{AiProvider, StreamDelta, TokenUsage};
+use crate::ai::provider::{AiProvider, StreamDelta, StreamSignal, TokenUsage};
 use crate::ai::retry;
 use crate::ai::streaming::parse_sse_events;
 use crate::error::AppError;
 
 const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+/// Most stop sequences `generationConfig` accepts.
+const MAX_STOP_SEQUENCES: usize = 5;
 
 pub struct GeminiProvider {
     client: Client,
     api_key: String,
     model: String,
     temperature: Option<f32>,
+    stop: Vec<String>,
 }
 
 #[allow(dead_code)]
@@ -27,6 +30,7 @@ impl GeminiProvider {
             api_key,
             model,
             temperature: None,
+            stop: Vec::new(),
         }
     }
 
@@ -35,6 +39,12 @@ impl GeminiProvider {
         self
     }
 
+    /// Stop generating at any of `stop`; the matched sequence is not returned.
+    pub fn with_stop_sequences(mut self, stop: Vec<String>) -> Self {
+        self.stop = stop;
+        self
+    }
+
     fn generate_endpoint(&self) -> String {
         format!(
             "{}/models/{}:generateContent?key={}",
@@ -82,9 +92,11 @@ impl GeminiProvider {
             })
         };
 
-        let generation_config = self.temperature.map(|t| GeminiGenerationConfig {
-            temperature: Some(t),
-        });
+        let generation_config =
+            (self.temperature.is_some() || !self.stop.is_empty()).then(|| GeminiGenerationConfig {
+                temperature: self.temperature,
+                stop_sequences: self.stop.iter().take(MAX_STOP_SEQUENCES).cloned().collect(),
+            });
 
         GeminiRequest {
             contents,
@@ -101,6 +113,8 @@ impl GeminiProvider {
 struct GeminiGenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -217,7 +231,7 @@ impl AiProvider for GeminiProvider {
     async fn stream(
         &self,
         messages: &[ChatMessage],
-        tx: mpsc::Sender<StreamDelta>,
+        tx: mpsc::Sender<StreamSignal>,
     ) -> Result<Option<TokenUsage>, AppError> {
         let body = self.build_request(messages);
 
@@ -271,10 +285,10 @@ impl AiProvider for GeminiProvider {
 
                         if let Some(text) = text {
                             let _ = tx
-                                .send(StreamDelta {
+                                .send(StreamSignal::Delta(StreamDelta {
                                     content: text,
                                     done: false,
-                                })
+                                }))
                                 .await;
                         }
                     }
@@ -284,12 +298,40 @@ impl AiProvider for GeminiProvider {
 
         // Ensure a done signal is always sent.
         let _ = tx
-            .send(StreamDelta {
+            .send(StreamSignal::Delta(StreamDelta {
                 content: String::new(),
                 done: true,
-            })
+            }))
             .await;
 
         Ok(tracked_usage)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: "user".to_string(),
+            content: "A 20mm cube".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_stop_sequences_in_request_body() {
+        let provider = GeminiProvider::new("key".into(), "gemini-test".into())
+            .with_stop_sequences(vec!["</CODE>".to_string()]);
+        let body = serde_json::to_value(provider.build_request(&messages())).unwrap();
+        assert_eq!(
+            body["generationConfig"]["stopSequences"],
+            serde_json::json!(["</CODE>"])
+        );
+        assert!(body["generationConfig"].get("temperature").is_none());
+
+        let plain = GeminiProvider::new("key".into(), "gemini-test".into());
+        let body = serde_json::to_value(plain.build_request(&messages())).unwrap();
+        assert!(body.get("generationConfig").is_none());
+    }
+}
@@ -1,10 +1,14 @@
+pub mod audit;
 pub mod claude;
 pub mod cost;
 pub mod gemini;
 pub mod message;
+pub mod models;
 pub mod ollama;
 pub mod openai;
 pub mod provider;
+pub mod rate_limit;
 pub mod registry;
+pub mod response_cache;
 pub mod retry;
 pub mod streaming;
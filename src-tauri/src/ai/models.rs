@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use reqwest::Client;
+use serde_json::Value;
+
+use crate::ai::registry;
+use crate::config::AppConfig;
+use crate::error::AppError;
+
+const ANTHROPIC_MODELS_URL: &str = "https://api.anthropic.com/v1/models";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+/// How long a fetched model list is reused before asking the provider again.
+const MODELS_CACHE_TTL: Duration = Duration::from_secs(300);
+const MODELS_REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// OpenAI-style ids that are not chat models (embeddings, audio, images, ...).
+const NON_CHAT_MARKERS: &[&str] = &[
+    "embedding",
+    "whisper",
+    "tts",
+    "dall-e",
+    "moderation",
+    "transcribe",
+    "realtime",
+    "image",
+    "audio",
+    "search",
+    "babbage",
+    "davinci",
+];
+
+type ModelsCache = HashMap<String, (Instant, Vec<String>)>;
+
+static MODELS_CACHE: OnceLock<Mutex<ModelsCache>> = OnceLock::new();
+
+fn models_cache() -> &'static Mutex<ModelsCache> {
+    MODELS_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn provider_display_name(provider: &str) -> String {
+    registry::get_provider_registry()
+        .into_iter()
+        .find(|p| p.id == provider)
+        .map(|p| p.display_name)
+        .unwrap_or_else(|| provider.to_string())
+}
+
+/// Base URL of an OpenAI-compatible provider, matching `create_provider`.
+fn openai_compatible_base(provider: &str, config: &AppConfig) -> Option<String> {
+    match provider {
+        "openai" => Some(
+            config
+                .openai_base_url
+                .clone()
+                .unwrap_or_else(|| OPENAI_BASE_URL.to_string()),
+        ),
+        "runpod" => config.runpod_base_url.clone(),
+        _ => registry::get_provider_registry()
+            .into_iter()
+            .find(|p| p.id == provider)
+            .and_then(|p| p.base_url),
+    }
+}
+
+fn is_chat_model(id: &str) -> bool {
+    let lower = id.to_lowercase();
+    !NON_CHAT_MARKERS.iter().any(|m| lower.contains(m))
+}
+
+/// Extract model ids from a provider's models-list response, keeping only
+/// chat-capable models where the API distinguishes them. Sorted, deduplicated.
+pub fn parse_models_response(provider: &str, body: &Value) -> Vec<String> {
+    let mut ids: Vec<String> = match provider {
+        "ollama" => body["models"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|m| m["name"].as_str().or_else(|| m["model"].as_str()))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        "gemini" => body["models"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter(|m| {
+                        m["supportedGenerationMethods"]
+                            .as_array()
+                            .is_none_or(|methods| {
+                                methods
+                                    .iter()
+                                    .any(|x| x.as_str() == Some("generateContent"))
+                            })
+                    })
+                    .filter_map(|m| m["name"].as_str())
+                    .map(|name| name.trim_start_matches("models/").to_string())
+                    .filter(|id| is_chat_model(id))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => body["data"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|m| m["id"].as_str())
+                    .filter(|id| provider == "claude" || is_chat_model(id))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// Error for a failed models request. Rejected credentials get a message
+/// pointing at the key instead of the raw response body.
+pub fn models_request_error(provider: &str, status: u16, body: &str) -> AppError {
+    let name = provider_display_name(provider);
+    match status {
+        401 | 403 => AppError::AiProviderError(format!(
+            "{} rejected the API key while listing models. Check the key in Settings.",
+            name
+        )),
+        _ => AppError::AiProviderError(format!(
+            "{} models request failed ({}): {}",
+            name,
+            status,
+            body.chars().take(200).collect::<String>()
+        )),
+    }
+}
+
+fn require_key(provider: &str, config: &AppConfig) -> Result<String, AppError> {
+    config
+        .api_key
+        .clone()
+        .filter(|k| !k.trim().is_empty())
+        .ok_or_else(|| {
+            AppError::AiProviderError(format!(
+                "{} API key not set",
+                provider_display_name(provider)
+            ))
+        })
+}
+
+fn build_request(
+    client: &Client,
+    provider: &str,
+    config: &AppConfig,
+) -> Result<reqwest::RequestBuilder, AppError> {
+    let request = match provider {
+        "claude" => client
+            .get(ANTHROPIC_MODELS_URL)
+            .query(&[("limit", "1000")])
+            .header("x-api-key", require_key(provider, config)?)
+            .header("anthropic-version", ANTHROPIC_VERSION),
+        "gemini" => client.get(format!("{}/models", GEMINI_API_BASE)).query(&[
+            ("key", require_key(provider, config)?),
+            ("pageSize", "1000".into()),
+        ]),
+        "ollama" => {
+            let base = config
+                .ollama_base_url
+                .clone()
+                .unwrap_or_else(|| OLLAMA_BASE_URL.to_string());
+            client.get(format!("{}/api/tags", base.trim_end_matches('/')))
+        }
+        other => {
+            let base = openai_compatible_base(other, config).ok_or_else(|| {
+                AppError::AiProviderError(format!(
+                    "{} base URL not set. Configure it in Settings.",
+                    provider_display_name(other)
+                ))
+            })?;
+            client
+                .get(format!("{}/models", base.trim_end_matches('/')))
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", require_key(other, config)?),
+                )
+        }
+    };
+    Ok(request.timeout(MODELS_REQUEST_TIMEOUT))
+}
+
+fn cache_key(provider: &str, config: &AppConfig) -> String {
+    let key_hash = crate::agent::telemetry::hash_request(config.api_key.as_deref().unwrap_or(""));
+    let base = match provider {
+        "ollama" => config.ollama_base_url.clone(),
+        _ => openai_compatible_base(provider, config),
+    };
+    format!("{}|{}|{}", provider, base.unwrap_or_default(), key_hash)
+}
+
+/// Model ids available to `provider` with the stored credentials. Results
+/// are cached for a few minutes per provider, endpoint and key.
+pub async fn list_models(provider: &str, config: &AppConfig) -> Result<Vec<String>, AppError> {
+    let key = cache_key(provider, config);
+    if let Some((fetched_at, ids)) = models_cache().lock().unwrap().get(&key) {
+        if fetched_at.elapsed() < MODELS_CACHE_TTL {
+            return Ok(ids.clone());
+        }
+    }
+
+    let client = Client::new();
+    let response = build_request(&client, provider, config)?
+        .send()
+        .await
+        .map_err(|e| {
+            AppError::AiProviderError(format!(
+                "{} models request failed: {}",
+                provider_display_name(provider),
+                e
+            ))
+        })?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(models_request_error(provider, status.as_u16(), &body));
+    }
+    let body: Value = response.json().await.map_err(|e| {
+        AppError::AiProviderError(format!("Failed to parse models response: {}", e))
+    })?;
+
+    let ids = parse_models_response(provider, &body);
+    models_cache()
+        .lock()
+        .unwrap()
+        .insert(key, (Instant::now(), ids.clone()));
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_models_responses_per_provider() {
+        let openai = json!({
+            "object": "list",
+            "data": [
+                {"id": "gpt-5.2", "object": "model"},
+                {"id": "text-embedding-3-small", "object": "model"},
+                {"id": "o3-mini", "object": "model"},
+                {"id": "whisper-1", "object": "model"},
+                {"id": "gpt-4o-realtime-preview", "object": "model"},
+                {"id": "dall-e-3", "object": "model"}
+            ]
+        });
+        assert_eq!(
+            parse_models_response("openai", &openai),
+            vec!["gpt-5.2", "o3-mini"]
+        );
+
+        let claude = json!({
+            "data": [
+                {"type": "model", "id": "claude-sonnet-4-5-20250929", "display_name": "Sonnet 4.5"},
+                {"type": "model", "id": "claude-opus-4-6", "display_name": "Opus 4.6"}
+            ],
+            "has_more": false
+        });
+        assert_eq!(
+            parse_models_response("claude", &claude),
+            vec!["claude-opus-4-6", "claude-sonnet-4-5-20250929"]
+        );
+
+        let gemini = json!({
+            "models": [
+                {"name": "models/gemini-2.5-pro", "supportedGenerationMethods": ["generateContent", "countTokens"]},
+                {"name": "models/text-embedding-004", "supportedGenerationMethods": ["embedContent"]},
+                {"name": "models/gemini-2.5-flash", "supportedGenerationMethods": ["generateContent"]}
+            ]
+        });
+        assert_eq!(
+            parse_models_response("gemini", &gemini),
+            vec!["gemini-2.5-flash", "gemini-2.5-pro"]
+        );
+
+        let ollama = json!({
+            "models": [
+                {"name": "qwen2.5-coder:7b", "size": 4683087332u64},
+                {"name": "llama3.1:8b", "size": 4920753328u64}
+            ]
+        });
+        assert_eq!(
+            parse_models_response("ollama", &ollama),
+            vec!["llama3.1:8b", "qwen2.5-coder:7b"]
+        );
+
+        assert!(parse_models_response("deepseek", &json!({"error": "x"})).is_empty());
+    }
+
+    #[test]
+    fn test_auth_error_is_reported_without_raw_body() {
+        let body = r#"{"error":{"message":"Incorrect API key provided: sk-abc***","type":"invalid_request_error"}}"#;
+        let msg = models_request_error("openai", 401, body).to_string();
+        assert!(msg.contains("OpenAI rejected the API key"));
+        assert!(!msg.contains("sk-abc"));
+
+        let msg = models_request_error("deepseek", 500, "upstream down").to_string();
+        assert!(msg.contains("DeepSeek models request failed (500)"));
+
+        let mut config = AppConfig::default();
+        config.api_key = None;
+        let err = build_request(&Client::new(), "claude", &config).unwrap_err();
+        assert!(err.to_string().contains("API key not set"));
+        let runpod = build_request(&Client::new(), "runpod", &config).unwrap_err();
+        assert!(runpod.to_string().contains("base URL not set"));
+        assert!(build_request(&Client::new(), "ollama", &config).is_ok());
+    }
+}
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
 use crate::ai::message::ChatMessage;
-use crate::ai::provider::{AiProvider, StreamDelta, TokenUsage};
+use crate::ai::provider::{AiProvider, StreamDelta, StreamSignal, TokenUsage};
 use crate::ai::retry;
 use crate::error::AppError;
 
@@ -16,6 +16,7 @@ pub struct OllamaProvider {
     base_url: String,
     model: String,
     temperature: Option<f32>,
+    stop: Vec<String>,
 }
 
 impl OllamaProvider {
@@ -25,6 +26,7 @@ impl OllamaProvider {
             base_url: base_url.unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string()),
             model,
             temperature: None,
+            stop: Vec::new(),
         }
     }
 
@@ -33,9 +35,29 @@ impl OllamaProvider {
         self
     }
 
+    /// Stop generating at any of `stop`; the matched sequence is not returned.
+    pub fn with_stop_sequences(mut self, stop: Vec<String>) -> Self {
+        self.stop = stop;
+        self
+    }
+
     fn chat_endpoint(&self) -> String {
         format!("{}/api/chat", self.base_url)
     }
+
+    fn request_body(&self, messages: &[ChatMessage], stream: bool) -> OllamaRequest {
+        let options =
+            (self.temperature.is_some() || !self.stop.is_empty()).then(|| OllamaOptions {
+                temperature: self.temperature,
+                stop: self.stop.clone(),
+            });
+        OllamaRequest {
+            model: self.model.clone(),
+            messages: messages.iter().map(OllamaMessage::from).collect(),
+            stream,
+            options,
+        }
+    }
 }
 
 // --- Request / Response types for the Ollama Chat API ---
@@ -44,6 +66,8 @@ impl OllamaProvider {
 struct OllamaOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -106,19 +130,7 @@ impl AiProvider for OllamaProvider {
         messages: &[ChatMessage],
         _max_tokens: Option<u32>,
     ) -> Result<(String, Option<TokenUsage>), AppError> {
-        let ollama_messages: Vec<OllamaMessage> =
-            messages.iter().map(OllamaMessage::from).collect();
-
-        let options = self.temperature.map(|t| OllamaOptions {
-            temperature: Some(t),
-        });
-
-        let body = OllamaRequest {
-            model: self.model.clone(),
-            messages: ollama_messages,
-            stream: false,
-            options,
-        };
+        let body = self.request_body(messages, false);
 
         let response = retry::send_with_retry(
             || {
@@ -160,21 +172,9 @@ impl AiProvider for OllamaProvider {
     async fn stream(
         &self,
         messages: &[ChatMessage],
-        tx: mpsc::Sender<StreamDelta>,
+        tx: mpsc::Sender<StreamSignal>,
     ) -> Result<Option<TokenUsage>, AppError> {
-        let ollama_messages: Vec<OllamaMessage> =
-            messages.iter().map(OllamaMessage::from).collect();
-
-        let options = self.temperature.map(|t| OllamaOptions {
-            temperature: Some(t),
-        });
-
-        let body = OllamaRequest {
-            model: self.model.clone(),
-            messages: ollama_messages,
-            stream: true,
-            options,
-        };
+        let body = self.request_body(messages, true);
 
         let response = retry::send_with_retry(
             || {
@@ -229,10 +229,10 @@ impl AiProvider for OllamaProvider {
                     }
 
                     let _ = tx
-                        .send(StreamDelta {
+                        .send(StreamSignal::Delta(StreamDelta {
                             content,
                             done: is_done,
-                        })
+                        }))
                         .await;
 
                     if is_done {
@@ -244,12 +244,37 @@ impl AiProvider for OllamaProvider {
 
         // Ensure a done signal is always sent.
         let _ = tx
-            .send(StreamDelta {
+            .send(StreamSignal::Delta(StreamDelta {
                 content: String::new(),
                 done: true,
-            })
+            }))
             .await;
 
         Ok(tracked_usage)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: "user".to_string(),
+            content: "A 20mm cube".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_stop_sequences_in_request_body() {
+        let provider = OllamaProvider::new(None, "qwen2.5-coder".into())
+            .with_stop_sequences(vec!["</CODE>".to_string()]);
+        let body = serde_json::to_value(provider.request_body(&messages(), true)).unwrap();
+        assert_eq!(body["options"]["stop"], serde_json::json!(["</CODE>"]));
+        assert!(body["options"].get("temperature").is_none());
+
+        let plain = OllamaProvider::new(None, "qwen2.5-coder".into()).with_temperature(Some(0.2));
+        let body = serde_json::to_value(plain.request_body(&messages(), false)).unwrap();
+        assert!(body["options"].get("stop").is_none());
+    }
+}
@@ -5,12 +5,19 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
 use crate::ai::message::ChatMessage;
-use crate::ai::provider::{AiProvider, StreamDelta, TokenUsage};
+use crate::ai::provider::{AiProvider, StreamDelta, StreamSignal, TokenUsage};
 use crate::ai::retry;
-use crate::ai::streaming::parse_sse_events;
+use crate::ai::streaming::{is_sse_keep_alive, parse_sse_events};
 use crate::error::AppError;
 
 const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+/// Most stop sequences the Chat Completions API accepts.
+const MAX_STOP_SEQUENCES: usize = 4;
+/// Reasoning model families that reject the `stop` parameter.
+const NO_STOP_MODEL_PREFIXES: &[&str] = &["o1", "o3", "o4", "gpt-5"];
+/// Gateways known to send SSE comments while the model has not produced a
+/// token yet. Other OpenAI-compatible servers may stay silent for minutes.
+const KEEP_ALIVE_HOSTS: &[&str] = &["openrouter.ai"];
 
 pub struct OpenAiProvider {
     client: Client,
@@ -18,6 +25,7 @@ pub struct OpenAiProvider {
     model: String,
     base_url: String,
     temperature: Option<f32>,
+    stop: Vec<String>,
 }
 
 impl OpenAiProvider {
@@ -39,6 +47,7 @@ impl OpenAiProvider {
             model,
             base_url: url,
             temperature: None,
+            stop: Vec::new(),
         }
     }
 
@@ -47,6 +56,41 @@ impl OpenAiProvider {
         self
     }
 
+    /// Stop generating at any of `stop`; the matched sequence is not returned.
+    pub fn with_stop_sequences(mut self, stop: Vec<String>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// The `stop` parameter, omitted for models that reject it so the request
+    /// still succeeds and only the trailing prose is kept.
+    fn stop_param(&self) -> Option<Vec<String>> {
+        let model = self.model.to_ascii_lowercase();
+        if self.stop.is_empty() || NO_STOP_MODEL_PREFIXES.iter().any(|p| model.starts_with(p)) {
+            return None;
+        }
+        Some(self.stop.iter().take(MAX_STOP_SEQUENCES).cloned().collect())
+    }
+
+    fn request_body(
+        &self,
+        messages: &[ChatMessage],
+        max_tokens: Option<u32>,
+        stream: bool,
+    ) -> OpenAiRequest {
+        OpenAiRequest {
+            model: self.model.clone(),
+            messages: messages.iter().map(OpenAiMessage::from).collect(),
+            stream,
+            max_tokens,
+            stream_options: stream.then_some(OpenAiStreamOptions {
+                include_usage: true,
+            }),
+            temperature: self.temperature,
+            stop: self.stop_param(),
+        }
+    }
+
     fn chat_endpoint(&self) -> String {
         format!("{}/chat/completions", self.base_url)
     }
@@ -65,6 +109,8 @@ struct OpenAiRequest {
     stream_options: Option<OpenAiStreamOptions>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
 }
 
 #[derive(Serialize)]
@@ -143,17 +189,7 @@ impl AiProvider for OpenAiProvider {
         messages: &[ChatMessage],
         max_tokens: Option<u32>,
     ) -> Result<(String, Option<TokenUsage>), AppError> {
-        let openai_messages: Vec<OpenAiMessage> =
-            messages.iter().map(OpenAiMessage::from).collect();
-
-        let body = OpenAiRequest {
-            model: self.model.clone(),
-            messages: openai_messages,
-            stream: false,
-            max_tokens,
-            stream_options: None,
-            temperature: self.temperature,
-        };
+        let body = self.request_body(messages, max_tokens, false);
 
         let response = retry::send_with_retry(
             || {
@@ -205,21 +241,9 @@ impl AiProvider for OpenAiProvider {
     async fn stream(
         &self,
         messages: &[ChatMessage],
-        tx: mpsc::Sender<StreamDelta>,
+        tx: mpsc::Sender<StreamSignal>,
     ) -> Result<Option<TokenUsage>, AppError> {
-        let openai_messages: Vec<OpenAiMessage> =
-            messages.iter().map(OpenAiMessage::from).collect();
-
-        let body = OpenAiRequest {
-            model: self.model.clone(),
-            messages: openai_messages,
-            stream: true,
-            max_tokens: None,
-            stream_options: Some(OpenAiStreamOptions {
-                include_usage: true,
-            }),
-            temperature: self.temperature,
-        };
+        let body = self.request_body(messages, None, true);
 
         let response = retry::send_with_retry(
             || {
@@ -250,6 +274,9 @@ impl AiProvider for OpenAiProvider {
                 let event_block = buffer[..pos].to_string();
                 buffer = buffer[pos + 2..].to_string();
 
+                if is_sse_keep_alive(&event_block) {
+                    let _ = tx.send(StreamSignal::KeepAlive).await;
+                }
                 let events = parse_sse_events(&event_block);
                 for event_data in events {
                     if let Ok(chunk) = serde_json::from_str::<OpenAiStreamChunk>(&event_data) {
@@ -265,10 +292,10 @@ impl AiProvider for OpenAiProvider {
                                 // Emit content deltas (standard models)
                                 if let Some(ref content) = delta.content {
                                     let _ = tx
-                                        .send(StreamDelta {
+                                        .send(StreamSignal::Delta(StreamDelta {
                                             content: content.clone(),
                                             done: false,
-                                        })
+                                        }))
                                         .await;
                                 }
                                 // Emit reasoning_content deltas as regular content
@@ -277,20 +304,20 @@ impl AiProvider for OpenAiProvider {
                                 if delta.content.is_none() {
                                     if let Some(ref reasoning) = delta.reasoning_content {
                                         let _ = tx
-                                            .send(StreamDelta {
+                                            .send(StreamSignal::Delta(StreamDelta {
                                                 content: reasoning.clone(),
                                                 done: false,
-                                            })
+                                            }))
                                             .await;
                                     }
                                 }
                             }
                             if choice.finish_reason.is_some() {
                                 let _ = tx
-                                    .send(StreamDelta {
+                                    .send(StreamSignal::Delta(StreamDelta {
                                         content: String::new(),
                                         done: true,
-                                    })
+                                    }))
                                     .await;
                             }
                         }
@@ -301,12 +328,84 @@ impl AiProvider for OpenAiProvider {
 
         // Ensure a done signal is always sent.
         let _ = tx
-            .send(StreamDelta {
+            .send(StreamSignal::Delta(StreamDelta {
                 content: String::new(),
                 done: true,
-            })
+            }))
             .await;
 
         Ok(tracked_usage)
     }
+
+    /// Only gateways in `KEEP_ALIVE_HOSTS` send SSE comments before the first
+    /// token; a silent self-hosted server must not be reported as stalled.
+    fn detects_keep_alives(&self) -> bool {
+        let url = self.base_url.to_ascii_lowercase();
+        KEEP_ALIVE_HOSTS.iter().any(|host| url.contains(host))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: "user".to_string(),
+            content: "A 20mm cube".to_string(),
+        }]
+    }
+
+    fn code_stops() -> Vec<String> {
+        ["</CODE>", "\n\n\n", "###", "Explanation:", "Note:"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_stop_sequences_in_request_body() {
+        let provider = OpenAiProvider::new("key".into(), "gpt-4o".into(), None)
+            .with_stop_sequences(code_stops());
+        let body = serde_json::to_value(provider.request_body(&messages(), None, true)).unwrap();
+        // Capped at the API's limit of four.
+        assert_eq!(
+            body["stop"],
+            serde_json::json!(["</CODE>", "\n\n\n", "###", "Explanation:"])
+        );
+        assert_eq!(body["stream_options"]["include_usage"], true);
+
+        let plain = OpenAiProvider::new("key".into(), "gpt-4o".into(), None);
+        let body = serde_json::to_value(plain.request_body(&messages(), Some(256), false)).unwrap();
+        assert!(body.get("stop").is_none());
+        assert!(body.get("stream_options").is_none());
+    }
+
+    #[test]
+    fn test_keep_alives_detected_only_for_known_gateways() {
+        let openrouter = OpenAiProvider::new(
+            "key".into(),
+            "gpt-4o".into(),
+            Some("https://openrouter.ai/api/v1".into()),
+        );
+        assert!(openrouter.detects_keep_alives());
+        let local = OpenAiProvider::new(
+            "key".into(),
+            "qwen".into(),
+            Some("http://localhost:8000/v1".into()),
+        );
+        assert!(!local.detects_keep_alives());
+        assert!(!OpenAiProvider::new("key".into(), "gpt-4o".into(), None).detects_keep_alives());
+    }
+
+    #[test]
+    fn test_reasoning_models_omit_stop_sequences() {
+        for model in ["o3-mini", "o4-mini", "gpt-5"] {
+            let provider = OpenAiProvider::new("key".into(), model.into(), None)
+                .with_stop_sequences(code_stops());
+            let body =
+                serde_json::to_value(provider.request_body(&messages(), None, false)).unwrap();
+            assert!(body.get("stop").is_none(), "{} rejects stop", model);
+        }
+    }
 }
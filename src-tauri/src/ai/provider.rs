@@ -29,6 +29,20 @@ pub struct StreamDelta {
     pub done: bool,
 }
 
+/// What a provider stream sends: content, or a sign the connection is alive.
+#[derive(Debug, Clone)]
+pub enum StreamSignal {
+    Delta(StreamDelta),
+    /// Transport keep-alive (SSE ping or comment) that carries no content.
+    KeepAlive,
+}
+
+impl From<StreamDelta> for StreamSignal {
+    fn from(delta: StreamDelta) -> Self {
+        Self::Delta(delta)
+    }
+}
+
 #[async_trait]
 #[allow(dead_code)]
 pub trait AiProvider: Send + Sync {
@@ -44,8 +58,14 @@ pub trait AiProvider: Send + Sync {
     async fn stream(
         &self,
         messages: &[ChatMessage],
-        tx: mpsc::Sender<StreamDelta>,
+        tx: mpsc::Sender<StreamSignal>,
     ) -> Result<Option<TokenUsage>, AppError>;
+
+    /// Whether `stream` sends `StreamSignal::KeepAlive`. Streams from
+    /// providers that cannot detect keep-alives are never treated as stalled.
+    fn detects_keep_alives(&self) -> bool {
+        false
+    }
 }
 
 #[cfg(test)]
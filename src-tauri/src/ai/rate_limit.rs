@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::ai::message::ChatMessage;
+use crate::ai::provider::{AiProvider, StreamSignal, TokenUsage};
+use crate::config::AppConfig;
+use crate::error::AppError;
+
+/// Requests-per-minute limiter: a token bucket holding one token that refills
+/// every `interval`, so permits are handed out at least `interval` apart.
+pub struct TokenBucket {
+    interval: Duration,
+    /// Earliest time the next permit may be used.
+    next_slot: Mutex<Instant>,
+}
+
+impl TokenBucket {
+    pub fn per_minute(rpm: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(60) / rpm.max(1),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until this caller's permit is due. Callers are served in the order
+    /// they reserve a slot, without holding the lock while sleeping.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// Provider name → (RPM limit, bucket).
+type BucketMap = HashMap<String, (u32, Arc<TokenBucket>)>;
+
+/// Buckets per provider, shared by every provider instance of a generation.
+fn buckets() -> &'static Mutex<BucketMap> {
+    static BUCKETS: OnceLock<Mutex<BucketMap>> = OnceLock::new();
+    BUCKETS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The bucket for `provider`, replaced when its RPM limit changed.
+fn shared_bucket(provider: &str, rpm: u32) -> Arc<TokenBucket> {
+    let mut buckets = buckets().lock().unwrap();
+    match buckets.get(provider) {
+        Some((limit, bucket)) if *limit == rpm => bucket.clone(),
+        _ => {
+            let bucket = Arc::new(TokenBucket::per_minute(rpm));
+            buckets.insert(provider.to_string(), (rpm, bucket.clone()));
+            bucket
+        }
+    }
+}
+
+/// Provider that awaits a bucket permit before each call.
+pub struct RateLimitedProvider {
+    inner: Box<dyn AiProvider>,
+    bucket: Arc<TokenBucket>,
+}
+
+impl RateLimitedProvider {
+    pub fn new(inner: Box<dyn AiProvider>, bucket: Arc<TokenBucket>) -> Self {
+        Self { inner, bucket }
+    }
+}
+
+#[async_trait]
+impl AiProvider for RateLimitedProvider {
+    async fn complete(
+        &self,
+        messages: &[ChatMessage],
+        max_tokens: Option<u32>,
+    ) -> Result<(String, Option<TokenUsage>), AppError> {
+        self.bucket.acquire().await;
+        self.inner.complete(messages, max_tokens).await
+    }
+
+    async fn stream(
+        &self,
+        messages: &[ChatMessage],
+        tx: mpsc::Sender<StreamSignal>,
+    ) -> Result<Option<TokenUsage>, AppError> {
+        self.bucket.acquire().await;
+        self.inner.stream(messages, tx).await
+    }
+
+    fn detects_keep_alives(&self) -> bool {
+        self.inner.detects_keep_alives()
+    }
+}
+
+/// Wrap `provider` in the shared bucket of `config.ai_provider` when
+/// `provider_rpm_limit` is set.
+pub fn limit_provider(provider: Box<dyn AiProvider>, config: &AppConfig) -> Box<dyn AiProvider> {
+    match config.provider_rpm_limit.filter(|rpm| *rpm > 0) {
+        Some(rpm) => Box::new(RateLimitedProvider::new(
+            provider,
+            shared_bucket(&config.ai_provider, rpm),
+        )),
+        None => provider,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TimestampProvider {
+        calls: Arc<Mutex<Vec<Instant>>>,
+    }
+
+    #[async_trait]
+    impl AiProvider for TimestampProvider {
+        async fn complete(
+            &self,
+            _messages: &[ChatMessage],
+            _max_tokens: Option<u32>,
+        ) -> Result<(String, Option<TokenUsage>), AppError> {
+            self.calls.lock().unwrap().push(Instant::now());
+            Ok((String::new(), None))
+        }
+
+        async fn stream(
+            &self,
+            _messages: &[ChatMessage],
+            _tx: mpsc::Sender<StreamSignal>,
+        ) -> Result<Option<TokenUsage>, AppError> {
+            self.calls.lock().unwrap().push(Instant::now());
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_low_rpm_spaces_concurrent_requests() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let bucket = Arc::new(TokenBucket::per_minute(1200));
+        let provider = Arc::new(RateLimitedProvider::new(
+            Box::new(TimestampProvider {
+                calls: calls.clone(),
+            }),
+            bucket.clone(),
+        ));
+
+        let start = Instant::now();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let provider = provider.clone();
+                tokio::spawn(async move { provider.complete(&[], None).await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+
+        let mut calls = calls.lock().unwrap().clone();
+        calls.sort();
+        assert_eq!(calls.len(), 4);
+        assert_eq!(bucket.interval, Duration::from_millis(50));
+        // The k-th permit is not due before `start + k * interval`.
+        for (k, at) in calls.iter().enumerate() {
+            assert!(*at - start >= bucket.interval * k as u32);
+        }
+    }
+
+    #[test]
+    fn test_bucket_shared_per_provider_until_limit_changes() {
+        let first = shared_bucket("rate-limit-test", 30);
+        assert!(Arc::ptr_eq(&first, &shared_bucket("rate-limit-test", 30)));
+        let changed = shared_bucket("rate-limit-test", 60);
+        assert!(!Arc::ptr_eq(&first, &changed));
+        assert_eq!(changed.interval, Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use crate::ai::message::ChatMessage;
+use crate::ai::provider::{AiProvider, StreamDelta, StreamSignal, TokenUsage};
+use crate::config::AppConfig;
+use crate::error::AppError;
+
+/// Responses kept before the oldest is evicted.
+const MAX_CACHED_RESPONSES: usize = 256;
+
+/// Content-addressed store of provider responses: hash of provider, model,
+/// sampling parameters and the full message list → response text.
+pub struct ResponseCache {
+    capacity: usize,
+    entries: Mutex<(HashMap<String, String>, VecDeque<String>)>,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new((HashMap::new(), VecDeque::new())),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.entries.lock().unwrap().0.get(key).cloned()
+    }
+
+    /// Store `response`, evicting the oldest entries beyond capacity.
+    pub fn insert(&self, key: String, response: String) {
+        let mut guard = self.entries.lock().unwrap();
+        let (map, order) = &mut *guard;
+        if map.insert(key.clone(), response).is_none() {
+            order.push_back(key);
+        }
+        while order.len() > self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                map.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Cache shared by every provider instance, so a retry loop and a sibling
+/// part generation hit the same entries.
+fn global_cache() -> Arc<ResponseCache> {
+    static CACHE: OnceLock<Arc<ResponseCache>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| Arc::new(ResponseCache::new(MAX_CACHED_RESPONSES)))
+        .clone()
+}
+
+/// Provider that answers repeated identical requests from a `ResponseCache`
+/// with zero token usage.
+pub struct CachedProvider {
+    inner: Box<dyn AiProvider>,
+    cache: Arc<ResponseCache>,
+    /// Provider, model and temperature, hashed into every key.
+    scope: String,
+}
+
+impl CachedProvider {
+    pub fn new(inner: Box<dyn AiProvider>, cache: Arc<ResponseCache>, scope: String) -> Self {
+        Self {
+            inner,
+            cache,
+            scope,
+        }
+    }
+
+    fn key(&self, messages: &[ChatMessage], max_tokens: Option<u32>, streamed: bool) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.scope.as_bytes());
+        hasher.update(format!("|max_tokens:{:?}|stream:{}|", max_tokens, streamed).as_bytes());
+        for message in messages {
+            hasher.update((message.role.len() as u64).to_le_bytes());
+            hasher.update(message.role.as_bytes());
+            hasher.update((message.content.len() as u64).to_le_bytes());
+            hasher.update(message.content.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[async_trait]
+impl AiProvider for CachedProvider {
+    async fn complete(
+        &self,
+        messages: &[ChatMessage],
+        max_tokens: Option<u32>,
+    ) -> Result<(String, Option<TokenUsage>), AppError> {
+        let key = self.key(messages, max_tokens, false);
+        if let Some(response) = self.cache.get(&key) {
+            return Ok((response, Some(TokenUsage::default())));
+        }
+        let (response, usage) = self.inner.complete(messages, max_tokens).await?;
+        if !response.trim().is_empty() {
+            self.cache.insert(key, response.clone());
+        }
+        Ok((response, usage))
+    }
+
+    async fn stream(
+        &self,
+        messages: &[ChatMessage],
+        tx: mpsc::Sender<StreamSignal>,
+    ) -> Result<Option<TokenUsage>, AppError> {
+        let key = self.key(messages, None, true);
+        if let Some(response) = self.cache.get(&key) {
+            for delta in [
+                StreamDelta {
+                    content: response,
+                    done: false,
+                },
+                StreamDelta {
+                    content: String::new(),
+                    done: true,
+                },
+            ] {
+                if tx.send(delta.into()).await.is_err() {
+                    break;
+                }
+            }
+            return Ok(Some(TokenUsage::default()));
+        }
+
+        // Forward the inner stream unchanged while collecting its text.
+        let (inner_tx, mut inner_rx) = mpsc::channel::<StreamSignal>(100);
+        let forward = async {
+            let mut text = String::new();
+            let mut delivered = true;
+            while let Some(signal) = inner_rx.recv().await {
+                if let StreamSignal::Delta(delta) = &signal {
+                    text.push_str(&delta.content);
+                }
+                if delivered && tx.send(signal).await.is_err() {
+                    delivered = false;
+                }
+            }
+            (text, delivered)
+        };
+        let (result, (text, delivered)) =
+            tokio::join!(self.inner.stream(messages, inner_tx), forward);
+        let usage = result?;
+        // A stream the caller abandoned may have been cut short.
+        if delivered && !text.trim().is_empty() {
+            self.cache.insert(key, text);
+        }
+        Ok(usage)
+    }
+
+    fn detects_keep_alives(&self) -> bool {
+        self.inner.detects_keep_alives()
+    }
+}
+
+/// Wrap `provider` in the shared response cache when
+/// `cache_provider_responses` is on. Calls sampled above temperature 0, such
+/// as consensus candidates, are never cached: their variation is the point.
+pub fn cache_provider(
+    provider: Box<dyn AiProvider>,
+    config: &AppConfig,
+    temperature: Option<f32>,
+) -> Box<dyn AiProvider> {
+    if !config.cache_provider_responses || temperature.is_some_and(|t| t > 0.0) {
+        return provider;
+    }
+    let scope = format!(
+        "{}|{}|temp:{:?}",
+        config.ai_provider, config.model, temperature
+    );
+    Box::new(CachedProvider::new(provider, global_cache(), scope))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    struct CountingProvider {
+        calls: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl AiProvider for CountingProvider {
+        async fn complete(
+            &self,
+            messages: &[ChatMessage],
+            _max_tokens: Option<u32>,
+        ) -> Result<(String, Option<TokenUsage>), AppError> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst);
+            let usage = TokenUsage {
+                input_tokens: 100,
+                output_tokens: 50,
+            };
+            Ok((format!("reply {} to {}", n, messages.len()), Some(usage)))
+        }
+
+        async fn stream(
+            &self,
+            _messages: &[ChatMessage],
+            tx: mpsc::Sender<StreamSignal>,
+        ) -> Result<Option<TokenUsage>, AppError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            for chunk in ["result = ", "Box(1, 2, 3)"] {
+                let _ = tx
+                    .send(
+                        StreamDelta {
+                            content: chunk.to_string(),
+                            done: false,
+                        }
+                        .into(),
+                    )
+                    .await;
+            }
+            Ok(Some(TokenUsage {
+                input_tokens: 10,
+                output_tokens: 5,
+            }))
+        }
+    }
+
+    fn provider(calls: &Arc<AtomicU32>) -> CachedProvider {
+        CachedProvider::new(
+            Box::new(CountingProvider {
+                calls: calls.clone(),
+            }),
+            Arc::new(ResponseCache::new(8)),
+            "claude|test-model|temp:None".to_string(),
+        )
+    }
+
+    fn messages(user: &str) -> Vec<ChatMessage> {
+        vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "You write build123d code.".to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: user.to_string(),
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_identical_messages_hit_cache_with_zero_usage() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let provider = provider(&calls);
+
+        let (first, usage) = provider.complete(&messages("bracket"), None).await.unwrap();
+        assert_eq!(usage.unwrap().total(), 150);
+        let (second, usage) = provider.complete(&messages("bracket"), None).await.unwrap();
+        assert_eq!(second, first);
+        assert_eq!(usage.unwrap().total(), 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_differing_requests_miss_cache() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let provider = provider(&calls);
+
+        provider.complete(&messages("bracket"), None).await.unwrap();
+        provider.complete(&messages("hinge"), None).await.unwrap();
+        provider
+            .complete(&messages("bracket"), Some(512))
+            .await
+            .unwrap();
+        let mut longer = messages("bracket");
+        longer.push(ChatMessage {
+            role: "user".to_string(),
+            content: "Fix the fillet error".to_string(),
+        });
+        provider.complete(&longer, None).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(provider.cache.entries.lock().unwrap().0.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_stream_replays_cached_text() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let provider = provider(&calls);
+
+        let (tx, mut rx) = mpsc::channel(16);
+        provider.stream(&messages("plate"), tx).await.unwrap();
+        let mut first = String::new();
+        while let Some(StreamSignal::Delta(delta)) = rx.recv().await {
+            first.push_str(&delta.content);
+        }
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let usage = provider.stream(&messages("plate"), tx).await.unwrap();
+        let mut second = String::new();
+        while let Some(StreamSignal::Delta(delta)) = rx.recv().await {
+            second.push_str(&delta.content);
+        }
+        assert_eq!(first, "result = Box(1, 2, 3)");
+        assert_eq!(second, first);
+        assert_eq!(usage.unwrap().total(), 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_cache_evicts_oldest_beyond_capacity() {
+        let cache = ResponseCache::new(2);
+        cache.insert("a".into(), "1".into());
+        cache.insert("b".into(), "2".into());
+        cache.insert("c".into(), "3".into());
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.get("b").as_deref(), Some("2"));
+        assert_eq!(cache.get("c").as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn test_only_enabled_for_deterministic_calls() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let mut config = AppConfig::default();
+        let boxed = || -> Box<dyn AiProvider> {
+            Box::new(CountingProvider {
+                calls: calls.clone(),
+            })
+        };
+        assert!(!config.cache_provider_responses);
+        config.cache_provider_responses = true;
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        for (temperature, expected_calls) in [(None, 1), (Some(0.0), 1), (Some(0.7), 2)] {
+            calls.store(0, Ordering::SeqCst);
+            let provider = cache_provider(boxed(), &config, temperature);
+            let user = format!("temperature {:?}", temperature);
+            rt.block_on(async {
+                provider.complete(&messages(&user), None).await.unwrap();
+                provider.complete(&messages(&user), None).await.unwrap();
+            });
+            assert_eq!(calls.load(Ordering::SeqCst), expected_calls);
+        }
+    }
+}
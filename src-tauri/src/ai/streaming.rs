@@ -1,3 +1,13 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+use crate::ai::provider::{AiProvider, StreamDelta, StreamSignal};
+
+/// How long a keep-alive-aware stream may go without content or keep-alives
+/// before it counts as stalled.
+pub const STREAM_STALL_TIMEOUT: Duration = Duration::from_secs(120);
+
 /// Parse a chunk of SSE data into individual event data strings.
 /// SSE format: lines starting with "data: " followed by JSON, separated by blank lines.
 /// The special "data: [DONE]" marker signals the end of the stream.
@@ -16,6 +26,84 @@ pub fn parse_sse_events(buffer: &str) -> Vec<String> {
     events
 }
 
+/// Whether an SSE event block is a keep-alive: a comment line (`: ping`) or
+/// an `event: ping`.
+pub fn is_sse_keep_alive(block: &str) -> bool {
+    block.lines().any(|line| {
+        let line = line.trim();
+        line.starts_with(':') || line == "event: ping"
+    })
+}
+
+/// Error text for a stream reported as `StreamProgress::Stalled`.
+pub fn stalled_message() -> String {
+    format!(
+        "Provider stream stalled: no content or keep-alive for {} seconds",
+        STREAM_STALL_TIMEOUT.as_secs()
+    )
+}
+
+/// A provider stream as seen by a consumer.
+#[derive(Debug)]
+pub enum StreamProgress {
+    Delta(StreamDelta),
+    /// A keep-alive arrived before any content: the model is still thinking.
+    Thinking,
+    /// Neither content nor keep-alives arrived within the stall timeout.
+    Stalled,
+}
+
+/// Reads a provider stream, telling a thinking model apart from a stalled one.
+pub struct StreamWatch {
+    rx: mpsc::Receiver<StreamSignal>,
+    stall_timeout: Option<Duration>,
+    content_seen: bool,
+}
+
+impl StreamWatch {
+    /// With `stall_timeout` of `None` the stream is never reported as stalled.
+    pub fn new(rx: mpsc::Receiver<StreamSignal>, stall_timeout: Option<Duration>) -> Self {
+        Self {
+            rx,
+            stall_timeout,
+            content_seen: false,
+        }
+    }
+
+    /// Stall detection only for providers that report keep-alives, so the
+    /// others keep streaming exactly as before.
+    pub fn for_provider(provider: &dyn AiProvider, rx: mpsc::Receiver<StreamSignal>) -> Self {
+        let stall_timeout = provider
+            .detects_keep_alives()
+            .then_some(STREAM_STALL_TIMEOUT);
+        Self::new(rx, stall_timeout)
+    }
+
+    /// Next step of the stream, or `None` once the provider closed it.
+    /// Keep-alives after the first content are absorbed.
+    pub async fn next(&mut self) -> Option<StreamProgress> {
+        loop {
+            let signal = match self.stall_timeout {
+                Some(limit) => match tokio::time::timeout(limit, self.rx.recv()).await {
+                    Ok(signal) => signal?,
+                    Err(_) => return Some(StreamProgress::Stalled),
+                },
+                None => self.rx.recv().await?,
+            };
+            match signal {
+                StreamSignal::Delta(delta) => {
+                    self.content_seen = true;
+                    return Some(StreamProgress::Delta(delta));
+                }
+                StreamSignal::KeepAlive if !self.content_seen => {
+                    return Some(StreamProgress::Thinking);
+                }
+                StreamSignal::KeepAlive => {}
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,4 +137,70 @@ mod tests {
         assert_eq!(events.len(), 1);
         assert_eq!(events[0], "{\"x\":1}");
     }
+
+    #[test]
+    fn test_sse_keep_alive_blocks() {
+        assert!(is_sse_keep_alive(": keep-alive"));
+        assert!(is_sse_keep_alive("event: ping\ndata: {\"type\": \"ping\"}"));
+        assert!(!is_sse_keep_alive("event: content_block_delta\ndata: {\"x\":1}"));
+    }
+
+    fn delta(content: &str) -> StreamSignal {
+        StreamSignal::Delta(StreamDelta {
+            content: content.to_string(),
+            done: false,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_ping_only_period_then_content_is_not_a_stall() {
+        let (tx, rx) = mpsc::channel(16);
+        let mut watch = StreamWatch::new(rx, Some(Duration::from_millis(150)));
+        tokio::spawn(async move {
+            // Thinking for longer than the stall timeout, pinging throughout.
+            for _ in 0..4 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                tx.send(StreamSignal::KeepAlive).await.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            tx.send(delta("result = 1")).await.unwrap();
+            tx.send(StreamSignal::KeepAlive).await.unwrap();
+            tx.send(delta("")).await.unwrap();
+        });
+
+        let mut thinking = 0;
+        let mut content = String::new();
+        while let Some(progress) = watch.next().await {
+            match progress {
+                StreamProgress::Thinking => thinking += 1,
+                StreamProgress::Delta(d) => content.push_str(&d.content),
+                StreamProgress::Stalled => panic!("pings should keep the stream alive"),
+            }
+        }
+        assert_eq!(thinking, 4);
+        assert_eq!(content, "result = 1");
+    }
+
+    #[tokio::test]
+    async fn test_silence_without_pings_is_a_stall() {
+        let (tx, rx) = mpsc::channel(16);
+        let mut watch = StreamWatch::new(rx, Some(Duration::from_millis(50)));
+        tx.send(StreamSignal::KeepAlive).await.unwrap();
+        assert!(matches!(watch.next().await, Some(StreamProgress::Thinking)));
+        assert!(matches!(watch.next().await, Some(StreamProgress::Stalled)));
+        drop(tx);
+        assert!(watch.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_without_stall_timeout_silence_is_waited_out() {
+        let (tx, rx) = mpsc::channel(16);
+        let mut watch = StreamWatch::new(rx, None);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            tx.send(delta("late")).await.unwrap();
+        });
+        assert!(matches!(watch.next().await, Some(StreamProgress::Delta(_))));
+        assert!(watch.next().await.is_none());
+    }
 }
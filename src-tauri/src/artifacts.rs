@@ -0,0 +1,575 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::agent::{embedding_cache, telemetry};
+use crate::config::AppConfig;
+use crate::error::AppError;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+/// Scratch directories are removed by their writer; older ones are leftovers.
+const SCRATCH_MAX_AGE_MS: u64 = DAY_MS;
+const HEAVY_EXTENSIONS: &[&str] = &["stl", "step", "stp"];
+const RUN_META_FILE: &str = "meta.json";
+const RUN_PINS_FILE: &str = "pinned_by.json";
+const RUN_ATTEMPTS_DIR: &str = "attempts";
+
+/// Mirrors `AppConfig::allows_app_data_writes` for writers that have no
+/// config at hand, like the Python runner.
+static APP_DATA_WRITES: AtomicBool = AtomicBool::new(true);
+
+/// Called with the loaded config and again on every settings update.
+pub fn set_app_data_writes(allowed: bool) {
+    APP_DATA_WRITES.store(allowed, Ordering::SeqCst);
+}
+
+/// Whether the current config allows writes under app data.
+pub fn app_data_writes_allowed() -> bool {
+    APP_DATA_WRITES.load(Ordering::SeqCst)
+}
+
+/// App data root: `<config dir>/cadai-studio`, or the OS temp directory when
+/// there is no config directory.
+pub fn data_root() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("cadai-studio")
+}
+
+fn runs_dir(root: &Path) -> PathBuf {
+    root.join("runs")
+}
+
+fn scratch_root(root: &Path) -> PathBuf {
+    root.join("scratch")
+}
+
+fn exports_root(root: &Path) -> PathBuf {
+    root.join("exports")
+}
+
+/// Where scratch directories go: under the app data root, or in the OS temp
+/// directory when safe mode forbids app data writes.
+fn scratch_parent(root: &Path, subdir: &str, app_data_writes: bool) -> PathBuf {
+    if app_data_writes {
+        root.join(subdir)
+    } else {
+        std::env::temp_dir().join("cadai-studio").join(subdir)
+    }
+}
+
+fn fresh_dir(parent: PathBuf, label: &str) -> Result<PathBuf, AppError> {
+    let dir = parent.join(format!("{}-{}", label, Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// New directory for one execution's temp files. The caller removes it when
+/// done; `clean` removes leftovers.
+pub fn scratch_dir(label: &str) -> Result<PathBuf, AppError> {
+    let writes = app_data_writes_allowed();
+    fresh_dir(scratch_parent(&data_root(), "scratch", writes), label)
+}
+
+/// Like `scratch_dir`, for exporters' intermediate files.
+pub fn export_scratch_dir(label: &str) -> Result<PathBuf, AppError> {
+    let writes = app_data_writes_allowed();
+    fresh_dir(scratch_parent(&data_root(), "exports", writes), label)
+}
+
+/// Lightweight record kept for every saved run, even after its STL is pruned.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunMeta {
+    pub run_id: String,
+    pub created_at_ms: u64,
+    pub success: bool,
+    #[serde(default)]
+    pub geometry_hash: Option<String>,
+}
+
+/// Run ids are UUIDs; anything else could escape the runs directory.
+fn run_dir(root: &Path, run_id: &str) -> Result<PathBuf, AppError> {
+    if run_id.is_empty()
+        || !run_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(AppError::ConfigError(format!(
+            "Invalid run id '{}'",
+            run_id
+        )));
+    }
+    Ok(runs_dir(root).join(run_id))
+}
+
+fn save_run_in(
+    root: &Path,
+    meta: &RunMeta,
+    code: Option<&str>,
+    stl: Option<&[u8]>,
+) -> Result<(), AppError> {
+    let dir = run_dir(root, &meta.run_id)?;
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(RUN_META_FILE), serde_json::to_vec_pretty(meta)?)?;
+    if let Some(code) = code {
+        std::fs::write(dir.join("final.py"), code)?;
+    }
+    if let Some(stl) = stl {
+        std::fs::write(dir.join("final.stl"), stl)?;
+    }
+    Ok(())
+}
+
+/// Save a finished run under `runs/<run_id>/` and apply the retention policy.
+/// Does nothing when safe mode forbids app data writes.
+pub fn record_run(config: &AppConfig, meta: &RunMeta, code: Option<&str>, stl: Option<&[u8]>) {
+    if !config.allows_app_data_writes() {
+        return;
+    }
+    let root = data_root();
+    if let Err(e) = save_run_in(&root, meta, code, stl) {
+        eprintln!("saving run artifacts failed: {}", e);
+    }
+    let policy = RetentionPolicy::from_config(config);
+    if let Err(e) = clean_in(&root, &policy, telemetry::now_ms(), false) {
+        eprintln!("artifact cleanup failed: {}", e);
+    }
+}
+
+fn save_attempt_code_in(
+    root: &Path,
+    run_id: &str,
+    sha256: &str,
+    code: &str,
+) -> Result<(), AppError> {
+    let dir = run_dir(root, run_id)?.join(RUN_ATTEMPTS_DIR);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(format!("{}.py", sha256)), code)?;
+    Ok(())
+}
+
+/// Keep the full script of a validation attempt whose history entry was
+/// truncated, as `runs/<run_id>/attempts/<sha256>.py`. It expires with the
+/// run. Does nothing when safe mode forbids app data writes.
+pub fn save_attempt_code(config: &AppConfig, run_id: &str, sha256: &str, code: &str) {
+    if !config.allows_app_data_writes() {
+        return;
+    }
+    if let Err(e) = save_attempt_code_in(&data_root(), run_id, sha256, code) {
+        eprintln!("saving attempt code failed: {}", e);
+    }
+}
+
+fn pin_run_in(root: &Path, run_id: &str, project_path: &str) -> Result<(), AppError> {
+    let dir = run_dir(root, run_id)?;
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let pins_path = dir.join(RUN_PINS_FILE);
+    let mut pins: Vec<String> = std::fs::read_to_string(&pins_path)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+    if !pins.iter().any(|p| p == project_path) {
+        pins.push(project_path.to_string());
+        std::fs::write(pins_path, serde_json::to_vec_pretty(&pins)?)?;
+    }
+    Ok(())
+}
+
+/// Protect a run's artifacts from cleanup while the project at
+/// `project_path` still references it.
+pub fn pin_run(run_id: &str, project_path: &str) -> Result<(), AppError> {
+    pin_run_in(&data_root(), run_id, project_path)
+}
+
+/// Some project that pinned the run still exists and still names it.
+fn is_referenced_by_project(dir: &Path, run_id: &str) -> bool {
+    let pins: Vec<String> = std::fs::read_to_string(dir.join(RUN_PINS_FILE))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+    pins.iter().any(|path| {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+            .is_some_and(|project| project["generation"]["run_id"].as_str() == Some(run_id))
+    })
+}
+
+/// How long run artifacts are kept, and how many runs keep their STLs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_age_ms: u64,
+    pub keep_heavy_runs: usize,
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            max_age_ms: config.artifact_max_age_days as u64 * DAY_MS,
+            keep_heavy_runs: config.artifact_keep_heavy_runs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemovalReason {
+    /// The whole run was older than the maximum age.
+    Expired,
+    /// STL/STEP files beyond the newest `keep_heavy_runs` runs.
+    HeavyPruned,
+    /// Scratch directory its writer never removed.
+    StaleScratch,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovedArtifact {
+    pub path: String,
+    pub bytes: u64,
+    pub reason: RemovalReason,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanupReport {
+    pub dry_run: bool,
+    pub removed: Vec<RemovedArtifact>,
+    pub freed_bytes: u64,
+}
+
+fn modified_ms(path: &Path) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Bytes and file count under `path`, which may be a file or a directory.
+fn disk_usage(path: &Path) -> (u64, u64) {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return (0, 0);
+    };
+    if !meta.is_dir() {
+        return (meta.len(), 1);
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| disk_usage(&e.path()))
+                .fold((0, 0), |(b, f), (eb, ef)| (b + eb, f + ef))
+        })
+        .unwrap_or((0, 0))
+}
+
+fn entries(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default()
+}
+
+/// Saved runs, newest first. Runs without readable metadata are dated by
+/// their directory's modification time.
+fn saved_runs(root: &Path) -> Vec<(PathBuf, RunMeta)> {
+    let mut runs: Vec<(PathBuf, RunMeta)> = entries(&runs_dir(root))
+        .into_iter()
+        .filter(|p| p.is_dir())
+        .map(|dir| {
+            let meta = std::fs::read_to_string(dir.join(RUN_META_FILE))
+                .ok()
+                .and_then(|raw| serde_json::from_str(&raw).ok())
+                .unwrap_or_else(|| RunMeta {
+                    run_id: dir
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    created_at_ms: modified_ms(&dir),
+                    success: false,
+                    geometry_hash: None,
+                });
+            (dir, meta)
+        })
+        .collect();
+    runs.sort_by_key(|(_, meta)| std::cmp::Reverse(meta.created_at_ms));
+    runs
+}
+
+fn is_heavy(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| HEAVY_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Apply `policy` under `root`. Runs referenced by a saved project are never
+/// touched and do not count towards `keep_heavy_runs`.
+fn clean_in(
+    root: &Path,
+    policy: &RetentionPolicy,
+    now_ms: u64,
+    dry_run: bool,
+) -> Result<CleanupReport, AppError> {
+    let mut removed = Vec::new();
+    let mut mark = |path: PathBuf, reason: RemovalReason| {
+        removed.push(RemovedArtifact {
+            bytes: disk_usage(&path).0,
+            path: path.to_string_lossy().to_string(),
+            reason,
+        });
+    };
+
+    let mut heavy_runs_kept = 0;
+    for (dir, meta) in saved_runs(root) {
+        if is_referenced_by_project(&dir, &meta.run_id) {
+            continue;
+        }
+        if now_ms.saturating_sub(meta.created_at_ms) > policy.max_age_ms {
+            mark(dir, RemovalReason::Expired);
+            continue;
+        }
+        let heavy: Vec<PathBuf> = entries(&dir).into_iter().filter(|p| is_heavy(p)).collect();
+        if heavy.is_empty() {
+            continue;
+        }
+        heavy_runs_kept += 1;
+        if heavy_runs_kept > policy.keep_heavy_runs {
+            for file in heavy {
+                mark(file, RemovalReason::HeavyPruned);
+            }
+        }
+    }
+
+    for dir in entries(&scratch_root(root))
+        .into_iter()
+        .chain(entries(&exports_root(root)))
+    {
+        if now_ms.saturating_sub(modified_ms(&dir)) > SCRATCH_MAX_AGE_MS {
+            mark(dir, RemovalReason::StaleScratch);
+        }
+    }
+
+    if !dry_run {
+        for artifact in &removed {
+            let path = Path::new(&artifact.path);
+            if path.is_dir() {
+                std::fs::remove_dir_all(path)?;
+            } else {
+                std::fs::remove_file(path)?;
+            }
+        }
+    }
+    Ok(CleanupReport {
+        dry_run,
+        freed_bytes: removed.iter().map(|r| r.bytes).sum(),
+        removed,
+    })
+}
+
+/// Apply `policy` to the app data directory; with `dry_run`, only report
+/// what would be freed.
+pub fn clean(policy: &RetentionPolicy, dry_run: bool) -> Result<CleanupReport, AppError> {
+    clean_in(&data_root(), policy, telemetry::now_ms(), dry_run)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    Runs,
+    Caches,
+    Telemetry,
+    Exports,
+}
+
+/// Entries of the app data root making up each category. The embedding
+/// cache lives in the OS data directory instead and is added separately.
+const STORAGE_LAYOUT: &[(StorageCategory, &[&str])] = &[
+    (StorageCategory::Runs, &["runs", "scratch", "resumable"]),
+    (StorageCategory::Caches, &["mechanisms"]),
+    (
+        StorageCategory::Telemetry,
+        &["telemetry", "multipart_debug.log"],
+    ),
+    (StorageCategory::Exports, &["exports"]),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryUsage {
+    pub category: StorageCategory,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageUsage {
+    pub root: String,
+    pub categories: Vec<CategoryUsage>,
+    pub total_bytes: u64,
+}
+
+fn storage_usage_in(root: &Path, embedding_cache: Option<&Path>) -> StorageUsage {
+    let categories: Vec<CategoryUsage> = STORAGE_LAYOUT
+        .iter()
+        .map(|(category, names)| {
+            let mut paths: Vec<PathBuf> = names.iter().map(|name| root.join(name)).collect();
+            if *category == StorageCategory::Caches {
+                paths.extend(embedding_cache.map(Path::to_path_buf));
+            }
+            let (bytes, files) = paths
+                .iter()
+                .map(|path| disk_usage(path))
+                .fold((0, 0), |(b, f), (eb, ef)| (b + eb, f + ef));
+            CategoryUsage {
+                category: *category,
+                bytes,
+                files,
+            }
+        })
+        .collect();
+    StorageUsage {
+        root: root.to_string_lossy().to_string(),
+        total_bytes: categories.iter().map(|c| c.bytes).sum(),
+        categories,
+    }
+}
+
+pub fn storage_usage() -> StorageUsage {
+    let embedding_cache = embedding_cache::cache_path().ok();
+    storage_usage_in(&data_root(), embedding_cache.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_root() -> PathBuf {
+        std::env::temp_dir().join(format!("cadai-artifacts-{}", Uuid::new_v4()))
+    }
+
+    fn save(root: &Path, run_id: &str, created_at_ms: u64) {
+        let meta = RunMeta {
+            run_id: run_id.to_string(),
+            created_at_ms,
+            success: true,
+            geometry_hash: None,
+        };
+        save_run_in(
+            root,
+            &meta,
+            Some("result = Box(1, 1, 1)"),
+            Some(&[0u8; 100]),
+        )
+        .unwrap();
+    }
+
+    fn policy() -> RetentionPolicy {
+        RetentionPolicy {
+            max_age_ms: 10 * DAY_MS,
+            keep_heavy_runs: 2,
+        }
+    }
+
+    #[test]
+    fn test_retention_expires_old_runs_and_prunes_heavy_files() {
+        let root = test_root();
+        let now = 100 * DAY_MS;
+        save(&root, "run-old", now - 20 * DAY_MS);
+        save(&root, "run-1", now - 3 * DAY_MS);
+        save(&root, "run-2", now - 2 * DAY_MS);
+        save(&root, "run-3", now - DAY_MS);
+
+        let preview = clean_in(&root, &policy(), now, true).unwrap();
+        assert_eq!(preview.removed.len(), 2);
+        assert_eq!(
+            preview.freed_bytes,
+            disk_usage(&runs_dir(&root).join("run-old")).0 + 100
+        );
+        assert!(runs_dir(&root).join("run-old").exists());
+
+        let report = clean_in(&root, &policy(), now, false).unwrap();
+        assert_eq!(
+            report.removed.iter().map(|r| r.reason).collect::<Vec<_>>(),
+            vec![RemovalReason::HeavyPruned, RemovalReason::Expired]
+        );
+        let runs = runs_dir(&root);
+        assert!(!runs.join("run-old").exists());
+        assert!(!runs.join("run-1").join("final.stl").exists());
+        assert!(runs.join("run-1").join(RUN_META_FILE).exists());
+        assert!(runs.join("run-1").join("final.py").exists());
+        assert!(runs.join("run-2").join("final.stl").exists());
+        assert!(runs.join("run-3").join("final.stl").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_runs_referenced_by_saved_project_survive_cleanup() {
+        let root = test_root();
+        let now = 100 * DAY_MS;
+        save(&root, "run-kept", now - 50 * DAY_MS);
+        save(&root, "run-stale-pin", now - 50 * DAY_MS);
+
+        let project = root.join("project.cadai");
+        let other = root.join("other.cadai");
+        std::fs::write(&project, r#"{"generation":{"run_id":"run-kept"}}"#).unwrap();
+        std::fs::write(&other, r#"{"generation":{"run_id":"run-newer"}}"#).unwrap();
+        pin_run_in(&root, "run-kept", &project.to_string_lossy()).unwrap();
+        pin_run_in(&root, "run-stale-pin", &other.to_string_lossy()).unwrap();
+        pin_run_in(&root, "run-missing", &project.to_string_lossy()).unwrap();
+
+        clean_in(&root, &policy(), now, false).unwrap();
+        let runs = runs_dir(&root);
+        assert!(runs.join("run-kept").join("final.stl").exists());
+        assert!(!runs.join("run-stale-pin").exists());
+        assert!(!runs.join("run-missing").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_storage_usage_groups_by_category() {
+        let root = test_root();
+        save(&root, "run-1", 0);
+        std::fs::create_dir_all(root.join("telemetry")).unwrap();
+        std::fs::write(root.join("telemetry").join("traces.jsonl"), [0u8; 40]).unwrap();
+        // The embedding cache sits outside the root, in the OS data directory.
+        let cache_root = test_root();
+        std::fs::create_dir_all(&cache_root).unwrap();
+        let cache = cache_root.join("embedding-cache.json");
+        std::fs::write(&cache, [0u8; 25]).unwrap();
+
+        let usage = storage_usage_in(&root, Some(&cache));
+        let bytes = |category| {
+            usage
+                .categories
+                .iter()
+                .find(|c| c.category == category)
+                .unwrap()
+                .bytes
+        };
+        assert!(bytes(StorageCategory::Runs) > 100);
+        assert_eq!(bytes(StorageCategory::Telemetry), 40);
+        assert_eq!(bytes(StorageCategory::Exports), 0);
+        assert_eq!(bytes(StorageCategory::Caches), 25);
+        assert_eq!(
+            usage.total_bytes,
+            bytes(StorageCategory::Runs)
+                + bytes(StorageCategory::Telemetry)
+                + bytes(StorageCategory::Caches)
+        );
+        assert!(run_dir(&root, "../escape").is_err());
+        let _ = std::fs::remove_dir_all(&root);
+        let _ = std::fs::remove_dir_all(&cache_root);
+    }
+
+    #[test]
+    fn test_scratch_stays_out_of_app_data_in_safe_mode() {
+        let root = test_root();
+        assert_eq!(scratch_parent(&root, "scratch", true), root.join("scratch"));
+        let safe = scratch_parent(&root, "scratch", false);
+        assert!(!safe.starts_with(&root));
+        assert!(safe.starts_with(std::env::temp_dir()));
+    }
+}
@@ -0,0 +1,14 @@
+//! Writes the IPC JSON Schema into the frontend tree.
+//!
+//! Run `cargo run --bin export-ipc-schema` after changing `MultiPartEvent` or
+//! another exported payload type.
+
+fn main() {
+    let path =
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(cadai_studio_lib::IPC_SCHEMA_PATH);
+    if let Err(e) = std::fs::write(&path, cadai_studio_lib::ipc_schema_json()) {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+        std::process::exit(1);
+    }
+    println!("Wrote {}", path.display());
+}
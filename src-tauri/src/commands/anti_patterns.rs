@@ -0,0 +1,25 @@
+use crate::agent::anti_pattern_mining::{self, LearnedAntiPattern};
+use crate::error::AppError;
+
+/// Scan telemetry traces for recurring failures and queue draft anti-patterns
+/// for review. Returns only the drafts added by this run.
+#[tauri::command]
+pub async fn mine_anti_patterns(
+    min_occurrences: Option<usize>,
+) -> Result<Vec<LearnedAntiPattern>, AppError> {
+    let min_occurrences = min_occurrences.unwrap_or(anti_pattern_mining::DEFAULT_MIN_OCCURRENCES);
+    tokio::task::spawn_blocking(move || anti_pattern_mining::mine(min_occurrences))
+        .await
+        .map_err(|e| AppError::ConfigError(format!("Anti-pattern mining failed: {}", e)))?
+}
+
+#[tauri::command]
+pub fn get_pending_anti_patterns() -> Vec<LearnedAntiPattern> {
+    anti_pattern_mining::pending()
+}
+
+/// Accept a pending draft so retry strategies and repair prompts use it.
+#[tauri::command]
+pub fn approve_anti_pattern(id: String) -> Result<LearnedAntiPattern, AppError> {
+    anti_pattern_mining::approve(&id)
+}
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use tauri::State;
+
+use crate::ai::audit::AuditLog;
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Write provider-call audit records with `from_ms <= timestamp <= to_ms`
+/// (either bound optional) to `path` as JSONL. Returns the number written.
+#[tauri::command]
+pub fn export_audit_log(
+    from_ms: Option<u64>,
+    to_ms: Option<u64>,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, AppError> {
+    let config = state.config.lock().unwrap().clone();
+    AuditLog::from_config(&config)?.export(from_ms, to_ms, &PathBuf::from(path))
+}
+
+/// Delete every provider-call audit record.
+#[tauri::command]
+pub fn purge_audit_log(state: State<'_, AppState>) -> Result<(), AppError> {
+    let config = state.config.lock().unwrap().clone();
+    AuditLog::from_config(&config)?.purge()
+}
@@ -4,8 +4,9 @@ use base64::Engine;
 use serde::Serialize;
 use tauri::State;
 
+use crate::agent::assumptions;
 use crate::error::AppError;
-use crate::python::{detector, installer, runner, venv};
+use crate::python::{detector, env_lock, installer, runner, venv, version_watch};
 use crate::state::AppState;
 
 const IMPORT_TIMEOUT_MS: u64 = 60_000;
@@ -36,6 +37,23 @@ pub struct ExecuteResult {
     pub error: Option<String>,
     // Backward compatibility for existing frontend callers.
     pub stl_base64: Option<String>,
+    /// Per-variable objects when run with `export_all_solids`; the single
+    /// `result` STL fields stay empty then.
+    pub objects: Option<Vec<ExecutedObject>>,
+}
+
+/// One named geometry variable exported by `execute_code`.
+#[derive(Serialize)]
+pub struct ExecutedObject {
+    pub name: String,
+    pub stl_base64: String,
+    pub report: runner::ObjectReport,
+}
+
+/// What a successful runner call produced.
+enum RunnerOutput {
+    Single(runner::ExecutionResult),
+    Objects(runner::ObjectsExecutionResult),
 }
 
 #[derive(Serialize)]
@@ -46,6 +64,8 @@ pub struct PythonStatus {
     pub venv_ready: bool,
     pub build123d_installed: bool,
     pub build123d_version: Option<String>,
+    /// Difference from the known-good package snapshot, when one exists.
+    pub env_drift: Option<env_lock::EnvDrift>,
 }
 
 fn clamp_timeout(timeout_ms: Option<u64>) -> u64 {
@@ -70,10 +90,15 @@ fn collect_logs(stdout: &str, stderr: &str, error: Option<&str>) -> Vec<String>
     logs
 }
 
+/// Run `code` and return its `result` as STL. With `export_all_solids`, every
+/// top-level geometry variable assigned once (or only the `include` names)
+/// comes back as a separate named object instead.
 #[tauri::command]
 pub async fn execute_code(
     code: String,
     timeout_ms: Option<u64>,
+    export_all_solids: Option<bool>,
+    include: Option<Vec<String>>,
     state: State<'_, AppState>,
 ) -> Result<ExecuteResult, AppError> {
     let start = Instant::now();
@@ -102,6 +127,7 @@ pub async fn execute_code(
                 },
                 error: Some(stderr),
                 stl_base64: None,
+                objects: None,
             });
         }
     };
@@ -111,37 +137,69 @@ pub async fn execute_code(
     let venv_owned = venv_dir.clone();
     let runner_owned = runner_script.clone();
     let code_owned = code.clone();
+    let include = include.unwrap_or_default();
 
     let result = tokio::task::spawn_blocking(move || {
-        runner::execute_cad_with_timeout_ms(
-            &venv_owned,
-            &runner_owned,
-            &code_owned,
-            timeout_ms,
-        )
+        if export_all_solids.unwrap_or(false) {
+            runner::execute_cad_objects_with_timeout_ms(
+                &venv_owned,
+                &runner_owned,
+                &code_owned,
+                &include,
+                timeout_ms,
+            )
+            .map(RunnerOutput::Objects)
+        } else {
+            runner::execute_cad_with_timeout_ms(&venv_owned, &runner_owned, &code_owned, timeout_ms)
+                .map(RunnerOutput::Single)
+        }
     })
     .await;
 
     let duration_ms = start.elapsed().as_millis() as u64;
 
     match result {
-        Ok(Ok(exec_result)) => {
-            let stl_base64 =
-                base64::engine::general_purpose::STANDARD.encode(&exec_result.stl_data);
+        Ok(Ok(output)) => {
+            let engine = base64::engine::general_purpose::STANDARD;
+            let (stl_base64, objects, stdout, stderr) = match output {
+                RunnerOutput::Single(exec_result) => (
+                    Some(engine.encode(&exec_result.stl_data)),
+                    None,
+                    exec_result.stdout,
+                    exec_result.stderr,
+                ),
+                RunnerOutput::Objects(exec_result) => (
+                    None,
+                    Some(
+                        exec_result
+                            .objects
+                            .into_iter()
+                            .map(|(object, stl_data)| ExecutedObject {
+                                name: object.name,
+                                stl_base64: engine.encode(&stl_data),
+                                report: object.report,
+                            })
+                            .collect(),
+                    ),
+                    exec_result.stdout,
+                    exec_result.stderr,
+                ),
+            };
             Ok(ExecuteResult {
                 success: true,
                 artifacts: ExecutionArtifacts {
-                    stl_base64: Some(stl_base64.clone()),
+                    stl_base64: stl_base64.clone(),
                 },
-                stdout: exec_result.stdout.clone(),
-                stderr: exec_result.stderr.clone(),
-                logs: collect_logs(&exec_result.stdout, &exec_result.stderr, None),
+                logs: collect_logs(&stdout, &stderr, None),
+                stdout,
+                stderr,
                 timing: ExecutionTiming {
                     duration_ms,
                     timeout_ms,
                 },
                 error: None,
-                stl_base64: Some(stl_base64),
+                stl_base64,
+                objects,
             })
         }
         Ok(Err(e)) => {
@@ -158,6 +216,7 @@ pub async fn execute_code(
                 },
                 error: Some(message),
                 stl_base64: None,
+                objects: None,
             })
         }
         Err(join_err) => {
@@ -174,11 +233,19 @@ pub async fn execute_code(
                 },
                 error: Some(message),
                 stl_base64: None,
+                objects: None,
             })
         }
     }
 }
 
+/// TODO/FIXME/ASSUME/NOTE comments in `code`, for a "review these
+/// assumptions" list.
+#[tauri::command]
+pub fn extract_assumptions(code: String) -> Vec<assumptions::CodeAssumption> {
+    assumptions::extract_assumptions(&code)
+}
+
 #[tauri::command]
 pub async fn check_python(state: State<'_, AppState>) -> Result<PythonStatus, AppError> {
     // Check if Python is detected
@@ -219,6 +286,15 @@ pub async fn check_python(state: State<'_, AppState>) -> Result<PythonStatus, Ap
         None
     };
 
+    let env_drift = if venv_ready {
+        match (env_lock::load_lock(), env_lock::freeze(&venv_dir)) {
+            (Ok(Some(lock)), Ok(installed)) => Some(env_lock::compare(&lock, &installed)),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
     if venv_ready {
         *state
             .venv_path
@@ -234,6 +310,7 @@ pub async fn check_python(state: State<'_, AppState>) -> Result<PythonStatus, Ap
         venv_ready,
         build123d_installed,
         build123d_version,
+        env_drift,
     })
 }
 
@@ -271,6 +348,19 @@ pub async fn setup_python(state: State<'_, AppState>) -> Result<String, AppError
         .lock()
         .map_err(|_| AppError::ConfigError("Failed to update venv state".into()))? = Some(venv_dir);
 
+    // The first working setup becomes the known-good snapshot.
+    let allows_writes = state.config.lock().unwrap().allows_app_data_writes();
+    if allows_writes && env_lock::load_lock()?.is_none() {
+        if let Some(venv_dir) = state.venv_path.lock().unwrap().clone() {
+            let lock = env_lock::snapshot(
+                &venv_dir,
+                Some(info.version.clone()),
+                crate::agent::telemetry::now_ms(),
+            )?;
+            env_lock::save_lock(&lock)?;
+        }
+    }
+
     let b3d_ver_str = b3d_version.unwrap_or_else(|| "unknown".to_string());
     Ok(format!(
         "Python {} environment ready with Build123d {}",
@@ -278,6 +368,120 @@ pub async fn setup_python(state: State<'_, AppState>) -> Result<String, AppError
     ))
 }
 
+/// The known-good package snapshot captured after setup, if any.
+#[tauri::command]
+pub fn get_python_env_manifest() -> Result<Option<env_lock::EnvLock>, AppError> {
+    env_lock::load_lock()
+}
+
+fn require_lock() -> Result<env_lock::EnvLock, AppError> {
+    let lock = env_lock::load_lock()?.ok_or_else(|| {
+        AppError::ConfigError(
+            "No Python environment snapshot exists yet. Run Python setup first.".into(),
+        )
+    })?;
+    env_lock::ensure_same_platform(&lock)?;
+    Ok(lock)
+}
+
+/// Reinstall the locked package versions into the existing venv and report
+/// any drift that remains.
+#[tauri::command]
+pub async fn pin_python_env(state: State<'_, AppState>) -> Result<env_lock::EnvDrift, AppError> {
+    let lock = require_lock()?;
+    let venv_dir = venv::get_venv_dir()?;
+    if !venv::venv_exists(&venv_dir) {
+        return Err(AppError::ConfigError(
+            "Python environment not set up. Use restore to rebuild it from the snapshot.".into(),
+        ));
+    }
+    env_lock::install_locked(&venv_dir, &lock)?;
+    *state
+        .build123d_version
+        .lock()
+        .map_err(|_| AppError::ConfigError("Failed to update Build123d version state".into()))? =
+        installer::detect_build123d_version(&venv_dir);
+    Ok(env_lock::compare(&lock, &env_lock::freeze(&venv_dir)?))
+}
+
+/// Recreate the venv from scratch with exactly the locked package versions.
+/// The new venv is built beside the live one and only swapped in once every
+/// package installed, so a failed restore leaves the current venv working.
+#[tauri::command]
+pub async fn restore_python_env(state: State<'_, AppState>) -> Result<String, AppError> {
+    let lock = require_lock()?;
+    let info = detector::detect_python()?;
+    let venv_dir = venv::get_venv_dir()?;
+    let staging = venv_dir.with_extension("restore");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    let built = venv::create_venv(&info.path, &staging)
+        .and_then(|_| env_lock::install_locked(&staging, &lock));
+    if let Err(e) = built {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    venv::replace_venv(&staging, &venv_dir)?;
+
+    let b3d_version = installer::detect_build123d_version(&venv_dir);
+    *state
+        .build123d_version
+        .lock()
+        .map_err(|_| AppError::ConfigError("Failed to update Build123d version state".into()))? =
+        b3d_version.clone();
+    *state
+        .venv_path
+        .lock()
+        .map_err(|_| AppError::ConfigError("Failed to update venv state".into()))? = Some(venv_dir);
+
+    Ok(format!(
+        "Restored {} packages from the snapshot of {} (Build123d {})",
+        lock.packages.len(),
+        env_lock::format_snapshot_date(lock.created_at_ms),
+        b3d_version.unwrap_or_else(|| "unknown".to_string())
+    ))
+}
+
+/// Cheap pre-run check that the venv's Build123d did not change under an open
+/// session. Python is only asked for the version when the venv stamp moved;
+/// on an actual upgrade/downgrade the session version is updated and caches
+/// tied to the old version (validated code, retrieval indexes) are dropped.
+/// Python runs on the blocking pool; the watch lock is only held to check the
+/// stamp and to publish the result.
+pub(crate) async fn refresh_build123d_version(
+    state: &AppState,
+) -> Option<version_watch::VersionChange> {
+    let venv_dir = state.venv_path.lock().ok()?.clone()?;
+    let known = state.build123d_version.lock().ok()?.clone();
+    let stamp = version_watch::venv_stamp(&venv_dir);
+    if !version_watch::global_watch()
+        .lock()
+        .ok()?
+        .needs_detect(stamp, known.as_deref())
+    {
+        return None;
+    }
+    let detected =
+        tokio::task::spawn_blocking(move || installer::detect_build123d_version(&venv_dir))
+            .await
+            .ok()?;
+    let change =
+        version_watch::global_watch()
+            .lock()
+            .ok()?
+            .publish(stamp, known.as_deref(), detected)?;
+
+    *state.build123d_version.lock().ok()? = change.current.clone();
+    if change.is_upgrade_or_downgrade() {
+        if let Ok(mut cache) = crate::agent::revalidation::global_cache().lock() {
+            cache.clear();
+        }
+        crate::agent::retrieval::invalidate_index_cache(change.current.as_deref());
+    }
+    Some(change)
+}
+
 #[tauri::command]
 pub async fn import_cad_file(
     file_path: String,
@@ -6,24 +6,33 @@ use tokio::sync::mpsc;
 use crate::agent::prompts;
 use crate::agent::retrieval;
 use crate::agent::rules::{AgentRules, AntiPatternEntry};
+use crate::agent::slash_commands;
 use crate::agent::validate;
+use crate::ai::audit;
 use crate::ai::claude::ClaudeProvider;
 use crate::ai::cost;
 use crate::ai::gemini::GeminiProvider;
 use crate::ai::message::ChatMessage;
 use crate::ai::ollama::OllamaProvider;
 use crate::ai::openai::OpenAiProvider;
-use crate::ai::provider::{AiProvider, StreamDelta, TokenUsage};
+use crate::ai::provider::{AiProvider, StreamSignal, TokenUsage};
+use crate::ai::rate_limit;
+use crate::ai::response_cache;
+use crate::ai::streaming::{stalled_message, StreamProgress, StreamWatch};
 use crate::config::AppConfig;
 use crate::error::AppError;
 use crate::state::AppState;
 
+use super::parallel::{self, MultiPartEvent};
+use super::run_events::{EventEnvelope, EventSink};
+
 /// Event payload sent to the frontend over a Tauri Channel during streaming.
 #[derive(Clone, Serialize)]
 pub struct StreamEvent {
     pub delta: String,
     pub done: bool,
-    /// Optional event type: "design_plan" for geometry plans, "token_usage" for usage data.
+    /// Optional event type: "design_plan" for geometry plans, "token_usage" for usage data,
+    /// "heartbeat" while the model thinks before its first token.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub event_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -35,7 +44,11 @@ pub struct StreamTokenUsage {
     pub input_tokens: u32,
     pub output_tokens: u32,
     pub total_tokens: u32,
+    /// Cost in `currency`, whatever the field name says.
     pub cost_usd: Option<f64>,
+    pub currency: String,
+    /// The model is not in the pricing table; its default rate was used.
+    pub estimated_with_default_rate: bool,
 }
 
 /// Result returned by the auto_retry command.
@@ -49,7 +62,21 @@ pub struct AutoRetryResult {
 
 /// Create an AI provider based on the current configuration.
 /// Shared between `send_message`, `auto_retry`, and `generate_parallel`.
+/// All providers of the same kind share one `provider_rpm_limit` bucket;
+/// cache hits skip the bucket and the audit log, which records only calls
+/// that reach the provider.
 pub(crate) fn create_provider(config: &AppConfig) -> Result<Box<dyn AiProvider>, AppError> {
+    Ok(response_cache::cache_provider(
+        rate_limit::limit_provider(
+            audit::audit_provider(build_provider(config)?, config, "chat"),
+            config,
+        ),
+        config,
+        None,
+    ))
+}
+
+fn build_provider(config: &AppConfig) -> Result<Box<dyn AiProvider>, AppError> {
     match config.ai_provider.as_str() {
         "openai" => {
             let api_key = config
@@ -131,11 +158,62 @@ pub(crate) fn create_provider(config: &AppConfig) -> Result<Box<dyn AiProvider>,
     }
 }
 
+/// Return a copy of `config` that targets `escalation_model` for last-chance retries.
+///
+/// `None` when no escalation model is configured or it matches the default model.
+pub(crate) fn escalation_config(config: &AppConfig) -> Option<AppConfig> {
+    let model = config
+        .escalation_model
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty() && *m != config.model)?;
+    let mut escalated = config.clone();
+    escalated.model = model.to_string();
+    Some(escalated)
+}
+
 /// Create an AI provider with an explicit temperature setting.
 /// Used by consensus mode to run parallel generations at different temperatures.
 pub(crate) fn create_provider_with_temp(
     config: &AppConfig,
     temperature: Option<f32>,
+) -> Result<Box<dyn AiProvider>, AppError> {
+    Ok(response_cache::cache_provider(
+        rate_limit::limit_provider(
+            audit::audit_provider(
+                build_provider_with_options(config, temperature, &[])?,
+                config,
+                "sampled",
+            ),
+            config,
+        ),
+        config,
+        temperature,
+    ))
+}
+
+/// Create an AI provider for code-generation calls. Responses end at
+/// `code_stop_sequences` (by default the closing `</CODE>` tag), so no tokens
+/// are spent on commentary after the code.
+pub(crate) fn create_code_provider(config: &AppConfig) -> Result<Box<dyn AiProvider>, AppError> {
+    Ok(response_cache::cache_provider(
+        rate_limit::limit_provider(
+            audit::audit_provider(
+                build_provider_with_options(config, None, &config.code_stop_sequences)?,
+                config,
+                "code",
+            ),
+            config,
+        ),
+        config,
+        None,
+    ))
+}
+
+fn build_provider_with_options(
+    config: &AppConfig,
+    temperature: Option<f32>,
+    stop: &[String],
 ) -> Result<Box<dyn AiProvider>, AppError> {
     match config.ai_provider.as_str() {
         "openai" => {
@@ -149,7 +227,8 @@ pub(crate) fn create_provider_with_temp(
                     config.model.clone(),
                     config.openai_base_url.clone(),
                 )
-                .with_temperature(temperature),
+                .with_temperature(temperature)
+                .with_stop_sequences(stop.to_vec()),
             ))
         }
         "deepseek" => {
@@ -163,7 +242,8 @@ pub(crate) fn create_provider_with_temp(
                     config.model.clone(),
                     Some("https://api.deepseek.com/v1".to_string()),
                 )
-                .with_temperature(temperature),
+                .with_temperature(temperature)
+                .with_stop_sequences(stop.to_vec()),
             ))
         }
         "qwen" => {
@@ -177,7 +257,8 @@ pub(crate) fn create_provider_with_temp(
                     config.model.clone(),
                     Some("https://dashscope-intl.aliyuncs.com/compatible-mode/v1".to_string()),
                 )
-                .with_temperature(temperature),
+                .with_temperature(temperature)
+                .with_stop_sequences(stop.to_vec()),
             ))
         }
         "kimi" => {
@@ -191,7 +272,8 @@ pub(crate) fn create_provider_with_temp(
                     config.model.clone(),
                     Some("https://api.moonshot.ai/v1".to_string()),
                 )
-                .with_temperature(temperature),
+                .with_temperature(temperature)
+                .with_stop_sequences(stop.to_vec()),
             ))
         }
         "gemini" => {
@@ -200,7 +282,9 @@ pub(crate) fn create_provider_with_temp(
                 .clone()
                 .ok_or_else(|| AppError::AiProviderError("Gemini API key not set".into()))?;
             Ok(Box::new(
-                GeminiProvider::new(api_key, config.model.clone()).with_temperature(temperature),
+                GeminiProvider::new(api_key, config.model.clone())
+                    .with_temperature(temperature)
+                    .with_stop_sequences(stop.to_vec()),
             ))
         }
         "runpod" => {
@@ -217,12 +301,14 @@ pub(crate) fn create_provider_with_temp(
                     config.model.clone(),
                     Some(base_url),
                 )
-                .with_temperature(temperature),
+                .with_temperature(temperature)
+                .with_stop_sequences(stop.to_vec()),
             ))
         }
         "ollama" => Ok(Box::new(
             OllamaProvider::new(config.ollama_base_url.clone(), config.model.clone())
-                .with_temperature(temperature),
+                .with_temperature(temperature)
+                .with_stop_sequences(stop.to_vec()),
         )),
         _ => {
             let api_key = config
@@ -230,27 +316,54 @@ pub(crate) fn create_provider_with_temp(
                 .clone()
                 .ok_or_else(|| AppError::AiProviderError("API key not set".into()))?;
             Ok(Box::new(
-                ClaudeProvider::new(api_key, config.model.clone()).with_temperature(temperature),
+                ClaudeProvider::new(api_key, config.model.clone())
+                    .with_temperature(temperature)
+                    .with_stop_sequences(stop.to_vec()),
             ))
         }
     }
 }
 
-/// Stream an AI response from the provider, forwarding deltas over the Tauri channel.
+/// Stream an AI response from the provider, forwarding deltas over the Tauri channel
+/// and, when given, as `SingleDelta` events on `mirror`.
 /// Returns the fully accumulated response string and optional token usage.
 pub(crate) async fn stream_ai_response(
     provider: Box<dyn AiProvider>,
     messages: Vec<ChatMessage>,
     on_event: &Channel<StreamEvent>,
+    mirror: Option<&EventSink>,
 ) -> Result<(String, Option<TokenUsage>), AppError> {
-    let (tx, mut rx) = mpsc::channel::<StreamDelta>(100);
+    let (tx, rx) = mpsc::channel::<StreamSignal>(100);
+    let mut stream = StreamWatch::for_provider(provider.as_ref(), rx);
 
     let provider_handle = tokio::spawn(async move { provider.stream(&messages, tx).await });
 
     let mut full_response = String::new();
 
-    while let Some(delta) = rx.recv().await {
+    while let Some(progress) = stream.next().await {
+        let delta = match progress {
+            StreamProgress::Delta(delta) => delta,
+            StreamProgress::Thinking => {
+                let _ = on_event.send(StreamEvent {
+                    delta: String::new(),
+                    done: false,
+                    event_type: Some("heartbeat".to_string()),
+                    token_usage: None,
+                });
+                continue;
+            }
+            StreamProgress::Stalled => {
+                provider_handle.abort();
+                return Err(AppError::AiProviderError(stalled_message()));
+            }
+        };
         full_response.push_str(&delta.content);
+        if let Some(sink) = mirror {
+            let _ = sink.send(MultiPartEvent::SingleDelta {
+                delta: delta.content.clone(),
+                done: delta.done,
+            });
+        }
         let _ = on_event.send(StreamEvent {
             delta: delta.content,
             done: delta.done,
@@ -273,20 +386,47 @@ pub(crate) async fn stream_ai_response(
     Ok((full_response, usage))
 }
 
+/// Chat reply streamed over `on_event`. With `generation_events`, the reply is
+/// also sent as `MultiPartEvent`s, and code in it is reviewed and validated
+/// like single-part generation.
 #[tauri::command]
 pub async fn send_message(
     message: String,
     history: Vec<ChatMessage>,
     on_event: Channel<StreamEvent>,
     state: State<'_, AppState>,
+    generation_events: Option<Channel<EventEnvelope>>,
 ) -> Result<String, AppError> {
     // Read config (clone to release the lock immediately).
     let config = state.config.lock().unwrap().clone();
 
+    // Leading slash commands override settings for this message only.
+    let (config, commands) = match slash_commands::apply(&config, &message) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let _ = on_event.send(StreamEvent {
+                delta: e.clone(),
+                done: true,
+                event_type: Some("error".to_string()),
+                token_usage: None,
+            });
+            return Err(AppError::ConfigError(e));
+        }
+    };
+    if let Some(summary) = commands.summary() {
+        let _ = on_event.send(StreamEvent {
+            delta: summary,
+            done: false,
+            event_type: Some("overrides_status".to_string()),
+            token_usage: None,
+        });
+    }
+    let message = commands.message;
+
     // Build the system prompt from the configured preset.
     let cq_version = state.build123d_version.lock().unwrap().clone();
 
-    let system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
+    let mut system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
         // Fine-tuned model: minimal prompt, no retrieval or session context.
         prompts::build_finetuned_system_prompt()
     } else {
@@ -330,6 +470,10 @@ pub async fn send_message(
         }
         sp
     };
+    prompts::append_house_style(
+        &mut system_prompt,
+        config.custom_system_prompt_suffix.as_deref(),
+    );
 
     // Create the AI provider.
     let provider = create_provider(&config)?;
@@ -337,20 +481,24 @@ pub async fn send_message(
     // Build the full message list: system prompt + conversation history + new user message.
     let mut messages = vec![ChatMessage {
         role: "system".to_string(),
-        content: system_prompt,
+        content: system_prompt.clone(),
     }];
     messages.extend(history);
     messages.push(ChatMessage {
         role: "user".to_string(),
-        content: message,
+        content: message.clone(),
     });
 
+    let generation_events =
+        generation_events.map(|raw| EventSink::register(&state.run_events, raw));
+
     // Stream and return the full response.
-    let (full_response, usage) = stream_ai_response(provider, messages, &on_event).await?;
+    let (full_response, usage) =
+        stream_ai_response(provider, messages, &on_event, generation_events.as_ref()).await?;
 
     // Emit token usage event if available.
     if let Some(ref u) = usage {
-        let cost_usd = cost::estimate_cost(&config.ai_provider, &config.model, u);
+        let estimate = cost::estimate_cost(&config.ai_provider, &config.model, u);
         let _ = on_event.send(StreamEvent {
             delta: String::new(),
             done: true,
@@ -359,12 +507,30 @@ pub async fn send_message(
                 input_tokens: u.input_tokens,
                 output_tokens: u.output_tokens,
                 total_tokens: u.total(),
-                cost_usd,
+                cost_usd: Some(estimate.cost),
+                currency: estimate.currency,
+                estimated_with_default_rate: estimate.estimated_with_default_rate,
             }),
         });
     }
 
-    Ok(full_response)
+    match generation_events {
+        Some(sink) => {
+            let execution_ctx = parallel::execution_context(&state, &config);
+            parallel::finish_chat_reply(
+                full_response,
+                &message,
+                &system_prompt,
+                &config,
+                execution_ctx.as_ref(),
+                &sink,
+                usage,
+                &state.run_store,
+            )
+            .await
+        }
+        None => Ok(full_response),
+    }
 }
 
 /// Build a targeted retry prompt from the error classification and strategy.
@@ -441,7 +607,7 @@ pub async fn auto_retry(
     // Build the system prompt from the configured preset.
     let cq_version = state.build123d_version.lock().unwrap().clone();
 
-    let system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
+    let mut system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
         prompts::build_finetuned_system_prompt()
     } else {
         let base_prompt = prompts::build_compact_system_prompt_for_preset(
@@ -463,6 +629,10 @@ pub async fn auto_retry(
         }
         sp
     };
+    prompts::append_house_style(
+        &mut system_prompt,
+        config.custom_system_prompt_suffix.as_deref(),
+    );
 
     // Create the AI provider.
     let provider = create_provider(&config)?;
@@ -502,11 +672,11 @@ pub async fn auto_retry(
     });
 
     // Stream the AI response.
-    let (full_response, usage) = stream_ai_response(provider, messages, &on_event).await?;
+    let (full_response, usage) = stream_ai_response(provider, messages, &on_event, None).await?;
 
     // Emit token usage event if available.
     if let Some(ref u) = usage {
-        let cost_usd = cost::estimate_cost(&config.ai_provider, &config.model, u);
+        let estimate = cost::estimate_cost(&config.ai_provider, &config.model, u);
         let _ = on_event.send(StreamEvent {
             delta: String::new(),
             done: true,
@@ -515,7 +685,9 @@ pub async fn auto_retry(
                 input_tokens: u.input_tokens,
                 output_tokens: u.output_tokens,
                 total_tokens: u.total(),
-                cost_usd,
+                cost_usd: Some(estimate.cost),
+                currency: estimate.currency,
+                estimated_with_default_rate: estimate.estimated_with_default_rate,
             }),
         });
     }
@@ -657,4 +829,21 @@ mod tests {
         let prompt = build_retry_prompt("code", "error", &error, &strategy, None);
         assert!(prompt.contains("The failing operation: `fillet`"));
     }
+
+    #[test]
+    fn test_escalation_config_swaps_model() {
+        let mut config = AppConfig::default();
+        assert!(escalation_config(&config).is_none());
+
+        config.escalation_model = Some(config.model.clone());
+        assert!(escalation_config(&config).is_none());
+
+        config.escalation_model = Some("  ".to_string());
+        assert!(escalation_config(&config).is_none());
+
+        config.escalation_model = Some("claude-opus-4-1".to_string());
+        let escalated = escalation_config(&config).unwrap();
+        assert_eq!(escalated.model, "claude-opus-4-1");
+        assert_eq!(escalated.ai_provider, config.ai_provider);
+    }
 }
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use serde::Serialize;
 use tauri::State;
 
@@ -34,20 +36,38 @@ pub async fn generate_drawing_view(
         }
     };
 
+    render_drawing_view(
+        &venv_dir,
+        &code,
+        [proj_x, proj_y, proj_z],
+        show_hidden,
+        section_plane
+            .as_deref()
+            .map(|plane| (plane, section_offset.unwrap_or(0.0))),
+    )
+}
+
+/// Project `code`'s `result` along `projection` into an SVG drawing view,
+/// optionally cut by `section` (plane name, offset).
+pub(crate) fn render_drawing_view(
+    venv_dir: &Path,
+    code: &str,
+    projection: [f64; 3],
+    show_hidden: bool,
+    section: Option<(&str, f64)>,
+) -> Result<DrawingViewResult, AppError> {
     let script = super::find_python_script("drawing_view.py")?;
 
     // Write code to temp file
-    let temp_dir = std::env::temp_dir().join("cadai-studio");
-    std::fs::create_dir_all(&temp_dir)?;
+    let temp_dir = crate::artifacts::export_scratch_dir("drawing")?;
     let input_file = temp_dir.join("drawing_input.py");
     let output_svg = temp_dir.join("drawing_output.svg");
-    std::fs::write(&input_file, &code)?;
-    let _ = std::fs::remove_file(&output_svg);
+    std::fs::write(&input_file, code)?;
 
     // Build args
-    let proj_x_s = proj_x.to_string();
-    let proj_y_s = proj_y.to_string();
-    let proj_z_s = proj_z.to_string();
+    let proj_x_s = projection[0].to_string();
+    let proj_y_s = projection[1].to_string();
+    let proj_z_s = projection[2].to_string();
     let input_s = input_file.to_string_lossy().to_string();
     let output_s = output_svg.to_string_lossy().to_string();
 
@@ -58,14 +78,14 @@ pub async fn generate_drawing_view(
     }
 
     let section_offset_s;
-    if let Some(ref plane) = section_plane {
+    if let Some((plane, offset)) = section {
         args.push("--section");
-        args.push(plane.as_str());
-        section_offset_s = section_offset.unwrap_or(0.0).to_string();
+        args.push(plane);
+        section_offset_s = offset.to_string();
         args.push(&section_offset_s);
     }
 
-    let result = runner::execute_python_script(&venv_dir, &script, &args)?;
+    let result = runner::execute_python_script(venv_dir, &script, &args)?;
 
     if result.exit_code != 0 {
         let error_msg = match result.exit_code {
@@ -79,14 +99,13 @@ pub async fn generate_drawing_view(
             ),
         };
         // Cleanup
-        let _ = std::fs::remove_file(&input_file);
-        let _ = std::fs::remove_file(&output_svg);
+        let _ = std::fs::remove_dir_all(&temp_dir);
         return Err(AppError::CadError(error_msg));
     }
 
     // Read the generated SVG
     if !output_svg.exists() {
-        let _ = std::fs::remove_file(&input_file);
+        let _ = std::fs::remove_dir_all(&temp_dir);
         return Err(AppError::CadError("SVG file was not generated".into()));
     }
 
@@ -101,8 +120,7 @@ pub async fn generate_drawing_view(
     }
 
     // Cleanup
-    let _ = std::fs::remove_file(&input_file);
-    let _ = std::fs::remove_file(&output_svg);
+    let _ = std::fs::remove_dir_all(&temp_dir);
 
     Ok(DrawingViewResult {
         svg_content,
@@ -129,8 +147,7 @@ pub async fn export_drawing_pdf(
 
     let script = super::find_python_script("drawing_export.py")?;
 
-    let temp_dir = std::env::temp_dir().join("cadai-studio");
-    std::fs::create_dir_all(&temp_dir)?;
+    let temp_dir = crate::artifacts::export_scratch_dir("drawing-export")?;
     let input_svg = temp_dir.join("export_drawing.svg");
     std::fs::write(&input_svg, &svg_content)?;
 
@@ -139,7 +156,7 @@ pub async fn export_drawing_pdf(
 
     let result = runner::execute_python_script(&venv_dir, &script, &args)?;
 
-    let _ = std::fs::remove_file(&input_svg);
+    let _ = std::fs::remove_dir_all(&temp_dir);
 
     if result.exit_code != 0 {
         let msg = match result.exit_code {
@@ -174,8 +191,7 @@ pub async fn export_drawing_dxf(
 
     let script = super::find_python_script("drawing_export.py")?;
 
-    let temp_dir = std::env::temp_dir().join("cadai-studio");
-    std::fs::create_dir_all(&temp_dir)?;
+    let temp_dir = crate::artifacts::export_scratch_dir("drawing-export")?;
     let input_svg = temp_dir.join("export_drawing.svg");
     std::fs::write(&input_svg, &svg_content)?;
 
@@ -184,7 +200,7 @@ pub async fn export_drawing_dxf(
 
     let result = runner::execute_python_script(&venv_dir, &script, &args)?;
 
-    let _ = std::fs::remove_file(&input_svg);
+    let _ = std::fs::remove_dir_all(&temp_dir);
 
     if result.exit_code != 0 {
         let msg = match result.exit_code {
@@ -0,0 +1,65 @@
+use tauri::State;
+
+use crate::agent::executor;
+use crate::agent::geometry_query::{self, GeometryQuery, GeometryQueryResponse};
+use crate::error::AppError;
+use crate::python::runner;
+use crate::state::AppState;
+
+/// Measure `code` (or the final code of `run_id`) on its BREP: point-to-point
+/// distance, ray-cast thickness or closest-face radius. With a run id, points
+/// are checked against the run's buffered STL before the runner is started.
+#[tauri::command]
+pub async fn query_geometry(
+    code: Option<String>,
+    run_id: Option<String>,
+    query: GeometryQuery,
+    state: State<'_, AppState>,
+) -> Result<GeometryQueryResponse, AppError> {
+    query.validate()?;
+
+    let code = match (code, run_id) {
+        (Some(code), _) => code,
+        (None, Some(run_id)) => {
+            let (code, stl) = state
+                .run_events
+                .lock()
+                .map_err(|_| AppError::ConfigError("Run event store lock poisoned".into()))?
+                .final_code(&run_id)
+                .ok_or_else(|| {
+                    AppError::ConfigError(format!("No final code buffered for run '{}'", run_id))
+                })?;
+            if let Some(bounds) = stl.as_deref().and_then(executor::stl_bounds_base64) {
+                query.check_bounds(&bounds)?;
+            }
+            code
+        }
+        (None, None) => {
+            return Err(AppError::ConfigError(
+                "Geometry query needs code or a run id".into(),
+            ))
+        }
+    };
+
+    let venv_dir = state.venv_path.lock().unwrap().clone().ok_or_else(|| {
+        AppError::CadError(
+            "Python environment not set up. Click 'Setup Python' in settings.".into(),
+        )
+    })?;
+    let runner_script = super::find_python_script("runner.py")?;
+    let query_json = serde_json::to_string(&query)?;
+
+    let answer = tokio::task::spawn_blocking(move || {
+        runner::run_geometry_query(
+            &venv_dir,
+            &runner_script,
+            &code,
+            &query_json,
+            geometry_query::QUERY_TIMEOUT_MS,
+        )
+    })
+    .await
+    .map_err(|e| AppError::CadError(format!("Geometry query task panicked: {}", e)))??;
+
+    geometry_query::parse_answer(&query, &answer)
+}
@@ -0,0 +1,109 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use crate::agent::executor::PostGeometryValidationReport;
+use crate::agent::telemetry;
+use crate::commands::parallel::{
+    DesignPlanResult, GenerationPlan, GenerationResult, MultiPartEvent,
+};
+use crate::commands::run_events::{EventEnvelope, RunEvents};
+
+/// Version of the IPC payload schema. Bump it whenever a `MultiPartEvent`
+/// variant or another exported type changes its fields, and update
+/// `EVENT_SCHEMA_FINGERPRINT` in the tests to match (they print the new value).
+pub const EVENT_SCHEMA_VERSION: u32 = 19;
+
+/// Committed schema in the frontend tree, relative to the crate root.
+/// Regenerate with `cargo run --bin export-ipc-schema`.
+pub const IPC_SCHEMA_PATH: &str = "../src/lib/types/ipc-schema.json";
+
+fn add_type<T: JsonSchema>(generator: &mut SchemaGenerator, types: &mut Map<String, Value>) {
+    let schema = generator.subschema_for::<T>();
+    types.insert(
+        T::schema_name(),
+        serde_json::to_value(schema).unwrap_or(Value::Null),
+    );
+}
+
+/// Exported types and the definitions they reference, without version info.
+fn schema_body() -> Value {
+    let mut generator = SchemaSettings::draft07().into_generator();
+    let mut types = Map::new();
+    add_type::<MultiPartEvent>(&mut generator, &mut types);
+    add_type::<EventEnvelope>(&mut generator, &mut types);
+    add_type::<RunEvents>(&mut generator, &mut types);
+    add_type::<DesignPlanResult>(&mut generator, &mut types);
+    add_type::<GenerationPlan>(&mut generator, &mut types);
+    add_type::<GenerationResult>(&mut generator, &mut types);
+    add_type::<PostGeometryValidationReport>(&mut generator, &mut types);
+    json!({
+        "types": types,
+        "definitions": generator.take_definitions(),
+    })
+}
+
+fn fingerprint(body: &Value) -> String {
+    telemetry::hash_request(&body.to_string())
+}
+
+/// JSON Schema document for the frontend bindings.
+pub fn ipc_schema() -> Value {
+    let body = schema_body();
+    json!({
+        "$comment": "Generated by `cargo run --bin export-ipc-schema`; do not edit.",
+        "version": EVENT_SCHEMA_VERSION,
+        "fingerprint": fingerprint(&body),
+        "types": body["types"],
+        "definitions": body["definitions"],
+    })
+}
+
+pub fn ipc_schema_json() -> String {
+    let mut text = serde_json::to_string_pretty(&ipc_schema()).unwrap_or_default();
+    text.push('\n');
+    text
+}
+
+/// Schema version the backend was built with, so the UI can detect stale bindings.
+#[tauri::command]
+pub fn get_event_schema_version() -> u32 {
+    EVENT_SCHEMA_VERSION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENT_SCHEMA_FINGERPRINT: &str = "b2693e02f4fc5826";
+    const COMMITTED_SCHEMA: &str = include_str!("../../../src/lib/types/ipc-schema.json");
+
+    #[test]
+    fn test_schema_version_tracks_field_changes() {
+        let actual = fingerprint(&schema_body());
+        assert_eq!(
+            actual, EVENT_SCHEMA_FINGERPRINT,
+            "IPC payload types changed: bump EVENT_SCHEMA_VERSION, set \
+             EVENT_SCHEMA_FINGERPRINT to \"{}\" and run `cargo run --bin export-ipc-schema`",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_committed_schema_is_up_to_date() {
+        assert!(
+            ipc_schema_json() == COMMITTED_SCHEMA,
+            "src/lib/types/ipc-schema.json is stale; run `cargo run --bin export-ipc-schema`"
+        );
+    }
+
+    #[test]
+    fn test_schema_covers_event_kinds() {
+        let schema = ipc_schema();
+        let text = schema.to_string();
+        for kind in ["RetrievalStatus", "PartStlReady", "PlanValidation", "Done"] {
+            assert!(text.contains(&format!("\"{}\"", kind)), "missing {}", kind);
+        }
+        assert!(schema["definitions"]["PostGeometryValidationReport"].is_object());
+    }
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
+use crate::agent::part_colors::{self, PartColor};
+use crate::agent::print_estimate::{self, MeshStats, PrintEstimate};
 use crate::error::AppError;
 use crate::python::runner;
 use crate::state::AppState;
@@ -9,6 +11,8 @@ use crate::state::AppState;
 pub struct Export3mfResult {
     pub path: String,
     pub triangles: u64,
+    /// Object names (`lid_group/lid`) when the assembly was exported per part.
+    pub objects: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -53,6 +57,7 @@ pub async fn export_3mf(
     code: String,
     output_path: String,
     colors: Option<Vec<ColorInfo>>,
+    objects: Option<Vec<String>>,
     state: State<'_, AppState>,
 ) -> Result<Export3mfResult, AppError> {
     let venv_path = state.venv_path.lock().unwrap().clone();
@@ -67,8 +72,7 @@ pub async fn export_3mf(
 
     let script = super::find_python_script("manufacturing.py")?;
 
-    let temp_dir = std::env::temp_dir().join("cadai-studio");
-    std::fs::create_dir_all(&temp_dir)?;
+    let temp_dir = crate::artifacts::export_scratch_dir("manufacturing")?;
     let code_file = temp_dir.join("mfg_code.py");
     std::fs::write(&code_file, &code)?;
 
@@ -88,13 +92,17 @@ pub async fn export_3mf(
         args.push("--colors".into());
         args.push(colors_file_s);
     }
+    // Named variables of a multi-object script, one 3MF object each.
+    if let Some(names) = objects.filter(|names| !names.is_empty()) {
+        args.push("--objects".into());
+        args.push(names.join(","));
+    }
 
     let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
     let result = runner::execute_python_script(&venv_dir, &script, &arg_refs)?;
 
     // Cleanup
-    let _ = std::fs::remove_file(&code_file);
-    let _ = std::fs::remove_file(&colors_file);
+    let _ = std::fs::remove_dir_all(&temp_dir);
 
     if result.exit_code != 0 {
         let msg = match result.exit_code {
@@ -102,6 +110,7 @@ pub async fn export_3mf(
             3 => "Code must assign final geometry to 'result' variable.".to_string(),
             4 => format!("Export error:\n{}", result.stderr),
             5 => "Missing dependency (trimesh). Will auto-install on next attempt.".to_string(),
+            6 => result.stderr.trim().to_string(),
             _ => format!(
                 "Manufacturing error (exit code {}):\n{}",
                 result.exit_code, result.stderr
@@ -117,6 +126,14 @@ pub async fn export_3mf(
     Ok(Export3mfResult {
         path: parsed["path"].as_str().unwrap_or(&output_path).to_string(),
         triangles: parsed["triangles"].as_u64().unwrap_or(0),
+        objects: parsed["objects"]
+            .as_array()
+            .map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default(),
     })
 }
 
@@ -137,8 +154,7 @@ pub async fn mesh_check(
 
     let script = super::find_python_script("manufacturing.py")?;
 
-    let temp_dir = std::env::temp_dir().join("cadai-studio");
-    std::fs::create_dir_all(&temp_dir)?;
+    let temp_dir = crate::artifacts::export_scratch_dir("manufacturing")?;
     let code_file = temp_dir.join("mfg_check_code.py");
     std::fs::write(&code_file, &code)?;
 
@@ -147,7 +163,7 @@ pub async fn mesh_check(
 
     let result = runner::execute_python_script(&venv_dir, &script, &args)?;
 
-    let _ = std::fs::remove_file(&code_file);
+    let _ = std::fs::remove_dir_all(&temp_dir);
 
     if result.exit_code != 0 {
         let msg = match result.exit_code {
@@ -201,8 +217,7 @@ pub async fn orient_for_print(
 
     let script = super::find_python_script("manufacturing.py")?;
 
-    let temp_dir = std::env::temp_dir().join("cadai-studio");
-    std::fs::create_dir_all(&temp_dir)?;
+    let temp_dir = crate::artifacts::export_scratch_dir("manufacturing")?;
     let code_file = temp_dir.join("mfg_orient_code.py");
     std::fs::write(&code_file, &code)?;
 
@@ -211,7 +226,7 @@ pub async fn orient_for_print(
 
     let result = runner::execute_python_script(&venv_dir, &script, &args)?;
 
-    let _ = std::fs::remove_file(&code_file);
+    let _ = std::fs::remove_dir_all(&temp_dir);
 
     if result.exit_code != 0 {
         let msg = match result.exit_code {
@@ -271,8 +286,7 @@ pub async fn sheet_metal_unfold(
 
     let script = super::find_python_script("manufacturing.py")?;
 
-    let temp_dir = std::env::temp_dir().join("cadai-studio");
-    std::fs::create_dir_all(&temp_dir)?;
+    let temp_dir = crate::artifacts::export_scratch_dir("manufacturing")?;
     let code_file = temp_dir.join("mfg_unfold_code.py");
     std::fs::write(&code_file, &code)?;
 
@@ -287,7 +301,7 @@ pub async fn sheet_metal_unfold(
     let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
     let result = runner::execute_python_script(&venv_dir, &script, &arg_refs)?;
 
-    let _ = std::fs::remove_file(&code_file);
+    let _ = std::fs::remove_dir_all(&temp_dir);
 
     if result.exit_code != 0 {
         let msg = match result.exit_code {
@@ -323,3 +337,158 @@ pub async fn sheet_metal_unfold(
         flat_height: parsed["flat_height"].as_f64().unwrap_or(0.0),
     })
 }
+
+/// Assign a configured material to a part by name for mass-properties reports.
+/// Passing `None` clears the assignment so the plan description is used again.
+#[tauri::command]
+pub fn set_part_material(
+    part_name: String,
+    material: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let mut assignments = state
+        .part_materials
+        .lock()
+        .map_err(|e| AppError::ConfigError(format!("Failed to lock part materials: {}", e)))?;
+    let Some(material) = material else {
+        assignments.remove(&part_name);
+        return Ok(());
+    };
+
+    let material = material.trim().to_lowercase();
+    let known = state
+        .config
+        .lock()
+        .map_err(|e| AppError::ConfigError(format!("Failed to lock config: {}", e)))?
+        .materials
+        .contains_key(&material);
+    if !known {
+        return Err(AppError::ConfigError(format!(
+            "Unknown material '{}'; add it to the materials table first",
+            material
+        )));
+    }
+    assignments.insert(part_name, material);
+    Ok(())
+}
+
+/// Assign an RGB color (channels 0..1) to a part by name. Later assemblies
+/// tint the part with it; `None` goes back to the part's palette color.
+#[tauri::command]
+pub fn set_part_color(
+    part_name: String,
+    color: Option<PartColor>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let mut assignments = state
+        .part_colors
+        .lock()
+        .map_err(|e| AppError::ConfigError(format!("Failed to lock part colors: {}", e)))?;
+    match color {
+        Some(color) => {
+            let color = part_colors::validate(color).map_err(AppError::ConfigError)?;
+            assignments.insert(part_name, color);
+        }
+        None => {
+            assignments.remove(&part_name);
+        }
+    }
+    Ok(())
+}
+
+/// Volume, surface area and height of an STL via `manufacturing.py stl_stats`.
+fn stl_mesh_stats(venv_dir: &std::path::Path, stl: &[u8]) -> Result<MeshStats, AppError> {
+    let script = super::find_python_script("manufacturing.py")?;
+
+    let temp_dir = crate::artifacts::export_scratch_dir("manufacturing")?;
+    let stl_file = temp_dir.join("mfg_estimate.stl");
+    std::fs::write(&stl_file, stl)?;
+
+    let stl_file_s = stl_file.to_string_lossy().to_string();
+    let args: Vec<&str> = vec!["stl_stats", &stl_file_s];
+    let result = runner::execute_python_script(venv_dir, &script, &args);
+
+    let _ = std::fs::remove_dir_all(&temp_dir);
+
+    let result = result?;
+    if result.exit_code != 0 {
+        return Err(AppError::CadError(format!(
+            "STL analysis error (exit code {}):\n{}",
+            result.exit_code, result.stderr
+        )));
+    }
+
+    let parsed: serde_json::Value = serde_json::from_str(result.stdout.trim())
+        .map_err(|e| AppError::CadError(format!("Failed to parse result: {}", e)))?;
+    let bound_z = |i: usize| parsed["bounds"][i][2].as_f64().unwrap_or(0.0);
+    Ok(MeshStats {
+        volume_mm3: parsed["volume"].as_f64().unwrap_or(0.0),
+        surface_area_mm2: parsed["surface_area"].as_f64().unwrap_or(0.0),
+        height_mm: bound_z(1) - bound_z(0),
+    })
+}
+
+/// Rough print time and material usage (±30%) for a stored multi-part run
+/// or a raw STL. `build_height_mm` overrides the measured height, e.g. with
+/// the height from `orient_for_print`.
+#[tauri::command]
+pub async fn estimate_print_job(
+    run_id: Option<String>,
+    stl_base64: Option<String>,
+    profile_id: Option<String>,
+    build_height_mm: Option<f64>,
+    state: State<'_, AppState>,
+) -> Result<PrintEstimate, AppError> {
+    use base64::Engine;
+
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| AppError::ConfigError(format!("Failed to lock config: {}", e)))?
+        .clone();
+    let profile = print_estimate::resolve_profile(&config, profile_id.as_deref())?;
+
+    let mut stats = match (run_id, stl_base64) {
+        (Some(run_id), None) => {
+            let store = state
+                .run_store
+                .lock()
+                .map_err(|e| AppError::ConfigError(format!("Failed to lock run store: {}", e)))?;
+            let reports = store.run(&run_id)?.selected_reports();
+            MeshStats::from_reports(reports.values()).ok_or_else(|| {
+                AppError::CadError(format!(
+                    "Run '{}' has no closed-mesh measurements to estimate from",
+                    run_id
+                ))
+            })?
+        }
+        (None, Some(stl_base64)) => {
+            let venv_dir = state.venv_path.lock().unwrap().clone().ok_or_else(|| {
+                AppError::CadError(
+                    "Python environment not set up. Click 'Setup Python' in settings.".into(),
+                )
+            })?;
+            let stl = base64::engine::general_purpose::STANDARD
+                .decode(stl_base64.trim())
+                .map_err(|e| AppError::CadError(format!("Invalid STL data: {}", e)))?;
+            stl_mesh_stats(&venv_dir, &stl)?
+        }
+        _ => {
+            return Err(AppError::CadError(
+                "Pass exactly one of a run id or STL data".into(),
+            ))
+        }
+    };
+    if let Some(height) = build_height_mm.filter(|h| h.is_finite() && *h > 0.0) {
+        stats.height_mm = height;
+    }
+    if stats.volume_mm3 <= 0.0 {
+        return Err(AppError::CadError(
+            "Mesh has no enclosed volume; check that it is watertight".into(),
+        ));
+    }
+
+    Ok(print_estimate::estimate_print_job(
+        &stats, &profile, &config,
+    ))
+}
@@ -1,10 +1,17 @@
+use std::collections::HashMap;
+
+use base64::Engine;
 use serde::Serialize;
 use tauri::State;
 
+use crate::agent::executor;
 use crate::error::AppError;
 use crate::mechanisms::catalog;
 use crate::mechanisms::importer;
-use crate::mechanisms::schema::{CatalogMechanism, CatalogPackage, MechanismImportReport};
+use crate::mechanisms::schema::{
+    CatalogMechanism, CatalogPackage, MechanismImportReport, MechanismInstance,
+};
+use crate::mechanisms::template;
 use crate::state::AppState;
 
 #[derive(Debug, Clone, Serialize)]
@@ -62,6 +69,7 @@ pub fn search_mechanisms(
 pub async fn install_mechanism_pack(
     state: State<'_, AppState>,
     manifest_url: String,
+    confirm_flagged: Option<bool>,
 ) -> Result<MechanismImportReport, AppError> {
     let config = state
         .config
@@ -69,7 +77,7 @@ pub async fn install_mechanism_pack(
         .map_err(|e| AppError::ConfigError(format!("Failed to lock config: {}", e)))?
         .clone();
 
-    importer::install_pack_from_url(&config, &manifest_url).await
+    importer::install_pack_from_url(&config, &manifest_url, confirm_flagged.unwrap_or(false)).await
 }
 
 #[tauri::command]
@@ -79,3 +87,52 @@ pub fn remove_mechanism_pack(
 ) -> Result<bool, AppError> {
     importer::remove_imported_pack(&package_id)
 }
+
+/// Render a mechanism's code template at the given parameters and execute it.
+#[tauri::command]
+pub async fn instantiate_mechanism(
+    id: String,
+    params: HashMap<String, f64>,
+    state: State<'_, AppState>,
+) -> Result<MechanismInstance, AppError> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| AppError::ConfigError(format!("Failed to lock config: {}", e)))?
+        .clone();
+
+    let mechanism = catalog::get_mechanism_by_id(&config, &id)?
+        .ok_or_else(|| AppError::ConfigError(format!("Mechanism '{}' not found", id)))?;
+    let code_template = mechanism.code_template.as_deref().ok_or_else(|| {
+        AppError::ConfigError(format!(
+            "Mechanism '{}' has no code template to instantiate",
+            mechanism.id
+        ))
+    })?;
+
+    let values = template::resolve_parameters(&mechanism.id, &mechanism.parameters, &params)?;
+    let code = template::render_template(code_template, &values)?;
+
+    let venv_dir = state
+        .venv_path
+        .lock()
+        .map_err(|_| AppError::ConfigError("Failed to access Python environment state".into()))?
+        .clone()
+        .ok_or_else(|| {
+            AppError::ConfigError(
+                "Python environment not set up. Click 'Setup Python' in settings.".to_string(),
+            )
+        })?;
+    let runner_script = super::find_python_script("runner.py")?;
+
+    let exec_result = executor::execute_with_timeout(&code, &venv_dir, &runner_script)
+        .await
+        .map_err(AppError::CadError)?;
+
+    Ok(MechanismInstance {
+        mechanism_id: mechanism.id,
+        code,
+        stl_base64: base64::engine::general_purpose::STANDARD.encode(&exec_result.stl_data),
+        parameters: values,
+    })
+}
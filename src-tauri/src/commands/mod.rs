@@ -1,11 +1,21 @@
+pub mod anti_patterns;
+pub mod audit;
 pub mod cad;
 pub mod chat;
 pub mod drawing;
+pub mod geometry_query;
+pub mod ipc_schema;
 pub mod manufacturing;
 pub mod mechanisms;
 pub mod parallel;
 pub mod project;
+pub mod queue;
+pub mod repair_examples;
+pub mod run_compare;
+pub mod run_events;
 pub mod settings;
+pub mod storage;
+pub mod views;
 
 use crate::error::AppError;
 
@@ -1,56 +1,150 @@
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::Write;
+use std::sync::Mutex;
 use std::time::Duration;
 use tauri::ipc::Channel;
 use tauri::State;
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 
+use crate::agent::clearance;
+use crate::agent::code_import;
+use crate::agent::complexity;
 use crate::agent::confidence;
 use crate::agent::consensus;
 use crate::agent::design;
 use crate::agent::executor;
+use crate::agent::failure_localization::{self, FailureLocation};
+use crate::agent::features;
 use crate::agent::iterative;
+use crate::agent::kinematics;
+use crate::agent::layout;
+use crate::agent::mass;
+use crate::agent::mechanism_reference;
 use crate::agent::memory;
 use crate::agent::modify;
+use crate::agent::next_actions;
+use crate::agent::numparse;
+use crate::agent::part_colors::{self, PartColor};
+use crate::agent::part_constraints;
+use crate::agent::part_dedup;
+use crate::agent::print_estimate;
 use crate::agent::prompts;
+use crate::agent::repair_examples;
 use crate::agent::retrieval;
+use crate::agent::revalidation::RevalidationScope;
+use crate::agent::run_state;
 use crate::agent::review;
 use crate::agent::semantic_validate;
+use crate::agent::slash_commands;
 use crate::agent::telemetry;
+use crate::agent::threads;
 use crate::agent::validate::ErrorCategory;
+use crate::agent::validation_history;
+use crate::ai::audit;
 use crate::ai::cost;
 use crate::ai::message::ChatMessage;
-use crate::ai::provider::{StreamDelta, TokenUsage};
+use crate::ai::provider::{StreamDelta, StreamSignal, TokenUsage};
+use crate::ai::streaming::{stalled_message, StreamProgress, StreamWatch};
 use crate::error::AppError;
 use crate::state::AppState;
 
-use super::chat::create_provider;
+use super::chat::{create_code_provider, create_provider, escalation_config};
+use super::run_events::{
+    self, EventEnvelope, EventSink, InFlightGuard, RunChannelStatus, RunClaim,
+};
 
 // ---------------------------------------------------------------------------
 // Data structures
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GenerationPlan {
     pub mode: String,
     pub description: Option<String>,
     #[serde(default)]
     pub parts: Vec<PartSpec>,
+    /// Booleans between built parts, applied in order before assembly.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assembly_ops: Vec<AssemblyOp>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Boolean used by an `AssemblyOp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssemblyBoolean {
+    Cut,
+    Intersect,
+    Union,
+}
+
+impl AssemblyBoolean {
+    /// Build123d method applying the boolean.
+    fn method(self) -> &'static str {
+        match self {
+            Self::Cut => "cut",
+            Self::Intersect => "intersect",
+            Self::Union => "fuse",
+        }
+    }
+}
+
+/// Replaces part `target` with `target <op> tool`, e.g. a mold cavity cut by
+/// the plug it is shaped around.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AssemblyOp {
+    pub op: AssemblyBoolean,
+    pub target: String,
+    pub tool: String,
+    /// Keep `tool` in the assembly; by default it is consumed by the operation.
+    #[serde(default)]
+    pub keep_tool: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PartSpec {
     pub name: String,
     pub description: String,
     pub position: [f64; 3],
     #[serde(default)]
     pub constraints: Vec<String>,
+    /// Overrides `generation_reliability_profile` for this part only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reliability_profile: Option<crate::config::GenerationReliabilityProfile>,
+}
+
+impl PartSpec {
+    /// The part's own reliability profile, or the global one.
+    pub fn effective_reliability_profile<'a>(
+        &'a self,
+        config: &'a crate::config::AppConfig,
+    ) -> &'a crate::config::GenerationReliabilityProfile {
+        self.reliability_profile
+            .as_ref()
+            .unwrap_or(&config.generation_reliability_profile)
+    }
+
+    /// `config` with the part's reliability profile applied, for per-part acceptance.
+    pub fn acceptance_config(&self, config: &crate::config::AppConfig) -> crate::config::AppConfig {
+        let mut part_config = config.clone();
+        part_config.generation_reliability_profile =
+            self.effective_reliability_profile(config).clone();
+        part_config
+    }
+}
+
+/// A planned part, by position in the plan and name.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct PartRef {
+    pub index: usize,
+    pub name: String,
 }
 
 /// Events streamed to the frontend over a Tauri Channel during parallel generation.
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, JsonSchema)]
 #[serde(tag = "kind")]
 pub enum MultiPartEvent {
     RetrievalStatus {
@@ -58,6 +152,9 @@ pub enum MultiPartEvent {
         items: Vec<crate::agent::retrieval::RetrievedContextItem>,
         used_embeddings: bool,
         lexical_fallback: bool,
+        dropped_below_threshold: usize,
+        /// "online", "cached", "lexical" or "disabled"; see `RetrievalResult`.
+        embedding_mode: String,
     },
     /// Geometry design plan produced before code generation.
     DesignPlan {
@@ -72,6 +169,8 @@ pub enum MultiPartEvent {
         fatal_combo: bool,
         negation_conflict: bool,
         repair_sensitive_ops: Vec<String>,
+        /// Geometric operations the Build Plan steps are estimated to need.
+        estimated_operations: Option<u32>,
     },
     /// Generation confidence assessment based on plan risk + cookbook matching.
     ConfidenceAssessment {
@@ -86,6 +185,18 @@ pub enum MultiPartEvent {
     },
     PlanResult {
         plan: GenerationPlan,
+        /// Static / moving / fastener tag per planned part.
+        kinematics: Vec<kinematics::PartKinematics>,
+    },
+    /// Multi-part run whose part candidates can be listed and swapped in.
+    RunStarted {
+        run_id: String,
+    },
+    /// Run parked by `pause_before_assembly` after per-part acceptance; it
+    /// continues with `approve_assembly`.
+    AwaitingAssemblyApproval {
+        accepted_parts: Vec<PartRef>,
+        rejected_parts: Vec<PartRef>,
     },
     /// Streaming delta for a single-mode fallback (acts like StreamEvent).
     SingleDelta {
@@ -116,6 +227,8 @@ pub enum MultiPartEvent {
         part_index: usize,
         part_name: String,
         stl_base64: String,
+        /// RGB (0..1) the part is tinted with in the assembled code.
+        color: Option<PartColor>,
     },
     PartStlFailed {
         part_index: usize,
@@ -125,9 +238,31 @@ pub enum MultiPartEvent {
     AssemblyStatus {
         message: String,
     },
+    /// Per-part and assembly mass, center of gravity and inertia.
+    MassPropertiesReport {
+        report: mass::MassPropertiesReport,
+    },
+    /// Rough print time and material usage for 3D printing requests.
+    PrintEstimate {
+        estimate: print_estimate::PrintEstimate,
+    },
+    /// The run is alive but has nothing new to show yet, e.g. a model that
+    /// is still thinking before its first token.
+    Heartbeat {
+        phase: String,
+        detail: String,
+    },
+    /// Non-fatal condition the user should know about, identified by `code`.
+    Warning {
+        code: String,
+        message: String,
+    },
     FinalCode {
         code: String,
         stl_base64: Option<String>,
+        /// `executor::geometry_hash` of the STL, to tell whether a regenerated
+        /// result actually differs from the previous one.
+        geometry_hash: Option<String>,
     },
     ReviewStatus {
         message: String,
@@ -135,22 +270,39 @@ pub enum MultiPartEvent {
     ReviewComplete {
         was_modified: bool,
         explanation: String,
+        /// Structured findings per configured review focus.
+        findings: Vec<review::ReviewFinding>,
+        /// False when the static pre-screen passed the code and the AI
+        /// reviewer was not called.
+        ai_reviewed: bool,
+    },
+    /// The reviewer changed the code but the change was rejected and the
+    /// pre-review code kept.
+    ReviewReverted {
+        reason: String,
     },
     TokenUsage {
         phase: String,
         input_tokens: u32,
         output_tokens: u32,
         total_tokens: u32,
+        /// Cost in `currency`, whatever the field name says.
         cost_usd: Option<f64>,
+        currency: String,
+        /// The model is not in the pricing table; its default rate was used.
+        estimated_with_default_rate: bool,
     },
     ValidationAttempt {
         attempt: u32,
         max_attempts: u32,
         message: String,
+        revalidation_scope: RevalidationScope,
     },
     StaticValidationReport {
         passed: bool,
         findings: Vec<String>,
+        /// Geometric operations counted in the validated script.
+        operation_count: Option<u32>,
     },
     ValidationSuccess {
         attempt: u32,
@@ -161,6 +313,21 @@ pub enum MultiPartEvent {
         error_category: String,
         error_message: String,
         will_retry: bool,
+        /// The assembled-script failure was traced to this part's block, which
+        /// is repaired on its own instead of the whole script.
+        localized_part: Option<String>,
+    },
+    /// Size of the repair between `attempt` and the next validation attempt.
+    ValidationAttemptDiff {
+        attempt: u32,
+        additions: usize,
+        deletions: usize,
+    },
+    /// Plain-language note on a validation retry, sent when
+    /// `explain_retries` is set.
+    ValidationFixExplanation {
+        attempt: u32,
+        explanation: String,
     },
     PostGeometryValidationReport {
         report: executor::PostGeometryValidationReport,
@@ -168,6 +335,13 @@ pub enum MultiPartEvent {
     PostGeometryValidationWarning {
         message: String,
     },
+    /// A retry switched from the default model to `escalation_model`.
+    ModelEscalation {
+        part_name: Option<String>,
+        from_model: String,
+        to_model: String,
+        message: String,
+    },
     SemanticValidationReport {
         part_name: String,
         passed: bool,
@@ -207,6 +381,8 @@ pub enum MultiPartEvent {
     },
     CodeDiff {
         diff_lines: Vec<crate::agent::modify::DiffLine>,
+        /// Changed regions of `diff_lines`, for jumping between changes.
+        hunks: Vec<crate::agent::modify::DiffHunk>,
         old_line_count: usize,
         new_line_count: usize,
         additions: usize,
@@ -231,6 +407,28 @@ pub enum MultiPartEvent {
     ClarificationNeeded {
         questions: Vec<String>,
     },
+    /// `generate_batch` started the prompt at `index`.
+    BatchItemStarted {
+        index: usize,
+        prompt: String,
+    },
+    /// `generate_batch` finished the prompt at `index`, successfully or not.
+    BatchItemComplete {
+        index: usize,
+        success: bool,
+        final_code: Option<String>,
+        error: Option<String>,
+    },
+    /// Every prompt of a `generate_batch` run has finished.
+    BatchComplete {
+        succeeded: usize,
+        failed: usize,
+    },
+    /// Deterministic next steps after a failed generation, sent before the
+    /// run's result is returned.
+    SuggestedActions {
+        actions: Vec<next_actions::SuggestedAction>,
+    },
     Done {
         success: bool,
         error: Option<String>,
@@ -238,13 +436,21 @@ pub enum MultiPartEvent {
     },
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, JsonSchema)]
 pub struct DesignPlanResult {
     pub plan_text: String,
     pub risk_score: u32,
     pub warnings: Vec<String>,
     pub is_valid: bool,
+    /// Geometric operations the Build Plan steps are estimated to need.
+    pub estimated_operations: Option<u32>,
     pub clarification_questions: Option<Vec<String>>,
+    /// Auto-approval gate values; `None` when triage asked for clarification.
+    pub auto_approval: Option<confidence::AutoApprovalDecision>,
+    /// Generation was chained in without manual approval.
+    pub auto_approved: bool,
+    /// Response of the chained generation, when `auto_approved`.
+    pub generation_response: Option<String>,
 }
 
 /// Outcome from the generation pipeline, used for session memory recording.
@@ -252,6 +458,8 @@ struct PipelineOutcome {
     response: String,
     final_code: Option<String>,
     success: bool,
+    /// Whether the code went through backend execution/validation.
+    validated: bool,
     error: Option<String>,
     validation_attempts: Option<u32>,
     static_findings: Vec<String>,
@@ -263,6 +471,81 @@ struct PipelineOutcome {
     empty_viewport_after_generation: bool,
     retry_ladder_stage_reached: Option<u32>,
     failure_signatures: Vec<String>,
+    model_escalations: Vec<telemetry::ModelEscalation>,
+    /// Stored repair examples used by the validation loop, with outcomes.
+    repair_example_uses: Vec<repair_examples::RepairExampleUse>,
+    /// `executor::geometry_hash` of the final STL, when one was produced.
+    geometry_hash: Option<String>,
+    /// The run was parked by `pause_before_assembly` and has no result yet.
+    awaiting_assembly_approval: bool,
+    /// Where the assembled script's first failure was routed for repair.
+    failure_localization: Option<telemetry::FailureLocalization>,
+    /// Retrieved mechanisms whose reference code went into a part prompt.
+    injected_mechanism_ids: Vec<String>,
+}
+
+impl PipelineOutcome {
+    fn failure_summary(&self, python_available: bool) -> next_actions::FailureSummary<'_> {
+        next_actions::FailureSummary {
+            error: self.error.as_deref(),
+            failure_signatures: &self.failure_signatures,
+            retry_ladder_stage_reached: self.retry_ladder_stage_reached,
+            part_acceptance_rate: self.part_acceptance_rate,
+            python_available,
+        }
+    }
+}
+
+/// Structured outcome of `generate_parallel_result`, for callers that do not
+/// consume the event stream.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct GenerationResult {
+    pub success: bool,
+    pub final_code: Option<String>,
+    pub validated: bool,
+    pub part_acceptance_rate: Option<f32>,
+    pub total_cost_usd: Option<f64>,
+    pub failure_signatures: Vec<String>,
+    pub error: Option<String>,
+    /// The chat response `generate_parallel` returns.
+    pub response: String,
+    /// Fingerprint of the final geometry; equal hashes mean a regeneration
+    /// produced the same shape.
+    pub geometry_hash: Option<String>,
+}
+
+impl GenerationResult {
+    fn from_outcome(outcome: &PipelineOutcome, total_cost_usd: Option<f64>) -> Self {
+        Self {
+            success: outcome.success,
+            final_code: outcome.final_code.clone(),
+            validated: outcome.validated,
+            part_acceptance_rate: outcome.part_acceptance_rate,
+            total_cost_usd,
+            failure_signatures: outcome.failure_signatures.clone(),
+            error: outcome.error.clone(),
+            response: outcome.response.clone(),
+            geometry_hash: outcome.geometry_hash.clone(),
+        }
+    }
+}
+
+/// Escalation record for a validation loop that switched to `escalation_model`.
+fn validation_escalations(
+    config: &crate::config::AppConfig,
+    part: &str,
+    result: &executor::ValidationResult,
+) -> Vec<telemetry::ModelEscalation> {
+    result
+        .escalated_model
+        .iter()
+        .map(|to| telemetry::ModelEscalation {
+            part: part.to_string(),
+            from: config.model.clone(),
+            to: to.clone(),
+            succeeded: result.success,
+        })
+        .collect()
 }
 
 /// Record a generation attempt into the session memory.
@@ -289,33 +572,142 @@ fn record_generation_attempt(
     state.session_memory.lock().unwrap().record_attempt(attempt);
 }
 
+/// Send `MultiPartEvent::SuggestedActions` for a failed run.
+fn send_suggested_actions(
+    on_event: &EventSink,
+    config: &crate::config::AppConfig,
+    summary: &next_actions::FailureSummary,
+) {
+    let actions = next_actions::suggest_actions(summary, config);
+    let _ = on_event.send(MultiPartEvent::SuggestedActions { actions });
+}
+
+/// Apply `channel_disconnect_policy` to a run whose frontend stopped
+/// receiving events, and note `channel_disconnected` in the trace.
+fn handle_channel_disconnect(
+    state: &AppState,
+    config: &crate::config::AppConfig,
+    user_request: &str,
+    retrieval_result: &retrieval::RetrievalResult,
+    on_event: &EventSink,
+) {
+    let run_id = on_event.run_id();
+    match config.channel_disconnect_policy {
+        crate::config::ChannelDisconnectPolicy::Park => {
+            on_event.set_status(RunChannelStatus::Parked);
+            // Buffered only; a reconnecting frontend sees it via `get_run_events`.
+            let _ = on_event.send(MultiPartEvent::Warning {
+                code: "channel_disconnected".to_string(),
+                message: "The run stopped because the window stopped receiving updates. \
+                          Generated part candidates were kept."
+                    .to_string(),
+            });
+        }
+        crate::config::ChannelDisconnectPolicy::Cancel => {
+            on_event.set_status(RunChannelStatus::Cancelled);
+            if let Ok(mut store) = state.run_store.lock() {
+                store.remove_run(run_id);
+            }
+            if let Ok(mut events) = state.run_events.lock() {
+                events.remove(run_id);
+            }
+        }
+    }
+    eprintln!(
+        "[parallel] Event channel disconnected; run {} stopped ({:?})",
+        run_id, config.channel_disconnect_policy
+    );
+
+    let outcome = PipelineOutcome {
+        response: String::new(),
+        final_code: None,
+        success: false,
+        validated: false,
+        error: Some("channel_disconnected".to_string()),
+        validation_attempts: None,
+        static_findings: vec![],
+        post_check_soft_failed: false,
+        post_check_soft_fail_reason: None,
+        part_acceptance_rate: None,
+        assembly_success_rate: None,
+        partial_preview_shown: false,
+        empty_viewport_after_generation: false,
+        retry_ladder_stage_reached: None,
+        failure_signatures: vec!["channel_disconnected".to_string()],
+        model_escalations: vec![],
+        repair_example_uses: vec![],
+        geometry_hash: None,
+        awaiting_assembly_approval: false,
+        failure_localization: None,
+        injected_mechanism_ids: Vec::new(),
+    };
+    record_generation_trace(
+        config,
+        on_event,
+        user_request,
+        retrieval_result,
+        None,
+        None,
+        &outcome,
+        false,
+    );
+}
+
+/// Keep the run's final code and STL as managed artifacts (see `artifacts`).
+fn save_run_artifacts(
+    config: &crate::config::AppConfig,
+    on_event: &EventSink,
+    outcome: &PipelineOutcome,
+) {
+    use base64::Engine;
+
+    let stl = on_event
+        .final_stl()
+        .and_then(|s| base64::engine::general_purpose::STANDARD.decode(s).ok());
+    let meta = crate::artifacts::RunMeta {
+        run_id: on_event.run_id().to_string(),
+        created_at_ms: telemetry::now_ms(),
+        success: outcome.success,
+        geometry_hash: outcome.geometry_hash.clone(),
+    };
+    crate::artifacts::record_run(config, &meta, outcome.final_code.as_deref(), stl.as_deref());
+}
+
+/// `plan_risk_score` and `estimated_operations` come from the design plan
+/// the run was generated from, when there was one.
+#[allow(clippy::too_many_arguments)]
 fn record_generation_trace(
     config: &crate::config::AppConfig,
+    on_event: &EventSink,
     user_request: &str,
     retrieval_result: &retrieval::RetrievalResult,
     plan_risk_score: Option<u32>,
+    estimated_operations: Option<u32>,
     outcome: &PipelineOutcome,
+    auto_approved: bool,
 ) {
-    if !config.telemetry_enabled {
+    on_event.record_settings(crate::pipeline_presets::fingerprint_settings(config));
+    save_run_artifacts(config, on_event, outcome);
+    if !telemetry::trace_writes_allowed(config) {
         return;
     }
 
-    let semantic_failure_signatures = outcome
-        .failure_signatures
+    // Derived counts below count unique root causes, not repeats.
+    let failure_signatures = telemetry::dedup_failure_signatures(&outcome.failure_signatures);
+
+    let semantic_failure_signatures = failure_signatures
         .iter()
         .filter(|s| s.to_lowercase().contains("semantic"))
         .cloned()
         .collect::<Vec<_>>();
-    let split_part_rejection_count = outcome
-        .failure_signatures
+    let split_part_rejection_count = failure_signatures
         .iter()
         .filter(|s| {
             let lower = s.to_lowercase();
             lower.contains("component count") || lower.contains("split_part")
         })
         .count() as u32;
-    let multipart_contract_failure_count = outcome
-        .failure_signatures
+    let multipart_contract_failure_count = failure_signatures
         .iter()
         .filter(|s| {
             let lower = s.to_lowercase();
@@ -324,8 +716,7 @@ fn record_generation_trace(
                 || lower.contains("required multipart")
         })
         .count() as u32;
-    let fallback_activation_count = outcome
-        .failure_signatures
+    let fallback_activation_count = failure_signatures
         .iter()
         .filter(|s| s.starts_with("fallback_activated:"))
         .count() as u32;
@@ -358,6 +749,7 @@ fn record_generation_trace(
     let trace = telemetry::GenerationTraceV1 {
         version: 1,
         timestamp_ms: telemetry::now_ms(),
+        run_id: Some(on_event.run_id().to_string()),
         request_hash: telemetry::hash_request(user_request),
         intent_tags: telemetry::infer_intent_tags(user_request),
         provider: config.ai_provider.clone(),
@@ -393,7 +785,7 @@ fn record_generation_trace(
         partial_preview_shown: outcome.partial_preview_shown,
         empty_viewport_after_generation: outcome.empty_viewport_after_generation,
         retry_ladder_stage_reached: outcome.retry_ladder_stage_reached,
-        failure_signatures: outcome.failure_signatures.clone(),
+        failure_signatures,
         mechanism_candidates: retrieval_result
             .items
             .iter()
@@ -406,6 +798,26 @@ fn record_generation_trace(
             .filter(|i| i.source == "mechanism")
             .map(|i| i.id.clone())
             .collect(),
+        mechanism_code_uptake: mechanism_reference::code_uptake(
+            &retrieval_result.items,
+            &outcome.injected_mechanism_ids,
+            outcome.final_code.as_deref(),
+        ),
+        model_escalations: outcome.model_escalations.clone(),
+        config_fingerprint: crate::pipeline_presets::config_fingerprint(config),
+        auto_approved,
+        estimated_operations,
+        actual_operations: outcome
+            .final_code
+            .as_deref()
+            .map(complexity::count_code_operations),
+        repair_examples_used: outcome.repair_example_uses.len() as u32,
+        repair_examples_succeeded: outcome
+            .repair_example_uses
+            .iter()
+            .filter(|u| u.succeeded)
+            .count() as u32,
+        failure_localization: outcome.failure_localization.clone(),
     };
 
     if let Err(e) = telemetry::write_trace(&trace) {
@@ -438,6 +850,10 @@ If the request involves 2-4 clearly distinct SEPARABLE components that fit toget
   ]
 }
 
+Only when one part is the negative or overlap of another (e.g. a mold cavity shaped by a plug), also add:
+"assembly_ops": [{"op": "cut", "target": "mold", "tool": "plug", "keep_tool": true}]
+"op" is "cut", "intersect" or "union"; "target" and "tool" must be names from "parts". The tool leaves the assembly unless "keep_tool" is true.
+
 ## When to use multi mode
 - The request describes 2-4 PHYSICALLY SEPARATE objects that assemble together
 - Example: "bottle with screw-on cap" → multi (bottle body + cap)
@@ -464,8 +880,9 @@ If the request involves 2-4 clearly distinct SEPARABLE components that fit toget
 Rules:
 - Part names must be valid Python identifiers (snake_case)
 - Positions are in mm, relative to origin [0,0,0]
+- Write every number with a dot decimal separator (1.8mm, not 1,8mm), even if the user wrote commas
 - Do NOT decompose decorative features, fillets, or chamfers into separate parts
-- Do NOT include these words/phrases in your output: Build sequence, Extrude, subtract, intersect, union, shell, boolean, cut
+- Do NOT include these words/phrases in part descriptions: Build sequence, Extrude, subtract, intersect, union, shell, boolean, cut
 
 Keep your response as short as possible. For single mode, return ONLY {"mode":"single"} with no other text."#;
 
@@ -485,7 +902,7 @@ fn reliability_policy_text(profile: &crate::config::GenerationReliabilityProfile
 
 /// Extract dimensional constraints that reference mating surfaces between parts.
 fn extract_dimensional_dependencies(constraints: &[String]) -> String {
-    let dim_re = Regex::new(r"(\d+(?:\.\d+)?)\s*mm").unwrap();
+    let dim_re = Regex::new(&format!(r"({})\s*mm", numparse::NUMBER)).unwrap();
     let mating_keywords = ["match", "fit", "mate", "diameter", "width", "height", "align", "receive", "bore", "OD", "ID"];
 
     let relevant: Vec<&String> = constraints
@@ -511,7 +928,7 @@ fn extract_dimensional_dependencies(constraints: &[String]) -> String {
 /// For constraints that reference another part name but contain no numeric dimension,
 /// appends the referenced part's dimensions for context.
 fn resolve_cross_references(plan: &mut GenerationPlan) {
-    let dim_re = Regex::new(r"\d+(?:\.\d+)?\s*mm").unwrap();
+    let dim_re = Regex::new(&format!(r"{}\s*mm", numparse::NUMBER)).unwrap();
     let ref_re = Regex::new(r"(?:of|from|match)\s+(\w+)").unwrap();
 
     // Build lookup: part_name → description
@@ -549,11 +966,24 @@ fn resolve_cross_references(plan: &mut GenerationPlan) {
     }
 }
 
-fn build_sibling_dimensions_summary(plan: &GenerationPlan, current_part_name: &str) -> String {
-    let dim_re = Regex::new(r"(\d+(?:\.\d+)?)\s*mm").unwrap();
+/// Dimensions of the other parts for a part prompt. Plan parts contribute the
+/// `Nmm` values of their description and constraints; `sibling_code` holds
+/// `(name, code)` of parts already generated, whose actual dimensions are
+/// listed as `generated`, including parts not in `parts`.
+fn build_sibling_dimensions_summary(
+    parts: &[PartSpec],
+    current_part_name: &str,
+    sibling_code: &[(String, String)],
+) -> String {
+    let dim_re = Regex::new(&format!(r"({})\s*mm", numparse::NUMBER)).unwrap();
     let mut summary = String::new();
+    let generated = |name: &str| -> Option<String> {
+        let (_, code) = sibling_code.iter().find(|(n, _)| n == name)?;
+        let dims = code_import::extract_dimensions(code);
+        (!dims.is_empty()).then(|| format!("generated [{}]", dims.join(", ")))
+    };
 
-    for part in &plan.parts {
+    for part in parts {
         if part.name == current_part_name {
             continue;
         }
@@ -588,8 +1018,19 @@ fn build_sibling_dimensions_summary(plan: &GenerationPlan, current_part_name: &s
         if !mating_constraints.is_empty() {
             summary.push_str(&format!(" | mating: {}", mating_constraints.iter().map(|c| c.as_str()).collect::<Vec<_>>().join("; ")));
         }
+        if let Some(generated) = generated(&part.name) {
+            summary.push_str(&format!(" | {}", generated));
+        }
         summary.push('\n');
     }
+    for (name, _code) in sibling_code {
+        if name == current_part_name || parts.iter().any(|p| &p.name == name) {
+            continue;
+        }
+        if let Some(generated) = generated(name) {
+            summary.push_str(&format!("- **{}**: {}\n", name, generated));
+        }
+    }
 
     if summary.is_empty() {
         return String::new();
@@ -604,6 +1045,7 @@ fn build_part_prompt(
     design_context: &str,
     config: &crate::config::AppConfig,
     sibling_summary: &str,
+    mechanism_reference: &str,
 ) -> String {
     let constraints_text = part.constraints
         .iter()
@@ -617,6 +1059,45 @@ fn build_part_prompt(
     } else {
         format!("\n\n{}", sibling_summary)
     };
+    // Callers usually pass a system prompt that already carries the house style.
+    let house_style_section =
+        prompts::house_style_section(config.custom_system_prompt_suffix.as_deref());
+    let house_style = match house_style_section {
+        Some((section, _)) if !system_prompt.contains(prompts::HOUSE_STYLE_HEADING) => {
+            format!("{}\n\n", section)
+        }
+        _ => String::new(),
+    };
+    let fastener_features = features::fastener_features_section(&format!(
+        "{}\n{}",
+        part.description, constraints_text
+    ))
+    .map(|section| format!("{}\n", section))
+    .unwrap_or_default();
+    let pattern_features =
+        features::pattern_features_section(&format!("{}\n{}", part.description, constraints_text))
+            .map(|section| format!("{}\n", section))
+            .unwrap_or_default();
+    let thread_helper = threads::thread_helper_section(
+        &format!("{}\n{}", part.description, constraints_text),
+        config,
+    )
+    .map(|section| format!("{}\n", section))
+    .unwrap_or_default();
+    let clearance_target = clearance::prompt_section(
+        &clearance::load_rules(config),
+        &clearance::active_process(config),
+        &format!("{}\n{}", part.description, constraints_text),
+    )
+    .map(|section| format!("{}\n", section))
+    .unwrap_or_default();
+    let operation_budget = match config.operation_soft_budget {
+        0 => String::new(),
+        budget => format!(
+            "Max operation budget: keep the script under ~{} geometric operations before optional polish.\n",
+            budget
+        ),
+    };
 
     format!(
         "## ⚠ CRITICAL: SINGLE-PART GENERATION MODE\n\
@@ -631,7 +1112,7 @@ fn build_part_prompt(
         Constraints:\n{}\n\
         {}\n\n\
         Active reliability policy: {}\n\
-        Max operation budget: keep the script under ~22 geometric operations before optional polish.\n\n\
+        {}\n\
         ## Build123d Construction Rules (MANDATORY)\n\
         Operation order: 1) Base shape 2) Additive features (union/bosses/lips) 3) Main cavity (boolean subtract) \
         4) Large cuts (slots/pockets/through-holes) 5) Small cuts (grooves/channels) 6) Holes (drill last) \
@@ -661,6 +1142,7 @@ fn build_part_prompt(
         - Wrap code in <CODE>...</CODE> tags.\n\
         - Must assign final geometry to variable `result`.\n\
         - Keep repair-friendly structure (named intermediates over one giant chain).\n\n\
+        {}{}{}{}{}{}\
         ## ⚠ REMINDER: Generate ONLY part '{}'. No other parts. No assembly.",
         part.name,
         system_prompt,
@@ -670,11 +1152,31 @@ fn build_part_prompt(
         part.description,
         constraints_text,
         mating_dims,
-        reliability_policy_text(&config.generation_reliability_profile),
+        reliability_policy_text(part.effective_reliability_profile(config)),
+        operation_budget,
+        fastener_features,
+        pattern_features,
+        thread_helper,
+        clearance_target,
+        house_style,
+        mechanism_reference,
         part.name,
     )
 }
 
+/// Reference code of the retrieved mechanisms that match `part`, rendered
+/// for `build_part_prompt`.
+fn part_reference_section(
+    retrieved: &[retrieval::RetrievedContextItem],
+    part: &PartSpec,
+) -> String {
+    mechanism_reference::reference_section(&mechanism_reference::references_for_part(
+        retrieved,
+        &part.name,
+        &part.description,
+    ))
+}
+
 /// Build a structured retry hint from a Build123d error, using the validation
 /// module's error parsing and retry strategy logic.
 fn build_error_retry_hint(error_text: &str) -> String {
@@ -710,7 +1212,7 @@ async fn request_code_only_part_retry(
     design_context: &str,
     previous_response: &str,
 ) -> Result<(Option<String>, Option<TokenUsage>), AppError> {
-    let provider = create_provider(config)?;
+    let provider = create_code_provider(config)?;
     let strict_prompt = format!(
         "{}\n\n\
         STRICT OUTPUT RULES (MANDATORY):\n\
@@ -719,7 +1221,7 @@ async fn request_code_only_part_retry(
         - No prose, no markdown explanation, no bullets.\n\
         - Must assign final geometry to variable named result.\n\
         - Generate ONLY the '{}' part.",
-        build_part_prompt(system_prompt, part, design_context, config, ""),
+        build_part_prompt(system_prompt, part, design_context, config, "", ""),
         part.name
     );
 
@@ -759,7 +1261,17 @@ async fn request_code_only_part_retry(
 // Assembly
 // ---------------------------------------------------------------------------
 
-fn assemble_parts(parts: &[(String, String, [f64; 3])]) -> Result<String, String> {
+/// Assemble parts, nesting moving parts in labeled sub-assembly compounds
+/// (`lid_group/lid`) when `kinematics` has any, so STEP/3MF exports keep the
+/// static base and moving groups apart. `assembly_ops` are applied to the
+/// built parts first; an op whose parts were not built is skipped. Each part
+/// gets its assigned or palette color, so exports and re-runs keep it.
+fn assemble_parts(
+    parts: &[(String, String, [f64; 3])],
+    part_kinematics: &[kinematics::PartKinematics],
+    assembly_ops: &[AssemblyOp],
+    part_colors: &HashMap<String, PartColor>,
+) -> Result<String, String> {
     // parts: Vec<(name, code, position)>
     if parts.is_empty() {
         return Err("No parts to assemble".to_string());
@@ -768,153 +1280,663 @@ fn assemble_parts(parts: &[(String, String, [f64; 3])]) -> Result<String, String
     let mut assembled = String::new();
     assembled.push_str("from build123d import *\n\n");
 
-    // Process each part: strip duplicate imports and rename `result` → `part_{name}`
-    let result_re = Regex::new(r"\bresult\b").unwrap();
+    // Strip import lines (we already have the import at the top)
+    let cleaned: Vec<String> = parts
+        .iter()
+        .map(|(_name, code, _pos)| {
+            code.lines()
+                .filter(|line| {
+                    let trimmed = line.trim();
+                    !trimmed.starts_with("from build123d")
+                        && !trimmed.starts_with("import build123d")
+                })
+                .collect::<Vec<&str>>()
+                .join("\n")
+        })
+        .collect();
 
-    for (name, code, _pos) in parts {
-        let var_name = format!("part_{}", name);
+    // Parts that only differ in a few literals share one `make_*` function
+    let dedup_input: Vec<(&str, &str)> = parts
+        .iter()
+        .zip(cleaned.iter())
+        .map(|((name, _, _), code)| (name.as_str(), code.as_str()))
+        .collect();
+    let groups = part_dedup::find_shared_groups(&dedup_input);
 
-        // Strip import lines (we already have the import at the top)
-        let cleaned: Vec<&str> = code
-            .lines()
-            .filter(|line| {
-                let trimmed = line.trim();
-                !trimmed.starts_with("from build123d") && !trimmed.starts_with("import build123d")
-            })
-            .collect();
+    // Process each remaining part: rename `result` → `part_{name}`
+    let result_re = Regex::new(r"\bresult\b").unwrap();
 
-        // Rename `result` to `part_{name}`
-        let renamed = result_re
-            .replace_all(&cleaned.join("\n"), var_name.as_str())
-            .to_string();
+    for (idx, ((name, _code, _pos), code)) in parts.iter().zip(cleaned.iter()).enumerate() {
+        if let Some(group) = groups.iter().find(|g| g.members.contains(&idx)) {
+            if group.members[0] != idx {
+                continue;
+            }
+            let names: Vec<&str> = group.members.iter().map(|&m| parts[m].0.as_str()).collect();
+            assembled.push_str(&format!("# --- {} (shared structure) ---\n", names.join(", ")));
+            assembled.push_str(&group.definition);
+            assembled.push_str("\n\n");
+            for (&member, args) in group.members.iter().zip(group.args.iter()) {
+                assembled.push_str(&format!(
+                    "part_{} = {}({})\n",
+                    parts[member].0,
+                    group.function_name,
+                    args.join(", ")
+                ));
+            }
+            assembled.push('\n');
+            continue;
+        }
+
+        let var_name = format!("part_{}", name);
+        let renamed = result_re.replace_all(code, var_name.as_str()).to_string();
 
         assembled.push_str(&format!("# --- {} ---\n", name));
         assembled.push_str(&renamed);
         assembled.push_str("\n\n");
     }
 
+    // Booleans between parts, in the target's frame: the tool is shifted by
+    // the difference of the two placements.
+    let consumed = consumed_tools(parts, assembly_ops);
+    if !assembly_ops.is_empty() {
+        assembled.push_str("# --- Assembly operations ---\n");
+    }
+    for op in assembly_ops {
+        let method = op.op.method();
+        let find = |name: &str| parts.iter().find(|(n, _, _)| n == name);
+        let (Some(target), Some(tool)) = (find(&op.target), find(&op.tool)) else {
+            assembled.push_str(&format!(
+                "# skipped {} of '{}' by '{}': part not built\n",
+                method, op.target, op.tool
+            ));
+            continue;
+        };
+        let offset: Vec<f64> = (0..3).map(|i| tool.2[i] - target.2[i]).collect();
+        let tool_expr = if offset.iter().all(|d| *d == 0.0) {
+            format!("part_{}", op.tool)
+        } else {
+            format!(
+                "Pos({}, {}, {}) * part_{}",
+                offset[0], offset[1], offset[2], op.tool
+            )
+        };
+        assembled.push_str(&format!(
+            "part_{} = part_{}.{}({})\n",
+            op.target, op.target, method, tool_expr
+        ));
+    }
+    if !assembly_ops.is_empty() {
+        assembled.push('\n');
+    }
+    let remaining: Vec<&(String, String, [f64; 3])> = parts
+        .iter()
+        .filter(|(name, _, _)| !consumed.contains(&name.as_str()))
+        .collect();
+    let parts = remaining.as_slice();
+    if parts.is_empty() {
+        return Err("Assembly operations consumed every part".to_string());
+    }
+
     // Build the assembly
     assembled.push_str("# --- Assembly ---\n");
-    assembled.push_str("assy = Compound(label=\"assembly\", children=[\n");
-
-    for (name, _code, pos) in parts {
-        let var_name = format!("part_{}", name);
-        assembled.push_str(&format!(
-            "    Pos({}, {}, {}) * {},\n",
-            pos[0], pos[1], pos[2], var_name,
+    for (name, _code, _pos) in parts {
+        assembled.push_str(&part_colors::color_statement(
+            name,
+            part_colors::resolve(name, part_colors),
         ));
     }
+    let placed = |name: &str, pos: &[f64; 3]| {
+        format!("    Pos({}, {}, {}) * part_{},\n", pos[0], pos[1], pos[2], name)
+    };
+    let group_of = |name: &str| {
+        part_kinematics
+            .iter()
+            .find(|k| k.name == name)
+            .and_then(|k| k.group.clone())
+    };
 
-    assembled.push_str("])\n");
+    if !kinematics::has_moving_groups(part_kinematics) {
+        assembled.push_str("assy = Compound(label=\"assembly\", children=[\n");
+        for (name, _code, pos) in parts {
+            assembled.push_str(&placed(name, pos));
+        }
+        assembled.push_str("])\n");
+    } else {
+        for (name, _code, _pos) in parts {
+            assembled.push_str(&format!("part_{}.label = \"{}\"\n", name, name));
+        }
+        let mut groups: Vec<String> = Vec::new();
+        for (name, _code, _pos) in parts {
+            if let Some(group) = group_of(name) {
+                if !groups.contains(&group) {
+                    groups.push(group);
+                }
+            }
+        }
+        for group in &groups {
+            assembled.push_str(&format!("{} = Compound(label=\"{}\", children=[\n", group, group));
+            for (name, _code, pos) in parts {
+                if group_of(name).as_ref() == Some(group) {
+                    assembled.push_str(&placed(name, pos));
+                }
+            }
+            assembled.push_str("])\n");
+        }
+        assembled.push_str("assy = Compound(label=\"assembly\", children=[\n");
+        for (name, _code, pos) in parts {
+            if group_of(name).is_none() {
+                assembled.push_str(&placed(name, pos));
+            }
+        }
+        for group in &groups {
+            assembled.push_str(&format!("    {},\n", group));
+        }
+        assembled.push_str("])\n");
+    }
     assembled.push_str("result = assy\n");
 
     Ok(assembled)
 }
 
-fn assembly_contract_issues(code: &str, parts: &[(String, String, [f64; 3])]) -> Vec<String> {
-    let mut issues = Vec::new();
-    for (name, _code, _pos) in parts {
-        let var_name = format!("part_{}", name);
-        if !code.contains(&var_name) {
-            issues.push(format!("missing {}", var_name));
-        }
-        // Check that the part variable is used in the assembly Compound
-        if !code.contains(&format!("{},", var_name)) && !code.contains(&format!("* {},", var_name)) {
-            issues.push(format!("missing assembly reference for {}", var_name));
+/// Motion roles of the accepted parts. Moving sub-assemblies and parts
+/// that defaulted to static are reported as `AssemblyStatus` messages.
+fn classify_accepted_parts(
+    plan: &GenerationPlan,
+    successful_parts: &[(String, String, [f64; 3])],
+    on_event: &EventSink,
+) -> Vec<kinematics::PartKinematics> {
+    let accepted: Vec<PartSpec> = plan
+        .parts
+        .iter()
+        .filter(|p| successful_parts.iter().any(|(name, _, _)| name == &p.name))
+        .cloned()
+        .collect();
+    let tags = kinematics::classify_parts(&accepted);
+    let moving: Vec<String> = tags
+        .iter()
+        .filter(|k| k.group.is_some())
+        .map(|k| k.hierarchical_name())
+        .collect();
+    if !moving.is_empty() {
+        let _ = on_event.send(MultiPartEvent::AssemblyStatus {
+            message: format!("Grouping moving parts: {}", moving.join(", ")),
+        });
+    }
+    for tag in &tags {
+        if let Some(ref note) = tag.note {
+            let _ = on_event.send(MultiPartEvent::AssemblyStatus {
+                message: format!("Part '{}' {}", tag.name, note),
+            });
         }
     }
+    tags
+}
 
-    if !code.contains("Compound(") {
-        issues.push("missing assembly initialization".to_string());
-    }
-    if !code.contains("result = assy") {
-        issues.push("missing assembly compound result".to_string());
+/// Planned parts split into accepted and rejected, for `AwaitingAssemblyApproval`.
+fn assembly_part_refs(
+    plan: &GenerationPlan,
+    accepted_parts: &[(String, String, [f64; 3])],
+) -> (Vec<PartRef>, Vec<PartRef>) {
+    plan.parts
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| PartRef {
+            index,
+            name: spec.name.clone(),
+        })
+        .partition(|part| accepted_parts.iter().any(|(name, _, _)| *name == part.name))
+}
+
+/// Check accepted parts that name a fit against the clearance range of the
+/// active process, warning per violation. Returns whether any were found.
+fn check_part_clearances(
+    plan: &GenerationPlan,
+    accepted_parts: &[(String, String, [f64; 3])],
+    config: &crate::config::AppConfig,
+    on_event: &EventSink,
+) -> bool {
+    let parts: Vec<clearance::ClearancePart> = accepted_parts
+        .iter()
+        .map(|(name, code, _)| clearance::ClearancePart {
+            name,
+            text: plan
+                .parts
+                .iter()
+                .find(|spec| spec.name == *name)
+                .map(|spec| format!("{}\n{}", spec.description, spec.constraints.join("\n")))
+                .unwrap_or_default(),
+            code,
+        })
+        .collect();
+    let violations = clearance::check_clearances(
+        &parts,
+        &clearance::load_rules(config),
+        &clearance::active_process(config),
+    );
+    for violation in &violations {
+        let _ = on_event.send(MultiPartEvent::Warning {
+            code: "clearance_out_of_range".to_string(),
+            message: violation.message(),
+        });
     }
+    !violations.is_empty()
+}
 
-    issues
+/// Prompt debug log location: the app data directory, or the OS temp
+/// directory when there is none.
+fn prompt_debug_log_path() -> std::path::PathBuf {
+    dirs::config_dir()
+        .map(|d| d.join("cadai-studio"))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("multipart_debug.log")
 }
 
-fn format_bbox_hint_from_dims(dims: [f64; 3]) -> String {
-    format!(
-        "overall envelope {:.3}x{:.3}x{:.3}mm",
-        dims[0], dims[1], dims[2]
-    )
+/// Debug logging is on and safe mode does not forbid the file.
+fn prompt_debug_logging_enabled(config: &crate::config::AppConfig) -> bool {
+    config.debug_prompt_logging && config.allows_app_data_writes()
 }
 
-fn build_part_bbox_hint(
-    semantic_contract: Option<&semantic_validate::SemanticPartContract>,
-    part_request: &str,
-    mode: &crate::config::SemanticBboxMode,
-) -> Option<String> {
-    match mode {
-        crate::config::SemanticBboxMode::Legacy => Some(part_request.to_string()),
-        crate::config::SemanticBboxMode::SemanticAware => {
-            if let Some(contract) = semantic_contract {
-                if let Some(expected) = &contract.expected_bbox_mm {
-                    return Some(format_bbox_hint_from_dims(expected.sorted_extents_mm));
-                }
-            }
-            semantic_validate::infer_envelope_dimensions_mm(part_request)
-                .map(format_bbox_hint_from_dims)
-        }
+/// Create the prompt debug log, or nothing at all when logging is off.
+fn open_prompt_debug_log(enabled: bool, path: &std::path::Path) -> Option<std::fs::File> {
+    if !enabled {
+        return None;
+    }
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
     }
+    std::fs::File::create(path).ok()
 }
 
-fn build_assembly_bbox_hint(
-    plan: &GenerationPlan,
-    user_request: &str,
-    mode: &crate::config::SemanticBboxMode,
-) -> Option<String> {
-    match mode {
-        crate::config::SemanticBboxMode::Legacy => Some(user_request.to_string()),
-        crate::config::SemanticBboxMode::SemanticAware => {
-            let mut aggregate: Option<[f64; 3]> = None;
-            for part in &plan.parts {
-                let contract =
-                    semantic_validate::build_default_contract(&part.name, &part.description);
-                if let Some(expected) = contract.expected_bbox_mm {
-                    aggregate = Some(match aggregate {
-                        Some(current) => [
-                            current[0].max(expected.sorted_extents_mm[0]),
-                            current[1].max(expected.sorted_extents_mm[1]),
-                            current[2].max(expected.sorted_extents_mm[2]),
-                        ],
-                        None => expected.sorted_extents_mm,
-                    });
-                }
-            }
-
-            if let Some(dims) = aggregate {
-                Some(format_bbox_hint_from_dims(dims))
-            } else {
-                semantic_validate::infer_envelope_dimensions_mm(user_request)
-                    .map(format_bbox_hint_from_dims)
-            }
-        }
+fn measured_bounds(report: &executor::PostGeometryValidationReport) -> layout::PartBounds {
+    layout::PartBounds {
+        min: report.bounds_min,
+        max: report.bounds_max,
     }
 }
 
-// ---------------------------------------------------------------------------
-// Token usage helper
-// ---------------------------------------------------------------------------
+/// Replace plan positions with geometry-aware ones: `layout::refine_positions`
+/// when enabled, then `layout::apply_part_gap` for the configured clearance.
+/// Each adjustment is reported as an `AssemblyStatus` message. Geometry below
+/// the build plate is either shifted up (`assembly_rest_on_plate`) or warned
+/// about.
+fn layout_part_positions(
+    parts: Vec<(String, String, [f64; 3])>,
+    plan: &GenerationPlan,
+    part_reports: &HashMap<String, executor::PostGeometryValidationReport>,
+    config: &crate::config::AppConfig,
+    on_event: &EventSink,
+) -> Vec<(String, String, [f64; 3])> {
+    let layout_parts: Vec<layout::LayoutPart> = parts
+        .iter()
+        .map(|(name, _code, pos)| layout::LayoutPart {
+            name: name.clone(),
+            position: *pos,
+            constraints: plan
+                .parts
+                .iter()
+                .find(|p| &p.name == name)
+                .map(|p| p.constraints.clone())
+                .unwrap_or_default(),
+            bounds: part_reports.get(name).map(measured_bounds),
+        })
+        .collect();
+
+    let (mut positions, mut adjustments) = if config.refine_assembly_positions {
+        let refinement = layout::refine_positions(&layout_parts);
+        (refinement.positions, refinement.adjustments)
+    } else {
+        (layout_parts.iter().map(|p| p.position).collect(), Vec::new())
+    };
+    adjustments.extend(layout::apply_part_gap(
+        &layout_parts,
+        &mut positions,
+        config.assembly_part_gap_mm,
+    ));
+    for adjustment in &adjustments {
+        let _ = on_event.send(MultiPartEvent::AssemblyStatus {
+            message: adjustment.message(),
+        });
+    }
+
+    if config.assembly_rest_on_plate {
+        if let Some(shift) = layout::plate_shift(&layout_parts, &positions) {
+            for pos in positions.iter_mut() {
+                pos[2] += shift;
+            }
+            let _ = on_event.send(MultiPartEvent::Warning {
+                code: "assembly_shifted_to_plate".to_string(),
+                message: format!(
+                    "Shifted the assembly {:+.1}mm in Z so its lowest point rests on the build plate.",
+                    shift
+                ),
+            });
+        }
+    } else {
+        let below_plate = layout::parts_below_plate(&layout_parts, &positions);
+        if !below_plate.is_empty() {
+            let _ = on_event.send(MultiPartEvent::Warning {
+                code: "parts_below_plate".to_string(),
+                message: format!(
+                    "Part positions put geometry below the build plate (Z=0): {}.",
+                    below_plate.join(", ")
+                ),
+            });
+        }
+    }
+
+    parts
+        .into_iter()
+        .zip(positions)
+        .map(|((name, code, _), pos)| (name, code, pos))
+        .collect()
+}
+
+/// Mass properties for the placed parts, or `None` when any part lacks a
+/// closed-mesh measurement to derive them from.
+fn assembly_mass_properties(
+    parts: &[(String, String, [f64; 3])],
+    plan: &GenerationPlan,
+    part_reports: &HashMap<String, executor::PostGeometryValidationReport>,
+    part_materials: &HashMap<String, String>,
+    user_request: &str,
+    config: &crate::config::AppConfig,
+) -> Option<mass::MassPropertiesReport> {
+    let mut inputs = Vec::with_capacity(parts.len());
+    for (name, _code, pos) in parts {
+        let report = part_reports.get(name)?;
+        let description = plan
+            .parts
+            .iter()
+            .find(|p| &p.name == name)
+            .map(|p| p.description.as_str())
+            .unwrap_or("");
+        inputs.push(mass::PartMassInput {
+            part_name: name.clone(),
+            material: mass::resolve_material(name, description, part_materials, config),
+            volume_mm3: report.volume,
+            center_of_mass: report.center_of_mass?,
+            unit_inertia: report.unit_inertia?,
+            bounds_min: report.bounds_min,
+            bounds_max: report.bounds_max,
+            position: *pos,
+        });
+    }
+    if inputs.is_empty() {
+        return None;
+    }
+    Some(mass::compute_mass_properties(
+        &inputs,
+        mass::is_free_standing(user_request),
+    ))
+}
+
+/// Send a print estimate for the final geometry when the request is about
+/// 3D printing and the mesh is closed.
+fn emit_print_estimate(
+    on_event: &EventSink,
+    report: Option<&executor::PostGeometryValidationReport>,
+    user_request: &str,
+    config: &crate::config::AppConfig,
+) {
+    let printable = telemetry::infer_intent_tags(user_request)
+        .iter()
+        .any(|tag| tag == "printable");
+    if !printable {
+        return;
+    }
+    let Some(stats) = report.and_then(|r| print_estimate::MeshStats::from_reports([r])) else {
+        return;
+    };
+    if let Ok(profile) = print_estimate::resolve_profile(config, None) {
+        let estimate = print_estimate::estimate_print_job(&stats, &profile, config);
+        let _ = on_event.send(MultiPartEvent::PrintEstimate { estimate });
+    }
+}
+
+/// Tools of `assembly_ops` that are consumed by their operation and so leave
+/// the assembly. Operations naming a part that was not built are skipped.
+fn consumed_tools<'a>(
+    parts: &[(String, String, [f64; 3])],
+    assembly_ops: &'a [AssemblyOp],
+) -> Vec<&'a str> {
+    let built = |name: &str| parts.iter().any(|(n, _, _)| n == name);
+    assembly_ops
+        .iter()
+        .filter(|op| !op.keep_tool && built(&op.target) && built(&op.tool))
+        .map(|op| op.tool.as_str())
+        .collect()
+}
+
+fn assembly_contract_issues(
+    code: &str,
+    parts: &[(String, String, [f64; 3])],
+    assembly_ops: &[AssemblyOp],
+) -> Vec<String> {
+    let mut issues = Vec::new();
+    let consumed = consumed_tools(parts, assembly_ops);
+
+    // Parts built by a shared `make_*` function need the definition and a
+    // single call each.
+    let call_re = Regex::new(r"(?m)^(part_\w+)\s*=\s*(make_\w+)\(").unwrap();
+    let mut called: Vec<&str> = Vec::new();
+    for cap in call_re.captures_iter(code) {
+        let var_name = cap.get(1).unwrap().as_str();
+        let function = cap.get(2).unwrap().as_str();
+        if !code.contains(&format!("def {}(", function)) {
+            issues.push(format!("missing shared function {} for {}", function, var_name));
+        }
+        if called.contains(&var_name) {
+            issues.push(format!("duplicate shared call for {}", var_name));
+        }
+        called.push(var_name);
+    }
+
+    // A consumed tool has no body of its own in the assembly.
+    for (name, _code, _pos) in parts
+        .iter()
+        .filter(|(name, _, _)| !consumed.contains(&name.as_str()))
+    {
+        let var_name = format!("part_{}", name);
+        if !code.contains(&var_name) {
+            issues.push(format!("missing {}", var_name));
+        }
+        // Check that the part variable is used in the assembly Compound
+        if !code.contains(&format!("{},", var_name)) && !code.contains(&format!("* {},", var_name)) {
+            issues.push(format!("missing assembly reference for {}", var_name));
+        }
+    }
+
+    if !code.contains("Compound(") {
+        issues.push("missing assembly initialization".to_string());
+    }
+    if !code.contains("result = assy") {
+        issues.push("missing assembly compound result".to_string());
+    }
+
+    issues
+}
+
+/// Run code review on assembled code, keeping the assembly when the review
+/// fails or breaks the part contract.
+async fn review_assembly(
+    code: String,
+    user_request: &str,
+    plan_text: &str,
+    parts: &[(String, String, [f64; 3])],
+    assembly_ops: &[AssemblyOp],
+    config: &crate::config::AppConfig,
+    on_event: &EventSink,
+    total_usage: &mut TokenUsage,
+) -> Result<String, AppError> {
+    let _ = on_event.send(MultiPartEvent::ReviewStatus {
+        message: "Reviewing assembled code...".to_string(),
+    });
+    on_event.ensure_connected()?;
+    let review_provider = create_provider(config)?;
+    match review::review_code(
+        review_provider,
+        user_request,
+        &code,
+        Some(plan_text),
+        &review::ReviewOptions::from_config(config),
+    )
+    .await
+    {
+        Ok((result, review_usage)) => {
+            if let Some(ref u) = review_usage {
+                total_usage.add(u);
+                emit_usage(on_event, "review", u, &config.ai_provider, &config.model);
+            }
+            let _ = on_event.send(MultiPartEvent::ReviewComplete {
+                was_modified: result.was_modified,
+                explanation: result.explanation.clone(),
+                findings: result.findings.clone(),
+                ai_reviewed: result.ai_reviewed,
+            });
+            if result.was_modified {
+                Ok(reviewed_assembly_code(
+                    on_event,
+                    code,
+                    result.code,
+                    parts,
+                    assembly_ops,
+                ))
+            } else {
+                Ok(code)
+            }
+        }
+        Err(e) => {
+            eprintln!("Code review failed: {}", e);
+            Ok(code)
+        }
+    }
+}
+
+/// Reviewed assembly code if it keeps the multipart contract; otherwise the
+/// assembled code, with a `ReviewReverted` event explaining why.
+fn reviewed_assembly_code(
+    on_event: &EventSink,
+    assembled: String,
+    reviewed: String,
+    parts: &[(String, String, [f64; 3])],
+    assembly_ops: &[AssemblyOp],
+) -> String {
+    let review_issues = assembly_contract_issues(&reviewed, parts, assembly_ops);
+    if review_issues.is_empty() {
+        return reviewed;
+    }
+    let reason = format!(
+        "Reviewer output dropped multipart structure ({}). Keeping assembled code.",
+        review_issues.join(", ")
+    );
+    let _ = on_event.send(MultiPartEvent::PlanStatus {
+        message: reason.clone(),
+    });
+    let _ = on_event.send(MultiPartEvent::ReviewReverted { reason });
+    assembled
+}
+
+fn format_bbox_hint_from_dims(dims: [f64; 3]) -> String {
+    format!(
+        "overall envelope {:.3}x{:.3}x{:.3}mm",
+        dims[0], dims[1], dims[2]
+    )
+}
+
+fn build_part_bbox_hint(
+    semantic_contract: Option<&semantic_validate::SemanticPartContract>,
+    part_request: &str,
+    mode: &crate::config::SemanticBboxMode,
+) -> Option<String> {
+    match mode {
+        crate::config::SemanticBboxMode::Legacy => Some(part_request.to_string()),
+        crate::config::SemanticBboxMode::SemanticAware => {
+            if let Some(contract) = semantic_contract {
+                if let Some(expected) = &contract.expected_bbox_mm {
+                    return Some(format_bbox_hint_from_dims(expected.sorted_extents_mm));
+                }
+            }
+            semantic_validate::infer_envelope_dimensions_mm(part_request)
+                .map(format_bbox_hint_from_dims)
+        }
+    }
+}
+
+/// Whole-assembly envelope: the per-axis maxima of the parts' sorted contract
+/// extents, or the envelope stated in the user request when no part has one.
+fn assembly_envelope_mm(plan: &GenerationPlan, user_request: &str) -> Option<[f64; 3]> {
+    let mut aggregate: Option<[f64; 3]> = None;
+    for part in &plan.parts {
+        let contract = semantic_validate::build_default_contract(&part.name, &part.description);
+        if let Some(expected) = contract.expected_bbox_mm {
+            aggregate = Some(match aggregate {
+                Some(current) => [
+                    current[0].max(expected.sorted_extents_mm[0]),
+                    current[1].max(expected.sorted_extents_mm[1]),
+                    current[2].max(expected.sorted_extents_mm[2]),
+                ],
+                None => expected.sorted_extents_mm,
+            });
+        }
+    }
+    aggregate.or_else(|| semantic_validate::infer_envelope_dimensions_mm(user_request))
+}
+
+fn build_assembly_bbox_hint(
+    plan: &GenerationPlan,
+    user_request: &str,
+    mode: &crate::config::SemanticBboxMode,
+) -> Option<String> {
+    match mode {
+        crate::config::SemanticBboxMode::Legacy => Some(user_request.to_string()),
+        crate::config::SemanticBboxMode::SemanticAware => {
+            assembly_envelope_mm(plan, user_request).map(format_bbox_hint_from_dims)
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Token usage helper
+// ---------------------------------------------------------------------------
 
 fn emit_usage(
-    on_event: &Channel<MultiPartEvent>,
+    on_event: &EventSink,
     phase: &str,
     usage: &TokenUsage,
     provider: &str,
     model: &str,
 ) {
-    let cost_usd = cost::estimate_cost(provider, model, usage);
+    let estimate = cost::estimate_cost(provider, model, usage);
     let _ = on_event.send(MultiPartEvent::TokenUsage {
         phase: phase.to_string(),
         input_tokens: usage.input_tokens,
         output_tokens: usage.output_tokens,
         total_tokens: usage.total(),
-        cost_usd,
+        cost_usd: Some(estimate.cost),
+        currency: estimate.currency,
+        estimated_with_default_rate: estimate.estimated_with_default_rate,
     });
 }
 
+/// Content of one provider stream step. Keep-alives before the first token
+/// become a `Heartbeat` and yield `None`; a stalled stream is an error.
+fn stream_step_delta(
+    progress: StreamProgress,
+    on_event: &EventSink,
+) -> Result<Option<StreamDelta>, String> {
+    match progress {
+        StreamProgress::Delta(delta) => Ok(Some(delta)),
+        StreamProgress::Thinking => {
+            let _ = on_event.send(MultiPartEvent::Heartbeat {
+                phase: "generate".to_string(),
+                detail: "model thinking…".to_string(),
+            });
+            Ok(None)
+        }
+        StreamProgress::Stalled => Err(stalled_message()),
+    }
+}
+
 /// Per-part timeout for failed-part retry loop (seconds).
 const PER_PART_RETRY_TIMEOUT_SECS: u64 = 120;
 
@@ -925,21 +1947,31 @@ fn effective_generation_timeout_seconds(config: &crate::config::AppConfig) -> u6
     (config.max_generation_runtime_seconds as u64).max(MIN_EFFECTIVE_TIMEOUT_SECONDS)
 }
 
-fn forward_validation_event(on_event: &Channel<MultiPartEvent>, evt: executor::ValidationEvent) {
+fn forward_validation_event(on_event: &EventSink, evt: executor::ValidationEvent) {
     match evt {
         executor::ValidationEvent::Attempt {
             attempt,
             max_attempts,
             message,
+            revalidation_scope,
         } => {
             let _ = on_event.send(MultiPartEvent::ValidationAttempt {
                 attempt,
                 max_attempts,
                 message,
+                revalidation_scope,
             });
         }
-        executor::ValidationEvent::StaticValidation { passed, findings } => {
-            let _ = on_event.send(MultiPartEvent::StaticValidationReport { passed, findings });
+        executor::ValidationEvent::StaticValidation {
+            passed,
+            findings,
+            operation_count,
+        } => {
+            let _ = on_event.send(MultiPartEvent::StaticValidationReport {
+                passed,
+                findings,
+                operation_count,
+            });
         }
         executor::ValidationEvent::Success { attempt, message } => {
             let _ = on_event.send(MultiPartEvent::ValidationSuccess { attempt, message });
@@ -955,6 +1987,7 @@ fn forward_validation_event(on_event: &Channel<MultiPartEvent>, evt: executor::V
                 error_category,
                 error_message,
                 will_retry,
+                localized_part: None,
             });
         }
         executor::ValidationEvent::PostGeometryValidation { report } => {
@@ -963,7 +1996,230 @@ fn forward_validation_event(on_event: &Channel<MultiPartEvent>, evt: executor::V
         executor::ValidationEvent::PostGeometryWarning { message } => {
             let _ = on_event.send(MultiPartEvent::PostGeometryValidationWarning { message });
         }
+        executor::ValidationEvent::ModelEscalation {
+            from_model,
+            to_model,
+            message,
+        } => {
+            let _ = on_event.send(MultiPartEvent::ModelEscalation {
+                part_name: None,
+                from_model,
+                to_model,
+                message,
+            });
+        }
+        executor::ValidationEvent::AttemptDiff {
+            attempt,
+            additions,
+            deletions,
+        } => {
+            let _ = on_event.send(MultiPartEvent::ValidationAttemptDiff {
+                attempt,
+                additions,
+                deletions,
+            });
+        }
+        executor::ValidationEvent::FixExplanation {
+            attempt,
+            explanation,
+        } => {
+            let _ = on_event.send(MultiPartEvent::ValidationFixExplanation {
+                attempt,
+                explanation,
+            });
+        }
+    }
+}
+
+/// Validate an assembled script. It runs once without repair first; when the
+/// failing line falls inside one part's block, that part is repaired on its
+/// own like a freshly generated part and spliced back, so the model never
+/// sees the other parts. Failures in the assembly section or in shared
+/// structure keep the whole-script repair, starting from the probe's error so
+/// the failing script is not run twice; a spliced assembly that still fails
+/// is repaired as a whole too.
+async fn validate_assembled_code(
+    code: String,
+    parts: &[PartSpec],
+    ctx: &executor::ExecutionContext,
+    system_prompt: &str,
+    bbox_hint: Option<&str>,
+    on_event: &EventSink,
+    run_store: &Mutex<run_state::RunStore>,
+) -> Result<
+    (
+        executor::ValidationResult,
+        Option<telemetry::FailureLocalization>,
+    ),
+    AppError,
+> {
+    let probe_ctx = executor::ExecutionContext {
+        venv_dir: ctx.venv_dir.clone(),
+        runner_script: ctx.runner_script.clone(),
+        config: crate::config::AppConfig {
+            max_validation_attempts: 1,
+            ..ctx.config.clone()
+        },
+    };
+    // The probe's failure is reported below, once it has been localized.
+    let probe_failure: Mutex<Option<(String, String)>> = Mutex::new(None);
+    let on_probe_event = |evt: executor::ValidationEvent| match evt {
+        executor::ValidationEvent::Failed {
+            error_category,
+            error_message,
+            ..
+        } => {
+            if let Ok(mut failure) = probe_failure.lock() {
+                *failure = Some((error_category, error_message));
+            }
+        }
+        evt => forward_validation_event(on_event, evt),
+    };
+    let probe =
+        executor::validate_and_retry(code, &probe_ctx, system_prompt, bbox_hint, &on_probe_event)
+            .await?;
+    let Some((error_category, error_message)) = probe_failure.into_inner().ok().flatten() else {
+        validation_history::record(
+            run_store,
+            &ctx.config,
+            on_event.run_id(),
+            None,
+            &probe.attempt_history,
+        );
+        return Ok((probe, None));
+    };
+
+    let location = if error_category == "PostGeometry" {
+        FailureLocation::Unknown
+    } else {
+        let structured = crate::agent::validate::parse_traceback(&error_message);
+        failure_localization::failing_line(&error_message, &structured)
+            .map(|line| failure_localization::locate(&probe.code, line))
+            .unwrap_or(FailureLocation::Unknown)
+    };
+    let section = match location {
+        FailureLocation::Part(section) => Some(section),
+        FailureLocation::Assembly | FailureLocation::Unknown => None,
+    };
+    let _ = on_event.send(MultiPartEvent::ValidationFailed {
+        attempt: probe.attempts,
+        error_category,
+        error_message: error_message.clone(),
+        will_retry: true,
+        localized_part: section.as_ref().map(|s| s.name.clone()),
+    });
+
+    let mut code = probe.code.clone();
+    let mut part_repaired = false;
+    if let Some(section) = section.as_ref() {
+        let _ = on_event.send(MultiPartEvent::ReviewStatus {
+            message: format!(
+                "Assembly failed inside '{}'; repairing that part on its own...",
+                section.name
+            ),
+        });
+        let isolated = failure_localization::isolate_part(&probe.code, section);
+        let part_request = parts
+            .iter()
+            .find(|p| p.name == section.name)
+            .map(|p| p.description.as_str())
+            .unwrap_or(section.name.as_str());
+        match evaluate_part_acceptance(
+            &isolated,
+            ctx,
+            system_prompt,
+            part_request,
+            &section.name,
+            None,
+            None,
+            Some((run_store, on_event.run_id())),
+        )
+        .await
+        {
+            Ok(artifact) => {
+                code = failure_localization::splice_part(&probe.code, section, &artifact.code);
+                part_repaired = true;
+            }
+            Err(rejection) => {
+                eprintln!(
+                    "[assembly] Isolated repair of '{}' failed: {}",
+                    section.name, rejection.error
+                );
+            }
+        }
+    }
+
+    let on_validation_event =
+        |evt: executor::ValidationEvent| forward_validation_event(on_event, evt);
+    if !part_repaired {
+        // The whole-script repair picks up the probe's attempt as its first.
+        let mut result = executor::validate_and_retry_after(
+            code,
+            Some(error_message),
+            ctx,
+            system_prompt,
+            bbox_hint,
+            &on_validation_event,
+        )
+        .await?;
+        validation_history::record(
+            run_store,
+            &ctx.config,
+            on_event.run_id(),
+            None,
+            &result.attempt_history,
+        );
+        result.retry_usage.add(&probe.retry_usage);
+        let localization = telemetry::FailureLocalization {
+            repaired: false,
+            part: section.map(|s| s.name),
+        };
+        return Ok((result, Some(localization)));
+    }
+
+    // The probe is the chain's first attempt; the spliced part is its repair.
+    let mut attempt_history = probe.attempt_history;
+    if let (Some(first), Some(section)) = (attempt_history.last_mut(), section.as_ref()) {
+        first.repair_instruction = Some(format!(
+            "Repaired part '{}' on its own and spliced it back",
+            section.name
+        ));
+        first.diff_to_next = modify::compute_diff(&first.code, &code);
+        let count = |tag: &str| first.diff_to_next.iter().filter(|l| l.tag == tag).count();
+        let _ = on_event.send(MultiPartEvent::ValidationAttemptDiff {
+            attempt: first.attempt,
+            additions: count("insert"),
+            deletions: count("delete"),
+        });
+    }
+
+    let mut result =
+        executor::validate_and_retry(code, ctx, system_prompt, bbox_hint, &on_validation_event)
+            .await?;
+    attempt_history.extend(result.attempt_history.drain(..).map(|mut record| {
+        record.attempt += probe.attempts;
+        record
+    }));
+    result.attempt_history = attempt_history;
+    validation_history::record(
+        run_store,
+        &ctx.config,
+        on_event.run_id(),
+        None,
+        &result.attempt_history,
+    );
+    result.attempts += probe.attempts;
+    result.retry_usage.add(&probe.retry_usage);
+    for finding in probe.static_findings {
+        if !result.static_findings.contains(&finding) {
+            result.static_findings.push(finding);
+        }
     }
+    let localization = telemetry::FailureLocalization {
+        repaired: result.success && result.attempts == probe.attempts + 1,
+        part: section.map(|s| s.name),
+    };
+    Ok((result, Some(localization)))
 }
 
 async fn build_system_prompt_with_retrieval(
@@ -971,15 +2227,25 @@ async fn build_system_prompt_with_retrieval(
     cq_version: Option<&str>,
     query: &str,
     session_context: Option<String>,
-    on_event: &Channel<MultiPartEvent>,
+    on_event: &EventSink,
     compact: bool,
 ) -> (String, retrieval::RetrievalResult) {
+    let house_style = config.custom_system_prompt_suffix.as_deref();
+    if let Some((_, true)) = prompts::house_style_section(house_style) {
+        let _ = on_event.send(MultiPartEvent::Warning {
+            code: "house_style_truncated".to_string(),
+            message: format!(
+                "The custom system prompt suffix was cut to {} characters.",
+                prompts::MAX_HOUSE_STYLE_CHARS
+            ),
+        });
+    }
+
     // Fine-tuned provider: skip retrieval entirely and use a minimal prompt.
     if prompts::is_finetuned_provider(&config.ai_provider) {
-        return (
-            prompts::build_finetuned_system_prompt(),
-            retrieval::RetrievalResult::empty(),
-        );
+        let mut system_prompt = prompts::build_finetuned_system_prompt();
+        prompts::append_house_style(&mut system_prompt, house_style);
+        return (system_prompt, retrieval::RetrievalResult::empty());
     }
 
     let base = if compact {
@@ -999,6 +2265,8 @@ async fn build_system_prompt_with_retrieval(
         items: vec![],
         used_embeddings: false,
         lexical_fallback: false,
+        dropped_below_threshold: 0,
+        embedding_mode: String::new(),
     });
 
     let mut retrieval_result = retrieval::retrieve_context(
@@ -1013,15 +2281,56 @@ async fn build_system_prompt_with_retrieval(
         message: if retrieval_result.items.is_empty() {
             "No retrieval snippets matched; using compact core prompt.".to_string()
         } else {
-            format!(
-                "Selected {} retrieval snippets.",
-                retrieval_result.items.len()
-            )
+            let boosted: Vec<&str> = retrieval_result
+                .items
+                .iter()
+                .filter(|item| item.boosted)
+                .map(|item| item.title.as_str())
+                .collect();
+            if boosted.is_empty() {
+                format!(
+                    "Selected {} retrieval snippets.",
+                    retrieval_result.items.len()
+                )
+            } else {
+                format!(
+                    "Selected {} retrieval snippets ({} boosted from prioritized packs: {}).",
+                    retrieval_result.items.len(),
+                    boosted.len(),
+                    boosted.join(", ")
+                )
+            }
         },
         items: retrieval_result.items.clone(),
         used_embeddings: retrieval_result.used_embeddings,
         lexical_fallback: retrieval_result.lexical_fallback,
+        dropped_below_threshold: retrieval_result.dropped_below_threshold,
+        embedding_mode: retrieval_result.embedding_mode.to_string(),
     });
+    for message in crate::agent::custom_rules::take_warnings() {
+        let _ = on_event.send(MultiPartEvent::Warning {
+            code: "custom_rules_invalid".to_string(),
+            message,
+        });
+    }
+    if !retrieval_result.missing_pinned_ids.is_empty() {
+        let _ = on_event.send(MultiPartEvent::Warning {
+            code: "pinned_retrieval_missing".to_string(),
+            message: format!(
+                "Pinned retrieval items not found: {}",
+                retrieval_result.missing_pinned_ids.join(", ")
+            ),
+        });
+    }
+    if let Some(reason) = retrieval::EMBEDDINGS_WARNING.take(&retrieval_result) {
+        let _ = on_event.send(MultiPartEvent::Warning {
+            code: "embeddings_unavailable".to_string(),
+            message: format!(
+                "Embeddings are unavailable, so retrieval fell back to keyword matching and may be less relevant: {}",
+                reason
+            ),
+        });
+    }
 
     let mut system_prompt = base;
     if let Some(ctx) = session_context {
@@ -1032,6 +2341,7 @@ async fn build_system_prompt_with_retrieval(
         system_prompt.push_str("\n\n");
         system_prompt.push_str(&retrieval_result.context_markdown);
     }
+    prompts::append_house_style(&mut system_prompt, house_style);
 
     if retrieval_result.items.is_empty() {
         retrieval_result = retrieval::RetrievalResult::empty();
@@ -1044,11 +2354,99 @@ async fn build_system_prompt_with_retrieval(
 // Extracted helpers (shared by generate_parallel, generate_design_plan, generate_from_plan)
 // ---------------------------------------------------------------------------
 
+/// Substitute proportional dimensions ("1/3 of the width") into the plan and
+/// validate it. Substitutions are appended to the warnings as notes; the
+/// returned questions cover relatives with no base dimension.
+fn validate_design_plan(
+    design_plan: &mut design::DesignPlan,
+    message: &str,
+    config: &crate::config::AppConfig,
+) -> (design::PlanValidation, Vec<String>) {
+    let resolution = design::resolve_proportional_dimensions(&design_plan.text, message);
+    design_plan.text = resolution.plan_text;
+    let mut validation =
+        design::validate_plan_with_profile(&design_plan.text, &config.generation_reliability_profile);
+    validation.warnings.extend(resolution.notes);
+    design::apply_organic_approximation_check(
+        &mut validation,
+        message,
+        &config.generation_reliability_profile,
+        config.organic_missing_notes_risk,
+    );
+    design::apply_operation_budget(
+        &mut validation,
+        config.operation_soft_budget,
+        config.operation_hard_budget,
+    );
+    (validation, resolution.questions)
+}
+
+/// Confidence of a validated plan against the active preset's cookbook and patterns.
+fn assess_plan_confidence(
+    validation: &design::PlanValidation,
+    config: &crate::config::AppConfig,
+) -> confidence::ConfidenceAssessment {
+    let confidence_rules =
+        crate::agent::rules::AgentRules::from_preset(config.agent_rules_preset.as_deref()).ok();
+    let cookbook_ref = confidence_rules
+        .as_ref()
+        .and_then(|r| r.cookbook.as_deref());
+    let patterns_ref = confidence_rules
+        .as_ref()
+        .and_then(|r| r.design_patterns.as_deref());
+
+    confidence::assess_confidence_with_profile(
+        validation,
+        cookbook_ref,
+        patterns_ref,
+        &config.generation_reliability_profile,
+    )
+}
+
+/// Targeted repair for a plan whose Build Plan has no numbered steps: ask for
+/// just the steps instead of a full re-plan. A failed request keeps the plan
+/// as it is and leaves the validator's warning in place.
+async fn ensure_numbered_build_steps(
+    design_plan: &mut design::DesignPlan,
+    message: &str,
+    config: &crate::config::AppConfig,
+    on_event: &EventSink,
+    total_usage: &mut TokenUsage,
+    provider_id: &str,
+    model_id: &str,
+) -> Result<(), AppError> {
+    if !design::build_plan_lacks_numbered_steps(&design_plan.text) {
+        return Ok(());
+    }
+    let _ = on_event.send(MultiPartEvent::PlanStatus {
+        message: "Build Plan has no numbered steps, requesting them...".to_string(),
+    });
+    let provider = create_provider(config)?;
+    match design::request_numbered_build_steps(provider, message, &design_plan.text).await {
+        Ok((repaired, usage)) => {
+            if let Some(ref u) = usage {
+                total_usage.add(u);
+                emit_usage(on_event, "design", u, provider_id, model_id);
+            }
+            if let Some(text) = repaired {
+                design_plan.text = text;
+            }
+        }
+        Err(e) => {
+            let _ = on_event.send(MultiPartEvent::Warning {
+                code: "build_steps_repair_failed".to_string(),
+                message: format!("Could not get numbered build steps: {}", e),
+            });
+        }
+    }
+    Ok(())
+}
+
 /// Phase 0: Generate and validate the geometry design plan.
 async fn run_design_plan_phase(
     message: &str,
     config: &crate::config::AppConfig,
-    on_event: &Channel<MultiPartEvent>,
+    on_event: &EventSink,
     total_usage: &mut TokenUsage,
     provider_id: &str,
     model_id: &str,
@@ -1063,21 +2461,13 @@ async fn run_design_plan_phase(
             crate::agent::rules::AgentRules::from_preset(config.agent_rules_preset.as_deref()).ok();
         let mut ctx = String::new();
         if let Some(ref r) = rules {
-            if let Some(ref m) = r.manufacturing {
-                ctx.push_str(&crate::agent::design::format_manufacturing_constraints(m));
-            }
-            if let Some(ref d) = r.dimension_guidance {
-                if !ctx.is_empty() {
-                    ctx.push_str("\n\n");
-                }
-                ctx.push_str(&crate::agent::design::format_dimension_guidance(d));
-            }
-            if let Some(ref fp) = r.failure_prevention {
-                if !ctx.is_empty() {
-                    ctx.push_str("\n\n");
-                }
-                ctx.push_str(&crate::agent::design::format_failure_prevention(fp));
+            let rule_context = design::format_rule_context(r, message, config);
+            if !rule_context.sections.is_empty() {
+                let _ = on_event.send(MultiPartEvent::PlanStatus {
+                    message: rule_context.summary(),
+                });
             }
+            ctx.push_str(&rule_context.context);
         }
         // Append session memory context so the geometry advisor knows what failed
         if let Some(session_ctx) = state.session_memory.lock().unwrap().build_context_section() {
@@ -1110,10 +2500,19 @@ async fn run_design_plan_phase(
         ));
     }
 
-    let mut validation = design::validate_plan_with_profile(
-        &design_plan.text,
-        &config.generation_reliability_profile,
-    );
+    ensure_numbered_build_steps(
+        &mut design_plan,
+        message,
+        config,
+        on_event,
+        total_usage,
+        provider_id,
+        model_id,
+    )
+    .await?;
+
+    let (mut validation, mut clarification_questions) =
+        validate_design_plan(&mut design_plan, message, config);
 
     let _ = on_event.send(MultiPartEvent::PlanValidation {
         risk_score: validation.risk_score,
@@ -1123,19 +2522,20 @@ async fn run_design_plan_phase(
         fatal_combo: validation.risk_signals.fatal_combo,
         negation_conflict: validation.risk_signals.negation_conflict,
         repair_sensitive_ops: validation.risk_signals.repair_sensitive_ops.clone(),
+        estimated_operations: validation.risk_signals.estimated_operations,
     });
 
     // Give the planner multiple chances to return a valid structured plan.
     // This significantly reduces failures from malformed first responses.
-    const MAX_PLAN_ATTEMPTS: usize = 3;
+    let max_plan_attempts = design_replan_attempts(config);
     let mut attempts = 1usize;
-    while !validation.is_valid && attempts < MAX_PLAN_ATTEMPTS {
+    while !validation.is_valid && attempts < max_plan_attempts {
         let _ = on_event.send(MultiPartEvent::PlanStatus {
             message: format!(
                 "Design plan too risky (score {}/10), re-planning (attempt {}/{})...",
                 validation.risk_score,
                 attempts + 1,
-                MAX_PLAN_ATTEMPTS
+                max_plan_attempts
             ),
         });
 
@@ -1153,11 +2553,19 @@ async fn run_design_plan_phase(
             total_usage.add(u);
             emit_usage(on_event, "design", u, provider_id, model_id);
         }
+        ensure_numbered_build_steps(
+            &mut design_plan,
+            message,
+            config,
+            on_event,
+            total_usage,
+            provider_id,
+            model_id,
+        )
+        .await?;
 
-        validation = design::validate_plan_with_profile(
-            &design_plan.text,
-            &config.generation_reliability_profile,
-        );
+        (validation, clarification_questions) =
+            validate_design_plan(&mut design_plan, message, config);
         let _ = on_event.send(MultiPartEvent::PlanValidation {
             risk_score: validation.risk_score,
             warnings: validation.warnings.clone(),
@@ -1166,59 +2574,125 @@ async fn run_design_plan_phase(
             fatal_combo: validation.risk_signals.fatal_combo,
             negation_conflict: validation.risk_signals.negation_conflict,
             repair_sensitive_ops: validation.risk_signals.repair_sensitive_ops.clone(),
+            estimated_operations: validation.risk_signals.estimated_operations,
         });
 
         attempts += 1;
     }
 
+    let mut conf = assess_plan_confidence(&validation, config);
+
+    // Optionally give a valid but Low-confidence plan one more round, keeping
+    // whichever plan scores higher.
+    if confidence::should_replan_for_confidence(
+        &validation,
+        &conf,
+        config.replan_on_low_confidence,
+    ) {
+        let _ = on_event.send(MultiPartEvent::PlanStatus {
+            message: format!(
+                "Design plan confidence is low ({}/100), re-planning once...",
+                conf.score
+            ),
+        });
+
+        let feedback = confidence::build_low_confidence_feedback(&conf);
+        let retry_provider = create_provider(config)?;
+        let (mut retry_plan, retry_usage) = design::plan_geometry_with_feedback(
+            retry_provider,
+            message,
+            &feedback,
+            design_extra_context.as_deref(),
+        )
+        .await?;
+        if let Some(ref u) = retry_usage {
+            total_usage.add(u);
+            emit_usage(on_event, "design", u, provider_id, model_id);
+        }
+
+        if !retry_plan.text.trim().is_empty() {
+            let (retry_validation, retry_questions) =
+                validate_design_plan(&mut retry_plan, message, config);
+            let retry_conf = assess_plan_confidence(&retry_validation, config);
+            if confidence::replan_is_better(&conf, &retry_validation, &retry_conf) {
+                let _ = on_event.send(MultiPartEvent::PlanValidation {
+                    risk_score: retry_validation.risk_score,
+                    warnings: retry_validation.warnings.clone(),
+                    is_valid: retry_validation.is_valid,
+                    rejected_reason: retry_validation.rejected_reason.clone(),
+                    fatal_combo: retry_validation.risk_signals.fatal_combo,
+                    negation_conflict: retry_validation.risk_signals.negation_conflict,
+                    repair_sensitive_ops: retry_validation.risk_signals.repair_sensitive_ops.clone(),
+                    estimated_operations: retry_validation.risk_signals.estimated_operations,
+                });
+                design_plan = retry_plan;
+                validation = retry_validation;
+                clarification_questions = retry_questions;
+                conf = retry_conf;
+            } else {
+                let _ = on_event.send(MultiPartEvent::PlanStatus {
+                    message: format!(
+                        "Re-planned design scored {}/100; keeping the original plan.",
+                        retry_conf.score
+                    ),
+                });
+            }
+        }
+    }
+
     let final_risk_score = validation.risk_score;
     let final_warnings = validation.warnings.clone();
     let final_is_valid = validation.is_valid;
 
+    // Sizes given only relative to an unknown dimension: ask instead of
+    // letting the model invent the base value.
+    if !clarification_questions.is_empty() {
+        let _ = on_event.send(MultiPartEvent::ClarificationNeeded {
+            questions: clarification_questions.clone(),
+        });
+    }
+
     let _ = on_event.send(MultiPartEvent::DesignPlan {
         plan_text: design_plan.text.clone(),
     });
 
-    // Compute and emit confidence assessment
-    {
-        let confidence_rules =
-            crate::agent::rules::AgentRules::from_preset(config.agent_rules_preset.as_deref()).ok();
-        let cookbook_ref = confidence_rules
-            .as_ref()
-            .and_then(|r| r.cookbook.as_deref());
-        let patterns_ref = confidence_rules
-            .as_ref()
-            .and_then(|r| r.design_patterns.as_deref());
-
-        let conf = confidence::assess_confidence_with_profile(
-            &validation,
-            cookbook_ref,
-            patterns_ref,
-            &config.generation_reliability_profile,
-        );
-        let _ = on_event.send(MultiPartEvent::ConfidenceAssessment {
-            level: match conf.level {
-                confidence::ConfidenceLevel::High => "high".to_string(),
-                confidence::ConfidenceLevel::Medium => "medium".to_string(),
-                confidence::ConfidenceLevel::Low => "low".to_string(),
-            },
-            score: conf.score,
-            cookbook_matches: conf
-                .cookbook_matches
-                .iter()
-                .map(|m| m.title.clone())
-                .collect(),
-            warnings: conf.warnings.clone(),
-            message: conf.message.clone(),
-        });
-    }
+    let _ = on_event.send(MultiPartEvent::ConfidenceAssessment {
+        level: match conf.level {
+            confidence::ConfidenceLevel::High => "high".to_string(),
+            confidence::ConfidenceLevel::Medium => "medium".to_string(),
+            confidence::ConfidenceLevel::Low => "low".to_string(),
+        },
+        score: conf.score,
+        cookbook_matches: conf
+            .cookbook_matches
+            .iter()
+            .map(|m| m.title.clone())
+            .collect(),
+        warnings: conf.warnings.clone(),
+        message: conf.message.clone(),
+    });
 
+    let auto_approval = confidence::auto_approval_decision(
+        final_is_valid,
+        final_risk_score,
+        conf.level,
+        !clarification_questions.is_empty(),
+        config.auto_approve_max_risk,
+    );
     let result = DesignPlanResult {
         plan_text: design_plan.text.clone(),
         risk_score: final_risk_score,
         warnings: final_warnings,
         is_valid: final_is_valid,
-        clarification_questions: None,
+        estimated_operations: validation.risk_signals.estimated_operations,
+        clarification_questions: if clarification_questions.is_empty() {
+            None
+        } else {
+            Some(clarification_questions)
+        },
+        auto_approval: Some(auto_approval),
+        auto_approved: false,
+        generation_response: None,
     };
 
     Ok((design_plan, result))
@@ -1233,12 +2707,17 @@ async fn run_generation_pipeline(
     history: Vec<ChatMessage>,
     config: &crate::config::AppConfig,
     system_prompt: &str,
-    on_event: &Channel<MultiPartEvent>,
+    on_event: &EventSink,
     execution_ctx: Option<&executor::ExecutionContext>,
     total_usage: &mut TokenUsage,
     provider_id: &str,
     model_id: &str,
+    part_materials: &HashMap<String, String>,
+    part_colors: &HashMap<String, PartColor>,
+    run_store: &Mutex<run_state::RunStore>,
+    retrieved: &[retrieval::RetrievedContextItem],
 ) -> Result<PipelineOutcome, AppError> {
+    let pipeline_started_ms = telemetry::now_ms();
     let enhanced_message = format!(
         "## Geometry Design Plan\n{}\n\n## User Request\n{}",
         plan_text, user_request
@@ -1252,15 +2731,18 @@ async fn run_generation_pipeline(
     });
 
     let requires_multipart_contract = request_requires_multipart_contract(user_request, plan_text);
-    let planner_system = PLANNER_SYSTEM_PROMPT.to_string();
+    let mut planner_system = PLANNER_SYSTEM_PROMPT.to_string();
+    if let Some(max_parts) = config.max_plan_parts {
+        planner_system.push_str(&format!("\n\nUse at most {} part(s).", max_parts));
+    }
     let mut plan: Option<GenerationPlan> = None;
     let mut last_parse_err: Option<String> = None;
     let mut planner_response = String::new();
     let mut planner_parse_failures: u32 = 0;
-    const MAX_PLANNER_PARSE_ATTEMPTS: usize = 3;
+    let max_planner_parse_attempts = planner_parse_attempts(config);
     const PLANNER_MAX_TOKENS: u32 = 3072;
 
-    for attempt in 1..=MAX_PLANNER_PARSE_ATTEMPTS {
+    for attempt in 1..=max_planner_parse_attempts {
         let planner = create_provider(config)?;
         let planner_messages = if attempt == 1 {
             vec![
@@ -1315,20 +2797,45 @@ async fn run_generation_pipeline(
                 attempt
             ));
             planner_parse_failures = planner_parse_failures.saturating_add(1);
-            if attempt < MAX_PLANNER_PARSE_ATTEMPTS {
+            if attempt < max_planner_parse_attempts {
                 let _ = on_event.send(MultiPartEvent::PlanStatus {
                     message: format!(
                         "Planner returned empty response (attempt {}/{}), retrying...",
-                        attempt, MAX_PLANNER_PARSE_ATTEMPTS
+                        attempt, max_planner_parse_attempts
                     ),
                 });
             }
             continue;
         }
 
-        match parse_plan(&plan_json) {
-            Ok(parsed) => {
+        match parse_plan_with_renames(&plan_json) {
+            Ok((parsed, renames)) => {
                 let mut p = parsed;
+                if !renames.is_empty() {
+                    let listed: Vec<String> = renames
+                        .iter()
+                        .map(|(from, to)| format!("'{}' -> '{}'", from, to))
+                        .collect();
+                    let _ = on_event.send(MultiPartEvent::Warning {
+                        code: "part_name_sanitized".to_string(),
+                        message: format!(
+                            "Renamed planner part(s) to valid Python identifiers: {}",
+                            listed.join(", ")
+                        ),
+                    });
+                }
+                if let Some(message) = reconcile_plan_mode(&mut p, &config.decomposition_bias) {
+                    let _ = on_event.send(MultiPartEvent::Warning {
+                        code: "planner_mode_parts_mismatch".to_string(),
+                        message,
+                    });
+                }
+                if let Some(message) = cap_plan_parts(&mut p, config.max_plan_parts) {
+                    let _ = on_event.send(MultiPartEvent::Warning {
+                        code: "plan_parts_capped".to_string(),
+                        message,
+                    });
+                }
                 resolve_cross_references(&mut p);
                 plan = Some(p);
                 break;
@@ -1336,11 +2843,11 @@ async fn run_generation_pipeline(
             Err(parse_err) => {
                 last_parse_err = Some(parse_err.clone());
                 planner_parse_failures = planner_parse_failures.saturating_add(1);
-                if attempt < MAX_PLANNER_PARSE_ATTEMPTS {
+                if attempt < max_planner_parse_attempts {
                     let _ = on_event.send(MultiPartEvent::PlanStatus {
                         message: format!(
                             "Planner JSON parse failed (attempt {}/{}), retrying with strict compact JSON...",
-                            attempt, MAX_PLANNER_PARSE_ATTEMPTS
+                            attempt, max_planner_parse_attempts
                         ),
                     });
                 }
@@ -1351,27 +2858,28 @@ async fn run_generation_pipeline(
     let plan: GenerationPlan = match plan {
         Some(p) => p,
         None => {
-            if requires_multipart_contract {
-                let parse_err = last_parse_err
-                    .clone()
-                    .unwrap_or_else(|| "unknown planner parse error".to_string());
-                let response_preview: String = planner_response.chars().take(200).collect();
-                return Err(AppError::AiProviderError(format!(
-                    "Planner failed to decompose parts after {} attempt(s): {}. Last response: '{}'",
-                    planner_parse_failures, parse_err, response_preview
-                )));
-            } else {
-                let _ = on_event.send(MultiPartEvent::PlanStatus {
-                    message:
-                        "Planner returned invalid JSON; falling back to single-part generation."
-                            .to_string(),
+            let parse_err = last_parse_err
+                .clone()
+                .unwrap_or_else(|| "unknown planner parse error".to_string());
+            let response_preview: String = planner_response.chars().take(200).collect();
+            let (fallback, warning) = planner_failure_fallback(
+                &config.planner_failure_policy,
+                requires_multipart_contract,
+                planner_parse_failures,
+                &parse_err,
+                &response_preview,
+            )?;
+            if let Some(message) = warning {
+                let _ = on_event.send(MultiPartEvent::Warning {
+                    code: "planner_failed_single_fallback".to_string(),
+                    message,
                 });
-                GenerationPlan {
-                    mode: "single".to_string(),
-                    description: None,
-                    parts: vec![],
-                }
             }
+            let _ = on_event.send(MultiPartEvent::PlanStatus {
+                message: "Planner returned invalid JSON; falling back to single-part generation."
+                    .to_string(),
+            });
+            fallback
         }
     };
 
@@ -1380,12 +2888,33 @@ async fn run_generation_pipeline(
             "Planner failed to produce a valid multipart decomposition — the plan did not contain at least 2 parts.".to_string(),
         ));
     }
-    let _ = on_event.send(MultiPartEvent::PlanResult { plan: plan.clone() });
+    let _ = on_event.send(MultiPartEvent::PlanResult {
+        plan: plan.clone(),
+        kinematics: kinematics::classify_parts(&plan.parts),
+    });
+    on_event.ensure_connected()?;
 
     // -----------------------------------------------------------------------
     // Single mode: fall through to normal streaming
     // -----------------------------------------------------------------------
     if plan.mode == "single" || plan.parts.is_empty() {
+        // Ready-made helpers for standard fasteners and patterns named in the
+        // request or plan.
+        let mut single_system_prompt = match features::fastener_features_section(&enhanced_message)
+        {
+            Some(section) => format!("{}\n\n{}", system_prompt, section),
+            None => system_prompt.to_string(),
+        };
+        if let Some(section) = features::pattern_features_section(&enhanced_message) {
+            single_system_prompt.push_str("\n\n");
+            single_system_prompt.push_str(&section);
+        }
+        if let Some(section) = threads::thread_helper_section(&enhanced_message, config) {
+            single_system_prompt.push_str("\n\n");
+            single_system_prompt.push_str(&section);
+        }
+        let system_prompt = single_system_prompt.as_str();
+
         // Check if iterative mode should be used
         let build_steps = iterative::parse_build_steps(plan_text);
 
@@ -1468,9 +2997,11 @@ async fn run_generation_pipeline(
                     );
                 }
 
+                let geometry_hash = executor::geometry_hash_base64(result.stl_base64.as_deref());
                 let _ = on_event.send(MultiPartEvent::FinalCode {
                     code: result.final_code.clone(),
                     stl_base64: result.stl_base64.clone(),
+                    geometry_hash: geometry_hash.clone(),
                 });
 
                 let _ = on_event.send(MultiPartEvent::IterativeComplete {
@@ -1498,6 +3029,7 @@ async fn run_generation_pipeline(
                     response: result.final_code.clone(),
                     final_code: Some(result.final_code),
                     success: result.success,
+                    validated: true,
                     error: iter_error,
                     validation_attempts: None,
                     static_findings: vec![],
@@ -1508,7 +3040,13 @@ async fn run_generation_pipeline(
                     partial_preview_shown: result.stl_base64.is_some(),
                     empty_viewport_after_generation: result.stl_base64.is_none(),
                     retry_ladder_stage_reached: None,
+                    model_escalations: vec![],
+                    repair_example_uses: vec![],
+                    geometry_hash,
                     failure_signatures: vec![],
+                    awaiting_assembly_approval: false,
+                    failure_localization: None,
+                    injected_mechanism_ids: Vec::new(),
                 });
             }
             // No Python execution context → fall through to single-shot
@@ -1594,13 +3132,14 @@ async fn run_generation_pipeline(
                         let _ = on_event.send(MultiPartEvent::ReviewStatus {
                             message: "Reviewing consensus winner...".to_string(),
                         });
+                        on_event.ensure_connected()?;
                         let review_provider = create_provider(config)?;
                         match review::review_code(
                             review_provider,
                             user_request,
                             code,
                             Some(plan_text),
-                            &config.reviewer_mode,
+                            &review::ReviewOptions::from_config(config),
                         )
                         .await
                         {
@@ -1612,6 +3151,8 @@ async fn run_generation_pipeline(
                                 let _ = on_event.send(MultiPartEvent::ReviewComplete {
                                     was_modified: result.was_modified,
                                     explanation: result.explanation.clone(),
+                                    findings: result.findings.clone(),
+                                    ai_reviewed: result.ai_reviewed,
                                 });
                                 if result.was_modified {
                                     final_code = result.code;
@@ -1624,10 +3165,14 @@ async fn run_generation_pipeline(
                         }
                     }
 
+                    let final_geometry_hash;
                     if winner.execution_success && !reviewed {
+                        final_geometry_hash =
+                            executor::geometry_hash_base64(winner.stl_base64.as_deref());
                         let _ = on_event.send(MultiPartEvent::FinalCode {
                             code: final_code.clone(),
                             stl_base64: winner.stl_base64.clone(),
+                            geometry_hash: final_geometry_hash.clone(),
                         });
                     } else {
                         let on_validation_event = |evt: executor::ValidationEvent| {
@@ -1642,6 +3187,13 @@ async fn run_generation_pipeline(
                             &on_validation_event,
                         )
                         .await?;
+                        validation_history::record(
+                            run_store,
+                            &ctx.config,
+                            on_event.run_id(),
+                            None,
+                            &validation_result.attempt_history,
+                        );
 
                         if validation_result.retry_usage.total() > 0 {
                             total_usage.add(&validation_result.retry_usage);
@@ -1654,10 +3206,36 @@ async fn run_generation_pipeline(
                             );
                         }
 
-                        let _ = on_event.send(MultiPartEvent::FinalCode {
-                            code: validation_result.code.clone(),
-                            stl_base64: validation_result.stl_base64.clone(),
-                        });
+                        if reviewed && !validation_result.success && winner.execution_success {
+                            // The unreviewed winner already executed; prefer it over
+                            // reviewed code that never validated.
+                            let _ = on_event.send(MultiPartEvent::ReviewReverted {
+                                reason: format!(
+                                    "Reviewed code failed validation ({}). Keeping the consensus winner.",
+                                    validation_result
+                                        .error
+                                        .as_deref()
+                                        .unwrap_or("unknown error")
+                                ),
+                            });
+                            final_code = code.clone();
+                            final_geometry_hash =
+                                executor::geometry_hash_base64(winner.stl_base64.as_deref());
+                            let _ = on_event.send(MultiPartEvent::FinalCode {
+                                code: final_code.clone(),
+                                stl_base64: winner.stl_base64.clone(),
+                                geometry_hash: final_geometry_hash.clone(),
+                            });
+                        } else {
+                            final_geometry_hash = executor::geometry_hash_base64(
+                                validation_result.stl_base64.as_deref(),
+                            );
+                            let _ = on_event.send(MultiPartEvent::FinalCode {
+                                code: validation_result.code.clone(),
+                                stl_base64: validation_result.stl_base64.clone(),
+                                geometry_hash: final_geometry_hash.clone(),
+                            });
+                        }
                     }
 
                     if total_usage.total() > 0 {
@@ -1674,6 +3252,7 @@ async fn run_generation_pipeline(
                         response: response_text,
                         final_code: Some(final_code),
                         success: true,
+                        validated: true,
                         error: None,
                         validation_attempts: None,
                         static_findings: vec![],
@@ -1684,7 +3263,13 @@ async fn run_generation_pipeline(
                         partial_preview_shown: false,
                         empty_viewport_after_generation: false,
                         retry_ladder_stage_reached: None,
+                        model_escalations: vec![],
+                        repair_example_uses: vec![],
+                        geometry_hash: final_geometry_hash,
                         failure_signatures: vec![],
+                        awaiting_assembly_approval: false,
+                        failure_localization: None,
+                        injected_mechanism_ids: Vec::new(),
                     });
                 }
 
@@ -1701,8 +3286,9 @@ async fn run_generation_pipeline(
         let _ = on_event.send(MultiPartEvent::PlanStatus {
             message: "Generating code...".to_string(),
         });
+        on_event.ensure_connected()?;
 
-        let provider = create_provider(config)?;
+        let provider = create_code_provider(config)?;
 
         let mut messages_list = vec![ChatMessage {
             role: "system".to_string(),
@@ -1714,17 +3300,32 @@ async fn run_generation_pipeline(
             content: enhanced_message.clone(),
         });
 
-        let (tx, mut rx) = mpsc::channel::<StreamDelta>(100);
+        let (tx, rx) = mpsc::channel::<StreamSignal>(100);
+        let mut stream = StreamWatch::for_provider(provider.as_ref(), rx);
         let provider_handle =
             tokio::spawn(async move { provider.stream(&messages_list, tx).await });
 
         let mut full_response = String::new();
-        while let Some(delta) = rx.recv().await {
+        let mut deltas_seen = 0usize;
+        while let Some(progress) = stream.next().await {
+            let delta = match stream_step_delta(progress, on_event) {
+                Ok(Some(delta)) => delta,
+                Ok(None) => continue,
+                Err(e) => {
+                    provider_handle.abort();
+                    return Err(AppError::AiProviderError(e));
+                }
+            };
             full_response.push_str(&delta.content);
             let _ = on_event.send(MultiPartEvent::SingleDelta {
                 delta: delta.content,
                 done: delta.done,
             });
+            deltas_seen += 1;
+            if on_event.stream_interrupted(deltas_seen) {
+                provider_handle.abort();
+                return Err(AppError::ChannelDisconnected(on_event.run_id().to_string()));
+            }
         }
 
         match provider_handle.await {
@@ -1747,210 +3348,104 @@ async fn run_generation_pipeline(
             full_response: full_response.clone(),
         });
 
-        let mut final_code = extract_code_from_response(&full_response);
-        let mut final_response = full_response.clone();
+        return finish_single_response(
+            full_response,
+            user_request,
+            Some(plan_text),
+            system_prompt,
+            config,
+            execution_ctx,
+            on_event,
+            total_usage,
+            provider_id,
+            model_id,
+            run_store,
+        )
+        .await;
+    }
 
-        if config.enable_code_review {
-            if let Some(ref code) = final_code {
-                let _ = on_event.send(MultiPartEvent::ReviewStatus {
-                    message: "Reviewing generated code...".to_string(),
-                });
+    // -----------------------------------------------------------------------
+    // Phase 2: Parallel generation
+    // -----------------------------------------------------------------------
+    let injected_mechanism_ids = mechanism_reference::injected_ids(
+        retrieved,
+        plan.parts
+            .iter()
+            .map(|p| (p.name.as_str(), p.description.as_str())),
+    );
+    // Shares the event run id so a parked run's candidates and events line up.
+    let run_id = on_event.run_id().to_string();
+    if let Ok(mut store) = run_store.lock() {
+        store.start_run(&run_id, user_request, plan_text, &plan);
+    }
+    note_expired_parked_runs(run_store, config);
+    let _ = on_event.send(MultiPartEvent::RunStarted {
+        run_id: run_id.clone(),
+    });
+    let _ = on_event.send(MultiPartEvent::PlanStatus {
+        message: format!("Generating {} parts in parallel...", plan.parts.len()),
+    });
+    on_event.ensure_connected()?;
 
-                let review_provider = create_provider(config)?;
-                match review::review_code(
-                    review_provider,
-                    user_request,
-                    code,
-                    Some(plan_text),
-                    &config.reviewer_mode,
-                )
-                .await
-                {
-                    Ok((result, review_usage)) => {
-                        if let Some(ref u) = review_usage {
-                            total_usage.add(u);
-                            emit_usage(on_event, "review", u, provider_id, model_id);
-                        }
-                        let _ = on_event.send(MultiPartEvent::ReviewComplete {
-                            was_modified: result.was_modified,
-                            explanation: result.explanation.clone(),
-                        });
-                        if result.was_modified {
-                            final_response = full_response.replace(code, &result.code);
-                            final_code = Some(result.code);
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Code review failed: {}", e);
-                    }
-                }
-            }
-        }
+    let mut handles = Vec::new();
 
-        // Backend validation
-        if let (Some(code), Some(ctx)) = (&final_code, execution_ctx) {
-            let on_validation_event =
-                |evt: executor::ValidationEvent| forward_validation_event(on_event, evt);
+    // Write prompt debug log to file for inspection
+    let debug_log_path = prompt_debug_log_path();
+    let mut debug_log = open_prompt_debug_log(prompt_debug_logging_enabled(config), &debug_log_path);
+    if let Some(ref mut f) = debug_log {
+        let _ = writeln!(f, "╔══════════════════════════════════════════════════════════════════╗");
+        let _ = writeln!(f, "║  MULTI-PART DISPATCH: {} API calls for {} parts", plan.parts.len(), plan.parts.len());
+        let _ = writeln!(f, "║  Parts: {:?}", plan.parts.iter().map(|p| p.name.as_str()).collect::<Vec<_>>());
+        let _ = writeln!(f, "║  Timestamp: {:?}", std::time::SystemTime::now());
+        let _ = writeln!(f, "╚══════════════════════════════════════════════════════════════════╝");
+        let _ = writeln!(f);
+        eprintln!("[multipart] Debug log: {}", debug_log_path.display());
+    }
 
-            let validation_result = executor::validate_and_retry(
-                code.clone(),
-                ctx,
-                system_prompt,
-                Some(user_request),
-                &on_validation_event,
-            )
-            .await?;
+    for (idx, part) in plan.parts.iter().enumerate() {
+        for conflict in part_constraints::validate_part_constraints(part) {
+            let _ = on_event.send(MultiPartEvent::Warning {
+                code: "part_constraint_conflict".to_string(),
+                message: format!("Part '{}': {}", part.name, conflict.message),
+            });
+        }
 
-            if validation_result.retry_usage.total() > 0 {
-                total_usage.add(&validation_result.retry_usage);
-                emit_usage(
-                    on_event,
-                    "validation",
-                    &validation_result.retry_usage,
-                    provider_id,
-                    model_id,
-                );
-            }
+        let part_provider = create_code_provider(config)?;
+        let sibling_summary = build_sibling_dimensions_summary(&plan.parts, &part.name, &[]);
+        let reference = part_reference_section(retrieved, part);
+        let part_prompt = build_part_prompt(
+            system_prompt,
+            part,
+            plan_text,
+            config,
+            &sibling_summary,
+            &reference,
+        );
+        let part_name = part.name.clone();
+        let event_channel = on_event.clone();
 
-            let _ = on_event.send(MultiPartEvent::FinalCode {
-                code: validation_result.code.clone(),
-                stl_base64: validation_result.stl_base64.clone(),
-            });
+        let user_content = format!(
+            "## User Request\n{}\n\n## Your Task\nGenerate the Build123d code for part '{}': {}",
+            user_request, part.name, part.description
+        );
 
-            if validation_result.code != *code {
-                final_response = final_response.replace(code, &validation_result.code);
+        // ── Prompt debug logging to file ──────────────────────────────────
+        if let Some(ref mut f) = debug_log {
+            let _ = writeln!(f, "┌─── PART {}/{}: '{}' ───────────────────────────────────────", idx + 1, plan.parts.len(), part.name);
+            let _ = writeln!(f, "│ SYSTEM MESSAGE ({} chars):", part_prompt.len());
+            for line in part_prompt.lines() {
+                let _ = writeln!(f, "│   {}", line);
             }
-
-            if total_usage.total() > 0 {
-                emit_usage(on_event, "total", total_usage, provider_id, model_id);
+            let _ = writeln!(f, "│");
+            let _ = writeln!(f, "│ USER MESSAGE ({} chars):", user_content.len());
+            for line in user_content.lines() {
+                let _ = writeln!(f, "│   {}", line);
             }
-
-            let _ = on_event.send(MultiPartEvent::Done {
-                success: validation_result.success,
-                error: validation_result.error.clone(),
-                validated: true,
-            });
-
-            return Ok(PipelineOutcome {
-                response: final_response,
-                final_code: Some(validation_result.code),
-                success: validation_result.success,
-                error: validation_result.error,
-                validation_attempts: Some(validation_result.attempts),
-                static_findings: validation_result.static_findings,
-                post_check_soft_failed: validation_result.post_check_warning.is_some(),
-                post_check_soft_fail_reason: validation_result.post_check_warning,
-                part_acceptance_rate: None,
-                assembly_success_rate: None,
-                partial_preview_shown: validation_result.stl_base64.is_some(),
-                empty_viewport_after_generation: validation_result.stl_base64.is_none(),
-                retry_ladder_stage_reached: validation_result.retry_ladder_stage_reached,
-                failure_signatures: vec![],
-            });
+            let _ = writeln!(f, "└─── END PART {} ─────────────────────────────────────────────", idx + 1);
+            let _ = writeln!(f);
+            let _ = f.flush();
         }
-
-        // No execution context — emit as-is
-        if let Some(ref code) = final_code {
-            let _ = on_event.send(MultiPartEvent::FinalCode {
-                code: code.clone(),
-                stl_base64: None,
-            });
-        }
-
-        if total_usage.total() > 0 {
-            emit_usage(on_event, "total", total_usage, provider_id, model_id);
-        }
-
-        // Guard: report failure if no code was extracted from the AI response
-        let has_code = final_code.is_some();
-        let no_code_error = if has_code {
-            None
-        } else {
-            Some("No code block extracted from AI response".to_string())
-        };
-
-        let _ = on_event.send(MultiPartEvent::Done {
-            success: has_code,
-            error: no_code_error.clone(),
-            validated: false,
-        });
-
-        return Ok(PipelineOutcome {
-            response: final_response,
-            final_code,
-            success: has_code,
-            error: no_code_error,
-            validation_attempts: None,
-            static_findings: vec![],
-            post_check_soft_failed: false,
-            post_check_soft_fail_reason: None,
-            part_acceptance_rate: None,
-            assembly_success_rate: None,
-            partial_preview_shown: false,
-            empty_viewport_after_generation: !has_code,
-            retry_ladder_stage_reached: None,
-            failure_signatures: if has_code {
-                vec![]
-            } else {
-                vec!["no_code_extracted".to_string()]
-            },
-        });
-    }
-
-    // -----------------------------------------------------------------------
-    // Phase 2: Parallel generation
-    // -----------------------------------------------------------------------
-    let _ = on_event.send(MultiPartEvent::PlanStatus {
-        message: format!("Generating {} parts in parallel...", plan.parts.len()),
-    });
-
-    let mut handles = Vec::new();
-
-    // Write prompt debug log to file for inspection
-    let debug_log_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap_or(std::path::Path::new("."))
-        .join("multipart_debug.log");
-    let mut debug_log = std::fs::File::create(&debug_log_path).ok();
-    if let Some(ref mut f) = debug_log {
-        let _ = writeln!(f, "╔══════════════════════════════════════════════════════════════════╗");
-        let _ = writeln!(f, "║  MULTI-PART DISPATCH: {} API calls for {} parts", plan.parts.len(), plan.parts.len());
-        let _ = writeln!(f, "║  Parts: {:?}", plan.parts.iter().map(|p| p.name.as_str()).collect::<Vec<_>>());
-        let _ = writeln!(f, "║  Timestamp: {:?}", std::time::SystemTime::now());
-        let _ = writeln!(f, "╚══════════════════════════════════════════════════════════════════╝");
-        let _ = writeln!(f);
-    }
-    eprintln!("[multipart] Debug log: {}", debug_log_path.display());
-
-    for (idx, part) in plan.parts.iter().enumerate() {
-        let part_provider = create_provider(config)?;
-        let sibling_summary = build_sibling_dimensions_summary(&plan, &part.name);
-        let part_prompt = build_part_prompt(system_prompt, part, plan_text, config, &sibling_summary);
-        let part_name = part.name.clone();
-        let event_channel = on_event.clone();
-
-        let user_content = format!(
-            "## User Request\n{}\n\n## Your Task\nGenerate the Build123d code for part '{}': {}",
-            user_request, part.name, part.description
-        );
-
-        // ── Prompt debug logging to file ──────────────────────────────────
-        if let Some(ref mut f) = debug_log {
-            let _ = writeln!(f, "┌─── PART {}/{}: '{}' ───────────────────────────────────────", idx + 1, plan.parts.len(), part.name);
-            let _ = writeln!(f, "│ SYSTEM MESSAGE ({} chars):", part_prompt.len());
-            for line in part_prompt.lines() {
-                let _ = writeln!(f, "│   {}", line);
-            }
-            let _ = writeln!(f, "│");
-            let _ = writeln!(f, "│ USER MESSAGE ({} chars):", user_content.len());
-            for line in user_content.lines() {
-                let _ = writeln!(f, "│   {}", line);
-            }
-            let _ = writeln!(f, "└─── END PART {} ─────────────────────────────────────────────", idx + 1);
-            let _ = writeln!(f);
-            let _ = f.flush();
-        }
-        // ── End prompt debug logging ──────────────────────────────────────
+        // ── End prompt debug logging ──────────────────────────────────────
 
         let part_messages = vec![
             ChatMessage {
@@ -1964,19 +3459,34 @@ async fn run_generation_pipeline(
         ];
 
         let handle = tokio::spawn(async move {
-            let (tx, mut rx) = mpsc::channel::<StreamDelta>(100);
+            let (tx, rx) = mpsc::channel::<StreamSignal>(100);
+            let mut stream = StreamWatch::for_provider(part_provider.as_ref(), rx);
 
             let stream_handle =
                 tokio::spawn(async move { part_provider.stream(&part_messages, tx).await });
 
             let mut full_response = String::new();
-            while let Some(delta) = rx.recv().await {
+            let mut deltas_seen = 0usize;
+            while let Some(progress) = stream.next().await {
+                let delta = match stream_step_delta(progress, &event_channel) {
+                    Ok(Some(delta)) => delta,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        stream_handle.abort();
+                        return (idx, Err(e));
+                    }
+                };
                 full_response.push_str(&delta.content);
                 let _ = event_channel.send(MultiPartEvent::PartDelta {
                     part_index: idx,
                     part_name: part_name.clone(),
                     delta: delta.content,
                 });
+                deltas_seen += 1;
+                if event_channel.stream_interrupted(deltas_seen) {
+                    stream_handle.abort();
+                    return (idx, Err("Event channel disconnected".to_string()));
+                }
             }
 
             let result = match stream_handle.await {
@@ -2092,7 +3602,11 @@ async fn run_generation_pipeline(
     let mut accepted_parts: Vec<(String, String, [f64; 3])> = Vec::new();
     let mut accepted_retry_stage: Option<u32> = None;
     let mut part_failure_signatures: Vec<String> = Vec::new();
+    let mut part_escalations: Vec<telemetry::ModelEscalation> = Vec::new();
     let mut partial_preview_available = false;
+    let mut part_reports: HashMap<String, executor::PostGeometryValidationReport> =
+        HashMap::new();
+    let assembly_envelope = assembly_envelope_mm(&plan, user_request);
 
     if let Some(ctx) = execution_ctx {
         for (part_idx, part_entry) in part_codes.iter_mut().enumerate() {
@@ -2103,7 +3617,7 @@ async fn run_generation_pipeline(
                 let preview_ctx = executor::ExecutionContext {
                     venv_dir: ctx.venv_dir.clone(),
                     runner_script: ctx.runner_script.clone(),
-                    config: config.clone(),
+                    config: plan.parts[part_idx].acceptance_config(config),
                 };
 
                 let artifact_result = evaluate_part_acceptance(
@@ -2113,8 +3627,11 @@ async fn run_generation_pipeline(
                     &part_request,
                     &name,
                     Some(&semantic_contract),
+                    assembly_envelope,
+                    Some((run_store, &run_id)),
                 )
                 .await;
+                record_part_candidate(run_store, &run_id, part_idx, &code, &artifact_result, config);
 
                 match artifact_result {
                     Ok(artifact) => {
@@ -2128,6 +3645,7 @@ async fn run_generation_pipeline(
                                 Some(accepted_retry_stage.map(|s| s.max(stage)).unwrap_or(stage));
                         }
                         if let Some(ref report) = artifact.post_geometry_report {
+                            part_reports.insert(name.clone(), report.clone());
                             let _ = on_event.send(MultiPartEvent::PostGeometryValidationReport {
                                 report: report.clone(),
                             });
@@ -2144,14 +3662,16 @@ async fn run_generation_pipeline(
                                 let _ = on_event.send(MultiPartEvent::PartStlReady {
                                     part_index: part_idx,
                                     part_name: name.clone(),
-                                    stl_base64,
+                                    stl_base64: preview_stl_base64(stl_base64, &preview_ctx).await,
+                                    color: Some(part_colors::resolve(name, part_colors)),
                                 });
                             }
                         }
                         *part_entry = Some((name.clone(), artifact.code.clone(), pos));
                         accepted_parts.push((name, artifact.code, pos));
                     }
-                    Err(e) => {
+                    Err(rejection) => {
+                        let e = rejection.error;
                         let semantic_findings = if e.contains("semantic validation failed: ") {
                             e.trim_start_matches("semantic validation failed: ")
                                 .split(';')
@@ -2192,6 +3712,7 @@ async fn run_generation_pipeline(
             .map(|(idx, _)| idx)
             .collect();
 
+        on_event.ensure_connected()?;
         if !failed_indices.is_empty() {
             let _ = on_event.send(MultiPartEvent::PlanStatus {
                 message: format!(
@@ -2201,165 +3722,260 @@ async fn run_generation_pipeline(
             });
 
             for &failed_idx in &failed_indices {
+                on_event.ensure_connected()?;
                 let part_spec = &plan.parts[failed_idx];
                 let part_name_for_timeout = part_spec.name.clone();
+                let escalated_config = escalation_config(config);
+                let attempt_configs = std::iter::once((config, false))
+                    .chain(escalated_config.as_ref().map(|c| (c, true)));
 
-                let retry_result = timeout(
-                    Duration::from_secs(PER_PART_RETRY_TIMEOUT_SECS),
-                    async {
-                        let first_error = part_failure_signatures
-                            .get(failed_idx)
-                            .cloned()
-                            .unwrap_or_else(|| "unknown error".to_string());
-
-                        let error_hint = build_error_retry_hint(&first_error);
-                        let sibling_summary = build_sibling_dimensions_summary(&plan, &part_spec.name);
-                        let retry_prompt = format!(
-                            "{}\n\n{}\n\n{}",
-                            system_prompt,
-                            error_hint,
-                            build_part_prompt("", part_spec, plan_text, config, &sibling_summary)
+                for (attempt_config, escalating) in attempt_configs {
+                    if part_codes[failed_idx].is_some() {
+                        break;
+                    }
+                    if escalating {
+                        let message = format!(
+                            "Escalating part '{}' to {} for final attempt",
+                            part_spec.name, attempt_config.model
                         );
+                        let _ = on_event.send(MultiPartEvent::ModelEscalation {
+                            part_name: Some(part_spec.name.clone()),
+                            from_model: config.model.clone(),
+                            to_model: attempt_config.model.clone(),
+                            message: message.clone(),
+                        });
+                        let _ = on_event.send(MultiPartEvent::PlanStatus { message });
+                    }
 
-                        let retry_messages = vec![
-                            ChatMessage {
-                                role: "system".to_string(),
-                                content: retry_prompt,
-                            },
-                            ChatMessage {
-                                role: "user".to_string(),
-                                content: format!(
-                                    "## User Request\n{}\n\n## Your Task\nGenerate the Build123d code for part '{}': {}. \
-                                    Use only robust primitives and boolean operations.",
-                                    user_request, part_spec.name, part_spec.description
-                                ),
-                            },
-                        ];
+                    // Parts accepted so far give the retry their real dimensions.
+                    let accepted_siblings: Vec<(String, String)> = part_codes
+                        .iter()
+                        .flatten()
+                        .map(|(name, code, _)| (name.clone(), code.clone()))
+                        .collect();
+                    let retry_result = timeout(
+                        Duration::from_secs(PER_PART_RETRY_TIMEOUT_SECS),
+                        async {
+                            let first_error = part_failure_signatures
+                                .get(failed_idx)
+                                .cloned()
+                                .unwrap_or_else(|| "unknown error".to_string());
+
+                            let error_hint = build_error_retry_hint(&first_error);
+                            let sibling_summary = build_sibling_dimensions_summary(
+                                &plan.parts,
+                                &part_spec.name,
+                                &accepted_siblings,
+                            );
+                            let reference = part_reference_section(retrieved, part_spec);
+                            let retry_prompt = format!(
+                                "{}\n\n{}\n\n{}",
+                                system_prompt,
+                                error_hint,
+                                build_part_prompt(
+                                    "",
+                                    part_spec,
+                                    plan_text,
+                                    config,
+                                    &sibling_summary,
+                                    &reference,
+                                )
+                            );
 
-                        let retry_provider = match create_provider(config) {
-                            Ok(p) => p,
-                            Err(_) => return,
-                        };
+                            let retry_messages = vec![
+                                ChatMessage {
+                                    role: "system".to_string(),
+                                    content: retry_prompt,
+                                },
+                                ChatMessage {
+                                    role: "user".to_string(),
+                                    content: format!(
+                                        "## User Request\n{}\n\n## Your Task\nGenerate the Build123d code for part '{}': {}. \
+                                        Use only robust primitives and boolean operations.",
+                                        user_request, part_spec.name, part_spec.description
+                                    ),
+                                },
+                            ];
 
-                        let _ = on_event.send(MultiPartEvent::PlanStatus {
-                            message: format!("Retry-generating part '{}'...", part_spec.name),
-                        });
+                            let retry_provider = match create_code_provider(attempt_config) {
+                                Ok(p) => p,
+                                Err(_) => return,
+                            };
 
-                        match retry_provider.complete(&retry_messages, None).await {
-                            Ok((response, usage)) => {
-                                if let Some(ref u) = usage {
-                                    total_usage.add(u);
-                                }
-                                if let Some(code) = extract_code_from_response(&response) {
-                                    let _ = on_event.send(MultiPartEvent::PartCodeExtracted {
-                                        part_index: failed_idx,
-                                        part_name: part_spec.name.clone(),
-                                        code: code.clone(),
-                                    });
+                            let _ = on_event.send(MultiPartEvent::PlanStatus {
+                                message: format!("Retry-generating part '{}'...", part_spec.name),
+                            });
 
-                                    let part_request = part_spec.description.clone();
-                                    let semantic_contract =
-                                        semantic_validate::build_default_contract(&part_spec.name, &part_request);
-                                    let mut retry_config = config.clone();
-                                    retry_config.max_validation_attempts = retry_config.max_validation_attempts.min(2);
-                                    let preview_ctx = executor::ExecutionContext {
-                                        venv_dir: ctx.venv_dir.clone(),
-                                        runner_script: ctx.runner_script.clone(),
-                                        config: retry_config,
-                                    };
-
-                                    match evaluate_part_acceptance(
-                                        &code,
-                                        &preview_ctx,
-                                        system_prompt,
-                                        &part_request,
-                                        &part_spec.name,
-                                        Some(&semantic_contract),
-                                    )
-                                    .await
-                                    {
-                                        Ok(artifact) => {
-                                            let _ = on_event.send(MultiPartEvent::SemanticValidationReport {
-                                                part_name: part_spec.name.clone(),
-                                                passed: true,
-                                                findings: artifact.semantic_findings.clone(),
-                                            });
-                                            if let Some(stage) = artifact.retry_ladder_stage_reached {
-                                                accepted_retry_stage = Some(
-                                                    accepted_retry_stage.map(|s| s.max(stage)).unwrap_or(stage),
-                                                );
-                                            }
-                                            if let Some(ref report) = artifact.post_geometry_report {
-                                                let _ = on_event.send(
-                                                    MultiPartEvent::PostGeometryValidationReport {
-                                                        report: report.clone(),
-                                                    },
-                                                );
-                                            }
-                                            {
-                                                // Always emit individual part STLs for assembly import
-                                                if let Some(stl_base64) = artifact.stl_base64.clone() {
-                                                    partial_preview_available = true;
-                                                    let _ = on_event.send(MultiPartEvent::PartStlReady {
-                                                        part_index: failed_idx,
-                                                        part_name: part_spec.name.clone(),
-                                                        stl_base64,
-                                                    });
+                            match retry_provider.complete(&retry_messages, None).await {
+                                Ok((response, usage)) => {
+                                    if let Some(ref u) = usage {
+                                        total_usage.add(u);
+                                        if escalating {
+                                            emit_usage(
+                                                on_event,
+                                                &format!("part_escalation:{}", part_spec.name),
+                                                u,
+                                                provider_id,
+                                                &attempt_config.model,
+                                            );
+                                        }
+                                    }
+                                    if let Some(code) = extract_code_from_response(&response) {
+                                        let _ = on_event.send(MultiPartEvent::PartCodeExtracted {
+                                            part_index: failed_idx,
+                                            part_name: part_spec.name.clone(),
+                                            code: code.clone(),
+                                        });
+
+                                        let part_request = part_spec.description.clone();
+                                        let semantic_contract =
+                                            semantic_validate::build_default_contract(&part_spec.name, &part_request);
+                                        let mut retry_config = part_spec.acceptance_config(attempt_config);
+                                        retry_config.max_validation_attempts = retry_config.max_validation_attempts.min(2);
+                                        let preview_ctx = executor::ExecutionContext {
+                                            venv_dir: ctx.venv_dir.clone(),
+                                            runner_script: ctx.runner_script.clone(),
+                                            config: retry_config,
+                                        };
+
+                                        let artifact_result = evaluate_part_acceptance(
+                                            &code,
+                                            &preview_ctx,
+                                            system_prompt,
+                                            &part_request,
+                                            &part_spec.name,
+                                            Some(&semantic_contract),
+                                            assembly_envelope,
+                                            Some((run_store, &run_id)),
+                                        )
+                                        .await;
+                                        record_part_candidate(
+                                            run_store,
+                                            &run_id,
+                                            failed_idx,
+                                            &code,
+                                            &artifact_result,
+                                            attempt_config,
+                                        );
+                                        match artifact_result {
+                                            Ok(artifact) => {
+                                                let _ = on_event.send(MultiPartEvent::SemanticValidationReport {
+                                                    part_name: part_spec.name.clone(),
+                                                    passed: true,
+                                                    findings: artifact.semantic_findings.clone(),
+                                                });
+                                                if let Some(stage) = artifact.retry_ladder_stage_reached {
+                                                    accepted_retry_stage = Some(
+                                                        accepted_retry_stage.map(|s| s.max(stage)).unwrap_or(stage),
+                                                    );
+                                                }
+                                                if let Some(ref report) = artifact.post_geometry_report {
+                                                    part_reports.insert(
+                                                        part_spec.name.clone(),
+                                                        report.clone(),
+                                                    );
+                                                    let _ = on_event.send(
+                                                        MultiPartEvent::PostGeometryValidationReport {
+                                                            report: report.clone(),
+                                                        },
+                                                    );
                                                 }
+                                                {
+                                                    // Always emit individual part STLs for assembly import
+                                                    if let Some(stl_base64) = artifact.stl_base64.clone() {
+                                                        partial_preview_available = true;
+                                                        let _ = on_event.send(MultiPartEvent::PartStlReady {
+                                                            part_index: failed_idx,
+                                                            part_name: part_spec.name.clone(),
+                                                            stl_base64: preview_stl_base64(
+                                                                stl_base64,
+                                                                &preview_ctx,
+                                                            )
+                                                            .await,
+                                                            color: Some(part_colors::resolve(
+                                                                &part_spec.name,
+                                                                part_colors,
+                                                            )),
+                                                        });
+                                                    }
+                                                }
+                                                let position = part_spec.position;
+                                                part_codes[failed_idx] = Some((
+                                                    part_spec.name.clone(),
+                                                    artifact.code.clone(),
+                                                    position,
+                                                ));
+                                                accepted_parts.push((
+                                                    part_spec.name.clone(),
+                                                    artifact.code,
+                                                    position,
+                                                ));
+                                                any_success = true;
+                                                let _ = on_event.send(MultiPartEvent::PartComplete {
+                                                    part_index: failed_idx,
+                                                    part_name: part_spec.name.clone(),
+                                                    success: true,
+                                                    error: None,
+                                                });
+                                            }
+                                            Err(rejection) => {
+                                                let _ = on_event.send(MultiPartEvent::PlanStatus {
+                                                    message: format!(
+                                                        "Retry for '{}' also failed acceptance: {}",
+                                                        part_spec.name, rejection.error
+                                                    ),
+                                                });
                                             }
-                                            let position = part_spec.position;
-                                            part_codes[failed_idx] = Some((
-                                                part_spec.name.clone(),
-                                                artifact.code.clone(),
-                                                position,
-                                            ));
-                                            accepted_parts.push((
-                                                part_spec.name.clone(),
-                                                artifact.code,
-                                                position,
-                                            ));
-                                            any_success = true;
-                                            let _ = on_event.send(MultiPartEvent::PartComplete {
-                                                part_index: failed_idx,
-                                                part_name: part_spec.name.clone(),
-                                                success: true,
-                                                error: None,
-                                            });
-                                        }
-                                        Err(e) => {
-                                            let _ = on_event.send(MultiPartEvent::PlanStatus {
-                                                message: format!(
-                                                    "Retry for '{}' also failed acceptance: {}",
-                                                    part_spec.name, e
-                                                ),
-                                            });
                                         }
                                     }
                                 }
-                            }
-                            Err(e) => {
-                                let _ = on_event.send(MultiPartEvent::PlanStatus {
-                                    message: format!(
-                                        "Retry generation for '{}' failed: {}",
-                                        part_spec.name, e
-                                    ),
-                                });
+                                Err(e) => {
+                                    let _ = on_event.send(MultiPartEvent::PlanStatus {
+                                        message: format!(
+                                            "Retry generation for '{}' failed: {}",
+                                            part_spec.name, e
+                                        ),
+                                    });
+                                }
                             }
                         }
+                    ).await;
+
+                    if retry_result.is_err() {
+                        let _ = on_event.send(MultiPartEvent::PlanStatus {
+                            message: format!(
+                                "Retry for '{}' timed out after {}s, skipping to next part.",
+                                part_name_for_timeout, PER_PART_RETRY_TIMEOUT_SECS
+                            ),
+                        });
                     }
-                ).await;
 
-                if retry_result.is_err() {
-                    let _ = on_event.send(MultiPartEvent::PlanStatus {
-                        message: format!(
-                            "Retry for '{}' timed out after {}s, skipping to next part.",
-                            part_name_for_timeout, PER_PART_RETRY_TIMEOUT_SECS
-                        ),
-                    });
+                    if escalating {
+                        part_escalations.push(telemetry::ModelEscalation {
+                            part: part_spec.name.clone(),
+                            from: config.model.clone(),
+                            to: attempt_config.model.clone(),
+                            succeeded: part_codes[failed_idx].is_some(),
+                        });
+                    }
                 }
             }
         }
     } else {
+        // Unvalidated, but kept so a parked run can still be approved.
+        for (part_idx, part_entry) in part_codes.iter().enumerate() {
+            if let Some((_, code, _)) = part_entry {
+                let candidate = run_state::PartCandidate {
+                    code: code.clone(),
+                    stl_base64: None,
+                    accepted: true,
+                    findings: vec![],
+                    created_at_ms: telemetry::now_ms(),
+                    post_geometry_report: None,
+                };
+                store_part_candidate(run_store, &run_id, part_idx, candidate, config);
+            }
+        }
         accepted_parts = part_codes.into_iter().flatten().collect();
     }
 
@@ -2385,6 +4001,7 @@ async fn run_generation_pipeline(
             response: String::new(),
             final_code: None,
             success: false,
+            validated: true,
             error: Some("All generated parts were rejected by per-part acceptance".to_string()),
             validation_attempts: None,
             static_findings: vec![],
@@ -2395,96 +4012,124 @@ async fn run_generation_pipeline(
             partial_preview_shown: partial_preview_available,
             empty_viewport_after_generation: !partial_preview_available,
             retry_ladder_stage_reached: accepted_retry_stage,
+            model_escalations: part_escalations,
+            repair_example_uses: vec![],
+            geometry_hash: None,
             failure_signatures: part_failure_signatures,
+            awaiting_assembly_approval: false,
+            failure_localization: None,
+            injected_mechanism_ids: injected_mechanism_ids.clone(),
         });
     }
 
+    if check_part_clearances(&plan, &accepted_parts, config, on_event)
+        && config.quality_gates_strict
+    {
+        part_failure_signatures.push("clearance_out_of_range".to_string());
+    }
+
     // -----------------------------------------------------------------------
     // Phase 3: Assemble
     // -----------------------------------------------------------------------
+    persist_resumable(run_store, &run_id, config);
+    if config.pause_before_assembly {
+        let (accepted, rejected) = assembly_part_refs(&plan, &accepted_parts);
+        run_store
+            .lock()
+            .map_err(|e| AppError::ConfigError(format!("Failed to lock run store: {}", e)))?
+            .park(&run_id, telemetry::now_ms().saturating_sub(pipeline_started_ms))?;
+        let _ = on_event.send(MultiPartEvent::AwaitingAssemblyApproval {
+            accepted_parts: accepted.clone(),
+            rejected_parts: rejected,
+        });
+        return Ok(PipelineOutcome {
+            response: format!(
+                "{}/{} parts accepted. Review them and approve assembly to continue.",
+                accepted.len(),
+                plan.parts.len()
+            ),
+            final_code: None,
+            success: false,
+            validated: false,
+            error: None,
+            validation_attempts: None,
+            static_findings: vec![],
+            post_check_soft_failed: false,
+            post_check_soft_fail_reason: None,
+            part_acceptance_rate: Some(accepted.len() as f32 / plan.parts.len() as f32),
+            assembly_success_rate: None,
+            partial_preview_shown: partial_preview_available,
+            empty_viewport_after_generation: !partial_preview_available,
+            retry_ladder_stage_reached: accepted_retry_stage,
+            model_escalations: part_escalations,
+            repair_example_uses: vec![],
+            geometry_hash: None,
+            failure_signatures: part_failure_signatures,
+            awaiting_assembly_approval: true,
+            failure_localization: None,
+            injected_mechanism_ids: injected_mechanism_ids.clone(),
+        });
+    }
     let _ = on_event.send(MultiPartEvent::AssemblyStatus {
         message: "Assembling parts...".to_string(),
     });
+    on_event.ensure_connected()?;
 
-    let successful_parts = accepted_parts;
+    let successful_parts =
+        layout_part_positions(accepted_parts, &plan, &part_reports, config, on_event);
     let strict_multipart_required =
         config.quality_gates_strict && request_requires_multipart_contract(user_request, plan_text);
     let required_parts_met =
         !strict_multipart_required || successful_parts.len() == plan.parts.len();
 
-    match assemble_parts(&successful_parts) {
+    let part_kinematics = classify_accepted_parts(&plan, &successful_parts, on_event);
+
+    match assemble_parts(
+        &successful_parts,
+        &part_kinematics,
+        &plan.assembly_ops,
+        part_colors,
+    ) {
         Ok(code) => {
             // Emit assembled code early — if the pipeline times out during
             // review/validation, the frontend still has usable code.
             let _ = on_event.send(MultiPartEvent::FinalCode {
                 code: code.clone(),
                 stl_base64: None,
+                geometry_hash: None,
             });
 
             let final_code = if config.enable_code_review {
-                let _ = on_event.send(MultiPartEvent::ReviewStatus {
-                    message: "Reviewing assembled code...".to_string(),
-                });
-                let review_provider = create_provider(config)?;
-                match review::review_code(
-                    review_provider,
+                review_assembly(
+                    code,
                     user_request,
-                    &code,
-                    Some(plan_text),
-                    &config.reviewer_mode,
+                    plan_text,
+                    &successful_parts,
+                    &plan.assembly_ops,
+                    config,
+                    on_event,
+                    total_usage,
                 )
-                .await
-                {
-                    Ok((result, review_usage)) => {
-                        if let Some(ref u) = review_usage {
-                            total_usage.add(u);
-                            emit_usage(on_event, "review", u, provider_id, model_id);
-                        }
-                        let _ = on_event.send(MultiPartEvent::ReviewComplete {
-                            was_modified: result.was_modified,
-                            explanation: result.explanation.clone(),
-                        });
-                        if result.was_modified {
-                            let review_issues =
-                                assembly_contract_issues(&result.code, &successful_parts);
-                            if review_issues.is_empty() {
-                                result.code
-                            } else {
-                                let _ = on_event.send(MultiPartEvent::PlanStatus {
-                                    message: format!(
-                                        "Reviewer output dropped multipart structure ({}). Keeping assembled code.",
-                                        review_issues.join(", ")
-                                    ),
-                                });
-                                code
-                            }
-                        } else {
-                            code
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Code review failed: {}", e);
-                        code
-                    }
-                }
+                .await?
             } else {
                 code
             };
 
             if let Some(ctx) = execution_ctx {
-                let on_validation_event =
-                    |evt: executor::ValidationEvent| forward_validation_event(on_event, evt);
-
                 let assembly_bbox_hint =
                     build_assembly_bbox_hint(&plan, user_request, &config.semantic_bbox_mode);
-                let validation_result = executor::validate_and_retry(
+                let (validation_result, failure_localization) = validate_assembled_code(
                     final_code.clone(),
+                    &plan.parts,
                     ctx,
                     system_prompt,
                     assembly_bbox_hint.as_deref(),
-                    &on_validation_event,
+                    on_event,
+                    run_store,
                 )
                 .await?;
+                let mut model_escalations = part_escalations;
+                model_escalations.extend(validation_escalations(config, "assembly", &validation_result));
 
                 if validation_result.retry_usage.total() > 0 {
                     total_usage.add(&validation_result.retry_usage);
@@ -2497,13 +4142,38 @@ async fn run_generation_pipeline(
                     );
                 }
 
+                let geometry_hash =
+                    executor::geometry_hash_base64(validation_result.stl_base64.as_deref());
                 let _ = on_event.send(MultiPartEvent::FinalCode {
                     code: validation_result.code.clone(),
                     stl_base64: validation_result.stl_base64.clone(),
+                    geometry_hash: geometry_hash.clone(),
                 });
 
-                let contract_issues =
-                    assembly_contract_issues(&validation_result.code, &successful_parts);
+                if validation_result.success {
+                    if let Some(report) = assembly_mass_properties(
+                        &successful_parts,
+                        &plan,
+                        &part_reports,
+                        part_materials,
+                        user_request,
+                        config,
+                    ) {
+                        let _ = on_event.send(MultiPartEvent::MassPropertiesReport { report });
+                    }
+                    emit_print_estimate(
+                        on_event,
+                        validation_result.post_geometry_report.as_ref(),
+                        user_request,
+                        config,
+                    );
+                }
+
+                let contract_issues = assembly_contract_issues(
+                    &validation_result.code,
+                    &successful_parts,
+                    &plan.assembly_ops,
+                );
                 if config.quality_gates_strict && !contract_issues.is_empty() {
                     let msg = format!(
                         "Validation retry produced code that breaks multipart assembly contract: {}",
@@ -2521,6 +4191,7 @@ async fn run_generation_pipeline(
                         response: validation_result.code.clone(),
                         final_code: Some(validation_result.code),
                         success: false,
+                        validated: true,
                         error: Some(msg),
                         validation_attempts: Some(validation_result.attempts),
                         static_findings: validation_result.static_findings,
@@ -2535,7 +4206,13 @@ async fn run_generation_pipeline(
                         retry_ladder_stage_reached: validation_result
                             .retry_ladder_stage_reached
                             .or(accepted_retry_stage),
+                        model_escalations,
+                        repair_example_uses: validation_result.repair_example_uses.clone(),
+                        geometry_hash: geometry_hash.clone(),
                         failure_signatures,
+                        awaiting_assembly_approval: false,
+                        failure_localization,
+                        injected_mechanism_ids: injected_mechanism_ids.clone(),
                     });
                 } else if !contract_issues.is_empty() {
                     let _ = on_event.send(MultiPartEvent::ReviewStatus {
@@ -2565,6 +4242,9 @@ async fn run_generation_pipeline(
                     emit_usage(on_event, "total", total_usage, provider_id, model_id);
                 }
 
+                if final_success {
+                    clear_resumable(&run_id, config);
+                }
                 let _ = on_event.send(MultiPartEvent::Done {
                     success: final_success,
                     error: done_error.clone(),
@@ -2575,6 +4255,7 @@ async fn run_generation_pipeline(
                     response: validation_result.code.clone(),
                     final_code: Some(validation_result.code),
                     success: final_success,
+                    validated: true,
                     error: done_error,
                     validation_attempts: Some(validation_result.attempts),
                     static_findings: validation_result.static_findings,
@@ -2590,7 +4271,13 @@ async fn run_generation_pipeline(
                     retry_ladder_stage_reached: validation_result
                         .retry_ladder_stage_reached
                         .or(accepted_retry_stage),
+                    model_escalations,
+                    repair_example_uses: validation_result.repair_example_uses.clone(),
+                    geometry_hash,
                     failure_signatures: part_failure_signatures,
+                    awaiting_assembly_approval: false,
+                    failure_localization,
+                    injected_mechanism_ids: injected_mechanism_ids.clone(),
                 });
             }
 
@@ -2602,6 +4289,7 @@ async fn run_generation_pipeline(
             let _ = on_event.send(MultiPartEvent::FinalCode {
                 code: final_code.clone(),
                 stl_base64: None,
+                geometry_hash: None,
             });
             let done_error = if required_parts_met {
                 None
@@ -2613,6 +4301,9 @@ async fn run_generation_pipeline(
                     plan.parts.len()
                 ))
             };
+            if done_error.is_none() {
+                clear_resumable(&run_id, config);
+            }
             let _ = on_event.send(MultiPartEvent::Done {
                 success: done_error.is_none(),
                 error: done_error.clone(),
@@ -2622,6 +4313,7 @@ async fn run_generation_pipeline(
                 response: final_code.clone(),
                 final_code: Some(final_code),
                 success: done_error.is_none(),
+                validated: false,
                 error: done_error,
                 validation_attempts: None,
                 static_findings: vec![],
@@ -2632,7 +4324,13 @@ async fn run_generation_pipeline(
                 partial_preview_shown: partial_preview_available,
                 empty_viewport_after_generation: !partial_preview_available,
                 retry_ladder_stage_reached: accepted_retry_stage,
+                model_escalations: part_escalations,
+                repair_example_uses: vec![],
+                geometry_hash: None,
                 failure_signatures: part_failure_signatures,
+                awaiting_assembly_approval: false,
+                failure_localization: None,
+                injected_mechanism_ids: injected_mechanism_ids.clone(),
             })
         }
         Err(e) => {
@@ -2655,954 +4353,3922 @@ pub async fn generate_parallel(
     message: String,
     history: Vec<ChatMessage>,
     existing_code: Option<String>,
-    on_event: Channel<MultiPartEvent>,
+    on_event: Channel<EventEnvelope>,
     state: State<'_, AppState>,
+    preset: Option<String>,
+    target_part: Option<String>,
 ) -> Result<String, AppError> {
+    // A double-click or impatient retry joins the identical run in flight.
     let config = state.config.lock().unwrap().clone();
-    let cq_version = state.build123d_version.lock().unwrap().clone();
-    let user_request = message.clone();
-    let session_ctx = state.session_memory.lock().unwrap().build_context_section();
-    let (system_prompt, retrieval_result) = build_system_prompt_with_retrieval(
-        &config,
-        cq_version.as_deref(),
+    let fingerprint = run_events::run_fingerprint(
         &message,
-        session_ctx,
-        &on_event,
-        true, // compact prompt for multi-part
-    )
-    .await;
-
-    let provider_id = config.ai_provider.clone();
-    let model_id = config.model.clone();
-    let mut total_usage = TokenUsage::default();
-
-    // Resolve execution context for backend validation (None if Python not set up)
-    let execution_ctx = {
-        let venv_path = state.venv_path.lock().unwrap().clone();
-        match venv_path {
-            Some(venv_dir) => match super::find_python_script("runner.py") {
-                Ok(runner_script) => Some(executor::ExecutionContext {
-                    venv_dir,
-                    runner_script,
-                    config: config.clone(),
-                }),
-                Err(_) => None,
-            },
-            None => None,
+        None,
+        target_part.as_deref(),
+        &crate::pipeline_presets::resolve_run_config(&config, preset.as_deref())?,
+        existing_code.as_deref(),
+    );
+    let claim = state
+        .run_events
+        .lock()
+        .map_err(|_| AppError::ConfigError("Run event store lock poisoned".into()))?
+        .claim_run(&fingerprint, on_event, config.merge_duplicate_runs);
+    let (on_event, duplicate) = match claim {
+        RunClaim::Joined { run_id, outcome } => {
+            return run_events::merged_outcome(&run_id, outcome).await;
         }
+        RunClaim::Started { sink, duplicate } => (sink, duplicate),
     };
-
-    // -----------------------------------------------------------------------
-    // Modification branch: detect and handle code modifications (early return)
-    // -----------------------------------------------------------------------
-    let modification_intent =
-        modify::detect_modification_intent(&message, existing_code.as_deref());
-
-    if modification_intent.is_modification {
-        let intent_summary = modification_intent
-            .intent_summary
-            .unwrap_or_else(|| "modifying code".to_string());
-
-        let _ = on_event.send(MultiPartEvent::ModificationDetected {
-            intent_summary: intent_summary.clone(),
-        });
-
+    if duplicate {
         let _ = on_event.send(MultiPartEvent::PlanStatus {
-            message: "Modifying existing code...".to_string(),
+            message: "An identical request is already running; starting a separate run \
+                      because duplicate merging is off."
+                .to_string(),
         });
+    }
 
-        let old_code = existing_code.as_deref().unwrap_or("");
-
-        // Build modification-specific system prompt and user message.
-        // For fine-tuned providers the base prompt is already minimal — don't
-        // append the lengthy MODIFICATION_INSTRUCTIONS block.
-        let mod_system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
-            system_prompt.clone()
-        } else {
-            format!("{}\n{}", system_prompt, modify::MODIFICATION_INSTRUCTIONS)
-        };
-        let modification_message = modify::build_modification_message(old_code, &message);
+    let guard = InFlightGuard::new(&state.run_events, fingerprint, &on_event);
+    let result = run_parallel_generation(
+        message,
+        history,
+        existing_code,
+        on_event,
+        state.clone(),
+        preset,
+        target_part,
+    )
+    .await
+    .map(|result| result.response);
+    guard.finish(&result);
+    result
+}
 
-        let provider = create_provider(&config)?;
-        let mut messages_list = vec![ChatMessage {
-            role: "system".to_string(),
-            content: mod_system_prompt,
-        }];
-        messages_list.extend(history);
-        messages_list.push(ChatMessage {
-            role: "user".to_string(),
-            content: modification_message,
-        });
+/// Same as `generate_parallel`, but returns a structured `GenerationResult`.
+/// The event stream is still emitted for live UI.
+#[tauri::command]
+pub async fn generate_parallel_result(
+    message: String,
+    history: Vec<ChatMessage>,
+    existing_code: Option<String>,
+    on_event: Channel<EventEnvelope>,
+    state: State<'_, AppState>,
+    preset: Option<String>,
+    target_part: Option<String>,
+) -> Result<GenerationResult, AppError> {
+    let on_event = EventSink::register(&state.run_events, on_event);
+    run_parallel_generation(
+        message,
+        history,
+        existing_code,
+        on_event,
+        state,
+        preset,
+        target_part,
+    )
+    .await
+}
 
-        // Stream the AI response (reuse SingleDelta/SingleDone events)
-        let (tx, mut rx) = mpsc::channel::<StreamDelta>(100);
-        let provider_handle =
-            tokio::spawn(async move { provider.stream(&messages_list, tx).await });
+/// Totals of a `generate_batch` run.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BatchResult {
+    pub succeeded: usize,
+    pub failed: usize,
+}
 
-        let mut full_response = String::new();
-        while let Some(delta) = rx.recv().await {
-            full_response.push_str(&delta.content);
-            let _ = on_event.send(MultiPartEvent::SingleDelta {
-                delta: delta.content,
-                done: delta.done,
-            });
-        }
+/// Run independent prompts one after another through the full pipeline
+/// (design plan, then generation). Items run sequentially to respect provider
+/// rate limits; a failed item is reported and the batch moves on. Each item
+/// gets its own run id, so its events and artifacts stay apart.
+#[tauri::command]
+pub async fn generate_batch(
+    prompts: Vec<String>,
+    on_event: Channel<EventEnvelope>,
+    state: State<'_, AppState>,
+) -> Result<BatchResult, AppError> {
+    let on_event = EventSink::register(&state.run_events, on_event);
+    run_batch(prompts, &on_event, |prompt| {
+        run_parallel_generation(
+            prompt,
+            vec![],
+            None,
+            on_event.child(&state.run_events),
+            state.clone(),
+            None,
+            None,
+        )
+    })
+    .await
+}
 
-        match provider_handle.await {
-            Ok(Ok(stream_usage)) => {
-                if let Some(ref u) = stream_usage {
-                    total_usage.add(u);
-                    emit_usage(&on_event, "generate", u, &provider_id, &model_id);
-                }
-            }
-            Ok(Err(e)) => return Err(e),
-            Err(e) => {
-                return Err(AppError::AiProviderError(format!(
-                    "Provider task panicked: {}",
-                    e
-                )));
+/// Drive `run_item` over `prompts` in order, emitting per-item and final batch
+/// events. Only a disconnected channel stops the batch early.
+async fn run_batch<F, Fut>(
+    prompts: Vec<String>,
+    on_event: &EventSink,
+    mut run_item: F,
+) -> Result<BatchResult, AppError>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = Result<GenerationResult, AppError>>,
+{
+    let mut totals = BatchResult {
+        succeeded: 0,
+        failed: 0,
+    };
+    for (index, prompt) in prompts.into_iter().enumerate() {
+        let _ = on_event.send(MultiPartEvent::BatchItemStarted {
+            index,
+            prompt: prompt.clone(),
+        });
+        let (success, final_code, error) = match run_item(prompt).await {
+            Ok(result) => (result.success, result.final_code, result.error),
+            Err(AppError::ChannelDisconnected(run_id)) => {
+                return Err(AppError::ChannelDisconnected(run_id));
             }
+            Err(e) => (false, None, Some(e.to_string())),
+        };
+        if success {
+            totals.succeeded += 1;
+        } else {
+            totals.failed += 1;
         }
-
-        let _ = on_event.send(MultiPartEvent::SingleDone {
-            full_response: full_response.clone(),
+        let _ = on_event.send(MultiPartEvent::BatchItemComplete {
+            index,
+            success,
+            final_code,
+            error,
         });
+    }
+    let _ = on_event.send(MultiPartEvent::BatchComplete {
+        succeeded: totals.succeeded,
+        failed: totals.failed,
+    });
+    Ok(totals)
+}
 
-        // Extract code from the response
-        let mut final_code = extract_code_from_response(&full_response);
-        let mut final_response = full_response.clone();
-
-        // Optional: code review
-        if config.enable_code_review {
-            if let Some(ref code) = final_code {
-                let _ = on_event.send(MultiPartEvent::ReviewStatus {
-                    message: "Reviewing modified code...".to_string(),
-                });
-
-                let review_provider = create_provider(&config)?;
-                match review::review_code(
-                    review_provider,
-                    &user_request,
-                    code,
-                    None,
-                    &config.reviewer_mode,
-                )
-                .await
-                {
-                    Ok((result, review_usage)) => {
-                        if let Some(ref u) = review_usage {
-                            total_usage.add(u);
-                            emit_usage(&on_event, "review", u, &provider_id, &model_id);
-                        }
-                        let _ = on_event.send(MultiPartEvent::ReviewComplete {
-                            was_modified: result.was_modified,
-                            explanation: result.explanation.clone(),
-                        });
-                        if result.was_modified {
-                            final_response = full_response.replace(code, &result.code);
-                            final_code = Some(result.code);
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Code review failed (modification): {}", e);
-                    }
-                }
-            }
+/// Re-check the installed Build123d before a run and tell the user when the
+/// guidance is being refreshed for a different version.
+async fn announce_build123d_version_change(state: &AppState, on_event: &EventSink) {
+    if let Some(change) = crate::commands::cad::refresh_build123d_version(state).await {
+        if change.is_upgrade_or_downgrade() {
+            let _ = on_event.send(MultiPartEvent::PlanStatus {
+                message: change.describe(),
+            });
         }
+    }
+}
 
-        // Optional: backend validation
-        if let (Some(code), Some(ref ctx)) = (&final_code, &execution_ctx) {
-            let on_validation_event =
-                |evt: executor::ValidationEvent| forward_validation_event(&on_event, evt);
-
-            let validation_result = executor::validate_and_retry(
-                code.clone(),
-                ctx,
-                &system_prompt,
-                Some(&user_request),
-                &on_validation_event,
-            )
-            .await?;
-
-            if validation_result.retry_usage.total() > 0 {
-                total_usage.add(&validation_result.retry_usage);
-                emit_usage(
-                    &on_event,
-                    "validation",
-                    &validation_result.retry_usage,
-                    &provider_id,
-                    &model_id,
-                );
-            }
+/// `CodeDiff` between the editor code and the modified code, if they differ.
+pub(crate) fn emit_code_diff(on_event: &EventSink, old_code: &str, new_code: &str) {
+    let diff = modify::compute_diff(old_code, new_code);
+    if modify::diff_has_changes(&diff) {
+        let additions = diff.iter().filter(|l| l.tag == "insert").count();
+        let deletions = diff.iter().filter(|l| l.tag == "delete").count();
+        let _ = on_event.send(MultiPartEvent::CodeDiff {
+            hunks: modify::compute_hunks(&diff),
+            diff_lines: diff,
+            old_line_count: old_code.lines().count(),
+            new_line_count: new_code.lines().count(),
+            additions,
+            deletions,
+        });
+    }
+}
 
-            let new_code = &validation_result.code;
+/// Modify one part of an assembled script: rewrite only `scope`'s section,
+/// accept it as a part, splice it back and re-validate the assembly. Fails
+/// with `unscoped_modification_detected` if any other section changed.
+#[allow(clippy::too_many_arguments)]
+async fn run_scoped_modification(
+    scope: &modify::PartScope,
+    old_code: &str,
+    user_request: &str,
+    history: Vec<ChatMessage>,
+    config: &crate::config::AppConfig,
+    system_prompt: &str,
+    execution_ctx: Option<&executor::ExecutionContext>,
+    on_event: &EventSink,
+    total_usage: &mut TokenUsage,
+    part_colors: &HashMap<String, PartColor>,
+    run_store: &Mutex<run_state::RunStore>,
+) -> Result<PipelineOutcome, AppError> {
+    let provider_id = config.ai_provider.as_str();
+    let model_id = config.model.as_str();
+    let _ = on_event.send(MultiPartEvent::PlanStatus {
+        message: format!(
+            "Modifying part '{}' only; other parts are kept as-is...",
+            scope.name
+        ),
+    });
 
-            // Compute diff between old code and final new code
-            let diff = modify::compute_diff(old_code, new_code);
-            if modify::diff_has_changes(&diff) {
-                let additions = diff.iter().filter(|l| l.tag == "insert").count();
-                let deletions = diff.iter().filter(|l| l.tag == "delete").count();
-                let _ = on_event.send(MultiPartEvent::CodeDiff {
-                    diff_lines: diff,
-                    old_line_count: old_code.lines().count(),
-                    new_line_count: new_code.lines().count(),
-                    additions,
-                    deletions,
-                });
-            }
+    let mod_system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
+        system_prompt.to_string()
+    } else {
+        format!("{}\n{}", system_prompt, modify::SCOPED_MODIFICATION_INSTRUCTIONS)
+    };
+    let mut messages_list = vec![ChatMessage {
+        role: "system".to_string(),
+        content: mod_system_prompt,
+    }];
+    messages_list.extend(history);
+    messages_list.push(ChatMessage {
+        role: "user".to_string(),
+        content: modify::build_scoped_modification_message(scope, user_request),
+    });
 
-            let _ = on_event.send(MultiPartEvent::FinalCode {
-                code: validation_result.code.clone(),
-                stl_base64: validation_result.stl_base64.clone(),
-            });
+    let provider = create_code_provider(config)?;
+    let (tx, rx) = mpsc::channel::<StreamSignal>(100);
+    let mut stream = StreamWatch::for_provider(provider.as_ref(), rx);
+    let provider_handle = tokio::spawn(async move { provider.stream(&messages_list, tx).await });
 
-            if total_usage.total() > 0 {
-                emit_usage(&on_event, "total", &total_usage, &provider_id, &model_id);
+    let mut full_response = String::new();
+    let mut deltas_seen = 0usize;
+    while let Some(progress) = stream.next().await {
+        let delta = match stream_step_delta(progress, on_event) {
+            Ok(Some(delta)) => delta,
+            Ok(None) => continue,
+            Err(e) => {
+                provider_handle.abort();
+                return Err(AppError::AiProviderError(e));
             }
-
-            let _ = on_event.send(MultiPartEvent::Done {
-                success: validation_result.success,
-                error: validation_result.error.clone(),
-                validated: true,
-            });
-
-            let outcome = PipelineOutcome {
-                response: final_response.clone(),
-                final_code: Some(validation_result.code.clone()),
-                success: validation_result.success,
-                error: validation_result.error.clone(),
-                validation_attempts: Some(validation_result.attempts),
-                static_findings: validation_result.static_findings.clone(),
-                post_check_soft_failed: validation_result.post_check_warning.is_some(),
-                post_check_soft_fail_reason: validation_result.post_check_warning.clone(),
-                part_acceptance_rate: None,
-                assembly_success_rate: None,
-                partial_preview_shown: validation_result.stl_base64.is_some(),
-                empty_viewport_after_generation: validation_result.stl_base64.is_none(),
-                retry_ladder_stage_reached: validation_result.retry_ladder_stage_reached,
-                failure_signatures: vec![],
-            };
-
-            record_generation_attempt(
-                &state,
-                &user_request,
-                Some(&validation_result.code),
-                validation_result.success,
-                None,
-                None,
-                validation_result.error.clone(),
-            );
-            record_generation_trace(&config, &user_request, &retrieval_result, None, &outcome);
-
-            return Ok(final_response);
+        };
+        full_response.push_str(&delta.content);
+        let _ = on_event.send(MultiPartEvent::PartDelta {
+            part_index: scope.index,
+            part_name: scope.name.clone(),
+            delta: delta.content,
+        });
+        deltas_seen += 1;
+        if on_event.stream_interrupted(deltas_seen) {
+            provider_handle.abort();
+            return Err(AppError::ChannelDisconnected(on_event.run_id().to_string()));
         }
+    }
 
-        // No execution context — emit diff and code as-is
-        if let Some(ref code) = final_code {
-            let diff = modify::compute_diff(old_code, code);
-            if modify::diff_has_changes(&diff) {
-                let additions = diff.iter().filter(|l| l.tag == "insert").count();
-                let deletions = diff.iter().filter(|l| l.tag == "delete").count();
-                let _ = on_event.send(MultiPartEvent::CodeDiff {
-                    diff_lines: diff,
-                    old_line_count: old_code.lines().count(),
-                    new_line_count: code.lines().count(),
-                    additions,
-                    deletions,
-                });
+    match provider_handle.await {
+        Ok(Ok(stream_usage)) => {
+            if let Some(ref u) = stream_usage {
+                total_usage.add(u);
+                emit_usage(on_event, "generate", u, provider_id, model_id);
             }
-
-            let _ = on_event.send(MultiPartEvent::FinalCode {
-                code: code.clone(),
-                stl_base64: None,
-            });
         }
-
-        if total_usage.total() > 0 {
-            emit_usage(&on_event, "total", &total_usage, &provider_id, &model_id);
+        Ok(Err(e)) => return Err(e),
+        Err(e) => {
+            return Err(AppError::AiProviderError(format!(
+                "Provider task panicked: {}",
+                e
+            )));
         }
+    }
 
-        // Guard: report failure if no code was extracted from the modification response
-        let has_code = final_code.is_some();
-        let no_code_error = if has_code {
-            None
-        } else {
-            Some("No code block extracted from modification response".to_string())
-        };
-
+    let failed = |error: String, failure_signature: Option<&str>| {
         let _ = on_event.send(MultiPartEvent::Done {
-            success: has_code,
-            error: no_code_error.clone(),
-            validated: false,
+            success: false,
+            error: Some(error.clone()),
+            validated: execution_ctx.is_some(),
         });
-
-        record_generation_attempt(
-            &state,
-            &user_request,
-            final_code.as_deref(),
-            has_code,
-            None,
-            None,
-            no_code_error.clone(),
-        );
-        let outcome = PipelineOutcome {
-            response: final_response.clone(),
-            final_code: final_code.clone(),
-            success: has_code,
-            error: no_code_error,
+        PipelineOutcome {
+            response: full_response.clone(),
+            final_code: None,
+            success: false,
+            validated: execution_ctx.is_some(),
+            error: Some(error),
             validation_attempts: None,
             static_findings: vec![],
             post_check_soft_failed: false,
             post_check_soft_fail_reason: None,
-            part_acceptance_rate: None,
+            part_acceptance_rate: Some(0.0),
             assembly_success_rate: None,
             partial_preview_shown: false,
-            empty_viewport_after_generation: !has_code,
+            empty_viewport_after_generation: true,
             retry_ladder_stage_reached: None,
-            failure_signatures: vec![],
-        };
-        record_generation_trace(&config, &user_request, &retrieval_result, None, &outcome);
-
-        return Ok(final_response);
-    }
+            model_escalations: vec![],
+            repair_example_uses: vec![],
+            geometry_hash: None,
+            failure_signatures: failure_signature.map(str::to_string).into_iter().collect(),
+            awaiting_assembly_approval: false,
+            failure_localization: None,
+            injected_mechanism_ids: Vec::new(),
+        }
+    };
 
-    // -----------------------------------------------------------------------
-    // Phase 0: Geometry Design Plan (always runs)
-    // -----------------------------------------------------------------------
-    let (design_plan, plan_result) = run_design_plan_phase(
-        &message,
-        &config,
-        &on_event,
-        &mut total_usage,
-        &provider_id,
-        &model_id,
-        &state,
-    )
-    .await?;
-
-    // -----------------------------------------------------------------------
-    // Phase 1+: Generation pipeline (planner, code gen, review, validation)
-    // -----------------------------------------------------------------------
-    let effective_timeout = effective_generation_timeout_seconds(&config);
-    let generation_timeout = Duration::from_secs(effective_timeout);
-    let outcome = match timeout(
-        generation_timeout,
-        run_generation_pipeline(
-            &design_plan.text,
-            &user_request,
-            history,
-            &config,
-            &system_prompt,
-            &on_event,
-            execution_ctx.as_ref(),
-            &mut total_usage,
-            &provider_id,
-            &model_id,
-        ),
-    )
-    .await
-    {
-        Ok(outcome) => outcome?,
-        Err(_) => {
-            let msg = format!(
-                "Generation runtime exceeded {} seconds (effective timeout; increase timeout in Settings for complex assemblies)",
-                effective_timeout
-            );
-            let _ = on_event.send(MultiPartEvent::Done {
-                success: false,
-                error: Some(msg.clone()),
-                validated: false,
-            });
-            return Err(AppError::AiProviderError(msg));
-        }
+    let Some(mut part_code) = extract_code_from_response(&full_response) else {
+        let error = "No code block extracted from modification response".to_string();
+        let _ = on_event.send(MultiPartEvent::PartComplete {
+            part_index: scope.index,
+            part_name: scope.name.clone(),
+            success: false,
+            error: Some(error.clone()),
+        });
+        return Ok(failed(error, None));
     };
 
-    record_generation_attempt(
-        &state,
-        &user_request,
-        outcome.final_code.as_deref(),
-        outcome.success,
-        None,
-        None,
-        outcome.error.clone(),
-    );
-    record_generation_trace(
-        &config,
-        &user_request,
-        &retrieval_result,
-        Some(plan_result.risk_score),
-        &outcome,
-    );
-
-    Ok(outcome.response)
-}
-
-// ---------------------------------------------------------------------------
-// New commands for two-phase plan flow
-// ---------------------------------------------------------------------------
+    // Per-part acceptance for the changed part only
+    if let Some(ctx) = execution_ctx {
+        match evaluate_part_acceptance(
+            &part_code,
+            ctx,
+            system_prompt,
+            user_request,
+            &scope.name,
+            None,
+            None,
+            Some((run_store, on_event.run_id())),
+        )
+        .await
+        {
+            Ok(artifact) => {
+                part_code = artifact.code;
+                if let Some(stl_base64) = artifact.stl_base64 {
+                    let _ = on_event.send(MultiPartEvent::PartStlReady {
+                        part_index: scope.index,
+                        part_name: scope.name.clone(),
+                        stl_base64: preview_stl_base64(stl_base64, ctx).await,
+                        color: Some(part_colors::resolve(&scope.name, part_colors)),
+                    });
+                }
+            }
+            Err(rejection) => {
+                let _ = on_event.send(MultiPartEvent::PartComplete {
+                    part_index: scope.index,
+                    part_name: scope.name.clone(),
+                    success: false,
+                    error: Some(rejection.error.clone()),
+                });
+                return Ok(failed(
+                    format!("Part '{}' failed acceptance: {}", scope.name, rejection.error),
+                    None,
+                ));
+            }
+        }
+    }
+    let _ = on_event.send(MultiPartEvent::PartComplete {
+        part_index: scope.index,
+        part_name: scope.name.clone(),
+        success: true,
+        error: None,
+    });
 
-#[tauri::command]
-pub async fn generate_design_plan(
-    message: String,
-    _history: Vec<ChatMessage>,
-    on_event: Channel<MultiPartEvent>,
-    state: State<'_, AppState>,
-) -> Result<DesignPlanResult, AppError> {
-    let config = state.config.lock().unwrap().clone();
-    let provider_id = config.ai_provider.clone();
-    let model_id = config.model.clone();
-    let mut total_usage = TokenUsage::default();
+    let Some(spliced) = modify::splice_part_block(old_code, &scope.name, &part_code) else {
+        return Ok(failed(
+            format!("Section for part '{}' not found in the assembly", scope.name),
+            None,
+        ));
+    };
 
-    // Fast prompt triage — ask clarifying questions if the request is vague
-    let triage_provider = create_provider(&config)?;
-    let analysis = design::analyze_prompt_clarity(triage_provider, &message).await?;
+    let mut outcome = PipelineOutcome {
+        response: full_response.clone(),
+        final_code: Some(spliced.clone()),
+        success: true,
+        validated: false,
+        error: None,
+        validation_attempts: None,
+        static_findings: vec![],
+        post_check_soft_failed: false,
+        post_check_soft_fail_reason: None,
+        part_acceptance_rate: Some(1.0),
+        assembly_success_rate: None,
+        partial_preview_shown: false,
+        empty_viewport_after_generation: true,
+        retry_ladder_stage_reached: None,
+        model_escalations: vec![],
+        repair_example_uses: vec![],
+        geometry_hash: None,
+        failure_signatures: vec![],
+        awaiting_assembly_approval: false,
+        failure_localization: None,
+        injected_mechanism_ids: Vec::new(),
+    };
+    let mut stl_base64 = None;
 
-    if analysis.needs_clarification {
-        let _ = on_event.send(MultiPartEvent::ClarificationNeeded {
-            questions: analysis.questions.clone(),
-        });
-        return Ok(DesignPlanResult {
-            plan_text: String::new(),
-            risk_score: 0,
-            warnings: vec![],
-            is_valid: false,
-            clarification_questions: Some(analysis.questions),
-        });
+    // Re-validate the whole assembly with the new part in place
+    if let Some(ctx) = execution_ctx {
+        let on_validation_event =
+            |evt: executor::ValidationEvent| forward_validation_event(on_event, evt);
+        let validation_result = executor::validate_and_retry(
+            spliced,
+            ctx,
+            system_prompt,
+            Some(user_request),
+            &on_validation_event,
+        )
+        .await?;
+        validation_history::record(
+            run_store,
+            &ctx.config,
+            on_event.run_id(),
+            None,
+            &validation_result.attempt_history,
+        );
+        if validation_result.retry_usage.total() > 0 {
+            total_usage.add(&validation_result.retry_usage);
+            emit_usage(
+                on_event,
+                "validation",
+                &validation_result.retry_usage,
+                provider_id,
+                model_id,
+            );
+        }
+        outcome.model_escalations =
+            validation_escalations(config, "modification", &validation_result);
+        outcome.final_code = Some(validation_result.code.clone());
+        outcome.success = validation_result.success;
+        outcome.validated = true;
+        outcome.error = validation_result.error.clone();
+        outcome.validation_attempts = Some(validation_result.attempts);
+        outcome.static_findings = validation_result.static_findings.clone();
+        outcome.post_check_soft_failed = validation_result.post_check_warning.is_some();
+        outcome.post_check_soft_fail_reason = validation_result.post_check_warning.clone();
+        outcome.assembly_success_rate = Some(if validation_result.success { 1.0 } else { 0.0 });
+        outcome.retry_ladder_stage_reached = validation_result.retry_ladder_stage_reached;
+        outcome.repair_example_uses = validation_result.repair_example_uses.clone();
+        stl_base64 = validation_result.stl_base64;
     }
 
-    // If triage returned an enriched prompt, use it for better plan quality
-    let effective_message = analysis
-        .enriched_prompt
-        .as_deref()
-        .unwrap_or(&message);
-
-    let (_design_plan, plan_result) = run_design_plan_phase(
-        effective_message,
-        &config,
-        &on_event,
-        &mut total_usage,
-        &provider_id,
-        &model_id,
-        &state,
-    )
-    .await?;
+    let new_code = outcome.final_code.clone().unwrap_or_default();
+    if !modify::only_part_changed(old_code, &new_code, &scope.name) {
+        let mut rejected = failed(
+            format!(
+                "unscoped_modification_detected: modifying '{}' changed code outside its section",
+                scope.name
+            ),
+            Some("unscoped_modification_detected"),
+        );
+        rejected.model_escalations = outcome.model_escalations;
+        rejected.repair_example_uses = outcome.repair_example_uses;
+        return Ok(rejected);
+    }
 
+    emit_code_diff(on_event, old_code, &new_code);
+    outcome.partial_preview_shown = stl_base64.is_some();
+    outcome.empty_viewport_after_generation = stl_base64.is_none();
+    outcome.geometry_hash = executor::geometry_hash_base64(stl_base64.as_deref());
+    let _ = on_event.send(MultiPartEvent::FinalCode {
+        code: new_code,
+        stl_base64,
+        geometry_hash: outcome.geometry_hash.clone(),
+    });
     if total_usage.total() > 0 {
-        emit_usage(&on_event, "total", &total_usage, &provider_id, &model_id);
+        emit_usage(on_event, "total", total_usage, provider_id, model_id);
     }
+    let _ = on_event.send(MultiPartEvent::Done {
+        success: outcome.success,
+        error: outcome.error.clone(),
+        validated: outcome.validated,
+    });
+    Ok(outcome)
+}
 
-    Ok(plan_result)
+/// Every provider call of the run is tagged with its run id in the audit log.
+async fn run_parallel_generation(
+    message: String,
+    history: Vec<ChatMessage>,
+    existing_code: Option<String>,
+    on_event: EventSink,
+    state: State<'_, AppState>,
+    preset: Option<String>,
+    target_part: Option<String>,
+) -> Result<GenerationResult, AppError> {
+    let run_id = on_event.run_id().to_string();
+    audit::with_run_id(
+        run_id,
+        run_parallel_generation_in_run(
+            message,
+            history,
+            existing_code,
+            on_event,
+            state,
+            preset,
+            target_part,
+        ),
+    )
+    .await
 }
 
-#[tauri::command]
-pub async fn generate_from_plan(
-    plan_text: String,
-    user_request: String,
+async fn run_parallel_generation_in_run(
+    message: String,
     history: Vec<ChatMessage>,
     existing_code: Option<String>,
-    on_event: Channel<MultiPartEvent>,
+    on_event: EventSink,
     state: State<'_, AppState>,
-) -> Result<String, AppError> {
-    let _ = existing_code; // reserved for future use
-    let config = state.config.lock().unwrap().clone();
+    preset: Option<String>,
+    target_part: Option<String>,
+) -> Result<GenerationResult, AppError> {
+    let config = crate::pipeline_presets::resolve_run_config(
+        &state.config.lock().unwrap().clone(),
+        preset.as_deref(),
+    )?;
+    let (config, commands) = match slash_commands::apply(&config, &message) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let _ = on_event.send(MultiPartEvent::Done {
+                success: false,
+                error: Some(e.clone()),
+                validated: false,
+            });
+            return Err(AppError::ConfigError(e));
+        }
+    };
+    if let Some(summary) = commands.summary() {
+        let _ = on_event.send(MultiPartEvent::PlanStatus { message: summary });
+    }
+    let message = commands.message;
+    announce_build123d_version_change(&state, &on_event).await;
     let cq_version = state.build123d_version.lock().unwrap().clone();
+    let user_request = message.clone();
     let session_ctx = state.session_memory.lock().unwrap().build_context_section();
-    let retrieval_query = format!("{}\n\n{}", user_request, plan_text);
     let (system_prompt, retrieval_result) = build_system_prompt_with_retrieval(
         &config,
         cq_version.as_deref(),
-        &retrieval_query,
+        &message,
         session_ctx,
         &on_event,
         true, // compact prompt for multi-part
     )
     .await;
+
     let provider_id = config.ai_provider.clone();
     let model_id = config.model.clone();
     let mut total_usage = TokenUsage::default();
 
-    let execution_ctx = {
-        let venv_path = state.venv_path.lock().unwrap().clone();
-        match venv_path {
-            Some(venv_dir) => match super::find_python_script("runner.py") {
-                Ok(runner_script) => Some(executor::ExecutionContext {
-                    venv_dir,
-                    runner_script,
-                    config: config.clone(),
-                }),
-                Err(_) => None,
-            },
-            None => None,
-        }
-    };
-
-    let effective_timeout = effective_generation_timeout_seconds(&config);
-    let generation_timeout = Duration::from_secs(effective_timeout);
-    let outcome = match timeout(
-        generation_timeout,
-        run_generation_pipeline(
-            &plan_text,
-            &user_request,
-            history,
-            &config,
-            &system_prompt,
-            &on_event,
-            execution_ctx.as_ref(),
-            &mut total_usage,
-            &provider_id,
-            &model_id,
-        ),
-    )
-    .await
-    {
-        Ok(outcome) => outcome?,
-        Err(_) => {
-            let msg = format!(
-                "Generation runtime exceeded {} seconds (effective timeout; increase timeout in Settings for complex assemblies)",
-                effective_timeout
-            );
-            let _ = on_event.send(MultiPartEvent::Done {
-                success: false,
-                error: Some(msg.clone()),
-                validated: false,
-            });
-            return Err(AppError::AiProviderError(msg));
-        }
-    };
+    // Resolve execution context for backend validation (None if Python not set up)
+    let execution_ctx = execution_context(&state, &config);
 
-    record_generation_attempt(
-        &state,
-        &user_request,
-        outcome.final_code.as_deref(),
-        outcome.success,
-        None,
-        None,
-        outcome.error.clone(),
-    );
-    record_generation_trace(&config, &user_request, &retrieval_result, None, &outcome);
+    // -----------------------------------------------------------------------
+    // Modification branch: detect and handle code modifications (early return)
+    // -----------------------------------------------------------------------
+    let modification_intent =
+        modify::detect_modification_intent(&message, existing_code.as_deref());
+    let has_existing_code = existing_code
+        .as_deref()
+        .is_some_and(|code| !code.trim().is_empty());
+    let target_part = target_part.filter(|_| has_existing_code);
 
-    Ok(outcome.response)
-}
+    if modification_intent.is_modification || target_part.is_some() {
+        let intent_summary = modification_intent
+            .intent_summary
+            .unwrap_or_else(|| "modifying code".to_string());
 
-// ---------------------------------------------------------------------------
-// Helpers
-// ---------------------------------------------------------------------------
+        let _ = on_event.send(MultiPartEvent::ModificationDetected {
+            intent_summary: intent_summary.clone(),
+        });
 
-/// Detect if the user request explicitly requires a strict multipart assembly outcome.
-fn request_requires_multipart_contract(user_request: &str, plan_text: &str) -> bool {
-    let text = format!("{}\n{}", user_request, plan_text).to_lowercase();
+        let old_code = existing_code.as_deref().unwrap_or("");
 
-    const EXPLICIT_MULTI_HINTS: [&str; 16] = [
-        "separate part",
-        "separate parts",
-        "separate component",
-        "separate components",
-        "separate body",
-        "separate bodies",
-        "separate piece",
-        "separate pieces",
-        "back plate",
-        "backplate",
-        "multi-part",
-        "multipart",
-        "exploded view",
-        "exploded",
-        "bakplate",
-        "eksplodert",
-    ];
-
-    if EXPLICIT_MULTI_HINTS.iter().any(|hint| text.contains(hint)) {
-        return true;
-    }
-
-    // Guard for prompts phrased as "separate X" without exact phrase matches.
-    text.contains("separate")
-        && (text.contains("part") || text.contains("component") || text.contains("piece"))
-}
-
-/// Parse the planner JSON response.
-fn parse_plan(json_str: &str) -> Result<GenerationPlan, String> {
-    fn try_repair_json_fragment(input: &str) -> Option<String> {
-        let mut s = input.trim().to_string();
-        if s.is_empty() {
-            return None;
-        }
-
-        let mut quote_count = 0usize;
-        let mut escaped = false;
-        for ch in s.chars() {
-            if escaped {
-                escaped = false;
-                continue;
-            }
-            if ch == '\\' {
-                escaped = true;
-                continue;
-            }
-            if ch == '"' {
-                quote_count += 1;
+        // Part-scoped branch: only the targeted part's section is rewritten.
+        let scope = match modify::resolve_part_scope(old_code, &message, target_part.as_deref()) {
+            Ok(scope) => scope,
+            Err(e) => {
+                let _ = on_event.send(MultiPartEvent::Done {
+                    success: false,
+                    error: Some(e.clone()),
+                    validated: false,
+                });
+                return Err(AppError::ConfigError(e));
             }
-        }
-        if quote_count % 2 == 1 {
-            s.push('"');
+        };
+        if let Some(scope) = scope {
+            let part_colors = state.part_colors.lock().unwrap().clone();
+            let outcome = run_scoped_modification(
+                &scope,
+                old_code,
+                &user_request,
+                history,
+                &config,
+                &system_prompt,
+                execution_ctx.as_ref(),
+                &on_event,
+                &mut total_usage,
+                &part_colors,
+                &state.run_store,
+            )
+            .await?;
+            record_generation_attempt(
+                &state,
+                &user_request,
+                outcome.final_code.as_deref(),
+                outcome.success,
+                None,
+                None,
+                outcome.error.clone(),
+            );
+            record_generation_trace(
+                &config,
+                &on_event,
+                &user_request,
+                &retrieval_result,
+                None,
+                None,
+                &outcome,
+                false,
+            );
+            return Ok(GenerationResult::from_outcome(
+                &outcome,
+                Some(cost::estimate_cost(&provider_id, &model_id, &total_usage).cost),
+            ));
         }
 
-        let open_brackets = s.chars().filter(|c| *c == '[').count();
-        let close_brackets = s.chars().filter(|c| *c == ']').count();
-        if open_brackets > close_brackets {
-            s.push_str(&"]".repeat(open_brackets - close_brackets));
-        }
+        let _ = on_event.send(MultiPartEvent::PlanStatus {
+            message: "Modifying existing code...".to_string(),
+        });
 
-        let open_braces = s.chars().filter(|c| *c == '{').count();
-        let close_braces = s.chars().filter(|c| *c == '}').count();
-        if open_braces > close_braces {
-            s.push_str(&"}".repeat(open_braces - close_braces));
-        }
+        // Build modification-specific system prompt and user message.
+        // For fine-tuned providers the base prompt is already minimal — don't
+        // append the lengthy MODIFICATION_INSTRUCTIONS block.
+        let mod_system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
+            system_prompt.clone()
+        } else {
+            format!("{}\n{}", system_prompt, modify::MODIFICATION_INSTRUCTIONS)
+        };
+        let modification_message = modify::build_modification_message(old_code, &message);
 
-        Some(s)
-    }
+        let provider = create_provider(&config)?;
+        let mut messages_list = vec![ChatMessage {
+            role: "system".to_string(),
+            content: mod_system_prompt,
+        }];
+        messages_list.extend(history);
+        messages_list.push(ChatMessage {
+            role: "user".to_string(),
+            content: modification_message,
+        });
 
-    // Try to extract JSON from the response (the AI might wrap it in markdown fences)
-    let cleaned = json_str
-        .trim()
-        .trim_start_matches("```json")
-        .trim_start_matches("```")
-        .trim_end_matches("```")
-        .trim();
+        // Stream the AI response (reuse SingleDelta/SingleDone events)
+        let (tx, rx) = mpsc::channel::<StreamSignal>(100);
+        let mut stream = StreamWatch::for_provider(provider.as_ref(), rx);
+        let provider_handle =
+            tokio::spawn(async move { provider.stream(&messages_list, tx).await });
 
-    match serde_json::from_str::<GenerationPlan>(cleaned) {
-        Ok(plan) => {
-            if plan.mode != "single" && plan.mode != "multi" {
-                return Err(format!("Invalid planner mode '{}'", plan.mode));
-            }
-            Ok(plan)
-        }
-        Err(first_err) => {
-            // Tertiary attempt for truncated JSON fragments (common EOF parser failures).
-            if let Some(repaired) = try_repair_json_fragment(cleaned) {
-                if let Ok(plan) = serde_json::from_str::<GenerationPlan>(&repaired) {
-                    if plan.mode != "single" && plan.mode != "multi" {
-                        return Err(format!("Invalid planner mode '{}'", plan.mode));
-                    }
-                    return Ok(plan);
+        let mut full_response = String::new();
+        let mut deltas_seen = 0usize;
+        while let Some(progress) = stream.next().await {
+            let delta = match stream_step_delta(progress, &on_event) {
+                Ok(Some(delta)) => delta,
+                Ok(None) => continue,
+                Err(e) => {
+                    provider_handle.abort();
+                    return Err(AppError::AiProviderError(e));
                 }
+            };
+            full_response.push_str(&delta.content);
+            let _ = on_event.send(MultiPartEvent::SingleDelta {
+                delta: delta.content,
+                done: delta.done,
+            });
+            deltas_seen += 1;
+            if on_event.stream_interrupted(deltas_seen) {
+                provider_handle.abort();
+                return Err(AppError::ChannelDisconnected(on_event.run_id().to_string()));
             }
+        }
 
-            // Secondary attempt: extract first outer JSON object if the model wrapped text around it.
-            if let (Some(start), Some(end)) = (cleaned.find('{'), cleaned.rfind('}')) {
-                if start < end {
-                    let candidate = &cleaned[start..=end];
-                    if let Ok(plan) = serde_json::from_str::<GenerationPlan>(candidate) {
-                        if plan.mode != "single" && plan.mode != "multi" {
-                            return Err(format!("Invalid planner mode '{}'", plan.mode));
-                        }
-                        return Ok(plan);
-                    }
-                    // The outer {..} failed (e.g. prose with scattered braces).
-                    // Try to repair the candidate in case it was truncated.
-                    if let Some(repaired) = try_repair_json_fragment(candidate) {
-                        if let Ok(plan) = serde_json::from_str::<GenerationPlan>(&repaired) {
-                            if plan.mode != "single" && plan.mode != "multi" {
-                                return Err(format!("Invalid planner mode '{}'", plan.mode));
-                            }
-                            return Ok(plan);
-                        }
-                    }
+        match provider_handle.await {
+            Ok(Ok(stream_usage)) => {
+                if let Some(ref u) = stream_usage {
+                    total_usage.add(u);
+                    emit_usage(&on_event, "generate", u, &provider_id, &model_id);
                 }
             }
+            Ok(Err(e)) => return Err(e),
+            Err(e) => {
+                return Err(AppError::AiProviderError(format!(
+                    "Provider task panicked: {}",
+                    e
+                )));
+            }
+        }
 
-            // Quaternary attempt: regex-locate a JSON object starting with `{"mode":`
-            // This handles prose responses where JSON is deeply embedded.
-            if let Some(m) = Regex::new(r#"\{"mode"\s*:\s*"(single|multi)""#)
-                .ok()
-                .and_then(|re| re.find(cleaned))
-            {
-                let json_start = m.start();
-                // Walk from json_start counting brace depth to find matching '}'
-                let bytes = cleaned[json_start..].as_bytes();
-                let mut depth: i32 = 0;
-                let mut in_string = false;
-                let mut escaped = false;
-                let mut json_end = None;
-                for (i, &b) in bytes.iter().enumerate() {
-                    if escaped {
-                        escaped = false;
-                        continue;
-                    }
-                    if b == b'\\' && in_string {
-                        escaped = true;
-                        continue;
-                    }
-                    if b == b'"' {
-                        in_string = !in_string;
-                        continue;
-                    }
-                    if in_string {
-                        continue;
-                    }
-                    if b == b'{' {
-                        depth += 1;
-                    } else if b == b'}' {
-                        depth -= 1;
-                        if depth == 0 {
-                            json_end = Some(json_start + i);
-                            break;
+        let _ = on_event.send(MultiPartEvent::SingleDone {
+            full_response: full_response.clone(),
+        });
+
+        // Extract code from the response
+        let mut final_code = extract_code_from_response(&full_response);
+        let mut final_response = full_response.clone();
+
+        // Optional: code review
+        if config.enable_code_review {
+            if let Some(ref code) = final_code {
+                let _ = on_event.send(MultiPartEvent::ReviewStatus {
+                    message: "Reviewing modified code...".to_string(),
+                });
+
+                on_event.ensure_connected()?;
+                let review_provider = create_provider(&config)?;
+                match review::review_code(
+                    review_provider,
+                    &user_request,
+                    code,
+                    None,
+                    &review::ReviewOptions::from_config(&config),
+                )
+                .await
+                {
+                    Ok((result, review_usage)) => {
+                        if let Some(ref u) = review_usage {
+                            total_usage.add(u);
+                            emit_usage(&on_event, "review", u, &provider_id, &model_id);
                         }
-                    }
-                }
-                if let Some(end_pos) = json_end {
-                    let candidate = &cleaned[json_start..=end_pos];
-                    if let Ok(plan) = serde_json::from_str::<GenerationPlan>(candidate) {
-                        if plan.mode == "single" || plan.mode == "multi" {
-                            return Ok(plan);
+                        let _ = on_event.send(MultiPartEvent::ReviewComplete {
+                            was_modified: result.was_modified,
+                            explanation: result.explanation.clone(),
+                            findings: result.findings.clone(),
+                            ai_reviewed: result.ai_reviewed,
+                        });
+                        if result.was_modified {
+                            final_response = full_response.replace(code, &result.code);
+                            final_code = Some(result.code);
                         }
                     }
+                    Err(e) => {
+                        eprintln!("Code review failed (modification): {}", e);
+                    }
                 }
             }
-
-            Err(format!("Planner JSON parse failed: {}", first_err))
         }
-    }
-}
-
-/// Extract a Python code block from an AI response.
-fn extract_code_from_response(response: &str) -> Option<String> {
-    crate::agent::extract::extract_code(response)
-}
 
-struct PartAcceptanceArtifact {
-    code: String,
-    stl_base64: Option<String>,
-    post_geometry_report: Option<executor::PostGeometryValidationReport>,
-    post_check_warning: Option<String>,
-    semantic_findings: Vec<String>,
-    retry_ladder_stage_reached: Option<u32>,
-}
+        // Optional: backend validation
+        if let (Some(code), Some(ref ctx)) = (&final_code, &execution_ctx) {
+            let on_validation_event =
+                |evt: executor::ValidationEvent| forward_validation_event(&on_event, evt);
 
-async fn evaluate_part_acceptance(
-    part_code: &str,
-    ctx: &executor::ExecutionContext,
-    system_prompt: &str,
-    part_request: &str,
-    part_name: &str,
-    semantic_contract: Option<&semantic_validate::SemanticPartContract>,
-) -> Result<PartAcceptanceArtifact, String> {
-    let no_event = |_evt: executor::ValidationEvent| {};
-    let bbox_hint_owned = build_part_bbox_hint(
-        semantic_contract,
-        part_request,
-        &ctx.config.semantic_bbox_mode,
-    );
-    let validation = executor::validate_and_retry(
-        part_code.to_string(),
-        ctx,
-        system_prompt,
-        bbox_hint_owned.as_deref(),
-        &no_event,
-    )
-    .await
-    .map_err(|e| format!("part acceptance validation error: {}", e))?;
+            let validation_result = executor::validate_and_retry(
+                code.clone(),
+                ctx,
+                &system_prompt,
+                Some(&user_request),
+                &on_validation_event,
+            )
+            .await?;
+            validation_history::record(
+                &state.run_store,
+                &ctx.config,
+                on_event.run_id(),
+                None,
+                &validation_result.attempt_history,
+            );
+            let model_escalations =
+                validation_escalations(&config, "modification", &validation_result);
 
-    if !validation.success {
-        return Err(validation
-            .error
-            .unwrap_or_else(|| "part validation failed".to_string()));
-    }
+            if validation_result.retry_usage.total() > 0 {
+                total_usage.add(&validation_result.retry_usage);
+                emit_usage(
+                    &on_event,
+                    "validation",
+                    &validation_result.retry_usage,
+                    &provider_id,
+                    &model_id,
+                );
+            }
 
-    let mut semantic_findings = Vec::new();
-    if ctx.config.quality_gates_strict && ctx.config.semantic_contract_strict {
-        if let Some(report) = validation.post_geometry_report.as_ref() {
-            let contract = semantic_contract
-                .cloned()
-                .unwrap_or_else(|| semantic_validate::build_default_contract(part_name, part_request));
-            let semantic =
-                semantic_validate::validate_part_semantics(&contract, report, &validation.code);
-            semantic_findings = semantic.findings.clone();
-            if !semantic.passed {
-                return Err(format!(
-                    "semantic validation failed: {}",
-                    semantic.findings.join("; ")
-                ));
+            let new_code = &validation_result.code;
+
+            // Compute diff between old code and final new code
+            emit_code_diff(&on_event, old_code, new_code);
+
+            let geometry_hash =
+                executor::geometry_hash_base64(validation_result.stl_base64.as_deref());
+            let _ = on_event.send(MultiPartEvent::FinalCode {
+                code: validation_result.code.clone(),
+                stl_base64: validation_result.stl_base64.clone(),
+                geometry_hash: geometry_hash.clone(),
+            });
+
+            if total_usage.total() > 0 {
+                emit_usage(&on_event, "total", &total_usage, &provider_id, &model_id);
             }
+
+            let _ = on_event.send(MultiPartEvent::Done {
+                success: validation_result.success,
+                error: validation_result.error.clone(),
+                validated: true,
+            });
+
+            let outcome = PipelineOutcome {
+                response: final_response.clone(),
+                final_code: Some(validation_result.code.clone()),
+                success: validation_result.success,
+                validated: true,
+                error: validation_result.error.clone(),
+                validation_attempts: Some(validation_result.attempts),
+                static_findings: validation_result.static_findings.clone(),
+                post_check_soft_failed: validation_result.post_check_warning.is_some(),
+                post_check_soft_fail_reason: validation_result.post_check_warning.clone(),
+                part_acceptance_rate: None,
+                assembly_success_rate: None,
+                partial_preview_shown: validation_result.stl_base64.is_some(),
+                empty_viewport_after_generation: validation_result.stl_base64.is_none(),
+                retry_ladder_stage_reached: validation_result.retry_ladder_stage_reached,
+                model_escalations,
+                repair_example_uses: validation_result.repair_example_uses.clone(),
+                geometry_hash,
+                failure_signatures: vec![],
+                awaiting_assembly_approval: false,
+                failure_localization: None,
+                injected_mechanism_ids: Vec::new(),
+            };
+
+            record_generation_attempt(
+                &state,
+                &user_request,
+                Some(&validation_result.code),
+                validation_result.success,
+                None,
+                None,
+                validation_result.error.clone(),
+            );
+            record_generation_trace(
+                &config,
+                &on_event,
+                &user_request,
+                &retrieval_result,
+                None,
+                None,
+                &outcome,
+                false,
+            );
+
+            return Ok(GenerationResult::from_outcome(
+                &outcome,
+                Some(cost::estimate_cost(&provider_id, &model_id, &total_usage).cost),
+            ));
         }
-        // When report is None (post-check soft-fail), semantic validation is
-        // skipped — the part still passes on its execution success.
-        // The post_check_warning field already carries the warning for logging/UI.
+
+        // No execution context — emit diff and code as-is
+        if let Some(ref code) = final_code {
+            emit_code_diff(&on_event, old_code, code);
+
+            let _ = on_event.send(MultiPartEvent::FinalCode {
+                code: code.clone(),
+                stl_base64: None,
+                geometry_hash: None,
+            });
+        }
+
+        if total_usage.total() > 0 {
+            emit_usage(&on_event, "total", &total_usage, &provider_id, &model_id);
+        }
+
+        // Guard: report failure if no code was extracted from the modification response
+        let has_code = final_code.is_some();
+        let no_code_error = if has_code {
+            None
+        } else {
+            Some("No code block extracted from modification response".to_string())
+        };
+
+        let _ = on_event.send(MultiPartEvent::Done {
+            success: has_code,
+            error: no_code_error.clone(),
+            validated: false,
+        });
+
+        record_generation_attempt(
+            &state,
+            &user_request,
+            final_code.as_deref(),
+            has_code,
+            None,
+            None,
+            no_code_error.clone(),
+        );
+        let outcome = PipelineOutcome {
+            response: final_response.clone(),
+            final_code: final_code.clone(),
+            success: has_code,
+            validated: false,
+            error: no_code_error,
+            validation_attempts: None,
+            static_findings: vec![],
+            post_check_soft_failed: false,
+            post_check_soft_fail_reason: None,
+            part_acceptance_rate: None,
+            assembly_success_rate: None,
+            partial_preview_shown: false,
+            empty_viewport_after_generation: !has_code,
+            retry_ladder_stage_reached: None,
+            model_escalations: vec![],
+            repair_example_uses: vec![],
+            geometry_hash: None,
+            failure_signatures: vec![],
+            awaiting_assembly_approval: false,
+            failure_localization: None,
+            injected_mechanism_ids: Vec::new(),
+        };
+        record_generation_trace(
+            &config,
+            &on_event,
+            &user_request,
+            &retrieval_result,
+            None,
+            None,
+            &outcome,
+            false,
+        );
+
+        return Ok(GenerationResult::from_outcome(
+            &outcome,
+            Some(cost::estimate_cost(&provider_id, &model_id, &total_usage).cost),
+        ));
     }
 
-    Ok(PartAcceptanceArtifact {
-        code: validation.code,
-        stl_base64: validation.stl_base64,
-        post_geometry_report: validation.post_geometry_report,
-        post_check_warning: validation.post_check_warning,
-        semantic_findings,
-        retry_ladder_stage_reached: validation.retry_ladder_stage_reached,
-    })
-}
+    // -----------------------------------------------------------------------
+    // Phase 0: Geometry Design Plan (always runs)
+    // -----------------------------------------------------------------------
+    let (design_plan, plan_result) = run_design_plan_phase(
+        &message,
+        &config,
+        &on_event,
+        &mut total_usage,
+        &provider_id,
+        &model_id,
+        &state,
+    )
+    .await?;
+    if let Some(questions) = &plan_result.clarification_questions {
+        let _ = on_event.send(MultiPartEvent::Done {
+            success: false,
+            error: Some("Clarification needed before generating code.".to_string()),
+            validated: false,
+        });
+        return Ok(GenerationResult {
+            success: false,
+            final_code: None,
+            validated: false,
+            part_acceptance_rate: None,
+            total_cost_usd: Some(cost::estimate_cost(&provider_id, &model_id, &total_usage).cost),
+            failure_signatures: vec![],
+            error: Some("Clarification needed before generating code.".to_string()),
+            response: format!(
+                "I need a few more details before designing this:\n\n{}",
+                questions
+                    .iter()
+                    .enumerate()
+                    .map(|(i, q)| format!("{}. {}", i + 1, q))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+            geometry_hash: None,
+        });
+    }
 
-async fn build_part_preview_stl_with_repair(
-    part_code: &str,
-    ctx: &executor::ExecutionContext,
-    system_prompt: &str,
-    part_request: &str,
-    part_name: &str,
-    semantic_contract: Option<&semantic_validate::SemanticPartContract>,
-) -> Result<String, String> {
-    match evaluate_part_acceptance(
-        part_code,
-        ctx,
-        system_prompt,
-        part_request,
-        part_name,
-        semantic_contract,
+    // -----------------------------------------------------------------------
+    // Phase 1+: Generation pipeline (planner, code gen, review, validation)
+    // -----------------------------------------------------------------------
+    let part_materials = state
+        .part_materials
+        .lock()
+        .map(|m| m.clone())
+        .unwrap_or_default();
+    let part_colors = state
+        .part_colors
+        .lock()
+        .map(|m| m.clone())
+        .unwrap_or_default();
+    let effective_timeout = effective_generation_timeout_seconds(&config);
+    let generation_timeout = Duration::from_secs(effective_timeout);
+    let outcome = match timeout(
+        generation_timeout,
+        run_generation_pipeline(
+            &design_plan.text,
+            &user_request,
+            history,
+            &config,
+            &system_prompt,
+            &on_event,
+            execution_ctx.as_ref(),
+            &mut total_usage,
+            &provider_id,
+            &model_id,
+            &part_materials,
+            &part_colors,
+            &state.run_store,
+            &retrieval_result.items,
+        ),
     )
     .await
     {
-        Ok(artifact) => artifact
-            .stl_base64
-            .ok_or_else(|| "validated part preview is missing STL output".to_string()),
-        Err(e) => Err(e),
+        Ok(Err(AppError::ChannelDisconnected(run_id))) => {
+            handle_channel_disconnect(&state, &config, &user_request, &retrieval_result, &on_event);
+            return Err(AppError::ChannelDisconnected(run_id));
+        }
+        Ok(outcome) => outcome?,
+        Err(_) => {
+            let msg = format!(
+                "Generation runtime exceeded {} seconds (effective timeout; increase timeout in Settings for complex assemblies)",
+                effective_timeout
+            );
+            let _ = on_event.send(MultiPartEvent::Done {
+                success: false,
+                error: Some(msg.clone()),
+                validated: false,
+            });
+            let summary = next_actions::FailureSummary {
+                error: Some(&msg),
+                python_available: execution_ctx.is_some(),
+                ..Default::default()
+            };
+            send_suggested_actions(&on_event, &config, &summary);
+            return Err(AppError::AiProviderError(msg));
+        }
+    };
+    // A parked run has no result to record until its assembly is approved.
+    if outcome.awaiting_assembly_approval {
+        return Ok(GenerationResult::from_outcome(
+            &outcome,
+            Some(cost::estimate_cost(&provider_id, &model_id, &total_usage).cost),
+        ));
+    }
+
+    if !outcome.success {
+        send_suggested_actions(
+            &on_event,
+            &config,
+            &outcome.failure_summary(execution_ctx.is_some()),
+        );
+    }
+
+    record_generation_attempt(
+        &state,
+        &user_request,
+        outcome.final_code.as_deref(),
+        outcome.success,
+        None,
+        None,
+        outcome.error.clone(),
+    );
+    record_generation_trace(
+        &config,
+        &on_event,
+        &user_request,
+        &retrieval_result,
+        Some(plan_result.risk_score),
+        plan_result.estimated_operations,
+        &outcome,
+        false,
+    );
+
+    Ok(GenerationResult::from_outcome(
+        &outcome,
+        Some(cost::estimate_cost(&provider_id, &model_id, &total_usage).cost),
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// New commands for two-phase plan flow
+// ---------------------------------------------------------------------------
+
+/// Sections and build steps of a design plan, for programmatic consumers.
+#[tauri::command]
+pub fn parse_design_plan(plan_text: String) -> design::StructuredPlan {
+    design::parse_plan_to_struct(&plan_text)
+}
+
+/// Semantic changes from `old_plan` to `new_plan`: build steps added, removed
+/// or rewritten, and per-section operation and dimension changes.
+#[tauri::command]
+pub fn diff_plans(old_plan: String, new_plan: String) -> design::PlanDiff {
+    design::diff_plans(&old_plan, &new_plan)
+}
+
+#[tauri::command]
+pub async fn generate_design_plan(
+    message: String,
+    history: Vec<ChatMessage>,
+    on_event: Channel<EventEnvelope>,
+    state: State<'_, AppState>,
+    auto_approve: Option<bool>,
+    preset: Option<String>,
+) -> Result<DesignPlanResult, AppError> {
+    let on_event = EventSink::register(&state.run_events, on_event);
+    let config = state.config.lock().unwrap().clone();
+    let provider_id = config.ai_provider.clone();
+    let model_id = config.model.clone();
+    let mut total_usage = TokenUsage::default();
+
+    // Fast prompt triage — ask clarifying questions if the request is vague
+    let triage_provider = create_provider(&config)?;
+    let analysis = design::analyze_prompt_clarity(triage_provider, &message).await?;
+
+    if analysis.needs_clarification {
+        let _ = on_event.send(MultiPartEvent::ClarificationNeeded {
+            questions: analysis.questions.clone(),
+        });
+        return Ok(DesignPlanResult {
+            plan_text: String::new(),
+            risk_score: 0,
+            warnings: vec![],
+            is_valid: false,
+            estimated_operations: None,
+            clarification_questions: Some(analysis.questions),
+            auto_approval: None,
+            auto_approved: false,
+            generation_response: None,
+        });
+    }
+
+    // If triage returned an enriched prompt, use it for better plan quality
+    let effective_message = analysis
+        .enriched_prompt
+        .as_deref()
+        .unwrap_or(&message);
+
+    let (_design_plan, mut plan_result) = run_design_plan_phase(
+        effective_message,
+        &config,
+        &on_event,
+        &mut total_usage,
+        &provider_id,
+        &model_id,
+        &state,
+    )
+    .await?;
+
+    if total_usage.total() > 0 {
+        emit_usage(&on_event, "total", &total_usage, &provider_id, &model_id);
+    }
+
+    if !auto_approve.unwrap_or(config.auto_approve_plan) {
+        return Ok(plan_result);
+    }
+    let Some(decision) = plan_result.auto_approval.clone() else {
+        return Ok(plan_result);
+    };
+    let _ = on_event.send(MultiPartEvent::PlanStatus {
+        message: decision.describe(),
+    });
+    if !decision.eligible {
+        return Ok(plan_result);
+    }
+
+    let response = run_from_plan(
+        plan_result.plan_text.clone(),
+        message,
+        history,
+        on_event.clone(),
+        state.clone(),
+        preset,
+        true,
+    )
+    .await?;
+    plan_result.auto_approved = true;
+    plan_result.generation_response = Some(response);
+    Ok(plan_result)
+}
+
+#[tauri::command]
+pub async fn generate_from_plan(
+    plan_text: String,
+    user_request: String,
+    history: Vec<ChatMessage>,
+    existing_code: Option<String>,
+    on_event: Channel<EventEnvelope>,
+    state: State<'_, AppState>,
+    preset: Option<String>,
+) -> Result<String, AppError> {
+    let on_event = EventSink::register(&state.run_events, on_event);
+    let _ = existing_code; // reserved for future use
+    run_from_plan(
+        plan_text,
+        user_request,
+        history,
+        on_event,
+        state,
+        preset,
+        false,
+    )
+    .await
+}
+
+/// Phase 1+ from an approved plan, shared by manual approval
+/// (`generate_from_plan`) and auto-approval (`generate_design_plan`).
+async fn run_from_plan(
+    plan_text: String,
+    user_request: String,
+    history: Vec<ChatMessage>,
+    on_event: EventSink,
+    state: State<'_, AppState>,
+    preset: Option<String>,
+    auto_approved: bool,
+) -> Result<String, AppError> {
+    let config = crate::pipeline_presets::resolve_run_config(
+        &state.config.lock().unwrap().clone(),
+        preset.as_deref(),
+    )?;
+    announce_build123d_version_change(&state, &on_event).await;
+    let cq_version = state.build123d_version.lock().unwrap().clone();
+    let session_ctx = state.session_memory.lock().unwrap().build_context_section();
+    let retrieval_query = format!("{}\n\n{}", user_request, plan_text);
+    let (system_prompt, retrieval_result) = build_system_prompt_with_retrieval(
+        &config,
+        cq_version.as_deref(),
+        &retrieval_query,
+        session_ctx,
+        &on_event,
+        true, // compact prompt for multi-part
+    )
+    .await;
+    let provider_id = config.ai_provider.clone();
+    let model_id = config.model.clone();
+    let mut total_usage = TokenUsage::default();
+
+    let execution_ctx = execution_context(&state, &config);
+
+    let part_materials = state
+        .part_materials
+        .lock()
+        .map(|m| m.clone())
+        .unwrap_or_default();
+    let part_colors = state
+        .part_colors
+        .lock()
+        .map(|m| m.clone())
+        .unwrap_or_default();
+    let effective_timeout = effective_generation_timeout_seconds(&config);
+    let generation_timeout = Duration::from_secs(effective_timeout);
+    let outcome = match timeout(
+        generation_timeout,
+        run_generation_pipeline(
+            &plan_text,
+            &user_request,
+            history,
+            &config,
+            &system_prompt,
+            &on_event,
+            execution_ctx.as_ref(),
+            &mut total_usage,
+            &provider_id,
+            &model_id,
+            &part_materials,
+            &part_colors,
+            &state.run_store,
+            &retrieval_result.items,
+        ),
+    )
+    .await
+    {
+        Ok(Err(AppError::ChannelDisconnected(run_id))) => {
+            handle_channel_disconnect(&state, &config, &user_request, &retrieval_result, &on_event);
+            return Err(AppError::ChannelDisconnected(run_id));
+        }
+        Ok(outcome) => outcome?,
+        Err(_) => {
+            let msg = format!(
+                "Generation runtime exceeded {} seconds (effective timeout; increase timeout in Settings for complex assemblies)",
+                effective_timeout
+            );
+            let _ = on_event.send(MultiPartEvent::Done {
+                success: false,
+                error: Some(msg.clone()),
+                validated: false,
+            });
+            let summary = next_actions::FailureSummary {
+                error: Some(&msg),
+                python_available: execution_ctx.is_some(),
+                ..Default::default()
+            };
+            send_suggested_actions(&on_event, &config, &summary);
+            return Err(AppError::AiProviderError(msg));
+        }
+    };
+    // A parked run has no result to record until its assembly is approved.
+    if outcome.awaiting_assembly_approval {
+        return Ok(outcome.response);
+    }
+
+    if !outcome.success {
+        send_suggested_actions(
+            &on_event,
+            &config,
+            &outcome.failure_summary(execution_ctx.is_some()),
+        );
+    }
+
+    record_generation_attempt(
+        &state,
+        &user_request,
+        outcome.final_code.as_deref(),
+        outcome.success,
+        None,
+        None,
+        outcome.error.clone(),
+    );
+    record_generation_trace(
+        &config,
+        &on_event,
+        &user_request,
+        &retrieval_result,
+        None,
+        Some(complexity::estimate_plan_operations(
+            &design::parse_plan_to_struct(&plan_text).build_steps,
+        )),
+        &outcome,
+        auto_approved,
+    );
+
+    Ok(outcome.response)
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Detect if the user request explicitly requires a strict multipart assembly outcome.
+fn request_requires_multipart_contract(user_request: &str, plan_text: &str) -> bool {
+    let text = format!("{}\n{}", user_request, plan_text).to_lowercase();
+
+    const EXPLICIT_MULTI_HINTS: [&str; 16] = [
+        "separate part",
+        "separate parts",
+        "separate component",
+        "separate components",
+        "separate body",
+        "separate bodies",
+        "separate piece",
+        "separate pieces",
+        "back plate",
+        "backplate",
+        "multi-part",
+        "multipart",
+        "exploded view",
+        "exploded",
+        "bakplate",
+        "eksplodert",
+    ];
+
+    if EXPLICIT_MULTI_HINTS.iter().any(|hint| text.contains(hint)) {
+        return true;
+    }
+
+    // Guard for prompts phrased as "separate X" without exact phrase matches.
+    text.contains("separate")
+        && (text.contains("part") || text.contains("component") || text.contains("piece"))
+}
+
+/// Resolve a `single` plan that still lists two or more parts: promote it to
+/// `multi` or drop the parts, depending on `bias`. Returns a warning message
+/// when the plan was changed.
+fn reconcile_plan_mode(
+    plan: &mut GenerationPlan,
+    bias: &crate::config::DecompositionBias,
+) -> Option<String> {
+    if plan.mode != "single" || plan.parts.len() < 2 {
+        return None;
+    }
+    let count = plan.parts.len();
+    match bias {
+        crate::config::DecompositionBias::PreferMulti => {
+            plan.mode = "multi".to_string();
+            Some(format!(
+                "Planner chose single-part mode but listed {} parts; generating them as a multi-part assembly.",
+                count
+            ))
+        }
+        crate::config::DecompositionBias::PreferSingle => {
+            plan.parts.clear();
+            Some(format!(
+                "Planner chose single-part mode but listed {} parts; ignoring the parts and generating a single part.",
+                count
+            ))
+        }
+    }
+}
+
+/// Plan to continue with when every planner attempt failed, plus a warning
+/// for the user. Errors when the multipart contract applies or the policy
+/// is `Fail`.
+fn planner_failure_fallback(
+    policy: &crate::config::PlannerFailurePolicy,
+    requires_multipart_contract: bool,
+    attempts: u32,
+    parse_err: &str,
+    response_preview: &str,
+) -> Result<(GenerationPlan, Option<String>), AppError> {
+    use crate::config::PlannerFailurePolicy;
+
+    if requires_multipart_contract {
+        return Err(AppError::AiProviderError(format!(
+            "Planner failed to decompose parts after {} attempt(s): {}. Last response: '{}'",
+            attempts, parse_err, response_preview
+        )));
+    }
+    let warning = match policy {
+        PlannerFailurePolicy::SilentSingleFallback => None,
+        PlannerFailurePolicy::WarnAndSingle => Some(format!(
+            "The planner could not produce a valid part plan after {} attempt(s) ({}); generating a single part instead. The result may not match a multi-part design — retry if it looks wrong.",
+            attempts, parse_err
+        )),
+        PlannerFailurePolicy::Fail => {
+            return Err(AppError::AiProviderError(format!(
+                "Planner failed to produce a valid plan after {} attempt(s): {}. Retry the request.",
+                attempts, parse_err
+            )));
+        }
+    };
+    Ok((
+        GenerationPlan {
+            mode: "single".to_string(),
+            description: None,
+            parts: vec![],
+            assembly_ops: vec![],
+        },
+        warning,
+    ))
+}
+
+/// Design plans tried in `run_design_plan_phase`, including the first.
+fn design_replan_attempts(config: &crate::config::AppConfig) -> usize {
+    config.max_design_replan_attempts.max(1) as usize
+}
+
+/// Planner responses requested before giving up on parsing a plan.
+fn planner_parse_attempts(config: &crate::config::AppConfig) -> usize {
+    config.max_planner_parse_attempts.max(1) as usize
+}
+
+/// Drop planner parts beyond `max_parts`; a cap of one falls back to a
+/// single-part plan. Returns a warning message when parts were dropped.
+fn cap_plan_parts(plan: &mut GenerationPlan, max_parts: Option<u32>) -> Option<String> {
+    let max_parts = max_parts? as usize;
+    if plan.parts.len() <= max_parts {
+        return None;
+    }
+    let dropped: Vec<String> = plan
+        .parts
+        .drain(max_parts..)
+        .map(|p| p.name)
+        .collect();
+    if plan.parts.len() < 2 {
+        plan.mode = "single".to_string();
+        plan.parts.clear();
+    }
+    let kept = &plan.parts;
+    plan.assembly_ops.retain(|op| {
+        [&op.target, &op.tool]
+            .iter()
+            .all(|name| kept.iter().any(|p| &p.name == *name))
+    });
+    Some(format!(
+        "Planner listed {} parts; keeping {} and dropping: {}",
+        max_parts + dropped.len(),
+        max_parts,
+        dropped.join(", ")
+    ))
+}
+
+/// ASCII spelling for common accented Latin letters; anything else non-ASCII
+/// becomes `_`.
+fn transliterate_char(ch: char) -> Option<&'static str> {
+    Some(match ch {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'ç' | 'ć' | 'č' => "c",
+        'Ç' | 'Ć' | 'Č' => "C",
+        'ď' | 'đ' | 'ð' => "d",
+        'Ď' | 'Đ' | 'Ð' => "D",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'ğ' => "g",
+        'Ğ' => "G",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' | 'Į' | 'İ' => "I",
+        'ł' => "l",
+        'Ł' => "L",
+        'ñ' | 'ń' | 'ň' => "n",
+        'Ñ' | 'Ń' | 'Ň' => "N",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ř' => "r",
+        'Ř' => "R",
+        'ś' | 'š' | 'ş' => "s",
+        'Ś' | 'Š' | 'Ş' => "S",
+        'ß' => "ss",
+        'ť' | 'ţ' => "t",
+        'Ť' | 'Ţ' => "T",
+        'þ' => "th",
+        'Þ' => "TH",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ý' | 'ÿ' => "y",
+        'Ý' | 'Ÿ' => "Y",
+        'ź' | 'ż' | 'ž' => "z",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        _ => return None,
+    })
+}
+
+/// Map a planner part name to a Python identifier so `part_{name}` stays
+/// valid: accented letters are transliterated, other invalid characters
+/// become `_`, and leading digits are stripped. Case is kept.
+fn sanitize_part_name(name: &str) -> String {
+    let mut ascii = String::with_capacity(name.len());
+    for ch in name.chars() {
+        if ch.is_ascii_alphanumeric() {
+            ascii.push(ch);
+        } else if let Some(latin) = transliterate_char(ch) {
+            ascii.push_str(latin);
+        } else {
+            ascii.push('_');
+        }
+    }
+
+    let mut sanitized = String::with_capacity(ascii.len());
+    for ch in ascii.trim_start_matches(|c: char| c.is_ascii_digit() || c == '_').chars() {
+        if ch == '_' && sanitized.ends_with('_') {
+            continue;
+        }
+        sanitized.push(ch);
+    }
+    let sanitized = sanitized.trim_end_matches('_');
+    if sanitized.is_empty() {
+        "part".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+/// Sanitize every part name in `plan`, suffixing duplicates created by the
+/// mapping. Returns `(original, sanitized)` for each renamed part.
+fn sanitize_plan_part_names(plan: &mut GenerationPlan) -> Vec<(String, String)> {
+    let mut renames = Vec::new();
+    let mut taken: Vec<String> = Vec::new();
+    for part in &mut plan.parts {
+        let base = sanitize_part_name(&part.name);
+        let mut candidate = base.clone();
+        let mut suffix = 2;
+        while taken.contains(&candidate) {
+            candidate = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        taken.push(candidate.clone());
+        if candidate != part.name {
+            let original = std::mem::replace(&mut part.name, candidate.clone());
+            renames.push((original, candidate));
+        }
+    }
+    for op in &mut plan.assembly_ops {
+        for name in [&mut op.target, &mut op.tool] {
+            if let Some((_, renamed)) = renames.iter().find(|(original, _)| original == name) {
+                *name = renamed.clone();
+            }
+        }
+    }
+    renames
+}
+
+/// Check that every assembly operation combines two different planned parts.
+fn validate_assembly_ops(plan: &GenerationPlan) -> Result<(), String> {
+    for op in &plan.assembly_ops {
+        for name in [&op.target, &op.tool] {
+            if !plan.parts.iter().any(|p| &p.name == name) {
+                return Err(format!(
+                    "Assembly operation references unknown part '{}'",
+                    name
+                ));
+            }
+        }
+        if op.target == op.tool {
+            return Err(format!(
+                "Assembly operation combines part '{}' with itself",
+                op.target
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Parse planner output with sanitized part names, also returning the renamed
+/// parts as `(original, sanitized)` pairs so the caller can warn about them.
+fn parse_plan_with_renames(
+    json_str: &str,
+) -> Result<(GenerationPlan, Vec<(String, String)>), String> {
+    let mut plan = parse_plan_json(json_str)?;
+    let renames = sanitize_plan_part_names(&mut plan);
+    validate_assembly_ops(&plan)?;
+    Ok((plan, renames))
+}
+
+/// Parse the planner JSON response.
+fn parse_plan_json(json_str: &str) -> Result<GenerationPlan, String> {
+    fn try_repair_json_fragment(input: &str) -> Option<String> {
+        let mut s = input.trim().to_string();
+        if s.is_empty() {
+            return None;
+        }
+
+        let mut quote_count = 0usize;
+        let mut escaped = false;
+        for ch in s.chars() {
+            if escaped {
+                escaped = false;
+                continue;
+            }
+            if ch == '\\' {
+                escaped = true;
+                continue;
+            }
+            if ch == '"' {
+                quote_count += 1;
+            }
+        }
+        if quote_count % 2 == 1 {
+            s.push('"');
+        }
+
+        let open_brackets = s.chars().filter(|c| *c == '[').count();
+        let close_brackets = s.chars().filter(|c| *c == ']').count();
+        if open_brackets > close_brackets {
+            s.push_str(&"]".repeat(open_brackets - close_brackets));
+        }
+
+        let open_braces = s.chars().filter(|c| *c == '{').count();
+        let close_braces = s.chars().filter(|c| *c == '}').count();
+        if open_braces > close_braces {
+            s.push_str(&"}".repeat(open_braces - close_braces));
+        }
+
+        Some(s)
+    }
+
+    // Try to extract JSON from the response (the AI might wrap it in markdown fences)
+    let cleaned = json_str
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+
+    match serde_json::from_str::<GenerationPlan>(cleaned) {
+        Ok(plan) => {
+            if plan.mode != "single" && plan.mode != "multi" {
+                return Err(format!("Invalid planner mode '{}'", plan.mode));
+            }
+            Ok(plan)
+        }
+        Err(first_err) => {
+            // Tertiary attempt for truncated JSON fragments (common EOF parser failures).
+            if let Some(repaired) = try_repair_json_fragment(cleaned) {
+                if let Ok(plan) = serde_json::from_str::<GenerationPlan>(&repaired) {
+                    if plan.mode != "single" && plan.mode != "multi" {
+                        return Err(format!("Invalid planner mode '{}'", plan.mode));
+                    }
+                    return Ok(plan);
+                }
+            }
+
+            // Secondary attempt: extract first outer JSON object if the model wrapped text around it.
+            if let (Some(start), Some(end)) = (cleaned.find('{'), cleaned.rfind('}')) {
+                if start < end {
+                    let candidate = &cleaned[start..=end];
+                    if let Ok(plan) = serde_json::from_str::<GenerationPlan>(candidate) {
+                        if plan.mode != "single" && plan.mode != "multi" {
+                            return Err(format!("Invalid planner mode '{}'", plan.mode));
+                        }
+                        return Ok(plan);
+                    }
+                    // The outer {..} failed (e.g. prose with scattered braces).
+                    // Try to repair the candidate in case it was truncated.
+                    if let Some(repaired) = try_repair_json_fragment(candidate) {
+                        if let Ok(plan) = serde_json::from_str::<GenerationPlan>(&repaired) {
+                            if plan.mode != "single" && plan.mode != "multi" {
+                                return Err(format!("Invalid planner mode '{}'", plan.mode));
+                            }
+                            return Ok(plan);
+                        }
+                    }
+                }
+            }
+
+            // Quaternary attempt: regex-locate a JSON object starting with `{"mode":`
+            // This handles prose responses where JSON is deeply embedded.
+            if let Some(m) = Regex::new(r#"\{"mode"\s*:\s*"(single|multi)""#)
+                .ok()
+                .and_then(|re| re.find(cleaned))
+            {
+                let json_start = m.start();
+                // Walk from json_start counting brace depth to find matching '}'
+                let bytes = cleaned[json_start..].as_bytes();
+                let mut depth: i32 = 0;
+                let mut in_string = false;
+                let mut escaped = false;
+                let mut json_end = None;
+                for (i, &b) in bytes.iter().enumerate() {
+                    if escaped {
+                        escaped = false;
+                        continue;
+                    }
+                    if b == b'\\' && in_string {
+                        escaped = true;
+                        continue;
+                    }
+                    if b == b'"' {
+                        in_string = !in_string;
+                        continue;
+                    }
+                    if in_string {
+                        continue;
+                    }
+                    if b == b'{' {
+                        depth += 1;
+                    } else if b == b'}' {
+                        depth -= 1;
+                        if depth == 0 {
+                            json_end = Some(json_start + i);
+                            break;
+                        }
+                    }
+                }
+                if let Some(end_pos) = json_end {
+                    let candidate = &cleaned[json_start..=end_pos];
+                    if let Ok(plan) = serde_json::from_str::<GenerationPlan>(candidate) {
+                        if plan.mode == "single" || plan.mode == "multi" {
+                            return Ok(plan);
+                        }
+                    }
+                }
+            }
+
+            Err(format!("Planner JSON parse failed: {}", first_err))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Part candidates
+// ---------------------------------------------------------------------------
+
+/// All stored attempts for one part of a run, with their acceptance outcome.
+#[tauri::command]
+pub fn get_part_candidates(
+    run_id: String,
+    part_index: usize,
+    state: State<'_, AppState>,
+) -> Result<Vec<run_state::PartCandidate>, AppError> {
+    let mut store = state
+        .run_store
+        .lock()
+        .map_err(|e| AppError::ConfigError(format!("Failed to lock run store: {}", e)))?;
+    store.prune_expired(telemetry::now_ms());
+    store.candidates(&run_id, part_index)
+}
+
+/// Swap a stored candidate into a run's part set, then re-run only assembly
+/// and validation. Emits fresh `FinalCode`/`Done` events.
+#[tauri::command]
+pub async fn use_part_candidate(
+    run_id: String,
+    part_index: usize,
+    candidate_idx: usize,
+    on_event: Channel<EventEnvelope>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let on_event = EventSink::register(&state.run_events, on_event);
+    let config = state.config.lock().unwrap().clone();
+    let cq_version = state.build123d_version.lock().unwrap().clone();
+    let part_materials = state.part_materials.lock().unwrap().clone();
+    let part_colors = state.part_colors.lock().unwrap().clone();
+    let run = {
+        let mut store = state
+            .run_store
+            .lock()
+            .map_err(|e| AppError::ConfigError(format!("Failed to lock run store: {}", e)))?;
+        store.prune_expired(telemetry::now_ms());
+        store.select_candidate(&run_id, part_index, candidate_idx)?
+    };
+
+    let part_name = run
+        .plan
+        .parts
+        .get(part_index)
+        .map(|p| p.name.clone())
+        .unwrap_or_default();
+    let _ = on_event.send(MultiPartEvent::AssemblyStatus {
+        message: format!(
+            "Re-assembling with candidate {} for part '{}'...",
+            candidate_idx + 1,
+            part_name
+        ),
+    });
+
+    let venv_path = state.venv_path.lock().unwrap().clone();
+    reassemble_run(
+        &run,
+        &config,
+        cq_version.as_deref(),
+        &part_materials,
+        &part_colors,
+        venv_path,
+        &on_event,
+        &state.run_store,
+    )
+    .await
+    .map(|reassembly| reassembly.code)
+}
+
+/// Resume a failed multi-part generation from its saved accepted parts,
+/// re-running only assembly and validation. Part generation is skipped.
+#[tauri::command]
+pub async fn resume_generation(
+    generation_id: String,
+    on_event: Channel<EventEnvelope>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let on_event = EventSink::register(&state.run_events, on_event);
+    let config = state.config.lock().unwrap().clone();
+    let cq_version = state.build123d_version.lock().unwrap().clone();
+    let part_materials = state.part_materials.lock().unwrap().clone();
+    let part_colors = state.part_colors.lock().unwrap().clone();
+    let venv_path = state.venv_path.lock().unwrap().clone();
+    let run = load_resumable_run(&state.run_store, &generation_id)?;
+    resume_from_run(
+        run,
+        &config,
+        cq_version.as_deref(),
+        &part_materials,
+        &part_colors,
+        venv_path,
+        &on_event,
+        &state.run_store,
+    )
+    .await
+}
+
+/// Prefer the in-memory run (it keeps geometry reports), else the saved state.
+pub(crate) fn load_resumable_run(
+    run_store: &Mutex<run_state::RunStore>,
+    generation_id: &str,
+) -> Result<run_state::RunRecord, AppError> {
+    let in_memory = run_store.lock().ok().and_then(|mut store| {
+        store.prune_expired(telemetry::now_ms());
+        store.run(generation_id).ok().cloned()
+    });
+    match in_memory {
+        Some(run) if !run.selected_parts().is_empty() => Ok(run),
+        _ => Ok(run_state::load_resumable(generation_id)?.into_run_record()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn resume_from_run(
+    run: run_state::RunRecord,
+    config: &crate::config::AppConfig,
+    cq_version: Option<&str>,
+    part_materials: &HashMap<String, String>,
+    part_colors: &HashMap<String, PartColor>,
+    venv_path: Option<std::path::PathBuf>,
+    on_event: &EventSink,
+    run_store: &Mutex<run_state::RunStore>,
+) -> Result<String, AppError> {
+    let _ = on_event.send(MultiPartEvent::AssemblyStatus {
+        message: format!(
+            "Resuming from {}/{} accepted parts...",
+            run.selected_parts().len(),
+            run.plan.parts.len()
+        ),
+    });
+    let reassembly = reassemble_run(
+        &run,
+        config,
+        cq_version,
+        part_materials,
+        part_colors,
+        venv_path,
+        on_event,
+        run_store,
+    )
+    .await?;
+    // A failed re-assembly stays resumable.
+    if reassembly.success {
+        clear_resumable(&run.run_id, config);
+    }
+    Ok(reassembly.code)
+}
+
+/// Continue a run parked by `pause_before_assembly`: re-roll and exclude the
+/// given parts, then assemble and validate. Emits fresh `FinalCode`/`Done`.
+#[tauri::command]
+pub async fn approve_assembly(
+    run_id: String,
+    exclude: Option<Vec<usize>>,
+    reroll: Option<Vec<usize>>,
+    on_event: Channel<EventEnvelope>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let on_event = EventSink::register(&state.run_events, on_event);
+    let config = state.config.lock().unwrap().clone();
+    let cq_version = state.build123d_version.lock().unwrap().clone();
+    let part_materials = state.part_materials.lock().unwrap().clone();
+    let part_colors = state.part_colors.lock().unwrap().clone();
+    let venv_path = state.venv_path.lock().unwrap().clone();
+    approve_parked_run(
+        &state.run_store,
+        &run_id,
+        exclude.unwrap_or_default(),
+        reroll.unwrap_or_default(),
+        &config,
+        cq_version.as_deref(),
+        &part_materials,
+        &part_colors,
+        venv_path,
+        &on_event,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn approve_parked_run(
+    run_store: &Mutex<run_state::RunStore>,
+    run_id: &str,
+    exclude: Vec<usize>,
+    reroll: Vec<usize>,
+    config: &crate::config::AppConfig,
+    cq_version: Option<&str>,
+    part_materials: &HashMap<String, String>,
+    part_colors: &HashMap<String, PartColor>,
+    venv_path: Option<std::path::PathBuf>,
+    on_event: &EventSink,
+) -> Result<String, AppError> {
+    let (mut run, parked_elapsed_ms) = {
+        let mut store = run_store
+            .lock()
+            .map_err(|e| AppError::ConfigError(format!("Failed to lock run store: {}", e)))?;
+        store.prune_expired(telemetry::now_ms());
+        // Checked before unparking so a refused approval can be corrected.
+        check_assembly_exclusions(store.run(run_id)?, &exclude, config)?;
+        store.unpark(run_id)?
+    };
+    note_expired_parked_runs(run_store, config);
+
+    let mut total_usage = TokenUsage::default();
+    for &part_index in &reroll {
+        let Some(part_spec) = run.plan.parts.get(part_index).cloned() else {
+            continue;
+        };
+        let kept_siblings = kept_sibling_parts(&run, &reroll);
+        let sibling_summary =
+            build_sibling_dimensions_summary(&run.plan.parts, &part_spec.name, &kept_siblings);
+        let regenerated = regenerate_part(
+            part_index,
+            &part_spec,
+            &run.plan_text,
+            &run.user_request,
+            config,
+            cq_version,
+            venv_path.clone(),
+            on_event,
+            &mut total_usage,
+            part_colors,
+            &sibling_summary,
+        )
+        .await;
+        match regenerated {
+            Ok(Some(part)) => {
+                let candidate = run_state::PartCandidate {
+                    accepted: part.preview_error.is_none(),
+                    findings: part.preview_error.into_iter().collect(),
+                    code: part.code,
+                    stl_base64: part.stl_base64,
+                    created_at_ms: telemetry::now_ms(),
+                    post_geometry_report: None,
+                };
+                if let Ok(mut store) = run_store.lock() {
+                    store.record_candidate(
+                        run_id,
+                        part_index,
+                        candidate,
+                        config.max_part_candidates,
+                        config.part_candidate_store_max_mb as usize * 1024 * 1024,
+                    );
+                    if let Ok(updated) = store.run(run_id) {
+                        run.parts = updated.parts.clone();
+                    }
+                }
+            }
+            Ok(None) | Err(_) => {
+                let _ = on_event.send(MultiPartEvent::Warning {
+                    code: "assembly_reroll_failed".to_string(),
+                    message: format!(
+                        "Re-roll of part '{}' failed; keeping its previous candidate.",
+                        part_spec.name
+                    ),
+                });
+            }
+        }
+    }
+    run.exclude_parts(&exclude)?;
+    if total_usage.total() > 0 {
+        emit_usage(
+            on_event,
+            "total",
+            &total_usage,
+            &config.ai_provider,
+            &config.model,
+        );
+    }
+
+    let _ = on_event.send(MultiPartEvent::AssemblyStatus {
+        message: format!(
+            "Assembling {}/{} approved parts...",
+            run.selected_parts().len(),
+            run.plan.parts.len()
+        ),
+    });
+    // The clock stopped while the run was parked; only the remainder is left.
+    let remaining_ms =
+        (effective_generation_timeout_seconds(config) * 1000).saturating_sub(parked_elapsed_ms);
+    let reassembly = match timeout(
+        Duration::from_millis(remaining_ms),
+        reassemble_run(
+            &run,
+            config,
+            cq_version,
+            part_materials,
+            part_colors,
+            venv_path,
+            on_event,
+            run_store,
+        ),
+    )
+    .await
+    {
+        Ok(reassembly) => reassembly?,
+        Err(_) => {
+            let msg = format!(
+                "Generation runtime exceeded {} seconds (effective timeout; increase timeout in Settings for complex assemblies)",
+                effective_generation_timeout_seconds(config)
+            );
+            let _ = on_event.send(MultiPartEvent::Done {
+                success: false,
+                error: Some(msg.clone()),
+                validated: false,
+            });
+            return Err(AppError::AiProviderError(msg));
+        }
+    };
+    note_assembly_approval(run_id, &exclude, &reroll, reassembly.success, config);
+    if reassembly.success {
+        clear_resumable(run_id, config);
+    }
+    Ok(reassembly.code)
+}
+
+/// Selected parts that are kept as they are; the ones being re-rolled (plan
+/// indices in `reroll`) may change.
+fn kept_sibling_parts(run: &run_state::RunRecord, reroll: &[usize]) -> Vec<(String, String)> {
+    let rerolled: Vec<&str> = reroll
+        .iter()
+        .filter_map(|&idx| run.plan.parts.get(idx))
+        .map(|spec| spec.name.as_str())
+        .collect();
+    run.selected_parts()
+        .into_iter()
+        .filter(|(name, _, _)| !rerolled.contains(&name.as_str()))
+        .map(|(name, code, _)| (name, code))
+        .collect()
+}
+
+/// Reject exclusions the multipart contract forbids or that name no planned part.
+fn check_assembly_exclusions(
+    run: &run_state::RunRecord,
+    exclude: &[usize],
+    config: &crate::config::AppConfig,
+) -> Result<(), AppError> {
+    if let Some(index) = exclude.iter().find(|&&i| i >= run.plan.parts.len()) {
+        return Err(AppError::ConfigError(format!(
+            "Run '{}' has no part at index {}",
+            run.run_id, index
+        )));
+    }
+    if !exclude.is_empty()
+        && config.quality_gates_strict
+        && request_requires_multipart_contract(&run.user_request, &run.plan_text)
+    {
+        return Err(AppError::ConfigError(
+            "The strict multipart contract requires every planned part; parts cannot be excluded"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Write a telemetry note for each parked run the TTL dropped unapproved.
+fn note_expired_parked_runs(
+    run_store: &Mutex<run_state::RunStore>,
+    config: &crate::config::AppConfig,
+) {
+    let expired = match run_store.lock() {
+        Ok(mut store) => store.take_expired_parked(),
+        Err(_) => return,
+    };
+    if !telemetry::trace_writes_allowed(config) {
+        return;
+    }
+    for run_id in expired {
+        let note = telemetry::TelemetryNoteV1 {
+            version: 1,
+            timestamp_ms: telemetry::now_ms(),
+            run_id,
+            kind: "parked_run_expired".to_string(),
+            message: "Run awaiting assembly approval expired before it was approved".to_string(),
+        };
+        if let Err(e) = telemetry::write_note(&note) {
+            eprintln!("Failed to write telemetry note: {}", e);
+        }
+    }
+}
+
+/// Telemetry note recording how a parked run was approved and whether the
+/// assembly that followed succeeded.
+fn assembly_approval_note(
+    run_id: &str,
+    exclude: &[usize],
+    reroll: &[usize],
+    success: bool,
+) -> telemetry::TelemetryNoteV1 {
+    telemetry::TelemetryNoteV1 {
+        version: 1,
+        timestamp_ms: telemetry::now_ms(),
+        run_id: run_id.to_string(),
+        kind: "assembly_approved".to_string(),
+        message: format!(
+            "Assembly approved (excluded parts {:?}, re-rolled parts {:?}); assembly {}",
+            exclude,
+            reroll,
+            if success { "succeeded" } else { "failed" }
+        ),
+    }
+}
+
+fn note_assembly_approval(
+    run_id: &str,
+    exclude: &[usize],
+    reroll: &[usize],
+    success: bool,
+    config: &crate::config::AppConfig,
+) {
+    if !telemetry::trace_writes_allowed(config) {
+        return;
+    }
+    let note = assembly_approval_note(run_id, exclude, reroll, success);
+    if let Err(e) = telemetry::write_note(&note) {
+        eprintln!("Failed to write telemetry note: {}", e);
+    }
+}
+
+fn clear_resumable(run_id: &str, config: &crate::config::AppConfig) {
+    if !config.allows_app_data_writes() {
+        return;
+    }
+    if let Err(e) = run_state::remove_resumable(run_id) {
+        eprintln!("Failed to clear resumable state: {}", e);
+    }
+}
+
+/// Persist the accepted parts of a run so `resume_generation` can re-assemble
+/// them if the rest of the pipeline fails.
+fn persist_resumable(
+    run_store: &Mutex<run_state::RunStore>,
+    run_id: &str,
+    config: &crate::config::AppConfig,
+) {
+    if !config.allows_app_data_writes() {
+        return;
+    }
+    let snapshot = run_store.lock().ok().and_then(|store| {
+        store
+            .run(run_id)
+            .ok()
+            .and_then(run_state::ResumableGeneration::from_run)
+    });
+    if let Some(generation) = snapshot {
+        if let Err(e) = run_state::save_resumable(&generation) {
+            eprintln!("Failed to save resumable state: {}", e);
+        }
+    }
+}
+
+/// Final code of a re-assembled run and the `success` its `Done` reported.
+struct Reassembly {
+    code: String,
+    success: bool,
+}
+
+/// Lay out, assemble and validate the selected parts of a stored run, then
+/// emit `FinalCode`/`Done`.
+#[allow(clippy::too_many_arguments)]
+async fn reassemble_run(
+    run: &run_state::RunRecord,
+    config: &crate::config::AppConfig,
+    cq_version: Option<&str>,
+    part_materials: &HashMap<String, String>,
+    part_colors: &HashMap<String, PartColor>,
+    venv_path: Option<std::path::PathBuf>,
+    on_event: &EventSink,
+    run_store: &Mutex<run_state::RunStore>,
+) -> Result<Reassembly, AppError> {
+    let part_reports = run.selected_reports();
+    let successful_parts = layout_part_positions(
+        run.selected_parts(),
+        &run.plan,
+        &part_reports,
+        config,
+        on_event,
+    );
+    let part_kinematics = classify_accepted_parts(&run.plan, &successful_parts, on_event);
+    let code = match assemble_parts(
+        &successful_parts,
+        &part_kinematics,
+        &run.plan.assembly_ops,
+        part_colors,
+    ) {
+        Ok(code) => code,
+        Err(e) => {
+            let _ = on_event.send(MultiPartEvent::Done {
+                success: false,
+                error: Some(e.clone()),
+                validated: false,
+            });
+            return Err(AppError::AiProviderError(e));
+        }
+    };
+
+    let strict_multipart_required = config.quality_gates_strict
+        && request_requires_multipart_contract(&run.user_request, &run.plan_text);
+    let missing_parts_error = if strict_multipart_required
+        && successful_parts.len() != run.plan.parts.len()
+    {
+        Some(format!(
+            "Only {}/{} parts accepted; strict multipart contract requires all parts.",
+            successful_parts.len(),
+            run.plan.parts.len()
+        ))
+    } else {
+        None
+    };
+
+    let Some(venv_dir) = venv_path else {
+        let _ = on_event.send(MultiPartEvent::FinalCode {
+            code: code.clone(),
+            stl_base64: None,
+            geometry_hash: None,
+        });
+        let success = missing_parts_error.is_none();
+        let _ = on_event.send(MultiPartEvent::Done {
+            success,
+            error: missing_parts_error,
+            validated: false,
+        });
+        return Ok(Reassembly { code, success });
+    };
+    let ctx = executor::ExecutionContext {
+        venv_dir,
+        runner_script: super::find_python_script("runner.py")?,
+        config: config.clone(),
+    };
+    let mut system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
+        prompts::build_finetuned_system_prompt()
+    } else {
+        prompts::build_compact_system_prompt_for_preset(
+            config.agent_rules_preset.as_deref(),
+            cq_version,
+        )
+    };
+    prompts::append_house_style(
+        &mut system_prompt,
+        config.custom_system_prompt_suffix.as_deref(),
+    );
+
+    let assembly_bbox_hint =
+        build_assembly_bbox_hint(&run.plan, &run.user_request, &config.semantic_bbox_mode);
+    let (validation_result, _) = validate_assembled_code(
+        code,
+        &run.plan.parts,
+        &ctx,
+        &system_prompt,
+        assembly_bbox_hint.as_deref(),
+        on_event,
+        run_store,
+    )
+    .await?;
+
+    if validation_result.retry_usage.total() > 0 {
+        emit_usage(
+            on_event,
+            "validation",
+            &validation_result.retry_usage,
+            &config.ai_provider,
+            &config.model,
+        );
+    }
+
+    let _ = on_event.send(MultiPartEvent::FinalCode {
+        code: validation_result.code.clone(),
+        stl_base64: validation_result.stl_base64.clone(),
+        geometry_hash: executor::geometry_hash_base64(validation_result.stl_base64.as_deref()),
+    });
+
+    if validation_result.success {
+        if let Some(report) = assembly_mass_properties(
+            &successful_parts,
+            &run.plan,
+            &part_reports,
+            part_materials,
+            &run.user_request,
+            config,
+        ) {
+            let _ = on_event.send(MultiPartEvent::MassPropertiesReport { report });
+        }
+        emit_print_estimate(
+            on_event,
+            validation_result.post_geometry_report.as_ref(),
+            &run.user_request,
+            config,
+        );
+    }
+
+    let contract_issues = assembly_contract_issues(
+        &validation_result.code,
+        &successful_parts,
+        &run.plan.assembly_ops,
+    );
+    let done_error = if config.quality_gates_strict && !contract_issues.is_empty() {
+        Some(format!(
+            "Validation retry produced code that breaks multipart assembly contract: {}",
+            contract_issues.join(", ")
+        ))
+    } else {
+        missing_parts_error.or(validation_result.error.clone())
+    };
+    let success = validation_result.success && done_error.is_none();
+    let _ = on_event.send(MultiPartEvent::Done {
+        success,
+        error: done_error,
+        validated: true,
+    });
+
+    Ok(Reassembly {
+        code: validation_result.code,
+        success,
+    })
+}
+
+/// Execution context for backend validation, or `None` if Python is not set up.
+pub(crate) fn execution_context(
+    state: &AppState,
+    config: &crate::config::AppConfig,
+) -> Option<executor::ExecutionContext> {
+    let venv_dir = state.venv_path.lock().unwrap().clone()?;
+    let runner_script = super::find_python_script("runner.py").ok()?;
+    Some(executor::ExecutionContext {
+        venv_dir,
+        runner_script,
+        config: config.clone(),
+    })
+}
+
+/// Single-mode tail shared by generation and chat: review the code in a
+/// streamed response, validate it when an execution context exists, and
+/// emit `ReviewComplete`/`FinalCode`/`TokenUsage`/`Done`.
+#[allow(clippy::too_many_arguments)]
+async fn finish_single_response(
+    full_response: String,
+    user_request: &str,
+    plan_text: Option<&str>,
+    system_prompt: &str,
+    config: &crate::config::AppConfig,
+    execution_ctx: Option<&executor::ExecutionContext>,
+    on_event: &EventSink,
+    total_usage: &mut TokenUsage,
+    provider_id: &str,
+    model_id: &str,
+    run_store: &Mutex<run_state::RunStore>,
+) -> Result<PipelineOutcome, AppError> {
+    let mut final_code = extract_code_from_response(&full_response);
+    let mut final_response = full_response.clone();
+
+    if config.enable_code_review {
+        if let Some(ref code) = final_code {
+            let _ = on_event.send(MultiPartEvent::ReviewStatus {
+                message: "Reviewing generated code...".to_string(),
+            });
+
+            on_event.ensure_connected()?;
+            let review_provider = create_provider(config)?;
+            match review::review_code(
+                review_provider,
+                user_request,
+                code,
+                plan_text,
+                &review::ReviewOptions::from_config(config),
+            )
+            .await
+            {
+                Ok((result, review_usage)) => {
+                    if let Some(ref u) = review_usage {
+                        total_usage.add(u);
+                        emit_usage(on_event, "review", u, provider_id, model_id);
+                    }
+                    let _ = on_event.send(MultiPartEvent::ReviewComplete {
+                        was_modified: result.was_modified,
+                        explanation: result.explanation.clone(),
+                        findings: result.findings.clone(),
+                        ai_reviewed: result.ai_reviewed,
+                    });
+                    if result.was_modified {
+                        final_response = full_response.replace(code, &result.code);
+                        final_code = Some(result.code);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Code review failed: {}", e);
+                }
+            }
+        }
+    }
+
+    // Backend validation
+    if let (Some(code), Some(ctx)) = (&final_code, execution_ctx) {
+        let on_validation_event =
+            |evt: executor::ValidationEvent| forward_validation_event(on_event, evt);
+
+        let validation_result = executor::validate_and_retry(
+            code.clone(),
+            ctx,
+            system_prompt,
+            Some(user_request),
+            &on_validation_event,
+        )
+        .await?;
+        validation_history::record(
+            run_store,
+            &ctx.config,
+            on_event.run_id(),
+            None,
+            &validation_result.attempt_history,
+        );
+        let model_escalations = validation_escalations(config, "single_part", &validation_result);
+
+        if validation_result.retry_usage.total() > 0 {
+            total_usage.add(&validation_result.retry_usage);
+            emit_usage(
+                on_event,
+                "validation",
+                &validation_result.retry_usage,
+                provider_id,
+                model_id,
+            );
+        }
+
+        let geometry_hash = executor::geometry_hash_base64(validation_result.stl_base64.as_deref());
+        let _ = on_event.send(MultiPartEvent::FinalCode {
+            code: validation_result.code.clone(),
+            stl_base64: validation_result.stl_base64.clone(),
+            geometry_hash: geometry_hash.clone(),
+        });
+        if validation_result.success {
+            emit_print_estimate(
+                on_event,
+                validation_result.post_geometry_report.as_ref(),
+                user_request,
+                config,
+            );
+        }
+
+        if validation_result.code != *code {
+            final_response = final_response.replace(code, &validation_result.code);
+        }
+
+        if total_usage.total() > 0 {
+            emit_usage(on_event, "total", total_usage, provider_id, model_id);
+        }
+
+        let _ = on_event.send(MultiPartEvent::Done {
+            success: validation_result.success,
+            error: validation_result.error.clone(),
+            validated: true,
+        });
+
+        return Ok(PipelineOutcome {
+            response: final_response,
+            final_code: Some(validation_result.code),
+            success: validation_result.success,
+            validated: true,
+            error: validation_result.error,
+            validation_attempts: Some(validation_result.attempts),
+            static_findings: validation_result.static_findings,
+            post_check_soft_failed: validation_result.post_check_warning.is_some(),
+            post_check_soft_fail_reason: validation_result.post_check_warning,
+            part_acceptance_rate: None,
+            assembly_success_rate: None,
+            partial_preview_shown: validation_result.stl_base64.is_some(),
+            empty_viewport_after_generation: validation_result.stl_base64.is_none(),
+            retry_ladder_stage_reached: validation_result.retry_ladder_stage_reached,
+            model_escalations,
+            repair_example_uses: validation_result.repair_example_uses.clone(),
+            geometry_hash,
+            failure_signatures: vec![],
+            awaiting_assembly_approval: false,
+            failure_localization: None,
+            injected_mechanism_ids: Vec::new(),
+        });
+    }
+
+    // No execution context — emit as-is
+    if let Some(ref code) = final_code {
+        let _ = on_event.send(MultiPartEvent::FinalCode {
+            code: code.clone(),
+            stl_base64: None,
+            geometry_hash: None,
+        });
+    }
+
+    if total_usage.total() > 0 {
+        emit_usage(on_event, "total", total_usage, provider_id, model_id);
+    }
+
+    // Guard: report failure if no code was extracted from the AI response
+    let has_code = final_code.is_some();
+    let no_code_error = if has_code {
+        None
+    } else {
+        Some("No code block extracted from AI response".to_string())
+    };
+
+    let _ = on_event.send(MultiPartEvent::Done {
+        success: has_code,
+        error: no_code_error.clone(),
+        validated: false,
+    });
+
+    Ok(PipelineOutcome {
+        response: final_response,
+        final_code,
+        success: has_code,
+        validated: false,
+        error: no_code_error,
+        validation_attempts: None,
+        static_findings: vec![],
+        post_check_soft_failed: false,
+        post_check_soft_fail_reason: None,
+        part_acceptance_rate: None,
+        assembly_success_rate: None,
+        partial_preview_shown: false,
+        empty_viewport_after_generation: !has_code,
+        retry_ladder_stage_reached: None,
+        model_escalations: vec![],
+        repair_example_uses: vec![],
+        geometry_hash: None,
+        failure_signatures: if has_code {
+            vec![]
+        } else {
+            vec!["no_code_extracted".to_string()]
+        },
+        awaiting_assembly_approval: false,
+        failure_localization: None,
+        injected_mechanism_ids: Vec::new(),
+    })
+}
+
+/// Event-channel tail of a chat reply. Replies with code go through the same
+/// review and validation as single-part generation; conversational replies
+/// only close the stream. Returns the final response text.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn finish_chat_reply(
+    full_response: String,
+    user_request: &str,
+    system_prompt: &str,
+    config: &crate::config::AppConfig,
+    execution_ctx: Option<&executor::ExecutionContext>,
+    on_event: &EventSink,
+    stream_usage: Option<TokenUsage>,
+    run_store: &Mutex<run_state::RunStore>,
+) -> Result<String, AppError> {
+    let provider_id = config.ai_provider.clone();
+    let model_id = config.model.clone();
+    let mut total_usage = TokenUsage::default();
+    if let Some(ref u) = stream_usage {
+        total_usage.add(u);
+        emit_usage(on_event, "generate", u, &provider_id, &model_id);
+    }
+    let _ = on_event.send(MultiPartEvent::SingleDone {
+        full_response: full_response.clone(),
+    });
+
+    if extract_code_from_response(&full_response).is_none() {
+        let _ = on_event.send(MultiPartEvent::Done {
+            success: true,
+            error: None,
+            validated: false,
+        });
+        return Ok(full_response);
+    }
+
+    let outcome = finish_single_response(
+        full_response,
+        user_request,
+        None,
+        system_prompt,
+        config,
+        execution_ctx,
+        on_event,
+        &mut total_usage,
+        &provider_id,
+        &model_id,
+        run_store,
+    )
+    .await?;
+    Ok(outcome.response)
+}
+
+/// Extract a Python code block from an AI response.
+fn extract_code_from_response(response: &str) -> Option<String> {
+    crate::agent::extract::extract_code(response)
+}
+
+struct PartAcceptanceArtifact {
+    code: String,
+    stl_base64: Option<String>,
+    post_geometry_report: Option<executor::PostGeometryValidationReport>,
+    post_check_warning: Option<String>,
+    semantic_findings: Vec<String>,
+    retry_ladder_stage_reached: Option<u32>,
+}
+
+/// Why a part failed acceptance. `artifact` is set when the code executed but
+/// a later gate (the semantic contract) rejected it, so its STL is still usable.
+struct PartRejection {
+    error: String,
+    artifact: Option<PartAcceptanceArtifact>,
+}
+
+impl PartRejection {
+    fn new(error: String) -> Self {
+        Self {
+            error,
+            artifact: None,
+        }
+    }
+}
+
+/// Validate and repair one part on its own. With `history_run`, the attempt
+/// chain is kept in that run store under the run id and the part's name, for
+/// `get_validation_history`.
+#[allow(clippy::too_many_arguments)]
+async fn evaluate_part_acceptance(
+    part_code: &str,
+    ctx: &executor::ExecutionContext,
+    system_prompt: &str,
+    part_request: &str,
+    part_name: &str,
+    semantic_contract: Option<&semantic_validate::SemanticPartContract>,
+    assembly_envelope: Option<[f64; 3]>,
+    history_run: Option<(&Mutex<run_state::RunStore>, &str)>,
+) -> Result<PartAcceptanceArtifact, PartRejection> {
+    let no_event = |_evt: executor::ValidationEvent| {};
+    let bbox_hint_owned = build_part_bbox_hint(
+        semantic_contract,
+        part_request,
+        &ctx.config.semantic_bbox_mode,
+    );
+    let validation = executor::validate_and_retry(
+        part_code.to_string(),
+        ctx,
+        system_prompt,
+        bbox_hint_owned.as_deref(),
+        &no_event,
+    )
+    .await
+    .map_err(|e| PartRejection::new(format!("part acceptance validation error: {}", e)))?;
+    if let Some((run_store, run_id)) = history_run {
+        validation_history::record(
+            run_store,
+            &ctx.config,
+            run_id,
+            Some(part_name),
+            &validation.attempt_history,
+        );
+    }
+
+    if !validation.success {
+        return Err(PartRejection::new(
+            validation
+                .error
+                .unwrap_or_else(|| "part validation failed".to_string()),
+        ));
+    }
+
+    let mut semantic_findings = Vec::new();
+    if ctx.config.quality_gates_strict && ctx.config.semantic_contract_strict {
+        if let Some(report) = validation.post_geometry_report.as_ref() {
+            let contract = semantic_contract
+                .cloned()
+                .unwrap_or_else(|| semantic_validate::build_default_contract(part_name, part_request));
+            let semantic =
+                semantic_validate::validate_part_semantics(&contract, report, &validation.code);
+            semantic_findings = semantic.findings.clone();
+            if !semantic.passed {
+                return Err(PartRejection {
+                    error: format!(
+                        "semantic validation failed: {}",
+                        semantic.findings.join("; ")
+                    ),
+                    artifact: Some(PartAcceptanceArtifact {
+                        code: validation.code,
+                        stl_base64: validation.stl_base64,
+                        post_geometry_report: validation.post_geometry_report,
+                        post_check_warning: validation.post_check_warning,
+                        semantic_findings,
+                        retry_ladder_stage_reached: validation.retry_ladder_stage_reached,
+                    }),
+                });
+            }
+        }
+        // When report is None (post-check soft-fail), semantic validation is
+        // skipped — the part still passes on its execution success.
+        // The post_check_warning field already carries the warning for logging/UI.
+    }
+
+    // A part larger than the whole assembly is rejected under strict quality
+    // gates and only flagged otherwise.
+    let mut envelope_rejection = None;
+    if ctx.config.assembly_envelope_check {
+        if let (Some(envelope), Some(report)) =
+            (assembly_envelope, validation.post_geometry_report.as_ref())
+        {
+            if let Some(finding) = semantic_validate::check_assembly_envelope(
+                report,
+                envelope,
+                ctx.config.assembly_envelope_margin_pct,
+            ) {
+                if ctx.config.quality_gates_strict {
+                    envelope_rejection =
+                        Some(format!("assembly envelope check failed: {}", finding));
+                }
+                semantic_findings.push(finding);
+            }
+        }
+    }
+
+    let artifact = PartAcceptanceArtifact {
+        code: validation.code,
+        stl_base64: validation.stl_base64,
+        post_geometry_report: validation.post_geometry_report,
+        post_check_warning: validation.post_check_warning,
+        semantic_findings,
+        retry_ladder_stage_reached: validation.retry_ladder_stage_reached,
+    };
+    match envelope_rejection {
+        Some(error) => Err(PartRejection {
+            error,
+            artifact: Some(artifact),
+        }),
+        None => Ok(artifact),
+    }
+}
+
+/// `PartStlReady` payload: the part STL, decimated to `preview_max_triangles`
+/// when it is larger. Falls back to the full mesh if decimation fails.
+/// Decimation runs Python, so it goes to the blocking pool.
+async fn preview_stl_base64(stl_base64: String, ctx: &executor::ExecutionContext) -> String {
+    use base64::Engine;
+
+    let budget = ctx.config.preview_max_triangles;
+    if budget == 0 {
+        return stl_base64;
+    }
+    let engine = base64::engine::general_purpose::STANDARD;
+    let Ok(stl) = engine.decode(&stl_base64) else {
+        return stl_base64;
+    };
+    if executor::stl_triangle_count(&stl) <= budget as u64 {
+        return stl_base64;
+    }
+    let ctx = ctx.clone();
+    let decimated =
+        tokio::task::spawn_blocking(move || executor::decimate_preview_stl(&stl, budget, &ctx))
+            .await
+            .unwrap_or_else(|e| Err(format!("decimation task failed: {}", e)));
+    match decimated {
+        Ok(preview) => engine.encode(preview),
+        Err(e) => {
+            eprintln!("[multipart] Preview decimation skipped: {}", e);
+            stl_base64
+        }
+    }
+}
+
+async fn build_part_preview_stl_with_repair(
+    part_code: &str,
+    ctx: &executor::ExecutionContext,
+    system_prompt: &str,
+    part_request: &str,
+    part_name: &str,
+    semantic_contract: Option<&semantic_validate::SemanticPartContract>,
+) -> Result<String, String> {
+    match evaluate_part_acceptance(
+        part_code,
+        ctx,
+        system_prompt,
+        part_request,
+        part_name,
+        semantic_contract,
+        None,
+        None,
+    )
+    .await
+    {
+        Ok(artifact) => match artifact.stl_base64 {
+            Some(stl) => Ok(preview_stl_base64(stl, ctx).await),
+            None => Err("validated part preview is missing STL output".to_string()),
+        },
+        Err(rejection) => Err(rejection.error),
+    }
+}
+
+/// Keep a part attempt in the run store, accepted or not, so the user can
+/// pick it later with `use_part_candidate`.
+fn record_part_candidate(
+    run_store: &Mutex<run_state::RunStore>,
+    run_id: &str,
+    part_index: usize,
+    raw_code: &str,
+    result: &Result<PartAcceptanceArtifact, PartRejection>,
+    config: &crate::config::AppConfig,
+) {
+    let candidate = match result {
+        Ok(artifact) => run_state::PartCandidate {
+            code: artifact.code.clone(),
+            stl_base64: artifact.stl_base64.clone(),
+            accepted: true,
+            findings: artifact.semantic_findings.clone(),
+            created_at_ms: telemetry::now_ms(),
+            post_geometry_report: artifact.post_geometry_report.clone(),
+        },
+        Err(rejection) => {
+            let artifact = rejection.artifact.as_ref();
+            run_state::PartCandidate {
+                code: artifact.map_or_else(|| raw_code.to_string(), |a| a.code.clone()),
+                stl_base64: artifact.and_then(|a| a.stl_base64.clone()),
+                accepted: false,
+                findings: match artifact {
+                    Some(a) if !a.semantic_findings.is_empty() => a.semantic_findings.clone(),
+                    _ => vec![rejection.error.clone()],
+                },
+                created_at_ms: telemetry::now_ms(),
+                post_geometry_report: artifact.and_then(|a| a.post_geometry_report.clone()),
+            }
+        }
+    };
+    store_part_candidate(run_store, run_id, part_index, candidate, config);
+}
+
+fn store_part_candidate(
+    run_store: &Mutex<run_state::RunStore>,
+    run_id: &str,
+    part_index: usize,
+    candidate: run_state::PartCandidate,
+    config: &crate::config::AppConfig,
+) {
+    if let Ok(mut store) = run_store.lock() {
+        store.record_candidate(
+            run_id,
+            part_index,
+            candidate,
+            config.max_part_candidates,
+            config.part_candidate_store_max_mb as usize * 1024 * 1024,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::executor;
+    use super::features;
+    use super::semantic_validate;
+    use super::{
+        assembly_envelope_mm, build_assembly_bbox_hint, build_part_prompt,
+        build_sibling_dimensions_summary, extract_dimensional_dependencies,
+        parse_plan_with_renames, preview_stl_base64, request_requires_multipart_contract,
+        resolve_cross_references, GenerationPlan, GenerationResult, PartSpec, PipelineOutcome,
+    };
+
+    #[test]
+    fn parse_plan_accepts_valid_json() {
+        let json = r#"{"mode":"multi","parts":[{"name":"body","description":"main","position":[0,0,0],"constraints":[]}],"description":"test"}"#;
+        let (plan, _) = parse_plan_with_renames(json).expect("plan should parse");
+        assert_eq!(plan.mode, "multi");
+        assert_eq!(plan.parts.len(), 1);
+        assert_eq!(plan.parts[0].name, "body");
+    }
+
+    #[test]
+    fn sanitize_part_name_maps_to_python_identifiers() {
+        use super::sanitize_part_name;
+        assert_eq!(sanitize_part_name("base_plate"), "base_plate");
+        assert_eq!(sanitize_part_name("Top Cover"), "Top_Cover");
+        assert_eq!(sanitize_part_name("left-hinge  pin"), "left_hinge_pin");
+        assert_eq!(sanitize_part_name("3d_bracket"), "d_bracket");
+        assert_eq!(sanitize_part_name("2nd-arm"), "nd_arm");
+        assert_eq!(
+            sanitize_part_name("Gehäuse_Außenwand"),
+            "Gehause_Aussenwand"
+        );
+        assert_eq!(sanitize_part_name("Çapa 🚀"), "Capa");
+        assert_eq!(sanitize_part_name("螺丝"), "part");
+    }
+
+    #[test]
+    fn parse_plan_sanitizes_part_names() {
+        let json = r#"{"mode":"multi","parts":[
+            {"name":"Top Cover","description":"lid","position":[0,0,20],"constraints":[]},
+            {"name":"Top-Cover","description":"second lid","position":[0,0,40],"constraints":[]},
+            {"name":"base","description":"main","position":[0,0,0],"constraints":[]}
+        ]}"#;
+        let (plan, renames) = parse_plan_with_renames(json).expect("plan should parse");
+        let names: Vec<&str> = plan.parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Top_Cover", "Top_Cover_2", "base"]);
+        assert_eq!(
+            renames,
+            vec![
+                ("Top Cover".to_string(), "Top_Cover".to_string()),
+                ("Top-Cover".to_string(), "Top_Cover_2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_plan_accepts_markdown_wrapped_json() {
+        let json = r#"```json
+{"mode":"single"}
+```"#;
+        let (plan, _) = parse_plan_with_renames(json).expect("wrapped json should parse");
+        assert_eq!(plan.mode, "single");
+    }
+
+    #[test]
+    fn reconcile_single_plan_with_parts_promotes_to_multi() {
+        use super::reconcile_plan_mode;
+        use crate::config::DecompositionBias;
+
+        let json = r#"{"mode":"single","parts":[
+            {"name":"housing","description":"main","position":[0,0,0],"constraints":[]},
+            {"name":"lid","description":"cover","position":[0,0,20],"constraints":[]}
+        ]}"#;
+        let (mut plan, _) = parse_plan_with_renames(json).expect("plan should parse");
+        let warning = reconcile_plan_mode(&mut plan, &DecompositionBias::PreferMulti);
+        assert!(warning.unwrap().contains("2 parts"));
+        assert_eq!(plan.mode, "multi");
+        assert_eq!(plan.parts.len(), 2);
+    }
+
+    #[test]
+    fn planner_failure_policy_controls_single_fallback() {
+        use super::planner_failure_fallback;
+        use crate::config::PlannerFailurePolicy;
+
+        let fallback = |policy: PlannerFailurePolicy, multipart: bool| {
+            planner_failure_fallback(&policy, multipart, 2, "bad json", "{oops")
+        };
+
+        let (plan, warning) = fallback(PlannerFailurePolicy::SilentSingleFallback, false).unwrap();
+        assert_eq!(plan.mode, "single");
+        assert!(plan.parts.is_empty());
+        assert!(warning.is_none());
+
+        let (plan, warning) = fallback(PlannerFailurePolicy::WarnAndSingle, false).unwrap();
+        assert_eq!(plan.mode, "single");
+        assert!(warning.unwrap().contains("2 attempt(s)"));
+
+        let err = fallback(PlannerFailurePolicy::Fail, false).unwrap_err();
+        assert!(err.to_string().contains("bad json"));
+
+        // The multipart contract fails regardless of policy.
+        let err = fallback(PlannerFailurePolicy::SilentSingleFallback, true).unwrap_err();
+        assert!(err.to_string().contains("decompose parts"));
+    }
+
+    #[test]
+    fn reconcile_single_plan_with_parts_clears_parts_when_preferring_single() {
+        use super::reconcile_plan_mode;
+        use crate::config::DecompositionBias;
+
+        let json = r#"{"mode":"single","parts":[
+            {"name":"housing","description":"main","position":[0,0,0],"constraints":[]},
+            {"name":"lid","description":"cover","position":[0,0,20],"constraints":[]}
+        ]}"#;
+        let (mut plan, _) = parse_plan_with_renames(json).expect("plan should parse");
+        assert!(reconcile_plan_mode(&mut plan, &DecompositionBias::PreferSingle).is_some());
+        assert_eq!(plan.mode, "single");
+        assert!(plan.parts.is_empty());
+
+        let (mut consistent, _) = parse_plan_with_renames(r#"{"mode":"single"}"#).unwrap();
+        assert!(reconcile_plan_mode(&mut consistent, &DecompositionBias::PreferMulti).is_none());
+        assert_eq!(consistent.mode, "single");
+    }
+
+    #[test]
+    fn cap_plan_parts_drops_trailing_parts() {
+        use super::cap_plan_parts;
+
+        let json = r#"{"mode":"multi","parts":[
+            {"name":"base","description":"","position":[0,0,0],"constraints":[]},
+            {"name":"lid","description":"","position":[0,0,20],"constraints":[]},
+            {"name":"hinge","description":"","position":[0,10,20],"constraints":[]}
+        ]}"#;
+        let (mut plan, _) = parse_plan_with_renames(json).unwrap();
+        assert!(cap_plan_parts(&mut plan, None).is_none());
+        assert!(cap_plan_parts(&mut plan, Some(3)).is_none());
+        let warning = cap_plan_parts(&mut plan, Some(2)).unwrap();
+        assert!(warning.contains("hinge"), "{}", warning);
+        assert_eq!(plan.mode, "multi");
+        assert_eq!(plan.parts.len(), 2);
+
+        assert!(cap_plan_parts(&mut plan, Some(1)).is_some());
+        assert_eq!(plan.mode, "single");
+        assert!(plan.parts.is_empty());
+    }
+
+    #[test]
+    fn assembly_op_cuts_one_part_by_another() {
+        use super::{assemble_parts, AssemblyBoolean};
+
+        let json = r#"{"mode":"multi","parts":[
+            {"name":"mold","description":"","position":[0,0,0],"constraints":[]},
+            {"name":"Plug Core","description":"","position":[0,0,5],"constraints":[]}
+        ],"assembly_ops":[{"op":"cut","target":"mold","tool":"Plug Core"}]}"#;
+        let (plan, _) = parse_plan_with_renames(json).unwrap();
+        assert_eq!(plan.assembly_ops[0].op, AssemblyBoolean::Cut);
+        assert_eq!(plan.assembly_ops[0].tool, "Plug_Core");
+
+        let parts: Vec<(String, String, [f64; 3])> = plan
+            .parts
+            .iter()
+            .map(|p| {
+                (
+                    p.name.clone(),
+                    "result = Box(10, 10, 10)".to_string(),
+                    p.position,
+                )
+            })
+            .collect();
+        let assembled = assemble_parts(&parts, &[], &plan.assembly_ops, &HashMap::new()).unwrap();
+        assert!(
+            assembled.contains("part_mold = part_mold.cut(Pos(0, 0, 5) * part_Plug_Core)\n"),
+            "{}",
+            assembled
+        );
+        let assembly = assembled.split("# --- Assembly ---").nth(1).unwrap();
+        assert!(assembly.contains("* part_mold,"));
+        assert!(!assembly.contains("part_Plug_Core"), "{}", assembly);
+
+        // A kept tool stays in the assembly; an unbuilt one is skipped.
+        let mut ops = plan.assembly_ops.clone();
+        ops[0].keep_tool = true;
+        let kept = assemble_parts(&parts, &[], &ops, &HashMap::new()).unwrap();
+        assert!(kept.contains("* part_Plug_Core,"));
+        let skipped = assemble_parts(&parts[..1], &[], &ops, &HashMap::new()).unwrap();
+        assert!(skipped.contains("# skipped cut of 'mold' by 'Plug_Core'"));
+    }
+
+    #[tokio::test]
+    async fn reviewed_cut_assembly_keeps_consumed_tool_out_of_contract() {
+        use super::{assemble_parts, assembly_contract_issues, review_assembly};
+        use crate::ai::provider::TokenUsage;
+
+        let json = r#"{"mode":"multi","parts":[
+            {"name":"mold","description":"","position":[0,0,0],"constraints":[]},
+            {"name":"plug","description":"","position":[0,0,5],"constraints":[]}
+        ],"assembly_ops":[{"op":"cut","target":"mold","tool":"plug","keep_tool":false}]}"#;
+        let (plan, _) = parse_plan_with_renames(json).unwrap();
+        let parts: Vec<(String, String, [f64; 3])> = plan
+            .parts
+            .iter()
+            .map(|p| {
+                (
+                    p.name.clone(),
+                    "result = Box(10, 10, 10)".to_string(),
+                    p.position,
+                )
+            })
+            .collect();
+        let assembled = assemble_parts(&parts, &[], &plan.assembly_ops, &HashMap::new()).unwrap();
+        assert!(assembly_contract_issues(&assembled, &parts, &plan.assembly_ops).is_empty());
+
+        // The reviewer changes a dimension and keeps the structure.
+        let reviewed = assembled.replace("Box(10, 10, 10)", "Box(12, 12, 10)");
+        let reply: &'static str = Box::leak(
+            format!(
+                "ISSUES: walls too thin\nFIXED CODE:\n```python\n{}```",
+                reviewed
+            )
+            .into_boxed_str(),
+        );
+        let (url, _requests) = mock_ollama(reply).await;
+        let mut config = crate::config::AppConfig::default();
+        config.ai_provider = "ollama".to_string();
+        config.model = "test-model".to_string();
+        config.ollama_base_url = Some(url);
+        config.quality_gates_strict = true;
+        let (on_event, events) = capture_events();
+        let mut usage = TokenUsage::default();
+
+        let code = review_assembly(
+            assembled,
+            "a mold cut by its plug",
+            "",
+            &parts,
+            &plan.assembly_ops,
+            &config,
+            &on_event,
+            &mut usage,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(code.trim(), reviewed.trim());
+        let events = events.lock().unwrap();
+        assert!(
+            !events.iter().any(|e| e["kind"] == "ReviewReverted"),
+            "{:?}",
+            events
+        );
+    }
+
+    #[test]
+    fn parse_plan_rejects_assembly_op_on_unknown_part() {
+        let json = r#"{"mode":"multi","parts":[
+            {"name":"mold","description":"","position":[0,0,0],"constraints":[]},
+            {"name":"plug","description":"","position":[0,0,5],"constraints":[]}
+        ],"assembly_ops":[{"op":"intersect","target":"mold","tool":"insert"}]}"#;
+        let (err, _) = parse_plan_with_renames(json).unwrap_err();
+        assert!(err.contains("unknown part 'insert'"), "{}", err);
+    }
+
+    #[test]
+    fn parse_plan_rejects_invalid_mode() {
+        let json = r#"{"mode":"unknown","parts":[]}"#;
+        assert!(parse_plan_with_renames(json).is_err());
+    }
+
+    #[test]
+    fn parse_plan_repairs_truncated_json() {
+        let truncated = r#"{"mode":"multi","description":"x","parts":[{"name":"housing","description":"main","position":[0,0,0],"constraints":[]}"#;
+        let (parsed, _) =
+            parse_plan_with_renames(truncated).expect("should repair truncated planner json");
+        assert_eq!(parsed.mode, "multi");
+        assert_eq!(parsed.parts.len(), 1);
+        assert_eq!(parsed.parts[0].name, "housing");
+    }
+
+    #[test]
+    fn multipart_contract_detects_explicit_separate_parts() {
+        let user = "Make a wearable housing with a separate back plate";
+        assert!(request_requires_multipart_contract(user, ""));
+    }
+
+    #[test]
+    fn multipart_contract_not_required_for_simple_single_object() {
+        let user = "Create a rounded enclosure with fillets";
+        assert!(!request_requires_multipart_contract(user, ""));
+    }
+
+    // -----------------------------------------------------------------------
+    // Whoop prompt integration tests
+    // -----------------------------------------------------------------------
+
+    const WHOOP_PROMPT: &str = r#"Create a fully parametric, editable CAD model of a wrist-worn fitness tracker housing with a snap-fit back plate, inspired by Whoop band design.
+- Units: millimeters
+- housing_length=42
+- housing_width=28
+- height_center=7.5
+- height_ends=5
+- wall=1.8
+- top_thk=1.5
+- corner_r=5
+- back_plate_thk=1.5
+- back_lip=1.5
+- snap_tolerance=0.15
+- oring_width=1.2
+- oring_depth=0.8
+- button_length=12
+- button_width=4
+- button_offset=6
+- indicator_depth=0.3
+- band_slot_width=20
+- band_slot_height=2.5
+- band_slot_depth=5
+- Create two separate solids/bodies: Housing and BackPlate."#;
+
+    #[test]
+    fn whoop_prompt_triggers_multipart_contract() {
+        assert!(
+            request_requires_multipart_contract(WHOOP_PROMPT, ""),
+            "Whoop prompt with 'separate solids' and 'back plate' must trigger multipart contract"
+        );
+    }
+
+    #[test]
+    fn whoop_prompt_triggers_multipart_contract_via_plan() {
+        let plan_text = "housing_length=42\nhousing_width=28\nback plate ledge\nseparate bodies";
+        assert!(request_requires_multipart_contract("", plan_text));
+    }
+
+    #[test]
+    fn assembly_produces_valid_code() {
+        use super::assemble_parts;
+        let mock_parts: Vec<(String, String, [f64; 3])> = vec![
+            (
+                "housing".to_string(),
+                "from build123d import *\nresult = Box(10, 10, 5)".to_string(),
+                [0.0, 0.0, 0.0],
+            ),
+            (
+                "back_plate".to_string(),
+                "from build123d import *\nresult = Box(10, 10, 5)".to_string(),
+                [0.0, 0.0, 0.0],
+            ),
+        ];
+
+        let assembled = assemble_parts(&mock_parts, &[], &[], &HashMap::new())
+            .expect("assembly should succeed");
+        assert!(assembled.contains("Compound("));
+        assert!(assembled.contains("part_housing"));
+        assert!(assembled.contains("part_back_plate"));
+        assert!(assembled.contains("result = assy"));
+    }
+
+    #[test]
+    fn assembly_contract_validates_assembly() {
+        use super::{assemble_parts, assembly_contract_issues};
+        let mock_parts: Vec<(String, String, [f64; 3])> = vec![
+            (
+                "housing".to_string(),
+                "from build123d import *\nresult = Box(10, 10, 5)".to_string(),
+                [0.0, 0.0, 0.0],
+            ),
+            (
+                "back_plate".to_string(),
+                "from build123d import *\nresult = Box(10, 10, 5)".to_string(),
+                [0.0, 0.0, 0.0],
+            ),
+        ];
+
+        let assembled = assemble_parts(&mock_parts, &[], &[], &HashMap::new()).unwrap();
+        let issues = assembly_contract_issues(&assembled, &mock_parts, &[]);
+        assert!(
+            issues.is_empty(),
+            "assembled code should pass contract validation, got: {:?}",
+            issues
+        );
+    }
+
+    fn capture_events() -> (
+        super::EventSink,
+        std::sync::Arc<std::sync::Mutex<Vec<serde_json::Value>>>,
+    ) {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let channel = tauri::ipc::Channel::new(move |body| {
+            if let tauri::ipc::InvokeResponseBody::Json(json) = body {
+                sink.lock().unwrap().push(serde_json::from_str(&json).unwrap());
+            }
+            Ok(())
+        });
+        (super::EventSink::new(channel), events)
+    }
+
+    #[test]
+    fn review_dropping_part_variables_is_reverted() {
+        use super::{assemble_parts, reviewed_assembly_code};
+        let parts = leg_parts(&["700", "700"]);
+        let assembled = assemble_parts(&parts, &[], &[], &HashMap::new()).unwrap();
+        let reviewed = "from build123d import *\nresult = Box(10, 10, 700)\n".to_string();
+        let (channel, events) = capture_events();
+
+        let kept = reviewed_assembly_code(&channel, assembled.clone(), reviewed, &parts, &[]);
+        assert_eq!(kept, assembled);
+        let events = events.lock().unwrap();
+        let reverted = events
+            .iter()
+            .find(|e| e["kind"] == "ReviewReverted")
+            .expect("ReviewReverted event");
+        let reason = reverted["reason"].as_str().unwrap();
+        assert!(reason.contains("missing part_leg_1"), "{}", reason);
+        assert!(reason.contains("missing part_leg_2"), "{}", reason);
+    }
+
+    #[test]
+    fn review_keeping_contract_is_accepted() {
+        use super::{assemble_parts, reviewed_assembly_code};
+        let parts = leg_parts(&["700", "700"]);
+        let assembled = assemble_parts(&parts, &[], &[], &HashMap::new()).unwrap();
+        let reviewed = assembled.replace("700", "720");
+        let (channel, events) = capture_events();
+
+        let kept = reviewed_assembly_code(&channel, assembled, reviewed.clone(), &parts, &[]);
+        assert_eq!(kept, reviewed);
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn assembly_exclusions_respect_multipart_contract() {
+        use super::{assembly_part_refs, check_assembly_exclusions, PartRef};
+        use crate::agent::run_state::RunStore;
+        let part = |name: &str| PartSpec {
+            name: name.to_string(),
+            description: String::new(),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+        let plan = GenerationPlan {
+            mode: "multi".to_string(),
+            description: None,
+            parts: vec![part("housing"), part("back_plate")],
+            assembly_ops: vec![],
+        };
+        let (accepted, rejected) =
+            assembly_part_refs(&plan, &[("housing".to_string(), String::new(), [0.0; 3])]);
+        assert_eq!(
+            accepted,
+            vec![PartRef {
+                index: 0,
+                name: "housing".to_string()
+            }]
+        );
+        assert_eq!(rejected[0].index, 1);
+
+        let mut store = RunStore::default();
+        store.start_run("loose", "Make a box with a lid", "", &plan);
+        store.start_run("strict", "Housing with a separate back plate", "", &plan);
+        let config = crate::config::AppConfig::default();
+
+        assert!(check_assembly_exclusions(store.run("loose").unwrap(), &[1], &config).is_ok());
+        assert!(check_assembly_exclusions(store.run("loose").unwrap(), &[2], &config).is_err());
+        assert!(check_assembly_exclusions(store.run("strict").unwrap(), &[1], &config).is_err());
+        assert!(check_assembly_exclusions(store.run("strict").unwrap(), &[], &config).is_ok());
+    }
+
+    #[tokio::test]
+    async fn resume_generation_skips_part_generation() {
+        use super::resume_from_run;
+        use crate::agent::run_state::{PartCandidate, ResumableGeneration, RunStore};
+        let part = |name: &str, z: f64| PartSpec {
+            name: name.to_string(),
+            description: format!("{} part", name),
+            position: [0.0, 0.0, z],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+        let plan = GenerationPlan {
+            mode: "multi".to_string(),
+            description: None,
+            parts: vec![part("base", 0.0), part("lid", 20.0)],
+            assembly_ops: vec![],
+        };
+        let mut store = RunStore::default();
+        store.start_run("gen-resume", "box with lid", "", &plan);
+        for (idx, name) in ["base", "lid"].iter().enumerate() {
+            let candidate = PartCandidate {
+                code: format!("from build123d import *\nresult = Box(10, 10, 5)  # {}\n", name),
+                stl_base64: None,
+                accepted: true,
+                findings: vec![],
+                created_at_ms: 0,
+                post_geometry_report: None,
+            };
+            store.record_candidate("gen-resume", idx, candidate, 3, usize::MAX);
+        }
+        let saved = ResumableGeneration::from_run(store.run("gen-resume").unwrap()).unwrap();
+
+        let mut config = crate::config::AppConfig::default();
+        config.safe_mode = true;
+        let (on_event, events) = capture_events();
+        let code = resume_from_run(
+            saved.into_run_record(),
+            &config,
+            None,
+            &HashMap::new(),
+            &HashMap::new(),
+            None,
+            &on_event,
+            &std::sync::Mutex::new(RunStore::default()),
+        )
+        .await
+        .unwrap();
+
+        assert!(code.contains("part_base") && code.contains("part_lid"), "{}", code);
+        let events = events.lock().unwrap();
+        let kinds: Vec<&str> = events.iter().filter_map(|e| e["kind"].as_str()).collect();
+        assert!(kinds.iter().all(|k| !k.starts_with("Part")), "{:?}", kinds);
+        assert!(kinds.contains(&"FinalCode"));
+        let done = events.iter().find(|e| e["kind"] == "Done").expect("Done event");
+        assert_eq!(done["success"], true);
+    }
+
+    #[test]
+    fn kept_siblings_skip_rerolled_parts_by_plan_index() {
+        use super::kept_sibling_parts;
+        use crate::agent::run_state::{PartCandidate, RunStore};
+        let part = |name: &str| PartSpec {
+            name: name.to_string(),
+            description: String::new(),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+        let plan = GenerationPlan {
+            mode: "multi".to_string(),
+            description: None,
+            parts: vec![part("base"), part("arm"), part("lid")],
+            assembly_ops: vec![],
+        };
+        let mut store = RunStore::default();
+        store.start_run("gen-reroll", "arm on a base", "", &plan);
+        // "arm" (plan index 1) has no selected candidate, so selected_parts()
+        // indices no longer line up with plan indices.
+        for idx in [0, 2] {
+            let candidate = PartCandidate {
+                code: format!("result = Box({}, 1, 1)", idx + 1),
+                stl_base64: None,
+                accepted: true,
+                findings: vec![],
+                created_at_ms: 0,
+                post_geometry_report: None,
+            };
+            store.record_candidate("gen-reroll", idx, candidate, 3, usize::MAX);
+        }
+        let run = store.run("gen-reroll").unwrap();
+
+        let names = |kept: Vec<(String, String)>| -> Vec<String> {
+            kept.into_iter().map(|(name, _)| name).collect()
+        };
+        assert_eq!(names(kept_sibling_parts(run, &[2])), vec!["base"]);
+        assert_eq!(names(kept_sibling_parts(run, &[1])), vec!["base", "lid"]);
+    }
+
+    #[tokio::test]
+    async fn reassembly_reports_failed_done() {
+        use super::reassemble_run;
+        use crate::agent::run_state::{PartCandidate, RunStore};
+        let part = |name: &str| PartSpec {
+            name: name.to_string(),
+            description: format!("{} part", name),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+        let plan = GenerationPlan {
+            mode: "multi".to_string(),
+            description: None,
+            parts: vec![part("housing"), part("back_plate")],
+            assembly_ops: vec![],
+        };
+        let mut store = RunStore::default();
+        store.start_run(
+            "gen-partial",
+            "housing with a separate back plate",
+            "",
+            &plan,
+        );
+        let candidate = PartCandidate {
+            code: "from build123d import *\nresult = Box(10, 10, 5)\n".to_string(),
+            stl_base64: None,
+            accepted: true,
+            findings: vec![],
+            created_at_ms: 0,
+            post_geometry_report: None,
+        };
+        store.record_candidate("gen-partial", 0, candidate, 3, usize::MAX);
+
+        let mut config = crate::config::AppConfig::default();
+        config.safe_mode = true;
+        config.quality_gates_strict = true;
+        let (on_event, events) = capture_events();
+        let reassembly = reassemble_run(
+            store.run("gen-partial").unwrap(),
+            &config,
+            None,
+            &HashMap::new(),
+            &HashMap::new(),
+            None,
+            &on_event,
+            &std::sync::Mutex::new(RunStore::default()),
+        )
+        .await
+        .unwrap();
+
+        // Only 1/2 parts under the strict contract: resumable state must stay.
+        assert!(!reassembly.success);
+        assert!(reassembly.code.contains("part_housing"));
+        let events = events.lock().unwrap();
+        let done = events
+            .iter()
+            .find(|e| e["kind"] == "Done")
+            .expect("Done event");
+        assert_eq!(done["success"], false);
+    }
+
+    #[tokio::test]
+    async fn chat_reply_validates_code_like_single_generation() {
+        use super::{finish_chat_reply, finish_single_response};
+        use crate::ai::provider::TokenUsage;
+        // No build123d import: static validation fails without running Python.
+        let response = "Here is the block.\n<CODE>\nresult = Box(10, 10, 5)\n</CODE>".to_string();
+        let mut config = crate::config::AppConfig::default();
+        config.enable_code_review = false;
+        config.max_validation_attempts = 1;
+        let ctx = executor::ExecutionContext {
+            venv_dir: std::path::PathBuf::from("/nonexistent/venv"),
+            runner_script: std::path::PathBuf::from("/nonexistent/runner.py"),
+            config: config.clone(),
+        };
+        let validation_events = |events: &[serde_json::Value]| {
+            events
+                .iter()
+                .filter(|e| {
+                    matches!(
+                        e["kind"].as_str(),
+                        Some("StaticValidationReport" | "ValidationFailed" | "FinalCode" | "Done")
+                    )
+                })
+                .map(|e| {
+                    let mut e = e.clone();
+                    e.as_object_mut().unwrap().remove("seq");
+                    e.as_object_mut().unwrap().remove("run_id");
+                    e
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let (pipeline_sink, pipeline_events) = capture_events();
+        let outcome = finish_single_response(
+            response.clone(),
+            "a small block",
+            None,
+            "sys",
+            &config,
+            Some(&ctx),
+            &pipeline_sink,
+            &mut TokenUsage::default(),
+            "ollama",
+            "test-model",
+            &std::sync::Mutex::new(crate::agent::run_state::RunStore::default()),
+        )
+        .await
+        .unwrap();
+        assert!(outcome.validated && !outcome.success);
+
+        let (chat_sink, chat_events) = capture_events();
+        let reply = finish_chat_reply(
+            response,
+            "a small block",
+            "sys",
+            &config,
+            Some(&ctx),
+            &chat_sink,
+            None,
+            &std::sync::Mutex::new(crate::agent::run_state::RunStore::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(reply, outcome.response);
+
+        let pipeline_events = validation_events(&pipeline_events.lock().unwrap()[..]);
+        let chat_events = validation_events(&chat_events.lock().unwrap()[..]);
+        assert!(pipeline_events
+            .iter()
+            .any(|e| e["kind"] == "StaticValidationReport" && e["passed"] == false));
+        assert_eq!(chat_events, pipeline_events);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{
-        build_assembly_bbox_hint, build_part_prompt, build_sibling_dimensions_summary, parse_plan,
-        request_requires_multipart_contract, resolve_cross_references, GenerationPlan, PartSpec,
-    };
+    #[tokio::test]
+    async fn conversational_chat_reply_skips_validation() {
+        use super::finish_chat_reply;
+        let config = crate::config::AppConfig::default();
+        let (sink, events) = capture_events();
+        let reply = finish_chat_reply(
+            "Fillets round edges; chamfers bevel them.".to_string(),
+            "what is the difference between a fillet and a chamfer?",
+            "sys",
+            &config,
+            None,
+            &sink,
+            None,
+            &std::sync::Mutex::new(crate::agent::run_state::RunStore::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(reply, "Fillets round edges; chamfers bevel them.");
+
+        let events = events.lock().unwrap();
+        let kinds: Vec<&str> = events.iter().filter_map(|e| e["kind"].as_str()).collect();
+        assert_eq!(kinds, vec!["SingleDone", "Done"]);
+        assert_eq!(events[1]["success"], true);
+        assert_eq!(events[1]["validated"], false);
+    }
 
     #[test]
-    fn parse_plan_accepts_valid_json() {
-        let json = r#"{"mode":"multi","parts":[{"name":"body","description":"main","position":[0,0,0],"constraints":[]}],"description":"test"}"#;
-        let plan = parse_plan(json).expect("plan should parse");
-        assert_eq!(plan.mode, "multi");
-        assert_eq!(plan.parts.len(), 1);
-        assert_eq!(plan.parts[0].name, "body");
+    fn print_estimate_is_sent_only_for_printing_requests() {
+        use super::emit_print_estimate;
+        let report = executor::PostGeometryValidationReport {
+            watertight: true,
+            manifold: true,
+            degenerate_faces: 0,
+            euler_number: 2,
+            triangle_count: 12,
+            component_count: 1,
+            bounds_min: [0.0, 0.0, 0.0],
+            bounds_max: [20.0, 20.0, 20.0],
+            volume: 8000.0,
+            surface_area: 2400.0,
+            center_of_mass: None,
+            unit_inertia: None,
+            bbox_ok: true,
+            warnings: vec![],
+        };
+        let config = crate::config::AppConfig::default();
+
+        let (channel, events) = capture_events();
+        emit_print_estimate(&channel, Some(&report), "A bracket for a CNC router", &config);
+        assert!(events.lock().unwrap().is_empty());
+
+        emit_print_estimate(&channel, Some(&report), "A 3D printable cable clip", &config);
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["kind"], "PrintEstimate");
+        assert_eq!(events[0]["estimate"]["layer_count"], 100);
+        assert!(events[0]["estimate"]["summary"]
+            .as_str()
+            .unwrap()
+            .contains("±30%"));
     }
 
     #[test]
-    fn parse_plan_accepts_markdown_wrapped_json() {
-        let json = r#"```json
-{"mode":"single"}
-```"#;
-        let plan = parse_plan(json).expect("wrapped json should parse");
-        assert_eq!(plan.mode, "single");
+    fn prompt_debug_log_is_skipped_when_disabled() {
+        use super::open_prompt_debug_log;
+        let dir = std::env::temp_dir().join(format!("cadai-debug-log-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("multipart_debug.log");
+
+        assert!(open_prompt_debug_log(false, &path).is_none());
+        assert!(!path.exists());
+        assert!(!dir.exists());
+
+        assert!(open_prompt_debug_log(true, &path).is_some());
+        assert!(path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn parse_plan_rejects_invalid_mode() {
-        let json = r#"{"mode":"unknown","parts":[]}"#;
-        assert!(parse_plan(json).is_err());
+    fn prompt_debug_log_is_skipped_in_safe_mode() {
+        use super::{open_prompt_debug_log, prompt_debug_logging_enabled};
+        let mut config = crate::config::AppConfig::default();
+        config.debug_prompt_logging = true;
+        assert!(prompt_debug_logging_enabled(&config));
+
+        config.safe_mode = true;
+        let dir = std::env::temp_dir().join(format!("cadai-debug-log-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("multipart_debug.log");
+        assert!(open_prompt_debug_log(prompt_debug_logging_enabled(&config), &path).is_none());
+        assert!(!dir.exists());
     }
 
     #[test]
-    fn parse_plan_repairs_truncated_json() {
-        let truncated = r#"{"mode":"multi","description":"x","parts":[{"name":"housing","description":"main","position":[0,0,0],"constraints":[]}"#;
-        let parsed = parse_plan(truncated).expect("should repair truncated planner json");
-        assert_eq!(parsed.mode, "multi");
-        assert_eq!(parsed.parts.len(), 1);
-        assert_eq!(parsed.parts[0].name, "housing");
+    fn prompt_debug_log_lives_in_app_data() {
+        let path = super::prompt_debug_log_path();
+        assert!(path.ends_with("multipart_debug.log"));
+        assert!(!path.starts_with(env!("CARGO_MANIFEST_DIR")));
+        match dirs::config_dir() {
+            Some(dir) => assert!(path.starts_with(dir.join("cadai-studio"))),
+            None => assert!(path.starts_with(std::env::temp_dir())),
+        }
     }
 
-    #[test]
-    fn multipart_contract_detects_explicit_separate_parts() {
-        let user = "Make a wearable housing with a separate back plate";
-        assert!(request_requires_multipart_contract(user, ""));
+    fn leg_parts(heights: &[&str]) -> Vec<(String, String, [f64; 3])> {
+        heights
+            .iter()
+            .enumerate()
+            .map(|(i, height)| {
+                (
+                    format!("leg_{}", i + 1),
+                    format!(
+                        "from build123d import *\nheight = {}\nwith BuildPart() as leg:\n    Box(40, 40, height)\nresult = leg.part",
+                        height
+                    ),
+                    [i as f64 * 100.0, 0.0, 0.0],
+                )
+            })
+            .collect()
     }
 
     #[test]
-    fn multipart_contract_not_required_for_simple_single_object() {
-        let user = "Create a rounded enclosure with fillets";
-        assert!(!request_requires_multipart_contract(user, ""));
+    fn assembly_shares_function_for_identical_parts() {
+        use super::{assemble_parts, assembly_contract_issues};
+        let parts = leg_parts(&["700", "700", "700", "700"]);
+        let assembled = assemble_parts(&parts, &[], &[], &HashMap::new()).unwrap();
+
+        assert_eq!(assembled.matches("def make_leg():").count(), 1);
+        assert_eq!(assembled.matches("Box(40, 40, height)").count(), 1);
+        for i in 1..=4 {
+            assert_eq!(
+                assembled.matches(&format!("part_leg_{} = make_leg()\n", i)).count(),
+                1
+            );
+            assert!(assembled.contains(&format!("* part_leg_{},", i)));
+        }
+        let compound = &assembled[assembled.find("Compound(").unwrap()..];
+        assert_eq!(compound.matches("    Pos(").count(), 4);
+        assert!(assembly_contract_issues(&assembled, &parts, &[]).is_empty());
     }
 
-    // -----------------------------------------------------------------------
-    // Whoop prompt integration tests
-    // -----------------------------------------------------------------------
+    #[test]
+    fn assembly_parameterizes_slightly_different_parts() {
+        use super::{assemble_parts, assembly_contract_issues};
+        let parts = leg_parts(&["700", "450"]);
+        let assembled = assemble_parts(&parts, &[], &[], &HashMap::new()).unwrap();
 
-    const WHOOP_PROMPT: &str = r#"Create a fully parametric, editable CAD model of a wrist-worn fitness tracker housing with a snap-fit back plate, inspired by Whoop band design.
-- Units: millimeters
-- housing_length=42
-- housing_width=28
-- height_center=7.5
-- height_ends=5
-- wall=1.8
-- top_thk=1.5
-- corner_r=5
-- back_plate_thk=1.5
-- back_lip=1.5
-- snap_tolerance=0.15
-- oring_width=1.2
-- oring_depth=0.8
-- button_length=12
-- button_width=4
-- button_offset=6
-- indicator_depth=0.3
-- band_slot_width=20
-- band_slot_height=2.5
-- band_slot_depth=5
-- Create two separate solids/bodies: Housing and BackPlate."#;
+        assert!(assembled.contains("def make_leg(height):"));
+        assert!(assembled.contains("part_leg_1 = make_leg(700)\n"));
+        assert!(assembled.contains("part_leg_2 = make_leg(450)\n"));
+        assert!(assembly_contract_issues(&assembled, &parts, &[]).is_empty());
+    }
 
     #[test]
-    fn whoop_prompt_triggers_multipart_contract() {
-        assert!(
-            request_requires_multipart_contract(WHOOP_PROMPT, ""),
-            "Whoop prompt with 'separate solids' and 'back plate' must trigger multipart contract"
-        );
+    fn assembly_keeps_structurally_different_parts_separate() {
+        use super::assemble_parts;
+        let mut parts = leg_parts(&["700"]);
+        parts.push((
+            "top".to_string(),
+            "result = Box(800, 500, 20)".to_string(),
+            [0.0, 0.0, 700.0],
+        ));
+        let assembled = assemble_parts(&parts, &[], &[], &HashMap::new()).unwrap();
+        assert!(!assembled.contains("def make_"));
+        assert!(assembled.contains("part_top = Box(800, 500, 20)"));
     }
 
     #[test]
-    fn whoop_prompt_triggers_multipart_contract_via_plan() {
-        let plan_text = "housing_length=42\nhousing_width=28\nback plate ledge\nseparate bodies";
-        assert!(request_requires_multipart_contract("", plan_text));
+    fn assembly_contract_flags_missing_shared_function() {
+        use super::{assemble_parts, assembly_contract_issues};
+        let parts = leg_parts(&["700", "700"]);
+        let assembled = assemble_parts(&parts, &[], &[], &HashMap::new())
+            .unwrap()
+            .replace("def make_leg():", "def build_leg():");
+        let issues = assembly_contract_issues(&assembled, &parts, &[]);
+        assert!(issues
+            .iter()
+            .any(|i| i.contains("missing shared function make_leg")));
+
+        let duplicated = assemble_parts(&parts, &[], &[], &HashMap::new())
+            .unwrap()
+            .replace("part_leg_2 = make_leg()", "part_leg_1 = make_leg()");
+        let issues = assembly_contract_issues(&duplicated, &parts, &[]);
+        assert!(issues
+            .iter()
+            .any(|i| i.contains("duplicate shared call for part_leg_1")));
     }
 
     #[test]
-    fn assembly_produces_valid_code() {
+    fn assembled_failure_is_repaired_in_the_owning_part_block_only() {
         use super::assemble_parts;
-        let mock_parts: Vec<(String, String, [f64; 3])> = vec![
+        use crate::agent::failure_localization::{self, FailureLocation};
+        let parts = vec![
             (
-                "housing".to_string(),
-                "from build123d import *\nresult = Box(10, 10, 5)".to_string(),
+                "base".to_string(),
+                "from build123d import *\nresult = Box(80, 60, 40)".to_string(),
                 [0.0, 0.0, 0.0],
             ),
             (
-                "back_plate".to_string(),
-                "from build123d import *\nresult = Box(10, 10, 5)".to_string(),
-                [0.0, 0.0, 0.0],
+                "lid".to_string(),
+                "lid_t = 4\nresult = Box(80, 60, lid_t)\nresult = fillet(result.edges(), 30)"
+                    .to_string(),
+                [0.0, 0.0, 40.0],
             ),
         ];
+        let assembled = assemble_parts(&parts, &[], &[], &HashMap::new()).unwrap();
+        let bad_line = assembled
+            .lines()
+            .position(|l| l.contains("fillet("))
+            .unwrap()
+            + 1;
+
+        let FailureLocation::Part(section) = failure_localization::locate(&assembled, bad_line)
+        else {
+            panic!(
+                "failure in the lid block should be localized:\n{}",
+                assembled
+            );
+        };
+        assert_eq!(section.name, "lid");
+        let isolated = failure_localization::isolate_part(&assembled, &section);
+        assert!(isolated.contains("result = fillet(result.edges(), 30)"));
+        assert!(!isolated.contains("part_base"));
+
+        let repaired = isolated.replace("30)", "1)");
+        let spliced = failure_localization::splice_part(&assembled, &section, &repaired);
+        let changed: Vec<(&str, &str)> = assembled
+            .lines()
+            .zip(spliced.lines())
+            .filter(|(a, b)| a != b)
+            .collect();
+        assert_eq!(
+            changed,
+            vec![(
+                "part_lid = fillet(part_lid.edges(), 30)",
+                "part_lid = fillet(part_lid.edges(), 1)"
+            )]
+        );
+        assert_eq!(assembled.lines().count(), spliced.lines().count());
 
-        let assembled = assemble_parts(&mock_parts).expect("assembly should succeed");
-        assert!(assembled.contains("Compound("));
-        assert!(assembled.contains("part_housing"));
-        assert!(assembled.contains("part_back_plate"));
-        assert!(assembled.contains("result = assy"));
+        let assy_line = assembled
+            .lines()
+            .position(|l| l.starts_with("assy"))
+            .unwrap()
+            + 1;
+        assert_eq!(
+            failure_localization::locate(&assembled, assy_line),
+            FailureLocation::Assembly
+        );
     }
 
     #[test]
-    fn assembly_contract_validates_assembly() {
+    fn assembly_tints_parts_with_assigned_and_palette_colors() {
         use super::{assemble_parts, assembly_contract_issues};
-        let mock_parts: Vec<(String, String, [f64; 3])> = vec![
+        use crate::agent::part_colors;
+        let parts = vec![
             (
-                "housing".to_string(),
-                "from build123d import *\nresult = Box(10, 10, 5)".to_string(),
+                "base".to_string(),
+                "result = Box(80, 60, 40)".to_string(),
                 [0.0, 0.0, 0.0],
             ),
             (
-                "back_plate".to_string(),
-                "from build123d import *\nresult = Box(10, 10, 5)".to_string(),
-                [0.0, 0.0, 0.0],
+                "lid".to_string(),
+                "result = Box(80, 60, 4)".to_string(),
+                [0.0, 0.0, 40.0],
             ),
         ];
+        let assigned = HashMap::from([("lid".to_string(), [1.0, 0.0, 0.0])]);
+
+        let assembled = assemble_parts(&parts, &[], &[], &assigned).unwrap();
+        let lid_color = "part_lid.color = Color(1.000, 0.000, 0.000)\n";
+        let base_color = part_colors::color_statement("base", part_colors::palette_color("base"));
+        assert!(assembled.contains(lid_color), "{}", assembled);
+        assert!(assembled.contains(&base_color), "{}", assembled);
+        // Colors are set after the parts exist and before they are placed.
+        let defined = assembled.find("part_lid = Box(80, 60, 4)").unwrap();
+        let colored = assembled.find(lid_color).unwrap();
+        assert!(defined < colored && colored < assembled.find("assy = Compound(").unwrap());
+        assert!(assembly_contract_issues(&assembled, &parts, &[]).is_empty());
+        // Unassigned parts keep their palette color from run to run.
+        assert_eq!(
+            assembled,
+            assemble_parts(&parts, &[], &[], &assigned).unwrap()
+        );
+    }
 
-        let assembled = assemble_parts(&mock_parts).unwrap();
-        let issues = assembly_contract_issues(&assembled, &mock_parts);
-        assert!(
-            issues.is_empty(),
-            "assembled code should pass contract validation, got: {:?}",
-            issues
+    #[test]
+    fn assembly_nests_moving_parts_in_labeled_groups() {
+        use super::{assemble_parts, assembly_contract_issues};
+        use crate::agent::kinematics;
+        let spec = |name: &str, constraint: &str| PartSpec {
+            name: name.to_string(),
+            description: String::new(),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![constraint.to_string()],
+            reliability_profile: None,
+        };
+        let tags = kinematics::classify_parts(&[
+            spec("base", "open-top box"),
+            spec("lid", "hinged to the base"),
+        ]);
+        let parts = vec![
+            ("base".to_string(), "result = Box(80, 60, 40)".to_string(), [0.0, 0.0, 0.0]),
+            ("lid".to_string(), "result = Box(80, 60, 4)".to_string(), [0.0, 0.0, 40.0]),
+        ];
+
+        let assembled = assemble_parts(&parts, &tags, &[], &HashMap::new()).unwrap();
+        assert!(assembled.contains("part_lid.label = \"lid\"\n"));
+        assert!(assembled.contains(
+            "lid_group = Compound(label=\"lid_group\", children=[\n    Pos(0, 0, 40) * part_lid,\n])"
+        ));
+        let assy = &assembled[assembled.find("assy = Compound(").unwrap()..];
+        assert!(assy.contains("    Pos(0, 0, 0) * part_base,\n    lid_group,\n])"));
+        assert!(assembly_contract_issues(&assembled, &parts, &[]).is_empty());
+
+        // Without moving parts the assembly is unchanged.
+        let static_tags = kinematics::classify_parts(&[
+            spec("base", "open-top box"),
+            spec("lid", "sits on the base"),
+        ]);
+        assert_eq!(
+            assemble_parts(&parts, &static_tags, &[], &HashMap::new()).unwrap(),
+            assemble_parts(&parts, &[], &[], &HashMap::new()).unwrap()
         );
     }
 
@@ -3613,7 +8279,7 @@ mod tests {
     #[test]
     fn parse_plan_handles_empty_parts_gracefully() {
         let json = r#"{"mode":"multi","parts":[]}"#;
-        let plan = parse_plan(json).expect("empty parts should parse");
+        let (plan, _) = parse_plan_with_renames(json).expect("empty parts should parse");
         assert_eq!(plan.mode, "multi");
         assert!(plan.parts.is_empty());
     }
@@ -3626,7 +8292,8 @@ mod tests {
   {"name":"back_plate","description":"cover","position":[0,0,0],"constraints":[]}
 ]}
 That should work."#;
-        let plan = parse_plan(wrapped).expect("should extract JSON from surrounding prose");
+        let (plan, _) =
+            parse_plan_with_renames(wrapped).expect("should extract JSON from surrounding prose");
         assert_eq!(plan.mode, "multi");
         assert_eq!(plan.parts.len(), 2);
     }
@@ -3634,9 +8301,9 @@ That should work."#;
     #[test]
     fn parse_plan_repairs_truncated_multi_part() {
         let truncated = r#"{"mode":"multi","description":"housing + plate","parts":[{"name":"housing","description":"main","position":[0,0,0],"constraints":[]},{"name":"back_plate","description":"cover","position":[0,0,0"#;
-        let result = parse_plan(truncated);
+        let result = parse_plan_with_renames(truncated);
         match result {
-            Ok(plan) => {
+            Ok((plan, _)) => {
                 assert_eq!(plan.mode, "multi");
                 assert!(!plan.parts.is_empty());
             }
@@ -3654,7 +8321,8 @@ The design has two distinct parts: 1. Base with integrated slot... 2. Back plate
 {"mode":"multi","description":"housing + plate","parts":[{"name":"housing","description":"main body","position":[0,0,0],"constraints":[]},{"name":"back_plate","description":"cover","position":[0,0,0],"constraints":[]}]}
 
 That should be the correct output."#;
-        let plan = parse_plan(prose).expect("should extract JSON via regex from deep prose");
+        let (plan, _) =
+            parse_plan_with_renames(prose).expect("should extract JSON via regex from deep prose");
         assert_eq!(plan.mode, "multi");
         assert_eq!(plan.parts.len(), 2);
     }
@@ -3667,7 +8335,8 @@ That should be the correct output."#;
 The design should be split into parts. Let me create the JSON:
 {"mode":"single","description":"simple box"}
 End of response."#;
-        let plan = parse_plan(prose).expect("regex should find JSON despite stray braces");
+        let (plan, _) =
+            parse_plan_with_renames(prose).expect("regex should find JSON despite stray braces");
         assert_eq!(plan.mode, "single");
     }
 
@@ -3675,7 +8344,10 @@ End of response."#;
     fn parse_plan_fails_on_pure_prose() {
         let prose = "The user wants a JSON response only. No markdown, no prose. Just valid compact JSON. \
                      The design has two distinct parts: 1. Base with integrated slot... 2. Back plate.";
-        assert!(parse_plan(prose).is_err(), "pure prose with no JSON should fail");
+        assert!(
+            parse_plan_with_renames(prose).is_err(),
+            "pure prose with no JSON should fail"
+        );
     }
 
     #[test]
@@ -3689,14 +8361,17 @@ End of response."#;
                     description: "Primary shell with outer dimensions 42x28x7.5mm and wall thickness 1.8mm.".to_string(),
                     position: [0.0, 0.0, 0.0],
                     constraints: vec![],
+                    reliability_profile: None,
                 },
                 PartSpec {
                     name: "cover".to_string(),
                     description: "Cover plate outer dimensions 30x24x1.5mm with lip height 1.2mm.".to_string(),
                     position: [0.0, 0.0, 0.0],
                     constraints: vec![],
+                    reliability_profile: None,
                 },
             ],
+            assembly_ops: vec![],
         };
         let hint = build_assembly_bbox_hint(
             &plan,
@@ -3709,6 +8384,50 @@ End of response."#;
         assert!(hint.contains("7.5"));
     }
 
+    #[test]
+    fn assembly_envelope_flags_part_larger_than_assembly() {
+        let part = |name: &str, description: &str| PartSpec {
+            name: name.to_string(),
+            description: description.to_string(),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+        let plan = GenerationPlan {
+            mode: "multi".to_string(),
+            description: None,
+            parts: vec![part("housing", "Shell"), part("lid", "Snap-fit lid")],
+            assembly_ops: vec![],
+        };
+        // No part states dimensions: the envelope comes from the request.
+        let envelope = assembly_envelope_mm(&plan, "an enclosure 60x40x30mm with a lid")
+            .expect("request envelope should be used");
+        assert_eq!(envelope, [60.0, 40.0, 30.0]);
+
+        // A lid modelled in the wrong unit dwarfs the assembly.
+        let mut report = executor::PostGeometryValidationReport {
+            watertight: true,
+            manifold: true,
+            degenerate_faces: 0,
+            euler_number: 2,
+            triangle_count: 12,
+            component_count: 1,
+            bounds_min: [0.0, 0.0, 0.0],
+            bounds_max: [600.0, 400.0, 3.0],
+            volume: 720_000.0,
+            surface_area: 486_000.0,
+            center_of_mass: None,
+            unit_inertia: None,
+            bbox_ok: true,
+            warnings: vec![],
+        };
+        let finding = semantic_validate::check_assembly_envelope(&report, envelope, 25.0);
+        assert!(finding.is_some_and(|f| f.contains("got 600.00mm")));
+
+        report.bounds_max = [60.0, 40.0, 3.0];
+        assert!(semantic_validate::check_assembly_envelope(&report, envelope, 25.0).is_none());
+    }
+
     #[test]
     fn multipart_contract_detects_norwegian_keywords() {
         let user = "Lag en bakplate og eksplodert visning";
@@ -3722,6 +8441,42 @@ End of response."#;
     // Sibling dimensions & cross-reference resolution tests
     // -----------------------------------------------------------------------
 
+    #[test]
+    fn test_mating_dimensions_keep_comma_decimals() {
+        let constraints = vec![
+            "Bore diameter 22,5 mm to receive the shaft".to_string(),
+            "Decorative only".to_string(),
+        ];
+        let section = extract_dimensional_dependencies(&constraints);
+        assert!(section.contains("22,5 mm"));
+        assert!(!section.contains("Decorative"));
+
+        let plan = GenerationPlan {
+            mode: "multi".to_string(),
+            description: None,
+            parts: vec![
+                PartSpec {
+                    name: "shaft".to_string(),
+                    description: "Welle, Durchmesser 22,5 mm, Länge 1.250,0 mm".to_string(),
+                    position: [0.0, 0.0, 0.0],
+                    constraints: vec![],
+                    reliability_profile: None,
+                },
+                PartSpec {
+                    name: "hub".to_string(),
+                    description: "Nabe".to_string(),
+                    position: [0.0, 0.0, 0.0],
+                    constraints: vec![],
+                    reliability_profile: None,
+                },
+            ],
+            assembly_ops: vec![],
+        };
+        let summary = build_sibling_dimensions_summary(&plan.parts, "hub", &[]);
+        assert!(summary.contains("22,5 mm"), "{}", summary);
+        assert!(summary.contains("1.250,0 mm"), "{}", summary);
+    }
+
     #[test]
     fn test_build_sibling_dimensions_summary() {
         let plan = GenerationPlan {
@@ -3733,17 +8488,20 @@ End of response."#;
                     description: "Main shell 42x28x7.5mm with wall 1.8mm".to_string(),
                     position: [0.0, 0.0, 0.0],
                     constraints: vec!["inner bore 40mm".to_string()],
+                    reliability_profile: None,
                 },
                 PartSpec {
                     name: "back_plate".to_string(),
                     description: "Cover plate 40x26x1.5mm".to_string(),
                     position: [0.0, 0.0, 0.0],
                     constraints: vec!["must match housing inner bore".to_string()],
+                    reliability_profile: None,
                 },
             ],
+            assembly_ops: vec![],
         };
 
-        let summary = build_sibling_dimensions_summary(&plan, "back_plate");
+        let summary = build_sibling_dimensions_summary(&plan.parts, "back_plate", &[]);
         assert!(summary.contains("housing"), "should contain sibling part name");
         // Compact format extracts only Nmm-formatted dimensions (7.5mm, 1.8mm)
         // — not bare numbers like "42x28x" which lack a mm suffix
@@ -3755,6 +8513,59 @@ End of response."#;
         assert!(!summary.contains("Main shell"), "should not contain full description text");
     }
 
+    #[test]
+    fn sibling_code_dimensions_flow_into_retry_prompt() {
+        let spec = |name: &str, description: &str| PartSpec {
+            name: name.to_string(),
+            description: description.to_string(),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+        let parts = vec![
+            spec("housing", "Shell about 40mm wide"),
+            spec("lid", "Lid that fits the housing"),
+        ];
+        let siblings = vec![
+            (
+                "housing".to_string(),
+                "width = 42\nresult = Box(width, 28, 8)".to_string(),
+            ),
+            ("latch".to_string(), "result = Cylinder(3, 12)".to_string()),
+            ("lid".to_string(), "result = Box(1, 1, 1)".to_string()),
+        ];
+
+        let summary = build_sibling_dimensions_summary(&parts, "lid", &siblings);
+        assert!(
+            summary.contains(
+                "- **housing**: dimensions [40mm] | generated [width = 42, Box(42, 28, 8)]"
+            ),
+            "{}",
+            summary
+        );
+        assert!(
+            summary.contains("- **latch**: generated [Cylinder(3, 12)]"),
+            "{}",
+            summary
+        );
+        assert!(!summary.contains("Box(1, 1, 1)"), "{}", summary);
+
+        let prompt = build_part_prompt(
+            "",
+            &parts[1],
+            "plan",
+            &crate::config::AppConfig::default(),
+            &summary,
+            "",
+        );
+        assert!(prompt.contains("Box(42, 28, 8)"));
+
+        // `retry_part` has no plan, only the generated siblings.
+        let summary = build_sibling_dimensions_summary(&[], "lid", &siblings);
+        assert!(summary.contains("- **housing**: generated [width = 42, Box(42, 28, 8)]"));
+        assert!(build_sibling_dimensions_summary(&[], "lid", &[]).is_empty());
+    }
+
     #[test]
     fn test_resolve_cross_references_adds_dimensions() {
         let mut plan = GenerationPlan {
@@ -3766,14 +8577,17 @@ End of response."#;
                     description: "Main shell 42mm wide, 28mm deep, 7.5mm tall".to_string(),
                     position: [0.0, 0.0, 0.0],
                     constraints: vec![],
+                    reliability_profile: None,
                 },
                 PartSpec {
                     name: "back_plate".to_string(),
                     description: "Cover plate".to_string(),
                     position: [0.0, 0.0, 0.0],
                     constraints: vec!["must match housing inner bore".to_string()],
+                    reliability_profile: None,
                 },
             ],
+            assembly_ops: vec![],
         };
 
         resolve_cross_references(&mut plan);
@@ -3802,49 +8616,799 @@ End of response."#;
                     description: "Main shell 42mm wide".to_string(),
                     position: [0.0, 0.0, 0.0],
                     constraints: vec![],
+                    reliability_profile: None,
                 },
                 PartSpec {
                     name: "back_plate".to_string(),
                     description: "Cover plate".to_string(),
                     position: [0.0, 0.0, 0.0],
                     constraints: vec!["inner bore 42mm to match housing".to_string()],
+                    reliability_profile: None,
                 },
             ],
+            assembly_ops: vec![],
+        };
+
+        let original = plan.parts[1].constraints[0].clone();
+        resolve_cross_references(&mut plan);
+
+        assert_eq!(
+            plan.parts[1].constraints[0], original,
+            "constraint with existing dimension should be left unchanged"
+        );
+    }
+
+    #[test]
+    fn test_build_part_prompt_includes_sibling_summary() {
+        let part = PartSpec {
+            name: "back_plate".to_string(),
+            description: "Cover plate 40x26x1.5mm".to_string(),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+
+        let sibling_text = "## Sibling Parts (for dimensional reference)\n### Sibling part: housing\nDescription: Main shell 42x28x7.5mm\nDimensions found: 42mm, 28mm, 7.5mm\n";
+
+        let config = crate::config::AppConfig::default();
+        let prompt =
+            build_part_prompt("system", &part, "design context", &config, sibling_text, "");
+
+        assert!(
+            prompt.contains("Sibling Parts"),
+            "prompt should include sibling summary section"
+        );
+        assert!(
+            prompt.contains("42"),
+            "prompt should include sibling dimensions"
+        );
+    }
+
+    #[test]
+    fn test_build_part_prompt_includes_house_style_once() {
+        let part = PartSpec {
+            name: "bracket".to_string(),
+            description: "L bracket".to_string(),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+        let mut config = crate::config::AppConfig::default();
+        config.custom_system_prompt_suffix =
+            Some("Always add 0.2mm clearance on mating features.".to_string());
+
+        let prompt = build_part_prompt("system", &part, "ctx", &config, "", "");
+        assert_eq!(prompt.matches("0.2mm clearance").count(), 1);
+        assert!(prompt.contains(crate::agent::prompts::HOUSE_STYLE_HEADING));
+
+        let (section, _) = crate::agent::prompts::house_style_section(
+            config.custom_system_prompt_suffix.as_deref(),
+        )
+        .unwrap();
+        let system = format!("system\n\n{}", section);
+        let prompt = build_part_prompt(&system, &part, "ctx", &config, "", "");
+        assert_eq!(prompt.matches("0.2mm clearance").count(), 1);
+    }
+
+    #[test]
+    fn test_build_part_prompt_injects_fastener_helpers_for_its_part() {
+        let plate = PartSpec {
+            name: "plate".to_string(),
+            description: "Base plate with four M4 countersunk holes on a 30mm bolt circle"
+                .to_string(),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+        let lid = PartSpec {
+            name: "lid".to_string(),
+            description: "Flat lid".to_string(),
+            constraints: vec!["Heat-set insert bosses for M3 at each corner".to_string()],
+            ..plate.clone()
+        };
+        let config = crate::config::AppConfig::default();
+
+        let prompt = build_part_prompt("system", &plate, "ctx", &config, "", "");
+        assert!(prompt.contains(features::FASTENER_FEATURES_HEADING));
+        assert!(prompt.contains("def m4_countersunk_holes(part, top_z, depth, positions=[(15, 0)"));
+        assert!(!prompt.contains("m3_insert_bosses"));
+        // The bolt circle is covered by the fastener helper, not a pattern loop.
+        assert!(!prompt.contains(features::PATTERN_FEATURES_HEADING));
+
+        let prompt = build_part_prompt("system", &lid, "ctx", &config, "", "");
+        assert!(prompt.contains("def m3_insert_bosses("));
+        assert!(!prompt.contains("m4_countersunk_holes"));
+
+        let vented = PartSpec {
+            name: "cover".to_string(),
+            description: "Cover with a grid of 6x4 ventilation slots spaced 12mm".to_string(),
+            constraints: vec![],
+            ..plate.clone()
+        };
+        let prompt = build_part_prompt("system", &vented, "ctx", &config, "", "");
+        assert!(prompt.contains(features::PATTERN_FEATURES_HEADING));
+        assert!(prompt.contains(
+            "for loc in GridLocations(slot_x_spacing, slot_y_spacing, slot_columns, slot_rows)"
+        ));
+    }
+
+    #[test]
+    fn test_build_part_prompt_states_clearance_for_active_process() {
+        let lid = PartSpec {
+            name: "lid".to_string(),
+            description: "Lid that snaps onto the base".to_string(),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: None,
+        };
+        let mut config = crate::config::AppConfig::default();
+
+        let prompt = build_part_prompt("system", &lid, "ctx", &config, "", "");
+        assert!(prompt.contains("## Clearance Target (FDM)"));
+        assert!(prompt.contains("snap fit: 0.2–0.3mm"));
+
+        config.manufacturing_process = Some("sla".to_string());
+        let prompt = build_part_prompt("system", &lid, "ctx", &config, "", "");
+        assert!(prompt.contains("## Clearance Target (SLA)"));
+
+        let plate = PartSpec {
+            description: "Solid base plate".to_string(),
+            ..lid
+        };
+        let prompt = build_part_prompt("system", &plate, "ctx", &config, "", "");
+        assert!(!prompt.contains(clearance::CLEARANCE_TARGET_HEADING));
+    }
+
+    #[tokio::test]
+    async fn test_system_prompt_ends_with_house_style_after_retrieval() {
+        use super::build_system_prompt_with_retrieval;
+        use crate::agent::prompts::{HOUSE_STYLE_HEADING, MAX_HOUSE_STYLE_CHARS};
+        let (on_event, events) = capture_events();
+        let mut config = crate::config::AppConfig::default();
+        config.retrieval_embeddings = crate::config::RetrievalEmbeddingsMode::Disabled;
+        config.custom_system_prompt_suffix = Some("Use metric coarse threads.".to_string());
+
+        let (prompt, _) = build_system_prompt_with_retrieval(
+            &config,
+            None,
+            "M6 threaded standoff",
+            None,
+            &on_event,
+            true,
+        )
+        .await;
+        let heading = prompt.find(HOUSE_STYLE_HEADING).expect("house style section");
+        assert!(prompt[heading..].contains("Use metric coarse threads."));
+        assert!(prompt.trim_end().ends_with("## End of House Style"));
+
+        config.custom_system_prompt_suffix = Some("x".repeat(MAX_HOUSE_STYLE_CHARS * 2));
+        let (prompt, _) =
+            build_system_prompt_with_retrieval(&config, None, "standoff", None, &on_event, true)
+                .await;
+        assert!(prompt.contains(&"x".repeat(MAX_HOUSE_STYLE_CHARS)));
+        assert!(!prompt.contains(&"x".repeat(MAX_HOUSE_STYLE_CHARS + 1)));
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|e| e["code"] == "house_style_truncated"));
+    }
+
+    #[test]
+    fn test_part_reliability_profile_overrides_global_policy() {
+        use crate::config::GenerationReliabilityProfile;
+
+        let part = |name: &str, profile: Option<GenerationReliabilityProfile>| PartSpec {
+            name: name.to_string(),
+            description: format!("{} part", name),
+            position: [0.0, 0.0, 0.0],
+            constraints: vec![],
+            reliability_profile: profile,
         };
+        let enclosure = part("enclosure", None);
+        let pin = part("pin", Some(GenerationReliabilityProfile::FidelityFirst));
 
-        let original = plan.parts[1].constraints[0].clone();
-        resolve_cross_references(&mut plan);
+        let mut config = crate::config::AppConfig::default();
+        config.generation_reliability_profile = GenerationReliabilityProfile::ReliabilityFirst;
+
+        let enclosure_prompt = build_part_prompt("system", &enclosure, "ctx", &config, "", "");
+        let pin_prompt = build_part_prompt("system", &pin, "ctx", &config, "", "");
+        assert!(enclosure_prompt.contains("Active reliability policy: reliability_first"));
+        assert!(pin_prompt.contains("Active reliability policy: fidelity_first"));
+        assert!(!pin_prompt.contains("Active reliability policy: reliability_first"));
 
         assert_eq!(
-            plan.parts[1].constraints[0], original,
-            "constraint with existing dimension should be left unchanged"
+            pin.acceptance_config(&config).generation_reliability_profile,
+            GenerationReliabilityProfile::FidelityFirst
+        );
+        assert_eq!(
+            enclosure.acceptance_config(&config).generation_reliability_profile,
+            GenerationReliabilityProfile::ReliabilityFirst
+        );
+
+        let parsed: PartSpec = serde_json::from_str(
+            r#"{"name":"pin","description":"d","position":[0,0,0],"reliability_profile":"balanced"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.reliability_profile,
+            Some(GenerationReliabilityProfile::Balanced)
         );
     }
 
     #[test]
-    fn test_build_part_prompt_includes_sibling_summary() {
-        let part = PartSpec {
-            name: "back_plate".to_string(),
-            description: "Cover plate 40x26x1.5mm".to_string(),
+    fn test_mechanism_reference_code_reaches_matching_part_only() {
+        use super::{part_reference_section, retrieval};
+
+        let part = |name: &str, description: &str| PartSpec {
+            name: name.to_string(),
+            description: description.to_string(),
             position: [0.0, 0.0, 0.0],
             constraints: vec![],
+            reliability_profile: None,
         };
+        let retrieved = vec![retrieval::RetrievedContextItem {
+            source: "mechanism".to_string(),
+            pack: "core".to_string(),
+            id: "mechanism:gt2_pulley".to_string(),
+            title: "GT2 Pulley (gt2_pulley)".to_string(),
+            score: 0.7,
+            boosted: false,
+            code: Some("def gt2_pulley(teeth, bore):\n    return Cylinder(teeth, 6)".to_string()),
+        }];
+        let config = crate::config::AppConfig::default();
 
-        let sibling_text = "## Sibling Parts (for dimensional reference)\n### Sibling part: housing\nDescription: Main shell 42x28x7.5mm\nDimensions found: 42mm, 28mm, 7.5mm\n";
+        let pulley = part("motor_pulley", "20-tooth GT2 pulley on a 5mm shaft");
+        let reference = part_reference_section(&retrieved, &pulley);
+        let prompt = build_part_prompt("system", &pulley, "ctx", &config, "", &reference);
+        assert!(prompt.contains("## Reference Mechanism Code"));
+        assert!(prompt.contains("def gt2_pulley(teeth, bore):"));
+        // The reference sits after the output contract, before the reminder.
+        let contract = prompt.find("STRICT OUTPUT CONTRACT").unwrap();
+        let section = prompt.find("## Reference Mechanism Code").unwrap();
+        assert!(contract < section && section < prompt.find("REMINDER").unwrap());
+
+        let bracket = part("motor_bracket", "L bracket holding a NEMA17 motor");
+        assert!(part_reference_section(&retrieved, &bracket).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_preview_triangle_budget_plumbing() {
+        use base64::Engine;
+
+        fn binary_stl(triangles: u32) -> Vec<u8> {
+            let mut stl = vec![0u8; 80];
+            stl.extend_from_slice(&triangles.to_le_bytes());
+            stl.resize(84 + 50 * triangles as usize, 0);
+            stl
+        }
+
+        assert_eq!(executor::stl_triangle_count(&binary_stl(3)), 3);
+        assert_eq!(
+            executor::stl_triangle_count(b"solid s\nfacet normal 0 0 1\nendfacet\nfacet normal 0 0 1\nendfacet\nendsolid s\n"),
+            2
+        );
 
         let config = crate::config::AppConfig::default();
-        let prompt = build_part_prompt("system", &part, "design context", &config, sibling_text);
+        assert_eq!(config.preview_max_triangles, 50_000);
+
+        let mut ctx = executor::ExecutionContext {
+            venv_dir: std::env::temp_dir().join("cadai-no-such-venv"),
+            runner_script: std::path::PathBuf::from("runner.py"),
+            config,
+        };
+        let engine = base64::engine::general_purpose::STANDARD;
+        let small = engine.encode(binary_stl(10));
+        let large = engine.encode(binary_stl(200));
+
+        // Within budget, or budget disabled: passed through without running Python.
+        ctx.config.preview_max_triangles = 100;
+        assert_eq!(preview_stl_base64(small.clone(), &ctx).await, small);
+        ctx.config.preview_max_triangles = 0;
+        assert_eq!(preview_stl_base64(large.clone(), &ctx).await, large);
+
+        // Over budget but decimation unavailable: the full mesh is still shown.
+        ctx.config.preview_max_triangles = 100;
+        assert_eq!(preview_stl_base64(large.clone(), &ctx).await, large);
+    }
+
+    fn outcome(success: bool, validated: bool) -> PipelineOutcome {
+        PipelineOutcome {
+            response: "Here is your bracket.".to_string(),
+            final_code: success.then(|| "result = Box(10, 10, 2)".to_string()),
+            success,
+            validated,
+            error: (!success).then(|| "lid failed to execute".to_string()),
+            validation_attempts: Some(2),
+            static_findings: vec![],
+            post_check_soft_failed: false,
+            post_check_soft_fail_reason: None,
+            part_acceptance_rate: Some(0.5),
+            assembly_success_rate: None,
+            partial_preview_shown: false,
+            empty_viewport_after_generation: !success,
+            retry_ladder_stage_reached: None,
+            failure_signatures: vec!["lid:GeometryKernel".to_string()],
+            model_escalations: vec![],
+            repair_example_uses: vec![],
+            geometry_hash: None,
+            awaiting_assembly_approval: false,
+            failure_localization: None,
+            injected_mechanism_ids: vec![],
+        }
+    }
+
+    #[test]
+    fn test_generation_result_from_successful_outcome() {
+        let result = GenerationResult::from_outcome(&outcome(true, true), Some(0.042));
+        assert!(result.success);
+        assert!(result.validated);
+        assert_eq!(result.final_code.as_deref(), Some("result = Box(10, 10, 2)"));
+        assert_eq!(result.part_acceptance_rate, Some(0.5));
+        assert_eq!(result.total_cost_usd, Some(0.042));
+        assert_eq!(result.response, "Here is your bracket.");
+
+        let json = serde_json::to_value(&result).unwrap();
+        for key in [
+            "success",
+            "final_code",
+            "validated",
+            "part_acceptance_rate",
+            "total_cost_usd",
+            "failure_signatures",
+            "geometry_hash",
+        ] {
+            assert!(json.get(key).is_some(), "missing {}", key);
+        }
+    }
+
+    #[test]
+    fn test_generation_result_from_failed_outcome() {
+        let result = GenerationResult::from_outcome(&outcome(false, false), None);
+        assert!(!result.success);
+        assert!(!result.validated);
+        assert!(result.final_code.is_none());
+        assert!(result.total_cost_usd.is_none());
+        assert_eq!(result.failure_signatures, vec!["lid:GeometryKernel"]);
+        assert_eq!(result.error.as_deref(), Some("lid failed to execute"));
+    }
+
+    /// Minimal Ollama `/api/chat` stand-in that counts requests and always
+    /// answers with `plan_json`.
+    async fn mock_ollama(
+        plan_json: &'static str,
+    ) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        mock_ollama_replies(vec![plan_json]).await
+    }
+
+    /// Like `mock_ollama`, answering the n-th request with `replies[n]` (the
+    /// last reply repeats).
+    async fn mock_ollama_replies(
+        replies: Vec<&'static str>,
+    ) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let counter = counter.clone();
+                let replies = replies.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    loop {
+                        let n = socket.read(&mut chunk).await.unwrap_or(0);
+                        if n == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                        let text = String::from_utf8_lossy(&buf).to_string();
+                        let Some(header_end) = text.find("\r\n\r\n") else {
+                            continue;
+                        };
+                        let length = text[..header_end]
+                            .lines()
+                            .find_map(|l| {
+                                let (k, v) = l.split_once(':')?;
+                                k.eq_ignore_ascii_case("content-length")
+                                    .then(|| v.trim().parse::<usize>().ok())?
+                            })
+                            .unwrap_or(0);
+                        if buf.len() >= header_end + 4 + length {
+                            break;
+                        }
+                    }
+                    let n = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let reply = replies[n.min(replies.len() - 1)];
+                    // Newline-terminated so streaming (NDJSON) callers see it too.
+                    let body = format!(
+                        "{}\n",
+                        serde_json::json!({
+                            "message": { "content": reply },
+                            "done": true,
+                        })
+                    );
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn dropped_channel_after_plan_stops_provider_calls() {
+        use super::{run_generation_pipeline, EventEnvelope, EventSink};
+        use crate::agent::run_state::RunStore;
+        use crate::ai::provider::TokenUsage;
+        use crate::error::AppError;
+        const PLAN_JSON: &str = r#"{"mode":"multi","description":"stool","parts":[
+            {"name":"seat","description":"round seat","position":[0,0,400],"constraints":[]},
+            {"name":"leg","description":"single leg","position":[0,0,0],"constraints":[]}
+        ]}"#;
+
+        let (url, requests) = mock_ollama(PLAN_JSON).await;
+        let mut config = crate::config::AppConfig::default();
+        config.ai_provider = "ollama".to_string();
+        config.model = "test-model".to_string();
+        config.ollama_base_url = Some(url);
+        config.enable_code_review = false;
+        config.enable_consensus = false;
+
+        // The webview goes away once the plan has been shown.
+        let dropped = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = dropped.clone();
+        let raw = tauri::ipc::Channel::<EventEnvelope>::new(move |body| {
+            if flag.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(tauri::Error::WebviewNotFound);
+            }
+            if let tauri::ipc::InvokeResponseBody::Json(json) = body {
+                if json.contains("\"PlanResult\"") {
+                    flag.store(true, std::sync::atomic::Ordering::SeqCst);
+                }
+            }
+            Ok(())
+        });
+        let on_event = EventSink::new(raw);
+        let mut usage = TokenUsage::default();
+        let run_store = std::sync::Mutex::new(RunStore::default());
+
+        let result = run_generation_pipeline(
+            "Two-part stool",
+            "Make a stool with a seat and a leg",
+            vec![],
+            &config,
+            "system",
+            &on_event,
+            None,
+            &mut usage,
+            "ollama",
+            "test-model",
+            &HashMap::new(),
+            &HashMap::new(),
+            &run_store,
+            &[],
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::ChannelDisconnected(_))));
+        // Only the planner call went out; no part generation was started.
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn approval_gate_parks_run_until_assembly_is_approved() {
+        use super::{approve_parked_run, run_generation_pipeline};
+        use crate::agent::run_state::RunStore;
+        use crate::ai::provider::TokenUsage;
+        const PLAN_JSON: &str = r#"{"mode":"multi","description":"box","parts":[
+            {"name":"base","description":"open box","position":[0,0,0],"constraints":[]},
+            {"name":"lid","description":"flat lid","position":[0,0,20],"constraints":[]}
+        ]}"#;
+        const PART_CODE: &str = "```python\nfrom build123d import *\nresult = Box(40, 40, 5)\n```";
+
+        let (url, _requests) = mock_ollama_replies(vec![PLAN_JSON, PART_CODE]).await;
+        let mut config = crate::config::AppConfig::default();
+        config.ai_provider = "ollama".to_string();
+        config.model = "test-model".to_string();
+        config.ollama_base_url = Some(url);
+        config.enable_code_review = false;
+        config.enable_consensus = false;
+        config.pause_before_assembly = true;
+        config.safe_mode = true;
+        let (on_event, events) = capture_events();
+        let mut usage = TokenUsage::default();
+        let run_store = std::sync::Mutex::new(RunStore::default());
+
+        let outcome = run_generation_pipeline(
+            "Box with lid",
+            "Make a box with a lid",
+            vec![],
+            &config,
+            "system",
+            &on_event,
+            None,
+            &mut usage,
+            "ollama",
+            "test-model",
+            &HashMap::new(),
+            &HashMap::new(),
+            &run_store,
+            &[],
+        )
+        .await
+        .unwrap();
+
+        // Gate: parts are accepted, nothing is assembled yet.
+        assert!(outcome.awaiting_assembly_approval);
+        assert!(outcome.final_code.is_none());
+        {
+            let events = events.lock().unwrap();
+            let gate = events
+                .iter()
+                .find(|e| e["kind"] == "AwaitingAssemblyApproval")
+                .expect("AwaitingAssemblyApproval event");
+            assert_eq!(gate["accepted_parts"].as_array().unwrap().len(), 2);
+            assert!(!events.iter().any(|e| e["kind"] == "FinalCode"));
+        }
+
+        // Approve: the parked run is assembled from its stored parts.
+        let (approve_event, approve_events) = capture_events();
+        let code = approve_parked_run(
+            &run_store,
+            on_event.run_id(),
+            vec![],
+            vec![],
+            &config,
+            None,
+            &HashMap::new(),
+            &HashMap::new(),
+            None,
+            &approve_event,
+        )
+        .await
+        .unwrap();
 
         assert!(
-            prompt.contains("Sibling Parts"),
-            "prompt should include sibling summary section"
+            code.contains("part_base") && code.contains("part_lid"),
+            "{}",
+            code
         );
+        let approve_events = approve_events.lock().unwrap();
+        assert!(approve_events.iter().any(|e| e["kind"] == "FinalCode"));
+        let done = approve_events
+            .iter()
+            .find(|e| e["kind"] == "Done")
+            .expect("Done event");
+        assert_eq!(done["success"], true);
+        // The run is no longer parked, so it cannot be approved twice.
+        assert!(run_store.lock().unwrap().unpark(on_event.run_id()).is_err());
+    }
+
+    #[test]
+    fn assembly_approval_note_records_choices_and_outcome() {
+        let note = super::assembly_approval_note("gen-1", &[2], &[0], true);
+        assert_eq!(note.run_id, "gen-1");
+        assert_eq!(note.kind, "assembly_approved");
         assert!(
-            prompt.contains("42"),
-            "prompt should include sibling dimensions"
+            note.message.contains("excluded parts [2]"),
+            "{}",
+            note.message
+        );
+        assert!(
+            note.message.contains("re-rolled parts [0]"),
+            "{}",
+            note.message
         );
+        assert!(note.message.ends_with("succeeded"), "{}", note.message);
+    }
+
+    #[tokio::test]
+    async fn scoped_modification_rewrites_only_the_target_part() {
+        use super::{assemble_parts, run_scoped_modification};
+        use crate::agent::modify;
+        use crate::ai::provider::TokenUsage;
+
+        let old_code = assemble_parts(
+            &[
+                ("base".to_string(), "result = Box(60, 40, 20)".to_string(), [0.0, 0.0, 0.0]),
+                ("lid".to_string(), "result = Cylinder(30, 3)".to_string(), [0.0, 0.0, 21.5]),
+            ],
+            &[],
+            &[],
+            &HashMap::new(),
+        )
+        .unwrap();
+        let (url, requests) =
+            mock_ollama("```python\nfrom build123d import *\nresult = Box(60, 40, 5)\n```").await;
+        let mut config = crate::config::AppConfig::default();
+        config.ai_provider = "ollama".to_string();
+        config.model = "test-model".to_string();
+        config.ollama_base_url = Some(url);
+        let (on_event, events) = capture_events();
+        let mut usage = TokenUsage::default();
+
+        let scope = modify::resolve_part_scope(&old_code, "make the lid 2mm taller", None)
+            .unwrap()
+            .expect("lid is named in the request");
+        let outcome = run_scoped_modification(
+            &scope,
+            &old_code,
+            "make the lid 2mm taller",
+            vec![],
+            &config,
+            "system",
+            None,
+            &on_event,
+            &mut usage,
+            &HashMap::new(),
+            &std::sync::Mutex::new(crate::agent::run_state::RunStore::default()),
+        )
+        .await
+        .unwrap();
+
+        assert!(outcome.success, "{:?}", outcome.error);
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+        let new_code = outcome.final_code.unwrap();
+        assert!(new_code.contains("part_lid = Box(60, 40, 5)"));
+        assert!(modify::only_part_changed(&old_code, &new_code, "lid"));
+
+        let events = events.lock().unwrap();
+        let kinds: Vec<&str> = events.iter().filter_map(|e| e["kind"].as_str()).collect();
+        assert!(kinds.contains(&"PartDelta"), "{:?}", kinds);
+        assert!(kinds.contains(&"FinalCode"), "{:?}", kinds);
+        for event in events.iter() {
+            if matches!(event["kind"].as_str(), Some("PartDelta" | "PartComplete")) {
+                assert_eq!(event["part_index"], 1);
+                assert_eq!(event["part_name"], "lid");
+            }
+        }
+    }
+
+    #[test]
+    fn test_attempt_caps_are_at_least_one() {
+        use super::{design_replan_attempts, planner_parse_attempts};
+        let mut config = crate::config::AppConfig::default();
+        assert_eq!(design_replan_attempts(&config), 3);
+        assert_eq!(planner_parse_attempts(&config), 3);
+        config.max_design_replan_attempts = 0;
+        config.max_planner_parse_attempts = 0;
+        assert_eq!(design_replan_attempts(&config), 1);
+        assert_eq!(planner_parse_attempts(&config), 1);
+    }
+
+    #[tokio::test]
+    async fn planner_parse_cap_of_one_skips_retries() {
+        use super::run_generation_pipeline;
+        use crate::agent::run_state::RunStore;
+        use crate::ai::provider::TokenUsage;
+
+        let (url, requests) = mock_ollama("not a plan").await;
+        let mut config = crate::config::AppConfig::default();
+        config.ai_provider = "ollama".to_string();
+        config.model = "test-model".to_string();
+        config.ollama_base_url = Some(url);
+        config.max_planner_parse_attempts = 1;
+        let (on_event, events) = capture_events();
+        let mut usage = TokenUsage::default();
+        let run_store = std::sync::Mutex::new(RunStore::default());
+
+        let result = run_generation_pipeline(
+            "Multi-part enclosure",
+            "Make a multi-part enclosure with a separate lid",
+            vec![],
+            &config,
+            "system",
+            &on_event,
+            None,
+            &mut usage,
+            "ollama",
+            "test-model",
+            &HashMap::new(),
+            &HashMap::new(),
+            &run_store,
+            &[],
+        )
+        .await;
+
+        let err = result.err().expect("unparseable plan must fail").to_string();
+        assert!(err.contains("after 1 attempt(s)"), "{}", err);
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(!events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e.to_string().contains("retrying")));
+    }
+
+    #[tokio::test]
+    async fn design_replan_cap_of_one_skips_replanning() {
+        use super::run_design_plan_phase;
+        use crate::ai::provider::TokenUsage;
+
+        // No required sections, so the validator rejects it.
+        let (url, requests) = mock_ollama("Just make a box.").await;
+        let mut config = crate::config::AppConfig::default();
+        config.ai_provider = "ollama".to_string();
+        config.model = "test-model".to_string();
+        config.ollama_base_url = Some(url);
+        config.max_design_replan_attempts = 1;
+        let (on_event, events) = capture_events();
+        let mut usage = TokenUsage::default();
+        let state = crate::state::AppState::default();
+
+        let (_, result) = run_design_plan_phase(
+            "a box",
+            &config,
+            &on_event,
+            &mut usage,
+            "ollama",
+            "test-model",
+            &state,
+        )
+        .await
+        .unwrap();
+
+        assert!(!result.is_valid);
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(!events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e.to_string().contains("re-planning")));
     }
 
+    #[tokio::test]
+    async fn stepless_build_plan_requests_numbered_steps_only() {
+        use super::run_design_plan_phase;
+        use crate::ai::provider::TokenUsage;
+
+        const STEPLESS_PLAN: &str = "### Object Analysis\n- A 40x20x5mm mounting plate\n\n\
+            ### CAD Approach\n- Box with two through holes\n\n\
+            ### Build Plan\n- Make the 40x20x5mm plate\n- Cut two 4mm holes 30mm apart\n\n\
+            ### Approximation Notes\n- Holes are plain cylinders";
+        const STEPS: &str = "1. Create a 40x20x5mm box\n2. Cut two 4mm through holes 30mm apart";
+        let (url, requests) = mock_ollama_replies(vec![STEPLESS_PLAN, STEPS]).await;
+        let mut config = crate::config::AppConfig::default();
+        config.ai_provider = "ollama".to_string();
+        config.model = "test-model".to_string();
+        config.ollama_base_url = Some(url);
+        config.max_design_replan_attempts = 1;
+        config.replan_on_low_confidence = false;
+        let (on_event, events) = capture_events();
+        let mut usage = TokenUsage::default();
+        let state = crate::state::AppState::default();
+
+        let (plan, result) = run_design_plan_phase(
+            "a mounting plate",
+            &config,
+            &on_event,
+            &mut usage,
+            "ollama",
+            "test-model",
+            &state,
+        )
+        .await
+        .unwrap();
+
+        // One plan request plus one numbered-steps request, no full re-plan.
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e.to_string().contains("no numbered steps")));
+        assert!(plan.text.contains("1. Create a 40x20x5mm box"));
+        assert!(plan.text.contains("- A 40x20x5mm mounting plate"));
+        assert!(plan.text.contains("### Approximation Notes"));
+        assert!(!result
+            .warnings
+            .iter()
+            .any(|w| w.contains("must include numbered steps")));
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -3857,13 +9421,14 @@ pub async fn retry_skipped_steps(
     skipped_steps: Vec<iterative::SkippedStep>,
     design_plan_text: String,
     user_request: String,
-    on_event: Channel<MultiPartEvent>,
+    on_event: Channel<EventEnvelope>,
     state: State<'_, AppState>,
 ) -> Result<String, AppError> {
+    let on_event = EventSink::register(&state.run_events, on_event);
     let config = state.config.lock().unwrap().clone();
     let cq_version = state.build123d_version.lock().unwrap().clone();
 
-    let system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
+    let mut system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
         prompts::build_finetuned_system_prompt()
     } else {
         let mut sp = crate::agent::prompts::build_system_prompt_for_preset(
@@ -3884,26 +9449,17 @@ pub async fn retry_skipped_steps(
         }
         sp
     };
+    prompts::append_house_style(
+        &mut system_prompt,
+        config.custom_system_prompt_suffix.as_deref(),
+    );
 
     let provider_id = config.ai_provider.clone();
     let model_id = config.model.clone();
     let mut total_usage = TokenUsage::default();
 
     // Resolve execution context
-    let execution_ctx = {
-        let venv_path = state.venv_path.lock().unwrap().clone();
-        match venv_path {
-            Some(venv_dir) => match super::find_python_script("runner.py") {
-                Ok(runner_script) => Some(executor::ExecutionContext {
-                    venv_dir,
-                    runner_script,
-                    config: config.clone(),
-                }),
-                Err(_) => None,
-            },
-            None => None,
-        }
-    };
+    let execution_ctx = execution_context(&state, &config);
 
     let ctx = execution_ctx.ok_or_else(|| {
         AppError::CadError("Python environment not available for retry".to_string())
@@ -3996,6 +9552,7 @@ pub async fn retry_skipped_steps(
     let _ = on_event.send(MultiPartEvent::FinalCode {
         code: result.final_code.clone(),
         stl_base64: result.stl_base64.clone(),
+        geometry_hash: executor::geometry_hash_base64(result.stl_base64.as_deref()),
     });
 
     let _ = on_event.send(MultiPartEvent::IterativeComplete {
@@ -4025,47 +9582,131 @@ pub async fn retry_skipped_steps(
 // Retry a single failed part
 // ---------------------------------------------------------------------------
 
+/// Regenerate one part of a multi-part result. `sibling_parts` are the
+/// `(name, code)` of the parts being kept; their generated dimensions are
+/// given to the prompt so the new part still mates with them.
 #[tauri::command]
 pub async fn retry_part(
     part_index: usize,
     part_spec: PartSpec,
     design_plan_text: String,
     user_request: String,
-    on_event: Channel<MultiPartEvent>,
+    sibling_parts: Vec<(String, String)>,
+    on_event: Channel<EventEnvelope>,
     state: State<'_, AppState>,
 ) -> Result<String, AppError> {
+    let on_event = EventSink::register(&state.run_events, on_event);
     let config = state.config.lock().unwrap().clone();
     let cq_version = state.build123d_version.lock().unwrap().clone();
+    let venv_path = state.venv_path.lock().unwrap().clone();
+    let part_colors = state.part_colors.lock().unwrap().clone();
+    let mut total_usage = TokenUsage::default();
+    let sibling_summary = build_sibling_dimensions_summary(&[], &part_spec.name, &sibling_parts);
 
-    let system_prompt = if prompts::is_finetuned_provider(&config.ai_provider) {
-        prompts::build_finetuned_system_prompt()
+    let regenerated = regenerate_part(
+        part_index,
+        &part_spec,
+        &design_plan_text,
+        &user_request,
+        &config,
+        cq_version.as_deref(),
+        venv_path,
+        &on_event,
+        &mut total_usage,
+        &part_colors,
+        &sibling_summary,
+    )
+    .await?;
+    if total_usage.total() > 0 {
+        emit_usage(
+            &on_event,
+            "total",
+            &total_usage,
+            &config.ai_provider,
+            &config.model,
+        );
+    }
+    match regenerated {
+        Some(part) => {
+            let _ = on_event.send(MultiPartEvent::Done {
+                success: true,
+                error: None,
+                validated: false,
+            });
+            Ok(part.code)
+        }
+        None => {
+            let _ = on_event.send(MultiPartEvent::Done {
+                success: false,
+                error: Some("No code extracted from retry response".to_string()),
+                validated: false,
+            });
+            Err(AppError::AiProviderError(
+                "No code extracted from retry response".to_string(),
+            ))
+        }
+    }
+}
+
+/// A part regenerated outside its run, with its preview outcome.
+struct RegeneratedPart {
+    code: String,
+    stl_base64: Option<String>,
+    preview_error: Option<String>,
+}
+
+/// Generate one part again and build its preview STL, streaming part events.
+/// Returns `None` when the response has no code; emits no `Done`.
+#[allow(clippy::too_many_arguments)]
+async fn regenerate_part(
+    part_index: usize,
+    part_spec: &PartSpec,
+    design_plan_text: &str,
+    user_request: &str,
+    config: &crate::config::AppConfig,
+    cq_version: Option<&str>,
+    venv_path: Option<std::path::PathBuf>,
+    on_event: &EventSink,
+    total_usage: &mut TokenUsage,
+    part_colors: &HashMap<String, PartColor>,
+    sibling_summary: &str,
+) -> Result<Option<RegeneratedPart>, AppError> {
+    let (system_prompt, reference) = if prompts::is_finetuned_provider(&config.ai_provider) {
+        (prompts::build_finetuned_system_prompt(), String::new())
     } else {
         // Use compact prompt for part retries (multi-part context)
         let mut sp = prompts::build_compact_system_prompt_for_preset(
             config.agent_rules_preset.as_deref(),
-            cq_version.as_deref(),
+            cq_version,
         );
         let retrieval_query = format!("{}\n\n{}", design_plan_text, part_spec.description);
         let retrieval_result = retrieval::retrieve_context(
             &retrieval_query,
-            &config,
+            config,
             config.agent_rules_preset.as_deref(),
-            cq_version.as_deref(),
+            cq_version,
         )
         .await;
         if !retrieval_result.context_markdown.is_empty() {
             sp.push_str("\n\n");
             sp.push_str(&retrieval_result.context_markdown);
         }
-        sp
+        let reference = part_reference_section(&retrieval_result.items, part_spec);
+        (sp, reference)
     };
 
     let provider_id = config.ai_provider.clone();
     let model_id = config.model.clone();
-    let mut total_usage = TokenUsage::default();
 
     // Build part prompt
-    let part_prompt = build_part_prompt(&system_prompt, &part_spec, &design_plan_text, &config, "");
+    let part_prompt = build_part_prompt(
+        &system_prompt,
+        part_spec,
+        design_plan_text,
+        config,
+        sibling_summary,
+        &reference,
+    );
 
     let part_messages = vec![
         ChatMessage {
@@ -4082,25 +9723,40 @@ pub async fn retry_part(
     ];
 
     // Stream generation for the single part
-    let provider = create_provider(&config)?;
-    let (tx, mut rx) = mpsc::channel::<StreamDelta>(100);
+    let provider = create_code_provider(config)?;
+    let (tx, rx) = mpsc::channel::<StreamSignal>(100);
+    let mut stream = StreamWatch::for_provider(provider.as_ref(), rx);
     let provider_handle = tokio::spawn(async move { provider.stream(&part_messages, tx).await });
 
     let mut full_response = String::new();
-    while let Some(delta) = rx.recv().await {
+    let mut deltas_seen = 0usize;
+    while let Some(progress) = stream.next().await {
+        let delta = match stream_step_delta(progress, on_event) {
+            Ok(Some(delta)) => delta,
+            Ok(None) => continue,
+            Err(e) => {
+                provider_handle.abort();
+                return Err(AppError::AiProviderError(e));
+            }
+        };
         full_response.push_str(&delta.content);
         let _ = on_event.send(MultiPartEvent::PartDelta {
             part_index,
             part_name: part_spec.name.clone(),
             delta: delta.content,
         });
+        deltas_seen += 1;
+        if on_event.stream_interrupted(deltas_seen) {
+            provider_handle.abort();
+            return Err(AppError::ChannelDisconnected(on_event.run_id().to_string()));
+        }
     }
 
     match provider_handle.await {
         Ok(Ok(stream_usage)) => {
             if let Some(ref u) = stream_usage {
                 total_usage.add(u);
-                emit_usage(&on_event, "retry_part", u, &provider_id, &model_id);
+                emit_usage(on_event, "retry_part", u, &provider_id, &model_id);
             }
         }
         Ok(Err(e)) => return Err(e),
@@ -4122,17 +9778,17 @@ pub async fn retry_part(
             ),
         });
         let (retried, usage) = request_code_only_part_retry(
-            &config,
+            config,
             &system_prompt,
-            &part_spec,
-            &design_plan_text,
+            part_spec,
+            design_plan_text,
             &full_response,
         )
         .await?;
         if let Some(ref u) = usage {
             total_usage.add(u);
             emit_usage(
-                &on_event,
+                on_event,
                 "retry_part_code_recovery",
                 u,
                 &provider_id,
@@ -4156,61 +9812,54 @@ pub async fn retry_part(
             });
 
             // Run STL execution for retried part and await completion so preview event is delivered.
-            let venv_path = state.venv_path.lock().unwrap().clone();
+            let mut regenerated = RegeneratedPart {
+                code: c,
+                stl_base64: None,
+                preview_error: None,
+            };
             if let Some(venv_dir) = venv_path {
                 if let Ok(runner_script) = super::find_python_script("runner.py") {
-                    let part_code = c.clone();
-                    let part_name = part_spec.name.clone();
-                    let evt_channel = on_event.clone();
-                    let pi = part_index;
                     let preview_ctx = executor::ExecutionContext {
                         venv_dir,
                         runner_script,
-                        config: config.clone(),
+                        config: part_spec.acceptance_config(config),
                     };
                     let semantic_contract = semantic_validate::build_default_contract(
-                        &part_name,
+                        &part_spec.name,
                         &part_spec.description,
                     );
                     match build_part_preview_stl_with_repair(
-                        &part_code,
+                        &regenerated.code,
                         &preview_ctx,
                         &system_prompt,
                         &part_spec.description,
-                        &part_name,
+                        &part_spec.name,
                         Some(&semantic_contract),
                     )
                     .await
                     {
                         Ok(stl_base64) => {
-                            let _ = evt_channel.send(MultiPartEvent::PartStlReady {
-                                part_index: pi,
-                                part_name,
-                                stl_base64,
+                            let _ = on_event.send(MultiPartEvent::PartStlReady {
+                                part_index,
+                                part_name: part_spec.name.clone(),
+                                stl_base64: stl_base64.clone(),
+                                color: Some(part_colors::resolve(&part_spec.name, part_colors)),
                             });
+                            regenerated.stl_base64 = Some(stl_base64);
                         }
                         Err(e) => {
-                            let _ = evt_channel.send(MultiPartEvent::PartStlFailed {
-                                part_index: pi,
-                                part_name: part_name.clone(),
-                                error: e,
+                            let _ = on_event.send(MultiPartEvent::PartStlFailed {
+                                part_index,
+                                part_name: part_spec.name.clone(),
+                                error: e.clone(),
                             });
+                            regenerated.preview_error = Some(e);
                         }
                     }
                 }
             }
 
-            if total_usage.total() > 0 {
-                emit_usage(&on_event, "total", &total_usage, &provider_id, &model_id);
-            }
-
-            let _ = on_event.send(MultiPartEvent::Done {
-                success: true,
-                error: None,
-                validated: false,
-            });
-
-            Ok(c)
+            Ok(Some(regenerated))
         }
         None => {
             let _ = on_event.send(MultiPartEvent::PartComplete {
@@ -4219,14 +9868,96 @@ pub async fn retry_part(
                 success: false,
                 error: Some("No code block found in response".to_string()),
             });
-            let _ = on_event.send(MultiPartEvent::Done {
-                success: false,
-                error: Some("No code extracted from retry response".to_string()),
-                validated: false,
-            });
-            Err(AppError::AiProviderError(
-                "No code extracted from retry response".to_string(),
-            ))
+            Ok(None)
+        }
+    }
+
+    fn batch_result(success: bool, code: Option<&str>) -> super::GenerationResult {
+        super::GenerationResult {
+            success,
+            final_code: code.map(|c| c.to_string()),
+            validated: success,
+            part_acceptance_rate: None,
+            total_cost_usd: None,
+            failure_signatures: vec![],
+            error: (!success).then(|| "validation failed".to_string()),
+            response: String::new(),
+            geometry_hash: None,
         }
     }
+
+    #[tokio::test]
+    async fn batch_emits_item_events_in_order() {
+        let (on_event, events) = capture_events();
+        let prompts = vec!["a bracket".to_string(), "a spacer".to_string()];
+
+        let totals = super::run_batch(prompts, &on_event, |prompt| async move {
+            Ok(batch_result(true, Some(&format!("# {}", prompt))))
+        })
+        .await
+        .unwrap();
+
+        assert_eq!((totals.succeeded, totals.failed), (2, 0));
+        let events = events.lock().unwrap();
+        let kinds: Vec<&str> = events.iter().filter_map(|e| e["kind"].as_str()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "BatchItemStarted",
+                "BatchItemComplete",
+                "BatchItemStarted",
+                "BatchItemComplete",
+                "BatchComplete",
+            ]
+        );
+        assert_eq!(events[2]["index"], 1);
+        assert_eq!(events[2]["prompt"], "a spacer");
+        assert_eq!(events[3]["final_code"], "# a spacer");
+        assert_eq!(events[4]["succeeded"], 2);
+    }
+
+    #[tokio::test]
+    async fn batch_continues_past_failed_items() {
+        use crate::error::AppError;
+
+        let (on_event, events) = capture_events();
+        let prompts = vec![
+            "a hinge".to_string(),
+            "a gear".to_string(),
+            "a knob".to_string(),
+        ];
+        let mut calls = 0;
+
+        let totals = super::run_batch(prompts, &on_event, |prompt| {
+            calls += 1;
+            async move {
+                match prompt.as_str() {
+                    "a hinge" => Err(AppError::AiProviderError("rate limited".to_string())),
+                    "a gear" => Ok(batch_result(false, None)),
+                    _ => Ok(batch_result(true, Some("result = Box(1, 1, 1)"))),
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(calls, 3);
+        assert_eq!((totals.succeeded, totals.failed), (1, 2));
+        let events = events.lock().unwrap();
+        let completed: Vec<&serde_json::Value> = events
+            .iter()
+            .filter(|e| e["kind"] == "BatchItemComplete")
+            .collect();
+        assert_eq!(completed.len(), 3);
+        assert_eq!(completed[0]["success"], false);
+        assert!(completed[0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("rate limited"));
+        assert_eq!(completed[1]["error"], "validation failed");
+        assert_eq!(completed[2]["success"], true);
+        let last = events.last().unwrap();
+        assert_eq!(last["kind"], "BatchComplete");
+        assert_eq!(last["failed"], 2);
+    }
 }
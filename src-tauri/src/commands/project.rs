@@ -1,48 +1,283 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use base64::Engine;
 use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
 use tauri::State;
 
+use crate::agent::assembly_instructions;
+use crate::agent::code_import::{self, CodeParameter, DerivedParameter};
+use crate::agent::executor;
+use crate::agent::export_verify::{
+    self, ExportReference, ExportTolerances, ExportVerificationReport,
+};
+use crate::agent::mass;
+use crate::agent::parametrize::{self, ParameterSuggestion, UncertainLiteral};
+use crate::agent::part_colors::PartColor;
+use crate::agent::static_validate::{self, StaticValidationFinding};
+use crate::agent::transcript;
+use crate::agent::validation_history;
+use crate::agent::views::ViewBookmark;
 use crate::ai::message::ChatMessage;
 use crate::error::AppError;
 use crate::state::AppState;
 
+use super::parallel::emit_code_diff;
+use super::run_events::{EventEnvelope, EventSink};
+
+/// Schema written by `save_project`. v1-v4 files (no generation report, code
+/// history, view bookmarks or part colors) still load with those sections
+/// defaulted; newer files load with unknown sections ignored.
+pub const PROJECT_SCHEMA_VERSION: u32 = 5;
+
+/// Longest plan excerpt placed in the loaded-project context section.
+const MAX_CONTEXT_PLAN_CHARS: usize = 1500;
+
+/// Runner timeout for exporting several named objects as STEP in one run.
+const OBJECT_EXPORT_TIMEOUT_MS: u64 = 60_000;
+
 #[derive(Serialize, Deserialize)]
 pub struct ProjectFile {
     pub name: String,
     pub code: String,
     pub messages: Vec<ChatMessage>,
+    #[serde(default = "legacy_project_version")]
     pub version: u32,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub scene: Option<serde_json::Value>,
+    /// Summary of the generation run that produced `code` (schema 3+).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub generation: Option<ProjectGenerationReport>,
+    /// Earlier versions of the code, oldest first (schema 3+).
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub code_history: Vec<CodeSnapshot>,
+    /// Named viewport cameras from `save_view_bookmark` (schema 4+).
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub view_bookmarks: Vec<ViewBookmark>,
+    /// Colors assigned with `set_part_color`, by part name (schema 5+).
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub part_colors: HashMap<String, PartColor>,
+}
+
+fn legacy_project_version() -> u32 {
+    1
+}
+
+/// What was asked and how the latest generation ended, so a colleague who
+/// opens the project can continue where the original session stopped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProjectGenerationReport {
+    #[serde(default)]
+    pub run_id: Option<String>,
+    #[serde(default)]
+    pub request: String,
+    #[serde(default)]
+    pub plan_text: String,
+    #[serde(default)]
+    pub parts: Vec<ProjectPartStatus>,
+    /// Warnings the user accepted without fixing.
+    #[serde(default)]
+    pub outstanding_warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProjectPartStatus {
+    pub name: String,
+    #[serde(default)]
+    pub accepted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CodeSnapshot {
+    pub code: String,
+    #[serde(default)]
+    pub label: String,
+    /// Unix seconds, when known.
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+}
+
+/// Generation context of the last loaded project, from `get_project_summary`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectSummary {
+    pub name: String,
+    pub schema_version: u32,
+    pub generation: Option<ProjectGenerationReport>,
+    pub snapshot_count: usize,
+    /// The section added to session memory; `None` when the file had no
+    /// generation context.
+    pub context_section: Option<String>,
+}
+
+/// An external script loaded as the starting point for further AI edits.
+#[derive(Serialize)]
+pub struct ImportedCode {
+    pub source_path: String,
+    pub code: String,
+    pub parameters: Vec<CodeParameter>,
+    /// Values computed from `parameters`; recompute or show read-only.
+    pub derived_parameters: Vec<DerivedParameter>,
+    pub static_findings: Vec<StaticValidationFinding>,
+    pub stl_base64: Option<String>,
+    pub post_geometry_report: Option<executor::PostGeometryValidationReport>,
+    pub execution_error: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Outcome of `parametrize_code`.
+#[derive(Debug, Serialize)]
+pub struct ParametrizedCode {
+    /// The rewritten code when it was applied, else the input unchanged.
+    pub code: String,
+    pub applied: bool,
+    pub suggestions: Vec<ParameterSuggestion>,
+    /// Repeated literals left alone for the user to decide.
+    pub uncertain: Vec<UncertainLiteral>,
+    /// Why the rewrite was discarded, e.g. the geometry changed.
+    pub rejected_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartExportFailure {
+    pub name: String,
+    pub error: String,
+}
+
+/// Outcome of `export_parts_step` and `export_objects_step`: files written
+/// and parts that failed.
+#[derive(Debug, Default, Serialize)]
+pub struct PartsStepExport {
+    pub written: Vec<String>,
+    pub failures: Vec<PartExportFailure>,
+}
+
+/// Session-memory section describing a loaded project's generation context.
+fn project_context_section(project: &ProjectFile) -> Option<String> {
+    let report = project.generation.as_ref()?;
+    let mut out = format!(
+        "## Loaded Project\nThis session continues project \"{}\", opened from a saved file. \
+         Treat follow-up requests as modifications of its current code.\n",
+        project.name
+    );
+    if let Some(ref run_id) = report.run_id {
+        out.push_str(&format!("Original run: {}\n", run_id));
+    }
+    if !report.request.trim().is_empty() {
+        out.push_str(&format!("Original request: \"{}\"\n", report.request.trim()));
+    }
+    let plan = report.plan_text.trim();
+    if !plan.is_empty() {
+        let excerpt: String = plan.chars().take(MAX_CONTEXT_PLAN_CHARS).collect();
+        let ellipsis = if excerpt.len() < plan.len() { "\n..." } else { "" };
+        out.push_str(&format!("Design plan:\n{}{}\n", excerpt, ellipsis));
+    }
+    if !report.parts.is_empty() {
+        let parts: Vec<String> = report
+            .parts
+            .iter()
+            .map(|p| {
+                format!(
+                    "{} ({})",
+                    p.name,
+                    if p.accepted { "accepted" } else { "not accepted" }
+                )
+            })
+            .collect();
+        out.push_str(&format!("Parts: {}\n", parts.join(", ")));
+    }
+    if !report.outstanding_warnings.is_empty() {
+        out.push_str("Outstanding warnings accepted by the author:\n");
+        for warning in &report.outstanding_warnings {
+            out.push_str(&format!("- {}\n", warning));
+        }
+    }
+    if !project.code_history.is_empty() {
+        out.push_str(&format!(
+            "Code history: {} earlier version(s) saved with the project.\n",
+            project.code_history.len()
+        ));
+    }
+    Some(out.trim_end().to_string())
+}
+
+fn project_summary(project: &ProjectFile) -> ProjectSummary {
+    ProjectSummary {
+        name: project.name.clone(),
+        schema_version: project.version,
+        generation: project.generation.clone(),
+        snapshot_count: project.code_history.len(),
+        context_section: project_context_section(project),
+    }
+}
+
+fn parse_project(contents: &str) -> Result<ProjectFile, AppError> {
+    serde_json::from_str(contents)
+        .map_err(|e| AppError::ConfigError(format!("Invalid project file: {}", e)))
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn save_project(
     name: String,
     code: String,
     messages: Vec<ChatMessage>,
     path: String,
     scene: Option<serde_json::Value>,
+    generation: Option<ProjectGenerationReport>,
+    code_history: Option<Vec<CodeSnapshot>>,
+    state: State<'_, AppState>,
 ) -> Result<(), AppError> {
+    let view_bookmarks = state.view_bookmarks.lock().unwrap().clone();
+    let part_colors = state.part_colors.lock().unwrap().clone();
     let project = ProjectFile {
         name,
         code,
         messages,
-        version: 2,
+        version: PROJECT_SCHEMA_VERSION,
         scene,
+        generation,
+        code_history: code_history.unwrap_or_default(),
+        view_bookmarks,
+        part_colors,
     };
     let json = serde_json::to_string_pretty(&project)?;
     std::fs::write(&path, json)?;
+    if let Some(run_id) = project.generation.as_ref().and_then(|g| g.run_id.as_deref()) {
+        if let Err(e) = crate::artifacts::pin_run(run_id, &path) {
+            eprintln!("pinning run artifacts failed: {}", e);
+        }
+    }
     Ok(())
 }
 
+/// Load a project and seed session memory with its generation context, so
+/// follow-up requests behave as if the original session continued.
 #[tauri::command]
-pub async fn load_project(path: String) -> Result<ProjectFile, AppError> {
+pub async fn load_project(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<ProjectFile, AppError> {
     let contents = std::fs::read_to_string(&path)?;
-    let project: ProjectFile = serde_json::from_str(&contents)
-        .map_err(|e| AppError::ConfigError(format!("Invalid project file: {}", e)))?;
+    let project = parse_project(&contents)?;
+    let summary = project_summary(&project);
+    state
+        .session_memory
+        .lock()
+        .unwrap()
+        .seed_loaded_project(summary.context_section.clone());
+    *state.loaded_project.lock().unwrap() = Some(summary);
+    *state.view_bookmarks.lock().unwrap() = project.view_bookmarks.clone();
+    *state.part_colors.lock().unwrap() = project.part_colors.clone();
     Ok(project)
 }
 
+/// Generation context of the project last opened with `load_project`.
+#[tauri::command]
+pub fn get_project_summary(state: State<'_, AppState>) -> Option<ProjectSummary> {
+    state.loaded_project.lock().unwrap().clone()
+}
+
 #[tauri::command]
 pub async fn export_stl(
     code: String,
@@ -67,16 +302,20 @@ pub async fn export_stl(
     Ok(format!("STL exported to {}", output_path))
 }
 
+/// Export `code` as STEP. With `verify` (default: `verify_step_exports`) the
+/// written file is re-imported and must match the code's post-geometry report.
 #[tauri::command]
 pub async fn export_step(
     code: String,
     output_path: String,
+    verify: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<String, AppError> {
     let venv_path = state.venv_path.lock().unwrap().clone();
     let venv_dir = venv_path.ok_or(AppError::CadError(
         "Python environment not set up".into(),
     ))?;
+    let config = state.config.lock().unwrap().clone();
 
     let runner_script = super::find_python_script("runner.py")?;
 
@@ -88,5 +327,638 @@ pub async fn export_step(
         &output_path,
     )?;
 
+    if verify.unwrap_or(config.verify_step_exports) {
+        let ctx = executor::ExecutionContext {
+            venv_dir,
+            runner_script,
+            config,
+        };
+        require_verified(&ctx, &code, &output_path)?;
+        return Ok(format!("STEP exported to {} and verified", output_path));
+    }
+
     Ok(format!("STEP exported to {}", output_path))
 }
+
+/// Re-import the STEP file at `path` and compare it with the post-geometry
+/// report of `code`, the script it was exported from.
+fn verify_step_file(
+    ctx: &executor::ExecutionContext,
+    code: &str,
+    path: &str,
+) -> Result<ExportVerificationReport, AppError> {
+    let reference = executor::run_post_geometry_checks(code, ctx, None).map_err(|e| {
+        AppError::CadError(format!(
+            "Cannot verify export: post-geometry check failed: {}",
+            e
+        ))
+    })?;
+    let measurement = crate::python::runner::measure_step_file(
+        &ctx.venv_dir,
+        &ctx.runner_script,
+        Path::new(path),
+        export_verify::MEASURE_TIMEOUT_MS,
+    )?;
+    export_verify::verify_measurement_json(
+        path,
+        &ExportReference::from(&reference),
+        &measurement,
+        ExportTolerances::from_config(&ctx.config),
+    )
+}
+
+/// Verify an export, turning drift into an error that names the metric.
+fn require_verified(
+    ctx: &executor::ExecutionContext,
+    code: &str,
+    path: &str,
+) -> Result<(), AppError> {
+    match verify_step_file(ctx, code, path)?.failure() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Re-import a STEP file exported from `code` and report, per metric, how
+/// far its bounding box, volume and component count drifted.
+#[tauri::command]
+pub async fn verify_export(
+    code: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<ExportVerificationReport, AppError> {
+    let venv_path = state.venv_path.lock().unwrap().clone();
+    let venv_dir = venv_path.ok_or(AppError::CadError("Python environment not set up".into()))?;
+    let ctx = executor::ExecutionContext {
+        venv_dir,
+        runner_script: super::find_python_script("runner.py")?,
+        config: state.config.lock().unwrap().clone(),
+    };
+    tokio::task::spawn_blocking(move || verify_step_file(&ctx, &code, &path))
+        .await
+        .map_err(|e| AppError::CadError(format!("Export verification task panicked: {}", e)))?
+}
+
+/// File name for a part's STEP export: anything outside `[A-Za-z0-9_-]`
+/// becomes `_`, and an empty result falls back to `part`.
+fn part_step_filename(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let sanitized = sanitized.trim_matches('_');
+    if sanitized.is_empty() {
+        "part.step".to_string()
+    } else {
+        format!("{}.step", sanitized)
+    }
+}
+
+/// Part code with its assembly position applied to `result`.
+fn positioned_part_code(code: &str, position: [f64; 3]) -> String {
+    if position == [0.0, 0.0, 0.0] {
+        return code.to_string();
+    }
+    format!(
+        "{}\n\nfrom build123d import Pos\nresult = Pos({}, {}, {}) * result\n",
+        code.trim_end(),
+        position[0],
+        position[1],
+        position[2]
+    )
+}
+
+/// Export each part through `export(code, path)`, collecting per-part failures
+/// instead of stopping at the first one. Duplicate file names get a suffix.
+fn export_parts_with<F>(
+    parts: &[(String, String, [f64; 3])],
+    dir: &Path,
+    mut export: F,
+) -> PartsStepExport
+where
+    F: FnMut(&str, &str) -> Result<(), AppError>,
+{
+    let mut outcome = PartsStepExport::default();
+    let mut used_names: Vec<String> = Vec::new();
+    for (name, code, position) in parts {
+        let base = part_step_filename(name);
+        let mut file_name = base.clone();
+        let mut suffix = 2;
+        while used_names.contains(&file_name) {
+            file_name = format!("{}_{}.step", base.trim_end_matches(".step"), suffix);
+            suffix += 1;
+        }
+        used_names.push(file_name.clone());
+
+        let path = dir.join(&file_name).to_string_lossy().to_string();
+        match export(&positioned_part_code(code, *position), &path) {
+            Ok(()) => outcome.written.push(path),
+            Err(e) => outcome.failures.push(PartExportFailure {
+                name: name.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+    outcome
+}
+
+/// Export every part as its own STEP file (`{name}.step`) in `dir`, placed at
+/// its assembly position, for CAM workflows that want parts separately.
+/// With `verify`, a part whose file does not re-import faithfully is reported
+/// as a failure.
+#[tauri::command]
+pub async fn export_parts_step(
+    parts: Vec<(String, String, [f64; 3])>,
+    dir: String,
+    verify: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<PartsStepExport, AppError> {
+    let venv_path = state.venv_path.lock().unwrap().clone();
+    let venv_dir = venv_path.ok_or(AppError::CadError(
+        "Python environment not set up".into(),
+    ))?;
+    let config = state.config.lock().unwrap().clone();
+    let verify = verify.unwrap_or(config.verify_step_exports);
+    let ctx = executor::ExecutionContext {
+        venv_dir,
+        runner_script: super::find_python_script("runner.py")?,
+        config,
+    };
+
+    let dir_path = Path::new(&dir);
+    std::fs::create_dir_all(dir_path)?;
+
+    Ok(export_parts_with(&parts, dir_path, |code, path| {
+        crate::python::runner::execute_cad_to_file(&ctx.venv_dir, &ctx.runner_script, code, path)?;
+        if verify {
+            require_verified(&ctx, code, path)?;
+        }
+        Ok(())
+    }))
+}
+
+/// Export named geometry variables of one multi-object script (as listed by
+/// `execute_code` with `export_all_solids`) as `{name}.step` files in `dir`.
+#[tauri::command]
+pub async fn export_objects_step(
+    code: String,
+    names: Vec<String>,
+    dir: String,
+    state: State<'_, AppState>,
+) -> Result<PartsStepExport, AppError> {
+    if names.is_empty() {
+        return Err(AppError::ConfigError(
+            "No objects selected for export".into(),
+        ));
+    }
+    let venv_path = state.venv_path.lock().unwrap().clone();
+    let venv_dir = venv_path.ok_or(AppError::CadError("Python environment not set up".into()))?;
+    let runner_script = super::find_python_script("runner.py")?;
+
+    let dir_path = Path::new(&dir);
+    std::fs::create_dir_all(dir_path)?;
+
+    let (objects, _stdout, _stderr) = crate::python::runner::export_cad_objects(
+        &venv_dir,
+        &runner_script,
+        &code,
+        &names,
+        "step",
+        dir_path,
+        OBJECT_EXPORT_TIMEOUT_MS,
+    )?;
+
+    Ok(PartsStepExport {
+        written: objects.into_iter().map(|object| object.file).collect(),
+        failures: vec![],
+    })
+}
+
+/// Write a Markdown transcript of a generation session. `events` are the
+/// `MultiPartEvent`s the frontend captured from the generation channel. With
+/// `run_id`, the run's validation attempt chains are appended.
+#[tauri::command]
+pub async fn export_transcript(
+    events: Vec<serde_json::Value>,
+    final_code: String,
+    plan_text: String,
+    path: String,
+    user_request: Option<String>,
+    run_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let mut markdown =
+        transcript::format_transcript(user_request.as_deref(), &plan_text, &events, &final_code);
+    let chains = run_id
+        .as_deref()
+        .and_then(|run_id| {
+            state
+                .run_store
+                .lock()
+                .ok()
+                .map(|store| store.validation_chains(run_id, None))
+        })
+        .unwrap_or_default();
+    if !chains.is_empty() {
+        markdown.push_str(&validation_history::transcript_section(&chains));
+    }
+    std::fs::write(&path, markdown)?;
+    Ok(format!("Transcript exported to {}", path))
+}
+
+/// Write Markdown assembly instructions for a multi-part run to `path`. Part
+/// thumbnails are isometric drawing views saved beside it, when Python is set
+/// up; parts whose view fails to render are listed without one.
+#[tauri::command]
+pub async fn export_assembly_instructions(
+    run_id: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let run = super::parallel::load_resumable_run(&state.run_store, &run_id)?;
+    let config = state.config.lock().unwrap().clone();
+    let part_materials = state
+        .part_materials
+        .lock()
+        .map(|m| m.clone())
+        .unwrap_or_default();
+    let venv_dir = state.venv_path.lock().unwrap().clone();
+    let reports = run.selected_reports();
+
+    let doc_path = Path::new(&path);
+    let stem = doc_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "assembly".to_string());
+    let thumbnail_dir_name = format!("{}_thumbnails", stem);
+    let thumbnail_dir = doc_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(&thumbnail_dir_name);
+
+    let mut parts = Vec::new();
+    for (name, code, _position) in run.selected_parts() {
+        let spec = run.plan.parts.iter().find(|p| p.name == name);
+        let description = spec.map(|p| p.description.clone()).unwrap_or_default();
+        let thumbnail = venv_dir.as_deref().and_then(|venv| {
+            let view =
+                super::drawing::render_drawing_view(venv, &code, [1.0, 1.0, 1.0], false, None)
+                    .ok()?;
+            std::fs::create_dir_all(&thumbnail_dir).ok()?;
+            std::fs::write(
+                thumbnail_dir.join(format!("{}.svg", name)),
+                view.svg_content,
+            )
+            .ok()?;
+            Some(format!("{}/{}.svg", thumbnail_dir_name, name))
+        });
+        parts.push(assembly_instructions::InstructionPart {
+            material: mass::resolve_material(&name, &description, &part_materials, &config).name,
+            size_mm: reports
+                .get(&name)
+                .map(|r| [0, 1, 2].map(|axis| r.bounds_max[axis] - r.bounds_min[axis])),
+            constraints: spec.map(|p| p.constraints.clone()).unwrap_or_default(),
+            name,
+            description,
+            thumbnail,
+        });
+    }
+    if parts.is_empty() {
+        return Err(AppError::ConfigError(format!(
+            "Run '{}' has no accepted parts to document",
+            run_id
+        )));
+    }
+
+    let matings = assembly_instructions::mating_relationships(&parts);
+    let mut steps = assembly_instructions::assembly_sequence(&parts, &matings);
+    if config.polish_assembly_instructions {
+        if let Ok(provider) = super::chat::create_provider(&config) {
+            steps = assembly_instructions::polish_steps(provider, steps).await;
+        }
+    }
+    let markdown =
+        assembly_instructions::format_instructions(&run.user_request, &parts, &matings, &steps);
+    std::fs::write(&path, markdown)?;
+    Ok(format!("Assembly instructions exported to {}", path))
+}
+
+/// Load a `.py` script written outside the app, preview it, and return it as
+/// the current code. The frontend passes `code` back as `existing_code`, so
+/// follow-up chat requests go through the modification branch.
+#[tauri::command]
+pub async fn import_code_file(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<ImportedCode, AppError> {
+    let is_python = Path::new(&path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("py"));
+    if !is_python {
+        return Err(AppError::ConfigError(format!(
+            "Only .py files can be imported: {}",
+            path
+        )));
+    }
+
+    let source = std::fs::read_to_string(&path)?;
+    let adapted = code_import::adapt_result_assignment(&source);
+    let code = adapted.code;
+    let mut warnings: Vec<String> = adapted.warning.into_iter().collect();
+
+    let static_findings = static_validate::validate_code(&code).findings;
+    let parameters = code_import::extract_parameters(&code);
+    let derived_parameters = code_import::extract_derived_parameters(&code, &parameters);
+
+    let venv_path = state.venv_path.lock().unwrap().clone();
+    let config = state.config.lock().unwrap().clone();
+
+    let mut stl_base64 = None;
+    let mut post_geometry_report = None;
+    let mut execution_error = None;
+    match venv_path {
+        None => {
+            warnings.push("Python environment not set up; preview skipped.".to_string());
+        }
+        Some(venv_dir) => {
+            let runner_script = super::find_python_script("runner.py")?;
+            match executor::execute_with_timeout(&code, &venv_dir, &runner_script).await {
+                Ok(exec_result) => {
+                    stl_base64 = Some(
+                        base64::engine::general_purpose::STANDARD.encode(&exec_result.stl_data),
+                    );
+                    let ctx = executor::ExecutionContext {
+                        venv_dir,
+                        runner_script,
+                        config,
+                    };
+                    match executor::run_post_geometry_checks(&code, &ctx, None) {
+                        Ok(report) => post_geometry_report = Some(report),
+                        Err(e) => warnings.push(format!("Post-geometry check skipped: {}", e)),
+                    }
+                }
+                Err(e) => execution_error = Some(e),
+            }
+        }
+    }
+
+    Ok(ImportedCode {
+        source_path: path,
+        code,
+        parameters,
+        derived_parameters,
+        static_findings,
+        stl_base64,
+        post_geometry_report,
+        execution_error,
+        warnings,
+    })
+}
+
+/// Replace numeric literals repeated at least `min_repeats` times with named
+/// variables declared after the imports, naming them from the request and
+/// plan text where possible. The rewrite is executed next to the original
+/// and kept, with a `CodeDiff` event, only if the geometry is unchanged.
+#[tauri::command]
+pub async fn parametrize_code(
+    code: String,
+    request: Option<String>,
+    plan_text: Option<String>,
+    min_repeats: Option<usize>,
+    on_event: Channel<EventEnvelope>,
+    state: State<'_, AppState>,
+) -> Result<ParametrizedCode, AppError> {
+    let texts: Vec<&str> = [request.as_deref(), plan_text.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect();
+    let plan = parametrize::suggest_parameters(
+        &code,
+        &texts,
+        min_repeats.unwrap_or(parametrize::DEFAULT_MIN_REPEATS),
+    );
+    let mut result = ParametrizedCode {
+        code: code.clone(),
+        applied: false,
+        suggestions: plan.suggestions,
+        uncertain: plan.uncertain,
+        rejected_reason: None,
+    };
+    if result.suggestions.is_empty() {
+        return Ok(result);
+    }
+    let rewritten = parametrize::apply_parameters(&code, &result.suggestions);
+
+    let venv_path = state.venv_path.lock().unwrap().clone();
+    let venv_dir = venv_path.ok_or(AppError::CadError("Python environment not set up".into()))?;
+    let ctx = executor::ExecutionContext {
+        venv_dir,
+        runner_script: super::find_python_script("runner.py")?,
+        config: state.config.lock().unwrap().clone(),
+    };
+    let candidate = rewritten.clone();
+    let reports = tokio::task::spawn_blocking(move || {
+        let before = executor::run_post_geometry_checks(&code, &ctx, None)?;
+        let after = executor::run_post_geometry_checks(&candidate, &ctx, None)?;
+        Ok::<_, String>((before, after))
+    })
+    .await
+    .map_err(|e| AppError::CadError(format!("Parametrize task panicked: {}", e)))?;
+
+    match reports {
+        Err(e) => {
+            result.rejected_reason = Some(format!("Could not compare geometry: {}", e));
+        }
+        Ok((before, after)) => {
+            let mismatches = parametrize::equivalence_mismatches(&before, &after);
+            if mismatches.is_empty() {
+                let on_event = EventSink::register(&state.run_events, on_event);
+                emit_code_diff(&on_event, &result.code, &rewritten);
+                result.code = rewritten;
+                result.applied = true;
+            } else {
+                result.rejected_reason =
+                    Some(format!("Geometry changed: {}", mismatches.join("; ")));
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v3_project_json() -> String {
+        serde_json::json!({
+            "name": "bracket",
+            "code": "result = Box(40, 20, 5)",
+            "messages": [{"role": "user", "content": "make a bracket"}],
+            "version": 3,
+            "generation": {
+                "run_id": "run-42",
+                "request": "wall bracket with two screw holes",
+                "plan_text": "Plate 40x20x5 with two 4mm holes",
+                "parts": [
+                    {"name": "plate", "accepted": true},
+                    {"name": "gusset", "accepted": false}
+                ],
+                "outstanding_warnings": ["gusset overlaps plate by 0.2mm"]
+            },
+            "code_history": [
+                {"code": "result = Box(40, 20, 4)", "label": "first draft", "timestamp": 1700000000}
+            ]
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_older_project_schemas_load_with_defaults() {
+        let v1 = r#"{"name": "old", "code": "result = Box(1, 1, 1)", "messages": []}"#;
+        let project = parse_project(v1).unwrap();
+        assert_eq!(project.version, 1);
+        assert!(project.generation.is_none());
+        assert!(project.code_history.is_empty());
+        assert!(project.view_bookmarks.is_empty());
+        assert!(project.part_colors.is_empty());
+        assert!(project_summary(&project).context_section.is_none());
+
+        let v2 = r#"{"name": "scene", "code": "", "messages": [], "version": 2, "scene": {"objects": []}}"#;
+        let project = parse_project(v2).unwrap();
+        assert_eq!(project.version, 2);
+        assert!(project.scene.is_some());
+        assert!(project.generation.is_none());
+    }
+
+    #[test]
+    fn test_v3_project_round_trips_and_builds_context() {
+        let project = parse_project(&v3_project_json()).unwrap();
+        let reparsed = parse_project(&serde_json::to_string(&project).unwrap()).unwrap();
+        assert_eq!(reparsed.generation, project.generation);
+        assert_eq!(reparsed.code_history, project.code_history);
+
+        let summary = project_summary(&project);
+        assert_eq!(summary.schema_version, 3);
+        assert_eq!(summary.snapshot_count, 1);
+        let section = summary.context_section.unwrap();
+        assert!(section.starts_with("## Loaded Project"));
+        assert!(section.contains("wall bracket with two screw holes"));
+        assert!(section.contains("gusset (not accepted)"));
+        assert!(section.contains("gusset overlaps plate"));
+        assert!(section.contains("run-42"));
+    }
+
+    #[test]
+    fn test_view_bookmarks_round_trip() {
+        let mut value: serde_json::Value = serde_json::from_str(&v3_project_json()).unwrap();
+        value["version"] = serde_json::json!(4);
+        value["view_bookmarks"] = serde_json::json!([{
+            "camera": {
+                "name": "hole detail",
+                "position": [20.0, -30.0, 10.0],
+                "target": [20.0, 10.0, 2.5],
+                "up": [0.0, 0.0, 1.0]
+            },
+            "bounds": {"min": [0.0, 0.0, 0.0], "max": [40.0, 20.0, 5.0]}
+        }]);
+
+        let project = parse_project(&value.to_string()).unwrap();
+        assert_eq!(project.view_bookmarks.len(), 1);
+        assert_eq!(project.view_bookmarks[0].camera.name, "hole detail");
+        let reparsed = parse_project(&serde_json::to_string(&project).unwrap()).unwrap();
+        assert_eq!(reparsed.view_bookmarks, project.view_bookmarks);
+    }
+
+    #[test]
+    fn test_part_colors_round_trip() {
+        let mut value: serde_json::Value = serde_json::from_str(&v3_project_json()).unwrap();
+        value["version"] = serde_json::json!(5);
+        value["part_colors"] = serde_json::json!({"lid": [0.9, 0.2, 0.2]});
+
+        let project = parse_project(&value.to_string()).unwrap();
+        assert_eq!(project.part_colors["lid"], [0.9, 0.2, 0.2]);
+        let reparsed = parse_project(&serde_json::to_string(&project).unwrap()).unwrap();
+        assert_eq!(reparsed.part_colors, project.part_colors);
+    }
+
+    #[test]
+    fn test_newer_project_schema_ignores_unknown_sections() {
+        let mut value: serde_json::Value = serde_json::from_str(&v3_project_json()).unwrap();
+        value["version"] = serde_json::json!(PROJECT_SCHEMA_VERSION + 1);
+        value["review_threads"] = serde_json::json!([{"author": "sam"}]);
+        value["generation"]["cost_usd"] = serde_json::json!(0.12);
+        value["generation"]["parts"][0]["material"] = serde_json::json!("PETG");
+
+        let project = parse_project(&value.to_string()).unwrap();
+        assert_eq!(project.version, PROJECT_SCHEMA_VERSION + 1);
+        let report = project.generation.unwrap();
+        assert_eq!(report.parts.len(), 2);
+        assert!(report.parts[0].accepted);
+    }
+
+    #[test]
+    fn test_loaded_project_seeds_session_memory() {
+        let project = parse_project(&v3_project_json()).unwrap();
+        let mut memory = crate::agent::memory::SessionMemory::new();
+        memory.seed_loaded_project(project_summary(&project).context_section);
+        let section = memory.build_context_section().unwrap();
+        assert!(section.contains("continues project \"bracket\""));
+
+        memory.reset();
+        assert!(memory.build_context_section().is_none());
+    }
+
+    #[test]
+    fn test_part_step_filename_sanitizes_invalid_chars() {
+        assert_eq!(part_step_filename("base_plate"), "base_plate.step");
+        assert_eq!(part_step_filename("Lid / Cover: v2"), "Lid___Cover__v2.step");
+        assert_eq!(part_step_filename("../etc/passwd"), "etc_passwd.step");
+        assert_eq!(part_step_filename("  "), "part.step");
+        assert_eq!(part_step_filename("ñut"), "ut.step");
+    }
+
+    #[test]
+    fn test_positioned_part_code_moves_result() {
+        let code = "from build123d import *\nresult = Box(1, 1, 1)\n";
+        assert_eq!(positioned_part_code(code, [0.0, 0.0, 0.0]), code);
+        let moved = positioned_part_code(code, [0.0, 0.0, 12.5]);
+        assert!(moved.ends_with("result = Pos(0, 0, 12.5) * result\n"));
+    }
+
+    #[test]
+    fn test_export_parts_collects_failures_and_continues() {
+        let parts = vec![
+            ("body".to_string(), "result = ok".to_string(), [0.0, 0.0, 0.0]),
+            ("lid".to_string(), "result = broken".to_string(), [0.0, 0.0, 20.0]),
+            ("body".to_string(), "result = ok".to_string(), [5.0, 0.0, 0.0]),
+        ];
+        let mut calls = 0;
+        let outcome = export_parts_with(&parts, Path::new("/tmp/out"), |code, _path| {
+            calls += 1;
+            if code.contains("broken") {
+                Err(AppError::CadError("fillet failed".to_string()))
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(calls, 3);
+        let written: Vec<String> = outcome
+            .written
+            .iter()
+            .map(|p| Path::new(p).file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(written, vec!["body.step", "body_2.step"]);
+        assert_eq!(outcome.failures.len(), 1);
+        assert_eq!(outcome.failures[0].name, "lid");
+        assert!(outcome.failures[0].error.contains("fillet failed"));
+    }
+}
@@ -0,0 +1,332 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::State;
+
+use crate::agent::queue::{
+    GenerationQueue, QueueEntry, QueueEntryOptions, QueueEntryOutcome, QueueEntryStatus,
+    QueueStatus,
+};
+use crate::agent::telemetry;
+use crate::error::AppError;
+use crate::state::AppState;
+
+use super::parallel;
+use super::run_events::EventEnvelope;
+
+/// How often a running entry checks whether it was cancelled.
+const CANCEL_POLL_MS: u64 = 250;
+
+/// Events streamed while the queue runner drains pending entries.
+/// Pipeline events are forwarded unchanged inside `Generation`, tagged with the entry id.
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind")]
+pub enum QueueEvent {
+    EntryStarted {
+        entry_id: String,
+        message: String,
+    },
+    Generation {
+        entry_id: String,
+        event: serde_json::Value,
+    },
+    EntryFinished {
+        entry_id: String,
+        status: QueueEntryStatus,
+        outcome: QueueEntryOutcome,
+    },
+    QueueIdle {
+        status: QueueStatus,
+    },
+}
+
+/// Final state observed from an entry's forwarded `FinalCode` / `Done` events.
+#[derive(Default)]
+struct ObservedRun {
+    success: Option<bool>,
+    error: Option<String>,
+    final_code: Option<String>,
+}
+
+impl ObservedRun {
+    fn observe(&mut self, event: &serde_json::Value) {
+        match event["kind"].as_str() {
+            Some("FinalCode") => {
+                self.final_code = event["code"].as_str().map(|s| s.to_string());
+            }
+            Some("Done") => {
+                self.success = event["success"].as_bool();
+                self.error = event["error"].as_str().map(|s| s.to_string());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn lock_queue<'a>(state: &'a State<'_, AppState>) -> Result<MutexGuard<'a, GenerationQueue>, AppError> {
+    state
+        .generation_queue
+        .lock()
+        .map_err(|e| AppError::ConfigError(format!("Failed to lock generation queue: {}", e)))
+}
+
+/// Save pending entries unless safe mode keeps the queue in memory only.
+fn persist(state: &AppState, queue: &GenerationQueue) {
+    let allowed = state
+        .config
+        .lock()
+        .map(|c| c.allows_app_data_writes())
+        .unwrap_or(false);
+    if !allowed {
+        return;
+    }
+    if let Err(e) = queue.save() {
+        eprintln!("generation queue save failed: {}", e);
+    }
+}
+
+fn tagged_channel(
+    entry_id: String,
+    on_event: Channel<QueueEvent>,
+    observed: Arc<Mutex<ObservedRun>>,
+) -> Channel<EventEnvelope> {
+    Channel::new(move |body| {
+        if let InvokeResponseBody::Json(json) = body {
+            let event: serde_json::Value =
+                serde_json::from_str(&json).unwrap_or(serde_json::Value::Null);
+            if let Ok(mut observed) = observed.lock() {
+                observed.observe(&event);
+            }
+            let _ = on_event.send(QueueEvent::Generation {
+                entry_id: entry_id.clone(),
+                event,
+            });
+        }
+        Ok(())
+    })
+}
+
+async fn wait_for_cancel(state: &State<'_, AppState>, entry_id: &str) {
+    loop {
+        tokio::time::sleep(Duration::from_millis(CANCEL_POLL_MS)).await;
+        let cancelled = state
+            .generation_queue
+            .lock()
+            .map(|q| q.is_cancelled(entry_id))
+            .unwrap_or(false);
+        if cancelled {
+            return;
+        }
+    }
+}
+
+async fn run_entry(
+    entry: &QueueEntry,
+    channel: Channel<EventEnvelope>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let history = entry.options.history.clone();
+    let existing_code = entry.options.existing_code.clone();
+    let preset = entry.options.preset.clone();
+    match &entry.plan_text {
+        Some(plan_text) => {
+            parallel::generate_from_plan(
+                plan_text.clone(),
+                entry.message.clone(),
+                history,
+                existing_code,
+                channel,
+                state,
+                preset,
+            )
+            .await
+        }
+        None => {
+            parallel::generate_parallel(
+                entry.message.clone(),
+                history,
+                existing_code,
+                channel,
+                state,
+                preset,
+                None,
+            )
+            .await
+        }
+    }
+}
+
+#[tauri::command]
+pub fn enqueue_generation(
+    message: String,
+    plan_text: Option<String>,
+    options: Option<QueueEntryOptions>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    if message.trim().is_empty() {
+        return Err(AppError::ConfigError(
+            "Cannot queue an empty generation request".to_string(),
+        ));
+    }
+    let mut queue = lock_queue(&state)?;
+    let id = queue.enqueue(message, plan_text, options.unwrap_or_default());
+    persist(&state, &queue);
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn get_queue_status(state: State<'_, AppState>) -> Result<QueueStatus, AppError> {
+    Ok(lock_queue(&state)?.status())
+}
+
+#[tauri::command]
+pub fn cancel_queue_entry(entry_id: String, state: State<'_, AppState>) -> Result<bool, AppError> {
+    let mut queue = lock_queue(&state)?;
+    let cancelled = queue.cancel(&entry_id);
+    if cancelled {
+        persist(&state, &queue);
+    }
+    Ok(cancelled)
+}
+
+#[tauri::command]
+pub fn reorder_queue(
+    entry_ids: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<QueueStatus, AppError> {
+    let mut queue = lock_queue(&state)?;
+    queue.reorder(&entry_ids).map_err(AppError::ConfigError)?;
+    persist(&state, &queue);
+    Ok(queue.status())
+}
+
+async fn drain_queue(
+    on_event: &Channel<QueueEvent>,
+    state: &State<'_, AppState>,
+) -> Result<(), AppError> {
+    loop {
+        let entry = {
+            let mut queue = lock_queue(state)?;
+            let next = queue.next_pending();
+            persist(state, &queue);
+            next
+        };
+        let Some(entry) = entry else {
+            break;
+        };
+
+        let _ = on_event.send(QueueEvent::EntryStarted {
+            entry_id: entry.id.clone(),
+            message: entry.message.clone(),
+        });
+
+        let observed = Arc::new(Mutex::new(ObservedRun::default()));
+        let channel = tagged_channel(entry.id.clone(), on_event.clone(), observed.clone());
+
+        let result = tokio::select! {
+            r = run_entry(&entry, channel, state.clone()) => Some(r),
+            _ = wait_for_cancel(state, &entry.id) => None,
+        };
+
+        let observed = observed
+            .lock()
+            .map(|o| (o.success, o.error.clone(), o.final_code.clone()))
+            .unwrap_or_default();
+        let outcome = match result {
+            None => QueueEntryOutcome {
+                success: false,
+                error: Some("Cancelled".to_string()),
+                final_code: observed.2,
+                finished_at_ms: telemetry::now_ms(),
+            },
+            Some(Err(e)) => QueueEntryOutcome {
+                success: false,
+                error: Some(e.to_string()),
+                final_code: observed.2,
+                finished_at_ms: telemetry::now_ms(),
+            },
+            Some(Ok(_)) => QueueEntryOutcome {
+                success: observed.0.unwrap_or(true),
+                error: observed.1,
+                final_code: observed.2,
+                finished_at_ms: telemetry::now_ms(),
+            },
+        };
+
+        let status = {
+            let mut queue = lock_queue(state)?;
+            queue.finish(&entry.id, outcome.clone());
+            queue
+                .status()
+                .entries
+                .into_iter()
+                .find(|e| e.id == entry.id)
+                .map(|e| e.status)
+                .unwrap_or(QueueEntryStatus::Failed)
+        };
+
+        let _ = on_event.send(QueueEvent::EntryFinished {
+            entry_id: entry.id,
+            status,
+            outcome,
+        });
+    }
+    Ok(())
+}
+
+/// Run pending queue entries one at a time until the queue is empty.
+///
+/// Entries enqueued while the runner is active are picked up in order.
+#[tauri::command]
+pub async fn run_generation_queue(
+    on_event: Channel<QueueEvent>,
+    state: State<'_, AppState>,
+) -> Result<QueueStatus, AppError> {
+    if !lock_queue(&state)?.try_start() {
+        return Err(AppError::ConfigError(
+            "Generation queue is already running".to_string(),
+        ));
+    }
+
+    let drained = drain_queue(&on_event, &state).await;
+
+    let status = {
+        let mut queue = lock_queue(&state)?;
+        queue.stop();
+        queue.status()
+    };
+    drained?;
+    let _ = on_event.send(QueueEvent::QueueIdle {
+        status: status.clone(),
+    });
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observed_run_tracks_final_code_and_done() {
+        let mut observed = ObservedRun::default();
+        observed.observe(&serde_json::json!({ "kind": "PlanStatus", "message": "x" }));
+        assert!(observed.success.is_none());
+
+        observed.observe(&serde_json::json!({
+            "kind": "FinalCode",
+            "code": "result = Box(1, 1, 1)",
+            "stl_base64": null
+        }));
+        observed.observe(&serde_json::json!({
+            "kind": "Done",
+            "success": false,
+            "error": "validation failed",
+            "validated": true
+        }));
+        assert_eq!(observed.success, Some(false));
+        assert_eq!(observed.error.as_deref(), Some("validation failed"));
+        assert_eq!(observed.final_code.as_deref(), Some("result = Box(1, 1, 1)"));
+    }
+}
@@ -0,0 +1,39 @@
+use std::sync::MutexGuard;
+
+use tauri::State;
+
+use crate::agent::repair_examples::{self, RepairExample, RepairExampleStore};
+use crate::error::AppError;
+use crate::state::AppState;
+
+fn lock_store() -> Result<MutexGuard<'static, RepairExampleStore>, AppError> {
+    repair_examples::global_store()
+        .lock()
+        .map_err(|e| AppError::ConfigError(format!("Failed to lock repair examples: {}", e)))
+}
+
+/// Stored repair examples, most recently used first.
+#[tauri::command]
+pub fn list_repair_examples() -> Result<Vec<RepairExample>, AppError> {
+    Ok(lock_store()?.list())
+}
+
+/// Drop a stored repair example so it is no longer offered to repair prompts.
+#[tauri::command]
+pub fn delete_repair_example(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<RepairExample>, AppError> {
+    let allows_writes = state.config.lock().unwrap().allows_app_data_writes();
+    let mut store = lock_store()?;
+    if !store.delete(&id) {
+        return Err(AppError::ConfigError(format!(
+            "Repair example '{}' not found",
+            id
+        )));
+    }
+    if allows_writes {
+        store.save()?;
+    }
+    Ok(store.list())
+}
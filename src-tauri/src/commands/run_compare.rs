@@ -0,0 +1,540 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::agent::telemetry;
+use crate::commands::parallel::MultiPartEvent;
+use crate::commands::run_events::RunTimeline;
+use crate::error::AppError;
+use crate::pipeline_presets;
+use crate::state::AppState;
+
+/// Where the numbers of a `RunSnapshot` came from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotSource {
+    /// Buffered run events, plus the trace when one was written.
+    RunState,
+    /// Only the telemetry trace; the run's events are no longer buffered.
+    TraceOnly,
+}
+
+/// Outcome and cost of one run. `None` fields are listed in `unavailable`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSnapshot {
+    pub run_id: String,
+    pub source: SnapshotSource,
+    pub success: Option<bool>,
+    pub plan_risk_score: Option<u32>,
+    pub confidence_score: Option<u32>,
+    pub part_acceptance_rate: Option<f32>,
+    pub retry_attempts: Option<u32>,
+    pub total_tokens: Option<u32>,
+    pub cost_usd: Option<f64>,
+    /// Wall-clock milliseconds per token-usage phase.
+    pub phase_ms: Option<BTreeMap<String, u64>>,
+    /// Bounding box extent along x/y/z of the final geometry (mm).
+    pub bbox_size: Option<[f64; 3]>,
+    pub volume: Option<f64>,
+    pub geometry_hash: Option<String>,
+    pub config_fingerprint: Option<String>,
+    pub settings: Option<Map<String, Value>>,
+    pub failure_signatures: Vec<String>,
+    pub unavailable: Vec<String>,
+}
+
+/// A setting whose value differs between the two runs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingChange {
+    pub key: String,
+    pub a: Value,
+    pub b: Value,
+}
+
+/// Run `b` measured against run `a`; deltas are `b - a`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunComparison {
+    pub a: RunSnapshot,
+    pub b: RunSnapshot,
+    pub bbox_size_delta: Option<[f64; 3]>,
+    pub volume_delta: Option<f64>,
+    pub same_geometry: Option<bool>,
+    pub same_config: Option<bool>,
+    /// `None` when either run's settings are unavailable.
+    pub settings_changes: Option<Vec<SettingChange>>,
+    pub failure_signatures_added: Vec<String>,
+    pub failure_signatures_removed: Vec<String>,
+}
+
+/// Latest trace written for `run_id` in a `generation_traces_v1.jsonl` dump.
+pub fn trace_for_run(jsonl: &str, run_id: &str) -> Option<Value> {
+    jsonl
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|trace| trace["run_id"].as_str() == Some(run_id))
+}
+
+/// Snapshot of a run from its trace and buffered events, either of which may
+/// be missing. Trace values win; events fill what the trace does not record.
+pub fn snapshot_run(
+    run_id: &str,
+    trace: Option<&Value>,
+    timeline: Option<&RunTimeline>,
+) -> Option<RunSnapshot> {
+    if trace.is_none() && timeline.is_none() {
+        return None;
+    }
+    let trace_u32 = |key: &str| trace.and_then(|t| t[key].as_u64()).map(|v| v as u32);
+
+    let mut snapshot = RunSnapshot {
+        run_id: run_id.to_string(),
+        source: if timeline.is_some() {
+            SnapshotSource::RunState
+        } else {
+            SnapshotSource::TraceOnly
+        },
+        success: trace.and_then(|t| t["execution_success"].as_bool()),
+        plan_risk_score: trace_u32("plan_risk_score"),
+        confidence_score: trace_u32("confidence_score"),
+        part_acceptance_rate: trace
+            .and_then(|t| t["part_acceptance_rate"].as_f64())
+            .map(|v| v as f32),
+        retry_attempts: trace_u32("retry_attempts"),
+        total_tokens: None,
+        cost_usd: None,
+        phase_ms: None,
+        bbox_size: None,
+        volume: None,
+        geometry_hash: None,
+        config_fingerprint: trace
+            .and_then(|t| t["config_fingerprint"].as_str())
+            .map(|s| s.to_string()),
+        settings: None,
+        failure_signatures: trace
+            .and_then(|t| t["failure_signatures"].as_array())
+            .map(|sigs| {
+                sigs.iter()
+                    .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        unavailable: Vec::new(),
+    };
+
+    if let Some(timeline) = timeline {
+        apply_timeline(&mut snapshot, timeline);
+    }
+
+    let checks: [(&str, bool); 14] = [
+        ("success", snapshot.success.is_none()),
+        ("plan_risk_score", snapshot.plan_risk_score.is_none()),
+        ("confidence_score", snapshot.confidence_score.is_none()),
+        (
+            "part_acceptance_rate",
+            snapshot.part_acceptance_rate.is_none(),
+        ),
+        ("retry_attempts", snapshot.retry_attempts.is_none()),
+        ("total_tokens", snapshot.total_tokens.is_none()),
+        ("cost_usd", snapshot.cost_usd.is_none()),
+        ("phase_ms", snapshot.phase_ms.is_none()),
+        ("bbox_size", snapshot.bbox_size.is_none()),
+        ("volume", snapshot.volume.is_none()),
+        ("geometry_hash", snapshot.geometry_hash.is_none()),
+        ("config_fingerprint", snapshot.config_fingerprint.is_none()),
+        ("settings", snapshot.settings.is_none()),
+        ("failure_signatures", trace.is_none()),
+    ];
+    snapshot.unavailable = checks
+        .iter()
+        .filter(|(_, missing)| *missing)
+        .map(|(field, _)| field.to_string())
+        .collect();
+    Some(snapshot)
+}
+
+fn apply_timeline(snapshot: &mut RunSnapshot, timeline: &RunTimeline) {
+    let mut risk = None;
+    let mut confidence = None;
+    let mut max_attempt = None;
+    let mut success = None;
+    let mut parts = (0u32, 0u32);
+    let mut tokens: Option<u32> = None;
+    let mut cost: Option<f64> = None;
+    let mut phases: BTreeMap<String, u64> = BTreeMap::new();
+    let mut phase_start = timeline.started_ms;
+
+    for (sent_ms, event) in &timeline.events {
+        match event {
+            MultiPartEvent::PlanValidation { risk_score, .. } => risk = Some(*risk_score),
+            MultiPartEvent::ConfidenceAssessment { score, .. } => confidence = Some(*score),
+            MultiPartEvent::ValidationAttempt { attempt, .. } => {
+                max_attempt = max_attempt.max(Some(*attempt));
+            }
+            MultiPartEvent::PartComplete { success: ok, .. } => {
+                parts.0 += u32::from(*ok);
+                parts.1 += 1;
+            }
+            MultiPartEvent::TokenUsage {
+                phase,
+                total_tokens,
+                cost_usd,
+                ..
+            } => {
+                tokens = Some(tokens.unwrap_or(0) + total_tokens);
+                if let Some(c) = cost_usd {
+                    cost = Some(cost.unwrap_or(0.0) + c);
+                }
+                *phases.entry(phase.clone()).or_insert(0) += sent_ms.saturating_sub(phase_start);
+                phase_start = *sent_ms;
+            }
+            MultiPartEvent::PostGeometryValidationReport { report } => {
+                snapshot.bbox_size = Some([
+                    report.bounds_max[0] - report.bounds_min[0],
+                    report.bounds_max[1] - report.bounds_min[1],
+                    report.bounds_max[2] - report.bounds_min[2],
+                ]);
+                snapshot.volume = Some(report.volume);
+            }
+            MultiPartEvent::FinalCode { geometry_hash, .. } => {
+                snapshot.geometry_hash = geometry_hash.clone();
+            }
+            MultiPartEvent::Done { success: ok, .. } => success = Some(*ok),
+            _ => {}
+        }
+    }
+
+    snapshot.success = snapshot.success.or(success);
+    snapshot.plan_risk_score = snapshot.plan_risk_score.or(risk);
+    snapshot.confidence_score = snapshot.confidence_score.or(confidence);
+    if parts.1 > 0 {
+        snapshot.part_acceptance_rate = snapshot
+            .part_acceptance_rate
+            .or(Some(parts.0 as f32 / parts.1 as f32));
+    }
+    snapshot.retry_attempts = snapshot.retry_attempts.or(max_attempt);
+    snapshot.total_tokens = tokens;
+    snapshot.cost_usd = cost;
+    if !phases.is_empty() {
+        snapshot.phase_ms = Some(phases);
+    }
+    if let Some(settings) = &timeline.settings {
+        if snapshot.config_fingerprint.is_none() {
+            snapshot.config_fingerprint = Some(pipeline_presets::settings_fingerprint(settings));
+        }
+        snapshot.settings = Some(settings.clone());
+    }
+}
+
+fn settings_changes(a: &Map<String, Value>, b: &Map<String, Value>) -> Vec<SettingChange> {
+    let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter_map(|key| {
+            let va = a.get(key).cloned().unwrap_or(Value::Null);
+            let vb = b.get(key).cloned().unwrap_or(Value::Null);
+            (va != vb).then(|| SettingChange {
+                key: key.clone(),
+                a: va,
+                b: vb,
+            })
+        })
+        .collect()
+}
+
+pub fn compare_snapshots(a: RunSnapshot, b: RunSnapshot) -> RunComparison {
+    let bbox_size_delta = match (a.bbox_size, b.bbox_size) {
+        (Some(sa), Some(sb)) => Some([sb[0] - sa[0], sb[1] - sa[1], sb[2] - sa[2]]),
+        _ => None,
+    };
+    let volume_delta = a.volume.zip(b.volume).map(|(va, vb)| vb - va);
+    let same_geometry = a
+        .geometry_hash
+        .as_ref()
+        .zip(b.geometry_hash.as_ref())
+        .map(|(ha, hb)| ha == hb);
+    let same_config = a
+        .config_fingerprint
+        .as_ref()
+        .zip(b.config_fingerprint.as_ref())
+        .map(|(fa, fb)| fa == fb);
+    let settings_changes = a
+        .settings
+        .as_ref()
+        .zip(b.settings.as_ref())
+        .map(|(sa, sb)| settings_changes(sa, sb));
+    let failure_signatures_added = b
+        .failure_signatures
+        .iter()
+        .filter(|s| !a.failure_signatures.contains(s))
+        .cloned()
+        .collect();
+    let failure_signatures_removed = a
+        .failure_signatures
+        .iter()
+        .filter(|s| !b.failure_signatures.contains(s))
+        .cloned()
+        .collect();
+
+    RunComparison {
+        a,
+        b,
+        bbox_size_delta,
+        volume_delta,
+        same_geometry,
+        same_config,
+        settings_changes,
+        failure_signatures_added,
+        failure_signatures_removed,
+    }
+}
+
+/// Where the app keeps its own files; a comparison may not be written there.
+fn app_dirs() -> Vec<PathBuf> {
+    [dirs::config_dir(), dirs::data_dir()]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join("cadai-studio"))
+        .collect()
+}
+
+/// Accept `path` for the comparison JSON only if it is an absolute `.json`
+/// file path in an existing directory outside `protected`.
+fn check_output_path(path: &str, protected: &[PathBuf]) -> Result<PathBuf, AppError> {
+    let path = Path::new(path);
+    let invalid = |reason: &str| {
+        AppError::ConfigError(format!(
+            "Cannot write the comparison to '{}': {}",
+            path.display(),
+            reason
+        ))
+    };
+    if !path.is_absolute() {
+        return Err(invalid("the path must be absolute"));
+    }
+    if path.extension().and_then(|e| e.to_str()) != Some("json") {
+        return Err(invalid("the file must have a .json extension"));
+    }
+    let parent = path
+        .parent()
+        .filter(|p| p.is_dir())
+        .ok_or_else(|| invalid("the directory does not exist"))?;
+    if path.is_dir() {
+        return Err(invalid("the path is a directory"));
+    }
+    let parent = parent.canonicalize()?;
+    if protected
+        .iter()
+        .any(|dir| parent.starts_with(dir.canonicalize().unwrap_or_else(|_| dir.clone())))
+    {
+        return Err(invalid("the app's own data directory is not allowed"));
+    }
+    Ok(parent.join(path.file_name().unwrap_or_default()))
+}
+
+/// Compare two runs for A/B testing prompt or settings changes. Runs whose
+/// events are no longer buffered fall back to their telemetry trace. With
+/// `output_path` the comparison is also written there as JSON.
+#[tauri::command]
+pub fn compare_runs(
+    run_a: String,
+    run_b: String,
+    output_path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RunComparison, AppError> {
+    let traces = match std::fs::read_to_string(telemetry::traces_path()?) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let snapshot = |run_id: &str| -> Result<RunSnapshot, AppError> {
+        let timeline = state
+            .run_events
+            .lock()
+            .map_err(|_| AppError::ConfigError("Run event store lock poisoned".into()))?
+            .timeline(run_id);
+        let trace = trace_for_run(&traces, run_id);
+        snapshot_run(run_id, trace.as_ref(), timeline.as_ref()).ok_or_else(|| {
+            AppError::ConfigError(format!("No buffered events or trace for run '{}'", run_id))
+        })
+    };
+
+    let output_path = output_path
+        .map(|path| check_output_path(&path, &app_dirs()))
+        .transpose()?;
+    let comparison = compare_snapshots(snapshot(&run_a)?, snapshot(&run_b)?);
+    if let Some(path) = output_path {
+        std::fs::write(&path, serde_json::to_string_pretty(&comparison)?)?;
+    }
+    Ok(comparison)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn token_usage(phase: &str, total_tokens: u32) -> MultiPartEvent {
+        MultiPartEvent::TokenUsage {
+            phase: phase.to_string(),
+            input_tokens: 0,
+            output_tokens: total_tokens,
+            total_tokens,
+            cost_usd: Some(0.01),
+            currency: "USD".to_string(),
+            estimated_with_default_rate: false,
+        }
+    }
+
+    fn settings(temperature: f64) -> Map<String, Value> {
+        json!({ "model": "m", "temperature": temperature })
+            .as_object()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn test_trace_for_run_takes_latest_matching_line() {
+        let jsonl = "{\"run_id\":\"r1\",\"execution_success\":false}\n\
+            not json\n\
+            {\"run_id\":\"r2\",\"execution_success\":true}\n\
+            {\"run_id\":\"r1\",\"execution_success\":true}\n";
+        let trace = trace_for_run(jsonl, "r1").unwrap();
+        assert_eq!(trace["execution_success"], json!(true));
+        assert!(trace_for_run(jsonl, "r3").is_none());
+    }
+
+    #[test]
+    fn test_run_state_snapshot_reads_events() {
+        let timeline = RunTimeline {
+            started_ms: 1_000,
+            events: vec![
+                (
+                    1_100,
+                    MultiPartEvent::ConfidenceAssessment {
+                        level: "high".into(),
+                        score: 80,
+                        cookbook_matches: vec![],
+                        warnings: vec![],
+                        message: String::new(),
+                    },
+                ),
+                (1_500, token_usage("design", 100)),
+                (
+                    1_600,
+                    MultiPartEvent::FinalCode {
+                        code: String::new(),
+                        stl_base64: None,
+                        geometry_hash: Some("abc".into()),
+                    },
+                ),
+                (2_300, token_usage("generate", 400)),
+                (
+                    2_400,
+                    MultiPartEvent::Done {
+                        success: true,
+                        error: None,
+                        validated: true,
+                    },
+                ),
+            ],
+            settings: Some(settings(0.2)),
+        };
+
+        let snapshot = snapshot_run("r1", None, Some(&timeline)).unwrap();
+        assert_eq!(snapshot.source, SnapshotSource::RunState);
+        assert_eq!(snapshot.success, Some(true));
+        assert_eq!(snapshot.confidence_score, Some(80));
+        assert_eq!(snapshot.total_tokens, Some(500));
+        assert_eq!(snapshot.geometry_hash.as_deref(), Some("abc"));
+        let phases = snapshot.phase_ms.unwrap();
+        assert_eq!(phases["design"], 500);
+        assert_eq!(phases["generate"], 800);
+        assert_eq!(
+            snapshot.config_fingerprint,
+            Some(pipeline_presets::settings_fingerprint(&settings(0.2)))
+        );
+        assert!(snapshot.unavailable.contains(&"bbox_size".to_string()));
+        assert!(!snapshot.unavailable.contains(&"total_tokens".to_string()));
+    }
+
+    #[test]
+    fn test_trace_only_comparison_marks_missing_fields() {
+        let a = json!({
+            "run_id": "a",
+            "execution_success": false,
+            "plan_risk_score": 6,
+            "retry_attempts": 3,
+            "config_fingerprint": "f1",
+            "failure_signatures": ["bool_fail", "fillet_fail"],
+        });
+        let b = json!({
+            "run_id": "b",
+            "execution_success": true,
+            "plan_risk_score": 2,
+            "retry_attempts": 1,
+            "config_fingerprint": "f2",
+            "failure_signatures": ["fillet_fail", "slow_export"],
+        });
+        let comparison = compare_snapshots(
+            snapshot_run("a", Some(&a), None).unwrap(),
+            snapshot_run("b", Some(&b), None).unwrap(),
+        );
+
+        assert_eq!(comparison.a.source, SnapshotSource::TraceOnly);
+        assert_eq!(comparison.b.retry_attempts, Some(1));
+        assert_eq!(comparison.same_config, Some(false));
+        assert_eq!(comparison.failure_signatures_added, vec!["slow_export"]);
+        assert_eq!(comparison.failure_signatures_removed, vec!["bool_fail"]);
+        assert!(comparison.settings_changes.is_none());
+        assert!(comparison.volume_delta.is_none());
+        for field in ["total_tokens", "phase_ms", "volume", "settings"] {
+            assert!(comparison.b.unavailable.contains(&field.to_string()));
+        }
+        assert!(snapshot_run("c", None, None).is_none());
+    }
+
+    #[test]
+    fn test_settings_changes_lists_differing_keys() {
+        let mut b = settings(0.7);
+        b.insert("review_enabled".into(), json!(true));
+        let changes = settings_changes(&settings(0.2), &b);
+        assert_eq!(
+            changes,
+            vec![
+                SettingChange {
+                    key: "review_enabled".into(),
+                    a: Value::Null,
+                    b: json!(true),
+                },
+                SettingChange {
+                    key: "temperature".into(),
+                    a: json!(0.2),
+                    b: json!(0.7),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output_path_must_be_absolute_json_outside_app_dirs() {
+        let root = std::env::temp_dir().join(format!("cadai-compare-{}", uuid::Uuid::new_v4()));
+        let app_dir = root.join("cadai-studio");
+        std::fs::create_dir_all(&app_dir).unwrap();
+        let protected = vec![app_dir.clone()];
+        let path = |p: &Path| p.to_string_lossy().to_string();
+
+        let ok = check_output_path(&path(&root.join("ab.json")), &protected).unwrap();
+        assert_eq!(ok.file_name().unwrap(), "ab.json");
+        assert!(check_output_path("ab.json", &protected).is_err());
+        assert!(check_output_path(&path(&root.join("ab.txt")), &protected).is_err());
+        assert!(check_output_path(&path(&root.join("missing/ab.json")), &protected).is_err());
+        assert!(check_output_path(&path(&app_dir.join("config.json")), &protected).is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,887 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::ipc::Channel;
+use tauri::State;
+use tokio::sync::watch;
+
+use crate::agent::telemetry;
+use crate::agent::validation_history::ValidationChain;
+use crate::commands::parallel::MultiPartEvent;
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Non-delta events kept per run for `get_run_events`.
+pub const EVENT_BUFFER_CAPACITY: usize = 256;
+/// Runs whose events stay available; the oldest run is dropped first.
+pub const MAX_BUFFERED_RUNS: usize = 8;
+/// Consecutive failed sends after which a streaming run counts as disconnected.
+pub const DISCONNECT_AFTER_FAILURES: u32 = 3;
+/// Streaming loops re-check the channel every this many deltas.
+pub const DELTA_CHECK_INTERVAL: usize = 32;
+
+/// Wire format of every generation event: the event's own fields plus the run
+/// it belongs to and a sequence number that increases by one per event.
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct EventEnvelope {
+    pub seq: u64,
+    pub run_id: String,
+    #[serde(flatten)]
+    pub event: MultiPartEvent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunChannelStatus {
+    Connected,
+    /// The frontend went away; the run stopped and its state was kept.
+    Parked,
+    /// The frontend went away and the run was discarded.
+    Cancelled,
+}
+
+struct RunEventLog {
+    next_seq: u64,
+    events: VecDeque<EventEnvelope>,
+    /// When each buffered event was sent (Unix ms), aligned with `events`.
+    sent_ms: VecDeque<u64>,
+    started_ms: u64,
+    /// Effective settings of the run, from `EventSink::record_settings`.
+    settings: Option<Map<String, Value>>,
+    consecutive_failures: u32,
+    disconnected: bool,
+    status: RunChannelStatus,
+    /// Channels of identical requests merged into this run.
+    followers: Vec<Channel<EventEnvelope>>,
+}
+
+impl RunEventLog {
+    fn new() -> Self {
+        Self {
+            next_seq: 0,
+            events: VecDeque::new(),
+            sent_ms: VecDeque::new(),
+            started_ms: telemetry::now_ms(),
+            settings: None,
+            consecutive_failures: 0,
+            disconnected: false,
+            status: RunChannelStatus::Connected,
+            followers: Vec::new(),
+        }
+    }
+
+    /// STL of the newest buffered `FinalCode`, matching `geometry_hash` when given.
+    fn final_stl(&self, geometry_hash: Option<&str>) -> Option<String> {
+        self.events.iter().rev().find_map(|e| match &e.event {
+            MultiPartEvent::FinalCode {
+                stl_base64: Some(stl),
+                geometry_hash: hash,
+                ..
+            } if geometry_hash.is_none_or(|h| hash.as_deref() == Some(h)) => Some(stl.clone()),
+            _ => None,
+        })
+    }
+
+    /// Code and STL of the newest buffered `FinalCode`.
+    fn final_code(&self) -> Option<(String, Option<String>)> {
+        self.events.iter().rev().find_map(|e| match &e.event {
+            MultiPartEvent::FinalCode {
+                code, stl_base64, ..
+            } => Some((code.clone(), stl_base64.clone())),
+            _ => None,
+        })
+    }
+}
+
+/// Streaming deltas and heartbeats are not worth replaying on catch-up.
+fn is_delta(event: &MultiPartEvent) -> bool {
+    matches!(
+        event,
+        MultiPartEvent::SingleDelta { .. }
+            | MultiPartEvent::PartDelta { .. }
+            | MultiPartEvent::Heartbeat { .. }
+    )
+}
+
+/// Sender for one run's events. Numbers every event, keeps the recent
+/// non-delta ones for catch-up, and tracks whether the frontend is still
+/// receiving them.
+#[derive(Clone)]
+pub struct EventSink {
+    run_id: String,
+    raw: Channel<EventEnvelope>,
+    log: Arc<Mutex<RunEventLog>>,
+}
+
+impl EventSink {
+    pub fn new(raw: Channel<EventEnvelope>) -> Self {
+        Self {
+            run_id: uuid::Uuid::new_v4().to_string(),
+            raw,
+            log: Arc::new(Mutex::new(RunEventLog::new())),
+        }
+    }
+
+    /// New sink whose buffered events are served by `get_run_events`.
+    pub fn register(store: &Mutex<RunEventStore>, raw: Channel<EventEnvelope>) -> Self {
+        let sink = Self::new(raw);
+        if let Ok(mut store) = store.lock() {
+            store.insert(sink.run_id.clone(), sink.log.clone());
+        }
+        sink
+    }
+
+    /// New registered sink on the same channel with its own run id and
+    /// buffer, for one item of a command that starts several runs.
+    pub fn child(&self, store: &Mutex<RunEventStore>) -> Self {
+        Self::register(store, self.raw.clone())
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Send `event`. Fails once the channel is known to be disconnected; the
+    /// event is still buffered so a reconnecting frontend can fetch it.
+    pub fn send(&self, event: MultiPartEvent) -> tauri::Result<()> {
+        let mut log = match self.log.lock() {
+            Ok(log) => log,
+            Err(_) => return Err(tauri::Error::WebviewNotFound),
+        };
+        log.next_seq += 1;
+        let envelope = EventEnvelope {
+            seq: log.next_seq,
+            run_id: self.run_id.clone(),
+            event,
+        };
+        if !is_delta(&envelope.event) {
+            log.events.push_back(envelope.clone());
+            log.sent_ms.push_back(telemetry::now_ms());
+            while log.events.len() > EVENT_BUFFER_CAPACITY {
+                log.events.pop_front();
+                log.sent_ms.pop_front();
+            }
+        }
+        if log.disconnected {
+            return Err(tauri::Error::WebviewNotFound);
+        }
+        // Sent under the lock so the frontend sees events in `seq` order.
+        // The run stays connected while any merged request still listens.
+        let mut followed = false;
+        for follower in &log.followers {
+            followed |= follower.send(envelope.clone()).is_ok();
+        }
+        let result = self.raw.send(envelope);
+        let result = if followed { Ok(()) } else { result };
+        if result.is_ok() {
+            log.consecutive_failures = 0;
+        } else {
+            log.consecutive_failures += 1;
+            if log.consecutive_failures >= DISCONNECT_AFTER_FAILURES {
+                log.disconnected = true;
+            }
+        }
+        result
+    }
+
+    /// Also deliver this run's events to `raw`, replaying the buffered ones
+    /// first so the new receiver catches up in `seq` order.
+    pub fn attach(&self, raw: Channel<EventEnvelope>) {
+        if let Ok(mut log) = self.log.lock() {
+            for envelope in &log.events {
+                let _ = raw.send(envelope.clone());
+            }
+            log.followers.push(raw);
+        }
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.log.lock().map(|log| log.disconnected).unwrap_or(true)
+    }
+
+    /// Check for streaming loops, made every `DELTA_CHECK_INTERVAL` deltas.
+    pub fn stream_interrupted(&self, deltas_seen: usize) -> bool {
+        deltas_seen.is_multiple_of(DELTA_CHECK_INTERVAL) && self.is_disconnected()
+    }
+
+    /// Phase-boundary check before work that costs provider calls. A channel
+    /// whose latest send failed is treated as gone here, without waiting for
+    /// `DISCONNECT_AFTER_FAILURES`.
+    pub fn ensure_connected(&self) -> Result<(), AppError> {
+        let mut log = self
+            .log
+            .lock()
+            .map_err(|_| AppError::ChannelDisconnected(self.run_id.clone()))?;
+        if log.disconnected || log.consecutive_failures > 0 {
+            log.disconnected = true;
+            return Err(AppError::ChannelDisconnected(self.run_id.clone()));
+        }
+        Ok(())
+    }
+
+    pub fn set_status(&self, status: RunChannelStatus) {
+        if let Ok(mut log) = self.log.lock() {
+            log.status = status;
+        }
+    }
+
+    /// STL of this run's newest buffered `FinalCode`.
+    pub fn final_stl(&self) -> Option<String> {
+        self.log.lock().ok()?.final_stl(None)
+    }
+
+    /// Keep the run's effective settings for `compare_runs`.
+    pub fn record_settings(&self, settings: Map<String, Value>) {
+        if let Ok(mut log) = self.log.lock() {
+            log.settings = Some(settings);
+        }
+    }
+}
+
+/// Buffered history of one run, for comparing runs.
+pub struct RunTimeline {
+    pub started_ms: u64,
+    /// Non-delta events with the time each was sent (Unix ms).
+    pub events: Vec<(u64, MultiPartEvent)>,
+    pub settings: Option<Map<String, Value>>,
+}
+
+/// What a finished run hands to the requests merged into it: the response,
+/// or the error message. `None` while the run is still going.
+pub type SharedOutcome = Option<Result<String, String>>;
+
+struct InFlightRun {
+    sink: EventSink,
+    outcome: watch::Sender<SharedOutcome>,
+}
+
+/// How a generation request was admitted by `RunEventStore::claim_run`.
+pub enum RunClaim {
+    /// The caller runs the pipeline on `sink`. `duplicate` is set when an
+    /// identical run is already going and merging is disabled.
+    Started { sink: EventSink, duplicate: bool },
+    /// The caller's channel was attached to the identical run `run_id`.
+    Joined {
+        run_id: String,
+        outcome: watch::Receiver<SharedOutcome>,
+    },
+}
+
+/// Fingerprint of a generation request; identical requests running at the
+/// same time are merged into one run.
+pub fn run_fingerprint(
+    message: &str,
+    plan_text: Option<&str>,
+    target_part: Option<&str>,
+    config: &AppConfig,
+    existing_code: Option<&str>,
+) -> String {
+    use sha2::{Digest, Sha256};
+
+    let code_hash = existing_code.map(|code| Sha256::digest(code.as_bytes()).to_vec());
+    let key = serde_json::json!({
+        "message": message,
+        "plan_text": plan_text,
+        "target_part": target_part,
+        "config": config,
+        "existing_code": code_hash,
+    });
+    let digest = Sha256::digest(key.to_string().as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Wait for the run a request was merged into and return its response.
+pub async fn merged_outcome(
+    run_id: &str,
+    mut outcome: watch::Receiver<SharedOutcome>,
+) -> Result<String, AppError> {
+    let finished = outcome
+        .wait_for(|o| o.is_some())
+        .await
+        .ok()
+        .and_then(|o| (*o).clone());
+    match finished {
+        Some(Ok(response)) => Ok(response),
+        Some(Err(message)) => Err(AppError::AiProviderError(message)),
+        None => Err(AppError::AiProviderError(format!(
+            "Merged run {} ended without a result",
+            run_id
+        ))),
+    }
+}
+
+/// Keeps a started run in the in-flight table until it finishes; dropping it
+/// early (a cancelled command) releases merged requests with an error.
+pub struct InFlightGuard<'a> {
+    store: &'a Mutex<RunEventStore>,
+    fingerprint: String,
+    run_id: String,
+}
+
+impl<'a> InFlightGuard<'a> {
+    pub fn new(store: &'a Mutex<RunEventStore>, fingerprint: String, sink: &EventSink) -> Self {
+        Self {
+            store,
+            fingerprint,
+            run_id: sink.run_id().to_string(),
+        }
+    }
+
+    /// Hand the run's result to every request merged into it.
+    pub fn finish(self, result: &Result<String, AppError>) {
+        if let Ok(mut store) = self.store.lock() {
+            if let Some(run) = store.take_in_flight(&self.fingerprint, &self.run_id) {
+                let shared = result.as_ref().cloned().map_err(|e| e.to_string());
+                let _ = run.outcome.send(Some(shared));
+            }
+        }
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut store) = self.store.lock() {
+            store.take_in_flight(&self.fingerprint, &self.run_id);
+        }
+    }
+}
+
+/// Event logs of recent runs, shared with their sinks.
+#[derive(Default)]
+pub struct RunEventStore {
+    runs: VecDeque<(String, Arc<Mutex<RunEventLog>>)>,
+    /// Runs that identical requests may still merge into, by fingerprint.
+    in_flight: HashMap<String, InFlightRun>,
+}
+
+impl RunEventStore {
+    /// Admit a generation request with `fingerprint`: attach `raw` to an
+    /// identical run already in flight when `merge` is set, or register a new
+    /// run for the caller to drive.
+    pub fn claim_run(
+        &mut self,
+        fingerprint: &str,
+        raw: Channel<EventEnvelope>,
+        merge: bool,
+    ) -> RunClaim {
+        if let Some(run) = self.in_flight.get(fingerprint) {
+            if merge {
+                run.sink.attach(raw);
+                return RunClaim::Joined {
+                    run_id: run.sink.run_id().to_string(),
+                    outcome: run.outcome.subscribe(),
+                };
+            }
+            let sink = EventSink::new(raw);
+            self.insert(sink.run_id.clone(), sink.log.clone());
+            return RunClaim::Started {
+                sink,
+                duplicate: true,
+            };
+        }
+        let sink = EventSink::new(raw);
+        self.insert(sink.run_id.clone(), sink.log.clone());
+        let (outcome, _) = watch::channel(None);
+        self.in_flight.insert(
+            fingerprint.to_string(),
+            InFlightRun {
+                sink: sink.clone(),
+                outcome,
+            },
+        );
+        RunClaim::Started {
+            sink,
+            duplicate: false,
+        }
+    }
+
+    /// Remove the in-flight entry for `fingerprint` if it still belongs to `run_id`.
+    fn take_in_flight(&mut self, fingerprint: &str, run_id: &str) -> Option<InFlightRun> {
+        if self.in_flight.get(fingerprint)?.sink.run_id() != run_id {
+            return None;
+        }
+        self.in_flight.remove(fingerprint)
+    }
+
+    fn insert(&mut self, run_id: String, log: Arc<Mutex<RunEventLog>>) {
+        self.runs.push_back((run_id, log));
+        while self.runs.len() > MAX_BUFFERED_RUNS {
+            self.runs.pop_front();
+        }
+    }
+
+    pub fn remove(&mut self, run_id: &str) {
+        self.runs.retain(|(id, _)| id != run_id);
+    }
+
+    /// STL of the newest buffered `FinalCode`, restricted to `run_id` and/or
+    /// `geometry_hash` when given.
+    pub fn final_stl(&self, run_id: Option<&str>, geometry_hash: Option<&str>) -> Option<String> {
+        self.runs
+            .iter()
+            .rev()
+            .filter(|(id, _)| run_id.is_none_or(|r| r == id))
+            .find_map(|(_, log)| log.lock().ok()?.final_stl(geometry_hash))
+    }
+
+    /// Code and STL of the newest buffered `FinalCode` of `run_id`.
+    pub fn final_code(&self, run_id: &str) -> Option<(String, Option<String>)> {
+        let (_, log) = self.runs.iter().find(|(id, _)| id == run_id)?;
+        let log = log.lock().ok()?;
+        log.final_code()
+    }
+
+    pub fn timeline(&self, run_id: &str) -> Option<RunTimeline> {
+        let (_, log) = self.runs.iter().find(|(id, _)| id == run_id)?;
+        let log = log.lock().ok()?;
+        Some(RunTimeline {
+            started_ms: log.started_ms,
+            events: log
+                .sent_ms
+                .iter()
+                .zip(log.events.iter())
+                .map(|(at, e)| (*at, e.event.clone()))
+                .collect(),
+            settings: log.settings.clone(),
+        })
+    }
+
+    /// Buffered events of `run_id` with `seq > since_seq`.
+    pub fn events_since(&self, run_id: &str, since_seq: u64) -> Result<RunEvents, AppError> {
+        let log = self
+            .runs
+            .iter()
+            .find(|(id, _)| id == run_id)
+            .map(|(_, log)| log)
+            .ok_or_else(|| {
+                AppError::ConfigError(format!("No buffered events for run '{}'", run_id))
+            })?;
+        let log = log
+            .lock()
+            .map_err(|_| AppError::ConfigError("Run event log lock poisoned".into()))?;
+        Ok(RunEvents {
+            run_id: run_id.to_string(),
+            status: log.status,
+            last_seq: log.next_seq,
+            events: log
+                .events
+                .iter()
+                .filter(|e| e.seq > since_seq)
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+#[derive(Clone, Serialize, JsonSchema)]
+pub struct RunEvents {
+    pub run_id: String,
+    pub status: RunChannelStatus,
+    /// Highest sequence number issued so far, including unbuffered deltas.
+    pub last_seq: u64,
+    pub events: Vec<EventEnvelope>,
+}
+
+/// Events a frontend missed, e.g. after a webview reload during a run.
+#[tauri::command]
+pub fn get_run_events(
+    run_id: String,
+    since_seq: u64,
+    state: State<'_, AppState>,
+) -> Result<RunEvents, AppError> {
+    let store = state
+        .run_events
+        .lock()
+        .map_err(|_| AppError::ConfigError("Run event store lock poisoned".into()))?;
+    store.events_since(&run_id, since_seq)
+}
+
+/// Attempt chains of a run's validations, each with the code, error, repair
+/// instruction and diff of every attempt. `part_name` limits them to one
+/// part's acceptance chain.
+#[tauri::command]
+pub fn get_validation_history(
+    run_id: String,
+    part_name: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ValidationChain>, AppError> {
+    let store = state
+        .run_store
+        .lock()
+        .map_err(|e| AppError::ConfigError(format!("Failed to lock run store: {}", e)))?;
+    Ok(store.validation_chains(&run_id, part_name.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tauri::ipc::InvokeResponseBody;
+
+    fn status(message: &str) -> MultiPartEvent {
+        MultiPartEvent::PlanStatus {
+            message: message.to_string(),
+        }
+    }
+
+    fn delta(text: &str) -> MultiPartEvent {
+        MultiPartEvent::SingleDelta {
+            delta: text.to_string(),
+            done: false,
+        }
+    }
+
+    fn sink_with(
+        connected: Arc<AtomicBool>,
+        received: Arc<Mutex<Vec<serde_json::Value>>>,
+    ) -> EventSink {
+        EventSink::new(Channel::new(move |body| {
+            if !connected.load(Ordering::SeqCst) {
+                return Err(tauri::Error::WebviewNotFound);
+            }
+            if let InvokeResponseBody::Json(json) = body {
+                received
+                    .lock()
+                    .unwrap()
+                    .push(serde_json::from_str(&json).unwrap());
+            }
+            Ok(())
+        }))
+    }
+
+    #[test]
+    fn test_envelope_numbers_events_and_flattens_kind() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = sink_with(Arc::new(AtomicBool::new(true)), received.clone());
+        sink.send(status("one")).unwrap();
+        sink.send(delta("x")).unwrap();
+        sink.send(status("two")).unwrap();
+
+        let received = received.lock().unwrap();
+        let seqs: Vec<u64> = received
+            .iter()
+            .map(|e| e["seq"].as_u64().unwrap())
+            .collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+        assert_eq!(received[0]["kind"], "PlanStatus");
+        assert_eq!(received[0]["message"], "one");
+        assert_eq!(received[0]["run_id"], sink.run_id());
+    }
+
+    #[test]
+    fn test_child_sink_has_own_run_id_and_buffer() {
+        let store = Mutex::new(RunEventStore::default());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let parent = sink_with(Arc::new(AtomicBool::new(true)), received.clone());
+        let first = parent.child(&store);
+        let second = parent.child(&store);
+        first.send(status("first")).unwrap();
+        second.send(status("second")).unwrap();
+
+        assert_ne!(first.run_id(), second.run_id());
+        assert_ne!(first.run_id(), parent.run_id());
+        let received = received.lock().unwrap();
+        assert_eq!(received[0]["run_id"], first.run_id());
+        assert_eq!(received[0]["seq"], 1);
+        assert_eq!(received[1]["run_id"], second.run_id());
+        assert_eq!(received[1]["seq"], 1);
+        let page = store
+            .lock()
+            .unwrap()
+            .events_since(second.run_id(), 0)
+            .unwrap();
+        assert_eq!(page.events.len(), 1);
+    }
+
+    #[test]
+    fn test_buffer_skips_deltas_and_serves_since_seq() {
+        let store = Mutex::new(RunEventStore::default());
+        let sink = EventSink::register(&store, Channel::new(|_| Ok(())));
+        sink.send(status("plan")).unwrap();
+        sink.send(delta("a")).unwrap();
+        sink.send(delta("b")).unwrap();
+        sink.send(status("parts")).unwrap();
+
+        let page = store
+            .lock()
+            .unwrap()
+            .events_since(sink.run_id(), 1)
+            .unwrap();
+        assert_eq!(page.last_seq, 4);
+        assert_eq!(page.status, RunChannelStatus::Connected);
+        let seqs: Vec<u64> = page.events.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![4]);
+        assert!(store.lock().unwrap().events_since("other", 0).is_err());
+
+        for i in 0..EVENT_BUFFER_CAPACITY + 10 {
+            sink.send(status(&i.to_string())).unwrap();
+        }
+        let page = store
+            .lock()
+            .unwrap()
+            .events_since(sink.run_id(), 0)
+            .unwrap();
+        assert_eq!(page.events.len(), EVENT_BUFFER_CAPACITY);
+        assert_eq!(page.events.last().unwrap().seq, page.last_seq);
+    }
+
+    #[test]
+    fn test_consistent_send_failures_mark_disconnected() {
+        let connected = Arc::new(AtomicBool::new(true));
+        let store = Mutex::new(RunEventStore::default());
+        let flag = connected.clone();
+        let sink = EventSink::register(
+            &store,
+            Channel::new(move |_| {
+                if flag.load(Ordering::SeqCst) {
+                    Ok(())
+                } else {
+                    Err(tauri::Error::WebviewNotFound)
+                }
+            }),
+        );
+        sink.send(status("plan")).unwrap();
+        assert!(sink.ensure_connected().is_ok());
+
+        connected.store(false, Ordering::SeqCst);
+        for _ in 0..DISCONNECT_AFTER_FAILURES - 1 {
+            assert!(sink.send(delta("x")).is_err());
+            assert!(!sink.is_disconnected());
+        }
+        assert!(sink.send(delta("x")).is_err());
+        assert!(sink.is_disconnected());
+
+        // Disconnected sinks keep buffering for catch-up, even if the webview returns.
+        connected.store(true, Ordering::SeqCst);
+        assert!(sink.send(status("after")).is_err());
+        let page = store
+            .lock()
+            .unwrap()
+            .events_since(sink.run_id(), 1)
+            .unwrap();
+        assert!(page.events.iter().any(|e| matches!(
+            &e.event,
+            MultiPartEvent::PlanStatus { message } if message == "after"
+        )));
+    }
+
+    #[test]
+    fn test_phase_boundary_stops_after_one_failed_send() {
+        let connected = Arc::new(AtomicBool::new(true));
+        let sink = sink_with(connected.clone(), Arc::new(Mutex::new(Vec::new())));
+        sink.send(status("plan")).unwrap();
+        connected.store(false, Ordering::SeqCst);
+        assert!(sink.send(status("plan ready")).is_err());
+        assert!(!sink.is_disconnected());
+        assert!(matches!(
+            sink.ensure_connected(),
+            Err(AppError::ChannelDisconnected(_))
+        ));
+        assert!(sink.is_disconnected());
+    }
+
+    #[test]
+    fn test_store_keeps_recent_runs() {
+        let store = Mutex::new(RunEventStore::default());
+        let sinks: Vec<EventSink> = (0..MAX_BUFFERED_RUNS + 2)
+            .map(|_| EventSink::register(&store, Channel::new(|_| Ok(()))))
+            .collect();
+        let store = store.lock().unwrap();
+        assert!(store.events_since(sinks[0].run_id(), 0).is_err());
+        assert!(store
+            .events_since(sinks.last().unwrap().run_id(), 0)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_final_stl_finds_latest_by_run_or_hash() {
+        let store = Mutex::new(RunEventStore::default());
+        let final_code = |stl: &str, hash: &str| MultiPartEvent::FinalCode {
+            code: "result = Box(1, 1, 1)".to_string(),
+            stl_base64: Some(stl.to_string()),
+            geometry_hash: Some(hash.to_string()),
+        };
+        let first = EventSink::register(&store, Channel::new(|_| Ok(())));
+        first.send(final_code("AAAA", "aaaa")).unwrap();
+        let second = EventSink::register(&store, Channel::new(|_| Ok(())));
+        second.send(status("done")).unwrap();
+        second.send(final_code("BBBB", "bbbb")).unwrap();
+
+        let store = store.lock().unwrap();
+        assert_eq!(store.final_stl(None, None).as_deref(), Some("BBBB"));
+        assert_eq!(
+            store.final_stl(Some(first.run_id()), None).as_deref(),
+            Some("AAAA")
+        );
+        assert_eq!(store.final_stl(None, Some("aaaa")).as_deref(), Some("AAAA"));
+        assert!(store
+            .final_stl(Some(first.run_id()), Some("bbbb"))
+            .is_none());
+    }
+
+    fn collecting_channel(received: Arc<Mutex<Vec<serde_json::Value>>>) -> Channel<EventEnvelope> {
+        Channel::new(move |body| {
+            if let InvokeResponseBody::Json(json) = body {
+                received
+                    .lock()
+                    .unwrap()
+                    .push(serde_json::from_str(&json).unwrap());
+            }
+            Ok(())
+        })
+    }
+
+    fn started(claim: RunClaim) -> (EventSink, bool) {
+        match claim {
+            RunClaim::Started { sink, duplicate } => (sink, duplicate),
+            RunClaim::Joined { .. } => panic!("expected a new run"),
+        }
+    }
+
+    #[test]
+    fn test_identical_request_joins_run_and_replays_buffered_events() {
+        let store = Mutex::new(RunEventStore::default());
+        let first_received = Arc::new(Mutex::new(Vec::new()));
+        let second_received = Arc::new(Mutex::new(Vec::new()));
+        let (sink, duplicate) = started(store.lock().unwrap().claim_run(
+            "fp",
+            collecting_channel(first_received.clone()),
+            true,
+        ));
+        assert!(!duplicate);
+        sink.send(status("plan")).unwrap();
+        sink.send(delta("a")).unwrap();
+        sink.send(status("parts")).unwrap();
+
+        let claim = store.lock().unwrap().claim_run(
+            "fp",
+            collecting_channel(second_received.clone()),
+            true,
+        );
+        let RunClaim::Joined { run_id, .. } = claim else {
+            panic!("identical request should join the running run");
+        };
+        assert_eq!(run_id, sink.run_id());
+        sink.send(delta("b")).unwrap();
+        sink.send(status("code")).unwrap();
+
+        let seqs = |received: &Arc<Mutex<Vec<serde_json::Value>>>| -> Vec<u64> {
+            received
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|e| e["seq"].as_u64().unwrap())
+                .collect()
+        };
+        assert_eq!(seqs(&first_received), vec![1, 2, 3, 4, 5]);
+        // Buffered non-delta events first, then the live stream.
+        assert_eq!(seqs(&second_received), vec![1, 3, 4, 5]);
+        assert!(second_received
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|e| e["run_id"] == sink.run_id()));
+    }
+
+    #[tokio::test]
+    async fn test_merged_request_receives_run_result() {
+        let store = Mutex::new(RunEventStore::default());
+        let (sink, _) = started(store.lock().unwrap().claim_run(
+            "fp",
+            Channel::new(|_| Ok(())),
+            true,
+        ));
+        let guard = InFlightGuard::new(&store, "fp".to_string(), &sink);
+        let claim = store
+            .lock()
+            .unwrap()
+            .claim_run("fp", Channel::new(|_| Ok(())), true);
+        let RunClaim::Joined { run_id, outcome } = claim else {
+            panic!("identical request should join the running run");
+        };
+
+        guard.finish(&Ok("Generated a bracket".to_string()));
+        assert_eq!(
+            merged_outcome(&run_id, outcome).await.unwrap(),
+            "Generated a bracket"
+        );
+        // The finished run no longer accepts merges.
+        let (_, duplicate) = started(store.lock().unwrap().claim_run(
+            "fp",
+            Channel::new(|_| Ok(())),
+            true,
+        ));
+        assert!(!duplicate);
+
+        // A run dropped without finishing releases its merged requests.
+        let (sink, _) = started(store.lock().unwrap().claim_run(
+            "other",
+            Channel::new(|_| Ok(())),
+            true,
+        ));
+        let guard = InFlightGuard::new(&store, "other".to_string(), &sink);
+        let claim = store
+            .lock()
+            .unwrap()
+            .claim_run("other", Channel::new(|_| Ok(())), true);
+        let RunClaim::Joined { run_id, outcome } = claim else {
+            panic!("identical request should join the running run");
+        };
+        drop(guard);
+        assert!(merged_outcome(&run_id, outcome).await.is_err());
+    }
+
+    #[test]
+    fn test_merge_opt_out_starts_separate_run() {
+        let store = Mutex::new(RunEventStore::default());
+        let (first, _) = started(store.lock().unwrap().claim_run(
+            "fp",
+            Channel::new(|_| Ok(())),
+            false,
+        ));
+        let _first_guard = InFlightGuard::new(&store, "fp".to_string(), &first);
+        let (second, duplicate) = started(store.lock().unwrap().claim_run(
+            "fp",
+            Channel::new(|_| Ok(())),
+            false,
+        ));
+        assert!(duplicate);
+        assert_ne!(second.run_id(), first.run_id());
+
+        // Ending the separate run leaves the original one in flight.
+        drop(InFlightGuard::new(&store, "fp".to_string(), &second));
+        let claim = store
+            .lock()
+            .unwrap()
+            .claim_run("fp", Channel::new(|_| Ok(())), true);
+        assert!(matches!(claim, RunClaim::Joined { run_id, .. } if run_id == first.run_id()));
+    }
+
+    #[test]
+    fn test_run_fingerprint_covers_request_inputs() {
+        let config = AppConfig::default();
+        let base = run_fingerprint("a bracket", None, None, &config, Some("result = 1"));
+        assert_eq!(
+            base,
+            run_fingerprint("a bracket", None, None, &config, Some("result = 1"))
+        );
+        assert_ne!(
+            base,
+            run_fingerprint("a bracket", None, None, &config, Some("result = 2"))
+        );
+        assert_ne!(
+            base,
+            run_fingerprint("a bracket", Some("plan"), None, &config, Some("result = 1"))
+        );
+        let mut other = config.clone();
+        other.model = "another-model".to_string();
+        assert_ne!(
+            base,
+            run_fingerprint("a bracket", None, None, &other, Some("result = 1"))
+        );
+    }
+}
@@ -1,6 +1,15 @@
+use crate::agent::custom_rules::{self, RuleSource};
+use crate::agent::design::{self, ProfileSuggestion};
+use crate::agent::print_estimate::{self, PrinterProfile};
+use crate::agent::rules::AgentRules;
+use crate::ai::cost::{self, ModelPricing, PricingOverrides};
+use crate::ai::models;
 use crate::ai::registry::{self, ProviderInfo};
+use crate::error::AppError;
 use crate::config::AppConfig;
+use crate::pipeline_presets::{self, PipelinePreset};
 use crate::state::AppState;
+use serde::Serialize;
 use tauri::State;
 
 #[tauri::command]
@@ -8,6 +17,84 @@ pub fn get_provider_registry() -> Vec<ProviderInfo> {
     registry::get_provider_registry()
 }
 
+/// Model ids the provider currently offers, queried with the stored key
+/// (installed models for Ollama). Briefly cached per provider.
+#[tauri::command]
+pub async fn list_models(
+    provider: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, AppError> {
+    let config = state.config.lock().unwrap().clone();
+    models::list_models(&provider, &config).await
+}
+
+/// Rule files behind the selected preset, in merge order, with the sections
+/// each one sets and why any were skipped.
+#[tauri::command]
+pub fn list_agent_rule_sources(state: State<'_, AppState>) -> Result<Vec<RuleSource>, AppError> {
+    let config = state.config.lock().unwrap().clone();
+    custom_rules::set_dir(config.custom_rules_dir.as_deref());
+    let (preset_name, preset_yaml) = AgentRules::preset_yaml(config.agent_rules_preset.as_deref());
+    custom_rules::list_sources(preset_name, preset_yaml)
+}
+
+/// Rule content the geometry advisor would receive for `message` under the
+/// current budgets, with each section's included and omitted rule counts.
+#[tauri::command]
+pub fn preview_prompt_context(
+    message: String,
+    state: State<'_, AppState>,
+) -> Result<design::RuleContext, AppError> {
+    let config = state.config.lock().unwrap().clone();
+    custom_rules::set_dir(config.custom_rules_dir.as_deref());
+    let rules = AgentRules::from_preset(config.agent_rules_preset.as_deref())?;
+    Ok(design::format_rule_context(&rules, &message, &config))
+}
+
+/// Reliability profile suggested for `message` from its text alone, so the
+/// setting can be chosen before generating.
+#[tauri::command]
+pub fn suggest_profile(message: String, state: State<'_, AppState>) -> ProfileSuggestion {
+    let mut suggestion = design::suggest_profile(&message);
+    suggestion.already_selected =
+        state.config.lock().unwrap().generation_reliability_profile == suggestion.profile;
+    suggestion
+}
+
+/// Pricing used for cost estimates, and the user overrides it includes.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelPricingSettings {
+    pub effective: ModelPricing,
+    pub overrides: PricingOverrides,
+}
+
+#[tauri::command]
+pub fn get_model_pricing() -> ModelPricingSettings {
+    let (effective, overrides) = cost::current_pricing();
+    ModelPricingSettings {
+        effective,
+        overrides,
+    }
+}
+
+/// Replace the user's pricing overrides. They apply to the next cost
+/// estimate and are saved as `model_pricing.json` unless in safe mode.
+#[tauri::command]
+pub fn set_model_pricing(
+    overrides: PricingOverrides,
+    state: State<'_, AppState>,
+) -> Result<ModelPricingSettings, AppError> {
+    overrides.validate()?;
+    if state.config.lock().unwrap().allows_app_data_writes() {
+        overrides.save()?;
+    }
+    let effective = cost::set_overrides(overrides.clone())?;
+    Ok(ModelPricingSettings {
+        effective,
+        overrides,
+    })
+}
+
 #[tauri::command]
 pub fn get_settings(state: State<'_, AppState>) -> Result<AppConfig, String> {
     let config = state
@@ -18,14 +105,103 @@ pub fn get_settings(state: State<'_, AppState>) -> Result<AppConfig, String> {
 }
 
 #[tauri::command]
-pub fn update_settings(state: State<'_, AppState>, config: AppConfig) -> Result<(), String> {
+pub fn update_settings(state: State<'_, AppState>, mut config: AppConfig) -> Result<(), String> {
+    let mut current = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock config: {}", e))?;
+    // Presets are managed by their own commands; a settings form loaded
+    // before a preset was saved must not drop it.
+    config.pipeline_presets = current.pipeline_presets.clone();
     // Save to disk
     config.save().map_err(|e| format!("{}", e))?;
+    custom_rules::set_dir(config.custom_rules_dir.as_deref());
+    crate::artifacts::set_app_data_writes(config.allows_app_data_writes());
     // Update in memory
+    *current = config;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_pipeline_presets(state: State<'_, AppState>) -> Result<Vec<PipelinePreset>, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock config: {}", e))?;
+    Ok(pipeline_presets::list(&config))
+}
+
+/// Create or replace a user preset. Built-ins are cloned by saving them under a new id.
+#[tauri::command]
+pub fn save_pipeline_preset(
+    state: State<'_, AppState>,
+    preset: PipelinePreset,
+) -> Result<Vec<PipelinePreset>, String> {
     let mut current = state
         .config
         .lock()
         .map_err(|e| format!("Failed to lock config: {}", e))?;
-    *current = config;
-    Ok(())
+    let mut updated = current.clone();
+    pipeline_presets::save(&mut updated, preset).map_err(|e| format!("{}", e))?;
+    updated.save().map_err(|e| format!("{}", e))?;
+    *current = updated;
+    Ok(pipeline_presets::list(&current))
+}
+
+#[tauri::command]
+pub fn delete_pipeline_preset(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<PipelinePreset>, String> {
+    let mut current = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock config: {}", e))?;
+    let mut updated = current.clone();
+    pipeline_presets::delete(&mut updated, &id).map_err(|e| format!("{}", e))?;
+    updated.save().map_err(|e| format!("{}", e))?;
+    *current = updated;
+    Ok(pipeline_presets::list(&current))
+}
+
+#[tauri::command]
+pub fn list_printer_profiles(state: State<'_, AppState>) -> Result<Vec<PrinterProfile>, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock config: {}", e))?;
+    Ok(config.printer_profiles.clone())
+}
+
+/// Create or replace a printer profile used for print estimates.
+#[tauri::command]
+pub fn save_printer_profile(
+    state: State<'_, AppState>,
+    profile: PrinterProfile,
+) -> Result<Vec<PrinterProfile>, String> {
+    let mut current = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock config: {}", e))?;
+    let mut updated = current.clone();
+    print_estimate::save(&mut updated, profile).map_err(|e| format!("{}", e))?;
+    updated.save().map_err(|e| format!("{}", e))?;
+    *current = updated;
+    Ok(current.printer_profiles.clone())
+}
+
+#[tauri::command]
+pub fn delete_printer_profile(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<PrinterProfile>, String> {
+    let mut current = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock config: {}", e))?;
+    let mut updated = current.clone();
+    print_estimate::delete(&mut updated, &id).map_err(|e| format!("{}", e))?;
+    updated.save().map_err(|e| format!("{}", e))?;
+    *current = updated;
+    Ok(current.printer_profiles.clone())
 }
@@ -0,0 +1,25 @@
+use tauri::State;
+
+use crate::artifacts::{self, CleanupReport, RetentionPolicy, StorageUsage};
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Disk used by the app data directory, by category.
+#[tauri::command]
+pub fn get_storage_usage() -> StorageUsage {
+    artifacts::storage_usage()
+}
+
+/// Apply the configured retention policy now. With `dry_run`, nothing is
+/// deleted and the report lists what would be freed.
+#[tauri::command]
+pub fn clean_storage(
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<CleanupReport, AppError> {
+    let config = state.config.lock().unwrap().clone();
+    artifacts::clean(
+        &RetentionPolicy::from_config(&config),
+        dry_run.unwrap_or(false),
+    )
+}
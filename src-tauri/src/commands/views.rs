@@ -0,0 +1,85 @@
+use tauri::State;
+
+use crate::agent::executor;
+use crate::agent::views::{self, Bounds, CameraView, StandardViews, ViewBookmark};
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Bounds of the newest final geometry of `run_id`, matching `geometry_hash`
+/// when given; with neither, of the latest run.
+fn resolve_bounds(
+    state: &AppState,
+    run_id: Option<&str>,
+    geometry_hash: Option<&str>,
+) -> Result<Option<Bounds>, AppError> {
+    let stl = state
+        .run_events
+        .lock()
+        .map_err(|_| AppError::ConfigError("Run event store lock poisoned".into()))?
+        .final_stl(run_id, geometry_hash);
+    Ok(stl.and_then(|stl| executor::stl_bounds_base64(&stl)))
+}
+
+fn no_geometry(run_id: Option<&str>, geometry_hash: Option<&str>) -> AppError {
+    let target = match (run_id, geometry_hash) {
+        (Some(run), _) => format!("run '{}'", run),
+        (None, Some(hash)) => format!("geometry '{}'", hash),
+        (None, None) => "any recent run".to_string(),
+    };
+    AppError::ConfigError(format!("No final geometry buffered for {}", target))
+}
+
+/// Front/back/top/bottom/left/right/isometric cameras framed on the final
+/// geometry of a run. `aspect` is the viewport's width over height.
+#[tauri::command]
+pub fn get_standard_views(
+    run_id: Option<String>,
+    geometry_hash: Option<String>,
+    aspect: Option<f64>,
+    state: State<'_, AppState>,
+) -> Result<StandardViews, AppError> {
+    let bounds = resolve_bounds(&state, run_id.as_deref(), geometry_hash.as_deref())?
+        .ok_or_else(|| no_geometry(run_id.as_deref(), geometry_hash.as_deref()))?;
+    Ok(StandardViews {
+        bounds,
+        views: views::standard_views(&bounds, views::DEFAULT_FOV_DEG, aspect.unwrap_or(1.0)),
+    })
+}
+
+/// Save `camera` under its name, replacing an existing bookmark of that name.
+/// The bookmark remembers the bounds of the geometry it was saved on.
+#[tauri::command]
+pub fn save_view_bookmark(
+    camera: CameraView,
+    run_id: Option<String>,
+    geometry_hash: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ViewBookmark>, AppError> {
+    if camera.name.trim().is_empty() {
+        return Err(AppError::ConfigError("Bookmark name is empty".into()));
+    }
+    let bounds = resolve_bounds(&state, run_id.as_deref(), geometry_hash.as_deref())?
+        .ok_or_else(|| no_geometry(run_id.as_deref(), geometry_hash.as_deref()))?;
+    let mut bookmarks = state.view_bookmarks.lock().unwrap();
+    views::upsert_bookmark(&mut bookmarks, ViewBookmark { camera, bounds });
+    Ok(bookmarks.clone())
+}
+
+/// Bookmarks of the open project, rescaled onto the given run's geometry when
+/// it can be found so they keep pointing at the same region of the model.
+#[tauri::command]
+pub fn list_view_bookmarks(
+    run_id: Option<String>,
+    geometry_hash: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ViewBookmark>, AppError> {
+    let bounds = resolve_bounds(&state, run_id.as_deref(), geometry_hash.as_deref())?;
+    let bookmarks = state.view_bookmarks.lock().unwrap();
+    Ok(match bounds {
+        Some(bounds) => bookmarks
+            .iter()
+            .map(|b| views::rescale_bookmark(b, &bounds))
+            .collect(),
+        None => bookmarks.clone(),
+    })
+}
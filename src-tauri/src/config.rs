@@ -1,8 +1,10 @@
 use crate::error::AppError;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum GenerationReliabilityProfile {
     ReliabilityFirst,
@@ -29,6 +31,15 @@ impl Default for ReviewerMode {
     }
 }
 
+/// Aspect the code reviewer should concentrate on, on top of its generic checklist.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewFocus {
+    DimensionalAccuracy,
+    Manufacturability,
+    CodeQuality,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum SemanticBboxMode {
@@ -42,6 +53,53 @@ impl Default for SemanticBboxMode {
     }
 }
 
+/// Whether retrieval may call the embeddings API.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetrievalEmbeddingsMode {
+    /// Embed uncached queries and corpus items over the network.
+    #[default]
+    Online,
+    /// Only use cached vectors; uncached queries are ranked lexically.
+    CachedOnly,
+    /// Lexical ranking only.
+    Disabled,
+}
+
+/// How to reconcile a planner reply that says `single` but lists several parts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecompositionBias {
+    #[default]
+    PreferMulti,
+    PreferSingle,
+}
+
+/// What to do when every planner attempt fails on a request that does not
+/// need the multipart contract.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannerFailurePolicy {
+    /// Generate a single part without telling the user.
+    SilentSingleFallback,
+    /// Emit a warning, then generate a single part.
+    #[default]
+    WarnAndSingle,
+    /// Stop with an error so the user can retry.
+    Fail,
+}
+
+/// What to do with a run whose frontend stopped receiving events.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelDisconnectPolicy {
+    /// Stop the run but keep its part candidates and buffered events.
+    #[default]
+    Park,
+    /// Stop the run and discard its state.
+    Cancel,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub ai_provider: String,
@@ -73,16 +131,56 @@ pub struct AppConfig {
     pub snap_sketch: Option<f64>,
     #[serde(default)]
     pub enable_consensus: bool,
+    /// Continue from a design plan into generation without manual approval
+    /// when the plan passes the auto-approval gates.
     #[serde(default)]
     pub auto_approve_plan: bool,
+    /// Highest plan risk score (0-10) that may be auto-approved.
+    #[serde(default = "default_auto_approve_max_risk")]
+    pub auto_approve_max_risk: u32,
     #[serde(default = "default_true")]
     pub retrieval_enabled: bool,
     #[serde(default = "default_retrieval_token_budget")]
     pub retrieval_token_budget: u32,
+    #[serde(default)]
+    pub retrieval_min_score: f32,
+    /// Rules presets, mechanism package ids or retrieval sources ranked
+    /// above the rest.
+    #[serde(default)]
+    pub prioritized_retrieval_packs: Vec<String>,
+    #[serde(default = "default_retrieval_pack_boost")]
+    pub retrieval_pack_boost: f32,
+    /// Retrieval item ids (e.g. "cookbook:3", "mechanism:snap_fit") always
+    /// placed at the top of the retrieved context, whatever their score.
+    #[serde(default)]
+    pub pinned_retrieval_ids: Vec<String>,
+    #[serde(default)]
+    pub retrieval_embeddings: RetrievalEmbeddingsMode,
+    /// Triangle budget for part preview STLs; larger meshes are decimated for
+    /// display only (0 disables).
+    #[serde(default = "default_preview_max_triangles")]
+    pub preview_max_triangles: u32,
+    /// Directory of YAML rule files merged over the selected rules preset.
+    #[serde(default)]
+    pub custom_rules_dir: Option<String>,
+    /// Character budgets for the rule sections given to the geometry advisor;
+    /// the least relevant rules are dropped past them (0 disables).
+    #[serde(default = "default_manufacturing_rules_budget_chars")]
+    pub manufacturing_rules_budget_chars: usize,
+    #[serde(default = "default_dimension_guidance_budget_chars")]
+    pub dimension_guidance_budget_chars: usize,
+    #[serde(default = "default_failure_prevention_budget_chars")]
+    pub failure_prevention_budget_chars: usize,
     #[serde(default = "default_true")]
     pub telemetry_enabled: bool,
     #[serde(default = "default_max_validation_attempts")]
     pub max_validation_attempts: u32,
+    /// Design plans tried before a too-risky plan is given up on (min 1).
+    #[serde(default = "default_max_design_replan_attempts")]
+    pub max_design_replan_attempts: u32,
+    /// Planner responses tried before JSON parse failures are given up on (min 1).
+    #[serde(default = "default_max_planner_parse_attempts")]
+    pub max_planner_parse_attempts: u32,
     #[serde(default)]
     pub generation_reliability_profile: GenerationReliabilityProfile,
     #[serde(default = "default_true")]
@@ -93,12 +191,46 @@ pub struct AppConfig {
     pub semantic_contract_strict: bool,
     #[serde(default)]
     pub reviewer_mode: ReviewerMode,
+    /// Empty runs the generic review only.
+    #[serde(default)]
+    pub reviewer_focus: Vec<ReviewFocus>,
+    /// Run the AI reviewer on every result. When off, code that passes the
+    /// static checks skips the review call.
+    #[serde(default = "default_true")]
+    pub always_review: bool,
     #[serde(default = "default_true")]
     pub quality_gates_strict: bool,
     #[serde(default = "default_true")]
     pub allow_euler_override: bool,
     #[serde(default)]
     pub semantic_bbox_mode: SemanticBboxMode,
+    /// Compare each multi-part part's bbox with the whole-assembly envelope;
+    /// strict quality gates reject an oversized part, otherwise it is flagged.
+    #[serde(default)]
+    pub assembly_envelope_check: bool,
+    /// How far (percent) a part extent may exceed the assembly envelope.
+    #[serde(default = "default_assembly_envelope_margin_pct")]
+    pub assembly_envelope_margin_pct: f64,
+    /// Extra plan risk when an organic request's plan has no Approximation
+    /// Notes section (0 disables the check).
+    #[serde(default = "default_organic_missing_notes_risk")]
+    pub organic_missing_notes_risk: u32,
+    /// Geometric operations a plan or script may need before a warning
+    /// (0 disables the check).
+    #[serde(default = "default_operation_soft_budget")]
+    pub operation_soft_budget: u32,
+    /// Geometric operations above which a plan is re-planned and a script is
+    /// repaired with a simplification instruction (0 disables the check).
+    #[serde(default = "default_operation_hard_budget")]
+    pub operation_hard_budget: u32,
+    /// Offer the predefined `metric_thread` helper for threaded parts and
+    /// prepend it to scripts that call it.
+    #[serde(default = "default_true")]
+    pub enable_thread_helper: bool,
+    /// Emit a short note per validation retry explaining the detected error
+    /// and the fix being applied.
+    #[serde(default)]
+    pub explain_retries: bool,
     #[serde(default = "default_true")]
     pub mechanisms_enabled: bool,
     #[serde(default)]
@@ -107,6 +239,113 @@ pub struct AppConfig {
     pub mechanism_cache_max_mb: u32,
     #[serde(default = "default_allowed_spdx_licenses")]
     pub allowed_spdx_licenses: Vec<String>,
+    #[serde(default)]
+    pub escalation_model: Option<String>,
+    #[serde(default = "default_true")]
+    pub refine_assembly_positions: bool,
+    #[serde(default)]
+    pub assembly_part_gap_mm: f64,
+    /// Shift the placed assembly in Z so its lowest point rests on the build
+    /// plate (Z=0).
+    #[serde(default)]
+    pub assembly_rest_on_plate: bool,
+    /// Reword exported assembly instructions with one AI call.
+    #[serde(default)]
+    pub polish_assembly_instructions: bool,
+    /// Material name (lowercase) → density in g/cm³, used for mass properties.
+    #[serde(default = "default_materials")]
+    pub materials: BTreeMap<String, f64>,
+    #[serde(default = "default_material_density_g_cm3")]
+    pub default_material_density_g_cm3: f64,
+    #[serde(default)]
+    pub decomposition_bias: DecompositionBias,
+    /// Upper bound on planner parts; extra parts are dropped (None = unlimited).
+    #[serde(default)]
+    pub max_plan_parts: Option<u32>,
+    #[serde(default)]
+    pub planner_failure_policy: PlannerFailurePolicy,
+    /// Requests per minute allowed to the AI provider (None = unlimited).
+    #[serde(default)]
+    pub provider_rpm_limit: Option<u32>,
+    /// Answer repeated identical provider requests (same messages, model and
+    /// parameters) from an in-memory cache at zero token cost. Never applies
+    /// to calls sampled above temperature 0.
+    #[serde(default)]
+    pub cache_provider_responses: bool,
+    /// Stop sequences sent with code-generation calls so the model halts
+    /// after the code block; providers that reject them ignore the setting.
+    #[serde(default = "default_code_stop_sequences")]
+    pub code_stop_sequences: Vec<String>,
+    /// Append a record of every provider call (sizes and SHA-256 digests) to
+    /// the audit log in the app data directory. Retrieval embeddings
+    /// requests are recorded too.
+    #[serde(default)]
+    pub audit_provider_calls: bool,
+    /// Also store the request and response text in audit records, with API
+    /// keys and `audit_redaction_patterns` masked.
+    #[serde(default)]
+    pub log_full_content: bool,
+    /// Regexes masked in logged audit content, on top of the API key itself.
+    #[serde(default = "default_audit_redaction_patterns")]
+    pub audit_redaction_patterns: Vec<String>,
+    /// The audit log rotates to a single backup file above this size.
+    #[serde(default = "default_audit_log_max_mb")]
+    pub audit_log_max_mb: u32,
+    /// Saved run artifacts older than this are deleted.
+    #[serde(default = "default_artifact_max_age_days")]
+    pub artifact_max_age_days: u32,
+    /// Newest runs that keep their STL/STEP files; older ones keep metadata only.
+    #[serde(default = "default_artifact_keep_heavy_runs")]
+    pub artifact_keep_heavy_runs: usize,
+    /// Re-import STEP exports and compare them with the post-geometry report
+    /// when the export command does not say either way.
+    #[serde(default)]
+    pub verify_step_exports: bool,
+    /// Per-axis bounding box drift accepted by STEP export verification (mm).
+    #[serde(default = "default_export_verify_bbox_tolerance_mm")]
+    pub export_verify_bbox_tolerance_mm: f64,
+    /// Relative volume drift accepted by STEP export verification (percent).
+    #[serde(default = "default_export_verify_volume_tolerance_pct")]
+    pub export_verify_volume_tolerance_pct: f64,
+    /// Park multi-part runs after part acceptance until `approve_assembly`.
+    #[serde(default)]
+    pub pause_before_assembly: bool,
+    /// Clearance rule set for mating parts (`fdm`, `sla`, `cnc_it7`, `cnc_it9`);
+    /// None picks one from the agent rules preset.
+    #[serde(default)]
+    pub manufacturing_process: Option<String>,
+    #[serde(default)]
+    pub channel_disconnect_policy: ChannelDisconnectPolicy,
+    /// Attach a request identical to one already running to that run instead
+    /// of starting a second pipeline.
+    #[serde(default = "default_true")]
+    pub merge_duplicate_runs: bool,
+    /// Standing house-style instructions appended to every generation prompt.
+    #[serde(default)]
+    pub custom_system_prompt_suffix: Option<String>,
+    /// Slicer settings for print time and material estimates.
+    #[serde(default = "crate::agent::print_estimate::default_printer_profiles")]
+    pub printer_profiles: Vec<crate::agent::print_estimate::PrinterProfile>,
+    #[serde(default)]
+    pub active_printer_profile: Option<String>,
+    /// Candidates (accepted or rejected) kept per part for cherry-picking.
+    #[serde(default = "default_max_part_candidates")]
+    pub max_part_candidates: usize,
+    #[serde(default = "default_part_candidate_store_max_mb")]
+    pub part_candidate_store_max_mb: u32,
+    /// Write per-part prompts to `multipart_debug.log` in the app data directory.
+    #[serde(default = "default_debug_prompt_logging")]
+    pub debug_prompt_logging: bool,
+    /// Re-plan once when a valid design plan still scores Low confidence.
+    #[serde(default)]
+    pub replan_on_low_confidence: bool,
+    /// Locked-down mode: no telemetry, debug logs or persisted caches/queues.
+    /// Only user-initiated exports and settings saves touch the disk.
+    #[serde(default)]
+    pub safe_mode: bool,
+    /// User-defined per-run overlays; built-ins live in `pipeline_presets`.
+    #[serde(default)]
+    pub pipeline_presets: Vec<crate::pipeline_presets::PipelinePreset>,
 }
 
 fn default_true() -> bool {
@@ -137,14 +376,62 @@ fn default_snap_sketch() -> Option<f64> {
     Some(0.5)
 }
 
+fn default_preview_max_triangles() -> u32 {
+    50_000
+}
+
+fn default_manufacturing_rules_budget_chars() -> usize {
+    4000
+}
+
+fn default_dimension_guidance_budget_chars() -> usize {
+    3000
+}
+
+fn default_failure_prevention_budget_chars() -> usize {
+    6000
+}
+
+fn default_organic_missing_notes_risk() -> u32 {
+    2
+}
+
+fn default_operation_soft_budget() -> u32 {
+    22
+}
+
+fn default_operation_hard_budget() -> u32 {
+    60
+}
+
+fn default_auto_approve_max_risk() -> u32 {
+    3
+}
+
 fn default_retrieval_token_budget() -> u32 {
     3500
 }
 
+fn default_retrieval_pack_boost() -> f32 {
+    1.5
+}
+
+fn default_debug_prompt_logging() -> bool {
+    cfg!(debug_assertions)
+}
+
 fn default_max_validation_attempts() -> u32 {
     4
 }
 
+fn default_max_design_replan_attempts() -> u32 {
+    3
+}
+
+fn default_max_planner_parse_attempts() -> u32 {
+    3
+}
+
 fn default_max_generation_runtime_seconds() -> u32 {
     600
 }
@@ -163,6 +450,81 @@ fn default_allowed_spdx_licenses() -> Vec<String> {
     ]
 }
 
+fn default_materials() -> BTreeMap<String, f64> {
+    [
+        ("pla", 1.24),
+        ("petg", 1.27),
+        ("abs", 1.04),
+        ("nylon", 1.14),
+        ("aluminum", 2.70),
+        ("steel", 7.85),
+        ("stainless steel", 8.00),
+        ("brass", 8.50),
+        ("copper", 8.96),
+        ("titanium", 4.43),
+        ("wood", 0.70),
+    ]
+    .into_iter()
+    .map(|(name, density)| (name.to_string(), density))
+    .collect()
+}
+
+fn default_material_density_g_cm3() -> f64 {
+    1.24
+}
+
+fn default_code_stop_sequences() -> Vec<String> {
+    vec!["</CODE>".to_string()]
+}
+
+/// Common API key and token formats: Anthropic, OpenAI-style `sk-`, Google,
+/// AWS access keys, GitHub tokens and bearer headers.
+fn default_audit_redaction_patterns() -> Vec<String> {
+    [
+        r"sk-ant-[A-Za-z0-9_\-]{16,}",
+        r"sk-[A-Za-z0-9_\-]{20,}",
+        r"AIza[0-9A-Za-z_\-]{35}",
+        r"AKIA[0-9A-Z]{16}",
+        r"gh[pousr]_[A-Za-z0-9]{36,}",
+        r"(?i)bearer\s+[A-Za-z0-9._\-]{16,}",
+    ]
+    .iter()
+    .map(|p| p.to_string())
+    .collect()
+}
+
+fn default_audit_log_max_mb() -> u32 {
+    20
+}
+
+fn default_artifact_max_age_days() -> u32 {
+    30
+}
+
+fn default_artifact_keep_heavy_runs() -> usize {
+    20
+}
+
+fn default_assembly_envelope_margin_pct() -> f64 {
+    25.0
+}
+
+fn default_export_verify_bbox_tolerance_mm() -> f64 {
+    0.1
+}
+
+fn default_export_verify_volume_tolerance_pct() -> f64 {
+    1.0
+}
+
+fn default_max_part_candidates() -> usize {
+    3
+}
+
+fn default_part_candidate_store_max_mb() -> u32 {
+    64
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -184,27 +546,90 @@ impl Default for AppConfig {
             snap_sketch: Some(0.5),
             enable_consensus: false,
             auto_approve_plan: false,
+            auto_approve_max_risk: default_auto_approve_max_risk(),
             retrieval_enabled: true,
             retrieval_token_budget: default_retrieval_token_budget(),
+            retrieval_min_score: 0.0,
+            prioritized_retrieval_packs: Vec::new(),
+            retrieval_pack_boost: default_retrieval_pack_boost(),
+            pinned_retrieval_ids: Vec::new(),
+            retrieval_embeddings: RetrievalEmbeddingsMode::default(),
+            preview_max_triangles: default_preview_max_triangles(),
+            custom_rules_dir: None,
+            manufacturing_rules_budget_chars: default_manufacturing_rules_budget_chars(),
+            dimension_guidance_budget_chars: default_dimension_guidance_budget_chars(),
+            failure_prevention_budget_chars: default_failure_prevention_budget_chars(),
             telemetry_enabled: true,
             max_validation_attempts: default_max_validation_attempts(),
+            max_design_replan_attempts: default_max_design_replan_attempts(),
+            max_planner_parse_attempts: default_max_planner_parse_attempts(),
             generation_reliability_profile: GenerationReliabilityProfile::default(),
             preview_on_partial_failure: true,
             max_generation_runtime_seconds: default_max_generation_runtime_seconds(),
             semantic_contract_strict: true,
             reviewer_mode: ReviewerMode::default(),
+            reviewer_focus: Vec::new(),
+            always_review: true,
             quality_gates_strict: true,
             allow_euler_override: true,
             semantic_bbox_mode: SemanticBboxMode::default(),
+            assembly_envelope_check: false,
+            assembly_envelope_margin_pct: default_assembly_envelope_margin_pct(),
+            organic_missing_notes_risk: default_organic_missing_notes_risk(),
+            operation_soft_budget: default_operation_soft_budget(),
+            operation_hard_budget: default_operation_hard_budget(),
+            enable_thread_helper: true,
+            explain_retries: false,
             mechanisms_enabled: true,
             mechanism_import_enabled: false,
             mechanism_cache_max_mb: default_mechanism_cache_max_mb(),
             allowed_spdx_licenses: default_allowed_spdx_licenses(),
+            escalation_model: None,
+            refine_assembly_positions: true,
+            assembly_part_gap_mm: 0.0,
+            assembly_rest_on_plate: false,
+            polish_assembly_instructions: false,
+            materials: default_materials(),
+            default_material_density_g_cm3: default_material_density_g_cm3(),
+            decomposition_bias: DecompositionBias::default(),
+            max_plan_parts: None,
+            planner_failure_policy: PlannerFailurePolicy::default(),
+            provider_rpm_limit: None,
+            cache_provider_responses: false,
+            code_stop_sequences: default_code_stop_sequences(),
+            audit_provider_calls: false,
+            log_full_content: false,
+            audit_redaction_patterns: default_audit_redaction_patterns(),
+            audit_log_max_mb: default_audit_log_max_mb(),
+            artifact_max_age_days: default_artifact_max_age_days(),
+            artifact_keep_heavy_runs: default_artifact_keep_heavy_runs(),
+            verify_step_exports: false,
+            export_verify_bbox_tolerance_mm: default_export_verify_bbox_tolerance_mm(),
+            export_verify_volume_tolerance_pct: default_export_verify_volume_tolerance_pct(),
+            pause_before_assembly: false,
+            manufacturing_process: None,
+            channel_disconnect_policy: ChannelDisconnectPolicy::default(),
+            merge_duplicate_runs: true,
+            custom_system_prompt_suffix: None,
+            printer_profiles: crate::agent::print_estimate::default_printer_profiles(),
+            active_printer_profile: None,
+            max_part_candidates: default_max_part_candidates(),
+            part_candidate_store_max_mb: default_part_candidate_store_max_mb(),
+            debug_prompt_logging: default_debug_prompt_logging(),
+            replan_on_low_confidence: false,
+            safe_mode: false,
+            pipeline_presets: Vec::new(),
         }
     }
 }
 
 impl AppConfig {
+    /// Whether background writes to the app data directory (traces, logs,
+    /// caches, queue persistence) are allowed.
+    pub fn allows_app_data_writes(&self) -> bool {
+        !self.safe_mode
+    }
+
     /// Get the path to the config file in app data dir
     pub fn config_path() -> Result<PathBuf, AppError> {
         let data_dir = dirs::config_dir()
@@ -15,6 +15,9 @@ pub enum AppError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
+    #[error("Event channel disconnected during run {0}")]
+    ChannelDisconnected(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
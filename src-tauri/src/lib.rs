@@ -1,14 +1,18 @@
 mod agent;
 mod ai;
+mod artifacts;
 mod commands;
 mod config;
 mod error;
 mod mechanisms;
+mod pipeline_presets;
 mod python;
 mod state;
 
 use state::AppState;
 
+pub use commands::ipc_schema::{ipc_schema_json, IPC_SCHEMA_PATH};
+
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
@@ -18,12 +22,22 @@ fn greet(name: &str) -> String {
 pub fn run() {
     // Load persisted config (or use defaults)
     let loaded_config = config::AppConfig::load().unwrap_or_default();
+    agent::anti_pattern_mining::init_active();
+    agent::custom_rules::set_dir(loaded_config.custom_rules_dir.as_deref());
+    artifacts::set_app_data_writes(loaded_config.allows_app_data_writes());
     let app_state = AppState {
         config: std::sync::Mutex::new(loaded_config),
         python_path: std::sync::Mutex::new(None),
         venv_path: std::sync::Mutex::new(None),
         session_memory: std::sync::Mutex::new(agent::memory::SessionMemory::new()),
         build123d_version: std::sync::Mutex::new(None),
+        generation_queue: std::sync::Mutex::new(agent::queue::GenerationQueue::load()),
+        part_materials: std::sync::Mutex::new(std::collections::HashMap::new()),
+        part_colors: std::sync::Mutex::new(std::collections::HashMap::new()),
+        run_store: std::sync::Mutex::new(agent::run_state::RunStore::default()),
+        run_events: std::sync::Mutex::new(commands::run_events::RunEventStore::default()),
+        loaded_project: std::sync::Mutex::new(None),
+        view_bookmarks: std::sync::Mutex::new(Vec::new()),
     };
 
     tauri::Builder::default()
@@ -38,34 +52,101 @@ pub fn run() {
             commands::chat::auto_retry,
             commands::chat::clear_session_memory,
             commands::cad::execute_code,
+            commands::cad::extract_assumptions,
             commands::cad::check_python,
             commands::cad::setup_python,
+            commands::cad::get_python_env_manifest,
+            commands::cad::pin_python_env,
+            commands::cad::restore_python_env,
             commands::cad::import_cad_file,
             commands::settings::get_provider_registry,
+            commands::settings::list_models,
+            commands::settings::list_agent_rule_sources,
+            commands::settings::preview_prompt_context,
+            commands::settings::suggest_profile,
             commands::settings::get_settings,
+            commands::settings::get_model_pricing,
+            commands::settings::set_model_pricing,
+            commands::audit::export_audit_log,
+            commands::audit::purge_audit_log,
+            commands::ipc_schema::get_event_schema_version,
+            commands::run_events::get_run_events,
+            commands::run_events::get_validation_history,
+            commands::run_compare::compare_runs,
             commands::settings::update_settings,
+            commands::settings::list_pipeline_presets,
+            commands::settings::save_pipeline_preset,
+            commands::settings::delete_pipeline_preset,
+            commands::settings::list_printer_profiles,
+            commands::settings::save_printer_profile,
+            commands::settings::delete_printer_profile,
             commands::project::save_project,
             commands::project::load_project,
+            commands::project::get_project_summary,
+            commands::views::get_standard_views,
+            commands::views::save_view_bookmark,
+            commands::views::list_view_bookmarks,
+            commands::geometry_query::query_geometry,
+            commands::storage::get_storage_usage,
+            commands::storage::clean_storage,
+            commands::project::import_code_file,
+            commands::project::parametrize_code,
             commands::project::export_stl,
             commands::project::export_step,
+            commands::project::export_parts_step,
+            commands::project::verify_export,
+            commands::project::export_objects_step,
+            commands::project::export_transcript,
+            commands::project::export_assembly_instructions,
             commands::parallel::generate_parallel,
+            commands::parallel::generate_parallel_result,
+            commands::parallel::generate_batch,
             commands::parallel::generate_design_plan,
+            commands::parallel::parse_design_plan,
+            commands::parallel::diff_plans,
             commands::parallel::generate_from_plan,
             commands::parallel::retry_skipped_steps,
             commands::parallel::retry_part,
+            commands::parallel::approve_assembly,
+            commands::parallel::get_part_candidates,
+            commands::parallel::use_part_candidate,
+            commands::parallel::resume_generation,
+            commands::queue::enqueue_generation,
+            commands::queue::get_queue_status,
+            commands::queue::cancel_queue_entry,
+            commands::queue::reorder_queue,
+            commands::queue::run_generation_queue,
             commands::drawing::generate_drawing_view,
             commands::drawing::export_drawing_pdf,
             commands::drawing::export_drawing_dxf,
             commands::manufacturing::export_3mf,
             commands::manufacturing::mesh_check,
             commands::manufacturing::orient_for_print,
+            commands::manufacturing::estimate_print_job,
             commands::manufacturing::sheet_metal_unfold,
+            commands::manufacturing::set_part_material,
+            commands::manufacturing::set_part_color,
+            commands::anti_patterns::mine_anti_patterns,
+            commands::anti_patterns::get_pending_anti_patterns,
+            commands::anti_patterns::approve_anti_pattern,
+            commands::repair_examples::list_repair_examples,
+            commands::repair_examples::delete_repair_example,
             commands::mechanisms::list_mechanisms,
             commands::mechanisms::get_mechanism,
             commands::mechanisms::search_mechanisms,
             commands::mechanisms::install_mechanism_pack,
             commands::mechanisms::remove_mechanism_pack,
+            commands::mechanisms::instantiate_mechanism,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                if artifacts::app_data_writes_allowed() {
+                    if let Err(e) = agent::embedding_cache::flush() {
+                        eprintln!("Failed to persist embedding cache: {}", e);
+                    }
+                }
+            }
+        });
 }
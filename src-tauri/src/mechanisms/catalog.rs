@@ -104,7 +104,7 @@ fn resolve_entries(
 
     for entry in &manifest.mechanisms {
         let record = match entry {
-            ManifestMechanismEntry::Inline(record) => record.clone(),
+            ManifestMechanismEntry::Inline(record) => (**record).clone(),
             ManifestMechanismEntry::FileRef { file, .. } => load_local_entry(dir, file)?,
         };
         out.push(record);
@@ -174,6 +174,8 @@ pub fn get_catalog(config: &AppConfig) -> Result<MechanismCatalog, AppError> {
                 source_url: record.source_url,
                 preview_url: record.preview_url,
                 parameters: record.parameters,
+                code_template: record.code_template,
+                code: record.code,
             });
         }
 
@@ -2,3 +2,4 @@ pub mod catalog;
 pub mod importer;
 pub mod license;
 pub mod schema;
+pub mod template;
@@ -9,6 +9,12 @@ pub struct MechanismParameter {
     pub description: String,
     #[serde(default)]
     pub unit: Option<String>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub param_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,12 +35,19 @@ pub struct MechanismRecord {
     pub preview_url: Option<String>,
     #[serde(default)]
     pub parameters: Vec<MechanismParameter>,
+    /// Build123d code with `{{param}}` placeholders, used by `instantiate_mechanism`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_template: Option<String>,
+    /// Verified reference code (helper functions and an example `result`),
+    /// injected into the prompt of a part that matches this mechanism.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ManifestMechanismEntry {
-    Inline(MechanismRecord),
+    Inline(Box<MechanismRecord>),
     FileRef {
         file: String,
         #[serde(default)]
@@ -71,6 +84,8 @@ pub struct CatalogMechanism {
     pub source_url: Option<String>,
     pub preview_url: Option<String>,
     pub parameters: Vec<MechanismParameter>,
+    pub code_template: Option<String>,
+    pub code: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -97,4 +112,14 @@ pub struct MechanismImportReport {
     pub package_name: String,
     pub installed_count: usize,
     pub source_url: String,
+    /// Instruction-like text the user confirmed when installing.
+    pub flagged_content: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MechanismInstance {
+    pub mechanism_id: String,
+    pub code: String,
+    pub stl_base64: String,
+    pub parameters: std::collections::BTreeMap<String, String>,
 }
@@ -0,0 +1,191 @@
+use std::collections::{BTreeMap, HashMap};
+
+use regex::Regex;
+
+use crate::error::AppError;
+
+use super::schema::MechanismParameter;
+
+fn format_number(value: f64) -> String {
+    let mut s = format!("{:.6}", value);
+    while s.contains('.') && s.ends_with('0') {
+        s.pop();
+    }
+    if s.ends_with('.') {
+        s.pop();
+    }
+    if s == "-0" {
+        s = "0".to_string();
+    }
+    s
+}
+
+/// Check user-supplied values against the mechanism's parameter schema and
+/// fill in defaults for anything not supplied.
+///
+/// Returns the rendered value for every schema parameter, keyed by name.
+pub fn resolve_parameters(
+    mechanism_id: &str,
+    schema: &[MechanismParameter],
+    params: &HashMap<String, f64>,
+) -> Result<BTreeMap<String, String>, AppError> {
+    let mut problems = Vec::new();
+
+    let mut unknown: Vec<&String> = params
+        .keys()
+        .filter(|k| !schema.iter().any(|p| &p.name == *k))
+        .collect();
+    unknown.sort();
+    for name in unknown {
+        problems.push(format!("unknown parameter '{}'", name));
+    }
+
+    let mut resolved = BTreeMap::new();
+    for param in schema {
+        let param_type = param.param_type.as_deref().unwrap_or("number");
+        let Some(&value) = params.get(&param.name) else {
+            resolved.insert(param.name.clone(), param.default_value.trim().to_string());
+            continue;
+        };
+
+        if matches!(param_type, "bool" | "string") {
+            problems.push(format!(
+                "parameter '{}' is a {} and cannot be set numerically",
+                param.name, param_type
+            ));
+            continue;
+        }
+        if !value.is_finite() {
+            problems.push(format!("parameter '{}' must be a finite number", param.name));
+            continue;
+        }
+        if matches!(param_type, "integer" | "count") && value.fract() != 0.0 {
+            problems.push(format!(
+                "parameter '{}' must be a whole number, got {}",
+                param.name, value
+            ));
+        }
+        if let Some(min) = param.min {
+            if value < min {
+                problems.push(format!(
+                    "parameter '{}' = {} is below min {}",
+                    param.name, value, min
+                ));
+            }
+        }
+        if let Some(max) = param.max {
+            if value > max {
+                problems.push(format!(
+                    "parameter '{}' = {} is above max {}",
+                    param.name, value, max
+                ));
+            }
+        }
+        resolved.insert(param.name.clone(), format_number(value));
+    }
+
+    if problems.is_empty() {
+        Ok(resolved)
+    } else {
+        Err(AppError::ConfigError(format!(
+            "Invalid parameters for mechanism '{}': {}",
+            mechanism_id,
+            problems.join("; ")
+        )))
+    }
+}
+
+/// Substitute `{{name}}` placeholders in a code template.
+///
+/// Fails if the template references a parameter that has no value.
+pub fn render_template(
+    template: &str,
+    values: &BTreeMap<String, String>,
+) -> Result<String, AppError> {
+    let re = Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}")
+        .expect("valid template placeholder regex");
+
+    let mut missing: Vec<String> = re
+        .captures_iter(template)
+        .map(|c| c[1].to_string())
+        .filter(|name| !values.contains_key(name))
+        .collect();
+    missing.sort();
+    missing.dedup();
+    if !missing.is_empty() {
+        return Err(AppError::ConfigError(format!(
+            "Mechanism template references undefined parameter(s): {}",
+            missing.join(", ")
+        )));
+    }
+
+    Ok(re
+        .replace_all(template, |c: &regex::Captures<'_>| values[&c[1]].clone())
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(name: &str, default: &str, min: Option<f64>, max: Option<f64>) -> MechanismParameter {
+        MechanismParameter {
+            name: name.to_string(),
+            default_value: default.to_string(),
+            description: String::new(),
+            unit: Some("mm".to_string()),
+            param_type: None,
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn test_render_template_substitutes_values() {
+        let schema = vec![
+            param("arm_len", "12", Some(4.0), Some(40.0)),
+            param("arm_thk", "1.6", None, None),
+        ];
+        let mut params = HashMap::new();
+        params.insert("arm_len".to_string(), 20.0);
+        let values = resolve_parameters("snap_fit", &schema, &params).unwrap();
+
+        let code = render_template(
+            "from build123d import *\nresult = Box({{arm_len}}, {{ arm_thk }}, 5)",
+            &values,
+        )
+        .unwrap();
+        assert_eq!(code, "from build123d import *\nresult = Box(20, 1.6, 5)");
+    }
+
+    #[test]
+    fn test_render_template_rejects_unknown_placeholder() {
+        let values = BTreeMap::new();
+        let err = render_template("result = Box({{width}}, 1, 1)", &values)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("width"));
+    }
+
+    #[test]
+    fn test_resolve_parameters_rejects_out_of_range_and_unknown() {
+        let schema = vec![param("arm_len", "12", Some(4.0), Some(40.0))];
+        let mut params = HashMap::new();
+        params.insert("arm_len".to_string(), 55.0);
+        params.insert("depth".to_string(), 3.0);
+        let err = resolve_parameters("snap_fit", &schema, &params)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown parameter 'depth'"));
+        assert!(err.contains("above max 40"));
+    }
+
+    #[test]
+    fn test_resolve_parameters_requires_whole_counts() {
+        let mut lugs = param("lug_count", "3", Some(2.0), None);
+        lugs.param_type = Some("count".to_string());
+        let mut params = HashMap::new();
+        params.insert("lug_count".to_string(), 2.5);
+        assert!(resolve_parameters("bayonet", &[lugs], &params).is_err());
+    }
+}
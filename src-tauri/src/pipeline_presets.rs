@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::agent::telemetry;
+use crate::config::AppConfig;
+use crate::error::AppError;
+
+/// Config keys a preset overlay may not touch.
+const RESERVED_KEYS: &[&str] = &["pipeline_presets"];
+
+/// Keys left out of the effective-config fingerprint.
+const FINGERPRINT_EXCLUDED_KEYS: &[&str] = &["api_key", "pipeline_presets"];
+
+/// A named partial `AppConfig` applied on top of the saved config for one run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PipelinePreset {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// `AppConfig` fields to override, keyed by their serialized name.
+    pub overlay: Map<String, Value>,
+    /// Shipped with the app; cannot be overwritten or deleted.
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+fn builtin(id: &str, name: &str, description: &str, overlay: Value) -> PipelinePreset {
+    PipelinePreset {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+        overlay: overlay.as_object().cloned().unwrap_or_default(),
+        builtin: true,
+    }
+}
+
+pub fn builtin_presets() -> Vec<PipelinePreset> {
+    vec![
+        builtin(
+            "fast_draft",
+            "Fast draft",
+            "Quick prototypes: no review or consensus, relaxed gates, fewer retries.",
+            json!({
+                "generation_reliability_profile": "balanced",
+                "enable_code_review": false,
+                "enable_consensus": false,
+                "quality_gates_strict": false,
+                "semantic_contract_strict": false,
+                "max_validation_attempts": 2,
+                "max_generation_runtime_seconds": 300
+            }),
+        ),
+        builtin(
+            "high_assurance",
+            "High assurance",
+            "Deliverables: reliability first, review and consensus on, strict gates, more retries.",
+            json!({
+                "generation_reliability_profile": "reliability_first",
+                "enable_code_review": true,
+                "enable_consensus": true,
+                "quality_gates_strict": true,
+                "semantic_contract_strict": true,
+                "max_validation_attempts": 6,
+                "max_generation_runtime_seconds": 1200
+            }),
+        ),
+    ]
+}
+
+/// Built-in presets followed by the user's own.
+pub fn list(config: &AppConfig) -> Vec<PipelinePreset> {
+    let mut presets = builtin_presets();
+    presets.extend(config.pipeline_presets.iter().cloned());
+    presets
+}
+
+pub fn find(config: &AppConfig, id: &str) -> Result<PipelinePreset, AppError> {
+    list(config)
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| AppError::ConfigError(format!("Pipeline preset '{}' not found", id)))
+}
+
+/// Apply a partial config on top of `base`. Unknown or reserved keys and
+/// values of the wrong type are rejected.
+pub fn apply_overlay(
+    base: &AppConfig,
+    overlay: &Map<String, Value>,
+) -> Result<AppConfig, AppError> {
+    let mut merged = serde_json::to_value(base)?;
+    let fields = merged
+        .as_object_mut()
+        .ok_or_else(|| AppError::ConfigError("Config did not serialize to an object".into()))?;
+    for (key, value) in overlay {
+        if RESERVED_KEYS.contains(&key.as_str()) {
+            return Err(AppError::ConfigError(format!(
+                "Setting '{}' cannot be part of a preset",
+                key
+            )));
+        }
+        if !fields.contains_key(key) {
+            return Err(AppError::ConfigError(format!("Unknown setting '{}'", key)));
+        }
+        fields.insert(key.clone(), value.clone());
+    }
+    serde_json::from_value(merged)
+        .map_err(|e| AppError::ConfigError(format!("Invalid preset setting: {}", e)))
+}
+
+/// Effective config for one run: the saved config, overlaid with `preset` if given.
+pub fn resolve_run_config(config: &AppConfig, preset: Option<&str>) -> Result<AppConfig, AppError> {
+    match preset.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(id) => apply_overlay(config, &find(config, id)?.overlay),
+        None => Ok(config.clone()),
+    }
+}
+
+/// Validate and insert or replace a user preset.
+pub fn save(config: &mut AppConfig, mut preset: PipelinePreset) -> Result<(), AppError> {
+    preset.id = preset.id.trim().to_string();
+    if preset.id.is_empty() {
+        return Err(AppError::ConfigError("Preset id cannot be empty".into()));
+    }
+    if builtin_presets().iter().any(|b| b.id == preset.id) {
+        return Err(AppError::ConfigError(format!(
+            "'{}' is a built-in preset; save a copy under a new id",
+            preset.id
+        )));
+    }
+    apply_overlay(config, &preset.overlay)?;
+    preset.builtin = false;
+    match config
+        .pipeline_presets
+        .iter_mut()
+        .find(|p| p.id == preset.id)
+    {
+        Some(existing) => *existing = preset,
+        None => config.pipeline_presets.push(preset),
+    }
+    Ok(())
+}
+
+pub fn delete(config: &mut AppConfig, id: &str) -> Result<(), AppError> {
+    if builtin_presets().iter().any(|b| b.id == id) {
+        return Err(AppError::ConfigError(format!(
+            "Built-in preset '{}' cannot be deleted",
+            id
+        )));
+    }
+    let before = config.pipeline_presets.len();
+    config.pipeline_presets.retain(|p| p.id != id);
+    if config.pipeline_presets.len() == before {
+        return Err(AppError::ConfigError(format!(
+            "Pipeline preset '{}' not found",
+            id
+        )));
+    }
+    Ok(())
+}
+
+/// The settings that shape a run, keyed by their serialized name.
+pub fn fingerprint_settings(config: &AppConfig) -> Map<String, Value> {
+    let mut fields = match serde_json::to_value(config) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    };
+    for key in FINGERPRINT_EXCLUDED_KEYS {
+        fields.remove(*key);
+    }
+    fields
+}
+
+/// Stable hash of the settings that shape a run, for comparing traces.
+pub fn config_fingerprint(config: &AppConfig) -> String {
+    settings_fingerprint(&fingerprint_settings(config))
+}
+
+/// `config_fingerprint` of settings captured with `fingerprint_settings`.
+pub fn settings_fingerprint(settings: &Map<String, Value>) -> String {
+    telemetry::hash_request(&Value::Object(settings.clone()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_preset(id: &str, overlay: Value) -> PipelinePreset {
+        PipelinePreset {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            overlay: overlay.as_object().cloned().unwrap(),
+            builtin: false,
+        }
+    }
+
+    #[test]
+    fn test_builtin_overlays_are_valid() {
+        let base = AppConfig::default();
+        for preset in builtin_presets() {
+            apply_overlay(&base, &preset.overlay)
+                .unwrap_or_else(|e| panic!("{}: {}", preset.id, e));
+        }
+        let fast = resolve_run_config(&base, Some("fast_draft")).unwrap();
+        assert!(!fast.enable_code_review);
+        assert_eq!(fast.max_validation_attempts, 2);
+        assert_eq!(fast.model, base.model);
+    }
+
+    #[test]
+    fn test_overlay_rejects_unknown_reserved_and_mistyped_keys() {
+        let base = AppConfig::default();
+        let unknown = user_preset("a", json!({"turbo_mode": true}));
+        assert!(apply_overlay(&base, &unknown.overlay).is_err());
+        let reserved = user_preset("b", json!({"pipeline_presets": []}));
+        assert!(apply_overlay(&base, &reserved.overlay).is_err());
+        let mistyped = user_preset("c", json!({"max_validation_attempts": "many"}));
+        assert!(apply_overlay(&base, &mistyped.overlay).is_err());
+    }
+
+    #[test]
+    fn test_run_config_does_not_mutate_saved_config() {
+        let mut config = AppConfig::default();
+        save(
+            &mut config,
+            user_preset(
+                "print_tonight",
+                json!({"model": "fast-model", "enable_consensus": false}),
+            ),
+        )
+        .unwrap();
+        let saved = config.clone();
+
+        let run = resolve_run_config(&config, Some("print_tonight")).unwrap();
+        assert_eq!(run.model, "fast-model");
+        assert_eq!(config.model, saved.model);
+        assert_ne!(config_fingerprint(&run), config_fingerprint(&config));
+        assert!(resolve_run_config(&config, Some("missing")).is_err());
+        assert_eq!(
+            config_fingerprint(&resolve_run_config(&config, None).unwrap()),
+            config_fingerprint(&config)
+        );
+    }
+
+    #[test]
+    fn test_builtins_cannot_be_replaced_or_deleted_but_can_be_cloned() {
+        let mut config = AppConfig::default();
+        let mut copy = find(&config, "high_assurance").unwrap();
+        assert!(save(&mut config, copy.clone()).is_err());
+        assert!(delete(&mut config, "high_assurance").is_err());
+
+        copy.id = "high_assurance_copy".to_string();
+        save(&mut config, copy).unwrap();
+        let listed = list(&config);
+        assert_eq!(listed.len(), 3);
+        assert!(!listed[2].builtin);
+
+        delete(&mut config, "high_assurance_copy").unwrap();
+        assert!(delete(&mut config, "high_assurance_copy").is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_api_key() {
+        let mut config = AppConfig::default();
+        let before = config_fingerprint(&config);
+        config.api_key = Some("sk-secret".to_string());
+        assert_eq!(config_fingerprint(&config), before);
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use super::venv;
+use crate::error::AppError;
+
+const LOCK_FORMAT_VERSION: u32 = 1;
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    /// Exact version, or the `@ <url>` spec for direct-reference installs.
+    pub version: String,
+}
+
+/// Known-good `pip freeze` of the generation venv.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvLock {
+    pub version: u32,
+    pub created_at_ms: u64,
+    /// `os-arch` the wheels were installed for; restores are refused elsewhere.
+    pub platform: String,
+    pub python_version: Option<String>,
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageChange {
+    pub name: String,
+    pub locked: String,
+    pub installed: String,
+}
+
+/// Difference between the live venv and the lock.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvDrift {
+    pub snapshot_created_at_ms: u64,
+    pub added: Vec<LockedPackage>,
+    pub removed: Vec<LockedPackage>,
+    pub changed: Vec<PackageChange>,
+    pub summary: String,
+}
+
+impl EnvDrift {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub fn platform_tag() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Stored next to the venv in the app data directory.
+pub fn lock_path() -> Result<PathBuf, AppError> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| AppError::ConfigError("Cannot find app data directory".into()))?;
+    Ok(data_dir.join("cadai-studio").join("python-env.lock.json"))
+}
+
+pub fn load_lock() -> Result<Option<EnvLock>, AppError> {
+    let path = lock_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(&path)?;
+    Ok(Some(serde_json::from_str(&contents)?))
+}
+
+pub fn save_lock(lock: &EnvLock) -> Result<(), AppError> {
+    let path = lock_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(lock)?)?;
+    Ok(())
+}
+
+/// pip treats `Foo_Bar` and `foo-bar` as the same distribution.
+fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase().replace(['_', '.'], "-")
+}
+
+/// Parse `pip freeze` output. Editable installs and options are skipped.
+pub fn parse_freeze(output: &str) -> Vec<LockedPackage> {
+    let mut packages: Vec<LockedPackage> = output
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('-'))
+        .filter_map(|line| {
+            if let Some((name, version)) = line.split_once("==") {
+                Some(LockedPackage {
+                    name: normalize_name(name),
+                    version: version.trim().to_string(),
+                })
+            } else {
+                line.split_once(" @ ").map(|(name, url)| LockedPackage {
+                    name: normalize_name(name),
+                    version: format!("@ {}", url.trim()),
+                })
+            }
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages.dedup_by(|a, b| a.name == b.name);
+    packages
+}
+
+/// Installed packages of the venv, as `pip freeze` reports them.
+pub fn freeze(venv_dir: &Path) -> Result<Vec<LockedPackage>, AppError> {
+    let output = Command::new(venv::get_venv_python(venv_dir))
+        .args(["-m", "pip", "freeze"])
+        .output()?;
+    if !output.status.success() {
+        return Err(AppError::CadError(format!(
+            "pip freeze failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(parse_freeze(&String::from_utf8_lossy(&output.stdout)))
+}
+
+pub fn snapshot(
+    venv_dir: &Path,
+    python_version: Option<String>,
+    created_at_ms: u64,
+) -> Result<EnvLock, AppError> {
+    Ok(EnvLock {
+        version: LOCK_FORMAT_VERSION,
+        created_at_ms,
+        platform: platform_tag(),
+        python_version,
+        packages: freeze(venv_dir)?,
+    })
+}
+
+/// "May 3, 2026" for a Unix timestamp in milliseconds (UTC).
+pub fn format_snapshot_date(ms: u64) -> String {
+    // Days since epoch → civil date (Howard Hinnant's algorithm).
+    let z = (ms / 86_400_000) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{} {}, {}", MONTHS[(month - 1) as usize], day, year)
+}
+
+/// Compare the live environment with the lock.
+pub fn compare(lock: &EnvLock, installed: &[LockedPackage]) -> EnvDrift {
+    let locked: BTreeMap<&str, &str> = lock
+        .packages
+        .iter()
+        .map(|p| (p.name.as_str(), p.version.as_str()))
+        .collect();
+    let live: BTreeMap<&str, &str> = installed
+        .iter()
+        .map(|p| (p.name.as_str(), p.version.as_str()))
+        .collect();
+
+    let added: Vec<LockedPackage> = installed
+        .iter()
+        .filter(|p| !locked.contains_key(p.name.as_str()))
+        .cloned()
+        .collect();
+    let removed: Vec<LockedPackage> = lock
+        .packages
+        .iter()
+        .filter(|p| !live.contains_key(p.name.as_str()))
+        .cloned()
+        .collect();
+    let changed: Vec<PackageChange> = lock
+        .packages
+        .iter()
+        .filter_map(|p| {
+            live.get(p.name.as_str())
+                .filter(|v| **v != p.version)
+                .map(|v| PackageChange {
+                    name: p.name.clone(),
+                    locked: p.version.clone(),
+                    installed: v.to_string(),
+                })
+        })
+        .collect();
+
+    let mut drift = EnvDrift {
+        snapshot_created_at_ms: lock.created_at_ms,
+        added,
+        removed,
+        changed,
+        summary: String::new(),
+    };
+    let date = format_snapshot_date(lock.created_at_ms);
+    drift.summary = if drift.is_empty() {
+        format!("Environment matches the known-good snapshot from {}", date)
+    } else {
+        let mut details: Vec<String> = drift
+            .changed
+            .iter()
+            .map(|c| format!("{} {} → {}", c.name, c.locked, c.installed))
+            .collect();
+        details.extend(
+            drift
+                .added
+                .iter()
+                .map(|p| format!("+{} {}", p.name, p.version)),
+        );
+        details.extend(drift.removed.iter().map(|p| format!("-{}", p.name)));
+        format!(
+            "Your environment drifted from the known-good snapshot from {}: {}",
+            date,
+            details.join(", ")
+        )
+    };
+    drift
+}
+
+/// Locks hold platform-specific wheels (OCP in particular), so restoring on
+/// another OS/architecture would install the wrong binaries.
+pub fn ensure_same_platform(lock: &EnvLock) -> Result<(), AppError> {
+    let current = platform_tag();
+    if lock.platform != current {
+        return Err(AppError::ConfigError(format!(
+            "The Python environment lock was created on {} and cannot be restored on {}. \
+             Run Python setup on this machine to create a new snapshot.",
+            lock.platform, current
+        )));
+    }
+    Ok(())
+}
+
+/// Requirements-file lines pinning exactly the locked versions.
+pub fn requirement_lines(lock: &EnvLock) -> Vec<String> {
+    lock.packages
+        .iter()
+        .map(|p| {
+            if p.version.starts_with('@') {
+                format!("{} {}", p.name, p.version)
+            } else {
+                format!("{}=={}", p.name, p.version)
+            }
+        })
+        .collect()
+}
+
+/// Reinstall exactly the locked versions into `venv_dir`. `--no-deps` keeps
+/// pip from resolving anything beyond the lock list.
+pub fn install_locked(venv_dir: &Path, lock: &EnvLock) -> Result<(), AppError> {
+    ensure_same_platform(lock)?;
+    let temp_dir = crate::artifacts::scratch_dir("env-lock")?;
+    let requirements = temp_dir.join("requirements.txt");
+    std::fs::write(&requirements, requirement_lines(lock).join("\n"))?;
+
+    let output = Command::new(venv::get_venv_python(venv_dir))
+        .args([
+            "-m",
+            "pip",
+            "install",
+            "--no-deps",
+            "--force-reinstall",
+            "-r",
+        ])
+        .arg(&requirements)
+        .output();
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    let output = output?;
+
+    if !output.status.success() {
+        return Err(AppError::CadError(format!(
+            "Failed to restore locked packages: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FREEZE: &str = "\
+# pip freeze
+build123d==0.8.0
+cadquery-ocp==7.7.2
+numpy==1.26.4
+-e git+https://example.com/local.git#egg=local
+Typing_Extensions==4.12.2
+localpkg @ file:///home/me/localpkg
+";
+
+    fn lock() -> EnvLock {
+        EnvLock {
+            version: LOCK_FORMAT_VERSION,
+            // 2026-05-03T10:00:00Z
+            created_at_ms: 1_777_802_400_000,
+            platform: platform_tag(),
+            python_version: Some("3.11.9".to_string()),
+            packages: parse_freeze(FREEZE),
+        }
+    }
+
+    #[test]
+    fn test_parse_freeze_normalizes_names_and_skips_editables() {
+        let packages = parse_freeze(FREEZE);
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "build123d",
+                "cadquery-ocp",
+                "localpkg",
+                "numpy",
+                "typing-extensions"
+            ]
+        );
+        let local = packages.iter().find(|p| p.name == "localpkg").unwrap();
+        assert_eq!(local.version, "@ file:///home/me/localpkg");
+        assert!(requirement_lines(&lock()).contains(&"cadquery-ocp==7.7.2".to_string()));
+        assert!(
+            requirement_lines(&lock()).contains(&"localpkg @ file:///home/me/localpkg".to_string())
+        );
+    }
+
+    #[test]
+    fn test_compare_reports_added_removed_and_changed() {
+        let lock = lock();
+        let live = parse_freeze(
+            "build123d==0.8.0\ncadquery-ocp==7.8.1\ntyping_extensions==4.12.2\nlocalpkg @ file:///home/me/localpkg\nscipy==1.14.0\n",
+        );
+        let drift = compare(&lock, &live);
+        assert!(!drift.is_empty());
+        assert_eq!(
+            drift.changed,
+            vec![PackageChange {
+                name: "cadquery-ocp".to_string(),
+                locked: "7.7.2".to_string(),
+                installed: "7.8.1".to_string(),
+            }]
+        );
+        assert_eq!(drift.added.len(), 1);
+        assert_eq!(drift.added[0].name, "scipy");
+        assert_eq!(drift.removed.len(), 1);
+        assert_eq!(drift.removed[0].name, "numpy");
+        assert!(drift
+            .summary
+            .starts_with("Your environment drifted from the known-good snapshot from May 3, 2026"));
+        assert!(drift.summary.contains("cadquery-ocp 7.7.2 → 7.8.1"));
+
+        let clean = compare(&lock, &lock.packages);
+        assert!(clean.is_empty());
+        assert!(clean.summary.contains("matches"));
+    }
+
+    #[test]
+    fn test_cross_platform_restore_is_refused() {
+        let mut foreign = lock();
+        assert!(ensure_same_platform(&foreign).is_ok());
+        foreign.platform = "plan9-sparc".to_string();
+        let err = ensure_same_platform(&foreign).unwrap_err().to_string();
+        assert!(err.contains("created on plan9-sparc"));
+        assert!(install_locked(Path::new("/nonexistent-venv"), &foreign).is_err());
+    }
+
+    #[test]
+    fn test_format_snapshot_date() {
+        assert_eq!(format_snapshot_date(0), "January 1, 1970");
+        assert_eq!(format_snapshot_date(951_782_400_000), "February 29, 2000");
+        assert_eq!(format_snapshot_date(1_777_802_400_000), "May 3, 2026");
+    }
+}
@@ -1,4 +1,6 @@
 pub mod detector;
+pub mod env_lock;
 pub mod installer;
 pub mod runner;
 pub mod venv;
+pub mod version_watch;
@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::time::{Duration, Instant};
 
-use uuid::Uuid;
+use serde::{Deserialize, Serialize};
 
 use super::venv;
 use crate::error::AppError;
@@ -17,12 +17,34 @@ pub struct ExecutionResult {
     pub stderr: String,
 }
 
+/// Geometry summary the runner reports for each exported object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectReport {
+    pub bounds_min: [f64; 3],
+    pub bounds_max: [f64; 3],
+    pub volume: f64,
+    pub solid_count: u64,
+}
+
+/// One named object written by the runner's `--all-solids` mode.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportedObject {
+    pub name: String,
+    /// Path of the exported STL/STEP file.
+    pub file: String,
+    pub report: ObjectReport,
+}
+
+/// Result of executing CAD code with every named object exported separately.
+pub struct ObjectsExecutionResult {
+    /// Objects with their STL data, in script order.
+    pub objects: Vec<(ExportedObject, Vec<u8>)>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
 fn create_execution_dir() -> Result<PathBuf, AppError> {
-    let dir = std::env::temp_dir()
-        .join("cadai-studio")
-        .join(Uuid::new_v4().to_string());
-    std::fs::create_dir_all(&dir)?;
-    Ok(dir)
+    crate::artifacts::scratch_dir("exec")
 }
 
 fn timeout_error(timeout_ms: u64) -> AppError {
@@ -39,6 +61,7 @@ fn map_runner_error(exit_code: i32, stderr: &str, export_error_label: &str) -> A
         3 => "Code must assign final geometry to 'result' variable.".to_string(),
         4 => format!("{export_error_label}:\n{}", stderr),
         5 => "Result contains multiple disconnected solids — a cut likely went through a wall and split the body. Reduce cut depth or increase wall thickness.".to_string(),
+        6..=8 => stderr.trim().to_string(),
         _ => format!("Python error (exit code {}):\n{}", exit_code, stderr),
     };
     AppError::CadError(error_msg)
@@ -49,6 +72,7 @@ fn run_runner_with_timeout(
     runner_script: &Path,
     input_file: &Path,
     output_file: &Path,
+    extra_args: &[&str],
     timeout_ms: u64,
     execution_dir: &Path,
 ) -> Result<(std::process::ExitStatus, String, String), AppError> {
@@ -63,6 +87,7 @@ fn run_runner_with_timeout(
             input_file.to_string_lossy().as_ref(),
             output_file.to_string_lossy().as_ref(),
         ])
+        .args(extra_args)
         .stdout(Stdio::from(stdout_file))
         .stderr(Stdio::from(stderr_file))
         .spawn()?;
@@ -127,6 +152,7 @@ pub fn execute_cad_with_timeout_ms(
             runner_script,
             &input_file,
             &output_file,
+            &[],
             timeout_ms,
             &temp_dir,
         )?;
@@ -153,6 +179,214 @@ pub fn execute_cad_with_timeout_ms(
     result
 }
 
+/// Runner arguments for `--all-solids` mode.
+fn all_solids_args(include: &[String], format: &str) -> Vec<String> {
+    let mut args = vec![
+        "--all-solids".to_string(),
+        "--format".to_string(),
+        format.to_string(),
+    ];
+    if !include.is_empty() {
+        args.push("--include".to_string());
+        args.push(include.join(","));
+    }
+    args
+}
+
+/// Execute Build123d code and export each top-level geometry variable (or
+/// only the `include` names) as `{name}.{format}` in `output_dir`.
+///
+/// Only variables assigned once at module level are picked up, so scripts
+/// with many intermediates export just their finished objects.
+pub fn export_cad_objects(
+    venv_dir: &Path,
+    runner_script: &Path,
+    code: &str,
+    include: &[String],
+    format: &str,
+    output_dir: &Path,
+    timeout_ms: u64,
+) -> Result<(Vec<ExportedObject>, String, String), AppError> {
+    let python = venv::get_venv_python(venv_dir);
+
+    if !python.exists() {
+        return Err(AppError::PythonNotFound);
+    }
+
+    let temp_dir = create_execution_dir()?;
+    let input_file = temp_dir.join("input.py");
+    let args = all_solids_args(include, format);
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = (|| -> Result<(Vec<ExportedObject>, String, String), AppError> {
+        std::fs::write(&input_file, code)?;
+
+        let (status, stdout, stderr) = run_runner_with_timeout(
+            &python,
+            runner_script,
+            &input_file,
+            output_dir,
+            &arg_refs,
+            timeout_ms,
+            &temp_dir,
+        )?;
+
+        if !status.success() {
+            let exit_code = status.code().unwrap_or(-1);
+            return Err(map_runner_error(exit_code, &stderr, "Export error"));
+        }
+
+        let manifest_path = output_dir.join("objects.json");
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .map_err(|_| AppError::CadError("Object manifest was not generated".into()))?;
+        let _ = std::fs::remove_file(&manifest_path);
+        let objects: Vec<ExportedObject> = serde_json::from_str(&manifest)?;
+        Ok((objects, stdout, stderr))
+    })();
+
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    result
+}
+
+/// Execute Build123d code and return STL data per named object, with a hard timeout.
+pub fn execute_cad_objects_with_timeout_ms(
+    venv_dir: &Path,
+    runner_script: &Path,
+    code: &str,
+    include: &[String],
+    timeout_ms: u64,
+) -> Result<ObjectsExecutionResult, AppError> {
+    let output_dir = create_execution_dir()?;
+    let result = (|| -> Result<ObjectsExecutionResult, AppError> {
+        let (exported, stdout, stderr) = export_cad_objects(
+            venv_dir,
+            runner_script,
+            code,
+            include,
+            "stl",
+            &output_dir,
+            timeout_ms,
+        )?;
+        let mut objects = Vec::with_capacity(exported.len());
+        for object in exported {
+            let stl_data = std::fs::read(&object.file)?;
+            objects.push((object, stl_data));
+        }
+        Ok(ObjectsExecutionResult {
+            objects,
+            stdout,
+            stderr,
+        })
+    })();
+
+    let _ = std::fs::remove_dir_all(&output_dir);
+    result
+}
+
+/// Execute Build123d code and answer `query_json` against the BREP of its
+/// `result` (runner `--query` mode), returning the runner's JSON answer.
+///
+/// The script is re-executed on every call; there is no resident runner to
+/// keep loaded shapes between queries. Exceeding `timeout_ms` is reported as
+/// a query timeout rather than a generic execution timeout.
+pub fn run_geometry_query(
+    venv_dir: &Path,
+    runner_script: &Path,
+    code: &str,
+    query_json: &str,
+    timeout_ms: u64,
+) -> Result<String, AppError> {
+    let python = venv::get_venv_python(venv_dir);
+
+    if !python.exists() {
+        return Err(AppError::PythonNotFound);
+    }
+
+    let temp_dir = create_execution_dir()?;
+    let input_file = temp_dir.join("input.py");
+    let query_file = temp_dir.join("query.json");
+    let output_file = temp_dir.join("answer.json");
+
+    let result = (|| -> Result<String, AppError> {
+        std::fs::write(&input_file, code)?;
+        std::fs::write(&query_file, query_json)?;
+        let query_arg = query_file.to_string_lossy().to_string();
+
+        let start = Instant::now();
+        let (status, _stdout, stderr) = run_runner_with_timeout(
+            &python,
+            runner_script,
+            &input_file,
+            &output_file,
+            &["--query", query_arg.as_str()],
+            timeout_ms,
+            &temp_dir,
+        )
+        .map_err(|e| {
+            if start.elapsed() >= Duration::from_millis(timeout_ms) {
+                AppError::CadError(format!(
+                    "Geometry query exceeded its {:.1} second budget; query a single part or simplify the model.",
+                    timeout_ms as f64 / 1000.0
+                ))
+            } else {
+                e
+            }
+        })?;
+
+        if !status.success() {
+            let exit_code = status.code().unwrap_or(-1);
+            return Err(map_runner_error(exit_code, &stderr, "Geometry query error"));
+        }
+
+        std::fs::read_to_string(&output_file)
+            .map_err(|_| AppError::CadError("Geometry query produced no answer".into()))
+    })();
+
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    result
+}
+
+/// Re-import an exported STEP file through the runner's `--measure-step` mode
+/// and return its measurement JSON (bounds, volume, solid and face counts).
+pub fn measure_step_file(
+    venv_dir: &Path,
+    runner_script: &Path,
+    step_path: &Path,
+    timeout_ms: u64,
+) -> Result<String, AppError> {
+    let python = venv::get_venv_python(venv_dir);
+
+    if !python.exists() {
+        return Err(AppError::PythonNotFound);
+    }
+
+    let temp_dir = create_execution_dir()?;
+    let output_file = temp_dir.join("measure.json");
+
+    let result = (|| -> Result<String, AppError> {
+        let (status, _stdout, stderr) = run_runner_with_timeout(
+            &python,
+            runner_script,
+            step_path,
+            &output_file,
+            &["--measure-step"],
+            timeout_ms,
+            &temp_dir,
+        )?;
+
+        if !status.success() {
+            let exit_code = status.code().unwrap_or(-1);
+            return Err(map_runner_error(exit_code, &stderr, "STEP re-import error"));
+        }
+
+        std::fs::read_to_string(&output_file)
+            .map_err(|_| AppError::CadError("STEP re-import produced no measurement".into()))
+    })();
+
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    result
+}
+
 /// Result of running a generic Python script
 pub struct ScriptResult {
     pub stdout: String,
@@ -302,6 +536,7 @@ pub fn execute_cad_to_file(
             runner_script,
             &input_file,
             output_file,
+            &[],
             DEFAULT_EXECUTION_TIMEOUT_MS,
             &temp_dir,
         )?;
@@ -323,3 +558,50 @@ pub fn execute_cad_to_file(
     let _ = std::fs::remove_dir_all(&temp_dir);
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_solids_args_pass_include_list() {
+        assert_eq!(
+            all_solids_args(&[], "stl"),
+            ["--all-solids", "--format", "stl"]
+        );
+        let include = vec!["housing".to_string(), "lid".to_string()];
+        assert_eq!(
+            all_solids_args(&include, "step"),
+            [
+                "--all-solids",
+                "--format",
+                "step",
+                "--include",
+                "housing,lid"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_manifest_parses_into_exported_objects() {
+        let manifest = r#"[{"name": "lid", "file": "/tmp/x/lid.stl", "report":
+            {"bounds_min": [0, 0, 0], "bounds_max": [10, 10, 2], "volume": 200.0, "solid_count": 1}}]"#;
+        let objects: Vec<ExportedObject> = serde_json::from_str(manifest).unwrap();
+        assert_eq!(objects[0].name, "lid");
+        assert_eq!(objects[0].report.bounds_max, [10.0, 10.0, 2.0]);
+        assert_eq!(objects[0].report.solid_count, 1);
+    }
+
+    #[test]
+    fn test_missing_named_object_error_is_runner_message() {
+        let err = map_runner_error(
+            6,
+            "Error: 'gasket' is not a geometry variable.\n",
+            "Export error",
+        );
+        assert_eq!(
+            err.to_string(),
+            "CAD error: Error: 'gasket' is not a geometry variable."
+        );
+    }
+}
@@ -54,3 +54,53 @@ pub fn create_venv(python_path: &Path, venv_dir: &Path) -> Result<(), AppError>
 
     Ok(())
 }
+
+/// Move a fully built venv at `staging` into place at `live`. The old venv is
+/// kept aside until the swap succeeds and put back if it fails. Callers run
+/// tools through `python -m`, so nothing depends on the venv's build path.
+pub fn replace_venv(staging: &Path, live: &Path) -> Result<(), AppError> {
+    let previous = live.with_extension("old");
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous)?;
+    }
+    if live.exists() {
+        std::fs::rename(live, &previous)?;
+    }
+    if let Err(e) = std::fs::rename(staging, live) {
+        if previous.exists() {
+            let _ = std::fs::rename(&previous, live);
+        }
+        return Err(e.into());
+    }
+    if previous.exists() {
+        let _ = std::fs::remove_dir_all(&previous);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_venv_swaps_and_keeps_live_on_failure() {
+        let root = std::env::temp_dir().join(format!("cadai-venv-{}", uuid::Uuid::new_v4()));
+        let live = root.join("venv");
+        let staging = root.join("venv.restore");
+        std::fs::create_dir_all(&live).unwrap();
+        std::fs::write(live.join("marker"), "old").unwrap();
+
+        // Nothing staged: the rename fails and the live venv is put back.
+        assert!(replace_venv(&staging, &live).is_err());
+        assert_eq!(std::fs::read_to_string(live.join("marker")).unwrap(), "old");
+
+        std::fs::create_dir_all(&staging).unwrap();
+        std::fs::write(staging.join("marker"), "new").unwrap();
+        replace_venv(&staging, &live).unwrap();
+        assert_eq!(std::fs::read_to_string(live.join("marker")).unwrap(), "new");
+        assert!(!staging.exists());
+        assert!(!live.with_extension("old").exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
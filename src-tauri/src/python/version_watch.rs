@@ -0,0 +1,164 @@
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Latest modification time of the venv root, `pyvenv.cfg` and its
+/// site-packages directories. `pip install`/`uninstall` touches
+/// site-packages, so an unchanged stamp means the installed packages did
+/// not change and re-running Python to ask for the version can be skipped.
+pub fn venv_stamp(venv_dir: &Path) -> Option<SystemTime> {
+    let mut candidates = vec![
+        venv_dir.to_path_buf(),
+        venv_dir.join("pyvenv.cfg"),
+        venv_dir.join("Lib").join("site-packages"),
+    ];
+    if let Ok(entries) = std::fs::read_dir(venv_dir.join("lib")) {
+        candidates.extend(entries.flatten().map(|e| e.path().join("site-packages")));
+    }
+    candidates
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+/// The installed Build123d version differs from the one the session used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionChange {
+    pub previous: Option<String>,
+    pub current: Option<String>,
+}
+
+impl VersionChange {
+    /// False for the first detection in a session (nothing to invalidate).
+    pub fn is_upgrade_or_downgrade(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "Build123d version changed {}→{}; refreshing guidance",
+            self.previous.as_deref().unwrap_or("unknown"),
+            self.current.as_deref().unwrap_or("unknown")
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VersionWatch {
+    stamp: Option<SystemTime>,
+    version: Option<String>,
+}
+
+static VERSION_WATCH: OnceLock<Mutex<VersionWatch>> = OnceLock::new();
+
+pub fn global_watch() -> &'static Mutex<VersionWatch> {
+    VERSION_WATCH.get_or_init(|| Mutex::new(VersionWatch::default()))
+}
+
+/// Version seen by the most recent check, for repair prompts.
+pub fn detected_version() -> Option<String> {
+    global_watch().lock().ok()?.version.clone()
+}
+
+impl VersionWatch {
+    /// Re-detect the version when the venv `stamp` moved since the last
+    /// check. `known` is the version the session currently uses (set by
+    /// `check_python`/`setup_python`); `detect` runs only when needed.
+    pub fn refresh(
+        &mut self,
+        stamp: Option<SystemTime>,
+        known: Option<&str>,
+        detect: impl FnOnce() -> Option<String>,
+    ) -> Option<VersionChange> {
+        if !self.needs_detect(stamp, known) {
+            return None;
+        }
+        self.publish(stamp, known, detect())
+    }
+
+    /// True when the venv `stamp` moved and the version must be re-detected.
+    /// Split from [`Self::publish`] so callers can run Python without holding
+    /// the watch lock.
+    pub fn needs_detect(&mut self, stamp: Option<SystemTime>, known: Option<&str>) -> bool {
+        if stamp.is_some() && stamp == self.stamp {
+            if self.version.is_none() {
+                self.version = known.map(str::to_string);
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Record the version detected for `stamp`.
+    pub fn publish(
+        &mut self,
+        stamp: Option<SystemTime>,
+        known: Option<&str>,
+        current: Option<String>,
+    ) -> Option<VersionChange> {
+        self.stamp = stamp;
+        let previous = known.map(str::to_string).or_else(|| self.version.clone());
+        self.version = current.clone();
+        if current.is_some() && current != previous {
+            Some(VersionChange { previous, current })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::prompts;
+    use std::time::Duration;
+
+    #[test]
+    fn test_version_bump_between_runs_refreshes_prompt() {
+        let t1 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let t2 = t1 + Duration::from_secs(60);
+        let mut watch = VersionWatch::default();
+        let mut session: Option<String> = Some("0.8.0".to_string());
+
+        // First run: stamp is new, detection confirms the session version.
+        let change = watch.refresh(Some(t1), session.as_deref(), || Some("0.8.0".into()));
+        assert_eq!(change, None);
+        let first = prompts::build_compact_system_prompt_for_preset(None, session.as_deref());
+        assert!(first.contains("Build123d 0.8.0"));
+
+        // Untouched venv: no re-detection.
+        let change = watch.refresh(Some(t1), session.as_deref(), || {
+            panic!("venv unchanged; should not re-detect")
+        });
+        assert_eq!(change, None);
+
+        // pip upgraded build123d between runs.
+        let change = watch
+            .refresh(Some(t2), session.as_deref(), || Some("0.9.1".into()))
+            .unwrap();
+        assert!(change.is_upgrade_or_downgrade());
+        assert_eq!(
+            change.describe(),
+            "Build123d version changed 0.8.0→0.9.1; refreshing guidance"
+        );
+        session = change.current.clone();
+
+        let second = prompts::build_compact_system_prompt_for_preset(None, session.as_deref());
+        assert!(second.contains("Build123d 0.9.1"));
+        assert!(!second.contains("0.8.0"));
+        let full = prompts::build_system_prompt_for_preset(None, session.as_deref());
+        assert!(full.contains("Build123d 0.9.1"));
+    }
+
+    #[test]
+    fn test_venv_stamp_tracks_site_packages() {
+        let venv = std::env::temp_dir().join(format!("cadai-venv-stamp-{}", uuid::Uuid::new_v4()));
+        let site = venv.join("lib").join("python3.11").join("site-packages");
+        std::fs::create_dir_all(&site).unwrap();
+        let before = venv_stamp(&venv).unwrap();
+        let site_mtime = std::fs::metadata(&site).unwrap().modified().unwrap();
+        assert!(before >= site_mtime);
+        assert_eq!(venv_stamp(&venv.join("missing")), None);
+        let _ = std::fs::remove_dir_all(&venv);
+    }
+}
@@ -1,7 +1,14 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
 use crate::agent::memory::SessionMemory;
+use crate::agent::part_colors::PartColor;
+use crate::agent::queue::GenerationQueue;
+use crate::agent::run_state::RunStore;
+use crate::agent::views::ViewBookmark;
+use crate::commands::run_events::RunEventStore;
+use crate::commands::project::ProjectSummary;
 use crate::config::AppConfig;
 
 #[allow(dead_code)]
@@ -11,6 +18,19 @@ pub struct AppState {
     pub venv_path: Mutex<Option<PathBuf>>,
     pub session_memory: Mutex<SessionMemory>,
     pub build123d_version: Mutex<Option<String>>,
+    pub generation_queue: Mutex<GenerationQueue>,
+    /// Material assigned to a part by name via `set_part_material`.
+    pub part_materials: Mutex<HashMap<String, String>>,
+    /// Color assigned to a part by name via `set_part_color`.
+    pub part_colors: Mutex<HashMap<String, PartColor>>,
+    /// Part candidates of recent multi-part runs, for `use_part_candidate`.
+    pub run_store: Mutex<RunStore>,
+    /// Recent events per run, for frontends catching up via `get_run_events`.
+    pub run_events: Mutex<RunEventStore>,
+    /// Generation context of the last project opened with `load_project`.
+    pub loaded_project: Mutex<Option<ProjectSummary>>,
+    /// Camera bookmarks of the open project, saved with it by `save_project`.
+    pub view_bookmarks: Mutex<Vec<ViewBookmark>>,
 }
 
 impl Default for AppState {
@@ -21,6 +41,13 @@ impl Default for AppState {
             venv_path: Mutex::new(None),
             session_memory: Mutex::new(SessionMemory::new()),
             build123d_version: Mutex::new(None),
+            generation_queue: Mutex::new(GenerationQueue::default()),
+            part_materials: Mutex::new(HashMap::new()),
+            part_colors: Mutex::new(HashMap::new()),
+            run_store: Mutex::new(RunStore::default()),
+            run_events: Mutex::new(RunEventStore::default()),
+            loaded_project: Mutex::new(None),
+            view_bookmarks: Mutex::new(Vec::new()),
         }
     }
 }
@@ -11,6 +11,8 @@ import {
   loadProject,
   exportStl,
   exportStep,
+  exportTranscript,
+  getRunEvents,
   export3mf,
   meshCheck,
   orientForPrint,
@@ -19,6 +21,8 @@ import {
   showOpenDialog,
   importCadFile,
   showImportCadDialog,
+  importCodeFile,
+  showImportCodeDialog,
 } from '$lib/services/tauri';
 import type { MeshCheckResult, OrientResult, ColorInfo } from '$lib/services/tauri';
 import { getHistoryStore } from '$lib/stores/history.svelte';
@@ -29,7 +33,7 @@ import { getComponentStore } from '$lib/stores/component.svelte';
 import { getMateStore } from '$lib/stores/mate.svelte';
 import { getDrawingStore } from '$lib/stores/drawing.svelte';
 import { clearDraft } from '$lib/services/autosave';
-import type { RustChatMessage, ChatMessage } from '$lib/types';
+import type { RustChatMessage, ChatMessage, MultiPartEvent } from '$lib/types';
 import type { SceneObject, CodeMode, CameraState, Sketch, DatumPlane, DatumAxis, DisplayMode, Component, SketchEntity, SketchConstraint, AssemblyMate } from '$lib/types/cad';
 import type { Drawing } from '$lib/types/drawing';
 import type { FeatureTreeSnapshot } from '$lib/stores/feature-tree.svelte';
@@ -87,6 +91,7 @@ export async function projectOpen(): Promise<string> {
   project.setName(file.name);
   project.setCode(file.code);
   project.setFilePath(path);
+  project.restoreGeneration(file.generation ?? null, file.code_history ?? []);
 
   // Convert RustChatMessages back into ChatMessages for the chat store
   chatStore.clear();
@@ -197,7 +202,15 @@ export async function projectSave(): Promise<string> {
     ? { objects: sceneData.objects, codeMode: sceneData.codeMode, camera, sketches: sketchData.sketches, featureTree: ftData, datumPlanes: datumData.datumPlanes, datumAxes: datumData.datumAxes, displayMode: viewportStore.displayMode, components: compData.components, componentNameCounter: compData.nameCounter, mates: mateData.mates, drawings: drawingData.drawings }
     : undefined;
 
-  await saveProject(project.name, project.code, rustMessages, path, scenePayload);
+  await saveProject(
+    project.name,
+    project.code,
+    rustMessages,
+    path,
+    scenePayload,
+    project.generation ?? undefined,
+    project.codeHistory,
+  );
   project.setFilePath(path);
   project.setModified(false);
   clearDraft().catch(() => {}); // Best-effort draft cleanup
@@ -370,6 +383,35 @@ export async function projectExportStep(): Promise<string> {
   return result || 'STEP exported successfully';
 }
 
+export async function projectExportTranscript(): Promise<string> {
+  const project = getProjectStore();
+  const generation = project.generation;
+  if (!generation) return 'No generation to export yet';
+
+  const path = await showSaveDialog('transcript.md', 'md');
+  if (!path) return '';
+
+  // Buffered events only outlive the run for a while; without them the
+  // transcript still carries the request, plan and final code.
+  let events: MultiPartEvent[] = [];
+  if (generation.run_id) {
+    try {
+      events = (await getRunEvents(generation.run_id, 0)).events;
+    } catch (err) {
+      console.warn('Run events unavailable for transcript:', err);
+    }
+  }
+
+  return await exportTranscript(
+    events,
+    project.code,
+    generation.plan_text,
+    path,
+    generation.request,
+    generation.run_id ?? undefined,
+  );
+}
+
 // ── Manufacturing Actions ──
 
 export async function projectExport3mf(): Promise<string> {
@@ -440,3 +482,26 @@ export async function projectImportStep(): Promise<string> {
 
   return 'Import completed but no geometry was returned';
 }
+
+// ── Python script import ──
+
+export async function projectImportCode(): Promise<string> {
+  const filePath = await showImportCodeDialog();
+  if (!filePath) return '';
+
+  const project = getProjectStore();
+  const result = await importCodeFile(filePath);
+  const fileName = filePath.split(/[/\\]/).pop() ?? 'script.py';
+
+  project.setCode(result.code);
+  project.addSnapshot(result.code, `Imported from ${fileName}`);
+  if (result.stl_base64) {
+    getViewportStore().setPendingStl(result.stl_base64);
+  }
+
+  if (result.execution_error) {
+    return `Imported ${fileName}, but it failed to run: ${result.execution_error}`;
+  }
+  const notes = result.warnings.length > 0 ? ` (${result.warnings.join('; ')})` : '';
+  return `Imported ${fileName} with ${result.parameters.length} parameters${notes}`;
+}
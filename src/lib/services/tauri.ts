@@ -3,20 +3,60 @@ import { save, open } from '@tauri-apps/plugin-dialog';
 import type {
   AppConfig,
   ExecuteResult,
+  CodeAssumption,
   PythonStatus,
+  EnvLock,
+  EnvDrift,
   StreamEvent,
   RustChatMessage,
   AutoRetryResult,
   ProjectFile,
+  ProjectGenerationReport,
+  CodeSnapshot,
+  ProjectSummary,
+  ImportedCode,
+  PartsStepExport,
+  CameraView,
+  StandardViews,
+  GeometryQuery,
+  GeometryQueryResponse,
+  ExportVerificationReport,
+  ParametrizedCode,
+  ViewBookmark,
+  StorageUsage,
+  CleanupReport,
   ProviderInfo,
+  AgentRuleSource,
+  RuleContext,
+  ProfileSuggestion,
+  StructuredPlan,
+  PlanDiff,
   MultiPartEvent,
+  MultiPartEventEnvelope,
+  RunEvents,
+  PartCandidate,
+  QueueEntryOptions,
+  QueueStatus,
+  QueueEvent,
+  ModelPricingSettings,
+  PricingOverrides,
+  ValidationChain,
+  RunComparison,
+  PrinterProfile,
+  PipelinePreset,
+  PrintEstimate,
+  RepairExample,
+  LearnedAntiPattern,
   TokenUsageData,
   SkippedStepInfo,
   DesignPlanResult,
+  GenerationResult,
+  BatchResult,
   PartSpec,
   MechanismListResponse,
   MechanismItem,
   MechanismImportReport,
+  MechanismInstance,
 } from '$lib/types';
 
 /**
@@ -46,27 +86,44 @@ export async function sendMessage(message: string): Promise<string> {
 /**
  * Send a chat message with streaming response via Tauri Channel API.
  * Streams delta events as they arrive, then returns the full response.
+ * With `onGenerationEvent`, the reply also arrives as generation events, and
+ * code in it is reviewed and validated like single-part generation.
+ * Status lines (e.g. which slash-command overrides apply) go to `onStatus`
+ * and never become part of the reply text.
  */
 export async function sendMessageStreaming(
   message: string,
   history: RustChatMessage[],
   onDelta: (delta: string, done: boolean) => void,
   onTokenUsage?: (usage: TokenUsageData) => void,
+  onGenerationEvent?: (event: MultiPartEvent) => void,
+  onStatus?: (message: string) => void,
 ): Promise<string> {
   try {
     const onEvent = new Channel<StreamEvent>();
     onEvent.onmessage = (event) => {
       if (event.event_type === 'token_usage' && event.token_usage && onTokenUsage) {
         onTokenUsage(event.token_usage);
+      } else if (event.event_type === 'overrides_status') {
+        onStatus?.(event.delta);
       } else {
         onDelta(event.delta, event.done);
       }
     };
 
+    let generationEvents: Channel<MultiPartEventEnvelope> | null = null;
+    if (onGenerationEvent) {
+      generationEvents = new Channel<MultiPartEventEnvelope>();
+      generationEvents.onmessage = (event) => {
+        onGenerationEvent(event);
+      };
+    }
+
     const result = await invoke<string>('send_message', {
       message,
       history,
       onEvent,
+      generationEvents,
     });
 
     return result;
@@ -123,9 +180,11 @@ export async function generateParallel(
   history: RustChatMessage[],
   onEvent: (event: MultiPartEvent) => void,
   existingCode?: string | null,
+  preset?: string | null,
+  targetPart?: string | null,
 ): Promise<string> {
   try {
-    const channel = new Channel<MultiPartEvent>();
+    const channel = new Channel<MultiPartEventEnvelope>();
     channel.onmessage = (event) => {
       onEvent(event);
     };
@@ -135,6 +194,8 @@ export async function generateParallel(
       history,
       existingCode: existingCode ?? null,
       onEvent: channel,
+      preset: preset ?? null,
+      targetPart: targetPart ?? null,
     });
 
     return result;
@@ -144,6 +205,61 @@ export async function generateParallel(
   }
 }
 
+/**
+ * Same as generateParallel, but resolves to a structured GenerationResult.
+ */
+export async function generateParallelResult(
+  message: string,
+  history: RustChatMessage[],
+  onEvent: (event: MultiPartEvent) => void,
+  existingCode?: string | null,
+  preset?: string | null,
+  targetPart?: string | null,
+): Promise<GenerationResult> {
+  try {
+    const channel = new Channel<MultiPartEventEnvelope>();
+    channel.onmessage = (event) => {
+      onEvent(event);
+    };
+
+    return await invoke<GenerationResult>('generate_parallel_result', {
+      message,
+      history,
+      existingCode: existingCode ?? null,
+      onEvent: channel,
+      preset: preset ?? null,
+      targetPart: targetPart ?? null,
+    });
+  } catch (err) {
+    console.error('generate_parallel_result failed:', err);
+    throw new Error(`Generate parallel failed: ${err}`);
+  }
+}
+
+/**
+ * Run several independent prompts through the pipeline, one after another.
+ * Resolves to the succeeded/failed totals once every prompt has finished.
+ */
+export async function generateBatch(
+  prompts: string[],
+  onEvent: (event: MultiPartEvent) => void,
+): Promise<BatchResult> {
+  try {
+    const channel = new Channel<MultiPartEventEnvelope>();
+    channel.onmessage = (event) => {
+      onEvent(event);
+    };
+
+    return await invoke<BatchResult>('generate_batch', {
+      prompts,
+      onEvent: channel,
+    });
+  } catch (err) {
+    console.error('generate_batch failed:', err);
+    throw new Error(`Batch generation failed: ${err}`);
+  }
+}
+
 /**
  * Retry skipped steps from an iterative build.
  * Sends the current code and skipped step info to the backend, which
@@ -157,7 +273,7 @@ export async function retrySkippedSteps(
   onEvent: (event: MultiPartEvent) => void,
 ): Promise<string> {
   try {
-    const channel = new Channel<MultiPartEvent>();
+    const channel = new Channel<MultiPartEventEnvelope>();
     channel.onmessage = (event) => {
       onEvent(event);
     };
@@ -185,10 +301,11 @@ export async function retryPart(
   partSpec: PartSpec,
   designPlanText: string,
   userRequest: string,
+  siblingParts: [string, string][],
   onEvent: (event: MultiPartEvent) => void,
 ): Promise<string> {
   try {
-    const channel = new Channel<MultiPartEvent>();
+    const channel = new Channel<MultiPartEventEnvelope>();
     channel.onmessage = (event) => {
       onEvent(event);
     };
@@ -198,6 +315,7 @@ export async function retryPart(
       partSpec,
       designPlanText,
       userRequest,
+      siblingParts,
       onEvent: channel,
     });
 
@@ -208,6 +326,30 @@ export async function retryPart(
   }
 }
 
+/**
+ * Split a design plan into its sections and build steps
+ */
+export async function parseDesignPlan(planText: string): Promise<StructuredPlan> {
+  try {
+    return await invoke<StructuredPlan>('parse_design_plan', { planText });
+  } catch (err) {
+    console.error('parse_design_plan failed:', err);
+    throw new Error(`Parse design plan failed: ${err}`);
+  }
+}
+
+/**
+ * Compare two design plans by build steps, operations and dimensions
+ */
+export async function diffPlans(oldPlan: string, newPlan: string): Promise<PlanDiff> {
+  try {
+    return await invoke<PlanDiff>('diff_plans', { oldPlan, newPlan });
+  } catch (err) {
+    console.error('diff_plans failed:', err);
+    throw new Error(`Diff plans failed: ${err}`);
+  }
+}
+
 /**
  * Generate only the design plan (Phase 0). Returns the plan result
  * for the user to review/edit before proceeding to code generation.
@@ -216,9 +358,11 @@ export async function generateDesignPlan(
   message: string,
   history: RustChatMessage[],
   onEvent: (event: MultiPartEvent) => void,
+  autoApprove: boolean | null = null,
+  preset: string | null = null,
 ): Promise<DesignPlanResult> {
   try {
-    const channel = new Channel<MultiPartEvent>();
+    const channel = new Channel<MultiPartEventEnvelope>();
     channel.onmessage = (event) => {
       onEvent(event);
     };
@@ -227,6 +371,8 @@ export async function generateDesignPlan(
       message,
       history,
       onEvent: channel,
+      autoApprove,
+      preset,
     });
     return result;
   } catch (err) {
@@ -245,9 +391,10 @@ export async function generateFromPlan(
   history: RustChatMessage[],
   onEvent: (event: MultiPartEvent) => void,
   existingCode?: string | null,
+  preset?: string | null,
 ): Promise<string> {
   try {
-    const channel = new Channel<MultiPartEvent>();
+    const channel = new Channel<MultiPartEventEnvelope>();
     channel.onmessage = (event) => {
       onEvent(event);
     };
@@ -258,6 +405,7 @@ export async function generateFromPlan(
       history,
       existingCode: existingCode ?? null,
       onEvent: channel,
+      preset: preset ?? null,
     });
   } catch (err) {
     console.error('generate_from_plan failed:', err);
@@ -267,7 +415,7 @@ export async function generateFromPlan(
 
 /**
  * Extract Python code from an AI response using a 3-tier cascade:
- * 1. <CODE>...</CODE> XML tags (case-insensitive)
+ * 1. <CODE>...</CODE> XML tags (case-insensitive), or an unclosed trailing <CODE> block
  * 2. ```python ... ``` markdown fence
  * 3. Any ``` block containing Build123d markers (from build123d / BuildPart / Part.)
  * Returns the first matched code block content, or null if none found.
@@ -276,6 +424,9 @@ export function extractPythonCode(text: string): string | null {
   // Tier 1: <CODE>...</CODE> XML tags
   const xmlMatch = text.match(/<CODE>([\s\S]*?)<\/CODE>/i);
   if (xmlMatch && xmlMatch[1].trim()) return xmlMatch[1].trim();
+  // A `</CODE>` stop sequence ends the response before the closing tag
+  const openMatch = xmlMatch ? null : text.match(/<CODE>([\s\S]*)$/i);
+  if (openMatch && openMatch[1].trim() && !openMatch[1].includes('```')) return openMatch[1].trim();
 
   // Tier 2: ```python ... ``` markdown fence
   const fenceMatch = text.match(/```python\s*\n([\s\S]*?)```/);
@@ -298,6 +449,7 @@ export function extractPythonCode(text: string): string | null {
 export async function executeCode(
   code: string,
   timeoutMs?: number,
+  options?: { exportAllSolids?: boolean; include?: string[] },
 ): Promise<ExecuteResult> {
   try {
     const request: Record<string, unknown> = {
@@ -306,6 +458,10 @@ export async function executeCode(
     if (timeoutMs !== undefined) {
       request.timeoutMs = timeoutMs;
     }
+    if (options?.exportAllSolids) {
+      request.exportAllSolids = true;
+      request.include = options.include ?? null;
+    }
     return await invoke<ExecuteResult>('execute_code', request);
   } catch (err) {
     console.error('execute_code failed:', err);
@@ -313,6 +469,18 @@ export async function executeCode(
   }
 }
 
+/**
+ * TODO/FIXME/assumption comments in code, for the user to review
+ */
+export async function extractAssumptions(code: string): Promise<CodeAssumption[]> {
+  try {
+    return await invoke<CodeAssumption[]>('extract_assumptions', { code });
+  } catch (err) {
+    console.error('extract_assumptions failed:', err);
+    throw new Error(`Extract assumptions failed: ${err}`);
+  }
+}
+
 export async function listMechanisms(): Promise<MechanismListResponse> {
   try {
     return await invoke<MechanismListResponse>('list_mechanisms');
@@ -331,9 +499,15 @@ export async function searchMechanisms(query: string, limit = 50): Promise<Mecha
   }
 }
 
-export async function installMechanismPack(manifestUrl: string): Promise<MechanismImportReport> {
+export async function installMechanismPack(
+  manifestUrl: string,
+  confirmFlagged?: boolean,
+): Promise<MechanismImportReport> {
   try {
-    return await invoke<MechanismImportReport>('install_mechanism_pack', { manifestUrl });
+    return await invoke<MechanismImportReport>('install_mechanism_pack', {
+      manifestUrl,
+      confirmFlagged,
+    });
   } catch (err) {
     console.error('install_mechanism_pack failed:', err);
     throw new Error(`Install mechanism pack failed: ${err}`);
@@ -349,6 +523,21 @@ export async function removeMechanismPack(packageId: string): Promise<boolean> {
   }
 }
 
+/**
+ * Render a mechanism's code template at the given parameters and execute it
+ */
+export async function instantiateMechanism(
+  id: string,
+  params: Record<string, number>,
+): Promise<MechanismInstance> {
+  try {
+    return await invoke<MechanismInstance>('instantiate_mechanism', { id, params });
+  } catch (err) {
+    console.error('instantiate_mechanism failed:', err);
+    throw new Error(`Instantiate mechanism failed: ${err}`);
+  }
+}
+
 /**
  * Check Python environment status (python, venv, build123d)
  */
@@ -373,6 +562,42 @@ export async function setupPython(): Promise<string> {
   }
 }
 
+/**
+ * Get the known-good Python package snapshot, if one was captured
+ */
+export async function getPythonEnvManifest(): Promise<EnvLock | null> {
+  try {
+    return await invoke<EnvLock | null>('get_python_env_manifest');
+  } catch (err) {
+    console.error('get_python_env_manifest failed:', err);
+    throw new Error(`Get Python environment manifest failed: ${err}`);
+  }
+}
+
+/**
+ * Reinstall the snapshot's package versions into the existing venv
+ */
+export async function pinPythonEnv(): Promise<EnvDrift> {
+  try {
+    return await invoke<EnvDrift>('pin_python_env');
+  } catch (err) {
+    console.error('pin_python_env failed:', err);
+    throw new Error(`Pin Python environment failed: ${err}`);
+  }
+}
+
+/**
+ * Rebuild the venv from the snapshot
+ */
+export async function restorePythonEnv(): Promise<string> {
+  try {
+    return await invoke<string>('restore_python_env');
+  } catch (err) {
+    console.error('restore_python_env failed:', err);
+    throw new Error(`Restore Python environment failed: ${err}`);
+  }
+}
+
 /**
  * Get the provider/model registry
  */
@@ -385,6 +610,314 @@ export async function getProviderRegistry(): Promise<ProviderInfo[]> {
   }
 }
 
+/**
+ * List the model ids a provider currently offers (installed models for Ollama)
+ */
+export async function listModels(provider: string): Promise<string[]> {
+  try {
+    return await invoke<string[]>('list_models', { provider });
+  } catch (err) {
+    console.error('list_models failed:', err);
+    throw new Error(`List models failed: ${err}`);
+  }
+}
+
+/**
+ * List the bundled preset and custom rule files behind the current rules, in merge order
+ */
+export async function listAgentRuleSources(): Promise<AgentRuleSource[]> {
+  try {
+    return await invoke<AgentRuleSource[]>('list_agent_rule_sources');
+  } catch (err) {
+    console.error('list_agent_rule_sources failed:', err);
+    throw new Error(`List agent rule sources failed: ${err}`);
+  }
+}
+
+/**
+ * Get the IPC event schema version the backend was built with
+ */
+export async function getEventSchemaVersion(): Promise<number> {
+  try {
+    return await invoke<number>('get_event_schema_version');
+  } catch (err) {
+    console.error('get_event_schema_version failed:', err);
+    throw new Error(`Get event schema version failed: ${err}`);
+  }
+}
+
+/**
+ * Get the bundled model prices, the user's overrides and the merged pricing in effect
+ */
+export async function getModelPricing(): Promise<ModelPricingSettings> {
+  try {
+    return await invoke<ModelPricingSettings>('get_model_pricing');
+  } catch (err) {
+    console.error('get_model_pricing failed:', err);
+    throw new Error(`Get model pricing failed: ${err}`);
+  }
+}
+
+/**
+ * Replace the user's pricing overrides; returns the merged pricing in effect
+ */
+export async function setModelPricing(overrides: PricingOverrides): Promise<ModelPricingSettings> {
+  try {
+    return await invoke<ModelPricingSettings>('set_model_pricing', { overrides });
+  } catch (err) {
+    console.error('set_model_pricing failed:', err);
+    throw new Error(`Set model pricing failed: ${err}`);
+  }
+}
+
+/**
+ * Get the buffered events of a run after `sinceSeq`, to catch up after a reconnect
+ */
+export async function getRunEvents(runId: string, sinceSeq: number): Promise<RunEvents> {
+  try {
+    return await invoke<RunEvents>('get_run_events', { runId, sinceSeq });
+  } catch (err) {
+    console.error('get_run_events failed:', err);
+    throw new Error(`Get run events failed: ${err}`);
+  }
+}
+
+/**
+ * Get the validation attempt chains recorded for a run, optionally for one part
+ */
+export async function getValidationHistory(
+  runId: string,
+  partName?: string,
+): Promise<ValidationChain[]> {
+  try {
+    return await invoke<ValidationChain[]>('get_validation_history', {
+      runId,
+      partName: partName ?? null,
+    });
+  } catch (err) {
+    console.error('get_validation_history failed:', err);
+    throw new Error(`Get validation history failed: ${err}`);
+  }
+}
+
+/**
+ * Write a Markdown transcript of a generation session. With runId, the run's
+ * validation attempt chains are appended.
+ */
+export async function exportTranscript(
+  events: MultiPartEvent[],
+  finalCode: string,
+  planText: string,
+  path: string,
+  userRequest?: string,
+  runId?: string,
+): Promise<string> {
+  try {
+    return await invoke<string>('export_transcript', {
+      events,
+      finalCode,
+      planText,
+      path,
+      userRequest: userRequest ?? null,
+      runId: runId ?? null,
+    });
+  } catch (err) {
+    console.error('export_transcript failed:', err);
+    throw new Error(`Export transcript failed: ${err}`);
+  }
+}
+
+/**
+ * Compare two runs for A/B testing, optionally writing the comparison as JSON to `outputPath`
+ */
+export async function compareRuns(
+  runA: string,
+  runB: string,
+  outputPath?: string,
+): Promise<RunComparison> {
+  try {
+    return await invoke<RunComparison>('compare_runs', {
+      runA,
+      runB,
+      outputPath: outputPath ?? null,
+    });
+  } catch (err) {
+    console.error('compare_runs failed:', err);
+    throw new Error(`Compare runs failed: ${err}`);
+  }
+}
+
+/**
+ * Assemble a run parked by pause_before_assembly, excluding or re-rolling parts first
+ */
+export async function approveAssembly(
+  runId: string,
+  exclude: number[],
+  reroll: number[],
+  onEvent: (event: MultiPartEvent) => void,
+): Promise<string> {
+  try {
+    const channel = new Channel<MultiPartEventEnvelope>();
+    channel.onmessage = (event) => {
+      onEvent(event);
+    };
+    return await invoke<string>('approve_assembly', { runId, exclude, reroll, onEvent: channel });
+  } catch (err) {
+    console.error('approve_assembly failed:', err);
+    throw new Error(`Approve assembly failed: ${err}`);
+  }
+}
+
+/**
+ * Re-assemble and validate a failed multi-part generation from its saved accepted parts
+ */
+export async function resumeGeneration(
+  generationId: string,
+  onEvent: (event: MultiPartEvent) => void,
+): Promise<string> {
+  try {
+    const channel = new Channel<MultiPartEventEnvelope>();
+    channel.onmessage = (event) => {
+      onEvent(event);
+    };
+    return await invoke<string>('resume_generation', { generationId, onEvent: channel });
+  } catch (err) {
+    console.error('resume_generation failed:', err);
+    throw new Error(`Resume generation failed: ${err}`);
+  }
+}
+
+/**
+ * Every stored candidate for one part of a run, accepted or not
+ */
+export async function getPartCandidates(runId: string, partIndex: number): Promise<PartCandidate[]> {
+  try {
+    return await invoke<PartCandidate[]>('get_part_candidates', { runId, partIndex });
+  } catch (err) {
+    console.error('get_part_candidates failed:', err);
+    throw new Error(`Get part candidates failed: ${err}`);
+  }
+}
+
+/**
+ * Swap a stored candidate into a run's part set, then re-run only assembly and validation
+ */
+export async function usePartCandidate(
+  runId: string,
+  partIndex: number,
+  candidateIdx: number,
+  onEvent: (event: MultiPartEvent) => void,
+): Promise<string> {
+  try {
+    const channel = new Channel<MultiPartEventEnvelope>();
+    channel.onmessage = (event) => {
+      onEvent(event);
+    };
+    return await invoke<string>('use_part_candidate', { runId, partIndex, candidateIdx, onEvent: channel });
+  } catch (err) {
+    console.error('use_part_candidate failed:', err);
+    throw new Error(`Use part candidate failed: ${err}`);
+  }
+}
+
+/**
+ * Add a generation request to the queue and return its entry id
+ */
+export async function enqueueGeneration(
+  message: string,
+  planText?: string | null,
+  options?: QueueEntryOptions,
+): Promise<string> {
+  try {
+    return await invoke<string>('enqueue_generation', {
+      message,
+      planText: planText ?? null,
+      options: options ?? null,
+    });
+  } catch (err) {
+    console.error('enqueue_generation failed:', err);
+    throw new Error(`Enqueue generation failed: ${err}`);
+  }
+}
+
+/**
+ * Current queue entries and whether the runner is active
+ */
+export async function getQueueStatus(): Promise<QueueStatus> {
+  try {
+    return await invoke<QueueStatus>('get_queue_status');
+  } catch (err) {
+    console.error('get_queue_status failed:', err);
+    throw new Error(`Get queue status failed: ${err}`);
+  }
+}
+
+/**
+ * Cancel a pending or running queue entry; false when it had already finished
+ */
+export async function cancelQueueEntry(entryId: string): Promise<boolean> {
+  try {
+    return await invoke<boolean>('cancel_queue_entry', { entryId });
+  } catch (err) {
+    console.error('cancel_queue_entry failed:', err);
+    throw new Error(`Cancel queue entry failed: ${err}`);
+  }
+}
+
+/**
+ * Move the listed pending entries to the front of the run order, in the given sequence
+ */
+export async function reorderQueue(entryIds: string[]): Promise<QueueStatus> {
+  try {
+    return await invoke<QueueStatus>('reorder_queue', { entryIds });
+  } catch (err) {
+    console.error('reorder_queue failed:', err);
+    throw new Error(`Reorder queue failed: ${err}`);
+  }
+}
+
+/**
+ * Run pending queue entries one at a time until the queue is empty
+ */
+export async function runGenerationQueue(
+  onEvent: (event: QueueEvent) => void,
+): Promise<QueueStatus> {
+  try {
+    const channel = new Channel<QueueEvent>();
+    channel.onmessage = (event) => {
+      onEvent(event);
+    };
+    return await invoke<QueueStatus>('run_generation_queue', { onEvent: channel });
+  } catch (err) {
+    console.error('run_generation_queue failed:', err);
+    throw new Error(`Run generation queue failed: ${err}`);
+  }
+}
+
+/**
+ * Show the rule content the geometry advisor would receive for a request
+ */
+export async function previewPromptContext(message: string): Promise<RuleContext> {
+  try {
+    return await invoke<RuleContext>('preview_prompt_context', { message });
+  } catch (err) {
+    console.error('preview_prompt_context failed:', err);
+    throw new Error(`Preview prompt context failed: ${err}`);
+  }
+}
+
+/**
+ * Suggest a reliability profile for a request from its text alone
+ */
+export async function suggestProfile(message: string): Promise<ProfileSuggestion> {
+  try {
+    return await invoke<ProfileSuggestion>('suggest_profile', { message });
+  } catch (err) {
+    console.error('suggest_profile failed:', err);
+    throw new Error(`Suggest profile failed: ${err}`);
+  }
+}
+
 /**
  * Get application settings
  */
@@ -412,9 +945,25 @@ export async function updateSettings(config: AppConfig): Promise<void> {
 /**
  * Save project to a file
  */
-export async function saveProject(name: string, code: string, messages: RustChatMessage[], path: string, scene?: unknown): Promise<void> {
+export async function saveProject(
+  name: string,
+  code: string,
+  messages: RustChatMessage[],
+  path: string,
+  scene?: unknown,
+  generation?: ProjectGenerationReport,
+  codeHistory?: CodeSnapshot[],
+): Promise<void> {
   try {
-    await invoke('save_project', { name, code, messages, path, scene: scene ?? null });
+    await invoke('save_project', {
+      name,
+      code,
+      messages,
+      path,
+      scene: scene ?? null,
+      generation: generation ?? null,
+      codeHistory: codeHistory ?? null,
+    });
   } catch (err) {
     console.error('save_project failed:', err);
     throw new Error(`Save project failed: ${err}`);
@@ -433,6 +982,132 @@ export async function loadProject(path: string): Promise<ProjectFile> {
   }
 }
 
+/**
+ * Generation context of the project last opened with loadProject, if any
+ */
+export async function getProjectSummary(): Promise<ProjectSummary | null> {
+  try {
+    return await invoke<ProjectSummary | null>('get_project_summary');
+  } catch (err) {
+    console.error('get_project_summary failed:', err);
+    throw new Error(`Get project summary failed: ${err}`);
+  }
+}
+
+/**
+ * Load an external build123d script, extracting its parameters and executing it once
+ */
+export async function importCodeFile(path: string): Promise<ImportedCode> {
+  try {
+    return await invoke<ImportedCode>('import_code_file', { path });
+  } catch (err) {
+    console.error('import_code_file failed:', err);
+    throw new Error(`Import code failed: ${err}`);
+  }
+}
+
+/**
+ * Standard front/back/top/bottom/left/right/isometric cameras framed on a run's final geometry
+ */
+export async function getStandardViews(
+  runId?: string,
+  geometryHash?: string,
+  aspect?: number,
+): Promise<StandardViews> {
+  try {
+    return await invoke<StandardViews>('get_standard_views', {
+      runId: runId ?? null,
+      geometryHash: geometryHash ?? null,
+      aspect: aspect ?? null,
+    });
+  } catch (err) {
+    console.error('get_standard_views failed:', err);
+    throw new Error(`Get standard views failed: ${err}`);
+  }
+}
+
+/**
+ * Save a named camera bookmark for the open project
+ */
+export async function saveViewBookmark(
+  camera: CameraView,
+  runId?: string,
+  geometryHash?: string,
+): Promise<ViewBookmark[]> {
+  try {
+    return await invoke<ViewBookmark[]>('save_view_bookmark', {
+      camera,
+      runId: runId ?? null,
+      geometryHash: geometryHash ?? null,
+    });
+  } catch (err) {
+    console.error('save_view_bookmark failed:', err);
+    throw new Error(`Save view bookmark failed: ${err}`);
+  }
+}
+
+/**
+ * Camera bookmarks of the open project, rescaled to the given geometry
+ */
+export async function listViewBookmarks(
+  runId?: string,
+  geometryHash?: string,
+): Promise<ViewBookmark[]> {
+  try {
+    return await invoke<ViewBookmark[]>('list_view_bookmarks', {
+      runId: runId ?? null,
+      geometryHash: geometryHash ?? null,
+    });
+  } catch (err) {
+    console.error('list_view_bookmarks failed:', err);
+    throw new Error(`List view bookmarks failed: ${err}`);
+  }
+}
+
+/**
+ * Measure code (or a run's final code) on its BREP: distance, thickness or radius
+ */
+export async function queryGeometry(
+  query: GeometryQuery,
+  code?: string,
+  runId?: string,
+): Promise<GeometryQueryResponse> {
+  try {
+    return await invoke<GeometryQueryResponse>('query_geometry', {
+      query,
+      code: code ?? null,
+      runId: runId ?? null,
+    });
+  } catch (err) {
+    console.error('query_geometry failed:', err);
+    throw new Error(`Geometry query failed: ${err}`);
+  }
+}
+
+/**
+ * Get disk usage of the app data directory by category
+ */
+export async function getStorageUsage(): Promise<StorageUsage> {
+  try {
+    return await invoke<StorageUsage>('get_storage_usage');
+  } catch (err) {
+    console.error('get_storage_usage failed:', err);
+    throw new Error(`Get storage usage failed: ${err}`);
+  }
+}
+
+/**
+ * Apply the artifact retention policy; with `dryRun`, only report what would be freed
+ */
+export async function cleanStorage(dryRun = false): Promise<CleanupReport> {
+  try {
+    return await invoke<CleanupReport>('clean_storage', { dryRun });
+  } catch (err) {
+    console.error('clean_storage failed:', err);
+    throw new Error(`Clean storage failed: ${err}`);
+  }
+}
+
 /**
  * Export STL: run Build123d code and save the resulting STL to a file
  */
@@ -448,15 +1123,105 @@ export async function exportStl(code: string, outputPath: string): Promise<strin
 /**
  * Export STEP: run Build123d code and save the resulting STEP to a file
  */
-export async function exportStep(code: string, outputPath: string): Promise<string> {
+export async function exportStep(
+  code: string,
+  outputPath: string,
+  verify?: boolean,
+): Promise<string> {
   try {
-    return await invoke<string>('export_step', { code, outputPath });
+    return await invoke<string>('export_step', { code, outputPath, verify });
   } catch (err) {
     console.error('export_step failed:', err);
     throw new Error(`Export STEP failed: ${err}`);
   }
 }
 
+/**
+ * Re-import a STEP file exported from `code` and compare it with the code's geometry
+ */
+export async function verifyExport(code: string, path: string): Promise<ExportVerificationReport> {
+  try {
+    return await invoke<ExportVerificationReport>('verify_export', { code, path });
+  } catch (err) {
+    console.error('verify_export failed:', err);
+    throw new Error(`Verify export failed: ${err}`);
+  }
+}
+
+/**
+ * Replace repeated numeric literals with named variables, keeping the rewrite only if the geometry is unchanged
+ */
+export async function parametrizeCode(
+  code: string,
+  onEvent: (event: MultiPartEvent) => void,
+  request?: string | null,
+  planText?: string | null,
+  minRepeats?: number | null,
+): Promise<ParametrizedCode> {
+  try {
+    const channel = new Channel<MultiPartEventEnvelope>();
+    channel.onmessage = (event) => {
+      onEvent(event);
+    };
+
+    return await invoke<ParametrizedCode>('parametrize_code', {
+      code,
+      request: request ?? null,
+      planText: planText ?? null,
+      minRepeats: minRepeats ?? null,
+      onEvent: channel,
+    });
+  } catch (err) {
+    console.error('parametrize_code failed:', err);
+    throw new Error(`Parametrize code failed: ${err}`);
+  }
+}
+
+/**
+ * Export every part as its own STEP file (`{name}.step`) in `dir`, placed at its
+ * assembly position. With `verify`, parts that do not re-import faithfully fail.
+ */
+export async function exportPartsStep(
+  parts: [string, string, [number, number, number]][],
+  dir: string,
+  verify?: boolean,
+): Promise<PartsStepExport> {
+  try {
+    return await invoke<PartsStepExport>('export_parts_step', { parts, dir, verify: verify ?? null });
+  } catch (err) {
+    console.error('export_parts_step failed:', err);
+    throw new Error(`Export parts STEP failed: ${err}`);
+  }
+}
+
+/**
+ * Export named objects of a multi-object script as one STEP file each in `dir`
+ */
+export async function exportObjectsStep(
+  code: string,
+  names: string[],
+  dir: string,
+): Promise<PartsStepExport> {
+  try {
+    return await invoke<PartsStepExport>('export_objects_step', { code, names, dir });
+  } catch (err) {
+    console.error('export_objects_step failed:', err);
+    throw new Error(`Export objects STEP failed: ${err}`);
+  }
+}
+
+/**
+ * Write Markdown assembly instructions (BOM, thumbnails, mating, sequence) for a multi-part run
+ */
+export async function exportAssemblyInstructions(runId: string, path: string): Promise<string> {
+  try {
+    return await invoke<string>('export_assembly_instructions', { runId, path });
+  } catch (err) {
+    console.error('export_assembly_instructions failed:', err);
+    throw new Error(`Export assembly instructions failed: ${err}`);
+  }
+}
+
 /**
  * Show a native save file dialog
  */
@@ -559,13 +1324,22 @@ export interface ColorInfo {
 /**
  * Export model as 3MF with optional colors
  */
-export async function export3mf(code: string, outputPath: string, colors?: ColorInfo[]): Promise<string> {
+export async function export3mf(
+  code: string,
+  outputPath: string,
+  colors?: ColorInfo[],
+  objects?: string[],
+): Promise<string> {
   try {
-    const result = await invoke<{ path: string; triangles: number }>('export_3mf', {
+    const result = await invoke<{ path: string; triangles: number; objects: string[] }>('export_3mf', {
       code,
       outputPath,
       colors: colors ?? null,
+      objects: objects ?? null,
     });
+    if (result.objects.length > 0) {
+      return `3MF exported (${result.triangles} triangles, ${result.objects.length} objects)`;
+    }
     return `3MF exported (${result.triangles} triangles)`;
   } catch (err) {
     console.error('export_3mf failed:', err);
@@ -573,6 +1347,35 @@ export async function export3mf(code: string, outputPath: string, colors?: Color
   }
 }
 
+/**
+ * Assign a configured material (a key of the settings materials table) to a
+ * part by name for mass-properties reports. `null` clears the assignment.
+ */
+export async function setPartMaterial(partName: string, material: string | null): Promise<void> {
+  try {
+    await invoke('set_part_material', { partName, material });
+  } catch (err) {
+    console.error('set_part_material failed:', err);
+    throw new Error(`Set part material failed: ${err}`);
+  }
+}
+
+/**
+ * Assign an RGB color (channels 0..1) to a generated part by name, so later
+ * assemblies and 3MF exports keep it. `null` restores the palette color.
+ */
+export async function setPartColor(
+  partName: string,
+  color: [number, number, number] | null,
+): Promise<void> {
+  try {
+    await invoke('set_part_color', { partName, color });
+  } catch (err) {
+    console.error('set_part_color failed:', err);
+    throw new Error(`Set part color failed: ${err}`);
+  }
+}
+
 /**
  * Check mesh quality for manufacturing
  */
@@ -597,6 +1400,190 @@ export async function orientForPrint(code: string): Promise<OrientResult> {
   }
 }
 
+/**
+ * Estimate print time and material (±30%) for a stored run or a raw STL
+ */
+export async function estimatePrintJob(
+  source: { runId: string } | { stlBase64: string },
+  profileId?: string | null,
+  buildHeightMm?: number | null,
+): Promise<PrintEstimate> {
+  try {
+    return await invoke<PrintEstimate>('estimate_print_job', {
+      runId: 'runId' in source ? source.runId : null,
+      stlBase64: 'stlBase64' in source ? source.stlBase64 : null,
+      profileId: profileId ?? null,
+      buildHeightMm: buildHeightMm ?? null,
+    });
+  } catch (err) {
+    console.error('estimate_print_job failed:', err);
+    throw new Error(`Print estimate failed: ${err}`);
+  }
+}
+
+/**
+ * List pipeline presets, built-ins first
+ */
+export async function listPipelinePresets(): Promise<PipelinePreset[]> {
+  try {
+    return await invoke<PipelinePreset[]>('list_pipeline_presets');
+  } catch (err) {
+    console.error('list_pipeline_presets failed:', err);
+    throw new Error(`List pipeline presets failed: ${err}`);
+  }
+}
+
+/**
+ * Create or replace a user preset. Built-ins are cloned by saving them under a new id.
+ */
+export async function savePipelinePreset(preset: PipelinePreset): Promise<PipelinePreset[]> {
+  try {
+    return await invoke<PipelinePreset[]>('save_pipeline_preset', { preset });
+  } catch (err) {
+    console.error('save_pipeline_preset failed:', err);
+    throw new Error(`Save pipeline preset failed: ${err}`);
+  }
+}
+
+/**
+ * Delete a user preset; built-ins cannot be deleted
+ */
+export async function deletePipelinePreset(id: string): Promise<PipelinePreset[]> {
+  try {
+    return await invoke<PipelinePreset[]>('delete_pipeline_preset', { id });
+  } catch (err) {
+    console.error('delete_pipeline_preset failed:', err);
+    throw new Error(`Delete pipeline preset failed: ${err}`);
+  }
+}
+
+/**
+ * List printer profiles used for print estimates
+ */
+export async function listPrinterProfiles(): Promise<PrinterProfile[]> {
+  try {
+    return await invoke<PrinterProfile[]>('list_printer_profiles');
+  } catch (err) {
+    console.error('list_printer_profiles failed:', err);
+    throw new Error(`List printer profiles failed: ${err}`);
+  }
+}
+
+/**
+ * Create or replace a printer profile
+ */
+export async function savePrinterProfile(profile: PrinterProfile): Promise<PrinterProfile[]> {
+  try {
+    return await invoke<PrinterProfile[]>('save_printer_profile', { profile });
+  } catch (err) {
+    console.error('save_printer_profile failed:', err);
+    throw new Error(`Save printer profile failed: ${err}`);
+  }
+}
+
+/**
+ * Delete a printer profile
+ */
+export async function deletePrinterProfile(id: string): Promise<PrinterProfile[]> {
+  try {
+    return await invoke<PrinterProfile[]>('delete_printer_profile', { id });
+  } catch (err) {
+    console.error('delete_printer_profile failed:', err);
+    throw new Error(`Delete printer profile failed: ${err}`);
+  }
+}
+
+/**
+ * List stored repair examples, most recently used first
+ */
+export async function listRepairExamples(): Promise<RepairExample[]> {
+  try {
+    return await invoke<RepairExample[]>('list_repair_examples');
+  } catch (err) {
+    console.error('list_repair_examples failed:', err);
+    throw new Error(`List repair examples failed: ${err}`);
+  }
+}
+
+/**
+ * Delete a stored repair example
+ */
+export async function deleteRepairExample(id: string): Promise<RepairExample[]> {
+  try {
+    return await invoke<RepairExample[]>('delete_repair_example', { id });
+  } catch (err) {
+    console.error('delete_repair_example failed:', err);
+    throw new Error(`Delete repair example failed: ${err}`);
+  }
+}
+
+/**
+ * Scan telemetry traces for recurring failures and queue draft anti-patterns
+ * for review. Returns only the drafts added by this run.
+ */
+export async function mineAntiPatterns(minOccurrences?: number): Promise<LearnedAntiPattern[]> {
+  try {
+    return await invoke<LearnedAntiPattern[]>('mine_anti_patterns', {
+      minOccurrences: minOccurrences ?? null,
+    });
+  } catch (err) {
+    console.error('mine_anti_patterns failed:', err);
+    throw new Error(`Mine anti-patterns failed: ${err}`);
+  }
+}
+
+/**
+ * List mined anti-pattern drafts awaiting review
+ */
+export async function getPendingAntiPatterns(): Promise<LearnedAntiPattern[]> {
+  try {
+    return await invoke<LearnedAntiPattern[]>('get_pending_anti_patterns');
+  } catch (err) {
+    console.error('get_pending_anti_patterns failed:', err);
+    throw new Error(`Get pending anti-patterns failed: ${err}`);
+  }
+}
+
+/**
+ * Accept a pending draft so retry strategies and repair prompts use it
+ */
+export async function approveAntiPattern(id: string): Promise<LearnedAntiPattern> {
+  try {
+    return await invoke<LearnedAntiPattern>('approve_anti_pattern', { id });
+  } catch (err) {
+    console.error('approve_anti_pattern failed:', err);
+    throw new Error(`Approve anti-pattern failed: ${err}`);
+  }
+}
+
+/**
+ * Export provider-call audit records in a date range (epoch ms) as JSONL
+ */
+export async function exportAuditLog(
+  path: string,
+  fromMs: number | null = null,
+  toMs: number | null = null,
+): Promise<number> {
+  try {
+    return await invoke<number>('export_audit_log', { fromMs, toMs, path });
+  } catch (err) {
+    console.error('export_audit_log failed:', err);
+    throw new Error(`Export audit log failed: ${err}`);
+  }
+}
+
+/**
+ * Delete every provider-call audit record
+ */
+export async function purgeAuditLog(): Promise<void> {
+  try {
+    await invoke('purge_audit_log');
+  } catch (err) {
+    console.error('purge_audit_log failed:', err);
+    throw new Error(`Purge audit log failed: ${err}`);
+  }
+}
+
 /**
  * Compute sheet metal flat pattern and export as DXF
  */
@@ -673,3 +1660,18 @@ export async function showImportCadDialog(): Promise<string | null> {
     return null;
   }
 }
+
+/**
+ * Show a native open file dialog filtered to Python scripts.
+ */
+export async function showImportCodeDialog(): Promise<string | null> {
+  try {
+    const path = await open({
+      multiple: false,
+      filters: [{ name: 'Python Scripts', extensions: ['py'] }],
+    });
+    return typeof path === 'string' ? path : null;
+  } catch {
+    return null;
+  }
+}
@@ -53,8 +53,8 @@ export function getMechanismStore() {
         loading = false;
       }
     },
-    async install(manifestUrl: string): Promise<MechanismImportReport> {
-      const report = await installMechanismPack(manifestUrl);
+    async install(manifestUrl: string, confirmFlagged = false): Promise<MechanismImportReport> {
+      const report = await installMechanismPack(manifestUrl, confirmFlagged);
       await this.refresh();
       return report;
     },
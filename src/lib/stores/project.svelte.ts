@@ -1,4 +1,4 @@
-import type { ChatMessage } from '$lib/types';
+import type { ChatMessage, CodeSnapshot, ProjectGenerationReport } from '$lib/types';
 
 const DEFAULT_CODE = `from build123d import *
 
@@ -6,12 +6,17 @@ const DEFAULT_CODE = `from build123d import *
 result = Box(10, 10, 10)
 `;
 
+// Earlier code versions kept for the project file, oldest first.
+const MAX_SNAPSHOTS = 20;
+
 let name = $state('Untitled Project');
 let code = $state(DEFAULT_CODE);
 let previousCode = $state<string | null>(null);
 let messages = $state<ChatMessage[]>([]);
 let modified = $state(false);
 let filePath = $state<string | null>(null);
+let generation = $state<ProjectGenerationReport | null>(null);
+let codeHistory = $state<CodeSnapshot[]>([]);
 
 export function getProjectStore() {
   return {
@@ -33,6 +38,12 @@ export function getProjectStore() {
     get previousCode() {
       return previousCode;
     },
+    get generation() {
+      return generation;
+    },
+    get codeHistory() {
+      return codeHistory;
+    },
     setName(val: string) {
       name = val;
       modified = true;
@@ -62,6 +73,22 @@ export function getProjectStore() {
     setModified(val: boolean) {
       modified = val;
     },
+    /** Remember how the latest generation ended, for the next save. */
+    setGeneration(report: ProjectGenerationReport | null) {
+      generation = report;
+      modified = true;
+    },
+    addSnapshot(snapshotCode: string, label: string) {
+      const last = codeHistory[codeHistory.length - 1];
+      if (last && last.code === snapshotCode) return;
+      const snapshot = { code: snapshotCode, label, timestamp: Math.floor(Date.now() / 1000) };
+      codeHistory = [...codeHistory.slice(-(MAX_SNAPSHOTS - 1)), snapshot];
+    },
+    /** Restore generation context from an opened project file. */
+    restoreGeneration(report: ProjectGenerationReport | null, history: CodeSnapshot[]) {
+      generation = report;
+      codeHistory = history;
+    },
     reset() {
       name = 'Untitled Project';
       code = DEFAULT_CODE;
@@ -69,6 +96,8 @@ export function getProjectStore() {
       messages = [];
       modified = false;
       filePath = null;
+      generation = null;
+      codeHistory = [];
     },
   };
 }
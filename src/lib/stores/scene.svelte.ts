@@ -98,6 +98,7 @@ export function getSceneStore() {
       name: string,
       stlBase64: string,
       position: [number, number, number],
+      color?: string,
     ): SceneObject {
       const existing = objects.find(
         (o) => o.importedPartKey === importedPartKey && !!o.importedMeshBase64,
@@ -114,6 +115,7 @@ export function getSceneStore() {
           visible: true,
           importedMeshBase64: stlBase64,
           importedPartKey,
+          color: color ?? existing.color,
         };
         objects = objects.map((o) => (o.id === existing.id ? updated : o));
         return updated;
@@ -130,7 +132,7 @@ export function getSceneStore() {
           ...getDefaultTransform(),
           position,
         },
-        color: '#89b4fa',
+        color: color ?? '#89b4fa',
         visible: true,
         locked: false,
         importedMeshBase64: stlBase64,
@@ -20,22 +20,110 @@ const defaultConfig: AppConfig = {
   snap_sketch: 0.5,
   enable_consensus: false,
   auto_approve_plan: false,
+  auto_approve_max_risk: 3,
+  organic_missing_notes_risk: 2,
+  operation_soft_budget: 22,
+  operation_hard_budget: 60,
+  enable_thread_helper: true,
+  explain_retries: false,
   retrieval_enabled: true,
   retrieval_token_budget: 3500,
+  retrieval_min_score: 0,
+  prioritized_retrieval_packs: [],
+  retrieval_pack_boost: 1.5,
+  pinned_retrieval_ids: [],
+  retrieval_embeddings: 'online',
+  preview_max_triangles: 50000,
+  custom_rules_dir: null,
+  manufacturing_rules_budget_chars: 4000,
+  dimension_guidance_budget_chars: 3000,
+  failure_prevention_budget_chars: 6000,
   telemetry_enabled: true,
   max_validation_attempts: 4,
+  max_design_replan_attempts: 3,
+  max_planner_parse_attempts: 3,
   generation_reliability_profile: 'reliability_first',
   preview_on_partial_failure: true,
   max_generation_runtime_seconds: 600,
   semantic_contract_strict: true,
   reviewer_mode: 'advisory_only',
+  reviewer_focus: [],
+  always_review: true,
+  channel_disconnect_policy: 'park',
+  merge_duplicate_runs: true,
+  custom_system_prompt_suffix: null,
+  printer_profiles: [
+    {
+      id: 'generic_fdm',
+      name: 'Generic FDM (0.4 mm nozzle)',
+      nozzle_width_mm: 0.4,
+      layer_height_mm: 0.2,
+      print_speed_mm_s: 60,
+      infill_pct: 20,
+      perimeters: 2,
+      filament_diameter_mm: 1.75,
+      material: 'pla',
+    },
+  ],
+  active_printer_profile: null,
   quality_gates_strict: true,
   allow_euler_override: true,
   semantic_bbox_mode: 'semantic_aware',
+  assembly_envelope_check: false,
+  assembly_envelope_margin_pct: 25,
   mechanisms_enabled: true,
   mechanism_import_enabled: false,
   mechanism_cache_max_mb: 512,
   allowed_spdx_licenses: ['MIT', 'Apache-2.0', 'BSD-2-Clause', 'BSD-3-Clause', 'CC0-1.0'],
+  escalation_model: null,
+  refine_assembly_positions: true,
+  assembly_part_gap_mm: 0,
+  assembly_rest_on_plate: false,
+  polish_assembly_instructions: false,
+  materials: {
+    pla: 1.24,
+    petg: 1.27,
+    abs: 1.04,
+    nylon: 1.14,
+    aluminum: 2.7,
+    steel: 7.85,
+    'stainless steel': 8.0,
+    brass: 8.5,
+    copper: 8.96,
+    titanium: 4.43,
+    wood: 0.7,
+  },
+  default_material_density_g_cm3: 1.24,
+  decomposition_bias: 'prefer_multi',
+  max_plan_parts: null,
+  planner_failure_policy: 'warn_and_single',
+  provider_rpm_limit: null,
+  cache_provider_responses: false,
+  code_stop_sequences: ['</CODE>'],
+  audit_provider_calls: false,
+  log_full_content: false,
+  audit_redaction_patterns: [
+    'sk-ant-[A-Za-z0-9_\\-]{16,}',
+    'sk-[A-Za-z0-9_\\-]{20,}',
+    'AIza[0-9A-Za-z_\\-]{35}',
+    'AKIA[0-9A-Z]{16}',
+    'gh[pousr]_[A-Za-z0-9]{36,}',
+    '(?i)bearer\\s+[A-Za-z0-9._\\-]{16,}',
+  ],
+  audit_log_max_mb: 20,
+  artifact_max_age_days: 30,
+  artifact_keep_heavy_runs: 20,
+  verify_step_exports: false,
+  export_verify_bbox_tolerance_mm: 0.1,
+  export_verify_volume_tolerance_pct: 1.0,
+  pause_before_assembly: false,
+  manufacturing_process: null,
+  max_part_candidates: 3,
+  part_candidate_store_max_mb: 64,
+  debug_prompt_logging: false,
+  replan_on_low_confidence: false,
+  safe_mode: false,
+  pipeline_presets: [],
 };
 
 let config = $state<AppConfig>({ ...defaultConfig });
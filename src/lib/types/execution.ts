@@ -1,6 +1,10 @@
 export interface ExecutionRequest {
   code: string;
   timeoutMs?: number;
+  /** Return each top-level geometry variable as its own object. */
+  exportAllSolids?: boolean;
+  /** Only these variables when exporting all solids. */
+  include?: string[];
 }
 
 export interface ExecutionArtifacts {
@@ -22,5 +26,20 @@ export interface CadExecutionResult {
   error: string | null;
   // Backward compatibility for existing callers while migrating to artifacts.
   stl_base64: string | null;
+  /** Named objects when run with `exportAllSolids`; the STL fields are null then. */
+  objects: ExecutedObject[] | null;
+}
+
+export interface ObjectReport {
+  bounds_min: [number, number, number];
+  bounds_max: [number, number, number];
+  volume: number;
+  solid_count: number;
+}
+
+export interface ExecutedObject {
+  name: string;
+  stl_base64: string;
+  report: ObjectReport;
 }
 
@@ -12,6 +12,14 @@ export interface ChatMessage {
   hasSkippedSteps?: boolean; // triggers retry button for iterative build skipped steps
 }
 
+export interface PipelinePreset {
+  id: string;
+  name: string;
+  description: string;
+  overlay: Partial<Omit<AppConfig, 'pipeline_presets'>>;
+  builtin: boolean;
+}
+
 export interface AppConfig {
   ai_provider: string;
   api_key: string | null;
@@ -31,22 +39,144 @@ export interface AppConfig {
   snap_sketch: number | null;
   enable_consensus: boolean;
   auto_approve_plan: boolean;
+  auto_approve_max_risk: number;
+  organic_missing_notes_risk: number;
+  operation_soft_budget: number;
+  operation_hard_budget: number;
+  enable_thread_helper: boolean;
+  explain_retries: boolean;
   retrieval_enabled: boolean;
   retrieval_token_budget: number;
+  retrieval_min_score: number;
+  prioritized_retrieval_packs: string[];
+  retrieval_pack_boost: number;
+  pinned_retrieval_ids: string[];
+  retrieval_embeddings: 'online' | 'cached_only' | 'disabled';
+  preview_max_triangles: number;
+  custom_rules_dir: string | null;
+  manufacturing_rules_budget_chars: number;
+  dimension_guidance_budget_chars: number;
+  failure_prevention_budget_chars: number;
   telemetry_enabled: boolean;
   max_validation_attempts: number;
+  max_design_replan_attempts: number;
+  max_planner_parse_attempts: number;
   generation_reliability_profile: 'reliability_first' | 'balanced' | 'fidelity_first';
   preview_on_partial_failure: boolean;
   max_generation_runtime_seconds: number;
   semantic_contract_strict: boolean;
   reviewer_mode: 'advisory_only' | 'rewrite_allowed';
+  reviewer_focus: ReviewFocus[];
+  /** Run the AI reviewer even when the static checks find nothing. */
+  always_review: boolean;
+  channel_disconnect_policy: 'park' | 'cancel';
+  merge_duplicate_runs: boolean;
+  custom_system_prompt_suffix: string | null;
+  printer_profiles: PrinterProfile[];
+  active_printer_profile: string | null;
   quality_gates_strict: boolean;
   allow_euler_override: boolean;
   semantic_bbox_mode: 'semantic_aware' | 'legacy';
+  assembly_envelope_check: boolean;
+  assembly_envelope_margin_pct: number;
   mechanisms_enabled: boolean;
   mechanism_import_enabled: boolean;
   mechanism_cache_max_mb: number;
   allowed_spdx_licenses: string[];
+  escalation_model: string | null;
+  refine_assembly_positions: boolean;
+  assembly_part_gap_mm: number;
+  assembly_rest_on_plate: boolean;
+  polish_assembly_instructions: boolean;
+  materials: Record<string, number>;
+  default_material_density_g_cm3: number;
+  decomposition_bias: 'prefer_multi' | 'prefer_single';
+  max_plan_parts: number | null;
+  planner_failure_policy: 'silent_single_fallback' | 'warn_and_single' | 'fail';
+  /** Requests per minute allowed to the AI provider (null = unlimited). */
+  provider_rpm_limit: number | null;
+  /** Answer repeated identical provider requests from a cache at zero token cost. */
+  cache_provider_responses: boolean;
+  code_stop_sequences: string[];
+  /** Record every provider call (sizes and SHA-256 digests) in the audit log. */
+  audit_provider_calls: boolean;
+  /** Also store redacted request and response text in audit records. */
+  log_full_content: boolean;
+  audit_redaction_patterns: string[];
+  audit_log_max_mb: number;
+  /** Saved run artifacts older than this are deleted. */
+  artifact_max_age_days: number;
+  /** Newest runs that keep their STL/STEP files; older ones keep metadata only. */
+  artifact_keep_heavy_runs: number;
+  /** Re-import STEP exports and compare them with the post-geometry report. */
+  verify_step_exports: boolean;
+  export_verify_bbox_tolerance_mm: number;
+  export_verify_volume_tolerance_pct: number;
+  pause_before_assembly: boolean;
+  /** Clearance rule set for mating parts; null picks one from the rules preset. */
+  manufacturing_process: 'fdm' | 'sla' | 'cnc_it7' | 'cnc_it9' | null;
+  max_part_candidates: number;
+  part_candidate_store_max_mb: number;
+  debug_prompt_logging: boolean;
+  replan_on_low_confidence: boolean;
+  safe_mode: boolean;
+  pipeline_presets: PipelinePreset[];
+}
+
+export interface PrinterProfile {
+  id: string;
+  name: string;
+  nozzle_width_mm: number;
+  layer_height_mm: number;
+  print_speed_mm_s: number;
+  infill_pct: number;
+  perimeters: number;
+  filament_diameter_mm: number;
+  material: string;
+}
+
+/** A validation repair that fixed a failure, reused as a few-shot example. */
+export interface RepairExample {
+  id: string;
+  error_category: string;
+  error_phrase: string;
+  diff: string;
+  cq_version: string | null;
+  created_at_ms: number;
+  last_used_ms: number;
+  assisted_attempts: number;
+  assisted_successes: number;
+}
+
+/** An anti-pattern mined from recurring failures, pending review or approved. */
+export interface LearnedAntiPattern {
+  id: string;
+  title: string;
+  /** Error category in its backend form, e.g. `GeometryKernel`. */
+  error_category: string;
+  failing_operation: string | null;
+  /** Normalized error phrase shared by the cluster. */
+  trigger_phrase: string;
+  occurrences: number;
+  example_errors: string[];
+  suggested_fix: string;
+  created_at_ms: number;
+}
+
+/** Rough print time and material usage; `uncertainty_pct` is the ± band. */
+export interface PrintEstimate {
+  profile_id: string;
+  profile_name: string;
+  material: string;
+  material_volume_mm3: number;
+  filament_length_m: number;
+  material_mass_g: number | null;
+  print_time_s: number;
+  print_time_min_s: number;
+  print_time_max_s: number;
+  layer_count: number;
+  uncertainty_pct: number;
+  summary: string;
 }
 
 export interface ModelInfo {
@@ -81,6 +211,13 @@ export type ExecutionRequest = import('$lib/types/execution').ExecutionRequest;
 export type ExecutionTiming = import('$lib/types/execution').ExecutionTiming;
 export type ExecutionArtifacts = import('$lib/types/execution').ExecutionArtifacts;
 
+/** A TODO/FIXME/ASSUME/NOTE comment found in generated code. */
+export interface CodeAssumption {
+  line: number;
+  marker: 'TODO' | 'FIXME' | 'ASSUME' | 'NOTE';
+  text: string;
+}
+
 export interface PythonStatus {
   python_found: boolean;
   python_version: string | null;
@@ -88,6 +225,28 @@ export interface PythonStatus {
   venv_ready: boolean;
   build123d_installed: boolean;
   build123d_version: string | null;
+  env_drift: EnvDrift | null;
+}
+
+export interface LockedPackage {
+  name: string;
+  version: string;
+}
+
+export interface EnvLock {
+  version: number;
+  created_at_ms: number;
+  platform: string;
+  python_version: string | null;
+  packages: LockedPackage[];
+}
+
+export interface EnvDrift {
+  snapshot_created_at_ms: number;
+  added: LockedPackage[];
+  removed: LockedPackage[];
+  changed: { name: string; locked: string; installed: string }[];
+  summary: string;
 }
 
 export interface StreamEvent {
@@ -101,7 +260,40 @@ export interface TokenUsageData {
   input_tokens: number;
   output_tokens: number;
   total_tokens: number;
+  /** Cost in `currency`, whatever the field name says. */
   cost_usd: number | null;
+  currency?: string;
+  /** The model is not in the pricing table; its default rate was used. */
+  estimated_with_default_rate?: boolean;
+}
+
+export interface Rate {
+  input_per_million: number;
+  output_per_million: number;
+}
+
+/** Rate of the `provider` models whose id matches `model`; `*` matches anything. */
+export interface ModelRate extends Rate {
+  provider: string;
+  model: string;
+}
+
+export interface ModelPricing {
+  currency: string;
+  default_rate: Rate;
+  models: ModelRate[];
+}
+
+/** The user's `model_pricing.json`; unset fields keep the bundled values. */
+export interface PricingOverrides {
+  currency: string | null;
+  default_rate: Rate | null;
+  models: ModelRate[];
+}
+
+export interface ModelPricingSettings {
+  effective: ModelPricing;
+  overrides: PricingOverrides;
 }
 
 export interface RustChatMessage {
@@ -128,10 +320,93 @@ export interface StructuredError {
 
 // Multi-part parallel generation types
 
+export interface PartKinematics {
+  name: string;
+  role: 'static' | 'moving' | 'fastener';
+  group: string | null;
+  note: string | null;
+}
+
+export interface AgentRuleSource {
+  path: string;
+  kind: 'preset' | 'user';
+  sections: string[];
+  error: string | null;
+}
+
+/** A design plan split into its sections. */
+export interface StructuredPlan {
+  object_analysis: string | null;
+  cad_approach: string | null;
+  /** Numbered Build Plan steps without their numbers. */
+  build_steps: string[];
+  approximation_notes: string | null;
+}
+
+/** A changed dimension; null on one side means it was added or removed. */
+export interface DimensionDelta {
+  old: number | null;
+  new: number | null;
+}
+
+export interface SectionDiff {
+  section: string;
+  added_operations: string[];
+  removed_operations: string[];
+  dimension_changes: DimensionDelta[];
+}
+
+export interface BuildStepChange {
+  old: string;
+  new: string;
+  added_operations: string[];
+  removed_operations: string[];
+  dimension_changes: DimensionDelta[];
+}
+
+export interface PlanDiff {
+  sections: SectionDiff[];
+  added_steps: string[];
+  removed_steps: string[];
+  changed_steps: BuildStepChange[];
+}
+
+/** Included and omitted rule counts of one budgeted prompt section. */
+export interface RuleSectionStats {
+  section: string;
+  included: number;
+  omitted: number;
+}
+
+/** Rule content the geometry advisor receives, from `preview_prompt_context`. */
+export interface RuleContext {
+  context: string;
+  sections: RuleSectionStats[];
+}
+
+export interface ProfileSuggestion {
+  profile: AppConfig['generation_reliability_profile'];
+  /** One-line reason for the suggestion. */
+  rationale: string;
+  /** The suggestion is already the configured profile. */
+  already_selected: boolean;
+}
+
 export interface GenerationPlan {
   mode: 'single' | 'multi';
   description?: string;
   parts: PartSpec[];
+  /** Booleans between built parts, applied in order before assembly. */
+  assembly_ops?: AssemblyOp[];
+}
+
+/** Replaces part `target` with `target <op> tool`. */
+export interface AssemblyOp {
+  op: 'cut' | 'intersect' | 'union';
+  target: string;
+  tool: string;
+  /** Keep `tool` in the assembly; by default it is consumed. */
+  keep_tool?: boolean;
 }
 
 export interface PartSpec {
@@ -139,6 +414,7 @@ export interface PartSpec {
   description: string;
   position: [number, number, number];
   constraints: string[];
+  reliability_profile?: AppConfig['generation_reliability_profile'];
 }
 
 export interface PendingAssemblyPart {
@@ -146,6 +422,33 @@ export interface PendingAssemblyPart {
   name: string;
   stl_base64: string;
   position: [number, number, number];
+  /** Hex color for the viewer, e.g. `#4e79a7`. */
+  color?: string;
+}
+
+export type ReviewFocus = 'dimensional_accuracy' | 'manufacturability' | 'code_quality';
+
+export interface ReviewFinding {
+  focus: ReviewFocus;
+  finding: string;
+  was_fixed: boolean;
+}
+
+export type SuggestedActionKind =
+  | 'increase_timeout'
+  | 'set_up_python'
+  | 'check_provider_settings'
+  | 'use_reliability_first'
+  | 'simplify_request'
+  | 'reduce_parts'
+  | 'retry_generation';
+
+/** A next step suggested after a failed generation. */
+export interface SuggestedAction {
+  kind: SuggestedActionKind;
+  label: string;
+  /** The part of the outcome that triggered the suggestion. */
+  reason: string;
 }
 
 export type MultiPartEvent =
@@ -159,25 +462,39 @@ export type MultiPartEvent =
       fatal_combo: boolean;
       negation_conflict: boolean;
       repair_sensitive_ops: string[];
+      estimated_operations: number | null;
     }
   | { kind: 'ConfidenceAssessment'; level: 'high' | 'medium' | 'low'; score: number; cookbook_matches: string[]; warnings: string[]; message: string }
   | { kind: 'PlanStatus'; message: string }
-  | { kind: 'PlanResult'; plan: GenerationPlan }
+  | { kind: 'PlanResult'; plan: GenerationPlan; kinematics: PartKinematics[] }
+  | { kind: 'RunStarted'; run_id: string }
+  | { kind: 'AwaitingAssemblyApproval'; accepted_parts: PartRef[]; rejected_parts: PartRef[] }
   | { kind: 'SingleDelta'; delta: string; done: boolean }
   | { kind: 'SingleDone'; full_response: string }
   | { kind: 'PartDelta'; part_index: number; part_name: string; delta: string }
   | { kind: 'PartComplete'; part_index: number; part_name: string; success: boolean; error?: string }
   | { kind: 'PartCodeExtracted'; part_index: number; part_name: string; code: string }
-  | { kind: 'PartStlReady'; part_index: number; part_name: string; stl_base64: string }
+  | { kind: 'PartStlReady'; part_index: number; part_name: string; stl_base64: string; color?: [number, number, number] | null }
   | { kind: 'PartStlFailed'; part_index: number; part_name: string; error: string }
   | { kind: 'AssemblyStatus'; message: string }
-  | { kind: 'FinalCode'; code: string; stl_base64?: string }
+  | { kind: 'Heartbeat'; phase: string; detail: string }
+  | { kind: 'Warning'; code: string; message: string }
+  | { kind: 'FinalCode'; code: string; stl_base64?: string; geometry_hash?: string | null }
   | { kind: 'ReviewStatus'; message: string }
-  | { kind: 'ReviewComplete'; was_modified: boolean; explanation: string }
-  | { kind: 'ValidationAttempt'; attempt: number; max_attempts: number; message: string }
-  | { kind: 'StaticValidationReport'; passed: boolean; findings: string[] }
+  | { kind: 'ReviewComplete'; was_modified: boolean; explanation: string; findings: ReviewFinding[]; ai_reviewed: boolean }
+  | { kind: 'ReviewReverted'; reason: string }
+  | {
+      kind: 'ValidationAttempt';
+      attempt: number;
+      max_attempts: number;
+      message: string;
+      revalidation_scope: 'full' | 'reuse_static' | 'skip_all';
+    }
+  | { kind: 'StaticValidationReport'; passed: boolean; findings: string[]; operation_count: number | null }
   | { kind: 'ValidationSuccess'; attempt: number; message: string }
-  | { kind: 'ValidationFailed'; attempt: number; error_category: string; error_message: string; will_retry: boolean }
+  | { kind: 'ValidationFailed'; attempt: number; error_category: string; error_message: string; will_retry: boolean; localized_part?: string | null }
+  | { kind: 'ValidationAttemptDiff'; attempt: number; additions: number; deletions: number }
+  | { kind: 'ValidationFixExplanation'; attempt: number; explanation: string }
   | {
       kind: 'PostGeometryValidationReport';
       report: {
@@ -190,13 +507,38 @@ export type MultiPartEvent =
         bounds_min: [number, number, number];
         bounds_max: [number, number, number];
         volume: number;
+        surface_area: number;
+        center_of_mass: [number, number, number] | null;
+        unit_inertia: [number, number, number][] | null;
         bbox_ok: boolean;
         warnings: string[];
       };
     }
+  | {
+      kind: 'MassPropertiesReport';
+      report: {
+        parts: {
+          part_name: string;
+          material: string;
+          density_g_cm3: number;
+          density_assumed: boolean;
+          volume_mm3: number;
+          mass_g: number;
+          center_of_gravity_mm: [number, number, number];
+          inertia_g_mm2: [number, number, number][];
+        }[];
+        total_mass_g: number;
+        center_of_gravity_mm: [number, number, number];
+        inertia_g_mm2: [number, number, number][];
+        assumptions: string[];
+        warnings: string[];
+      };
+    }
+  | { kind: 'PrintEstimate'; estimate: PrintEstimate }
   | { kind: 'PostGeometryValidationWarning'; message: string }
+  | { kind: 'ModelEscalation'; part_name: string | null; from_model: string; to_model: string; message: string }
   | { kind: 'SemanticValidationReport'; part_name: string; passed: boolean; findings: string[] }
-  | { kind: 'RetrievalStatus'; message: string; items: { source: string; id: string; title: string; score: number }[]; used_embeddings: boolean; lexical_fallback: boolean }
+  | { kind: 'RetrievalStatus'; message: string; items: { source: string; pack: string; id: string; title: string; score: number; boosted: boolean }[]; used_embeddings: boolean; lexical_fallback: boolean; dropped_below_threshold: number; embedding_mode: string }
   | { kind: 'IterativeStart'; total_steps: number; steps: { index: number; name: string; description: string; operations: string[] }[] }
   | { kind: 'IterativeStepStarted'; step_index: number; step_name: string; description: string }
   | { kind: 'IterativeStepComplete'; step_index: number; success: boolean; stl_base64?: string }
@@ -204,19 +546,125 @@ export type MultiPartEvent =
   | { kind: 'IterativeStepSkipped'; step_index: number; name: string; error: string }
   | { kind: 'IterativeComplete'; final_code: string; stl_base64?: string; skipped_steps: SkippedStepInfo[] }
   | { kind: 'ModificationDetected'; intent_summary: string }
-  | { kind: 'CodeDiff'; diff_lines: DiffLine[]; old_line_count: number; new_line_count: number; additions: number; deletions: number }
+  | { kind: 'CodeDiff'; diff_lines: DiffLine[]; hunks: DiffHunk[]; old_line_count: number; new_line_count: number; additions: number; deletions: number }
   | { kind: 'ConsensusStarted'; candidate_count: number }
   | { kind: 'ConsensusCandidate'; label: string; temperature: number; status: string; has_code?: boolean; execution_success?: boolean }
   | { kind: 'ConsensusWinner'; label: string; score: number; reason: string }
   | { kind: 'ClarificationNeeded'; questions: string[] }
-  | { kind: 'TokenUsage'; phase: string; input_tokens: number; output_tokens: number; total_tokens: number; cost_usd: number | null }
+  | { kind: 'TokenUsage'; phase: string; input_tokens: number; output_tokens: number; total_tokens: number; cost_usd: number | null; currency: string; estimated_with_default_rate: boolean }
+  | { kind: 'BatchItemStarted'; index: number; prompt: string }
+  | { kind: 'BatchItemComplete'; index: number; success: boolean; final_code: string | null; error: string | null }
+  | { kind: 'BatchComplete'; succeeded: number; failed: number }
+  | { kind: 'SuggestedActions'; actions: SuggestedAction[] }
   | { kind: 'Done'; success: boolean; error?: string; validated?: boolean };
 
+/** Channel payload: a MultiPartEvent tagged with its run and sequence number. */
+export type MultiPartEventEnvelope = MultiPartEvent & { seq: number; run_id: string };
+
+export type RunChannelStatus = 'connected' | 'parked' | 'cancelled';
+
+export interface RunEvents {
+  run_id: string;
+  status: RunChannelStatus;
+  last_seq: number;
+  events: MultiPartEventEnvelope[];
+}
+
+export interface ValidationAttemptRecord {
+  attempt: number;
+  /** The code this attempt ran; only its start when `code_truncated`. */
+  code: string;
+  code_sha256: string;
+  code_truncated: boolean;
+  error_category: string | null;
+  error_message: string | null;
+  error_line: number | null;
+  repair_instruction: string | null;
+  /** Line diff to the next attempt's code; empty for the last attempt. */
+  diff_to_next: DiffLine[];
+}
+
+/** One attempt at a part, kept whether or not per-part acceptance passed. */
+export interface PartCandidate {
+  code: string;
+  stl_base64: string | null;
+  accepted: boolean;
+  /** Semantic findings, or the execution error for candidates that never ran. */
+  findings: string[];
+  created_at_ms: number;
+}
+
+/** Validation attempts of a run's final script (`part_name` null) or of one part. */
+export interface ValidationChain {
+  run_id: string;
+  part_name: string | null;
+  recorded_at_ms: number;
+  attempts: ValidationAttemptRecord[];
+  omitted_attempts: number;
+}
+
+/** `run_state` when the run's events were still buffered, else `trace_only`. */
+export type SnapshotSource = 'run_state' | 'trace_only';
+
+export interface RunSnapshot {
+  run_id: string;
+  source: SnapshotSource;
+  success: boolean | null;
+  plan_risk_score: number | null;
+  confidence_score: number | null;
+  part_acceptance_rate: number | null;
+  retry_attempts: number | null;
+  total_tokens: number | null;
+  cost_usd: number | null;
+  /** Wall-clock milliseconds per token-usage phase. */
+  phase_ms: Record<string, number> | null;
+  bbox_size: [number, number, number] | null;
+  volume: number | null;
+  geometry_hash: string | null;
+  config_fingerprint: string | null;
+  settings: Record<string, unknown> | null;
+  failure_signatures: string[];
+  /** Fields that could not be recovered for this run. */
+  unavailable: string[];
+}
+
+export interface SettingChange {
+  key: string;
+  a: unknown;
+  b: unknown;
+}
+
+/** Run `b` measured against run `a`; deltas are `b - a`. */
+export interface RunComparison {
+  a: RunSnapshot;
+  b: RunSnapshot;
+  bbox_size_delta: [number, number, number] | null;
+  volume_delta: number | null;
+  same_geometry: boolean | null;
+  same_config: boolean | null;
+  settings_changes: SettingChange[] | null;
+  failure_signatures_added: string[];
+  failure_signatures_removed: string[];
+}
+
 export interface DiffLine {
   tag: 'equal' | 'insert' | 'delete';
   text: string;
 }
 
+/** Changed region of `diff_lines`; `start_line`/`end_line` are inclusive indices into it. */
+export interface DiffHunk {
+  start_line: number;
+  end_line: number;
+  added: number;
+  removed: number;
+}
+
+export interface PartRef {
+  index: number;
+  name: string;
+}
+
 export interface PartProgress {
   name: string;
   status: 'pending' | 'generating' | 'complete' | 'failed';
@@ -227,6 +675,8 @@ export interface PartProgress {
   position: [number, number, number];
   code?: string;
   stl_base64?: string;
+  /** RGB (0..1) the assembled code tints this part with. */
+  color?: [number, number, number];
 }
 
 export interface IterativeStepProgress {
@@ -246,12 +696,41 @@ export interface SkippedStepInfo {
   error: string;
 }
 
+export interface GenerationResult {
+  success: boolean;
+  final_code: string | null;
+  validated: boolean;
+  part_acceptance_rate: number | null;
+  total_cost_usd: number | null;
+  failure_signatures: string[];
+  error: string | null;
+  response: string;
+  geometry_hash: string | null;
+}
+
+export interface BatchResult {
+  succeeded: number;
+  failed: number;
+}
+
 export interface DesignPlanResult {
   plan_text: string;
   risk_score: number;
   warnings: string[];
   is_valid: boolean;
+  estimated_operations: number | null;
   clarification_questions?: string[];
+  auto_approval: AutoApprovalDecision | null;
+  auto_approved: boolean;
+  generation_response: string | null;
+}
+
+export interface AutoApprovalDecision {
+  eligible: boolean;
+  risk_score: number;
+  max_risk_score: number;
+  confidence_level: 'high' | 'medium' | 'low';
+  blocked_by: string | null;
 }
 
 export interface GenerationEntry {
@@ -285,6 +764,9 @@ export interface MechanismParameter {
   default_value: string;
   description: string;
   unit?: string | null;
+  type?: 'number' | 'length' | 'angle' | 'ratio' | 'integer' | 'count' | 'bool' | 'string';
+  min?: number;
+  max?: number;
 }
 
 export interface MechanismItem {
@@ -301,6 +783,8 @@ export interface MechanismItem {
   source_url?: string | null;
   preview_url?: string | null;
   parameters: MechanismParameter[];
+  code_template?: string | null;
+  code?: string | null;
 }
 
 export interface MechanismPackage {
@@ -324,6 +808,16 @@ export interface MechanismImportReport {
   package_name: string;
   installed_count: number;
   source_url: string;
+  flagged_content: string[];
+}
+
+/** A mechanism template rendered at concrete parameters and executed. */
+export interface MechanismInstance {
+  mechanism_id: string;
+  code: string;
+  stl_base64: string;
+  /** Resolved parameter values as substituted into the template. */
+  parameters: Record<string, string>;
 }
 
 export interface ProjectFile {
@@ -345,4 +839,244 @@ export interface ProjectFile {
     mates?: import('$lib/types/cad').AssemblyMate[];
     drawings?: import('$lib/types/drawing').Drawing[];
   };
+  generation?: ProjectGenerationReport;
+  code_history?: CodeSnapshot[];
+  view_bookmarks?: ViewBookmark[];
+  part_colors?: Record<string, [number, number, number]>;
+}
+
+export interface ProjectGenerationReport {
+  run_id: string | null;
+  request: string;
+  plan_text: string;
+  parts: { name: string; accepted: boolean }[];
+  outstanding_warnings: string[];
+}
+
+/** Files written by a per-part STEP export and the parts that failed. */
+export interface PartsStepExport {
+  written: string[];
+  failures: { name: string; error: string }[];
+}
+
+export interface CodeSnapshot {
+  code: string;
+  label: string;
+  timestamp: number | null;
+}
+
+export interface ProjectSummary {
+  name: string;
+  schema_version: number;
+  generation: ProjectGenerationReport | null;
+  snapshot_count: number;
+  context_section: string | null;
+}
+
+export type PostGeometryValidationReport = Extract<
+  MultiPartEvent,
+  { kind: 'PostGeometryValidationReport' }
+>['report'];
+
+export interface CodeParameter {
+  name: string;
+  value: number;
+  /** 1-based line number of the assignment. */
+  line: number;
+}
+
+/** A top-level assignment computed from parameters, e.g. `inner = outer - 2*wall`. */
+export interface DerivedParameter {
+  name: string;
+  expression: string;
+  depends_on: string[];
+  line: number;
+}
+
+export interface StaticValidationFinding {
+  level: 'error' | 'warning';
+  code: string;
+  message: string;
+}
+
+/** An external script loaded as the starting point for further AI edits. */
+export interface ImportedCode {
+  source_path: string;
+  code: string;
+  parameters: CodeParameter[];
+  derived_parameters: DerivedParameter[];
+  static_findings: StaticValidationFinding[];
+  stl_base64: string | null;
+  post_geometry_report: PostGeometryValidationReport | null;
+  execution_error: string | null;
+  warnings: string[];
+}
+
+export interface Bounds {
+  min: [number, number, number];
+  max: [number, number, number];
+}
+
+/** Camera in the viewer's Z-up coordinates. */
+export interface CameraView {
+  name: string;
+  position: [number, number, number];
+  target: [number, number, number];
+  up: [number, number, number];
 }
+
+export interface StandardViews {
+  bounds: Bounds;
+  views: CameraView[];
+}
+
+/** On-demand measurement answered from the model's BREP. */
+export type GeometryQuery =
+  | { kind: 'point_distance'; a: [number, number, number]; b: [number, number, number] }
+  | {
+      kind: 'thickness';
+      point: [number, number, number];
+      direction: [number, number, number];
+    }
+  | { kind: 'face_radius'; point: [number, number, number] };
+
+export interface QueryEntity {
+  kind: string;
+  /** -1 when the face could not be matched to the shape's face list. */
+  index: number;
+  surface_type: string;
+}
+
+export type GeometryQueryResponse =
+  | {
+      kind: 'point_distance';
+      distance: number;
+      a: [number, number, number];
+      b: [number, number, number];
+      entities: QueryEntity[];
+    }
+  | {
+      kind: 'thickness';
+      thickness: number;
+      entry: [number, number, number];
+      exit: [number, number, number];
+      entities: QueryEntity[];
+    }
+  | {
+      kind: 'face_radius';
+      radius: number;
+      point: [number, number, number];
+      entities: QueryEntity[];
+    };
+
+/** One metric compared by `verify_export`, e.g. "bounds_max.z" or "volume". */
+export interface MetricCheck {
+  metric: string;
+  expected: number;
+  actual: number;
+  delta: number;
+  tolerance: number;
+  passed: boolean;
+}
+
+export interface ExportVerificationReport {
+  path: string;
+  passed: boolean;
+  checks: MetricCheck[];
+  face_count: number;
+}
+
+export interface ParameterSuggestion {
+  name: string;
+  value: number;
+  literal: string;
+  lines: number[];
+  named_from_request: boolean;
+}
+
+export interface UncertainLiteral {
+  value: number;
+  literal: string;
+  lines: number[];
+  reason: string;
+}
+
+export interface ParametrizedCode {
+  code: string;
+  applied: boolean;
+  suggestions: ParameterSuggestion[];
+  uncertain: UncertainLiteral[];
+  rejected_reason: string | null;
+}
+
+export type StorageCategory = 'runs' | 'caches' | 'telemetry' | 'exports';
+
+export interface CategoryUsage {
+  category: StorageCategory;
+  bytes: number;
+  files: number;
+}
+
+export interface StorageUsage {
+  root: string;
+  categories: CategoryUsage[];
+  total_bytes: number;
+}
+
+export interface RemovedArtifact {
+  path: string;
+  bytes: number;
+  reason: 'expired' | 'heavy_pruned' | 'stale_scratch';
+}
+
+export interface CleanupReport {
+  dry_run: boolean;
+  removed: RemovedArtifact[];
+  freed_bytes: number;
+}
+
+export interface ViewBookmark {
+  camera: CameraView;
+  /** Bounds of the geometry the bookmark was saved (or rescaled) against. */
+  bounds: Bounds;
+}
+
+export type QueueEntryStatus = 'pending' | 'running' | 'completed' | 'failed' | 'cancelled';
+
+/** Extra inputs forwarded to the generation pipeline for a queued entry. */
+export interface QueueEntryOptions {
+  history?: RustChatMessage[];
+  existing_code?: string | null;
+  /** Pipeline preset applied to this entry's run. */
+  preset?: string | null;
+}
+
+/** Result of a finished queue entry, kept for the session's results list. */
+export interface QueueEntryOutcome {
+  success: boolean;
+  error: string | null;
+  final_code: string | null;
+  finished_at_ms: number;
+}
+
+export interface QueueEntry {
+  id: string;
+  message: string;
+  plan_text: string | null;
+  options: QueueEntryOptions;
+  status: QueueEntryStatus;
+  enqueued_at_ms: number;
+  outcome: QueueEntryOutcome | null;
+}
+
+export interface QueueStatus {
+  running: boolean;
+  entries: QueueEntry[];
+}
+
+/** Queue runner events; pipeline events arrive unchanged inside `Generation`. */
+export type QueueEvent =
+  | { kind: 'EntryStarted'; entry_id: string; message: string }
+  | { kind: 'Generation'; entry_id: string; event: MultiPartEventEnvelope }
+  | { kind: 'EntryFinished'; entry_id: string; status: QueueEntryStatus; outcome: QueueEntryOutcome }
+  | { kind: 'QueueIdle'; status: QueueStatus };
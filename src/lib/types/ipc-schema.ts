@@ -0,0 +1,9 @@
+import schema from './ipc-schema.json';
+
+/** Schema version the frontend bindings were generated against. */
+export const EVENT_SCHEMA_VERSION: number = schema.version;
+
+/** Every `MultiPartEvent` kind the backend can emit at this schema version. */
+export const KNOWN_EVENT_KINDS: ReadonlySet<string> = new Set(
+  schema.definitions.MultiPartEvent.oneOf.flatMap((variant) => variant.properties.kind.enum),
+);
@@ -0,0 +1,27 @@
+/**
+ * Convert an RGB triple with channels in 0..1 (as used by the backend and
+ * build123d's `Color`) to a `#rrggbb` hex string.
+ */
+export function rgbToHex(rgb: [number, number, number]): string {
+  return (
+    '#' +
+    rgb
+      .map((c) =>
+        Math.round(Math.min(1, Math.max(0, c)) * 255)
+          .toString(16)
+          .padStart(2, '0'),
+      )
+      .join('')
+  );
+}
+
+/**
+ * Convert a `#rrggbb` hex string to an RGB triple with channels in 0..1.
+ */
+export function hexToRgb(hex: string): [number, number, number] {
+  return [
+    parseInt(hex.slice(1, 3), 16) / 255,
+    parseInt(hex.slice(3, 5), 16) / 255,
+    parseInt(hex.slice(5, 7), 16) / 255,
+  ];
+}
@@ -0,0 +1,20 @@
+import type { TokenUsageData } from '$lib/types';
+
+/**
+ * Format a usage cost in the user's locale and the pricing table's currency.
+ * Costs priced at the default rate for an unknown model get a leading `~`.
+ */
+export function formatCost(usage: TokenUsageData): string {
+  const amount = usage.cost_usd ?? 0;
+  let formatted: string;
+  try {
+    formatted = new Intl.NumberFormat(undefined, {
+      style: 'currency',
+      currency: usage.currency || 'USD',
+      maximumFractionDigits: 4,
+    }).format(amount);
+  } catch {
+    formatted = `${amount.toFixed(4)} ${usage.currency ?? ''}`.trim();
+  }
+  return usage.estimated_with_default_rate ? `~${formatted}` : formatted;
+}